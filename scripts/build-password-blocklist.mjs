@@ -0,0 +1,38 @@
+// Rebuilds src-wasm/core/data/common-passwords.bin, the common-password
+// blocklist, from one or more lists with one password per line:
+//
+//   node scripts/build-password-blocklist.mjs top-100000.txt [more.txt ...]
+//
+// Each password is trimmed and lowercased, duplicates dropped, and only the
+// first RECORD_BYTES of its SHA-256 kept; the records are written sorted, back
+// to back, for the binary search in src-wasm/core/src/blocklist.rs. Keep
+// RECORD_BYTES in step with the constant there.
+
+import { createHash } from "node:crypto";
+import { readFileSync, writeFileSync } from "node:fs";
+
+const OUT_FILE = "src-wasm/core/data/common-passwords.bin";
+const RECORD_BYTES = 5;
+
+const sources = process.argv.slice(2);
+if (sources.length === 0) {
+  console.error("usage: node scripts/build-password-blocklist.mjs <list.txt> [more.txt ...]");
+  process.exit(1);
+}
+
+const passwords = new Set();
+for (const source of sources) {
+  for (const line of readFileSync(source, "utf8").split("\n")) {
+    const password = line.trim().toLowerCase();
+    if (password) {
+      passwords.add(password);
+    }
+  }
+}
+
+const records = [...passwords].map((p) => createHash("sha256").update(p, "utf8").digest().subarray(0, RECORD_BYTES));
+records.sort(Buffer.compare);
+const unique = records.filter((record, i) => i === 0 || !record.equals(records[i - 1]));
+
+writeFileSync(OUT_FILE, Buffer.concat(unique));
+console.log(`${unique.length} passwords, ${(unique.length * RECORD_BYTES / 1024).toFixed(1)} KiB, in ${OUT_FILE}`);
@@ -0,0 +1,38 @@
+// Fails when a Wasm build outgrows its budget in src-wasm/wasm-size-budget.json.
+// Paths there are relative to the repository root. Run after
+// `npm run build:wasm` and `npm run build:wasm:slim`.
+//
+// `--update` records each build's current size plus 5% headroom as its budget;
+// commit that together with the change that made the binary grow (or shrink). A
+// build without a budget fails the check like one over it.
+
+import { readFileSync, statSync, writeFileSync } from "node:fs";
+
+const BUDGET_FILE = "src-wasm/wasm-size-budget.json";
+const HEADROOM = 1.05;
+
+const update = process.argv.includes("--update");
+const budgets = JSON.parse(readFileSync(BUDGET_FILE, "utf8"));
+let failed = false;
+
+for (const [name, budget] of Object.entries(budgets)) {
+  const size = statSync(budget.wasm).size;
+  const kib = (bytes) => `${(bytes / 1024).toFixed(1)} KiB`;
+  if (update) {
+    budget.maxBytes = Math.ceil(size * HEADROOM);
+    console.log(`${name}: ${kib(size)}, budget set to ${kib(budget.maxBytes)}`);
+  } else if (typeof budget.maxBytes !== "number") {
+    console.error(`${name}: ${kib(size)} has no budget; run with --update and commit it`);
+    failed = true;
+  } else if (size > budget.maxBytes) {
+    console.error(`${name}: ${kib(size)} is over its ${kib(budget.maxBytes)} budget`);
+    failed = true;
+  } else {
+    console.log(`${name}: ${kib(size)} of ${kib(budget.maxBytes)}`);
+  }
+}
+
+if (update) {
+  writeFileSync(BUDGET_FILE, JSON.stringify(budgets, null, 2) + "\n");
+}
+process.exit(failed ? 1 : 0);
@@ -0,0 +1,26 @@
+// Records the SRI hash of a wasm-pack build's final .wasm next to it, as
+// securepass_wasm_integrity.js (with a .d.ts). The loader in
+// src/services/WasmCryptoService.ts imports it and refuses any module whose
+// bytes hash to something else. Run after every wasm-pack build:
+//
+//   node scripts/wasm-integrity.mjs src/pkg
+
+import { createHash } from "node:crypto";
+import { readFileSync, writeFileSync } from "node:fs";
+import { join } from "node:path";
+
+const dir = process.argv[2];
+if (!dir) {
+  console.error("usage: node scripts/wasm-integrity.mjs <wasm-pack out dir>");
+  process.exit(1);
+}
+
+const wasm = readFileSync(join(dir, "securepass_wasm_bg.wasm"));
+const integrity = `sha384-${createHash("sha384").update(wasm).digest("base64")}`;
+
+writeFileSync(
+  join(dir, "securepass_wasm_integrity.js"),
+  `// Generated by scripts/wasm-integrity.mjs; do not edit.\nexport const WASM_INTEGRITY = ${JSON.stringify(integrity)};\n`,
+);
+writeFileSync(join(dir, "securepass_wasm_integrity.d.ts"), "export declare const WASM_INTEGRITY: string;\n");
+console.log(`${join(dir, "securepass_wasm_bg.wasm")}: ${integrity}`);
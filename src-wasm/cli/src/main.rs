@@ -0,0 +1,344 @@
+// --- SecurePass CLI ---
+// Scripting access to the same encrypted vault files the web app exports.
+// Every command runs on securepass-core, so a vault saved here is byte-for-byte
+// what the browser would have written.
+//
+// The master password comes from $SECUREPASS_PASSWORD when set, otherwise from
+// a hidden prompt. Secrets are only ever written to stdout or an explicit file.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use securepass_core::otp::TotpConfig;
+use securepass_core::vault::{self, VaultEntry};
+use securepass_core::{generator, Engine, PasswordOptions};
+use zeroize::Zeroizing;
+
+mod vault_file;
+
+use vault_file::VaultFile;
+
+#[derive(Parser)]
+#[command(name = "securepass-cli", version, about = "Work with SecurePass vault files from the command line")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check that the password opens the vault and print a summary.
+    Unlock { vault: PathBuf },
+    /// List entries (no secrets).
+    List {
+        vault: PathBuf,
+        /// Print a JSON array instead of tab-separated lines.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one field of an entry, found by ID or exact title.
+    Get {
+        vault: PathBuf,
+        query: String,
+        #[arg(long, value_enum, default_value_t = Field::Password)]
+        field: Field,
+    },
+    /// Generate a password (no vault needed).
+    Generate {
+        #[arg(long, value_enum, default_value_t = Style::Random)]
+        style: Style,
+        #[arg(long, default_value_t = 20)]
+        length: usize,
+        #[arg(long)]
+        no_uppercase: bool,
+        #[arg(long)]
+        no_numbers: bool,
+        #[arg(long)]
+        no_symbols: bool,
+    },
+    /// Print the current TOTP code of an entry.
+    Totp { vault: PathBuf, query: String },
+    /// Add entries from a file and save the vault.
+    /// The file is either JSON (an entry array or a vault object) or
+    /// otpauth-migration:// URIs from Google Authenticator, one per line.
+    Import { vault: PathBuf, file: PathBuf },
+    /// Export the vault or its TOTP seeds.
+    Export {
+        vault: PathBuf,
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// Output file; JSON, Aegis and OpenPGP exports go to stdout when omitted.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// age public key ("age1...") to encrypt to; repeatable. Without one,
+        /// the age export uses the export password as its passphrase.
+        #[arg(long, short)]
+        recipient: Vec<String>,
+        /// OpenPGP public key file (armored or binary) for the openpgp export.
+        #[arg(long)]
+        key: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Field {
+    Password,
+    Username,
+    Url,
+    Notes,
+    Totp,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Style {
+    Random,
+    Mac,
+    Passphrase,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// The decrypted vault as JSON. Plaintext: handle with care.
+    Json,
+    /// Encrypted Aegis vault with every TOTP seed.
+    Aegis,
+    /// Encrypted andOTP backup (.json.aes) with every TOTP seed.
+    Andotp,
+    /// The whole vault as an age file, readable with the standard age tools.
+    Age,
+    /// The whole vault as an armored PGP message for --key, readable with gpg.
+    Openpgp,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("securepass-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Unlock { vault } => {
+            let (_, engine) = open(&vault)?;
+            println!("Unlocked {}: {} entries", vault.display(), engine.entry_count());
+        }
+        Command::List { vault, json } => {
+            let (_, engine) = open(&vault)?;
+            let entries = &engine.vault().entries;
+            if json {
+                let listed: Vec<_> = entries
+                    .iter()
+                    .map(|e| serde_json::json!({"id": e.id, "title": e.title, "username": e.username, "url": e.url}))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&listed).map_err(|e| format!("List serialize error: {}", e))?);
+            } else {
+                for e in entries {
+                    println!("{}\t{}\t{}\t{}", e.id, e.title, e.username, e.url);
+                }
+            }
+        }
+        Command::Get { vault, query, field } => {
+            let (_, engine) = open(&vault)?;
+            let entry = find_entry(&engine.vault().entries, &query)?;
+            let value = match field {
+                Field::Password => Zeroizing::new(entry.password.clone()),
+                Field::Username => Zeroizing::new(entry.username.clone()),
+                Field::Url => Zeroizing::new(entry.url.clone()),
+                Field::Notes => Zeroizing::new(entry.notes.clone()),
+                Field::Totp => Zeroizing::new(totp_code(entry, now())?),
+                Field::Json => Zeroizing::new(
+                    serde_json::to_string_pretty(entry).map_err(|e| format!("Entry serialize error: {}", e))?,
+                ),
+            };
+            println!("{}", value.as_str());
+        }
+        Command::Generate { style, length, no_uppercase, no_numbers, no_symbols } => {
+            let password = match style {
+                Style::Random => generator::password(&PasswordOptions {
+                    length,
+                    use_uppercase: !no_uppercase,
+                    use_numbers: !no_numbers,
+                    use_symbols: !no_symbols,
+                }),
+                Style::Mac => generator::mac_password(),
+                Style::Passphrase => generator::passphrase(),
+            };
+            println!("{}", password);
+        }
+        Command::Totp { vault, query } => {
+            let (_, engine) = open(&vault)?;
+            println!("{}", totp_code(find_entry(&engine.vault().entries, &query)?, now())?);
+        }
+        Command::Import { vault, file } => {
+            let (mut vault_file, mut engine) = open(&vault)?;
+            let text = Zeroizing::new(
+                std::fs::read_to_string(&file).map_err(|e| format!("Read error ({}): {}", file.display(), e))?,
+            );
+            let added = import(&mut engine, &text, now())?;
+            vault_file.seal(&engine, now())?;
+            vault_file.write(&vault)?;
+            println!("Imported {} entries into {}", added, vault.display());
+        }
+        Command::Export { vault, format, output, recipient, key } => {
+            if !recipient.is_empty() && !matches!(format, ExportFormat::Age) {
+                return Err("--recipient only applies to --format age".to_string());
+            }
+            let public_key = match (format, key) {
+                (ExportFormat::Openpgp, Some(path)) => {
+                    std::fs::read(&path).map_err(|e| format!("Read error ({}): {}", path.display(), e))?
+                }
+                (ExportFormat::Openpgp, None) => return Err("--format openpgp needs --key".to_string()),
+                (_, Some(_)) => return Err("--key only applies to --format openpgp".to_string()),
+                (_, None) => Vec::new(),
+            };
+            let (_, engine) = open(&vault)?;
+            let bytes = Zeroizing::new(match format {
+                ExportFormat::Json => engine.vault().to_json()?.into_bytes(),
+                ExportFormat::Aegis => engine.export_totp_aegis(&export_password()?)?.into_bytes(),
+                ExportFormat::Andotp => {
+                    if output.is_none() {
+                        return Err("andOTP backups are binary; pass --output".to_string());
+                    }
+                    engine.export_totp_andotp(&export_password()?)?
+                }
+                ExportFormat::Age => {
+                    if output.is_none() {
+                        return Err("age files are binary; pass --output".to_string());
+                    }
+                    if recipient.is_empty() {
+                        engine.export_age_passphrase(&export_password()?)?
+                    } else {
+                        engine.export_age_recipients(&recipient.join("\n"))?
+                    }
+                }
+                ExportFormat::Openpgp => engine.export_openpgp(&public_key, now())?.into_bytes(),
+            });
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, bytes.as_slice()).map_err(|e| format!("Write error ({}): {}", path.display(), e))?
+                }
+                None => io::stdout().write_all(&bytes).map_err(|e| format!("Write error: {}", e))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the vault file and unlocks it with the master password.
+fn open(path: &Path) -> Result<(VaultFile, Engine), String> {
+    let file = VaultFile::read(path)?;
+    let password = secret_input("SECUREPASS_PASSWORD", "Master password: ")?;
+    let engine = file.unlock(&password)?;
+    Ok((file, engine))
+}
+
+fn export_password() -> Result<Zeroizing<String>, String> {
+    secret_input("SECUREPASS_EXPORT_PASSWORD", "Export password: ")
+}
+
+/// The environment variable when set (for scripts), otherwise a hidden prompt on the terminal.
+fn secret_input(var: &str, prompt: &str) -> Result<Zeroizing<String>, String> {
+    if let Ok(value) = std::env::var(var) {
+        return Ok(Zeroizing::new(value));
+    }
+    rpassword::prompt_password(prompt).map(Zeroizing::new).map_err(|e| format!("Password prompt error: {}", e))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Finds an entry by exact ID, or else by case-insensitive title. Ambiguous titles are an error.
+fn find_entry<'a>(entries: &'a [VaultEntry], query: &str) -> Result<&'a VaultEntry, String> {
+    if let Some(entry) = entries.iter().find(|e| e.id == query) {
+        return Ok(entry);
+    }
+    let mut matches = entries.iter().filter(|e| e.title.eq_ignore_ascii_case(query));
+    match (matches.next(), matches.next()) {
+        (Some(entry), None) => Ok(entry),
+        (Some(_), Some(_)) => Err(format!("'{}' matches several entries; use the ID from 'list'", query)),
+        (None, _) => Err(format!("No entry matches '{}'", query)),
+    }
+}
+
+fn totp_code(entry: &VaultEntry, now: u64) -> Result<String, String> {
+    TotpConfig::from_entry(entry)
+        .ok_or_else(|| format!("Entry '{}' has no TOTP secret", entry.title))??
+        .code_at(now)
+}
+
+/// Adds the entries in `text` to the engine's vault and returns how many were added.
+fn import(engine: &mut Engine, text: &str, now: u64) -> Result<usize, String> {
+    let trimmed = text.trim_start();
+    if !(trimmed.starts_with('[') || trimmed.starts_with('{')) {
+        let mut added = 0;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            added += engine.import_google_migration(line, now)?;
+        }
+        return Ok(added);
+    }
+
+    let entries = if trimmed.starts_with('[') {
+        serde_json::from_str::<Vec<VaultEntry>>(text).map_err(|e| format!("Import parse error: {}", e))?
+    } else {
+        vault::VaultData::from_json(text)?.entries.clone()
+    };
+
+    let target = &mut engine.vault_mut().entries;
+    let added = entries.len();
+    for mut entry in entries {
+        // Keep IDs unique so an import can never shadow an existing entry.
+        if entry.id.is_empty() || target.iter().any(|e| e.id == entry.id) {
+            entry.id = vault::new_entry_id();
+        }
+        if entry.last_modified == 0 {
+            entry.last_modified = now;
+        }
+        target.push(entry);
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, title: &str) -> VaultEntry {
+        VaultEntry { id: id.to_string(), title: title.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_find_entry() {
+        let entries = vec![entry("1", "GitHub"), entry("2", "Mail"), entry("3", "mail")];
+        assert_eq!(find_entry(&entries, "github").unwrap().id, "1");
+        assert_eq!(find_entry(&entries, "3").unwrap().title, "mail");
+        assert!(find_entry(&entries, "Mail").unwrap_err().contains("several"));
+        assert!(find_entry(&entries, "Bank").is_err());
+    }
+
+    #[test]
+    fn test_import_json_and_migration() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault_mut().entries.push(entry("1", "Existing"));
+
+        let json = r#"[{"id": "1", "title": "Clash"}, {"id": "", "title": "Fresh", "totpSecret": "JBSWY3DPEHPK3PXP"}]"#;
+        assert_eq!(import(&mut engine, json, 100).unwrap(), 2);
+        let ids: Vec<&str> = engine.vault().entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids[1] != "1" && !ids[2].is_empty());
+        assert_eq!(engine.vault().entries[2].last_modified, 100);
+        assert_eq!(totp_code(&engine.vault().entries[2], 0).unwrap().len(), 6);
+
+        let uri = "otpauth-migration://offline?data=CiIKCkhlbGxvId6tvu8SBWFsaWNlGgdFeGFtcGxlIAEoATAC";
+        assert_eq!(import(&mut engine, &format!("{}\n\n", uri), 100).unwrap(), 1);
+        assert_eq!(engine.entry_count(), 4);
+    }
+}
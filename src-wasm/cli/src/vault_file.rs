@@ -0,0 +1,161 @@
+// --- Vault File ---
+// The on-disk format is the web app's backup file (src/utils/backup.ts):
+// {"version": 1, "timestamp": "...", "vault": {"iv": [...], "data": [...]}, "salt": [...]},
+// plus "passwordPolicy" (text::PasswordPolicy) for vaults that normalize the
+// master password; files without it were written before policies and use 0.
+// Once the web app has upgraded the vault's KDF, "keyEnvelope" (kdf::KeyEnvelope)
+// replaces "salt" and "passwordPolicy" as the way to the key.
+// A file exported from the browser opens here unchanged, and a file saved here
+// restores in the browser through "Restore from Backup".
+
+use std::fs;
+use std::path::Path;
+
+use securepass_core::duress::EncryptedPackage;
+use securepass_core::kdf::KeyEnvelope;
+use securepass_core::text::PasswordPolicy;
+use securepass_core::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VaultFile {
+    pub version: u32,
+    #[serde(default)]
+    pub timestamp: String,
+    pub vault: EncryptedPackage,
+    /// Empty when the file has a key envelope.
+    #[serde(default)]
+    pub salt: Vec<u8>,
+    /// Left out when 0, so a pre-policy vault's file is written back as it was read.
+    #[serde(default, rename = "passwordPolicy", skip_serializing_if = "is_raw")]
+    pub password_policy: PasswordPolicy,
+    #[serde(default, rename = "keyEnvelope", skip_serializing_if = "Option::is_none")]
+    pub key_envelope: Option<KeyEnvelope>,
+    /// Fields a newer web app may add are written back untouched.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl VaultFile {
+    pub fn read(path: &Path) -> Result<VaultFile, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Read error ({}): {}", path.display(), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("Vault file parse error: {}", e))
+    }
+
+    /// Writes via a temporary file and a rename, so a crash never leaves half a vault behind.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Vault file serialize error: {}", e))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Write error ({}): {}", tmp.display(), e))?;
+        fs::rename(&tmp, path).map_err(|e| format!("Write error ({}): {}", path.display(), e))
+    }
+
+    /// Derives the key and decrypts the vault. An outdated envelope is left for the
+    /// web app to upgrade, so a read here costs one Argon2 run.
+    pub fn unlock(&self, password: &str) -> Result<Engine, String> {
+        let mut engine = match &self.key_envelope {
+            Some(envelope) => Engine::from_key(envelope.unlock(password)?),
+            None => Engine::new_with_policy(password, &self.salt, self.password_policy)?,
+        };
+        engine
+            .load_vault_package(&self.vault)
+            .map_err(|e| format!("Wrong password or damaged vault file ({})", e))?;
+        Ok(engine)
+    }
+
+    /// Re-encrypts the engine's vault under a fresh IV and stamps the save time.
+    pub fn seal(&mut self, engine: &Engine, now: u64) -> Result<(), String> {
+        self.vault = engine.seal_vault_package()?;
+        self.timestamp = iso_timestamp(now);
+        Ok(())
+    }
+}
+
+fn is_raw(policy: &PasswordPolicy) -> bool {
+    *policy == PasswordPolicy::Raw
+}
+
+/// Unix seconds as the ISO 8601 string JS's `Date.toISOString()` produces.
+pub fn iso_timestamp(now: u64) -> String {
+    let (days, secs) = (now / 86_400, now % 86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm), valid for every date after 1970.
+    let z = days as i64 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.000Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_timestamp() {
+        assert_eq!(iso_timestamp(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso_timestamp(951_782_400), "2000-02-29T00:00:00.000Z");
+        assert_eq!(iso_timestamp(1_700_000_000), "2023-11-14T22:13:20.000Z");
+    }
+
+    #[test]
+    fn test_password_policy_field() {
+        let json = r#"{"version": 1, "vault": {"iv": [], "data": []}, "salt": [1]}"#;
+        let legacy: VaultFile = serde_json::from_str(json).unwrap();
+        assert_eq!(legacy.password_policy, PasswordPolicy::Raw);
+        assert!(!serde_json::to_string(&legacy).unwrap().contains("passwordPolicy"));
+
+        let nfc: VaultFile = serde_json::from_str(&json.replace("[1]}", "[1], \"passwordPolicy\": 1}")).unwrap();
+        assert_eq!(nfc.password_policy, PasswordPolicy::Nfc);
+        assert!(serde_json::to_string(&nfc).unwrap().contains("\"passwordPolicy\":1"));
+        assert!(nfc.extra.is_empty());
+    }
+
+    #[test]
+    fn test_seal_unlock_roundtrip() {
+        let salt = b"salt-123456789012".to_vec();
+        let mut engine = Engine::new("pw", &salt).unwrap();
+        engine.vault_mut().entries.push(securepass_core::vault::VaultEntry {
+            id: "1".to_string(),
+            title: "GitHub".to_string(),
+            ..Default::default()
+        });
+
+        let mut file = VaultFile {
+            version: 1,
+            timestamp: String::new(),
+            vault: EncryptedPackage::legacy(&[], &[]),
+            salt,
+            password_policy: PasswordPolicy::Raw,
+            key_envelope: None,
+            extra: Map::new(),
+        };
+        file.seal(&engine, 0).unwrap();
+
+        let reparsed: VaultFile = serde_json::from_str(&serde_json::to_string(&file).unwrap()).unwrap();
+        assert_eq!(reparsed.unlock("pw").unwrap().vault().entries[0].title, "GitHub");
+        assert!(reparsed.unlock("wrong").is_err());
+
+        // After the web app's KDF upgrade: the envelope replaces salt and policy.
+        let (_, upgraded) = KeyEnvelope::legacy(&file.salt, PasswordPolicy::Raw).unlock_upgrading("pw").unwrap();
+        file.key_envelope = upgraded;
+        file.salt.clear();
+        let reparsed: VaultFile = serde_json::from_str(&serde_json::to_string(&file).unwrap()).unwrap();
+        assert_eq!(reparsed.unlock("pw").unwrap().vault().entries[0].title, "GitHub");
+    }
+}
@@ -0,0 +1,327 @@
+// --- age Export ---
+// Writes files in the age v1 format (https://age-encryption.org/v1), so an
+// export stays readable with the standard `age`/`rage` tools even if SecurePass
+// is gone. Only encryption lives here; decrypting is the job of those tools.
+//
+// File layout:
+//   header:  "age-encryption.org/v1\n"
+//            one stanza per recipient: "-> <type> <args...>\n" + wrapped file key
+//            "--- " + Base64(HMAC-SHA256(HKDF(file key, "header"), header so far))
+//   payload: nonce (16) || STREAM chunks of 64 KiB sealed with ChaCha20-Poly1305
+//            under HKDF(file key, salt = nonce, "payload")
+//
+// Two stanza types are supported: scrypt (a passphrase, which must be the only
+// stanza) and X25519 (one or more "age1..." public keys).
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD as B64, Engine};
+use bech32::{Bech32, Hrp};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Nonce,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
+
+pub const VERSION_LINE: &str = "age-encryption.org/v1";
+
+/// The scrypt cost `age` itself picks for passphrases (N = 2^18, r = 8, p = 1).
+pub const SCRYPT_LOG_N: u8 = 18;
+
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const CHUNK_SIZE: usize = 64 * 1024;
+const STANZA_COLUMNS: usize = 64;
+
+/// A fresh X25519 identity in age's own encodings, for users without an age key yet.
+#[derive(Serialize, Deserialize)]
+pub struct AgeIdentity {
+    /// "AGE-SECRET-KEY-1...": keep offline; this is what decrypts the export.
+    pub identity: String,
+    /// "age1...": pass to `encrypt_to_recipients`.
+    pub recipient: String,
+}
+
+struct Stanza {
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+/// Creates a new identity, equivalent to running `age-keygen`.
+pub fn generate_identity() -> Result<AgeIdentity, String> {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    let secret_bytes = Zeroizing::new(secret.to_bytes());
+    let identity = bech32::encode::<Bech32>(Hrp::parse_unchecked("age-secret-key-"), secret_bytes.as_slice())
+        .map_err(|e| format!("age encode error: {}", e))?;
+    Ok(AgeIdentity { identity: identity.to_uppercase(), recipient: encode_recipient(&public)? })
+}
+
+/// Encrypts `plaintext` to a passphrase (`age --passphrase`).
+pub fn encrypt_with_passphrase(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    encrypt_with_passphrase_cost(passphrase, plaintext, SCRYPT_LOG_N)
+}
+
+pub(crate) fn encrypt_with_passphrase_cost(passphrase: &str, plaintext: &[u8], log_n: u8) -> Result<Vec<u8>, String> {
+    if passphrase.is_empty() {
+        return Err("age error: passphrase is empty".to_string());
+    }
+    let file_key = random_file_key();
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut scrypt_salt = SCRYPT_LABEL.to_vec();
+    scrypt_salt.extend_from_slice(&salt);
+    let mut wrap_key = Zeroizing::new([0u8; 32]);
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|e| format!("scrypt params error: {}", e))?;
+    scrypt::scrypt(passphrase.as_bytes(), &scrypt_salt, &params, wrap_key.as_mut_slice())
+        .map_err(|e| format!("scrypt error: {}", e))?;
+
+    let stanza = Stanza {
+        args: vec!["scrypt".to_string(), B64.encode(salt), log_n.to_string()],
+        body: wrap_file_key(&wrap_key, &file_key)?,
+    };
+    write_file(&file_key, &[stanza], plaintext)
+}
+
+/// Encrypts `plaintext` to every "age1..." recipient in `recipients` (`age -R`).
+/// Accepts age's recipients-file syntax: one key per line, blank lines and `#` comments ignored.
+pub fn encrypt_to_recipients(recipients: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let keys = recipients
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(decode_recipient)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("age error: no recipients".to_string());
+    }
+
+    let file_key = random_file_key();
+    let mut stanzas = Vec::with_capacity(keys.len());
+    for recipient in &keys {
+        let ephemeral = StaticSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(recipient);
+        if !shared.was_contributory() {
+            return Err("age error: recipient is a low-order point".to_string());
+        }
+
+        let mut salt = [0u8; 64];
+        salt[..32].copy_from_slice(ephemeral_public.as_bytes());
+        salt[32..].copy_from_slice(recipient.as_bytes());
+        let mut wrap_key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+            .expand(X25519_LABEL, wrap_key.as_mut_slice())
+            .map_err(|e| format!("HKDF error: {}", e))?;
+
+        stanzas.push(Stanza {
+            args: vec!["X25519".to_string(), B64.encode(ephemeral_public.as_bytes())],
+            body: wrap_file_key(&wrap_key, &file_key)?,
+        });
+    }
+    write_file(&file_key, &stanzas, plaintext)
+}
+
+fn random_file_key() -> Zeroizing<[u8; 16]> {
+    let mut file_key = Zeroizing::new([0u8; 16]);
+    OsRng.fill_bytes(file_key.as_mut_slice());
+    file_key
+}
+
+/// Stanza bodies always use an all-zero nonce: every wrap key is used exactly once.
+fn wrap_file_key(wrap_key: &[u8; 32], file_key: &[u8; 16]) -> Result<Vec<u8>, String> {
+    ChaCha20Poly1305::new(wrap_key.into())
+        .encrypt(&Nonce::default(), file_key.as_slice())
+        .map_err(|e| format!("age wrap error: {}", e))
+}
+
+fn encode_recipient(public: &PublicKey) -> Result<String, String> {
+    bech32::encode::<Bech32>(Hrp::parse_unchecked("age"), public.as_bytes()).map_err(|e| format!("age encode error: {}", e))
+}
+
+fn decode_recipient(recipient: &str) -> Result<PublicKey, String> {
+    let invalid = || format!("age error: invalid recipient '{}'", recipient);
+    let (hrp, data) = bech32::decode(recipient).map_err(|_| invalid())?;
+    // Recipients are lowercase by definition; bech32 itself would also accept all-caps.
+    if hrp.as_str() != "age" || recipient.starts_with("AGE") {
+        return Err(invalid());
+    }
+    let bytes: [u8; 32] = data.try_into().map_err(|_| invalid())?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn write_file(file_key: &[u8; 16], stanzas: &[Stanza], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut header = format!("{}\n", VERSION_LINE);
+    for stanza in stanzas {
+        header.push_str(&format!("-> {}\n", stanza.args.join(" ")));
+        // Bodies wrap at 64 columns and always end on a short line, even an empty one.
+        let body = B64.encode(&stanza.body);
+        let mut rest = body.as_str();
+        loop {
+            let (line, tail) = rest.split_at(rest.len().min(STANZA_COLUMNS));
+            header.push_str(line);
+            header.push('\n');
+            if line.len() < STANZA_COLUMNS {
+                break;
+            }
+            rest = tail;
+        }
+    }
+    header.push_str("---");
+
+    let mut mac_key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, file_key)
+        .expand(b"header", mac_key.as_mut_slice())
+        .map_err(|e| format!("HKDF error: {}", e))?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(mac_key.as_slice()).map_err(|e| format!("HMAC error: {}", e))?;
+    mac.update(header.as_bytes());
+    header.push_str(&format!(" {}\n", B64.encode(mac.finalize().into_bytes())));
+
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let mut payload_key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&nonce), file_key)
+        .expand(b"payload", &mut payload_key)
+        .map_err(|e| format!("HKDF error: {}", e))?;
+    let cipher = ChaCha20Poly1305::new(&payload_key.into());
+    payload_key.zeroize();
+
+    // An empty plaintext is still one (empty, final) chunk.
+    let mut chunks: Vec<&[u8]> = plaintext.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+    let mut out = Vec::with_capacity(header.len() + 16 + plaintext.len() + chunks.len() * 16);
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(&nonce);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let sealed = cipher
+            .encrypt(&stream_nonce(i as u128, i + 1 == chunks.len()), *chunk)
+            .map_err(|e| format!("age encrypt error: {}", e))?;
+        out.extend_from_slice(&sealed);
+    }
+    Ok(out)
+}
+
+/// STREAM nonce: an 11-byte big-endian chunk counter, then 0x01 on the last chunk.
+fn stream_nonce(counter: u128, last: bool) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[..11].copy_from_slice(&counter.to_be_bytes()[5..]);
+    nonce[11] = u8::from(last);
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal reader, enough to check what `age -d` would do with our output.
+    fn decrypt(file: &[u8], unwrap: impl Fn(&[String], &[u8]) -> Option<[u8; 16]>) -> Vec<u8> {
+        let header_end = file.windows(4).position(|w| w == b"\n---").unwrap() + 4;
+        let mac_end = header_end + file[header_end..].iter().position(|&b| b == b'\n').unwrap();
+        let header = std::str::from_utf8(&file[..header_end]).unwrap();
+        let mut lines = header.lines();
+        assert_eq!(lines.next(), Some(VERSION_LINE));
+
+        let mut file_key = None;
+        let mut lines = lines.peekable();
+        while let Some(line) = lines.next().filter(|l| l.starts_with("-> ")) {
+            let args: Vec<String> = line[3..].split(' ').map(str::to_string).collect();
+            let mut body = String::new();
+            while let Some(next) = lines.next_if(|l| !l.starts_with("->") && !l.starts_with("---")) {
+                body.push_str(next);
+                if next.len() < STANZA_COLUMNS {
+                    break;
+                }
+            }
+            file_key = file_key.or_else(|| unwrap(&args, &B64.decode(body).unwrap()));
+        }
+        let file_key = file_key.expect("no stanza matched");
+
+        let mut mac_key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, &file_key).expand(b"header", &mut mac_key).unwrap();
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).unwrap();
+        mac.update(&file[..header_end]);
+        mac.verify_slice(&B64.decode(&file[header_end + 1..mac_end]).unwrap()).unwrap();
+
+        let nonce = &file[mac_end + 1..mac_end + 17];
+        let mut payload_key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(nonce), &file_key).expand(b"payload", &mut payload_key).unwrap();
+        let cipher = ChaCha20Poly1305::new(&payload_key.into());
+        let chunks: Vec<&[u8]> = file[mac_end + 17..].chunks(CHUNK_SIZE + 16).collect();
+        chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| cipher.decrypt(&stream_nonce(i as u128, i + 1 == chunks.len()), *c).unwrap())
+            .collect()
+    }
+
+    fn unwrap_with(key: &[u8; 32], body: &[u8]) -> Option<[u8; 16]> {
+        ChaCha20Poly1305::new(key.into()).decrypt(&Nonce::default(), body).ok()?.try_into().ok()
+    }
+
+    #[test]
+    fn test_passphrase_roundtrip() {
+        let plaintext = vec![7u8; CHUNK_SIZE + 100];
+        let file = encrypt_with_passphrase_cost("correct horse", &plaintext, 4).unwrap();
+
+        let opened = decrypt(&file, |args, body| {
+            assert_eq!(args[0], "scrypt");
+            assert_eq!(args[2], "4");
+            let mut salt = SCRYPT_LABEL.to_vec();
+            salt.extend_from_slice(&B64.decode(&args[1]).unwrap());
+            let mut key = [0u8; 32];
+            scrypt::scrypt(b"correct horse", &salt, &scrypt::Params::new(4, 8, 1, 32).unwrap(), &mut key).unwrap();
+            unwrap_with(&key, body)
+        });
+        assert_eq!(opened, plaintext);
+        assert!(encrypt_with_passphrase("", b"x").is_err());
+    }
+
+    #[test]
+    fn test_recipients_roundtrip() {
+        let alice = generate_identity().unwrap();
+        let bob = generate_identity().unwrap();
+        assert!(alice.recipient.starts_with("age1") && alice.recipient.len() == 62);
+        assert!(alice.identity.starts_with("AGE-SECRET-KEY-1"));
+
+        let recipients = format!("# backup keys\n{}\n\n{}\n", alice.recipient, bob.recipient);
+        let file = encrypt_to_recipients(&recipients, b"").unwrap();
+        assert_eq!(file.windows(4).filter(|w| w == b"\n-> ").count(), 2);
+
+        let (_, secret) = bech32::decode(&bob.identity).unwrap();
+        let secret = StaticSecret::from(<[u8; 32]>::try_from(secret).unwrap());
+        let opened = decrypt(&file, |args, body| {
+            assert_eq!(args[0], "X25519");
+            let ephemeral: [u8; 32] = B64.decode(&args[1]).unwrap().try_into().unwrap();
+            let ephemeral = PublicKey::from(ephemeral);
+            let mut salt = ephemeral.as_bytes().to_vec();
+            salt.extend_from_slice(PublicKey::from(&secret).as_bytes());
+            let mut key = [0u8; 32];
+            Hkdf::<Sha256>::new(Some(&salt), secret.diffie_hellman(&ephemeral).as_bytes())
+                .expand(X25519_LABEL, &mut key)
+                .unwrap();
+            unwrap_with(&key, body)
+        });
+        assert!(opened.is_empty());
+
+        assert!(encrypt_to_recipients("# nobody\n", b"x").is_err());
+        assert!(encrypt_to_recipients(&alice.identity, b"x").is_err());
+        assert!(encrypt_to_recipients(&alice.recipient.to_uppercase(), b"x").is_err());
+    }
+}
+#[test]
+fn interop_dump() {
+    let big = vec![b'a'; 3 * 64 * 1024];
+    std::fs::write("/tmp/x25519.age", encrypt_to_recipients("age1lp00vhp47ncwxhdyaca249xyyfr4xjzdxuzepkrec3z262376awq0dqu47", &big).unwrap()).unwrap();
+    std::fs::write("/tmp/empty.age", encrypt_to_recipients("age1lp00vhp47ncwxhdyaca249xyyfr4xjzdxuzepkrec3z262376awq0dqu47", b"").unwrap()).unwrap();
+    std::fs::write("/tmp/pass.age", encrypt_with_passphrase_cost("hunter2", b"hello age", 10).unwrap()).unwrap();
+    let id = generate_identity().unwrap();
+    std::fs::write("/tmp/own_id.txt", &id.identity).unwrap();
+    std::fs::write("/tmp/own.age", encrypt_to_recipients(&id.recipient, b"own key").unwrap()).unwrap();
+}
@@ -0,0 +1,130 @@
+// --- Apple Passwords / iCloud Keychain Import ---
+// The CSV that Safari ("Export Passwords") and the Passwords app write:
+//   Title,URL,Username,Password,Notes,OTPAuth
+// Title is usually "host (username)", so it is cut back to the host. Keychain
+// keeps a login per host, and a site used both with and without "www." shows up
+// twice for the same account; rows with the same host (ignoring "www.") and
+// username are merged. If their passwords differ the second one is kept as an
+// "Other password" field, since the export doesn't say which is current.
+// Passkeys are never exported (their private keys can't leave the keychain), so
+// they have to be re-registered on each site.
+
+use url::Url;
+
+use crate::import::{Imported, Importer};
+use crate::vault::{new_entry_id, CustomField, FieldKind, VaultEntry};
+
+pub struct AppleKeychain;
+
+impl Importer for AppleKeychain {
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String> {
+        parse_csv(data, now)
+    }
+}
+
+/// Reads a Safari or Passwords app export.
+pub fn parse_csv(csv: &str, now: u64) -> Result<Imported, String> {
+    let mut rows = crate::csv::rows(csv)?.into_iter();
+    let header = rows.next().ok_or("Apple import error: the file is empty")?;
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    if column("title").is_none() || column("url").is_none() || column("password").is_none() {
+        return Err("Apple import error: not a Safari or Passwords app export".to_string());
+    }
+    let columns: Vec<Option<usize>> =
+        ["title", "url", "username", "password", "notes", "otpauth"].iter().map(|name| column(name)).collect();
+
+    let mut import = Imported::new("apple-keychain");
+    for mut row in rows {
+        if row.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let mut take = |i: usize| columns[i].and_then(|c| row.get_mut(c)).map(std::mem::take).unwrap_or_default();
+        let (title, url, username, password, notes, otp) = (take(0), take(1), take(2), take(3), take(4), take(5));
+        let entry = VaultEntry {
+            id: new_entry_id(),
+            title: trim_title(&title, &username),
+            url,
+            username,
+            password,
+            notes,
+            totp_secret: Some(otp).filter(|otp| !otp.is_empty()),
+            last_modified: now,
+            ..Default::default()
+        };
+        match import.entries.iter_mut().find(|e| same_account(e, &entry)) {
+            Some(existing) => {
+                import.duplicates += 1;
+                merge(existing, entry);
+            }
+            None => import.entries.push(entry),
+        }
+    }
+    Ok(import)
+}
+
+/// "github.com (octo)" to "github.com".
+fn trim_title(title: &str, username: &str) -> String {
+    let suffix = format!(" ({})", username);
+    title.strip_suffix(&suffix).filter(|_| !username.is_empty()).unwrap_or(title).trim().to_string()
+}
+
+/// The host without "www.", lowercased.
+fn host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+fn same_account(a: &VaultEntry, b: &VaultEntry) -> bool {
+    let same_site = match (host(&a.url), host(&b.url)) {
+        (Some(x), Some(y)) => x == y,
+        _ => a.url == b.url,
+    };
+    same_site && a.username == b.username
+}
+
+/// Folds a twin row into the entry already read.
+fn merge(existing: &mut VaultEntry, twin: VaultEntry) {
+    if twin.password != existing.password && !existing.fields.iter().any(|f| f.value == twin.password) {
+        existing.fields.push(CustomField { name: "Other password".to_string(), kind: FieldKind::Hidden, value: twin.password });
+    }
+    if existing.totp_secret.is_none() {
+        existing.totp_secret = twin.totp_secret;
+    }
+    if !twin.notes.is_empty() && !existing.notes.contains(&twin.notes) {
+        if !existing.notes.is_empty() {
+            existing.notes.push('\n');
+        }
+        existing.notes.push_str(&twin.notes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_merges_www_twins() {
+        let csv = "Title,URL,Username,Password,Notes,OTPAuth\n\
+            github.com (octo),https://github.com/,octo,pw,,otpauth://totp/GitHub:octo?secret=JBSWY3DPEHPK3PXP\n\
+            www.github.com (octo),https://www.github.com/,octo,pw,recovery codes in the safe,\n\
+            shop.example (me),https://shop.example/,me,old,,\n\
+            www.shop.example (me),https://www.shop.example/,me,new,,\n\
+            shop.example (you),https://shop.example/,you,pw,,\n";
+        let import = AppleKeychain.import(csv, 9).unwrap();
+        assert_eq!((import.source, import.entries.len(), import.duplicates), ("apple-keychain", 3, 2));
+
+        let github = &import.entries[0];
+        assert_eq!((github.title.as_str(), github.notes.as_str()), ("github.com", "recovery codes in the safe"));
+        assert!(github.totp_secret.as_deref().unwrap().starts_with("otpauth://totp/"));
+        assert!(github.fields.is_empty());
+        let shop = &import.entries[1];
+        assert_eq!((shop.password.as_str(), shop.fields[0].value.as_str()), ("old", "new"));
+    }
+
+    #[test]
+    fn test_other_files_are_refused() {
+        assert!(AppleKeychain.import("name,url,username,password\n", 0).unwrap_err().contains("not a Safari"));
+        assert!(AppleKeychain.import("", 0).unwrap_err().contains("empty"));
+    }
+}
@@ -0,0 +1,269 @@
+// --- Attachments ---
+// Files attached to entries are stored as two separately encrypted parts:
+//   * metadata (filename, MIME type, size), small enough to decrypt for every row of a list
+//   * content (the file bytes), only decrypted when the user opens or downloads the file
+// Each part has its own HKDF subkey per attachment, and both are bound to the
+// attachment ID so a metadata blob can't be paired with someone else's content.
+//
+// Deduplicated ("convergent") content is opt-in, per vault (see
+// 'VaultSettings::dedup_attachments'). The file key is an HMAC of the bytes
+// under a key of this vault, and the attachment ID comes from the file key, so
+// the same file attached twice gets the same ID and the same blob, and syncs
+// and stores once. The price: whoever sees the stored blobs (a sync server, a
+// backup) can tell which attachments are the same file, and which are the same
+// file as one deleted earlier. The HMAC key keeps that within one vault: no one
+// can test a guessed file against a blob without it.
+//
+// Both parts are sealed with key commitment ('keys::seal_committed') since
+// metadata version 2. The content blob has no room for a version, so the ID
+// carries it: IDs from 'new_attachment_id' start with COMMITTED_PREFIX, and only
+// content under an older, unprefixed ID opens as plain AES-GCM. The ID is the
+// key derivation input and the AAD, so renaming an attachment doesn't help.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::gcm_siv;
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::vault::{hex, new_entry_id};
+
+pub(crate) const META_PURPOSE: &str = "securepass-attachment-meta-v1";
+pub(crate) const CONTENT_PURPOSE: &str = "securepass-attachment-content-v1";
+const CONVERGENT_MAC_PURPOSE: &str = "securepass-attachment-convergent-mac-v1";
+const CONVERGENT_WRAP_PURPOSE: &str = "securepass-attachment-convergent-wrap-v1";
+const CONVERGENT_ID_PURPOSE: &str = "securepass-attachment-convergent-id-v1";
+/// Starts every convergent attachment ID; random IDs (see 'vault::new_entry_id') never do.
+const CONVERGENT_PREFIX: &str = "c-";
+/// The nonce for both convergent seals. Each file key seals one file only, and
+/// GCM-SIV under the vault's wrap key reveals no more than which file keys are equal.
+const CONVERGENT_NONCE: [u8; gcm_siv::NONCE_LEN] = [0; gcm_siv::NONCE_LEN];
+const WRAPPED_KEY_LEN: usize = 32 + gcm_siv::TAG_LEN;
+/// Starts every random attachment ID whose content is committed.
+const COMMITTED_PREFIX: &str = "k-";
+/// Metadata version 2 is committed; version 1 was plain AES-GCM.
+const META_VERSION: u8 = 2;
+const LEGACY_META_VERSION: u8 = 1;
+
+/// What the list view needs to render a file row.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AttachmentMeta {
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// How an entry refers to one of its attachments. The size is repeated here so
+/// totals can be shown without decrypting any metadata.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AttachmentRef {
+    pub id: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// The encrypted metadata, stored next to (not inside) the content blob.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SealedMeta {
+    pub version: u8,
+    pub iv: String,
+    pub data: String,
+}
+
+/// Encrypts the metadata part.
+pub fn encrypt_meta(master_key: &[u8; 32], attachment_id: &str, meta: &AttachmentMeta) -> Result<SealedMeta, String> {
+    let mut key = derive_subkey(master_key, META_PURPOSE, attachment_id.as_bytes());
+    let mut plain = serde_json::to_vec(meta).map_err(|e| format!("Meta serialize error: {}", e))?;
+    let sealed = seal_committed(&key, &plain, attachment_id.as_bytes());
+    key.zeroize();
+    plain.zeroize();
+
+    let (iv, data) = sealed?;
+    Ok(SealedMeta { version: META_VERSION, iv: B64.encode(iv), data: B64.encode(data) })
+}
+
+/// Decrypts the metadata part without touching the content.
+pub fn decrypt_meta(master_key: &[u8; 32], attachment_id: &str, sealed: &SealedMeta) -> Result<AttachmentMeta, String> {
+    if ![LEGACY_META_VERSION, META_VERSION].contains(&sealed.version) {
+        return Err(format!("Unsupported attachment meta version: {}", sealed.version));
+    }
+    let iv = B64.decode(&sealed.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&sealed.data).map_err(|e| format!("Meta decode error: {}", e))?;
+
+    let mut key = derive_subkey(master_key, META_PURPOSE, attachment_id.as_bytes());
+    let plain = if sealed.version == LEGACY_META_VERSION {
+        open(&key, &iv, &data, attachment_id.as_bytes())
+    } else {
+        open_committed(&key, &iv, &data, attachment_id.as_bytes())
+    };
+    key.zeroize();
+
+    let mut plain = plain?;
+    let meta = serde_json::from_slice(&plain).map_err(|e| format!("Meta parse error: {}", e));
+    plain.zeroize();
+    meta
+}
+
+/// A fresh ID for an attachment sealed with 'encrypt_content'.
+pub fn new_attachment_id() -> String {
+    format!("{}{}", COMMITTED_PREFIX, new_entry_id())
+}
+
+/// Encrypts the file bytes under an ID from 'new_attachment_id'. Output is
+/// iv (12 bytes) || commitment || ciphertext || tag.
+pub fn encrypt_content(master_key: &[u8; 32], attachment_id: &str, content: &[u8]) -> Result<Vec<u8>, String> {
+    if !attachment_id.starts_with(COMMITTED_PREFIX) {
+        return Err("Attachment error: content IDs come from 'new_attachment_id'".to_string());
+    }
+    let mut key = derive_subkey(master_key, CONTENT_PURPOSE, attachment_id.as_bytes());
+    let sealed = seal_committed(&key, content, attachment_id.as_bytes());
+    key.zeroize();
+
+    let (iv, data) = sealed?;
+    let mut blob = Vec::with_capacity(12 + data.len());
+    blob.extend_from_slice(&iv);
+    blob.extend_from_slice(&data);
+    Ok(blob)
+}
+
+/// Encrypts the file bytes convergently: the same bytes in the same vault always
+/// give the same ID and blob. Returns (attachment ID, blob); the blob is the
+/// wrapped file key (48 bytes) || ciphertext || tag.
+pub fn encrypt_content_convergent(master_key: &[u8; 32], content: &[u8]) -> Result<(String, Vec<u8>), String> {
+    let mut mac_key = derive_subkey(master_key, CONVERGENT_MAC_PURPOSE, b"");
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).map_err(|e| format!("HMAC error: {}", e))?;
+    mac_key.zeroize();
+    mac.update(content);
+    let mut file_key: [u8; 32] = mac.finalize().into_bytes().into();
+    let id = convergent_id(&file_key);
+
+    let mut wrap_key = derive_subkey(master_key, CONVERGENT_WRAP_PURPOSE, b"");
+    let wrapped = gcm_siv::encrypt(&wrap_key, &CONVERGENT_NONCE, &file_key, id.as_bytes());
+    wrap_key.zeroize();
+    let sealed = gcm_siv::encrypt(&file_key, &CONVERGENT_NONCE, content, id.as_bytes());
+    file_key.zeroize();
+
+    let mut blob = wrapped?;
+    blob.extend_from_slice(&sealed?);
+    Ok((id, blob))
+}
+
+/// True for an ID from 'encrypt_content_convergent'.
+pub fn is_convergent(attachment_id: &str) -> bool {
+    attachment_id.starts_with(CONVERGENT_PREFIX)
+}
+
+fn convergent_id(file_key: &[u8; 32]) -> String {
+    format!("{}{}", CONVERGENT_PREFIX, hex(&derive_subkey(file_key, CONVERGENT_ID_PURPOSE, b"")[..16]))
+}
+
+fn decrypt_convergent(master_key: &[u8; 32], attachment_id: &str, blob: &[u8]) -> Result<Vec<u8>, String> {
+    if blob.len() < WRAPPED_KEY_LEN + gcm_siv::TAG_LEN {
+        return Err("Attachment blob is too short".to_string());
+    }
+    let (wrapped, sealed) = blob.split_at(WRAPPED_KEY_LEN);
+    let mut wrap_key = derive_subkey(master_key, CONVERGENT_WRAP_PURPOSE, b"");
+    let unwrapped = gcm_siv::decrypt(&wrap_key, &CONVERGENT_NONCE, wrapped, attachment_id.as_bytes());
+    wrap_key.zeroize();
+
+    let mut unwrapped = unwrapped?;
+    let file_key = <[u8; 32]>::try_from(unwrapped.as_slice());
+    unwrapped.zeroize();
+    let Ok(mut file_key) = file_key else {
+        return Err("Attachment error: wrapped file key has the wrong length".to_string());
+    };
+    let plain = if convergent_id(&file_key) == attachment_id {
+        gcm_siv::decrypt(&file_key, &CONVERGENT_NONCE, sealed, attachment_id.as_bytes())
+    } else {
+        Err("Attachment error: blob does not belong to this attachment".to_string())
+    };
+    file_key.zeroize();
+    plain
+}
+
+/// Decrypts a blob produced by `encrypt_content` or `encrypt_content_convergent`.
+pub fn decrypt_content(master_key: &[u8; 32], attachment_id: &str, blob: &[u8]) -> Result<Vec<u8>, String> {
+    if is_convergent(attachment_id) {
+        return decrypt_convergent(master_key, attachment_id, blob);
+    }
+    if blob.len() < 12 + 16 {
+        return Err("Attachment blob is too short".to_string());
+    }
+    let mut key = derive_subkey(master_key, CONTENT_PURPOSE, attachment_id.as_bytes());
+    let plain = if attachment_id.starts_with(COMMITTED_PREFIX) {
+        open_committed(&key, &blob[..12], &blob[12..], attachment_id.as_bytes())
+    } else {
+        open(&key, &blob[..12], &blob[12..], attachment_id.as_bytes())
+    };
+    key.zeroize();
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [4u8; 32];
+
+    fn meta() -> AttachmentMeta {
+        AttachmentMeta { filename: "passport.pdf".to_string(), mime_type: "application/pdf".to_string(), size: 3 }
+    }
+
+    #[test]
+    fn test_meta_and_content_roundtrip() {
+        let sealed = encrypt_meta(&KEY, "att-1", &meta()).unwrap();
+        assert!(!sealed.data.contains("passport"));
+        assert_eq!(decrypt_meta(&KEY, "att-1", &sealed).unwrap(), meta());
+
+        let id = new_attachment_id();
+        let blob = encrypt_content(&KEY, &id, b"PDF").unwrap();
+        assert_eq!(decrypt_content(&KEY, &id, &blob).unwrap(), b"PDF");
+        assert!(encrypt_content(&KEY, "att-1", b"PDF").is_err());
+    }
+
+    #[test]
+    fn test_parts_are_bound_to_their_attachment() {
+        let sealed = encrypt_meta(&KEY, "att-1", &meta()).unwrap();
+        assert!(decrypt_meta(&KEY, "att-2", &sealed).is_err());
+
+        let id = new_attachment_id();
+        let blob = encrypt_content(&KEY, &id, b"PDF").unwrap();
+        assert!(decrypt_content(&KEY, &new_attachment_id(), &blob).is_err());
+        assert!(decrypt_content(&KEY, &id, &blob[..20]).is_err());
+        // Dropping the prefix changes the key and the AAD, so the legacy path fails too.
+        assert!(decrypt_content(&KEY, &id[COMMITTED_PREFIX.len()..], &blob).is_err());
+    }
+
+    #[test]
+    fn test_convergent_content_dedups_within_a_vault() {
+        let (id, blob) = encrypt_content_convergent(&KEY, b"PDF").unwrap();
+        assert!(is_convergent(&id) && !is_convergent(&new_attachment_id()));
+        assert_eq!(encrypt_content_convergent(&KEY, b"PDF").unwrap(), (id.clone(), blob.clone()));
+        assert_eq!(decrypt_content(&KEY, &id, &blob).unwrap(), b"PDF");
+
+        let (other_id, other_blob) = encrypt_content_convergent(&KEY, b"PNG").unwrap();
+        assert_ne!(other_id, id);
+        assert!(decrypt_content(&KEY, &id, &other_blob).is_err());
+        assert_ne!(encrypt_content_convergent(&[5u8; 32], b"PDF").unwrap().0, id);
+        assert!(decrypt_content(&[5u8; 32], &id, &blob).is_err());
+    }
+
+    #[test]
+    fn test_legacy_parts_still_open() {
+        let mut key = derive_subkey(&KEY, CONTENT_PURPOSE, b"att-1");
+        let (iv, data) = crate::keys::seal(&key, b"PDF", b"att-1").unwrap();
+        key.zeroize();
+        assert_eq!(decrypt_content(&KEY, "att-1", &[&iv[..], &data].concat()).unwrap(), b"PDF");
+
+        let mut key = derive_subkey(&KEY, META_PURPOSE, b"att-1");
+        let (iv, data) = crate::keys::seal(&key, &serde_json::to_vec(&meta()).unwrap(), b"att-1").unwrap();
+        key.zeroize();
+        let legacy = SealedMeta { version: LEGACY_META_VERSION, iv: B64.encode(iv), data: B64.encode(data) };
+        assert_eq!(decrypt_meta(&KEY, "att-1", &legacy).unwrap(), meta());
+
+        let relabelled = SealedMeta { version: LEGACY_META_VERSION, ..encrypt_meta(&KEY, "att-1", &meta()).unwrap() };
+        assert!(decrypt_meta(&KEY, "att-1", &relabelled).is_err());
+    }
+}
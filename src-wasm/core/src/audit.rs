@@ -0,0 +1,361 @@
+// --- Security Audit ---
+// Findings about the saved credentials, for the security dashboard. A report
+// names entries by ID and title only; no password or seed ever leaves here.
+//
+// Password reuse is judged per site, not per entry. Entries are grouped by the
+// registrable domain of their URL, with the user's equivalent domains folded
+// together, so "mail.google.com" and "accounts.google.com" sharing a password is
+// one account saved twice (reported as "sameSite", not as reuse), while
+// "alice.github.io" and "bob.github.io" are different sites because github.io is
+// a public suffix. An entry without a URL counts as a site of its own.
+//
+// TOTP setups are checked too: secrets under the 128 bits RFC 4226 asks for
+// (common 80-bit ones included, so users know which to re-enroll), algorithms we
+// can't compute at all (MD5), and TOTP kept for a site that offers passkeys,
+// which phishing can't get around the way it relays a typed code.
+//
+// For tracking fixes in a spreadsheet, 'exposure_rows' flattens a report to one
+// row per entry and finding, written out by 'to_csv' and 'to_json'. Cells a
+// spreadsheet would run as a formula (titles starting with "=", "+", "-", "@")
+// are prefixed with a quote.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::domains::{registrable_domain, EquivalentDomains};
+use crate::otp::{OtpAlgorithm, TotpConfig};
+use crate::vault::VaultEntry;
+
+/// RFC 4226 section 4: "The length of the shared secret MUST be at least 128 bits."
+pub const MIN_TOTP_SECRET_BITS: usize = 128;
+
+/// Registrable domains known to accept passkeys for sign-in. Sorted; a test checks.
+const PASSKEY_SITES: [&str; 40] = [
+    "adobe.com", "amazon.com", "apple.com", "bestbuy.com", "binance.com", "bitwarden.com", "canva.com", "cloudflare.com",
+    "coinbase.com", "discord.com", "dropbox.com", "ebay.com", "facebook.com", "github.com", "gitlab.com", "google.com",
+    "hp.com", "instagram.com", "kayak.com", "linkedin.com", "live.com", "mercadolibre.com", "microsoft.com", "namecheap.com",
+    "nintendo.com", "npmjs.com", "nvidia.com", "okta.com", "paypal.com", "playstation.com", "robinhood.com", "shopify.com",
+    "stripe.com", "synology.com", "tiktok.com", "uber.com", "whatsapp.com", "x.com", "yahoo.com", "zoho.com",
+];
+
+/// An entry as a finding names it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct EntryRef {
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReuseKind {
+    /// Different sites share the password: one breach opens all of them.
+    Reused,
+    /// Only entries of one site share it, e.g. a login saved per subdomain.
+    SameSite,
+}
+
+/// The entries of one site within a group.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SiteEntries {
+    /// Registrable domain; empty for an entry without a URL.
+    pub site: String,
+    pub entries: Vec<EntryRef>,
+}
+
+/// Entries sharing one password.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ReuseGroup {
+    pub kind: ReuseKind,
+    pub sites: Vec<SiteEntries>,
+}
+
+/// What is wrong with an entry's TOTP setup, tagged by "issue" in JSON.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "issue", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum TotpIssue {
+    /// Under MIN_TOTP_SECRET_BITS. Worst with SHA1, the default, so the algorithm is named.
+    ShortSecret { bits: usize, algorithm: OtpAlgorithm },
+    /// An algorithm (MD5) no authenticator should use and we can't compute.
+    WeakAlgorithm { algorithm: String },
+    /// The secret can't be read at all. The parser's reason isn't kept: it quotes
+    /// the characters it rejected, which are part of the secret.
+    Unreadable,
+    /// The site takes passkeys; switching removes the code to phish.
+    PasskeyAvailable { site: String },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TotpFinding {
+    pub entry: EntryRef,
+    #[serde(flatten)]
+    pub issue: TotpIssue,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    /// "reused" groups first, largest first.
+    pub reuse: Vec<ReuseGroup>,
+    /// Entries in "reused" groups: what the dashboard counts as reused passwords.
+    pub reused_entries: usize,
+    /// In entry order; an entry can have more than one.
+    pub totp: Vec<TotpFinding>,
+}
+
+pub fn audit(entries: &[VaultEntry], equivalent: &EquivalentDomains) -> AuditReport {
+    let mut by_password: HashMap<&str, Vec<&VaultEntry>> = HashMap::new();
+    for entry in entries.iter().filter(|e| !e.password.is_empty()) {
+        by_password.entry(entry.password.as_str()).or_default().push(entry);
+    }
+
+    let mut report = AuditReport::default();
+    for group in by_password.into_values().filter(|g| g.len() > 1) {
+        let mut sites: BTreeMap<(String, &str), Vec<EntryRef>> = BTreeMap::new();
+        for entry in group {
+            // Entries without a URL are keyed by their ID so they never merge.
+            let key = match site_of(entry, equivalent) {
+                Some(site) => (site, ""),
+                None => (String::new(), entry.id.as_str()),
+            };
+            sites.entry(key).or_default().push(EntryRef { id: entry.id.clone(), title: entry.title.clone() });
+        }
+        let kind = if sites.len() > 1 { ReuseKind::Reused } else { ReuseKind::SameSite };
+        let mut sites: Vec<SiteEntries> = sites.into_iter().map(|((site, _), entries)| SiteEntries { site, entries }).collect();
+        for site in &mut sites {
+            site.entries.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+        }
+        if kind == ReuseKind::Reused {
+            report.reused_entries += sites.iter().map(|s| s.entries.len()).sum::<usize>();
+        }
+        report.reuse.push(ReuseGroup { kind, sites });
+    }
+
+    let size = |g: &ReuseGroup| g.sites.iter().map(|s| s.entries.len()).sum::<usize>();
+    let first_id = |g: &ReuseGroup| g.sites[0].entries[0].id.clone();
+    report.reuse.sort_by(|a, b| {
+        (a.kind != ReuseKind::Reused)
+            .cmp(&(b.kind != ReuseKind::Reused))
+            .then_with(|| size(b).cmp(&size(a)))
+            .then_with(|| first_id(a).cmp(&first_id(b)))
+    });
+
+    for entry in entries {
+        let found = |issue| TotpFinding { entry: EntryRef { id: entry.id.clone(), title: entry.title.clone() }, issue };
+        report.totp.extend(totp_issues(entry).into_iter().map(found));
+    }
+    report
+}
+
+fn totp_issues(entry: &VaultEntry) -> Vec<TotpIssue> {
+    let Some(parsed) = TotpConfig::from_entry(entry) else {
+        return Vec::new();
+    };
+    let mut issues = Vec::new();
+    match parsed {
+        Ok(config) => {
+            // Canonical Base32 carries 5 bits per character.
+            let bits = config.secret.len() * 5 / 8 * 8;
+            if bits < MIN_TOTP_SECRET_BITS {
+                issues.push(TotpIssue::ShortSecret { bits, algorithm: config.algorithm });
+            }
+        }
+        Err(reason) if reason.to_ascii_uppercase().contains("MD5") => {
+            issues.push(TotpIssue::WeakAlgorithm { algorithm: "MD5".to_string() });
+        }
+        Err(_) => issues.push(TotpIssue::Unreadable),
+    }
+    if let Some(site) = registrable_domain(&entry.url).filter(|site| PASSKEY_SITES.binary_search(&site.as_str()).is_ok()) {
+        issues.push(TotpIssue::PasskeyAvailable { site });
+    }
+    issues
+}
+
+/// One line of an exported report.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExposureRow {
+    pub entry_id: String,
+    pub title: String,
+    /// "reused", "sameSite" or a TOTP issue ("shortSecret", "passkeyAvailable", ...).
+    pub finding: String,
+    /// Human-readable specifics, e.g. the other sites sharing the password.
+    pub detail: String,
+}
+
+const CSV_HEADER: &str = "Entry ID,Title,Finding,Detail";
+
+/// Every finding as rows: reuse groups in report order, then TOTP findings.
+pub fn exposure_rows(report: &AuditReport) -> Vec<ExposureRow> {
+    let mut rows = Vec::new();
+    for group in &report.reuse {
+        let finding = match group.kind {
+            ReuseKind::Reused => "reused",
+            ReuseKind::SameSite => "sameSite",
+        };
+        let count = group.sites.iter().map(|s| s.entries.len()).sum::<usize>();
+        for (i, site) in group.sites.iter().enumerate() {
+            let others: Vec<&str> =
+                group.sites.iter().enumerate().filter(|(j, s)| *j != i && !s.site.is_empty()).map(|(_, s)| s.site.as_str()).collect();
+            let detail = match (group.kind, others.is_empty()) {
+                (ReuseKind::SameSite, _) => format!("Same password on {} entries of this site", count),
+                (ReuseKind::Reused, true) => format!("Same password on {} entries", count),
+                (ReuseKind::Reused, false) => format!("Same password on {} entries, also used on {}", count, others.join(", ")),
+            };
+            rows.extend(site.entries.iter().map(|entry| row(entry, finding, detail.clone())));
+        }
+    }
+    for found in &report.totp {
+        let (finding, detail) = match &found.issue {
+            TotpIssue::ShortSecret { bits, algorithm } => {
+                ("shortSecret", format!("{}-bit TOTP secret with {:?}; at least {} bits is standard", bits, algorithm, MIN_TOTP_SECRET_BITS))
+            }
+            TotpIssue::WeakAlgorithm { algorithm } => ("weakAlgorithm", format!("TOTP uses {}", algorithm)),
+            TotpIssue::Unreadable => ("unreadable", "TOTP secret can't be read".to_string()),
+            TotpIssue::PasskeyAvailable { site } => ("passkeyAvailable", format!("{} supports passkeys", site)),
+        };
+        rows.push(row(&found.entry, finding, detail));
+    }
+    rows
+}
+
+fn row(entry: &EntryRef, finding: &str, detail: String) -> ExposureRow {
+    ExposureRow { entry_id: entry.id.clone(), title: entry.title.clone(), finding: finding.to_string(), detail }
+}
+
+/// RFC 4180 CSV with a header line, CRLF line ends.
+pub fn to_csv(report: &AuditReport) -> String {
+    let mut csv = format!("{}\r\n", CSV_HEADER);
+    for row in exposure_rows(report) {
+        let cells = [&row.entry_id, &row.title, &row.finding, &row.detail];
+        csv.push_str(&cells.iter().map(|cell| csv_cell(cell)).collect::<Vec<_>>().join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// The rows as a JSON array.
+pub fn to_json(report: &AuditReport) -> Result<String, String> {
+    serde_json::to_string_pretty(&exposure_rows(report)).map_err(|e| format!("Audit serialize error: {}", e))
+}
+
+fn csv_cell(text: &str) -> String {
+    let text = if text.starts_with(['=', '+', '-', '@', '\t', '\r']) { format!("'{}", text) } else { text.to_string() };
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// The entry's site: its registrable domain, or the first domain of the
+/// equivalent-domain group it belongs to.
+fn site_of(entry: &VaultEntry, equivalent: &EquivalentDomains) -> Option<String> {
+    let domain = registrable_domain(&entry.url)?;
+    Some(equivalent.group_of(&domain).and_then(|group| group.first().cloned()).unwrap_or(domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, url: &str, password: &str) -> VaultEntry {
+        VaultEntry { id: id.to_string(), title: id.to_string(), url: url.to_string(), password: password.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_subdomains_of_one_site_are_not_reuse() {
+        let entries = [
+            entry("gmail", "https://mail.google.com", "shared-1"),
+            entry("google", "https://accounts.google.com/signin", "shared-1"),
+            entry("alice", "https://alice.github.io", "shared-2"),
+            entry("bob", "https://bob.github.io", "shared-2"),
+            entry("bank", "https://bank.example", "shared-2"),
+            entry("unique", "https://unique.example", "only-once"),
+            entry("note", "", ""),
+        ];
+        let report = audit(&entries, &EquivalentDomains::default());
+
+        assert_eq!(report.reuse.len(), 2);
+        assert_eq!(report.reuse[0].kind, ReuseKind::Reused);
+        assert_eq!(report.reuse[0].sites.len(), 3);
+        assert_eq!(report.reuse[1].kind, ReuseKind::SameSite);
+        assert_eq!(report.reuse[1].sites[0].site, "google.com");
+        assert_eq!(report.reused_entries, 3);
+        assert!(!serde_json::to_string(&report).unwrap().contains("shared"));
+    }
+
+    #[test]
+    fn test_totp_findings() {
+        let totp = |id: &str, url: &str, secret: &str| VaultEntry { totp_secret: Some(secret.to_string()), ..entry(id, url, "") };
+        let long = crate::otp::encode_base32(&[7u8; 20]);
+        let entries = [
+            totp("short", "https://example.org", "JBSWY3DPEHPK3PXP"),
+            totp("fine", "https://example.org", &long),
+            totp("md5", "", &format!("otpauth://totp/x?secret={}&algorithm=MD5", long)),
+            totp("broken", "", "not base32!"),
+            totp("github", "https://github.com/login", &long),
+            entry("none", "https://github.com", "pw"),
+        ];
+        let report = audit(&entries, &EquivalentDomains::default());
+        let issues: Vec<(&str, &TotpIssue)> = report.totp.iter().map(|f| (f.entry.id.as_str(), &f.issue)).collect();
+        assert_eq!(issues.len(), 4);
+        assert_eq!(issues[0], ("short", &TotpIssue::ShortSecret { bits: 80, algorithm: OtpAlgorithm::SHA1 }));
+        assert_eq!(issues[1], ("md5", &TotpIssue::WeakAlgorithm { algorithm: "MD5".to_string() }));
+        assert_eq!(issues[2], ("broken", &TotpIssue::Unreadable));
+        assert_eq!(issues[3], ("github", &TotpIssue::PasskeyAvailable { site: "github.com".to_string() }));
+
+        let json = serde_json::to_string(&report.totp[0]).unwrap();
+        assert_eq!(json, r#"{"entry":{"id":"short","title":"short"},"issue":"shortSecret","bits":80,"algorithm":"SHA1"}"#);
+    }
+
+    #[test]
+    fn test_exposure_export() {
+        let mut entries = vec![
+            entry("a", "https://a.example", "pw"),
+            entry("b", "https://b.example", "pw"),
+            VaultEntry { totp_secret: Some("JBSWY3DPEHPK3PXP".to_string()), ..entry("c", "", "") },
+        ];
+        entries[0].title = "=HYPERLINK(\"x\")".to_string();
+        let report = audit(&entries, &EquivalentDomains::default());
+
+        let rows = exposure_rows(&report);
+        assert_eq!(rows.iter().map(|r| r.finding.as_str()).collect::<Vec<_>>(), ["reused", "reused", "shortSecret"]);
+        assert_eq!(rows[1].detail, "Same password on 2 entries, also used on a.example");
+
+        let csv = to_csv(&report);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[1], r#"a,"'=HYPERLINK(""x"")",reused,"Same password on 2 entries, also used on b.example""#);
+        assert_eq!(lines.len(), 5);
+        assert!(!csv.contains("pw") && !csv.contains("JBSWY3DP"));
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&report).unwrap()).unwrap();
+        assert_eq!(json[2]["entryId"], "c");
+    }
+
+    #[test]
+    fn test_passkey_sites_are_sorted() {
+        assert!(PASSKEY_SITES.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(PASSKEY_SITES.iter().all(|site| registrable_domain(site).as_deref() == Some(*site)));
+    }
+
+    #[test]
+    fn test_equivalent_domains_and_missing_urls() {
+        let entries = [
+            entry("us", "https://amazon.com", "pw"),
+            entry("de", "https://www.amazon.de", "pw"),
+            entry("a", "", "pin"),
+            entry("b", "", "pin"),
+        ];
+        let equivalent = EquivalentDomains::from_groups(vec![vec!["amazon.com".to_string(), "amazon.de".to_string()]]).unwrap();
+        let report = audit(&entries, &equivalent);
+        let kinds: Vec<ReuseKind> = report.reuse.iter().map(|g| g.kind).collect();
+        assert_eq!(kinds, [ReuseKind::Reused, ReuseKind::SameSite]);
+        assert_eq!(report.reuse[0].sites.iter().map(|s| s.site.as_str()).collect::<Vec<_>>(), ["", ""]);
+        assert_eq!(report.reuse[1].sites[0].entries.len(), 2);
+
+        let report = audit(&entries, &EquivalentDomains::default());
+        assert_eq!(report.reused_entries, 4);
+    }
+}
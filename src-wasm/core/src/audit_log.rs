@@ -0,0 +1,62 @@
+// --- Security Audit Log ---
+// A record of changes to how the vault is protected (KDF upgrades so far), for
+// the settings screen. Events hold settings and labels, never key material or
+// credentials, so the log is stored in the clear next to the vault and passed
+// in by the caller like the sync log. Persist it after every call that adds to it.
+
+use serde::{Deserialize, Serialize};
+
+/// Oldest events are dropped beyond this, so the log can't grow without bound.
+pub const MAX_EVENTS: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// The key envelope was replaced with stronger KDF settings or password policy.
+    KdfUpgrade,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEvent {
+    pub at: u64,
+    pub kind: AuditKind,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct AuditLog {
+    #[serde(default)]
+    pub events: Vec<AuditEvent>,
+}
+
+impl AuditLog {
+    pub fn new() -> AuditLog {
+        AuditLog::default()
+    }
+
+    pub fn record(&mut self, at: u64, kind: AuditKind, detail: String) {
+        self.events.push(AuditEvent { at, kind, detail });
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_the_newest_events() {
+        let mut log = AuditLog::new();
+        for at in 0..(MAX_EVENTS as u64 + 3) {
+            log.record(at, AuditKind::KdfUpgrade, String::new());
+        }
+        assert_eq!(log.events.len(), MAX_EVENTS);
+        assert_eq!(log.events[0].at, 3);
+        assert_eq!(
+            serde_json::to_string(&log.events[0]).unwrap(),
+            r#"{"at":3,"kind":"kdf_upgrade","detail":""}"#
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// --- Autofill Payloads ---
+// The extension's content script reports the fields it found on a page; this module
+// decides which credential value goes into which field. Keeping the heuristics here
+// means they are unit tested once instead of re-guessed in every content script.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::domains::{registrable_domain_of_host, EquivalentDomains};
+use crate::vault::VaultEntry;
+
+/// One input element as seen by the content script.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FormField {
+    /// Opaque handle the extension uses to find the element again.
+    pub field_id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub html_id: String,
+    #[serde(default, rename = "type")]
+    pub field_type: String,
+    #[serde(default)]
+    pub autocomplete: String,
+    #[serde(default)]
+    pub placeholder: String,
+    #[serde(default)]
+    pub label: String,
+}
+
+/// All fields of one form, in document order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FormDescriptor {
+    pub fields: Vec<FormField>,
+}
+
+/// Which kind of value ends up in a field.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillKind {
+    Username,
+    Password,
+    Totp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldFill {
+    pub field_id: String,
+    pub kind: FillKind,
+    pub value: String,
+}
+
+/// What the extension writes into the page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FillPayload {
+    pub entry_id: String,
+    pub fills: Vec<FieldFill>,
+}
+
+/// Builds the fill instructions for `entry` on `page_url`.
+/// Refuses outright when the page isn't the site the entry was saved for.
+pub fn build_fill_payload(
+    entry: &VaultEntry,
+    page_url: &str,
+    form: &FormDescriptor,
+    totp_code: Option<&str>,
+    equivalents: &EquivalentDomains,
+) -> Result<FillPayload, String> {
+    if !url_matches(&entry.url, page_url, equivalents)? {
+        return Err("Page does not match the entry's URL".to_string());
+    }
+
+    let mut fills = Vec::new();
+    let password_idx = pick_password_field(&form.fields);
+
+    if let Some(idx) = password_idx {
+        fills.push(FieldFill {
+            field_id: form.fields[idx].field_id.clone(),
+            kind: FillKind::Password,
+            value: entry.password.clone(),
+        });
+    }
+
+    if let Some(idx) = pick_username_field(&form.fields, password_idx, &entry.username) {
+        fills.push(FieldFill {
+            field_id: form.fields[idx].field_id.clone(),
+            kind: FillKind::Username,
+            value: entry.username.clone(),
+        });
+    }
+
+    if let Some(code) = totp_code {
+        if let Some(field) = form.fields.iter().find(|f| is_totp_field(f)) {
+            fills.push(FieldFill {
+                field_id: field.field_id.clone(),
+                kind: FillKind::Totp,
+                value: code.to_string(),
+            });
+        }
+    }
+
+    Ok(FillPayload { entry_id: entry.id.clone(), fills })
+}
+
+/// The page must share the saved URL's registrable domain (or one the user declared
+/// equivalent), and never be a downgrade from https to http.
+pub fn url_matches(entry_url: &str, page_url: &str, equivalents: &EquivalentDomains) -> Result<bool, String> {
+    let page = Url::parse(page_url).map_err(|e| format!("Page URL parse error: {}", e))?;
+    let saved = match parse_loose(entry_url) {
+        Some(url) => url,
+        None => return Ok(false),
+    };
+    if saved.scheme() == "https" && page.scheme() != "https" {
+        return Ok(false);
+    }
+
+    let page_domain = page.host_str().and_then(registrable_domain_of_host);
+    let saved_domain = saved.host_str().and_then(registrable_domain_of_host);
+    Ok(match (page_domain, saved_domain) {
+        (Some(page), Some(saved)) => equivalents.are_equivalent(&page, &saved),
+        _ => false,
+    })
+}
+
+/// Saved URLs are often typed without a scheme ("github.com"), so assume https.
+pub(crate) fn parse_loose(raw: &str) -> Option<Url> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    Url::parse(raw)
+        .ok()
+        .filter(|u| u.host_str().is_some())
+        .or_else(|| Url::parse(&format!("https://{}", raw)).ok())
+}
+
+fn pick_password_field(fields: &[FormField]) -> Option<usize> {
+    let is_password = |f: &FormField| {
+        f.field_type.eq_ignore_ascii_case("password") || has_token(&f.autocomplete, "current-password")
+    };
+
+    // An explicit "current-password" wins; otherwise the first password box
+    // that isn't asking for a new password (change-password forms have both).
+    fields
+        .iter()
+        .position(|f| has_token(&f.autocomplete, "current-password"))
+        .or_else(|| {
+            fields
+                .iter()
+                .position(|f| is_password(f) && !has_token(&f.autocomplete, "new-password"))
+        })
+}
+
+fn pick_username_field(fields: &[FormField], password_idx: Option<usize>, username: &str) -> Option<usize> {
+    if username.is_empty() {
+        return None;
+    }
+    let username_is_email = username.contains('@');
+
+    // The username field can't hold the value if it's an email box and we only have a handle.
+    let accepts = |f: &FormField| !is_email_field(f) || username_is_email;
+
+    if let Some(idx) = fields
+        .iter()
+        .position(|f| has_token(&f.autocomplete, "username") && accepts(f))
+    {
+        return Some(idx);
+    }
+
+    // Otherwise score the visible text/email inputs and prefer the one right before the password box.
+    let limit = password_idx.unwrap_or(fields.len());
+    let mut best: Option<(usize, i32)> = None;
+    for (idx, field) in fields.iter().enumerate().take(limit) {
+        if !is_text_like(field) || is_totp_field(field) || !accepts(field) {
+            continue;
+        }
+        let mut score = 1;
+        if looks_like_username(field) {
+            score += 4;
+        }
+        if is_email_field(field) && username_is_email {
+            score += 3;
+        }
+        if password_idx.is_some_and(|p| idx + 1 == p) {
+            score += 2;
+        }
+        if best.is_none_or(|(_, s)| score >= s) {
+            best = Some((idx, score));
+        }
+    }
+    best.map(|(idx, _)| idx)
+}
+
+fn is_text_like(field: &FormField) -> bool {
+    matches!(
+        field.field_type.to_ascii_lowercase().as_str(),
+        "" | "text" | "email" | "tel"
+    )
+}
+
+fn is_email_field(field: &FormField) -> bool {
+    field.field_type.eq_ignore_ascii_case("email") || has_token(&field.autocomplete, "email")
+}
+
+fn looks_like_username(field: &FormField) -> bool {
+    const HINTS: [&str; 6] = ["user", "login", "email", "e-mail", "account", "identifier"];
+    let haystack = format!("{} {} {} {}", field.name, field.html_id, field.placeholder, field.label).to_ascii_lowercase();
+    HINTS.iter().any(|h| haystack.contains(h))
+}
+
+fn is_totp_field(field: &FormField) -> bool {
+    if has_token(&field.autocomplete, "one-time-code") {
+        return true;
+    }
+    const HINTS: [&str; 6] = ["otp", "totp", "2fa", "mfa", "one-time", "verification"];
+    let haystack = format!("{} {} {}", field.name, field.html_id, field.label).to_ascii_lowercase();
+    HINTS.iter().any(|h| haystack.contains(h))
+}
+
+/// `autocomplete` is a space-separated token list ("section-login username").
+pub(crate) fn has_token(autocomplete: &str, token: &str) -> bool {
+    autocomplete.split_whitespace().any(|t| t.eq_ignore_ascii_case(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(id: &str, field_type: &str, name: &str, autocomplete: &str) -> FormField {
+        FormField {
+            field_id: id.to_string(),
+            name: name.to_string(),
+            field_type: field_type.to_string(),
+            autocomplete: autocomplete.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(username: &str) -> VaultEntry {
+        VaultEntry {
+            id: "e1".to_string(),
+            username: username.to_string(),
+            password: "hunter2".to_string(),
+            url: "https://github.com/login".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn kind_of(payload: &FillPayload, field_id: &str) -> Option<FillKind> {
+        payload.fills.iter().find(|f| f.field_id == field_id).map(|f| f.kind)
+    }
+
+    #[test]
+    fn test_basic_login_form() {
+        let form = FormDescriptor {
+            fields: vec![field("f0", "text", "q", ""), field("f1", "text", "login", ""), field("f2", "password", "pw", "")],
+        };
+        let payload = build_fill_payload(&entry("octocat"), "https://github.com/session", &form, None, &EquivalentDomains::default()).unwrap();
+        assert_eq!(kind_of(&payload, "f1"), Some(FillKind::Username));
+        assert_eq!(kind_of(&payload, "f2"), Some(FillKind::Password));
+        assert_eq!(kind_of(&payload, "f0"), None);
+    }
+
+    #[test]
+    fn test_email_field_needs_email_username() {
+        let form = FormDescriptor {
+            fields: vec![field("f1", "email", "email", ""), field("f2", "password", "pw", "")],
+        };
+        let handle = build_fill_payload(&entry("octocat"), "https://github.com", &form, None, &EquivalentDomains::default()).unwrap();
+        assert_eq!(kind_of(&handle, "f1"), None);
+
+        let email = build_fill_payload(&entry("me@example.com"), "https://github.com", &form, None, &EquivalentDomains::default()).unwrap();
+        assert_eq!(kind_of(&email, "f1"), Some(FillKind::Username));
+    }
+
+    #[test]
+    fn test_change_password_form_and_totp() {
+        let form = FormDescriptor {
+            fields: vec![
+                field("new", "password", "new", "new-password"),
+                field("cur", "password", "old", "current-password"),
+                field("otp", "text", "code", "one-time-code"),
+            ],
+        };
+        let payload = build_fill_payload(&entry("octocat"), "https://github.com", &form, Some("123456"), &EquivalentDomains::default()).unwrap();
+        assert_eq!(kind_of(&payload, "cur"), Some(FillKind::Password));
+        assert_eq!(kind_of(&payload, "new"), None);
+        assert_eq!(kind_of(&payload, "otp"), Some(FillKind::Totp));
+    }
+
+    #[test]
+    fn test_url_matching() {
+        let none = EquivalentDomains::default();
+        assert!(url_matches("github.com", "https://gist.github.com/x", &none).unwrap());
+        assert!(url_matches("https://www.github.com", "https://github.com", &none).unwrap());
+        assert!(url_matches("https://login.github.com", "https://github.com", &none).unwrap());
+        assert!(!url_matches("https://github.com", "https://github.com.evil.io", &none).unwrap());
+        assert!(!url_matches("https://github.com", "http://github.com", &none).unwrap());
+        assert!(!url_matches("https://alice.github.io", "https://mallory.github.io", &none).unwrap());
+        assert!(!url_matches("", "https://github.com", &none).unwrap());
+
+        let form = FormDescriptor { fields: vec![field("f2", "password", "pw", "")] };
+        assert!(build_fill_payload(&entry("a"), "https://evil.io", &form, None, &none).is_err());
+    }
+
+    #[test]
+    fn test_url_matching_with_equivalent_domains() {
+        let eq = EquivalentDomains::from_groups(vec![vec!["amazon.com".to_string(), "amazon.de".to_string()]]).unwrap();
+        assert!(url_matches("https://amazon.com", "https://www.amazon.de/login", &eq).unwrap());
+        assert!(!url_matches("https://amazon.com", "https://amazon.fr", &eq).unwrap());
+    }
+}
@@ -0,0 +1,214 @@
+// --- Autotype Templates ---
+// Turns a KeePass-style sequence like "{USERNAME}{TAB}{PASSWORD}{ENTER}" into an
+// ordered list of actions. Desktop and extension autotype both replay this list,
+// so there is exactly one parser deciding what a template means.
+//
+// Syntax:
+//   {FIELD}        USERNAME, PASSWORD, TITLE, URL, NOTES, TOTP
+//   {KEY}          TAB, ENTER, SPACE, BACKSPACE, ESC, UP, DOWN, LEFT, RIGHT, HOME, END, DELETE
+//   {KEY n}        press KEY n times, e.g. {TAB 2}
+//   {DELAY n}      wait n milliseconds
+//   {{} and {}}    literal braces
+// Everything else is typed as-is. Names are case-insensitive.
+
+use serde::{Deserialize, Serialize};
+
+use crate::vault::VaultEntry;
+
+/// The sequence used when an entry doesn't define its own.
+pub const DEFAULT_SEQUENCE: &str = "{USERNAME}{TAB}{PASSWORD}{ENTER}";
+
+/// Longest delay or repeat count we accept, so a typo can't hang the typist.
+const MAX_DELAY_MS: u32 = 10_000;
+const MAX_REPEAT: u32 = 100;
+
+/// One step for the typist to perform.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutotypeAction {
+    Text { value: String },
+    Key { key: String },
+    Delay { ms: u32 },
+}
+
+/// Expands `template` against `entry`. `totp_code` fills {TOTP} when the entry has one.
+pub fn expand(template: &str, entry: &VaultEntry, totp_code: Option<&str>) -> Result<Vec<AutotypeAction>, String> {
+    let mut actions: Vec<AutotypeAction> = Vec::new();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        push_text(&mut actions, &rest[..pos]);
+        let tail = &rest[pos..];
+
+        // Escaped braces come first so "{{}" isn't read as a placeholder named "{".
+        if let Some(after) = tail.strip_prefix("{{}") {
+            push_text(&mut actions, "{");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix("{}}") {
+            push_text(&mut actions, "}");
+            rest = after;
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err(format!("Unexpected '}}' at position {}", template.len() - tail.len()));
+        }
+
+        let close = tail
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' at position {}", template.len() - tail.len()))?;
+        expand_placeholder(&mut actions, &tail[1..close], entry, totp_code)?;
+        rest = &tail[close + 1..];
+    }
+    push_text(&mut actions, rest);
+
+    Ok(actions)
+}
+
+fn expand_placeholder(
+    actions: &mut Vec<AutotypeAction>,
+    inner: &str,
+    entry: &VaultEntry,
+    totp_code: Option<&str>,
+) -> Result<(), String> {
+    let mut parts = inner.split_whitespace();
+    let name = parts.next().unwrap_or("").to_ascii_uppercase();
+    let arg = match parts.next() {
+        Some(raw) => Some(raw.parse::<u32>().map_err(|_| format!("Invalid number in {{{}}}", inner))?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err(format!("Too many arguments in {{{}}}", inner));
+    }
+
+    let field = match name.as_str() {
+        "USERNAME" => Some(entry.username.as_str()),
+        "PASSWORD" => Some(entry.password.as_str()),
+        "TITLE" => Some(entry.title.as_str()),
+        "URL" => Some(entry.url.as_str()),
+        "NOTES" => Some(entry.notes.as_str()),
+        "TOTP" => Some(totp_code.ok_or("Entry has no TOTP secret")?),
+        _ => None,
+    };
+    if let Some(value) = field {
+        if arg.is_some() {
+            return Err(format!("{{{}}} does not take an argument", name));
+        }
+        push_text(actions, value);
+        return Ok(());
+    }
+
+    if name == "DELAY" {
+        let ms = arg.ok_or("{DELAY} needs a duration in milliseconds")?;
+        if ms > MAX_DELAY_MS {
+            return Err(format!("Delay too long: {}ms (max {}ms)", ms, MAX_DELAY_MS));
+        }
+        actions.push(AutotypeAction::Delay { ms });
+        return Ok(());
+    }
+
+    let key = match name.as_str() {
+        "TAB" => "Tab",
+        "ENTER" => "Enter",
+        "SPACE" => "Space",
+        "BACKSPACE" | "BS" => "Backspace",
+        "ESC" => "Escape",
+        "UP" => "ArrowUp",
+        "DOWN" => "ArrowDown",
+        "LEFT" => "ArrowLeft",
+        "RIGHT" => "ArrowRight",
+        "HOME" => "Home",
+        "END" => "End",
+        "DELETE" | "DEL" => "Delete",
+        _ => return Err(format!("Unknown placeholder: {{{}}}", inner)),
+    };
+
+    let count = arg.unwrap_or(1);
+    if count > MAX_REPEAT {
+        return Err(format!("Repeat count too high: {} (max {})", count, MAX_REPEAT));
+    }
+    for _ in 0..count {
+        actions.push(AutotypeAction::Key { key: key.to_string() });
+    }
+    Ok(())
+}
+
+/// Appends text, merging with the previous action so the typist gets whole strings.
+fn push_text(actions: &mut Vec<AutotypeAction>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(AutotypeAction::Text { value }) = actions.last_mut() {
+        value.push_str(text);
+    } else {
+        actions.push(AutotypeAction::Text { value: text.to_string() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn entry() -> VaultEntry {
+        VaultEntry {
+            username: "alice".to_string(),
+            password: "p{a}ss".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn text(v: &str) -> AutotypeAction {
+        AutotypeAction::Text { value: v.to_string() }
+    }
+
+    fn key(k: &str) -> AutotypeAction {
+        AutotypeAction::Key { key: k.to_string() }
+    }
+
+    #[test]
+    fn test_default_sequence() {
+        let actions = expand(DEFAULT_SEQUENCE, &entry(), None).unwrap();
+        assert_eq!(actions, vec![text("alice"), key("Tab"), text("p{a}ss"), key("Enter")]);
+    }
+
+    #[test]
+    fn test_escapes_repeats_and_delay() {
+        let actions = expand("{{}x{}}{tab 2}{DELAY 250}{username}!", &entry(), None).unwrap();
+        assert_eq!(
+            actions,
+            vec![text("{x}"), key("Tab"), key("Tab"), AutotypeAction::Delay { ms: 250 }, text("alice!")]
+        );
+    }
+
+    #[test]
+    fn test_totp_placeholder() {
+        let actions = expand("{PASSWORD}{TOTP}", &entry(), Some("123456")).unwrap();
+        assert_eq!(actions, vec![text("p{a}ss123456")]);
+        assert!(expand("{TOTP}", &entry(), None).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_templates() {
+        assert!(expand("{USERNAME", &entry(), None).is_err());
+        assert!(expand("oops}", &entry(), None).is_err());
+        assert!(expand("{NOPE}", &entry(), None).is_err());
+        assert!(expand("{TAB x}", &entry(), None).is_err());
+        assert!(expand("{DELAY 999999}", &entry(), None).is_err());
+        assert!(expand("{PASSWORD 2}", &entry(), None).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_expand_never_panics(template in "[{}A-Za-z0-9 ]{0,60}") {
+            let _ = expand(&template, &VaultEntry::default(), Some("123456"));
+        }
+
+        #[test]
+        fn prop_plain_text_is_typed_verbatim(text in "[^{}]{1,60}") {
+            let actions = expand(&text, &VaultEntry::default(), None).unwrap();
+            prop_assert_eq!(actions, vec![AutotypeAction::Text { value: text }]);
+        }
+    }
+}
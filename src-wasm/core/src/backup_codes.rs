@@ -0,0 +1,200 @@
+// --- 2FA Backup Codes ---
+// The one-time recovery codes a site hands out when two-factor login is turned
+// on, kept in a 'backup_codes' custom field so they are sealed, synced and
+// exported like any other secret. The field value is one code per line, with a
+// used code marked by a leading '~':
+//
+//   4f7k-2m9q
+//   ~8c1d-7h3x
+//   9wq2-5bna
+//
+// so the value reads sensibly in an export or in a client that doesn't know the
+// field type. Codes are matched the way sites accept them: case, spaces and
+// dashes are ignored. The app warns once LOW_REMAINING or fewer are left, which
+// is the time to generate a new set on the site.
+
+use serde::Serialize;
+
+use crate::vault::{CustomField, FieldKind, VaultEntry};
+
+pub const FIELD_NAME: &str = "Backup codes";
+/// At or below this many unused codes, the entry is reported as running low.
+pub const LOW_REMAINING: usize = 3;
+const USED_MARK: char = '~';
+/// Sites issue 8 to 16; anything past this is a paste gone wrong.
+const MAX_CODES: usize = 100;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupCode {
+    pub code: String,
+    pub used: bool,
+}
+
+/// How many codes an entry has left.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    pub entry_id: String,
+    pub title: String,
+    pub total: usize,
+    pub remaining: usize,
+    /// True at LOW_REMAINING or fewer: time to generate new codes.
+    pub low: bool,
+}
+
+/// The codes in a field value, in order.
+pub fn parse(value: &str) -> Vec<BackupCode> {
+    value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix(USED_MARK) {
+            Some(code) => BackupCode { code: code.trim().to_string(), used: true },
+            None => BackupCode { code: line.to_string(), used: false },
+        })
+        .collect()
+}
+
+/// The field value for `codes`.
+pub fn serialize(codes: &[BackupCode]) -> String {
+    let lines: Vec<String> =
+        codes.iter().map(|c| if c.used { format!("{}{}", USED_MARK, c.code) } else { c.code.clone() }).collect();
+    lines.join("\n")
+}
+
+/// Codes as the user pasted them from the site: one per line or separated by
+/// commas or semicolons, with or without "1." / "1)" numbering. Duplicates are dropped.
+pub fn from_text(raw: &str) -> Result<Vec<BackupCode>, String> {
+    let mut codes: Vec<BackupCode> = Vec::new();
+    for piece in raw.split(['\n', ',', ';']) {
+        let piece = piece.trim();
+        let code = match piece.split_once(['.', ')']) {
+            Some((number, rest)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => rest.trim(),
+            _ => piece,
+        };
+        if code.is_empty() {
+            continue;
+        }
+        if code.starts_with(USED_MARK) {
+            return Err(format!("Backup codes error: a code can't start with '{}'", USED_MARK));
+        }
+        if !codes.iter().any(|c| same_code(&c.code, code)) {
+            codes.push(BackupCode { code: code.to_string(), used: false });
+        }
+    }
+    if codes.is_empty() {
+        return Err("Backup codes error: no codes found".to_string());
+    }
+    if codes.len() > MAX_CODES {
+        return Err(format!("Backup codes error: {} codes, over the limit of {}", codes.len(), MAX_CODES));
+    }
+    Ok(codes)
+}
+
+/// Codes compare as sites check them: ignoring case, spaces and dashes.
+fn same_code(a: &str, b: &str) -> bool {
+    let significant = |s: &str| s.chars().filter(|c| !c.is_whitespace() && *c != '-').flat_map(char::to_lowercase).collect::<String>();
+    significant(a) == significant(b)
+}
+
+/// Marks `code` used. An unknown code, or one already used, is an error so the
+/// user notices a typo before relying on the count.
+pub fn mark_used(codes: &mut [BackupCode], code: &str) -> Result<(), String> {
+    let found = codes.iter_mut().find(|c| same_code(&c.code, code)).ok_or("Backup codes error: no such code")?;
+    if found.used {
+        return Err("Backup codes error: that code was already used".to_string());
+    }
+    found.used = true;
+    Ok(())
+}
+
+/// The entry's backup-codes field, if it has one.
+pub fn field(entry: &VaultEntry) -> Option<&CustomField> {
+    entry.fields.iter().find(|f| f.kind == FieldKind::BackupCodes)
+}
+
+/// The entry's codes, or None without a backup-codes field.
+pub fn entry_codes(entry: &VaultEntry) -> Option<Vec<BackupCode>> {
+    field(entry).map(|f| parse(&f.value))
+}
+
+/// Stores `codes` in the entry's backup-codes field, adding one if needed.
+pub fn set_entry_codes(entry: &mut VaultEntry, codes: &[BackupCode]) {
+    let value = serialize(codes);
+    match entry.fields.iter_mut().find(|f| f.kind == FieldKind::BackupCodes) {
+        Some(field) => field.value = value,
+        None => entry.fields.push(CustomField { name: FIELD_NAME.to_string(), kind: FieldKind::BackupCodes, value }),
+    }
+}
+
+pub fn status(entry: &VaultEntry) -> Option<Status> {
+    let codes = entry_codes(entry)?;
+    let remaining = codes.iter().filter(|c| !c.used).count();
+    Some(Status { entry_id: entry.id.clone(), title: entry.title.clone(), total: codes.len(), remaining, low: remaining <= LOW_REMAINING })
+}
+
+/// Entries with backup codes that are running low, in vault order.
+pub fn running_low(entries: &[VaultEntry]) -> Vec<Status> {
+    entries.iter().filter_map(status).filter(|s| s.low).collect()
+}
+
+/// A plain-text sheet to print and keep with the user's papers, used codes struck:
+///
+///   Example Corp - backup codes
+///   Codes: 10, unused: 9
+///
+///    1. 4f7k-2m9q
+///    2. ~~8c1d-7h3x~~  (used)
+pub fn format_for_printing(title: &str, codes: &[BackupCode]) -> String {
+    let remaining = codes.iter().filter(|c| !c.used).count();
+    let width = codes.len().to_string().len();
+    let mut sheet = format!("{} - backup codes\nCodes: {}, unused: {}\n\n", title.trim(), codes.len(), remaining);
+    for (i, c) in codes.iter().enumerate() {
+        if c.used {
+            sheet.push_str(&format!("{:>width$}. ~~{}~~  (used)\n", i + 1, c.code));
+        } else {
+            sheet.push_str(&format!("{:>width$}. {}\n", i + 1, c.code));
+        }
+    }
+    sheet.push_str("\nEach code works once. Cross it out when you use it.\n");
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pasted_codes_roundtrip_and_mark_used() {
+        let mut codes = from_text("1. 4f7k-2m9q\n2) 8C1D 7H3X\n\n3. 9wq2-5bna, 4f7k2m9q").unwrap();
+        assert_eq!(codes.iter().map(|c| c.code.as_str()).collect::<Vec<_>>(), ["4f7k-2m9q", "8C1D 7H3X", "9wq2-5bna"]);
+        assert!(from_text(" \n,").unwrap_err().contains("no codes"));
+
+        mark_used(&mut codes, "8c1d-7h3x").unwrap();
+        assert!(mark_used(&mut codes, "8C1D7H3X").unwrap_err().contains("already used"));
+        assert!(mark_used(&mut codes, "0000").unwrap_err().contains("no such code"));
+        let value = serialize(&codes);
+        assert_eq!(value, "4f7k-2m9q\n~8C1D 7H3X\n9wq2-5bna");
+        assert_eq!(parse(&value), codes);
+    }
+
+    #[test]
+    fn test_status_and_printing() {
+        let mut entry = VaultEntry { id: "e".to_string(), title: "Example".to_string(), ..Default::default() };
+        assert_eq!(status(&entry), None);
+        let mut codes = from_text("aaaa\nbbbb\ncccc\ndddd").unwrap();
+        set_entry_codes(&mut entry, &codes);
+        assert!(!status(&entry).unwrap().low);
+
+        mark_used(&mut codes, "bbbb").unwrap();
+        set_entry_codes(&mut entry, &codes);
+        assert_eq!(entry.fields.len(), 1);
+        let status = status(&entry).unwrap();
+        assert_eq!((status.total, status.remaining, status.low), (4, 3, true));
+        assert_eq!(running_low(&[entry]).len(), 1);
+
+        let sheet = format_for_printing("Example", &codes);
+        assert!(sheet.starts_with("Example - backup codes\nCodes: 4, unused: 3\n\n1. aaaa\n2. ~~bbbb~~  (used)\n"));
+    }
+}
@@ -0,0 +1,159 @@
+// --- Local Backup Retention ---
+// The app keeps automatic local backups of the sealed vault. Each one gets a
+// metadata record (when it was made and a generation counter that only goes
+// up), and after each new backup 'prune' says which to delete, so the set
+// stays bounded however long the app runs.
+//
+// Retention works like restic's and borg's: keep the newest backup of each of
+// the last 7 days that have one, of the last 4 weeks (Monday to Sunday) and of
+// the last 12 months, all in UTC. A backup can count for more than one rule.
+// The newest backup is always kept.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+const DAY_SECS: u64 = 86_400;
+
+/// Maps a timestamp to the day, week or month it falls in.
+type Period = fn(u64) -> u64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackupMeta {
+    /// "backup-<generation>", the key the backup is stored under.
+    pub id: String,
+    /// Unix seconds.
+    pub created_at: u64,
+    /// One more than the highest generation before it.
+    pub generation: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RetentionPolicy {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy { daily: 7, weekly: 4, monthly: 12 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PrunePlan {
+    /// Backup IDs to keep, newest first.
+    pub keep: Vec<String>,
+    /// Backup IDs to delete, newest first.
+    pub delete: Vec<String>,
+}
+
+/// Metadata for a backup made at `now`, after `existing`.
+pub fn next_backup(existing: &[BackupMeta], now: u64) -> BackupMeta {
+    let generation = existing.iter().map(|b| b.generation).max().unwrap_or(0) + 1;
+    BackupMeta { id: format!("backup-{}", generation), created_at: now, generation }
+}
+
+/// Whether a backup is due: none yet, or the newest is at least `interval_secs` old.
+pub fn is_due(existing: &[BackupMeta], now: u64, interval_secs: u64) -> bool {
+    existing.iter().map(|b| b.created_at).max().is_none_or(|newest| now.saturating_sub(newest) >= interval_secs)
+}
+
+/// Which backups `policy` keeps and which it deletes.
+pub fn prune(backups: &[BackupMeta], policy: RetentionPolicy) -> PrunePlan {
+    let mut newest_first: Vec<&BackupMeta> = backups.iter().collect();
+    newest_first.sort_by_key(|b| std::cmp::Reverse((b.created_at, b.generation)));
+
+    let mut kept: BTreeSet<usize> = newest_first.first().map(|_| 0).into_iter().collect();
+    let rules: [(usize, Period); 3] = [(policy.daily, day), (policy.weekly, week), (policy.monthly, month)];
+    for (count, period) in rules {
+        let mut seen = BTreeSet::new();
+        for (i, backup) in newest_first.iter().enumerate() {
+            if seen.len() == count {
+                break;
+            }
+            if seen.insert(period(backup.created_at)) {
+                kept.insert(i);
+            }
+        }
+    }
+
+    let mut plan = PrunePlan::default();
+    for (i, backup) in newest_first.into_iter().enumerate() {
+        let list = if kept.contains(&i) { &mut plan.keep } else { &mut plan.delete };
+        list.push(backup.id.clone());
+    }
+    plan
+}
+
+fn day(secs: u64) -> u64 {
+    secs / DAY_SECS
+}
+
+/// Weeks start on Monday; 1970-01-01 was a Thursday.
+fn week(secs: u64) -> u64 {
+    (day(secs) + 3) / 7
+}
+
+/// Months since year 0 (year * 12 + month - 1), from Howard Hinnant's civil_from_days.
+pub(crate) fn month(secs: u64) -> u64 {
+    let z = day(secs) + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    year * 12 + month - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily_backups(days: u64) -> Vec<BackupMeta> {
+        let mut backups = Vec::new();
+        for d in 0..days {
+            backups.push(next_backup(&backups, 1_704_067_200 + d * DAY_SECS + 3600));
+        }
+        backups
+    }
+
+    #[test]
+    fn test_month_boundaries() {
+        assert_eq!(month(0), 1970 * 12);
+        // 2024-02-29 23:59:59 and 2024-03-01 00:00:00.
+        assert_eq!(month(1_709_251_199), 2024 * 12 + 1);
+        assert_eq!(month(1_709_251_200), 2024 * 12 + 2);
+        // 1970-01-05 was the first Monday.
+        assert_eq!((week(4 * DAY_SECS - 1), week(4 * DAY_SECS)), (0, 1));
+    }
+
+    #[test]
+    fn test_a_year_of_daily_backups_is_pruned() {
+        // 2024-01-01 (a Monday) to 2024-12-30.
+        let backups = daily_backups(365);
+        assert_eq!(backups[364].id, "backup-365");
+        let plan = prune(&backups, RetentionPolicy::default());
+        assert_eq!(plan.keep.len() + plan.delete.len(), 365);
+
+        // 7 days, the Sundays of the 2 weeks before them (the 2 latest weeks are
+        // already covered), and the last day of the 11 months before December.
+        assert_eq!(plan.keep.len(), 7 + 2 + 11);
+        assert_eq!(plan.keep[6..9], ["backup-359", "backup-357", "backup-350"]);
+        assert!(plan.keep.contains(&"backup-31".to_string()));
+        assert!(!plan.keep.contains(&"backup-30".to_string()));
+    }
+
+    #[test]
+    fn test_scheduling() {
+        let backups = daily_backups(2);
+        let newest = backups[1].created_at;
+        assert!(is_due(&[], 0, DAY_SECS));
+        assert!(!is_due(&backups, newest + DAY_SECS - 1, DAY_SECS));
+        assert!(is_due(&backups, newest + DAY_SECS, DAY_SECS));
+        assert_eq!(prune(&backups[..1], RetentionPolicy { daily: 0, weekly: 0, monthly: 0 }).keep, ["backup-1"]);
+    }
+}
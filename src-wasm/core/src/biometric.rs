@@ -0,0 +1,77 @@
+// --- Biometric Unlock ---
+// A passkey credential ID is turned into a device-bound wrapping key, which seals
+// the master password for storage. These don't need an unlocked vault.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm,
+};
+use argon2::Argon2;
+
+use crate::validate;
+
+/// We use a fixed salt for biometric key derivation so it's consistent across sessions.
+const BIO_SALT: &[u8] = b"WebVault_BioSalt";
+
+/// Derives the 32-byte wrapping key for a WebAuthn credential.
+pub fn derive_bio_key(credential_id: &[u8]) -> Result<Vec<u8>, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(credential_id, BIO_SALT, &mut key)
+        .map_err(|e| format!("Argon2 error: {}", e))?;
+    Ok(key.to_vec())
+}
+
+/// WRAP: Encrypts the master password so it can be stored in browser storage safely.
+pub fn wrap_password(password: &str, bio_key: &[u8], iv: &[u8]) -> Result<Vec<u8>, String> {
+    validate::key(bio_key, "biometric key")?;
+    let nonce = validate::nonce(iv)?;
+    validate::size(password.len(), validate::MAX_PASSWORD_LEN, "password")?;
+    let cipher = Aes256Gcm::new_from_slice(bio_key).map_err(|e| format!("Cipher init error: {}", e))?;
+    cipher
+        .encrypt(nonce, password.as_bytes())
+        .map_err(|e| format!("Wrapping error: {}", e))
+}
+
+/// UNWRAP: Decrypts the master password when you use TouchID/FaceID.
+pub fn unwrap_password(wrapped_data: &[u8], bio_key: &[u8], iv: &[u8]) -> Result<String, String> {
+    validate::key(bio_key, "biometric key")?;
+    let nonce = validate::nonce(iv)?;
+    validate::size(wrapped_data.len(), validate::MAX_PASSWORD_LEN + 16, "wrapped password")?;
+    let cipher = Aes256Gcm::new_from_slice(bio_key).map_err(|e| format!("Cipher init error: {}", e))?;
+    let plaintext = cipher
+        .decrypt(nonce, wrapped_data)
+        .map_err(|e| format!("Unwrapping error: {}", e))?;
+    Ok(validate::utf8(plaintext, "password")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biometric_wrapping() {
+        let credential_id = b"test-credential-id";
+        let password = "super-secret-master-password";
+        let iv = [1u8; 12];
+
+        let bio_key = derive_bio_key(credential_id).unwrap();
+        assert_eq!(bio_key.len(), 32);
+
+        let wrapped = wrap_password(password, &bio_key, &iv).unwrap();
+        assert_ne!(wrapped, password.as_bytes());
+
+        let unwrapped = unwrap_password(&wrapped, &bio_key, &iv).unwrap();
+        assert_eq!(unwrapped, password);
+    }
+
+    #[test]
+    fn test_wrong_length_ivs_are_refused() {
+        let bio_key = [3u8; 32];
+        let wrapped = wrap_password("pw", &bio_key, &[1u8; 12]).unwrap();
+        for iv in [&[][..], &[1u8; 11], &[1u8; 16]] {
+            assert!(wrap_password("pw", &bio_key, iv).unwrap_err().contains("[iv_length]"));
+            assert!(unwrap_password(&wrapped, &bio_key, iv).unwrap_err().contains("[iv_length]"));
+        }
+    }
+}
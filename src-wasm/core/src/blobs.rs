@@ -0,0 +1,306 @@
+// --- Remote Blob Packing ---
+// Lays the sync op-log out as files on storage that can only put, get, list
+// and delete (WebDAV, S3, Dropbox). Nothing is ever rewritten: each device
+// appends numbered ops blobs under its own prefix, and any device may fold
+// everything into a numbered snapshot, after which the blobs it covers can go.
+//
+//   ops/<device>/<number>.blob        sealed ops from one device: 1, 2, 3, ...
+//   snapshots/<number>-<device>.blob  the replayed vault plus what it covers
+//
+// Numbers are zero-padded to 10 digits so a listing sorts in order. A blob is
+// MAGIC || IV (12) || AES-256-GCM(body JSON) with MAGIC || name as AAD, under a
+// subkey of the master key, so a blob that was altered, truncated or moved to
+// another name fails to open. A device's ops blobs must be merged without gaps,
+// so storage that loses one is caught at the next. The seal is committed to the
+// key ('keys::seal_committed'); blobs under LEGACY_MAGIC predate that and open
+// as plain AES-GCM.
+//
+// Compaction is safe with any number of readers, but two devices compacting at
+// once can delete ops the winning snapshot lacks; let one device do it.
+
+use zeroize::Zeroize;
+
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::oplog::{is_valid_device, open_op, OpLog, SealedOp, Snapshot};
+
+pub const MAGIC: &[u8; 4] = b"SPB2";
+const LEGACY_MAGIC: &[u8; 4] = b"SPB1";
+pub(crate) const PURPOSE: &str = "securepass-sync-blob-v1";
+
+/// A file to write to remote storage as-is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncBlob {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// What a blob's name says about it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlobName {
+    Ops { device: String, number: u64 },
+    Snapshot { number: u64, device: String },
+}
+
+impl BlobName {
+    /// Recognizes the two layouts above; anything else on the storage is not ours.
+    pub fn parse(name: &str) -> Option<BlobName> {
+        let number = |digits: &str| (digits.len() == 10).then(|| digits.parse::<u64>().ok()).flatten();
+        if let Some(rest) = name.strip_prefix("ops/").and_then(|r| r.strip_suffix(".blob")) {
+            let (device, digits) = rest.split_once('/')?;
+            return Some(BlobName::Ops { device: device.to_string(), number: number(digits)? })
+                .filter(|b| b.is_valid());
+        }
+        let rest = name.strip_prefix("snapshots/")?.strip_suffix(".blob")?;
+        let (digits, device) = rest.split_once('-')?;
+        Some(BlobName::Snapshot { number: number(digits)?, device: device.to_string() }).filter(|b| b.is_valid())
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            BlobName::Ops { device, number } | BlobName::Snapshot { number, device } => {
+                *number > 0 && is_valid_device(device)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BlobName {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BlobName::Ops { device, number } => write!(f, "ops/{}/{:010}.blob", device, number),
+            BlobName::Snapshot { number, device } => write!(f, "snapshots/{:010}-{}.blob", number, device),
+        }
+    }
+}
+
+/// Packs this device's freshly recorded ops into its next ops blob.
+/// Merge every pending blob first, so the number follows this device's last one.
+pub fn pack_ops(master_key: &[u8; 32], log: &mut OpLog, ops: &[SealedOp]) -> Result<SyncBlob, String> {
+    if ops.is_empty() {
+        return Err("Sync error: no ops to pack".to_string());
+    }
+    let device = log.device().to_string();
+    if ops.iter().any(|op| op.id.device != device) {
+        return Err("Sync error: only this device's own ops can be packed".to_string());
+    }
+    let number = log.last_blob(&device) + 1;
+    let body = serde_json::to_vec(ops).map_err(|e| format!("Sync serialize error: {}", e))?;
+    let name = BlobName::Ops { device: device.clone(), number }.to_string();
+    let data = seal_blob(master_key, &name, body)?;
+    log.set_last_blob(&device, number);
+    Ok(SyncBlob { name, data })
+}
+
+/// Folds the whole log into the next snapshot and moves the log onto it.
+/// Upload it, then delete what `compaction_plan` lists.
+pub fn pack_snapshot(master_key: &[u8; 32], log: &mut OpLog) -> Result<SyncBlob, String> {
+    let snapshot = log.snapshot();
+    let name = BlobName::Snapshot { number: snapshot.number, device: snapshot.device.clone() }.to_string();
+    let body = serde_json::to_vec(&snapshot).map_err(|e| format!("Sync serialize error: {}", e))?;
+    let data = seal_blob(master_key, &name, body)?;
+    log.rebase(snapshot);
+    Ok(SyncBlob { name, data })
+}
+
+/// Opens a downloaded blob and merges it into the log: an ops blob adds its ops,
+/// a snapshot later than the log's base becomes the new base. Blobs the log
+/// already has are skipped without decrypting. Returns how many ops were new.
+pub fn merge_blob(master_key: &[u8; 32], log: &mut OpLog, name: &str, data: &[u8]) -> Result<usize, String> {
+    match BlobName::parse(name).ok_or_else(|| format!("Sync error: '{}' is not a sync blob", name))? {
+        BlobName::Ops { device, number } => {
+            let last = log.last_blob(&device);
+            if number <= last {
+                return Ok(0);
+            }
+            if number != last + 1 {
+                return Err(format!("Sync error: {} is missing", BlobName::Ops { device, number: last + 1 }));
+            }
+            let mut body = open_blob(master_key, name, data)?;
+            let sealed = serde_json::from_slice::<Vec<SealedOp>>(&body).map_err(|e| format!("Sync parse error: {}", e));
+            body.zeroize();
+            let ops = sealed?.iter().map(|s| open_op(master_key, s)).collect::<Result<Vec<_>, _>>()?;
+            if ops.iter().any(|op| op.id.device != device) {
+                return Err(format!("Sync error: {} holds another device's ops", name));
+            }
+            let added = log.merge(ops);
+            log.set_last_blob(&device, number);
+            Ok(added)
+        }
+        BlobName::Snapshot { number, device } => {
+            if (number, device.as_str()) <= (log.base().number, log.base().device.as_str()) {
+                return Ok(0);
+            }
+            let mut body = open_blob(master_key, name, data)?;
+            let snapshot = serde_json::from_slice::<Snapshot>(&body).map_err(|e| format!("Sync parse error: {}", e));
+            body.zeroize();
+            let snapshot = snapshot?;
+            if snapshot.number != number || snapshot.device != device {
+                return Err(format!("Sync error: {} does not match its contents", name));
+            }
+            log.rebase(snapshot);
+            Ok(0)
+        }
+    }
+}
+
+/// The blobs to download, in the order to merge them: the newest snapshot (if it
+/// is later than the log's base), then every unmerged ops blob, per device in order.
+pub fn pending(log: &OpLog, names: &[String]) -> Vec<String> {
+    let mut snapshots = Vec::new();
+    let mut ops = Vec::new();
+    for parsed in names.iter().filter_map(|n| BlobName::parse(n)) {
+        match parsed {
+            BlobName::Snapshot { number, ref device } => {
+                if (number, device.as_str()) > (log.base().number, log.base().device.as_str()) {
+                    snapshots.push((number, device.clone()));
+                }
+            }
+            BlobName::Ops { ref device, number } => {
+                if number > log.last_blob(device) {
+                    ops.push((device.clone(), number));
+                }
+            }
+        }
+    }
+    ops.sort();
+    let newest = snapshots.into_iter().max().map(|(number, device)| BlobName::Snapshot { number, device });
+    newest
+        .into_iter()
+        .chain(ops.into_iter().map(|(device, number)| BlobName::Ops { device, number }))
+        .map(|b| b.to_string())
+        .collect()
+}
+
+/// The blobs the log's base snapshot makes redundant: older snapshots, and ops
+/// blobs it covers. Only call once that snapshot is on the storage.
+pub fn compaction_plan(log: &OpLog, names: &[String]) -> Vec<String> {
+    let base = log.base();
+    if base.number == 0 {
+        return Vec::new();
+    }
+    names
+        .iter()
+        .filter(|name| match BlobName::parse(name) {
+            Some(BlobName::Snapshot { number, device }) => (number, device.as_str()) < (base.number, base.device.as_str()),
+            Some(BlobName::Ops { device, number }) => number <= base.blobs.get(&device).copied().unwrap_or(0),
+            None => false,
+        })
+        .cloned()
+        .collect()
+}
+
+fn seal_blob(master_key: &[u8; 32], name: &str, mut body: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let sealed = seal_committed(&key, &body, &aad(MAGIC, name));
+    key.zeroize();
+    body.zeroize();
+
+    let (iv, ciphertext) = sealed?;
+    let mut blob = Vec::with_capacity(MAGIC.len() + iv.len() + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.extend_from_slice(&iv);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+fn open_blob(master_key: &[u8; 32], name: &str, blob: &[u8]) -> Result<Vec<u8>, String> {
+    if blob.len() < MAGIC.len() + 12 || ![MAGIC.as_slice(), LEGACY_MAGIC].contains(&&blob[..MAGIC.len()]) {
+        return Err(format!("Sync error: {} is not a SecurePass blob", name));
+    }
+    let (magic, rest) = blob.split_at(MAGIC.len());
+    let (iv, ciphertext) = rest.split_at(12);
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let body = if magic == LEGACY_MAGIC {
+        open(&key, iv, ciphertext, &aad(LEGACY_MAGIC, name))
+    } else {
+        open_committed(&key, iv, ciphertext, &aad(MAGIC, name))
+    };
+    key.zeroize();
+    body.map_err(|_| format!("Sync error: {} is damaged or belongs to another vault", name))
+}
+
+fn aad(magic: &[u8; 4], name: &str) -> Vec<u8> {
+    [magic.as_slice(), name.as_bytes()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oplog::{diff, seal_op};
+    use crate::vault::VaultEntry;
+
+    const KEY: [u8; 32] = [3u8; 32];
+
+    /// Records one new entry and packs it into the device's next blob.
+    fn add_and_pack(log: &mut OpLog, id: &str) -> SyncBlob {
+        let before = log.materialize();
+        let mut after = before.clone();
+        after.entries.push(VaultEntry { id: id.to_string(), title: id.to_string(), ..Default::default() });
+        let sealed: Vec<SealedOp> =
+            log.record(diff(&before, &after).unwrap()).iter().map(|op| seal_op(&KEY, op).unwrap()).collect();
+        pack_ops(&KEY, log, &sealed).unwrap()
+    }
+
+    #[test]
+    fn test_names_roundtrip() {
+        let ops = BlobName::Ops { device: "phone-1".to_string(), number: 7 };
+        assert_eq!(ops.to_string(), "ops/phone-1/0000000007.blob");
+        assert_eq!(BlobName::parse(&ops.to_string()), Some(ops));
+        let snapshot = BlobName::Snapshot { number: 2, device: "a-b".to_string() };
+        assert_eq!(BlobName::parse("snapshots/0000000002-a-b.blob"), Some(snapshot));
+
+        for bad in ["ops/../0000000001.blob", "ops/a/1.blob", "ops/a/0000000000.blob", "snapshots/0000000001-.blob", "notes.txt"] {
+            assert_eq!(BlobName::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_devices_sync_through_blobs_and_compact() {
+        let (mut a, mut b) = (OpLog::new("a").unwrap(), OpLog::new("b").unwrap());
+        let mut storage = vec![add_and_pack(&mut a, "1"), add_and_pack(&mut a, "2"), add_and_pack(&mut b, "3")];
+        let names = |storage: &[SyncBlob]| storage.iter().map(|b| b.name.clone()).collect::<Vec<_>>();
+
+        // b catches up, snapshots, and compacts.
+        for name in pending(&b, &names(&storage)) {
+            let blob = storage.iter().find(|s| s.name == name).unwrap();
+            merge_blob(&KEY, &mut b, &blob.name, &blob.data).unwrap();
+        }
+        storage.push(pack_snapshot(&KEY, &mut b).unwrap());
+        let doomed = compaction_plan(&b, &names(&storage));
+        assert_eq!(doomed.len(), 3);
+        storage.retain(|s| !doomed.contains(&s.name));
+        storage.push(add_and_pack(&mut b, "4"));
+
+        // A fresh install of a rebuilds everything from what is left.
+        let mut fresh = OpLog::new("a").unwrap();
+        let todo = pending(&fresh, &names(&storage));
+        assert_eq!(todo, vec!["snapshots/0000000001-b.blob", "ops/b/0000000002.blob"]);
+        for name in &todo {
+            let blob = storage.iter().find(|s| &s.name == name).unwrap();
+            merge_blob(&KEY, &mut fresh, name, &blob.data).unwrap();
+        }
+        assert_eq!(fresh.materialize(), b.materialize());
+        assert_eq!(fresh.materialize().entries.len(), 4);
+        assert_eq!(add_and_pack(&mut fresh, "5").name, "ops/a/0000000003.blob");
+    }
+
+    #[test]
+    fn test_rejects_gaps_and_tampering() {
+        let mut a = OpLog::new("a").unwrap();
+        let (first, second) = (add_and_pack(&mut a, "1"), add_and_pack(&mut a, "2"));
+        let mut reader = OpLog::new("r").unwrap();
+        assert!(merge_blob(&KEY, &mut reader, &second.name, &second.data).unwrap_err().contains("ops/a/0000000001.blob is missing"));
+
+        let mut flipped = first.data.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(merge_blob(&KEY, &mut reader, &first.name, &flipped).is_err());
+        assert!(merge_blob(&KEY, &mut reader, "ops/b/0000000001.blob", &first.data).is_err());
+        assert!(merge_blob(&[4u8; 32], &mut reader, &first.name, &first.data).is_err());
+        let relabelled = [LEGACY_MAGIC.as_slice(), &first.data[MAGIC.len()..]].concat();
+        assert!(merge_blob(&KEY, &mut reader, &first.name, &relabelled).is_err());
+
+        assert_eq!(merge_blob(&KEY, &mut reader, &first.name, &first.data).unwrap(), 1);
+        assert_eq!(merge_blob(&KEY, &mut reader, &first.name, &first.data).unwrap(), 0);
+        assert!(pack_ops(&KEY, &mut reader, &[]).is_err());
+    }
+}
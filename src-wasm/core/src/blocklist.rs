@@ -0,0 +1,88 @@
+// --- Common-Password Blocklist ---
+// The passwords that top every leaked-password frequency list, embedded in the
+// binary so the check works offline and before any vault is unlocked. Used two
+// ways: the strength estimate calls a common password weak whatever its length
+// or character mix, and master-password creation refuses one outright.
+//
+// data/common-passwords.bin holds the 100,000 most common passwords of the
+// SecLists leak compilation (lowercased, about 98,000 once merged), each as the
+// first RECORD_BYTES of its SHA-256, sorted bytewise with no duplicates (a test
+// checks this): 477 KiB where the text would take 816, and a lookup is one
+// hash and a binary search. Five bytes make a false match about one in ten
+// million per lookup. Rebuild it with scripts/build-password-blocklist.mjs.
+//
+// The list is behind the "wordlists" feature. A build without it embeds nothing
+// and calls no password common; the strength estimate and master-password rules
+// still apply.
+
+use sha2::{Digest, Sha256};
+
+/// Bytes kept of each password's SHA-256; the build script uses the same.
+const RECORD_BYTES: usize = 5;
+
+#[cfg(feature = "wordlists")]
+static LIST: &[u8] = include_bytes!("../data/common-passwords.bin");
+#[cfg(not(feature = "wordlists"))]
+static LIST: &[u8] = &[];
+
+fn record(index: usize) -> &'static [u8] {
+    &LIST[index * RECORD_BYTES..(index + 1) * RECORD_BYTES]
+}
+
+fn listed(lower: &str) -> bool {
+    let hash = Sha256::digest(lower.as_bytes());
+    let needle = &hash[..RECORD_BYTES];
+    let (mut low, mut high) = (0, len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match record(mid).cmp(needle) {
+            std::cmp::Ordering::Less => low = mid + 1,
+            std::cmp::Ordering::Greater => high = mid,
+            std::cmp::Ordering::Equal => return true,
+        }
+    }
+    false
+}
+
+/// How many passwords are embedded.
+pub fn len() -> usize {
+    LIST.len() / RECORD_BYTES
+}
+
+/// True if the password, ignoring case, is on the list, or is a listed password
+/// followed only by digits and symbols ("Dragon2024!" is "dragon" with a suffix).
+pub fn is_common_password(password: &str) -> bool {
+    let lower = password.trim().to_lowercase();
+    if lower.is_empty() {
+        return false;
+    }
+    if listed(&lower) {
+        return true;
+    }
+    let base = lower.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_ascii_punctuation());
+    base.len() >= 4 && base.len() < lower.len() && listed(base)
+}
+
+#[cfg(all(test, feature = "wordlists"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_is_compact_sorted_and_unique() {
+        assert_eq!(LIST.len() % RECORD_BYTES, 0);
+        assert!(len() > 95_000);
+        assert!(LIST.len() <= 500 * 1024);
+        assert!((1..len()).all(|i| record(i - 1) < record(i)));
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert!(is_common_password("123456") && is_common_password("P@ssw0rd") && is_common_password(" qwerty "));
+        assert!(is_common_password("Dragon2024!") && is_common_password("iloveyou!!"));
+        // Further down the list than the old 785-line one reached.
+        assert!(is_common_password("q1w2e3r4t5y6") && is_common_password("Snickers") && is_common_password("michelle1"));
+        assert!(!is_common_password("") && !is_common_password("vexing-tundra-Quill-42"));
+        // Bases shorter than four characters don't count.
+        assert!(!is_common_password("abc1"));
+    }
+}
@@ -0,0 +1,201 @@
+// --- Offline Breach Filter ---
+// A Bloom filter over the SHA-1 hashes of breached passwords (the hashes the
+// Have I Been Pwned dataset is published as), for users who won't send even a
+// k-anonymized hash prefix over the network. The app downloads or bundles the
+// filter once; every check after that is local.
+//
+// The bit positions are taken straight from the SHA-1 digest (double hashing
+// over its first 16 bytes), so a filter can be built from a hash list without
+// ever seeing a plaintext password. A hit means "probably breached" with the
+// filter's false-positive rate; a miss is certain.
+//
+// File format, gzip-compressed: "SPBF", version byte, hash count byte, bit
+// count as u64 little-endian, then the bit array (bit i is byte i / 8, bit i % 8).
+
+use std::io::{Read, Write};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+
+use crate::segments::EntrySummary;
+use crate::vault::VaultEntry;
+
+const MAGIC: &[u8; 4] = b"SPBF";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 1 + 8;
+
+/// Filters come from a download, so one that would take more memory than a
+/// browser tab can spare is refused (512 MiB of bits).
+pub const MAX_FILTER_BYTES: usize = 512 * 1024 * 1024;
+const MAX_HASHES: u8 = 32;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreachFilter {
+    bits: Vec<u8>,
+    bit_count: u64,
+    hash_count: u8,
+}
+
+/// What the settings screen shows about a loaded filter.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterInfo {
+    pub bit_count: u64,
+    pub hash_count: u8,
+    /// Share of bits set; the false-positive rate is about this to the power of 'hash_count'.
+    pub fill_ratio: f64,
+    pub false_positive_rate: f64,
+}
+
+impl BreachFilter {
+    /// An empty filter sized for `expected` hashes at about `false_positive_rate`.
+    /// Used by the tool that builds the downloadable file.
+    pub fn with_capacity(expected: u64, false_positive_rate: f64) -> Result<BreachFilter, String> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err("Breach filter error: the false-positive rate must be between 0 and 1".to_string());
+        }
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(expected.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(8.0);
+        let hashes = ((bits / expected.max(1) as f64) * ln2).round().clamp(1.0, f64::from(MAX_HASHES));
+        let byte_len = (bits / 8.0).ceil();
+        if byte_len > MAX_FILTER_BYTES as f64 {
+            return Err("Breach filter error: the filter would be too large".to_string());
+        }
+        let byte_len = byte_len as usize;
+        Ok(BreachFilter { bits: vec![0; byte_len], bit_count: byte_len as u64 * 8, hash_count: hashes as u8 })
+    }
+
+    /// Adds one breached password by its SHA-1 digest.
+    pub fn insert_sha1(&mut self, digest: &[u8; 20]) {
+        for bit in self.positions(digest) {
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    pub fn contains_sha1(&self, digest: &[u8; 20]) -> bool {
+        self.positions(digest).all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+
+    /// True if the password is probably in the breach data. It is hashed exactly
+    /// as typed, the way the dataset was.
+    pub fn contains_password(&self, password: &str) -> bool {
+        self.contains_sha1(&Sha1::digest(password.as_bytes()).into())
+    }
+
+    pub fn info(&self) -> FilterInfo {
+        let set: u64 = self.bits.iter().map(|b| u64::from(b.count_ones())).sum();
+        let fill_ratio = set as f64 / self.bit_count as f64;
+        FilterInfo {
+            bit_count: self.bit_count,
+            hash_count: self.hash_count,
+            fill_ratio,
+            false_positive_rate: fill_ratio.powi(i32::from(self.hash_count)),
+        }
+    }
+
+    /// Reads a gzip-compressed filter file.
+    pub fn from_compressed(data: &[u8]) -> Result<BreachFilter, String> {
+        let mut raw = Vec::new();
+        GzDecoder::new(data)
+            .take((HEADER_LEN + MAX_FILTER_BYTES + 1) as u64)
+            .read_to_end(&mut raw)
+            .map_err(|e| format!("Breach filter decompress error: {}", e))?;
+
+        if raw.len() < HEADER_LEN || &raw[..4] != MAGIC {
+            return Err("Breach filter error: not a breach filter file".to_string());
+        }
+        if raw[4] != FORMAT_VERSION {
+            return Err(format!("Unsupported breach filter version: {}", raw[4]));
+        }
+        let hash_count = raw[5];
+        let bit_count = u64::from_le_bytes(raw[6..HEADER_LEN].try_into().unwrap_or_default());
+        let bits = raw.split_off(HEADER_LEN);
+        if bits.len() > MAX_FILTER_BYTES {
+            return Err("Breach filter error: the filter is too large".to_string());
+        }
+        if hash_count == 0 || hash_count > MAX_HASHES || bit_count == 0 || bit_count.div_ceil(8) != bits.len() as u64 {
+            return Err("Breach filter error: the header doesn't match the data".to_string());
+        }
+        Ok(BreachFilter { bits, bit_count, hash_count })
+    }
+
+    /// Writes the filter in the file format 'from_compressed' reads.
+    pub fn to_compressed(&self) -> Result<Vec<u8>, String> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        let write = |encoder: &mut GzEncoder<Vec<u8>>| -> std::io::Result<()> {
+            encoder.write_all(MAGIC)?;
+            encoder.write_all(&[FORMAT_VERSION, self.hash_count])?;
+            encoder.write_all(&self.bit_count.to_le_bytes())?;
+            encoder.write_all(&self.bits)
+        };
+        write(&mut encoder).map_err(|e| format!("Breach filter compress error: {}", e))?;
+        encoder.finish().map_err(|e| format!("Breach filter compress error: {}", e))
+    }
+
+    /// Kirsch-Mitzenmacher double hashing: bit i is h1 + i * h2 (mod bit count).
+    fn positions(&self, digest: &[u8; 20]) -> impl Iterator<Item = u64> {
+        let h1 = u64::from_le_bytes(digest[0..8].try_into().unwrap_or_default());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap_or_default()) | 1;
+        let bit_count = self.bit_count;
+        (0..u64::from(self.hash_count)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+}
+
+/// The entries whose current password is probably breached, as summaries.
+pub fn breached_entries(filter: &BreachFilter, entries: &[VaultEntry]) -> Vec<EntrySummary> {
+    entries
+        .iter()
+        .filter(|e| !e.password.is_empty() && filter.contains_password(&e.password))
+        .map(EntrySummary::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_of(passwords: &[&str]) -> BreachFilter {
+        let mut filter = BreachFilter::with_capacity(1000, 0.001).unwrap();
+        for password in passwords {
+            filter.insert_sha1(&Sha1::digest(password.as_bytes()).into());
+        }
+        filter
+    }
+
+    #[test]
+    fn test_members_are_found_and_others_mostly_not() {
+        let filter = filter_of(&["123456", "password", "qwerty"]);
+        assert!(filter.contains_password("123456") && filter.contains_password("qwerty"));
+        let misses = (0..1000).filter(|i| filter.contains_password(&format!("unbreached-{}", i))).count();
+        assert!(misses < 5);
+        assert!(filter.info().false_positive_rate < 1e-6);
+    }
+
+    #[test]
+    fn test_compressed_roundtrip_and_rejects_garbage() {
+        let filter = filter_of(&["letmein"]);
+        let file = filter.to_compressed().unwrap();
+        assert!(file.len() < filter.bits.len());
+        let loaded = BreachFilter::from_compressed(&file).unwrap();
+        assert_eq!(loaded, filter);
+        assert!(loaded.contains_password("letmein"));
+
+        assert!(BreachFilter::from_compressed(b"not gzip").is_err());
+        let mut raw = BreachFilter::with_capacity(10, 0.01).unwrap();
+        raw.bit_count += 64;
+        assert!(BreachFilter::from_compressed(&raw.to_compressed().unwrap()).unwrap_err().contains("header"));
+    }
+
+    #[test]
+    fn test_breached_entries() {
+        let filter = filter_of(&["hunter2"]);
+        let entries = vec![
+            VaultEntry { id: "1".to_string(), title: "Old".to_string(), password: "hunter2".to_string(), ..Default::default() },
+            VaultEntry { id: "2".to_string(), title: "New".to_string(), password: "x7#Lq9!vR2".to_string(), ..Default::default() },
+            VaultEntry { id: "3".to_string(), title: "Note".to_string(), ..Default::default() },
+        ];
+        let found = breached_entries(&filter, &entries);
+        assert_eq!(found.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["1"]);
+    }
+}
@@ -0,0 +1,233 @@
+// --- Breached Sites ---
+// The breach filter ('breach') knows which passwords leaked; this knows which
+// sites did, and when. A login whose password was set before its site's breach
+// may have leaked with it, so the app can say "change this password" without a
+// network lookup, and stop saying it once the password has been changed.
+//
+// The list comes from the vendor as a signed, gzip-compressed bundle:
+//   gzip("SPBS" | version byte | Ed25519 signature (64 bytes) | payload JSON)
+// The signature covers a context label followed by the payload bytes, and is
+// checked against the vendor's public key before anything is parsed.
+//
+// Entries are matched to sites by registrable domain, so a breach of example.com
+// covers login.example.com too. The vault doesn't record when a password was
+// set, only when the entry last changed ('last_modified'); an entry edited after
+// the breach for some other reason is taken to have a new password.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::domains::{registrable_domain, registrable_domain_of_host};
+use crate::emergency::decode_key;
+use crate::extra_lock;
+use crate::redact;
+use crate::vault::VaultEntry;
+
+const MAGIC: &[u8; 4] = b"SPBS";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 64;
+/// Prefixed to the payload before signing, so a signature can't be replayed elsewhere.
+const SIGNING_CONTEXT: &[u8] = b"securepass-breached-sites-v1";
+/// Bundles come from a download; the list for every known breach is a few MiB.
+pub const MAX_BUNDLE_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BreachedSite {
+    pub domain: String,
+    /// For display: "Example Corp".
+    pub name: String,
+    /// When the breach happened (not when it was disclosed), in Unix seconds.
+    pub breached_at: u64,
+    /// What leaked: "Passwords", "Email addresses", ...
+    #[serde(default)]
+    pub data_classes: Vec<String>,
+}
+
+/// The signed payload.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteList {
+    pub version: u8,
+    pub published_at: u64,
+    pub sites: Vec<BreachedSite>,
+}
+
+/// What the settings screen shows about a loaded list.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListInfo {
+    pub published_at: u64,
+    pub site_count: usize,
+}
+
+/// A login whose password predates a breach of its site.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteAlert {
+    pub entry_id: String,
+    pub title: String,
+    pub domain: String,
+    pub site_name: String,
+    pub breached_at: u64,
+    /// The entry's 'last_modified', the closest the vault has to when the password was set.
+    pub password_set_at: u64,
+    pub data_classes: Vec<String>,
+}
+
+/// A verified list, indexed by registrable domain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreachedSites {
+    list: SiteList,
+    by_domain: BTreeMap<String, Vec<usize>>,
+}
+
+impl BreachedSites {
+    /// Reads a bundle, checking its signature against the vendor's Ed25519 public key (Base64).
+    pub fn from_signed(bundle: &[u8], vendor_public_key: &str) -> Result<BreachedSites, String> {
+        let mut raw = Vec::new();
+        GzDecoder::new(bundle)
+            .take((HEADER_LEN + MAX_BUNDLE_BYTES + 1) as u64)
+            .read_to_end(&mut raw)
+            .map_err(|e| format!("Breached sites decompress error: {}", e))?;
+        if raw.len() < HEADER_LEN || &raw[..4] != MAGIC {
+            return Err("Breached sites error: not a breached-sites bundle".to_string());
+        }
+        if raw[4] != FORMAT_VERSION {
+            return Err(format!("Unsupported breached sites version: {}", raw[4]));
+        }
+        if raw.len() > HEADER_LEN + MAX_BUNDLE_BYTES {
+            return Err("Breached sites error: the bundle is too large".to_string());
+        }
+
+        let signature = Signature::from_slice(&raw[5..HEADER_LEN]).map_err(|e| format!("Signature error: {}", e))?;
+        let key = decode_key(vendor_public_key, "Vendor public key")?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Vendor public key error: {}", e))?;
+        let payload = &raw[HEADER_LEN..];
+        key.verify_strict(&[SIGNING_CONTEXT, payload].concat(), &signature)
+            .map_err(|_| "Breached sites error: the signature does not match".to_string())?;
+
+        let list: SiteList = serde_json::from_slice(payload).map_err(|e| redact::json_error("Breached sites parse", &e))?;
+        if list.version != FORMAT_VERSION {
+            return Err(format!("Unsupported breached sites version: {}", list.version));
+        }
+        Ok(BreachedSites::index(list))
+    }
+
+    /// Writes a signed bundle in the format 'from_signed' reads. Used by the tool
+    /// that publishes the list.
+    pub fn sign(list: &SiteList, vendor: &SigningKey) -> Result<Vec<u8>, String> {
+        let payload = serde_json::to_vec(list).map_err(|e| format!("Breached sites serialize error: {}", e))?;
+        let signature = vendor.sign(&[SIGNING_CONTEXT, &payload[..]].concat());
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        let write = |encoder: &mut GzEncoder<Vec<u8>>| -> std::io::Result<()> {
+            encoder.write_all(MAGIC)?;
+            encoder.write_all(&[FORMAT_VERSION])?;
+            encoder.write_all(&signature.to_bytes())?;
+            encoder.write_all(&payload)
+        };
+        write(&mut encoder).map_err(|e| format!("Breached sites compress error: {}", e))?;
+        encoder.finish().map_err(|e| format!("Breached sites compress error: {}", e))
+    }
+
+    fn index(list: SiteList) -> BreachedSites {
+        let mut by_domain: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, site) in list.sites.iter().enumerate() {
+            if let Some(domain) = registrable_domain_of_host(site.domain.trim()) {
+                by_domain.entry(domain).or_default().push(i);
+            }
+        }
+        BreachedSites { list, by_domain }
+    }
+
+    pub fn info(&self) -> ListInfo {
+        ListInfo { published_at: self.list.published_at, site_count: self.list.sites.len() }
+    }
+
+    /// The latest breach of `domain` (registrable) that happened at or after `since`.
+    fn breach_since(&self, domain: &str, since: u64) -> Option<&BreachedSite> {
+        let indices = self.by_domain.get(domain)?;
+        indices.iter().map(|&i| &self.list.sites[i]).filter(|site| site.breached_at >= since).max_by_key(|site| site.breached_at)
+    }
+
+    /// Logins whose password was set no later than a breach of their site, in vault order.
+    pub fn alerts(&self, entries: &[VaultEntry]) -> Vec<SiteAlert> {
+        entries
+            .iter()
+            .filter(|e| e.kind() == "login" && (!e.password.is_empty() || extra_lock::is_locked(e)))
+            .filter_map(|entry| {
+                let domain = registrable_domain(&entry.url)?;
+                let site = self.breach_since(&domain, entry.last_modified)?;
+                Some(SiteAlert {
+                    entry_id: entry.id.clone(),
+                    title: entry.title.clone(),
+                    domain,
+                    site_name: site.name.clone(),
+                    breached_at: site.breached_at,
+                    password_set_at: entry.last_modified,
+                    data_classes: site.data_classes.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD as B64, Engine};
+
+    fn site(domain: &str, breached_at: u64) -> BreachedSite {
+        BreachedSite { domain: domain.to_string(), name: domain.to_string(), breached_at, data_classes: vec!["Passwords".to_string()] }
+    }
+
+    fn login(id: &str, url: &str, last_modified: u64) -> VaultEntry {
+        VaultEntry { id: id.to_string(), url: url.to_string(), password: "pw".to_string(), last_modified, ..Default::default() }
+    }
+
+    #[test]
+    fn test_signed_bundle_roundtrip_and_forgery() {
+        let vendor = SigningKey::from_bytes(&[7u8; 32]);
+        let public = B64.encode(vendor.verifying_key().as_bytes());
+        let list = SiteList { version: 1, published_at: 500, sites: vec![site("example.com", 100)] };
+        let bundle = BreachedSites::sign(&list, &vendor).unwrap();
+        let loaded = BreachedSites::from_signed(&bundle, &public).unwrap();
+        assert_eq!(loaded.info(), ListInfo { published_at: 500, site_count: 1 });
+
+        let other = B64.encode(SigningKey::from_bytes(&[8u8; 32]).verifying_key().as_bytes());
+        assert!(BreachedSites::from_signed(&bundle, &other).unwrap_err().contains("does not match"));
+        assert!(BreachedSites::from_signed(b"not gzip", &public).is_err());
+
+        // A payload swapped in under the old signature.
+        let mut raw = Vec::new();
+        GzDecoder::new(&bundle[..]).read_to_end(&mut raw).unwrap();
+        raw.truncate(HEADER_LEN);
+        raw.extend_from_slice(br#"{"version":1,"publishedAt":500,"sites":[]}"#);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&raw).unwrap();
+        assert!(BreachedSites::from_signed(&encoder.finish().unwrap(), &public).unwrap_err().contains("does not match"));
+    }
+
+    #[test]
+    fn test_alerts_for_passwords_older_than_the_breach() {
+        let sites = BreachedSites::index(SiteList {
+            version: 1,
+            published_at: 0,
+            sites: vec![site("example.com", 100), site("example.com", 300), site("other.example", 50)],
+        });
+        let entries = vec![
+            login("old", "https://login.example.com/", 90),
+            login("between", "https://example.com", 200),
+            login("changed", "https://example.com", 400),
+            login("unlisted", "https://safe.example", 10),
+            VaultEntry { id: "note".to_string(), url: "https://example.com".to_string(), ..Default::default() },
+        ];
+        let alerts = sites.alerts(&entries);
+        assert_eq!(alerts.iter().map(|a| (a.entry_id.as_str(), a.breached_at)).collect::<Vec<_>>(), [("old", 300), ("between", 300)]);
+        assert_eq!((alerts[0].domain.as_str(), alerts[0].password_set_at), ("example.com", 90));
+    }
+}
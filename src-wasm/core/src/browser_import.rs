@@ -0,0 +1,163 @@
+// --- Browser Password CSV Import ---
+// The password exports of Chrome (and Edge, Brave, Opera, which share its code)
+// and Firefox. The header says which browser wrote the file:
+//   Chrome   name,url,username,password[,note]
+//   Firefox  url,username,password,httpRealm,formActionOrigin,guid,
+//            timeCreated,timePasswordChanged,timeLastUsed
+// Browsers save a login per origin, so a site that moved from http to https
+// often shows up twice with the same credentials; those rows are merged, keeping
+// the https URL. Chrome's 'name' is usually just the host and Firefox has none,
+// so titles come from the URL: the host without "www.", or the package name of
+// an Android app ("android://<hash>@com.example.app/").
+
+use url::Url;
+
+use crate::import::{Imported, Importer};
+use crate::vault::{new_entry_id, VaultEntry};
+
+/// The Chrome/Edge and Firefox CSV importer; 'Imported::source' says which browser
+/// wrote the file, and 'duplicates' counts the rows merged into another (the http
+/// twin of an https login, or an exact repeat).
+pub struct BrowserCsv;
+
+impl Importer for BrowserCsv {
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String> {
+        parse_csv(data, now)
+    }
+}
+
+/// "chrome" or "firefox", from a header row.
+fn detect(header: &[String]) -> Option<&'static str> {
+    let has = |name: &str| header.iter().any(|h| h.trim() == name);
+    if has("url") && has("username") && has("password") {
+        if has("guid") && has("timePasswordChanged") {
+            return Some("firefox");
+        }
+        if has("name") {
+            return Some("chrome");
+        }
+    }
+    None
+}
+
+/// Reads a Chrome or Firefox password export.
+pub fn parse_csv(csv: &str, now: u64) -> Result<Imported, String> {
+    let mut rows = crate::csv::rows(csv)?.into_iter();
+    let header = rows.next().ok_or("Browser import error: the file is empty")?;
+    let browser = detect(&header).ok_or("Browser import error: not a Chrome or Firefox password export")?;
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let columns: Vec<Option<usize>> = ["name", "url", "username", "password", "note", "timePasswordChanged", "timeLastUsed"]
+        .iter()
+        .map(|name| column(name))
+        .collect();
+
+    let mut import = Imported::new(browser);
+    for mut row in rows {
+        if row.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let mut take = |i: usize| columns[i].and_then(|c| row.get_mut(c)).map(std::mem::take).unwrap_or_default();
+        let (name, url, username, password, notes) = (take(0), take(1), take(2), take(3), take(4));
+        // Firefox times are milliseconds.
+        let millis = |cell: String| cell.trim().parse::<u64>().ok().map(|ms| ms / 1000);
+        let (changed, used) = (millis(take(5)), millis(take(6)));
+
+        let entry = VaultEntry {
+            id: new_entry_id(),
+            title: title(&name, &url),
+            url,
+            username,
+            password,
+            notes,
+            last_modified: changed.unwrap_or(now),
+            last_used: used.unwrap_or(0),
+            ..Default::default()
+        };
+        match import.entries.iter_mut().find(|e| same_login(e, &entry)) {
+            Some(existing) => {
+                import.duplicates += 1;
+                if scheme(&existing.url) == Some("http") && scheme(&entry.url) == Some("https") {
+                    existing.url = entry.url;
+                }
+                existing.last_modified = existing.last_modified.max(entry.last_modified);
+                existing.last_used = existing.last_used.max(entry.last_used);
+            }
+            None => import.entries.push(entry),
+        }
+    }
+    Ok(import)
+}
+
+fn scheme(url: &str) -> Option<&str> {
+    url.split_once("://").map(|(scheme, _)| scheme)
+}
+
+/// The same site, ignoring http/https, with the same username and password.
+fn same_login(a: &VaultEntry, b: &VaultEntry) -> bool {
+    let site = |url: &str| {
+        Url::parse(url)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+            .and_then(|u| Some((u.host_str()?.to_ascii_lowercase(), u.port())))
+    };
+    let same_site = match (site(&a.url), site(&b.url)) {
+        (Some(x), Some(y)) => x == y,
+        _ => a.url == b.url,
+    };
+    same_site && a.username == b.username && a.password == b.password
+}
+
+/// Chrome's name when it is more than the host, otherwise a name from the URL.
+fn title(name: &str, url: &str) -> String {
+    let parsed = Url::parse(url).ok();
+    let from_url = match &parsed {
+        Some(u) if u.scheme() == "android" => u.host_str().map(str::to_string),
+        Some(u) => u.host_str().map(|host| host.strip_prefix("www.").unwrap_or(host).to_string()),
+        None => None,
+    };
+    let name = name.trim();
+    match from_url {
+        Some(host) if name.is_empty() || name.strip_prefix("www.").unwrap_or(name) == host => host,
+        _ if !name.is_empty() => name.to_string(),
+        Some(host) => host,
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chrome_export() {
+        let csv = "name,url,username,password,note\n\
+            www.github.com,https://www.github.com/login,octo,pw,\n\
+            My bank,https://bank.example/,me,secret,\"PIN on card\"\n\
+            ,android://abc==@com.example.app/,me,app,\n";
+        let import = parse_csv(csv, 7).unwrap();
+        assert_eq!(import.source, "chrome");
+        let titles: Vec<&str> = import.entries.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["github.com", "My bank", "com.example.app"]);
+        assert_eq!((import.entries[1].notes.as_str(), import.entries[1].last_modified), ("PIN on card", 7));
+    }
+
+    #[test]
+    fn test_firefox_export_merges_http_twins() {
+        let csv = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timePasswordChanged\",\"timeLastUsed\"\n\
+            \"http://shop.example\",\"me\",\"pw\",,\"\",\"{1}\",\"1000\",\"2000000\",\"3000000\"\n\
+            \"https://shop.example\",\"me\",\"pw\",,\"\",\"{2}\",\"1000\",\"5000000\",\"4000000\"\n\
+            \"https://shop.example\",\"you\",\"pw\",,\"\",\"{3}\",\"1000\",\"1000000\",\"1000000\"\n\
+            \"http://shop.example:8080\",\"me\",\"pw\",,\"\",\"{4}\",\"1000\",\"1000000\",\"1000000\"\n";
+        let import = parse_csv(csv, 0).unwrap();
+        assert_eq!((import.source, import.entries.len(), import.duplicates), ("firefox", 3, 1));
+        let shop = &import.entries[0];
+        assert_eq!((shop.url.as_str(), shop.title.as_str()), ("https://shop.example", "shop.example"));
+        assert_eq!((shop.last_modified, shop.last_used), (5000, 4000));
+    }
+
+    #[test]
+    fn test_other_files_are_refused() {
+        assert!(parse_csv("title,login,secret\n", 0).unwrap_err().contains("not a Chrome or Firefox"));
+        assert!(parse_csv("", 0).unwrap_err().contains("empty"));
+    }
+}
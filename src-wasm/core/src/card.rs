@@ -0,0 +1,314 @@
+// --- Payment Cards ---
+// The "card" entry type (the 'credit_card' template). Lists and detail views
+// show a card as "•••• 4242 (Visa, expires 08/26)": 'summary' builds that from
+// the entry inside the engine, so the full card number only crosses into JS
+// when the user asks to reveal or copy it.
+//
+// Brands are told apart by the number's first digits (the issuer
+// identification ranges each network publishes); a number outside every range
+// is still a card, just of an unknown brand. A card is good until the end of
+// the month it expires in, UTC.
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::backups;
+use crate::vault::VaultEntry;
+
+pub const ENTRY_TYPE: &str = "card";
+
+pub const CARDHOLDER_FIELD: &str = "Cardholder name";
+pub const NUMBER_FIELD: &str = "Card number";
+pub const EXPIRY_FIELD: &str = "Expiry date";
+pub const CVV_FIELD: &str = "Security code";
+pub const PIN_FIELD: &str = "PIN";
+
+/// Where the web app kept the number before cards had typed fields.
+const LEGACY_NUMBER_KEY: &str = "cardNumber";
+
+/// A card expiring within this many months (counting the current one) is "expiring soon".
+const EXPIRING_SOON_MONTHS: u64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Brand {
+    Visa,
+    Mastercard,
+    Amex,
+    Discover,
+    DinersClub,
+    Jcb,
+    UnionPay,
+    Maestro,
+    Unknown,
+}
+
+impl Brand {
+    pub fn name(self) -> &'static str {
+        match self {
+            Brand::Visa => "Visa",
+            Brand::Mastercard => "Mastercard",
+            Brand::Amex => "American Express",
+            Brand::Discover => "Discover",
+            Brand::DinersClub => "Diners Club",
+            Brand::Jcb => "JCB",
+            Brand::UnionPay => "UnionPay",
+            Brand::Maestro => "Maestro",
+            Brand::Unknown => "Card",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryStatus {
+    Valid,
+    /// Expires this month or next.
+    ExpiringSoon,
+    Expired,
+}
+
+/// The month a card expires at the end of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expiry {
+    pub month: u32,
+    pub year: u32,
+}
+
+/// What the UI shows for a card without seeing its number.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CardSummary {
+    pub brand: Brand,
+    /// "Visa", "American Express", ...
+    pub brand_name: String,
+    pub last4: String,
+    /// "•••• 4242".
+    pub masked: String,
+    /// "MM/YY", or empty when the entry has no valid expiry date.
+    pub expiry: String,
+    pub expiry_status: Option<ExpiryStatus>,
+    /// "•••• 4242 (Visa, expires 08/26)".
+    pub display: String,
+    /// Whether the number passes the Luhn check; a failing one was probably mistyped.
+    pub number_valid: bool,
+}
+
+/// The digits of a card number as typed (spaces and dashes allowed), or an error.
+fn digits(number: &str) -> Result<String, String> {
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    if number.chars().any(|c| !(c.is_ascii_digit() || c == ' ' || c == '-')) {
+        return Err("Card error: a card number has only digits, spaces and dashes".to_string());
+    }
+    if !(12..=19).contains(&digits.len()) {
+        return Err("Card error: a card number has 12-19 digits".to_string());
+    }
+    Ok(digits)
+}
+
+/// The card network, from the number's leading digits.
+pub fn detect_brand(number: &str) -> Brand {
+    let digits: String = number.chars().filter(char::is_ascii_digit).take(6).collect();
+    let prefix = |len: usize| digits.get(..len).and_then(|p| p.parse::<u32>().ok()).unwrap_or(0);
+    match (prefix(1), prefix(2), prefix(3), prefix(4)) {
+        (_, 34 | 37, _, _) => Brand::Amex,
+        (4, _, _, _) => Brand::Visa,
+        (_, 51..=55, _, _) | (_, _, _, 2221..=2720) => Brand::Mastercard,
+        (_, 65, _, _) | (_, _, 644..=649, _) | (_, _, _, 6011) => Brand::Discover,
+        (_, _, _, 3528..=3589) => Brand::Jcb,
+        (_, 36 | 38 | 39, _, _) | (_, _, 300..=305, _) => Brand::DinersClub,
+        (_, 62, _, _) => Brand::UnionPay,
+        (_, 50 | 56..=58, _, _) | (_, _, 676, _) | (_, _, _, 6304 | 6759) => Brand::Maestro,
+        _ => Brand::Unknown,
+    }
+}
+
+/// The Luhn checksum every card number ends with.
+pub fn luhn_valid(number: &str) -> bool {
+    let Ok(mut digits) = digits(number) else { return false };
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let d = u32::from(b - b'0');
+            match i % 2 {
+                0 => d,
+                _ if d > 4 => d * 2 - 9,
+                _ => d * 2,
+            }
+        })
+        .sum();
+    digits.zeroize();
+    sum.is_multiple_of(10)
+}
+
+/// "•••• 4242": the last four digits only.
+pub fn mask_number(number: &str) -> Result<String, String> {
+    let mut digits = digits(number)?;
+    let masked = format!("\u{2022}\u{2022}\u{2022}\u{2022} {}", &digits[digits.len() - 4..]);
+    digits.zeroize();
+    Ok(masked)
+}
+
+/// The full number in the groups printed on the card: 4-6-5 for American Express, fours otherwise.
+pub fn group_number(number: &str) -> Result<String, String> {
+    let mut digits = digits(number)?;
+    let groups: Vec<&str> = if detect_brand(&digits) == Brand::Amex && digits.len() == 15 {
+        vec![&digits[..4], &digits[4..10], &digits[10..]]
+    } else {
+        digits.as_bytes().chunks(4).map(|c| std::str::from_utf8(c).unwrap_or_default()).collect()
+    };
+    let grouped = groups.join(" ");
+    digits.zeroize();
+    Ok(grouped)
+}
+
+impl Expiry {
+    /// Accepts "MM/YY", "MM/YYYY", "MM-YY" and "MMYY".
+    pub fn parse(text: &str) -> Result<Expiry, String> {
+        let text = text.trim();
+        let (month, year) = match text.split_once(['/', '-']) {
+            Some((month, year)) => (month.trim(), year.trim()),
+            None if text.len() == 4 => text.split_at(2),
+            None => ("", ""),
+        };
+        let invalid = || format!("Card error: '{}' is not an expiry date (MM/YY)", text);
+        if !(1..=2).contains(&month.len()) || !matches!(year.len(), 2 | 4) {
+            return Err(invalid());
+        }
+        let (Ok(month), Ok(year)) = (month.parse::<u32>(), year.parse::<u32>()) else { return Err(invalid()) };
+        if !(1..=12).contains(&month) {
+            return Err(invalid());
+        }
+        Ok(Expiry { month, year: if year < 100 { 2000 + year } else { year } })
+    }
+
+    pub fn status(self, now: u64) -> ExpiryStatus {
+        let expires = u64::from(self.year) * 12 + u64::from(self.month) - 1;
+        let current = backups::month(now);
+        match expires.checked_sub(current) {
+            None => ExpiryStatus::Expired,
+            Some(left) if left < EXPIRING_SOON_MONTHS => ExpiryStatus::ExpiringSoon,
+            Some(_) => ExpiryStatus::Valid,
+        }
+    }
+}
+
+impl std::fmt::Display for Expiry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}/{:02}", self.month, self.year % 100)
+    }
+}
+
+/// The card number stored in a card entry: its "Card number" field, or the web app's older 'cardNumber'.
+pub fn entry_number(entry: &VaultEntry) -> Option<&str> {
+    entry
+        .fields
+        .iter()
+        .find(|f| f.name == NUMBER_FIELD)
+        .map(|f| f.value.as_str())
+        .or_else(|| entry.extra.get(LEGACY_NUMBER_KEY).and_then(|v| v.as_str()))
+        .filter(|n| !n.trim().is_empty())
+}
+
+/// A card entry's summary at `now`. Fails when it has no usable card number.
+pub fn summary(entry: &VaultEntry, now: u64) -> Result<CardSummary, String> {
+    let number = entry_number(entry).ok_or("Card error: the entry has no card number")?;
+    let masked = mask_number(number)?;
+    let brand = detect_brand(number);
+    let expiry = entry.fields.iter().find(|f| f.name == EXPIRY_FIELD).and_then(|f| Expiry::parse(&f.value).ok());
+    let expiry_status = expiry.map(|e| e.status(now));
+
+    let mut details: Vec<String> = Vec::new();
+    if brand != Brand::Unknown {
+        details.push(brand.name().to_string());
+    }
+    if let (Some(expiry), Some(status)) = (expiry, expiry_status) {
+        let verb = if status == ExpiryStatus::Expired { "expired" } else { "expires" };
+        details.push(format!("{} {}", verb, expiry));
+    }
+    let display = if details.is_empty() { masked.clone() } else { format!("{} ({})", masked, details.join(", ")) };
+
+    Ok(CardSummary {
+        brand,
+        brand_name: brand.name().to_string(),
+        last4: masked[masked.len() - 4..].to_string(),
+        masked,
+        expiry: expiry.map(|e| e.to_string()).unwrap_or_default(),
+        expiry_status,
+        display,
+        number_valid: luhn_valid(number),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-15.
+    const NOW: u64 = 1_792_022_400;
+
+    #[test]
+    fn test_brands_and_luhn() {
+        let cases = [
+            ("4242 4242 4242 4242", Brand::Visa),
+            ("5555-5555-5555-4444", Brand::Mastercard),
+            ("2223003122003222", Brand::Mastercard),
+            ("378282246310005", Brand::Amex),
+            ("6011111111111117", Brand::Discover),
+            ("3566002020360505", Brand::Jcb),
+            ("36227206271667", Brand::DinersClub),
+            ("6200000000000005", Brand::UnionPay),
+            ("6759649826438453", Brand::Maestro),
+            ("9999999999999995", Brand::Unknown),
+        ];
+        for (number, brand) in cases {
+            assert_eq!(detect_brand(number), brand, "{}", number);
+            assert!(luhn_valid(number), "{}", number);
+        }
+        assert!(!luhn_valid("4242424242424241"));
+        assert!(!luhn_valid("4242"));
+    }
+
+    #[test]
+    fn test_masking_and_grouping() {
+        assert_eq!(mask_number("4242 4242 4242 4242").unwrap(), "•••• 4242");
+        assert_eq!(group_number("378282246310005").unwrap(), "3782 822463 10005");
+        assert_eq!(group_number("4242424242424242").unwrap(), "4242 4242 4242 4242");
+        assert!(mask_number("4242.4242.4242.4242").is_err());
+        assert!(mask_number("1234").is_err());
+    }
+
+    #[test]
+    fn test_expiry_status() {
+        assert_eq!(Expiry::parse("8/26").unwrap(), Expiry { month: 8, year: 2026 });
+        assert_eq!(Expiry::parse("0827").unwrap().to_string(), "08/27");
+        assert_eq!(Expiry::parse("11-2026").unwrap().status(NOW), ExpiryStatus::ExpiringSoon);
+        assert_eq!(Expiry::parse("10/26").unwrap().status(NOW), ExpiryStatus::ExpiringSoon);
+        assert_eq!(Expiry::parse("12/26").unwrap().status(NOW), ExpiryStatus::Valid);
+        assert_eq!(Expiry::parse("09/26").unwrap().status(NOW), ExpiryStatus::Expired);
+        assert!(Expiry::parse("13/26").is_err());
+        assert!(Expiry::parse("2026").is_err());
+    }
+
+    #[test]
+    fn test_summary_of_an_entry() {
+        let mut entry = crate::templates::find("credit_card").unwrap().instantiate("Travel card", 1);
+        assert!(summary(&entry, NOW).is_err());
+        let set = |entry: &mut VaultEntry, name: &str, value: &str| {
+            entry.fields.iter_mut().find(|f| f.name == name).unwrap().value = value.to_string();
+        };
+        set(&mut entry, NUMBER_FIELD, "4242 4242 4242 4242");
+        set(&mut entry, EXPIRY_FIELD, "08/26");
+        let card = summary(&entry, NOW).unwrap();
+        assert_eq!(card.display, "•••• 4242 (Visa, expired 08/26)");
+        assert_eq!((card.last4.as_str(), card.expiry_status, card.number_valid), ("4242", Some(ExpiryStatus::Expired), true));
+        let json = serde_json::to_string(&card).unwrap();
+        assert!(!json.contains("4242 4242"));
+
+        let mut legacy = VaultEntry { entry_type: ENTRY_TYPE.to_string(), ..Default::default() };
+        legacy.extra.insert(LEGACY_NUMBER_KEY.to_string(), "9999 9999 9999 9995".into());
+        assert_eq!(summary(&legacy, NOW).unwrap().display, "•••• 9995");
+    }
+}
@@ -0,0 +1,141 @@
+// --- Clipboard Text ---
+// What goes on the clipboard when a password, username or code is copied. Values
+// imported from other managers (or pasted into a vault from a web page) often
+// carry characters nobody can see: a zero-width space, a byte-order mark, a
+// soft hyphen, a direction mark, or a trailing space or newline. Copied along,
+// they make a site reject a password that looks right. The clipboard gets the
+// value as plain text without them; the vault keeps the value as stored. The
+// zero-width joiner stays where it joins two emoji (👩‍💻, 🏳️‍🌈): there it is
+// part of what the user sees, and taking it out splits the emoji in two.
+//
+// 'autotype_literal' also escapes the value for an autotype sequence ('autotype'),
+// so braces in a password are typed rather than read as placeholders.
+
+/// Characters that render as nothing: zero-width spaces and joiners, direction
+/// and language marks, invisible fillers and tags.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{061C}'
+            | '\u{115F}'
+            | '\u{1160}'
+            | '\u{17B4}'
+            | '\u{17B5}'
+            | '\u{180B}'..='\u{180F}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206F}'
+            | '\u{3164}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{E0000}'..='\u{E007F}'
+    )
+}
+
+const ZWJ: char = '\u{200D}';
+
+/// Emoji and pictographs a zero-width joiner can join: the Extended_Pictographic
+/// blocks, near enough for telling a ZWJ sequence from a stray joiner.
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00A9}'
+            | '\u{00AE}'
+            | '\u{203C}'
+            | '\u{2049}'
+            | '\u{2122}'
+            | '\u{2139}'
+            | '\u{2194}'..='\u{21FF}'
+            | '\u{2300}'..='\u{23FF}'
+            | '\u{24C2}'
+            | '\u{25A0}'..='\u{27BF}'
+            | '\u{2900}'..='\u{297F}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+            | '\u{1F000}'..='\u{1FAFF}'
+    )
+}
+
+/// A password, username or note as plain text for the clipboard: invisible
+/// characters and control characters (but tabs and newlines) removed, except a
+/// joiner inside an emoji sequence, and trailing whitespace trimmed.
+pub fn plain_text(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    // A joiner follows an emoji, or the variation selector that makes one (❤️).
+    let joins_emoji = |i: usize| {
+        i > 0
+            && (is_pictographic(chars[i - 1]) || chars[i - 1] == '\u{FE0F}')
+            && chars.get(i + 1).is_some_and(|&next| is_pictographic(next))
+    };
+    let kept: String = chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| match c {
+            ZWJ => joins_emoji(i),
+            _ => !is_invisible(c) && (!c.is_control() || c == '\t' || c == '\n'),
+        })
+        .map(|(_, &c)| c)
+        .collect();
+    kept.trim_end().to_string()
+}
+
+/// A one-time code for the clipboard: 'plain_text' with the spaces shown between
+/// digit groups ("123 456") taken out as well.
+pub fn plain_code(code: &str) -> String {
+    plain_text(code).chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// 'plain_text', escaped to be typed verbatim inside an autotype sequence:
+/// braces become {{} and {}}, tabs {TAB} and newlines {ENTER}.
+pub fn autotype_literal(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in plain_text(value).chars() {
+        match c {
+            '{' => escaped.push_str("{{}"),
+            '}' => escaped.push_str("{}}"),
+            '\t' => escaped.push_str("{TAB}"),
+            '\n' => escaped.push_str("{ENTER}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autotype::{self, AutotypeAction};
+    use crate::vault::VaultEntry;
+
+    #[test]
+    fn test_invisible_characters_are_stripped() {
+        assert_eq!(plain_text("\u{FEFF}hunter\u{200B}2\u{00AD} \n"), "hunter2");
+        assert_eq!(plain_text("  leading stays\u{202E}\r\n"), "  leading stays");
+        assert_eq!(plain_text("two\nlines\t"), "two\nlines");
+        assert_eq!(plain_text("pässwörd-😀"), "pässwörd-😀");
+        // Joiners inside emoji sequences stay; stray ones don't.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let rainbow_flag = "\u{1F3F3}\u{FE0F}\u{200D}\u{1F308}";
+        assert_eq!(plain_text(&format!("pw-{}{}", family, rainbow_flag)), format!("pw-{}{}", family, rainbow_flag));
+        assert_eq!(plain_text("a\u{200D}b\u{1F600}\u{200D}\u{200D}"), "ab\u{1F600}");
+        assert_eq!(plain_code(" 123 456\u{200B}\n"), "123456");
+    }
+
+    #[test]
+    fn test_autotype_literal_types_back_verbatim() {
+        let password = "p{a}s{{TAB}}\u{200D}";
+        let escaped = autotype_literal(password);
+        assert_eq!(escaped, "p{{}a{}}s{{}{{}TAB{}}{}}");
+        let actions = autotype::expand(&escaped, &VaultEntry::default(), None).unwrap();
+        assert_eq!(actions, vec![AutotypeAction::Text { value: "p{a}s{{TAB}}".to_string() }]);
+
+        let actions = autotype::expand(&autotype_literal("a\tb"), &VaultEntry::default(), None).unwrap();
+        assert_eq!(actions[1], AutotypeAction::Key { key: "Tab".to_string() });
+    }
+}
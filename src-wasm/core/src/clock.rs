@@ -0,0 +1,13 @@
+// --- Clock ---
+// The time the engine's own checks run on: the export token's lifetime and
+// throttle, and the reprompt window. They read it here rather than take a time
+// from the caller, so a script holding the bridge can't pass one that keeps a
+// token alive or a window open. In the browser this is Date.now(); the system
+// clock elsewhere.
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the Unix epoch; 0 on a clock set before it.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
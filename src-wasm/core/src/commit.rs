@@ -0,0 +1,170 @@
+// --- Atomic Commits ---
+// One operation often writes several records: a password change replaces the
+// key envelope and the biometric wrappings, a save replaces the vault and the
+// manifest. Half of such a write is worse than none, so they travel together
+// as a CommitBundle: the records, then a commit record (under "commit/0")
+// listing every chunk written and a SHA-256 checksum over them.
+//
+// JS writes a bundle in one IndexedDB transaction, commit record last. On a
+// store without transactions, or after a crash between two of them, the
+// stored chunks no longer match the stored commit; 'verify' says so, and which
+// records differ, before anything is decrypted from a mix of old and new.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::storage::{self, Manifest, RecordKind, StoredRecord};
+
+pub const COMMIT_KEY: &str = "commit";
+const CHECKSUM_CONTEXT: &[u8] = b"securepass-commit-v1";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Commit {
+    /// One more than the commit it follows.
+    pub serial: u64,
+    pub created_at: u64,
+    /// Chunk keys written, sorted; the commit record itself is not listed.
+    pub keys: Vec<String>,
+    /// Hex SHA-256 over the serial and every listed chunk's key and value.
+    pub checksum: String,
+}
+
+/// Every record an operation writes, commit record last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitBundle {
+    pub commit: Commit,
+    pub records: Vec<StoredRecord>,
+}
+
+impl CommitBundle {
+    /// Bundles `records` with an updated manifest (see 'storage::update_manifest')
+    /// and the commit record. `serial` follows the last verified commit.
+    pub fn new(
+        serial: u64,
+        mut records: Vec<StoredRecord>,
+        manifest: Option<&Manifest>,
+        now: u64,
+    ) -> Result<CommitBundle, String> {
+        if records.iter().any(|r| is_reserved(&r.key)) {
+            return Err("Commit error: the manifest and commit records are written by the bundle".to_string());
+        }
+        records.extend(storage::update_manifest(manifest, &records, now)?);
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+        if records.windows(2).any(|pair| pair[0].key == pair[1].key) {
+            return Err("Commit error: a chunk is written twice".to_string());
+        }
+
+        let chunks: Vec<(&str, &[u8])> = records.iter().map(|r| (r.key.as_str(), r.value.as_slice())).collect();
+        let commit = Commit {
+            serial,
+            created_at: now,
+            keys: records.iter().map(|r| r.key.clone()).collect(),
+            checksum: checksum(serial, &chunks),
+        };
+        let json = serde_json::to_vec(&commit).map_err(|e| format!("Commit serialize error: {}", e))?;
+        records.extend(storage::encode_record(COMMIT_KEY, RecordKind::Commit, false, &json)?);
+        Ok(CommitBundle { commit, records })
+    }
+}
+
+/// Checks the stored commit against the store (chunk key -> value, as read back).
+/// Returns the commit, or an error naming the records a partial write left behind.
+pub fn verify(stored: &BTreeMap<String, Vec<u8>>) -> Result<Commit, String> {
+    let commit = read_commit(stored)?;
+    let mut chunks = Vec::with_capacity(commit.keys.len());
+    let mut damaged: Vec<&str> = Vec::new();
+    for key in &commit.keys {
+        match stored.get(key) {
+            Some(value) => chunks.push((key.as_str(), value.as_slice())),
+            None => damaged.push(record_key(key)),
+        }
+    }
+    if damaged.is_empty() && checksum(commit.serial, &chunks) == commit.checksum {
+        return Ok(commit);
+    }
+
+    // Which records changed can't be told from one checksum; list those whose
+    // chunks are missing, or failing that every record the commit wrote.
+    if damaged.is_empty() {
+        damaged = commit.keys.iter().map(|k| record_key(k)).collect();
+    }
+    damaged.dedup();
+    Err(format!("Storage error: commit {} is incomplete ({})", commit.serial, damaged.join(", ")))
+}
+
+fn read_commit(stored: &BTreeMap<String, Vec<u8>>) -> Result<Commit, String> {
+    let chunks: Vec<Vec<u8>> =
+        (0..=u16::MAX).map_while(|i| stored.get(&storage::chunk_key(COMMIT_KEY, i)).cloned()).collect();
+    if chunks.is_empty() {
+        return Err("Storage error: no commit record".to_string());
+    }
+    let record = storage::decode_record(&chunks)?;
+    if record.kind != RecordKind::Commit {
+        return Err("Storage error: not a commit record".to_string());
+    }
+    serde_json::from_slice(&record.payload).map_err(|e| format!("Commit parse error: {}", e))
+}
+
+fn checksum(serial: u64, chunks: &[(&str, &[u8])]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(CHECKSUM_CONTEXT);
+    hasher.update(serial.to_le_bytes());
+    for (key, value) in chunks {
+        hasher.update((key.len() as u64).to_le_bytes());
+        hasher.update(key.as_bytes());
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn record_key(chunk_key: &str) -> &str {
+    chunk_key.rsplit_once('/').map_or(chunk_key, |(key, _)| key)
+}
+
+fn is_reserved(chunk_key: &str) -> bool {
+    matches!(record_key(chunk_key), storage::MANIFEST_KEY | COMMIT_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(records: &[StoredRecord]) -> BTreeMap<String, Vec<u8>> {
+        records.iter().map(|r| (r.key.clone(), r.value.clone())).collect()
+    }
+
+    #[test]
+    fn test_complete_bundle_verifies() {
+        let mut records = storage::encode_record("envelope", RecordKind::Settings, false, b"{}").unwrap();
+        records.extend(storage::encode_record("att-1", RecordKind::Attachment, true, &[1u8; 40]).unwrap());
+        let bundle = CommitBundle::new(3, records, None, 1_700_000_000).unwrap();
+        assert_eq!(bundle.records.last().unwrap().key, "commit/0");
+        assert_eq!(bundle.commit.keys, ["att-1/0", "envelope/0", "manifest/0"]);
+
+        let commit = verify(&store(&bundle.records)).unwrap();
+        assert_eq!(commit, bundle.commit);
+        let manifest = storage::decode_manifest(&[store(&bundle.records)["manifest/0"].clone()]).unwrap();
+        assert_eq!(manifest.records.len(), 2);
+    }
+
+    #[test]
+    fn test_partial_write_is_reported() {
+        let envelope = |json: &[u8]| storage::encode_record("envelope", RecordKind::Settings, false, json).unwrap();
+        let old = CommitBundle::new(1, envelope(b"old"), None, 1).unwrap();
+        let new = CommitBundle::new(2, envelope(b"new"), None, 2).unwrap();
+
+        // The new envelope landed, the new commit didn't.
+        let mut stored = store(&old.records);
+        stored.insert("envelope/0".to_string(), new.records[0].value.clone());
+        assert!(verify(&stored).unwrap_err().contains("commit 1 is incomplete"));
+
+        stored.remove("envelope/0");
+        assert!(verify(&stored).unwrap_err().ends_with("(envelope)"));
+        assert!(verify(&BTreeMap::new()).unwrap_err().contains("no commit"));
+        let manifest = storage::encode_manifest(&[], 0).unwrap();
+        assert!(CommitBundle::new(1, manifest, None, 0).unwrap_err().contains("written by the bundle"));
+    }
+}
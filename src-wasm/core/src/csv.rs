@@ -0,0 +1,49 @@
+// --- CSV Reading ---
+// RFC 4180, as password managers and browsers write it: cells separated by
+// commas, rows by LF or CRLF; a quoted cell may hold commas, line breaks and
+// quotes (doubled). Shared by the CSV importers.
+
+/// Every row of `text`, without a leading byte order mark.
+pub(crate) fn rows(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let (mut row, mut cell) = (Vec::new(), String::new());
+    let mut chars = text.strip_prefix('\u{feff}').unwrap_or(text).chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if cell.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut cell)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err("CSV error: a quoted cell is never closed".to_string());
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_cells() {
+        let parsed = rows("\u{feff}a,\"b,\"\"c\"\"\"\r\n\"multi\nline\",\n\nlast").unwrap();
+        assert_eq!(parsed, vec![vec!["a", "b,\"c\""], vec!["multi\nline", ""], vec![""], vec!["last"]]);
+        assert!(rows("\"open").unwrap_err().contains("never closed"));
+    }
+}
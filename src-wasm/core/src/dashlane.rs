@@ -0,0 +1,193 @@
+// --- Dashlane Import ---
+// Dashlane's CSV export is a zip of one file per item kind; each is imported on
+// its own and recognized by its header:
+//   credentials.csv  username,username2,username3,title,password,note,url,category,otpUrl
+//                    (older exports: otpSecret instead of otpUrl)
+//   securenotes.csv  title,note[,category]
+//   payments.csv     type,account_name,account_holder,cc_number,code,expiration_month,
+//                    expiration_year,...  (only "payment_card" rows are cards)
+// Its older JSON export holds everything in one object of arrays:
+//   AUTHENTIFIANT            {title, domain, login, email, secondaryLogin, password, note}
+//   SECURENOTE               {title, content}
+//   PAYMENTMEANS_CREDITCARD  {name, owner, cardNumber, securityCode, expireMonth, expireYear}
+// Second and third usernames become custom fields; cards become entries of the
+// credit card template.
+
+use serde_json::Value;
+
+use crate::import::{Imported, Importer};
+use crate::vault::{new_entry_id, CustomField, FieldKind, VaultEntry};
+use crate::{card, templates};
+
+/// The Dashlane importer, for CSV and JSON alike.
+pub struct Dashlane;
+
+impl Importer for Dashlane {
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String> {
+        if data.trim_start().starts_with('{') {
+            parse_json(data, now)
+        } else {
+            parse_csv(data, now)
+        }
+    }
+}
+
+fn login(title: String, url: String, username: String, password: String, notes: String, now: u64) -> VaultEntry {
+    VaultEntry { id: new_entry_id(), title, url, username, password, notes, last_modified: now, ..Default::default() }
+}
+
+fn note(title: String, notes: String, now: u64) -> VaultEntry {
+    VaultEntry { id: new_entry_id(), entry_type: "note".to_string(), title, notes, last_modified: now, ..Default::default() }
+}
+
+fn card_entry(title: &str, holder: String, number: String, code: String, month: &str, year: &str, now: u64) -> VaultEntry {
+    let mut entry = templates::find("credit_card").map(|t| t.instantiate(title, now)).unwrap_or_default();
+    let expiry = match (month.trim().parse::<u32>(), year.trim()) {
+        (Ok(month), year) if year.len() >= 2 => format!("{:02}/{}", month, &year[year.len() - 2..]),
+        _ => String::new(),
+    };
+    let values = [(card::CARDHOLDER_FIELD, holder), (card::NUMBER_FIELD, number), (card::CVV_FIELD, code)];
+    for (name, value) in values.into_iter().chain([(card::EXPIRY_FIELD, expiry)]) {
+        if let Some(field) = entry.fields.iter_mut().find(|f| f.name == name) {
+            field.value = value;
+        }
+    }
+    entry
+}
+
+fn alternate_usernames(entry: &mut VaultEntry, usernames: impl IntoIterator<Item = String>) {
+    for username in usernames.into_iter().filter(|u| !u.is_empty() && *u != entry.username) {
+        entry.fields.push(CustomField { name: "Alternate username".to_string(), kind: FieldKind::Text, value: username });
+    }
+}
+
+/// Reads one of the CSV files of a Dashlane export.
+pub fn parse_csv(csv: &str, now: u64) -> Result<Imported, String> {
+    let mut rows = crate::csv::rows(csv)?.into_iter();
+    let header = rows.next().ok_or("Dashlane import error: the file is empty")?;
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let kind = if column("password").is_some() && column("username").is_some() {
+        "credentials"
+    } else if column("cc_number").is_some() {
+        "payments"
+    } else if column("title").is_some() && column("note").is_some() {
+        "securenotes"
+    } else {
+        return Err("Dashlane import error: not a Dashlane credentials, secure notes or payments CSV".to_string());
+    };
+    let names = [
+        "title", "url", "username", "username2", "username3", "password", "note", "category", "otpUrl", "otpSecret", "type",
+        "account_name", "account_holder", "cc_number", "code", "expiration_month", "expiration_year",
+    ];
+    let columns: Vec<Option<usize>> = names.iter().map(|name| column(name)).collect();
+
+    let mut import = Imported::new("dashlane");
+    for mut row in rows {
+        if row.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let mut take = |name: &str| {
+            let i = names.iter().position(|n| *n == name).and_then(|i| columns[i]);
+            i.and_then(|c| row.get_mut(c)).map(std::mem::take).unwrap_or_default()
+        };
+        let (title, category) = (take("title"), take("category"));
+        let mut entry = match kind {
+            "credentials" => {
+                let (url, username, password, notes) = (take("url"), take("username"), take("password"), take("note"));
+                let totp = [take("otpUrl"), take("otpSecret")].into_iter().find(|t| !t.is_empty());
+                let mut entry = login(title, url, username, password, notes, now);
+                entry.totp_secret = totp;
+                alternate_usernames(&mut entry, [take("username2"), take("username3")]);
+                entry
+            }
+            "securenotes" => note(title, take("note"), now),
+            _ if take("type") != "payment_card" => continue,
+            _ => {
+                let name = take("account_name");
+                let (month, year) = (take("expiration_month"), take("expiration_year"));
+                card_entry(&name, take("account_holder"), take("cc_number"), take("code"), &month, &year, now)
+            }
+        };
+        import.add_folder(&category);
+        entry.category = category;
+        import.entries.push(entry);
+    }
+    Ok(import)
+}
+
+/// Reads Dashlane's JSON export.
+pub fn parse_json(json: &str, now: u64) -> Result<Imported, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| crate::redact::json_error("Dashlane parse", &e))?;
+    let list = |key: &str| value.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    if ["AUTHENTIFIANT", "SECURENOTE", "PAYMENTMEANS_CREDITCARD"].iter().all(|key| value.get(key).is_none()) {
+        return Err("Dashlane import error: not a Dashlane JSON export".to_string());
+    }
+    let text = |item: &Value, key: &str| item.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+
+    let mut import = Imported::new("dashlane");
+    for item in list("AUTHENTIFIANT") {
+        let (login_name, email) = (text(item, "login"), text(item, "email"));
+        let username = if login_name.is_empty() { email.clone() } else { login_name };
+        let mut entry =
+            login(text(item, "title"), text(item, "domain"), username, text(item, "password"), text(item, "note"), now);
+        alternate_usernames(&mut entry, [email, text(item, "secondaryLogin")]);
+        import.entries.push(entry);
+    }
+    for item in list("SECURENOTE") {
+        import.entries.push(note(text(item, "title"), text(item, "content"), now));
+    }
+    for item in list("PAYMENTMEANS_CREDITCARD") {
+        let (month, year) = (text(item, "expireMonth"), text(item, "expireYear"));
+        let (holder, number, code) = (text(item, "owner"), text(item, "cardNumber"), text(item, "securityCode"));
+        import.entries.push(card_entry(&text(item, "name"), holder, number, code, &month, &year, now));
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field<'a>(entry: &'a VaultEntry, name: &str) -> &'a str {
+        entry.fields.iter().find(|f| f.name == name).map_or("", |f| f.value.as_str())
+    }
+
+    #[test]
+    fn test_csv_files() {
+        let credentials = "username,username2,username3,title,password,note,url,category,otpUrl\n\
+            me,me2,,GitHub,pw,,https://github.com,Work,otpauth://totp/GitHub?secret=JBSWY3DPEHPK3PXP\n";
+        let import = Dashlane.import(credentials, 3).unwrap();
+        let github = &import.entries[0];
+        assert_eq!((github.title.as_str(), github.username.as_str(), github.category.as_str()), ("GitHub", "me", "Work"));
+        assert!(github.totp_secret.as_deref().unwrap().starts_with("otpauth://"));
+        assert_eq!(field(github, "Alternate username"), "me2");
+        assert_eq!(import.folders, vec!["Work"]);
+
+        let notes = Dashlane.import("title,note\nWi-Fi,\"door code\n1234\"\n", 3).unwrap();
+        assert_eq!((notes.entries[0].kind(), notes.entries[0].notes.as_str()), ("note", "door code\n1234"));
+
+        let payments = "type,account_name,account_holder,cc_number,code,expiration_month,expiration_year\n\
+            payment_card,Visa,Jo Doe,4242424242424242,123,3,2027\nbank,Checking,Jo Doe,,,,\n";
+        let cards = Dashlane.import(payments, 3).unwrap();
+        assert_eq!(cards.entries.len(), 1);
+        assert_eq!((cards.entries[0].kind(), field(&cards.entries[0], card::EXPIRY_FIELD)), ("card", "03/27"));
+        assert!(Dashlane.import("a,b\n1,2\n", 0).is_err());
+    }
+
+    #[test]
+    fn test_json_export() {
+        let json = r#"{
+            "AUTHENTIFIANT": [{"title": "Mail", "domain": "mail.example", "login": "", "email": "me@mail.example",
+                               "password": "pw"}],
+            "SECURENOTE": [{"title": "Seed", "content": "zoo"}],
+            "PAYMENTMEANS_CREDITCARD": [{"name": "Amex", "owner": "Jo", "cardNumber": "378282246310005",
+                                         "expireMonth": "12", "expireYear": "2030"}]
+        }"#;
+        let import = Dashlane.import(json, 0).unwrap();
+        assert_eq!(import.entries.len(), 3);
+        assert_eq!((import.entries[0].username.as_str(), import.entries[0].fields.len()), ("me@mail.example", 0));
+        assert_eq!(import.entries[1].notes, "zoo");
+        assert_eq!(field(&import.entries[2], card::NUMBER_FIELD), "378282246310005");
+        assert!(Dashlane.import("{}", 0).unwrap_err().contains("not a Dashlane"));
+    }
+}
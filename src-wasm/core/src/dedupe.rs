@@ -0,0 +1,253 @@
+// --- Duplicate Entries ---
+// Imports (and years of saving the same login from different pages) leave a
+// vault with several entries for one account. 'find_duplicates' groups logins
+// by site and username; 'merge' folds a group into its first entry:
+//   - the password is chosen by the strategy, and every other password the group
+//     had goes into the history, so none is lost;
+//   - username, URL and TOTP come from the first entry that has them;
+//   - notes, custom fields and attachments are the union of the group's;
+//   - flags are kept if any entry had them (favorite, travel mode, reprompt).
+// Entries under an extra lock are left out: their secrets can't be compared.
+
+use serde::Serialize;
+use url::Url;
+
+use crate::extra_lock;
+use crate::strength::Strength;
+use crate::vault::VaultEntry;
+
+/// Logins that look like the same account.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// "host username", for display.
+    pub key: String,
+    pub ids: Vec<String>,
+    /// True when the group's passwords are not all the same.
+    pub passwords_differ: bool,
+}
+
+/// Which of the group's passwords the merged entry keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// The strongest by 'Strength', then the most recently modified.
+    Strongest,
+    /// The most recently modified entry's.
+    Newest,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Result<Strategy, String> {
+        match name {
+            "strongest" => Ok(Strategy::Strongest),
+            "newest" => Ok(Strategy::Newest),
+            _ => Err(format!("Merge error: unknown strategy '{}'", name)),
+        }
+    }
+}
+
+/// The host, lowercased, without a trailing dot or a leading "www.", with the port
+/// if it isn't the default one.
+fn site(url: &str) -> Option<String> {
+    let parsed = Url::parse(url.trim()).ok()?;
+    let host = parsed.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    let host = host.strip_prefix("www.").map(str::to_string).unwrap_or(host);
+    Some(match parsed.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    })
+}
+
+fn key(entry: &VaultEntry) -> Option<String> {
+    if entry.kind() != "login" || extra_lock::is_locked(entry) {
+        return None;
+    }
+    Some(format!("{} {}", site(&entry.url)?, entry.username.trim().to_lowercase()))
+}
+
+/// Groups of two or more logins with the same site and username, in vault order.
+pub fn find_duplicates(entries: &[VaultEntry]) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<(String, Vec<&VaultEntry>)> = Vec::new();
+    for entry in entries {
+        let Some(key) = key(entry) else { continue };
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, members)) => members.push(entry),
+            None => groups.push((key, vec![entry])),
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(key, members)| DuplicateGroup {
+            key,
+            passwords_differ: members.iter().any(|m| m.password != members[0].password),
+            ids: members.iter().map(|m| m.id.clone()).collect(),
+        })
+        .collect()
+}
+
+/// Folds the entries with the given IDs into the first of them and removes the
+/// rest (wiped). They must all be unlocked entries of the same type.
+pub fn merge(entries: &mut Vec<VaultEntry>, ids: &[String], strategy: Strategy, now: u64) -> Result<(), String> {
+    if ids.len() < 2 {
+        return Err("Merge error: choose at least two entries".to_string());
+    }
+    let mut positions = Vec::new();
+    for id in ids {
+        let position = entries.iter().position(|e| e.id == *id).ok_or_else(|| format!("Merge error: no entry '{}'", id))?;
+        if positions.contains(&position) {
+            return Err(format!("Merge error: entry '{}' is listed twice", id));
+        }
+        if extra_lock::is_locked(&entries[position]) {
+            return Err(format!("Merge error: '{}' is under an extra lock", entries[position].title));
+        }
+        if entries[position].kind() != entries[positions.first().copied().unwrap_or(position)].kind() {
+            return Err("Merge error: the entries are of different types".to_string());
+        }
+        positions.push(position);
+    }
+
+    let mut others = Vec::new();
+    for id in &ids[1..] {
+        let position = entries.iter().position(|e| e.id == *id).ok_or("Merge error: entry not found")?;
+        others.push(entries.remove(position));
+    }
+    let merged = entries.iter_mut().find(|e| e.id == ids[0]).ok_or("Merge error: entry not found")?;
+
+    let group: Vec<&VaultEntry> = std::iter::once(&*merged).chain(&others).collect();
+    let score = |e: &VaultEntry| match strategy {
+        Strategy::Strongest => (Some(Strength::of(&e.password)).filter(|_| !e.password.is_empty()), e.last_modified),
+        Strategy::Newest => (None, e.last_modified),
+    };
+    // max_by_key keeps the last of equals, so going backwards ties go to the earlier entry.
+    let winner = group.iter().rev().max_by_key(|e| score(e)).map(|e| e.password.clone()).unwrap_or_default();
+
+    for mut other in others {
+        absorb(merged, &mut other);
+        other.wipe();
+    }
+    if merged.password != winner {
+        let old = std::mem::replace(&mut merged.password, winner);
+        merged.history.insert(0, old);
+    }
+    let mut history: Vec<String> = Vec::new();
+    for old in std::mem::take(&mut merged.history) {
+        if !old.is_empty() && old != merged.password && !history.contains(&old) {
+            history.push(old);
+        }
+    }
+    merged.history = history;
+    merged.last_modified = now;
+    Ok(())
+}
+
+/// Moves what 'other' adds into 'merged'; its password goes to the history.
+fn absorb(merged: &mut VaultEntry, other: &mut VaultEntry) {
+    fn fill(into: &mut String, from: &mut String) {
+        if into.is_empty() {
+            std::mem::swap(into, from);
+        }
+    }
+    fill(&mut merged.username, &mut other.username);
+    fill(&mut merged.url, &mut other.url);
+    if merged.totp_secret.is_none() {
+        merged.totp_secret = other.totp_secret.take();
+    }
+    if merged.yubikey.is_none() {
+        merged.yubikey = other.yubikey.take();
+    }
+    if !other.notes.is_empty() && !merged.notes.contains(other.notes.as_str()) {
+        if !merged.notes.is_empty() {
+            merged.notes.push_str("\n\n");
+        }
+        merged.notes.push_str(&other.notes);
+    }
+    merged.history.push(other.password.clone());
+    merged.history.append(&mut other.history);
+    for field in other.fields.drain(..) {
+        if !merged.fields.iter().any(|f| f.name == field.name && f.value == field.value) {
+            merged.fields.push(field);
+        }
+    }
+    for attachment in other.attachments.drain(..) {
+        if !merged.attachments.iter().any(|a| a.id == attachment.id) {
+            merged.attachments.push(attachment);
+        }
+    }
+    for (key, value) in std::mem::take(&mut other.extra) {
+        merged.extra.entry(key).or_insert(value);
+    }
+    merged.favorite |= other.favorite;
+    merged.travel_mode |= other.travel_mode;
+    merged.reprompt |= other.reprompt;
+    merged.last_used = merged.last_used.max(other.last_used);
+    merged.use_count = merged.use_count.saturating_add(other.use_count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{CustomField, FieldKind};
+
+    fn login(id: &str, url: &str, username: &str, password: &str, last_modified: u64) -> VaultEntry {
+        VaultEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            url: url.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            last_modified,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let entries = vec![
+            login("a", "https://www.GitHub.com/login", "Octo", "pw", 1),
+            login("b", "http://github.com.", "octo ", "pw", 2),
+            login("c", "https://github.com", "other", "pw", 3),
+            login("d", "https://github.com:8443", "octo", "pw", 4),
+            login("e", "https://github.com/settings", "octo", "Different-1", 5),
+            login("f", "", "octo", "pw", 6),
+        ];
+        let groups = find_duplicates(&entries);
+        assert_eq!(groups.len(), 1);
+        assert_eq!((groups[0].key.as_str(), groups[0].ids.clone()), ("github.com octo", vec!["a".into(), "b".into(), "e".into()]));
+        assert!(groups[0].passwords_differ);
+    }
+
+    #[test]
+    fn test_merge_keeps_strongest_and_history() {
+        let mut first = login("a", "https://github.com", "octo", "hunter2", 10);
+        first.history = vec!["older".to_string()];
+        first.fields.push(CustomField { name: "PIN".into(), kind: FieldKind::Hidden, value: "1234".into() });
+        let mut second = login("b", "https://github.com", "octo", "c0rrect-Horse-battery-staple!", 5);
+        second.notes = "recovery codes in the safe".into();
+        second.totp_secret = Some("JBSWY3DPEHPK3PXP".into());
+        second.fields.push(CustomField { name: "PIN".into(), kind: FieldKind::Hidden, value: "1234".into() });
+        second.favorite = true;
+        let mut entries = vec![login("z", "https://other.example", "me", "x", 1), second, first];
+
+        merge(&mut entries, &["a".to_string(), "b".to_string()], Strategy::Strongest, 99).unwrap();
+        assert_eq!(entries.len(), 2);
+        let merged = &entries[1];
+        assert_eq!((merged.id.as_str(), merged.password.as_str()), ("a", "c0rrect-Horse-battery-staple!"));
+        assert_eq!(merged.history, vec!["hunter2", "older"]);
+        assert_eq!(merged.totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!(merged.notes, "recovery codes in the safe");
+        assert_eq!((merged.fields.len(), merged.favorite, merged.last_modified), (1, true, 99));
+    }
+
+    #[test]
+    fn test_merge_newest_and_refusals() {
+        let mut entries = vec![login("a", "https://x.example", "me", "old", 1), login("b", "https://x.example", "me", "new", 2)];
+        merge(&mut entries, &["a".to_string(), "b".to_string()], Strategy::Newest, 3).unwrap();
+        assert_eq!((entries.len(), entries[0].password.as_str(), entries[0].history.clone()), (1, "new", vec!["old".to_string()]));
+
+        assert!(merge(&mut entries, &["a".to_string()], Strategy::Newest, 3).unwrap_err().contains("at least two"));
+        assert!(merge(&mut entries, &["a".to_string(), "a".to_string()], Strategy::Newest, 3).unwrap_err().contains("twice"));
+        assert!(merge(&mut entries, &["a".to_string(), "q".to_string()], Strategy::Newest, 3).unwrap_err().contains("no entry"));
+        assert!(Strategy::parse("longest").is_err());
+    }
+}
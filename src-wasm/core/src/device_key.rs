@@ -0,0 +1,108 @@
+// --- Browser-Bound Device Key ---
+// Optionally ties a vault to one browser profile. The front end keeps a
+// non-extractable WebCrypto AES-GCM key in IndexedDB; script can use it but can't
+// read it, and it never leaves the profile. At unlock the front end encrypts
+// this binding's random challenge with that key and hands the output over, and
+// it is mixed into the Argon2id master key:
+//
+//   bound key = HKDF-SHA256(salt = device output, ikm = master key,
+//                           info = PURPOSE || 0x00 || challenge)
+//
+// The vault is then sealed under the bound key, so a copy of the site's storage
+// (or a backup file) can't be opened with the password alone. The flip side:
+// clearing the profile loses the key, so a bound vault needs an export that
+// isn't bound (age, OpenPGP, paper) to be recoverable.
+
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub(crate) const PURPOSE: &str = "securepass-device-bound-v1";
+
+pub const CHALLENGE_LEN: usize = 32;
+/// AES-GCM output over the challenge: the ciphertext plus a 16-byte tag.
+pub const RESPONSE_LEN: usize = CHALLENGE_LEN + 16;
+
+const FORMAT_VERSION: u8 = 1;
+
+/// What the front end stores next to the vault's salt. Not secret: the IV and
+/// challenge to encrypt with the profile's WebCrypto key. Byte arrays, like the
+/// vault package, so they go straight into `crypto.subtle.encrypt`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeviceBinding {
+    pub version: u8,
+    pub iv: Vec<u8>,
+    pub challenge: Vec<u8>,
+}
+
+impl DeviceBinding {
+    /// A fresh binding. Create it together with the WebCrypto key it will be used with.
+    pub fn generate() -> DeviceBinding {
+        let mut iv = vec![0u8; 12];
+        let mut challenge = vec![0u8; CHALLENGE_LEN];
+        OsRng.fill_bytes(&mut iv);
+        OsRng.fill_bytes(&mut challenge);
+        DeviceBinding { version: FORMAT_VERSION, iv, challenge }
+    }
+
+    /// Mixes the device output into `master_key`, giving the key the vault is sealed under.
+    pub fn bind(&self, master_key: &[u8; 32], response: &[u8]) -> Result<[u8; 32], String> {
+        if self.version != FORMAT_VERSION {
+            return Err(format!("Unsupported device binding version: {}", self.version));
+        }
+        if self.iv.len() != 12 || self.challenge.len() != CHALLENGE_LEN {
+            return Err("Device key error: damaged binding".to_string());
+        }
+        if response.len() != RESPONSE_LEN {
+            return Err(format!(
+                "Device key error: expected {} bytes of AES-GCM output, got {}",
+                RESPONSE_LEN,
+                response.len()
+            ));
+        }
+
+        let mut info = Vec::with_capacity(PURPOSE.len() + 1 + CHALLENGE_LEN);
+        info.extend_from_slice(PURPOSE.as_bytes());
+        info.push(0);
+        info.extend_from_slice(&self.challenge);
+
+        let mut bound = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(response), master_key)
+            .expand(&info, &mut bound)
+            .map_err(|e| format!("HKDF error: {}", e))?;
+        Ok(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::{
+        aead::{Aead, KeyInit},
+        Aes256Gcm,
+    };
+
+    /// What `crypto.subtle.encrypt({name: "AES-GCM", iv}, key, challenge)` returns.
+    fn webcrypto_output(device_key: &[u8; 32], binding: &DeviceBinding) -> Vec<u8> {
+        let cipher = Aes256Gcm::new_from_slice(device_key).unwrap();
+        cipher.encrypt(crate::validate::nonce(&binding.iv).unwrap(), binding.challenge.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_bound_key_needs_the_device_key() {
+        let master = [4u8; 32];
+        let binding = DeviceBinding::generate();
+        let response = webcrypto_output(&[1u8; 32], &binding);
+
+        let bound = binding.bind(&master, &response).unwrap();
+        assert_eq!(binding.bind(&master, &response).unwrap(), bound);
+        assert_ne!(bound, master);
+        assert_ne!(binding.bind(&master, &webcrypto_output(&[2u8; 32], &binding)).unwrap(), bound);
+        assert_ne!(binding.bind(&[5u8; 32], &response).unwrap(), bound);
+
+        assert!(binding.bind(&master, &response[..CHALLENGE_LEN]).is_err());
+        let json = serde_json::to_string(&binding).unwrap();
+        assert_eq!(serde_json::from_str::<DeviceBinding>(&json).unwrap(), binding);
+    }
+}
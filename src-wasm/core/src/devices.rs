@@ -0,0 +1,203 @@
+// --- Device Registry ---
+// Which devices may sync this vault. Each device makes an Ed25519 keypair and
+// keeps the secret; the vault owner enrolls its public key, under the device's
+// sync ID (see 'oplog'), in a device list and signs the list with a key derived
+// from the master key. The signed list is stored next to the sync blobs.
+//
+// A device that joins the list proves it holds a listed key, and from then on
+// the engine only merges ops and snapshots made by listed devices. Storage
+// can't add a device (it can't sign) or bring a revoked one back (each change
+// bumps the serial, and a device refuses a list older than one it has seen).
+// Revoking stops the revoked device's edits from reaching the others; to lock it
+// out of what it could already decrypt, change the master password too.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::emergency::decode_key;
+use crate::keys::derive_subkey;
+use crate::oplog::is_valid_device;
+
+pub const LIST_VERSION: u8 = 1;
+/// Most devices a list holds.
+pub const MAX_DEVICES: usize = 64;
+const SIGNING_PURPOSE: &str = "securepass-device-list-v1";
+/// Prefixed to the list JSON before signing, so the signature can't be replayed elsewhere.
+const SIGNATURE_CONTEXT: &[u8] = b"securepass-device-list-v1";
+
+/// A device's Ed25519 keypair, Base64. The secret never leaves the device.
+#[derive(Serialize, Deserialize)]
+pub struct DeviceKeypair {
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    /// The device's sync ID ('oplog::OpLog::new').
+    pub device_id: String,
+    pub name: String,
+    pub public_key: String,
+    pub enrolled_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeviceList {
+    pub version: u8,
+    /// Bumped by every enrollment and revocation.
+    pub serial: u64,
+    pub devices: Vec<Device>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SignedDeviceList {
+    pub list: DeviceList,
+    pub signature: String,
+}
+
+/// A fresh keypair for a device about to be enrolled.
+pub fn generate_keypair() -> DeviceKeypair {
+    let key = SigningKey::generate(&mut OsRng);
+    DeviceKeypair { public_key: B64.encode(key.verifying_key().as_bytes()), secret_key: B64.encode(key.to_bytes()) }
+}
+
+impl DeviceList {
+    pub fn contains(&self, device_id: &str) -> bool {
+        self.devices.iter().any(|d| d.device_id == device_id)
+    }
+
+    /// Adds a device, or replaces the key and name of one already listed (a reinstall).
+    pub fn enroll(&mut self, device: Device) -> Result<(), String> {
+        if !is_valid_device(&device.device_id) {
+            return Err("Device error: device ID must be 1-64 letters, digits, '-' or '_'".to_string());
+        }
+        VerifyingKey::from_bytes(&decode_key(&device.public_key, "Device public key")?)
+            .map_err(|e| format!("Device public key error: {}", e))?;
+        match self.devices.iter().position(|d| d.device_id == device.device_id) {
+            Some(i) => self.devices[i] = device,
+            None if self.devices.len() >= MAX_DEVICES => {
+                return Err(format!("Device error: at most {} devices", MAX_DEVICES));
+            }
+            None => self.devices.push(device),
+        }
+        self.serial += 1;
+        Ok(())
+    }
+
+    /// Removes a device; false if it wasn't listed.
+    pub fn revoke(&mut self, device_id: &str) -> bool {
+        let before = self.devices.len();
+        self.devices.retain(|d| d.device_id != device_id);
+        let removed = self.devices.len() != before;
+        if removed {
+            self.serial += 1;
+        }
+        removed
+    }
+}
+
+impl Default for DeviceList {
+    fn default() -> Self {
+        DeviceList { version: LIST_VERSION, serial: 0, devices: Vec::new() }
+    }
+}
+
+pub fn sign(master_key: &[u8; 32], list: DeviceList) -> Result<SignedDeviceList, String> {
+    let signature = owner_key(master_key).sign(&message(&list)?);
+    Ok(SignedDeviceList { list, signature: B64.encode(signature.to_bytes()) })
+}
+
+/// Checks the owner's signature and the version; returns the list.
+pub fn verify(master_key: &[u8; 32], signed: &SignedDeviceList) -> Result<DeviceList, String> {
+    if signed.list.version != LIST_VERSION {
+        return Err(format!("Unsupported device list version: {}", signed.list.version));
+    }
+    let signature = B64.decode(&signed.signature).map_err(|e| format!("Signature decode error: {}", e))?;
+    let signature = Signature::from_slice(&signature).map_err(|e| format!("Signature error: {}", e))?;
+    owner_key(master_key)
+        .verifying_key()
+        .verify_strict(&message(&signed.list)?, &signature)
+        .map_err(|_| "Device error: the device list is not signed by this vault".to_string())?;
+    Ok(signed.list.clone())
+}
+
+/// 'verify', then checks that `device_secret_key` belongs to a listed device and
+/// that the list is no older than `known_serial`. Returns the list and that device's ID.
+pub fn join(
+    master_key: &[u8; 32],
+    signed: &SignedDeviceList,
+    device_secret_key: &str,
+    known_serial: u64,
+) -> Result<(DeviceList, String), String> {
+    let list = verify(master_key, signed)?;
+    if list.serial < known_serial {
+        return Err(format!("Device error: device list {} is older than {} seen before", list.serial, known_serial));
+    }
+    let mut secret = decode_key(device_secret_key, "Device secret key")?;
+    let public_key = B64.encode(SigningKey::from_bytes(&secret).verifying_key().as_bytes());
+    secret.zeroize();
+    let device_id = list
+        .devices
+        .iter()
+        .find(|d| d.public_key == public_key)
+        .map(|d| d.device_id.clone())
+        .ok_or("Device error: this device is not enrolled in the vault")?;
+    Ok((list, device_id))
+}
+
+fn message(list: &DeviceList) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(list).map_err(|e| format!("Device list serialize error: {}", e))?;
+    Ok([SIGNATURE_CONTEXT, &json].concat())
+}
+
+fn owner_key(master_key: &[u8; 32]) -> SigningKey {
+    let mut seed = derive_subkey(master_key, SIGNING_PURPOSE, b"");
+    let key = SigningKey::from_bytes(&seed);
+    seed.zeroize();
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [3u8; 32];
+
+    fn device(id: &str, keypair: &DeviceKeypair) -> Device {
+        Device { device_id: id.to_string(), name: id.to_string(), public_key: keypair.public_key.clone(), enrolled_at: 1 }
+    }
+
+    #[test]
+    fn test_only_listed_devices_join() {
+        let (laptop, phone) = (generate_keypair(), generate_keypair());
+        let mut list = DeviceList::default();
+        list.enroll(device("laptop", &laptop)).unwrap();
+        list.enroll(device("phone", &phone)).unwrap();
+        let signed = sign(&KEY, list.clone()).unwrap();
+        assert_eq!(join(&KEY, &signed, &phone.secret_key, 0).unwrap().1, "phone");
+
+        list.revoke("phone");
+        let revoked = sign(&KEY, list).unwrap();
+        assert!(join(&KEY, &revoked, &phone.secret_key, 0).unwrap_err().contains("not enrolled"));
+        assert!(join(&KEY, &signed, &laptop.secret_key, revoked.list.serial).unwrap_err().contains("older"));
+        assert!(join(&[4u8; 32], &revoked, &laptop.secret_key, 0).unwrap_err().contains("not signed"));
+    }
+
+    #[test]
+    fn test_tampered_lists_fail() {
+        let laptop = generate_keypair();
+        let mut list = DeviceList::default();
+        list.enroll(device("laptop", &laptop)).unwrap();
+        let mut signed = sign(&KEY, list).unwrap();
+        signed.list.devices.push(device("intruder", &generate_keypair()));
+        assert!(verify(&KEY, &signed).is_err());
+
+        let mut list = DeviceList::default();
+        assert!(list.enroll(device("bad id!", &laptop)).is_err());
+        assert!(list.enroll(Device { public_key: "AAAA".to_string(), ..device("laptop", &laptop) }).is_err());
+    }
+}
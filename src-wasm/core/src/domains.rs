@@ -0,0 +1,197 @@
+// --- Domain Equivalence ---
+// "Is this page the same site as that entry?" depends on the registrable domain
+// (the part a person can actually buy: example.co.uk, not co.uk). The public
+// suffix list is compiled into the binary by the `psl` crate, so this works offline.
+// A build without the "psl" feature leaves the list out and guesses instead (see
+// 'guess_suffix_labels'): right for .com and .co.uk, wrong for private suffixes
+// like github.io, whose users then count as one site.
+//
+// On top of that, users can declare groups of domains that belong to one service
+// (amazon.com ≈ amazon.de ≈ amazon.co.uk) and the URL matcher treats them as one.
+
+use serde::{Deserialize, Serialize};
+use url::Host;
+
+use crate::autofill::parse_loose;
+
+/// The registrable domain ("eTLD+1") of a host, e.g. "mail.google.co.uk" -> "google.co.uk".
+/// IP addresses and single-label hosts (localhost, intranet names) are returned unchanged.
+pub fn registrable_domain_of_host(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() {
+        return None;
+    }
+    if !host.contains('.') || matches!(Host::parse(&host), Ok(Host::Ipv4(_)) | Ok(Host::Ipv6(_))) {
+        return Some(host);
+    }
+    registrable_part(&host)
+}
+
+#[cfg(feature = "psl")]
+fn registrable_part(host: &str) -> Option<String> {
+    psl::domain_str(host).map(str::to_string)
+}
+
+#[cfg(not(feature = "psl"))]
+fn registrable_part(host: &str) -> Option<String> {
+    let labels: Vec<&str> = host.split('.').collect();
+    let keep = guess_suffix_labels(&labels) + 1;
+    (labels.len() >= keep).then(|| labels[labels.len() - keep..].join("."))
+}
+
+/// Without the list: a two-letter country code under a short second level
+/// ("co.uk", "com.au", "ne.jp") is a two-label suffix, anything else one label.
+#[cfg(not(feature = "psl"))]
+fn guess_suffix_labels(labels: &[&str]) -> usize {
+    match labels {
+        [.., second, tld] if tld.len() == 2 && second.len() <= 3 && labels.len() > 2 => 2,
+        _ => 1,
+    }
+}
+
+/// The registrable domain of a URL. Scheme-less input ("github.com/login") is accepted.
+pub fn registrable_domain(url: &str) -> Option<String> {
+    parse_loose(url)?.host_str().and_then(registrable_domain_of_host)
+}
+
+/// The public suffix part of a host, e.g. "mail.google.co.uk" -> "co.uk".
+pub fn public_suffix(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    #[cfg(feature = "psl")]
+    let suffix = psl::suffix_str(&host).map(str::to_string);
+    #[cfg(not(feature = "psl"))]
+    let suffix = {
+        let labels: Vec<&str> = host.split('.').collect();
+        (!host.is_empty()).then(|| labels[labels.len() - guess_suffix_labels(&labels)..].join("."))
+    };
+    suffix
+}
+
+/// User-defined groups of registrable domains that count as one site.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct EquivalentDomains {
+    groups: Vec<Vec<String>>,
+}
+
+impl EquivalentDomains {
+    /// Builds a normalized set from raw user input: every domain is reduced to its
+    /// registrable form, duplicates are dropped, and overlapping groups are merged.
+    pub fn from_groups(raw: Vec<Vec<String>>) -> Result<EquivalentDomains, String> {
+        let mut set = EquivalentDomains::default();
+        for group in raw {
+            set.add_group(&group)?;
+        }
+        Ok(set)
+    }
+
+    /// Adds a group, merging it into any existing group it shares a domain with.
+    pub fn add_group(&mut self, domains: &[String]) -> Result<(), String> {
+        let mut merged: Vec<String> = Vec::new();
+        for raw in domains {
+            let domain = registrable_domain(raw).ok_or_else(|| format!("Not a valid domain: {}", raw))?;
+            if !merged.contains(&domain) {
+                merged.push(domain);
+            }
+        }
+        if merged.len() < 2 {
+            return Err("An equivalence group needs at least two different domains".to_string());
+        }
+
+        // Pull every overlapping group into the new one so "a≈b" + "b≈c" becomes "a≈b≈c".
+        let mut kept = Vec::with_capacity(self.groups.len());
+        for group in self.groups.drain(..) {
+            if group.iter().any(|d| merged.contains(d)) {
+                for d in group {
+                    if !merged.contains(&d) {
+                        merged.push(d);
+                    }
+                }
+            } else {
+                kept.push(group);
+            }
+        }
+        merged.sort();
+        kept.push(merged);
+        self.groups = kept;
+        Ok(())
+    }
+
+    /// Removes a domain from whichever group holds it; a group left with one domain disappears.
+    pub fn remove_domain(&mut self, domain: &str) {
+        let Some(domain) = registrable_domain(domain) else {
+            return;
+        };
+        for group in &mut self.groups {
+            group.retain(|d| *d != domain);
+        }
+        self.groups.retain(|g| g.len() >= 2);
+    }
+
+    /// The sorted group holding this registrable domain, if any.
+    pub fn group_of(&self, domain: &str) -> Option<&[String]> {
+        self.groups.iter().find(|g| g.iter().any(|d| d == domain)).map(Vec::as_slice)
+    }
+
+    /// True when two registrable domains are the same or declared equivalent.
+    pub fn are_equivalent(&self, a: &str, b: &str) -> bool {
+        a == b
+            || self
+                .groups
+                .iter()
+                .any(|g| g.iter().any(|d| d == a) && g.iter().any(|d| d == b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_registrable_domain() {
+        assert_eq!(registrable_domain("https://mail.google.co.uk/inbox").as_deref(), Some("google.co.uk"));
+        assert_eq!(registrable_domain("github.com").as_deref(), Some("github.com"));
+        if cfg!(feature = "psl") {
+            assert_eq!(registrable_domain("https://user.github.io").as_deref(), Some("user.github.io"));
+        }
+        assert_eq!(registrable_domain("http://192.168.1.1:8080").as_deref(), Some("192.168.1.1"));
+        assert_eq!(registrable_domain("http://localhost:3000").as_deref(), Some("localhost"));
+        assert_eq!(public_suffix("shop.example.com.au").as_deref(), Some("com.au"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "psl"))]
+    fn test_guessed_suffixes() {
+        assert_eq!(registrable_domain("https://user.github.io").as_deref(), Some("github.io"));
+        assert_eq!(registrable_domain("https://a.b.example.ne.jp").as_deref(), Some("example.ne.jp"));
+        assert_eq!(public_suffix("co.uk").as_deref(), Some("uk"));
+        assert_eq!(registrable_domain_of_host("co.uk"), Some("co.uk".to_string()));
+    }
+
+    #[test]
+    fn test_equivalent_groups_merge() {
+        let mut eq = EquivalentDomains::default();
+        eq.add_group(&strings(&["amazon.com", "https://www.amazon.de"])).unwrap();
+        eq.add_group(&strings(&["amazon.de", "amazon.co.uk"])).unwrap();
+
+        assert_eq!(eq.groups.len(), 1);
+        assert!(eq.are_equivalent("amazon.com", "amazon.co.uk"));
+        assert!(!eq.are_equivalent("amazon.com", "ebay.com"));
+
+        eq.remove_domain("amazon.de");
+        eq.remove_domain("amazon.co.uk");
+        assert!(eq.groups.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_groups_rejected() {
+        let mut eq = EquivalentDomains::default();
+        assert!(eq.add_group(&strings(&["example.com"])).is_err());
+        assert!(eq.add_group(&strings(&["example.com", "www.example.com"])).is_err());
+        assert!(eq.add_group(&strings(&["example.com", ""])).is_err());
+    }
+}
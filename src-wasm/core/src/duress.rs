@@ -0,0 +1,181 @@
+// --- Duress / Decoy Vaults ---
+// A second "decoy" password opens a harmless-looking vault instead of the real one.
+// Both vaults share one salt and one storage format, and are stored as an
+// unlabeled list of slots in random order. Unlocking derives one key and tries
+// every slot, so storage, timing and code path look the same whichever password
+// was typed. Someone forced to unlock can hand over the decoy password.
+//
+// Slots are committed to their key (package version 2), so no slot can be made
+// to open under both passwords. A slot list from before commitments (version 1)
+// still opens as plain AES-GCM, and is re-sealed as version 2 on the next save;
+// until then, a slot that says it is version 1 has no such guarantee.
+
+use rand::{rngs::OsRng, seq::SliceRandom, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::keys;
+
+/// Packages from before key commitment: plain AES-GCM, as 'Engine::encrypt' writes
+/// them and as the web app stored vaults until it moved to 'encrypt_package'.
+/// JSON without a version is this one.
+pub const LEGACY_PACKAGE_VERSION: u8 = 1;
+/// Committed to the key ('keys::open_committed').
+pub const PACKAGE_VERSION: u8 = 2;
+
+fn legacy_package_version() -> u8 {
+    LEGACY_PACKAGE_VERSION
+}
+
+/// Same shape as the web app's EncryptedPackage (see src/types.ts).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EncryptedPackage {
+    #[serde(default = "legacy_package_version")]
+    pub version: u8,
+    pub iv: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl EncryptedPackage {
+    /// A package from before key commitment, from a caller that keeps the IV and data apart.
+    pub fn legacy(iv: &[u8], data: &[u8]) -> EncryptedPackage {
+        EncryptedPackage { version: LEGACY_PACKAGE_VERSION, iv: iv.to_vec(), data: data.to_vec() }
+    }
+}
+
+/// Seals a vault JSON under `key` with a fresh IV, committed to the key (see
+/// 'keys::open_committed').
+pub fn seal_package(key: &[u8; 32], vault_json: &str) -> Result<EncryptedPackage, String> {
+    let (iv, data) = keys::seal_committed(key, vault_json.as_bytes(), &[])?;
+    Ok(EncryptedPackage { version: PACKAGE_VERSION, iv: iv.to_vec(), data })
+}
+
+/// Opens a package of either version. A current package must be committed to
+/// `key`; only one that says it is legacy is opened as plain AES-GCM.
+pub fn open_package(key: &[u8; 32], package: &EncryptedPackage) -> Result<Vec<u8>, String> {
+    match package.version {
+        LEGACY_PACKAGE_VERSION => keys::open(key, &package.iv, &package.data, &[]),
+        PACKAGE_VERSION => keys::open_committed(key, &package.iv, &package.data, &[]),
+        other => Err(format!("Unsupported package version: {}", other)),
+    }
+}
+
+/// Builds the slot list for a real and a decoy vault, in random order.
+/// Refuses when both passwords produced the same key (i.e. they were the same password).
+pub fn build_slots(
+    real_key: &[u8; 32],
+    real_vault_json: &str,
+    decoy_key: &[u8; 32],
+    decoy_vault_json: &str,
+) -> Result<Vec<EncryptedPackage>, String> {
+    if real_key == decoy_key {
+        return Err("The decoy password must differ from the master password".to_string());
+    }
+    let mut slots = vec![seal_package(real_key, real_vault_json)?, seal_package(decoy_key, decoy_vault_json)?];
+    slots.shuffle(&mut OsRng);
+    Ok(slots)
+}
+
+/// Tries `key` against every slot and returns the index and plaintext of the one it opens.
+/// Every slot is attempted even after a hit, so the work done doesn't depend on the position.
+pub fn open_slots(key: &[u8; 32], slots: &[EncryptedPackage]) -> Option<(usize, String)> {
+    let mut found = None;
+    for (idx, slot) in slots.iter().enumerate() {
+        let opened = open_package(key, slot).ok();
+        if found.is_none() {
+            found = opened.and_then(|bytes| String::from_utf8(bytes).ok()).map(|json| (idx, json));
+        }
+    }
+    found
+}
+
+/// Re-seals `vault_json` into whichever slot `key` opens, leaving the others untouched,
+/// so saving works the same for the real and the decoy vault.
+pub fn reseal_slot(key: &[u8; 32], slots: &mut [EncryptedPackage], vault_json: &str) -> Result<(), String> {
+    let (idx, mut old) = open_slots(key, slots).ok_or("No vault slot opens with this key")?;
+    old.zeroize();
+    slots[idx] = seal_package(key, vault_json)?;
+    Ok(())
+}
+
+/// Random filler shaped like a slot, for users without a decoy, so the slot count
+/// never reveals whether a decoy exists. It is laid out like a real one: the
+/// plaintext 'keys::COMMITTED_MARKER', then random bytes where the commitment and
+/// the ciphertext would be.
+pub fn filler_slot(len: usize) -> EncryptedPackage {
+    let mut iv = vec![0u8; 12];
+    let mut data = vec![0u8; len + 16 + keys::COMMITMENT_OVERHEAD];
+    OsRng.fill_bytes(&mut iv);
+    data[..keys::COMMITTED_MARKER.len()].copy_from_slice(keys::COMMITTED_MARKER);
+    OsRng.fill_bytes(&mut data[keys::COMMITTED_MARKER.len()..]);
+    EncryptedPackage { version: PACKAGE_VERSION, iv, data }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REAL: [u8; 32] = [1u8; 32];
+    const DECOY: [u8; 32] = [2u8; 32];
+
+    #[test]
+    fn test_each_key_opens_its_own_slot() {
+        let slots = build_slots(&REAL, r#"{"entries":["real"]}"#, &DECOY, r#"{"entries":["decoy"]}"#).unwrap();
+        assert_eq!(slots.len(), 2);
+
+        let (real_idx, real_json) = open_slots(&REAL, &slots).unwrap();
+        let (decoy_idx, decoy_json) = open_slots(&DECOY, &slots).unwrap();
+        assert_ne!(real_idx, decoy_idx);
+        assert!(real_json.contains("real"));
+        assert!(decoy_json.contains("decoy"));
+
+        assert!(open_slots(&[3u8; 32], &slots).is_none());
+    }
+
+    #[test]
+    fn test_reseal_touches_only_own_slot() {
+        let mut slots = build_slots(&REAL, "{}", &DECOY, "{}").unwrap();
+        let (real_idx, _) = open_slots(&REAL, &slots).unwrap();
+        let before = slots.clone();
+
+        reseal_slot(&DECOY, &mut slots, r#"{"entries":["changed"]}"#).unwrap();
+        assert_eq!(slots[real_idx], before[real_idx]);
+        assert!(open_slots(&DECOY, &slots).unwrap().1.contains("changed"));
+        assert!(reseal_slot(&[3u8; 32], &mut slots, "{}").is_err());
+    }
+
+    #[test]
+    fn test_same_password_rejected() {
+        assert!(build_slots(&REAL, "{}", &REAL, "{}").is_err());
+    }
+
+    #[test]
+    fn test_filler_never_opens() {
+        let slots = vec![seal_package(&REAL, "{}").unwrap(), filler_slot(2)];
+        assert_eq!(slots[0].data.len(), slots[1].data.len());
+        assert_eq!((slots[0].iv.len(), slots[0].version), (slots[1].iv.len(), slots[1].version));
+        // Same plaintext marker as a real slot, so nothing tells the two apart.
+        assert!(slots[0].data.starts_with(keys::COMMITTED_MARKER) && slots[1].data.starts_with(keys::COMMITTED_MARKER));
+        assert_eq!(open_slots(&REAL, &slots).unwrap().0, 0);
+        assert!(open_package(&REAL, &slots[1]).is_err() && open_package(&DECOY, &slots[1]).is_err());
+    }
+
+    #[test]
+    fn test_package_versions() {
+        let package = seal_package(&REAL, "{}").unwrap();
+        assert_eq!(open_package(&REAL, &package).unwrap(), b"{}");
+
+        // Relabelled as legacy, a committed package no longer opens.
+        let relabelled = EncryptedPackage { version: LEGACY_PACKAGE_VERSION, ..package.clone() };
+        assert!(open_package(&REAL, &relabelled).is_err());
+        let stripped = EncryptedPackage { data: package.data[keys::COMMITMENT_OVERHEAD..].to_vec(), ..package.clone() };
+        assert!(open_package(&REAL, &stripped).is_err());
+
+        // JSON from before versions is legacy, and opens as plain AES-GCM.
+        let (iv, data) = keys::seal(&REAL, b"{}", &[]).unwrap();
+        let legacy: EncryptedPackage = serde_json::from_str(&serde_json::json!({"iv": iv, "data": data}).to_string()).unwrap();
+        assert_eq!(legacy.version, LEGACY_PACKAGE_VERSION);
+        assert_eq!(open_package(&REAL, &legacy).unwrap(), b"{}");
+        assert!(open_package(&REAL, &EncryptedPackage { version: 3, ..legacy }).unwrap_err().contains("Unsupported"));
+    }
+}
@@ -0,0 +1,275 @@
+// --- Emergency Access ---
+// A vault owner can hand a trusted contact a sealed copy of the master key.
+// The contact can only open it after asking for access and waiting out the
+// owner's chosen delay. The server enforces the clock; this module owns the
+// key wrapping and the exact bytes of the policy that gets signed into it.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::validate;
+
+/// Version tag for the grant format, bumped whenever the wrapping changes.
+pub const GRANT_VERSION: u8 = 1;
+
+/// Domain separation label for the HKDF step.
+pub(crate) const HKDF_INFO: &[u8] = b"securepass-emergency-v1";
+
+/// The contact's X25519 keypair, Base64 encoded for storage in JS.
+#[derive(Serialize, Deserialize)]
+pub struct EmergencyKeypair {
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+/// The rules the owner attached to a grant.
+/// These bytes are authenticated by AES-GCM, so a server can't shorten the wait.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EmergencyPolicy {
+    pub version: u8,
+    pub grant_id: String,
+    pub contact_public_key: String,
+    pub wait_period_secs: u64,
+    pub created_at: u64,
+}
+
+/// What the owner uploads: the policy plus the master key sealed to the contact.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EmergencyGrant {
+    pub policy: EmergencyPolicy,
+    pub ephemeral_public_key: String,
+    pub iv: String,
+    pub wrapped_key: String,
+}
+
+/// What the contact sends to start the clock.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EmergencyRequest {
+    pub grant_id: String,
+    pub requested_at: u64,
+}
+
+/// Creates a fresh keypair for someone who wants to be a trusted contact.
+pub fn generate_keypair() -> EmergencyKeypair {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    EmergencyKeypair {
+        public_key: B64.encode(public.as_bytes()),
+        secret_key: B64.encode(secret.to_bytes()),
+    }
+}
+
+/// Seals the master key so that only the holder of `contact_public_key` can open it.
+pub fn wrap_master_key(
+    master_key: &[u8; 32],
+    contact_public_key: &str,
+    wait_period_secs: u64,
+    now: u64,
+) -> Result<EmergencyGrant, String> {
+    let contact_public = PublicKey::from(decode_key(contact_public_key, "Contact public key")?);
+
+    let mut id_bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut id_bytes);
+    let policy = EmergencyPolicy {
+        version: GRANT_VERSION,
+        grant_id: id_bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        contact_public_key: contact_public_key.to_string(),
+        wait_period_secs,
+        created_at: now,
+    };
+
+    // A one-time keypair means every grant gets its own wrapping key.
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&contact_public);
+
+    let mut wrapping_key = derive_wrapping_key(shared.as_bytes(), &ephemeral_public, &contact_public, HKDF_INFO)?;
+    let mut iv = [0u8; 12];
+    OsRng.fill_bytes(&mut iv);
+
+    let aad = serde_json::to_vec(&policy).map_err(|e| format!("Policy serialize error: {}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(&wrapping_key)
+        .map_err(|e| format!("Cipher init error: {}", e))?;
+    wrapping_key.zeroize();
+
+    let wrapped = cipher
+        .encrypt(&Nonce::from(iv), Payload { msg: master_key, aad: &aad })
+        .map_err(|e| format!("Wrapping error: {}", e))?;
+
+    Ok(EmergencyGrant {
+        policy,
+        ephemeral_public_key: B64.encode(ephemeral_public.as_bytes()),
+        iv: B64.encode(iv),
+        wrapped_key: B64.encode(wrapped),
+    })
+}
+
+/// Builds the request a contact sends when they want to start the waiting period.
+pub fn request_access(grant: &EmergencyGrant, now: u64) -> EmergencyRequest {
+    EmergencyRequest {
+        grant_id: grant.policy.grant_id.clone(),
+        requested_at: now,
+    }
+}
+
+/// Opens a grant once the wait period since `request` has passed.
+pub fn unwrap_master_key(
+    grant: &EmergencyGrant,
+    request: &EmergencyRequest,
+    contact_secret_key: &str,
+    now: u64,
+) -> Result<[u8; 32], String> {
+    if grant.policy.version != GRANT_VERSION {
+        return Err(format!("Unsupported grant version: {}", grant.policy.version));
+    }
+    if request.grant_id != grant.policy.grant_id {
+        return Err("Request does not match this grant".to_string());
+    }
+    let unlock_at = request.requested_at.saturating_add(grant.policy.wait_period_secs);
+    if now < unlock_at {
+        return Err(format!("Wait period not over: {} seconds remaining", unlock_at - now));
+    }
+
+    let mut secret_bytes = decode_key(contact_secret_key, "Contact secret key")?;
+    let secret = StaticSecret::from(secret_bytes);
+    secret_bytes.zeroize();
+
+    let contact_public = PublicKey::from(&secret);
+    if B64.encode(contact_public.as_bytes()) != grant.policy.contact_public_key {
+        return Err("Grant was not issued to this contact".to_string());
+    }
+
+    let ephemeral_public = PublicKey::from(decode_key(&grant.ephemeral_public_key, "Ephemeral key")?);
+    let shared = secret.diffie_hellman(&ephemeral_public);
+    let mut wrapping_key = derive_wrapping_key(shared.as_bytes(), &ephemeral_public, &contact_public, HKDF_INFO)?;
+
+    let iv = B64.decode(&grant.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let nonce = validate::nonce(&iv)?;
+    let wrapped = B64.decode(&grant.wrapped_key).map_err(|e| format!("Key decode error: {}", e))?;
+    let aad = serde_json::to_vec(&grant.policy).map_err(|e| format!("Policy serialize error: {}", e))?;
+
+    let cipher = Aes256Gcm::new_from_slice(&wrapping_key)
+        .map_err(|e| format!("Cipher init error: {}", e))?;
+    wrapping_key.zeroize();
+
+    let mut plain = cipher
+        .decrypt(nonce, Payload { msg: &wrapped, aad: &aad })
+        .map_err(|e| format!("Unwrapping error: {}", e))?;
+
+    if plain.len() != 32 {
+        plain.zeroize();
+        return Err("Unwrapped key has the wrong length".to_string());
+    }
+    let mut master_key = [0u8; 32];
+    master_key.copy_from_slice(&plain);
+    plain.zeroize();
+    Ok(master_key)
+}
+
+/// HKDF over the shared secret, bound to both public keys. `info` separates the
+/// features that seal to an X25519 key (emergency grants, shared entries).
+pub(crate) fn derive_wrapping_key(
+    shared: &[u8],
+    ephemeral: &PublicKey,
+    contact: &PublicKey,
+    info: &[u8],
+) -> Result<[u8; 32], String> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(contact.as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(info, &mut key)
+        .map_err(|e| format!("HKDF error: {}", e))?;
+    Ok(key)
+}
+
+pub(crate) fn decode_key(encoded: &str, label: &str) -> Result<[u8; 32], String> {
+    let bytes = B64.decode(encoded).map_err(|e| format!("{} decode error: {}", label, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("{} must be 32 bytes", label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_grant_roundtrip_after_wait() {
+        let contact = generate_keypair();
+        let grant = wrap_master_key(&KEY, &contact.public_key, 3600, 1_000).unwrap();
+        let request = request_access(&grant, 2_000);
+
+        let key = unwrap_master_key(&grant, &request, &contact.secret_key, 5_600).unwrap();
+        assert_eq!(key, KEY);
+    }
+
+    #[test]
+    fn test_unwrap_refused_before_wait() {
+        let contact = generate_keypair();
+        let grant = wrap_master_key(&KEY, &contact.public_key, 3600, 1_000).unwrap();
+        let request = request_access(&grant, 2_000);
+
+        let err = unwrap_master_key(&grant, &request, &contact.secret_key, 5_599).unwrap_err();
+        assert!(err.contains("1 seconds remaining"));
+    }
+
+    #[test]
+    fn test_tampered_policy_fails() {
+        let contact = generate_keypair();
+        let mut grant = wrap_master_key(&KEY, &contact.public_key, 86_400, 0).unwrap();
+        grant.policy.wait_period_secs = 0;
+        let request = request_access(&grant, 10);
+
+        assert!(unwrap_master_key(&grant, &request, &contact.secret_key, 10).is_err());
+    }
+
+    #[test]
+    fn test_wrong_contact_rejected() {
+        let contact = generate_keypair();
+        let stranger = generate_keypair();
+        let grant = wrap_master_key(&KEY, &contact.public_key, 0, 0).unwrap();
+        let request = request_access(&grant, 0);
+
+        assert!(unwrap_master_key(&grant, &request, &stranger.secret_key, 0).is_err());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_grant_envelope_roundtrip(master in any::<[u8; 32]>(), wait in 0u64..1_000_000, now in 0u64..1_000_000_000) {
+            let contact = generate_keypair();
+            let grant = wrap_master_key(&master, &contact.public_key, wait, now).unwrap();
+            let parsed: EmergencyGrant = serde_json::from_str(&serde_json::to_string(&grant).unwrap()).unwrap();
+            let request = request_access(&parsed, now);
+
+            prop_assert_eq!(unwrap_master_key(&parsed, &request, &contact.secret_key, now + wait).unwrap(), master);
+        }
+
+        #[test]
+        fn prop_mangled_envelope_never_panics(iv in "\\PC{0,24}", wrapped in "\\PC{0,80}", ephemeral in "\\PC{0,48}") {
+            let contact = generate_keypair();
+            let mut grant = wrap_master_key(&KEY, &contact.public_key, 0, 0).unwrap();
+            grant.iv = iv;
+            grant.wrapped_key = wrapped;
+            grant.ephemeral_public_key = ephemeral;
+            let request = request_access(&grant, 0);
+            prop_assert!(unwrap_master_key(&grant, &request, &contact.secret_key, 0).is_err());
+        }
+    }
+}
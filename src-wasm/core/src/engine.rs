@@ -0,0 +1,3039 @@
+// --- Vault Engine ---
+// The unlocked state of one vault: the Argon2id master key plus the decrypted
+// vault once 'load_vault' has been called. Everything a front end does with an
+// open vault goes through here. Structured inputs and outputs are JSON strings,
+// so the Wasm bridge, a CLI and an FFI layer can pass them through unchanged.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm,
+};
+use argon2::Argon2; // Memory-hard key derivation
+#[cfg(feature = "openpgp")]
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use rand::seq::SliceRandom;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize; // Security: physically wipes sensitive data from RAM
+
+use crate::keys::{self, NonceSequence};
+use crate::{
+    age, attachments, audit_log, autofill, autotype, backup_codes, blobs, breach, breached_sites, card, clipboard, clock, dedupe, device_key, devices, domains, duress, emergency, entitlement, export_gate, extra_lock, generator, hierarchy, identity, idle, journal, json_export, kdf, license, listing, otp,
+    oplog, password_change, phishing, pin, redact, reencrypt, reprompt, search_index, segments, settings, sharing, stats, templates, text, throttle, validate, vault, wifi, yubikey,
+};
+#[cfg(feature = "audit")]
+use crate::{audit, health};
+#[cfg(feature = "markdown")]
+use crate::markdown;
+#[cfg(feature = "openpgp")]
+use crate::openpgp;
+#[cfg(feature = "importers")]
+use crate::import::Importer;
+#[cfg(feature = "importers")]
+use crate::{browser_import, google_migration, import, lastpass, otp_export};
+
+/// How many caller-chosen IVs one engine seals with ('encrypt', 'seal_vault').
+const MAX_CALLER_IVS: usize = 1024;
+/// From the calls that need the master password, in a session not opened with it.
+const NO_MASTER_PASSWORD: &str = "Unlock error: this session wasn't opened with the master password";
+
+/// The two encrypted parts of a newly added attachment. Store them separately:
+/// 'meta' is small JSON for lists, 'content' is the (large) encrypted file.
+#[derive(Clone, Debug)]
+pub struct EncryptedAttachment {
+    pub id: String,
+    pub meta: String,
+    pub content: Vec<u8>,
+}
+
+pub struct Engine {
+    pub(crate) master_key: [u8; 32],
+    pub(crate) vault: vault::VaultData,
+    /// From 'from_envelope': the label of an outdated envelope and its replacement,
+    /// held until 'commit_kdf_upgrade'.
+    pending_upgrade: Option<(String, kdf::KeyEnvelope)>,
+    /// From 'join_device_list': once set, sync only merges what listed devices made.
+    devices: Option<devices::DeviceList>,
+    /// Set by 'bind_to_device': the master key is then the bound key, which must not be wrapped.
+    device_bound: bool,
+    /// The envelope the master password opened at unlock ('legacy' for 'new'); None
+    /// for a PIN, emergency or raw-key session. Retyped passwords are checked against
+    /// it, never against one the caller hands in; see 'unlock_retyped'.
+    unlock_envelope: Option<kdf::KeyEnvelope>,
+    /// When reprompt-flagged entries may be revealed; see 'confirm_master'.
+    reprompt: reprompt::RepromptGate,
+    /// Tokens for the two-step export; see 'request_export'.
+    exports: export_gate::ExportGate,
+    /// Unix seconds for the export gate and the reprompt window ('clock::now').
+    clock: fn() -> u64,
+    /// Set by 'go_idle': the loaded entries' secrets, sealed until 'wake'.
+    idle: Option<idle::IdleStore>,
+    /// From 'preview_import': the decrypted import, held until 'apply_import'.
+    #[cfg(feature = "importers")]
+    pending_import: Option<import::PendingImport>,
+    /// Nonces for everything this engine seals under the master key.
+    nonces: NonceSequence,
+    /// IVs callers sealed with through the raw-IV calls, so none is used twice.
+    /// At most MAX_CALLER_IVS; past that, 'encrypt_package' is the way to seal.
+    sealed_ivs: RefCell<BTreeSet<[u8; 12]>>,
+}
+
+// --- Unlock & Encryption ---
+
+impl Engine {
+    /// Derives the vault's master key from the password and its unique salt with Argon2id.
+    /// The password is used exactly as typed ('PasswordPolicy::Raw'); see 'new_with_policy'.
+    pub fn new(password: &str, salt: &[u8]) -> Result<Engine, String> {
+        validate::salt(salt)?;
+        validate::size(password.len(), validate::MAX_PASSWORD_LEN, "password")?;
+        let mut master_key = [0u8; 32];
+        let argon2 = Argon2::default(); // Uses Argon2id (the modern industry standard)
+
+        // This line does the heavy lifting: turning a readable password into raw binary bytes.
+        argon2
+            .hash_password_into(password.as_bytes(), salt, &mut master_key)
+            .map_err(|e| format!("Argon2 error: {}", e))?;
+
+        let mut engine = Self::from_key(master_key);
+        engine.unlock_envelope = Some(kdf::KeyEnvelope::legacy(salt, text::PasswordPolicy::Raw));
+        Ok(engine)
+    }
+
+    /// Like 'new', but the password is first normalized under 'policy', so it derives
+    /// the same key however the keyboard encoded it. Pass the policy stored with the
+    /// vault, or 'PasswordPolicy::CURRENT' when creating one.
+    pub fn new_with_policy(password: &str, salt: &[u8], policy: text::PasswordPolicy) -> Result<Engine, String> {
+        let mut engine = Self::new(&text::normalize_password(password, policy), salt)?;
+        engine.unlock_envelope = Some(kdf::KeyEnvelope::legacy(salt, policy));
+        Ok(engine)
+    }
+
+    /// Wraps an already-derived key in a fresh engine with an empty vault.
+    pub fn from_key(master_key: [u8; 32]) -> Engine {
+        Engine {
+            reprompt: reprompt::RepromptGate::new(&master_key),
+            exports: export_gate::ExportGate::new(&master_key),
+            clock: clock::now,
+            master_key,
+            vault: vault::VaultData::default(),
+            pending_upgrade: None,
+            devices: None,
+            device_bound: false,
+            unlock_envelope: None,
+            idle: None,
+            #[cfg(feature = "importers")]
+            pending_import: None,
+            nonces: NonceSequence::default(),
+            sealed_ivs: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// Like 'new_with_policy', but pays the throttle's current penalty first. The attempt is
+    /// marked pending in 'state'; report the outcome once the key is known to be right or wrong.
+    pub fn new_throttled(
+        password: &str,
+        salt: &[u8],
+        policy: text::PasswordPolicy,
+        state: &mut throttle::ThrottleState,
+    ) -> Result<Engine, String> {
+        validate::salt(salt)?;
+        let rounds = state.begin_attempt();
+        throttle::pay_penalty(password, salt, rounds)?;
+        Self::new_with_policy(password, salt, policy)
+    }
+
+    /// UNLOCK (envelope): Recovers the master key from the vault's key envelope
+    /// ('kdf::KeyEnvelope::legacy' describes vaults saved without one). If the envelope
+    /// needs an upgrade, the password is hashed a second time under the current
+    /// settings to prepare its replacement; see 'commit_kdf_upgrade'.
+    pub fn from_envelope(password: &str, envelope: &kdf::KeyEnvelope) -> Result<Engine, String> {
+        let (master_key, upgraded) = envelope.unlock_upgrading(password)?;
+        let mut engine = Self::from_key(master_key);
+        engine.pending_upgrade = upgraded.map(|upgraded| (envelope.label(), upgraded));
+        engine.unlock_envelope = Some(envelope.clone());
+        Ok(engine)
+    }
+
+    /// UPGRADE: Call once the vault has opened with this engine's key. Returns the
+    /// replacement envelope prepared by 'from_envelope' (None if there is none) and
+    /// records the upgrade in 'log'. The master key is unchanged, so the vault
+    /// needs no re-encryption: save the new envelope, in one write, and the log.
+    pub fn commit_kdf_upgrade(&mut self, log: &mut audit_log::AuditLog, now: u64) -> Option<kdf::KeyEnvelope> {
+        let (from, envelope) = self.pending_upgrade.take()?;
+        log.record(now, audit_log::AuditKind::KdfUpgrade, format!("{} -> {}", from, envelope.label()));
+        self.unlock_envelope = Some(envelope.clone());
+        Some(envelope)
+    }
+
+    /// DEVICE KEY: Replaces the master key with one bound to this browser profile:
+    /// 'response' is the profile's non-extractable WebCrypto key's AES-GCM output over
+    /// the binding's challenge (see 'device_key'). Call it right after deriving the
+    /// key, before loading or sealing the vault; a bound vault only opens this way.
+    pub fn bind_to_device(&mut self, binding_json: &str, response: &[u8]) -> Result<(), String> {
+        let binding: device_key::DeviceBinding =
+            serde_json::from_str(binding_json).map_err(|e| format!("Device binding parse error: {}", e))?;
+        let mut bound = binding.bind(&self.master_key, response)?;
+        self.master_key.zeroize();
+        self.master_key = bound;
+        bound.zeroize();
+        self.device_bound = true;
+        Ok(())
+    }
+
+    /// PASSWORD: Changes the master password without re-encrypting anything. Needs
+    /// the current password ('unlock_retyped'); then the
+    /// master key is wrapped again under `new_password` with `new_salt` and the KDF
+    /// settings in `kdf_json` ("" for the vault's 'settings.kdf'). `credential_ids_json`
+    /// is a JSON array of the Base64 passkey IDs with biometric unlock set up.
+    /// Returns JSON {envelope, biometric: [{credentialId, iv, wrapped}]}: save all
+    /// of it in one write, replacing the old envelope and wrappings. On a
+    /// device-bound vault, call this before 'bind_to_device'.
+    pub fn change_master_password(
+        &mut self,
+        current_password: &str,
+        new_password: &str,
+        new_salt: &[u8],
+        kdf_json: &str,
+        credential_ids_json: &str,
+    ) -> Result<String, String> {
+        if self.device_bound {
+            return Err("Password change error: change the password before binding the key to this device".to_string());
+        }
+        self.unlock_retyped(current_password)?;
+        validate::new_password(new_password, "new password")?;
+        validate::salt(new_salt)?;
+        let kdf = if kdf_json.trim().is_empty() {
+            self.settings().map(|s| s.kdf).unwrap_or(kdf::KdfSettings::CURRENT)
+        } else {
+            serde_json::from_str(kdf_json).map_err(|e| format!("KDF settings parse error: {}", e))?
+        };
+        let credential_ids: Vec<String> =
+            serde_json::from_str(credential_ids_json).map_err(|e| format!("Credential IDs parse error: {}", e))?;
+        let change = password_change::change_password(&self.master_key, new_password, new_salt, kdf, &credential_ids)?;
+        // The new envelope supersedes any upgrade prepared at unlock, and is now
+        // what a retyped password has to open.
+        self.pending_upgrade = None;
+        self.unlock_envelope = Some(change.envelope.clone());
+        serde_json::to_string(&change).map_err(|e| format!("Password change serialize error: {}", e))
+    }
+
+    /// ENCRYPT: Seals a piece of text using the master key, with a nonce from the
+    /// engine's own sequence, so no two seals share one. The data is committed to
+    /// the key ('keys::open_committed'). Returns {iv, data}.
+    pub fn encrypt_package(&self, plaintext: &str) -> Result<duress::EncryptedPackage, String> {
+        validate::size(plaintext.len(), validate::MAX_PLAINTEXT_LEN, "plaintext")?;
+        let iv = self.nonces.next()?;
+        let data = keys::seal_committed_with_nonce(&self.master_key, &iv, plaintext.as_bytes(), &[])?;
+        Ok(duress::EncryptedPackage { version: duress::PACKAGE_VERSION, iv: iv.to_vec(), data })
+    }
+
+    /// ENCRYPT (LEGACY): Seals a piece of text using the master key and a caller's IV.
+    /// For stores that keep the IV apart from the data; use 'encrypt_package' otherwise.
+    /// An IV this engine has already sealed with is refused; the caller must not
+    /// reuse one from stored data either.
+    pub fn encrypt(&self, plaintext: &str, iv: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = validate::nonce(iv)?; // Nonce is just another word for IV
+        validate::size(plaintext.len(), validate::MAX_PLAINTEXT_LEN, "plaintext")?;
+        self.claim_iv((*nonce).into())?;
+        // Initialize the AES-256-GCM cipher using our master key
+        let cipher = Aes256Gcm::new_from_slice(&self.master_key).map_err(|e| format!("Cipher init error: {}", e))?;
+
+        cipher
+            .encrypt(nonce, plaintext.as_bytes())
+            .map_err(|e| format!("Encryption error: {}", e))
+    }
+
+    /// DECRYPT: Unseals a package from 'encrypt_package', or a legacy one (version 1)
+    /// from 'encrypt' or the web app's WebCrypto code.
+    pub fn decrypt_package(&self, package: &duress::EncryptedPackage) -> Result<String, String> {
+        validate::nonce(&package.iv)?;
+        validate::size(package.data.len(), validate::MAX_CIPHERTEXT_LEN, "ciphertext")?;
+        Ok(validate::utf8(duress::open_package(&self.master_key, package)?, "plaintext")?)
+    }
+
+    /// DECRYPT (LEGACY): Unseals what 'encrypt' sealed: plain AES-GCM with the IV
+    /// kept apart. A committed package must go through 'decrypt_package'.
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<String, String> {
+        self.decrypt_package(&duress::EncryptedPackage::legacy(iv, ciphertext))
+    }
+
+    /// Records a caller's IV for sealing, or refuses it if this engine sealed with
+    /// it before or it falls in the range of the engine's own nonces.
+    fn claim_iv(&self, iv: [u8; 12]) -> Result<(), String> {
+        let mut sealed = self.sealed_ivs.borrow_mut();
+        if self.nonces.covers(&iv) || sealed.contains(&iv) {
+            return Err("Nonce error: this IV was already used with this key; pick a fresh one".to_string());
+        }
+        if sealed.len() >= MAX_CALLER_IVS {
+            return Err("Nonce error: too many caller IVs for one session; use encrypt_package".to_string());
+        }
+        sealed.insert(iv);
+        Ok(())
+    }
+}
+
+// --- TOTP & History ---
+
+impl Engine {
+    /// 2FA: Calculates the current TOTP code for a Base32 secret or an otpauth://totp/
+    /// URI, with the URI's algorithm, digits and period (SHA1, 6, 30 s for a bare
+    /// secret). Takes the secret as pasted; see 'normalize_totp_secret'.
+    pub fn get_totp_code(&self, secret: &str) -> Result<String, String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| format!("Clock error: {}", e))?;
+        self.get_totp_code_at(secret, now.as_secs(), 0)
+    }
+
+    /// 2FA: Like 'get_totp_code', for the device's Unix time `now` corrected by
+    /// `skew_secs` (see 'otp::corrected_time'). For devices whose clock is wrong,
+    /// and for hosts without a usable system clock.
+    pub fn get_totp_code_at(&self, secret: &str, now: u64, skew_secs: i64) -> Result<String, String> {
+        otp::TotpConfig::parse(secret)?.code_at(otp::corrected_time(now, skew_secs))
+    }
+
+    /// 2FA: The code at `now` with explicit parameters, for a bare secret whose
+    /// service lists them separately: algorithm "SHA1", "SHA256" or "SHA512",
+    /// 6 to 8 digits, and the period in seconds. They override those in a URI.
+    pub fn get_totp_code_with(&self, secret: &str, algorithm: &str, digits: u32, period: u64, now: u64) -> Result<String, String> {
+        otp::TotpConfig::parse(secret)?
+            .with_params(otp::OtpAlgorithm::parse(algorithm)?, digits, period)?
+            .code_at(now)
+    }
+
+    /// HISTORY: Manages the "Sliding Window" of previous passwords (most recent 5).
+    pub fn rotate_history(&self, current_password: &str, history_json: &str) -> Result<String, String> {
+        let mut history: Vec<String> =
+            serde_json::from_str(history_json).map_err(|e| redact::json_error("History parse", &e))?;
+
+        history.insert(0, current_password.to_string());
+        history.truncate(5);
+
+        serde_json::to_string(&history).map_err(|e| format!("History serialize error: {}", e))
+    }
+}
+
+// --- YubiKey Slots ---
+
+impl Engine {
+    /// YUBIKEY: Checks an OTP typed by the key saved in a "yubikey" entry, and on
+    /// success records its counters there so it can't be replayed; 'seal_vault'
+    /// persists them. Returns JSON {useCounter, sessionCounter, timestamp}.
+    pub fn verify_yubikey_otp(&mut self, entry_id: &str, otp: &str) -> Result<String, String> {
+        self.wake()?;
+        let token = match self.yubikey_slot(entry_id)? {
+            yubikey::YubiKeySecret::Otp(slot) => slot.verify(otp)?,
+            _ => return Err("This YubiKey entry is set up for challenge-response".to_string()),
+        };
+        serde_json::to_string(&token).map_err(|e| format!("Token serialize error: {}", e))
+    }
+
+    /// YUBIKEY: The 20-byte HMAC-SHA1 response the key saved in a "yubikey" entry
+    /// gives for `challenge` (at most 64 bytes).
+    pub fn yubikey_challenge_response(&self, entry_id: &str, challenge: &[u8]) -> Result<Vec<u8>, String> {
+        let entry = self.revealable(entry_id)?;
+        match entry.yubikey.as_ref().ok_or_else(|| format!("Entry '{}' holds no YubiKey secret", entry.title))? {
+            yubikey::YubiKeySecret::ChallengeResponse(slot) => slot.respond(challenge).map(Vec::from),
+            _ => Err("This YubiKey entry is set up for Yubico OTP".to_string()),
+        }
+    }
+
+    fn yubikey_slot(&mut self, entry_id: &str) -> Result<&mut yubikey::YubiKeySecret, String> {
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        let title = entry.title.clone();
+        entry.yubikey.as_mut().ok_or_else(|| format!("Entry '{}' holds no YubiKey secret", title))
+    }
+}
+
+// --- Emergency Access ---
+
+/// Creates the keypair a trusted contact shares with vault owners. Returns JSON.
+pub fn generate_emergency_keypair() -> Result<String, String> {
+    serde_json::to_string(&emergency::generate_keypair()).map_err(|e| format!("Keypair serialize error: {}", e))
+}
+
+/// CONTACT: Records the moment the contact asks for access (the server starts the clock).
+pub fn request_emergency_access(grant_json: &str, now: u64) -> Result<String, String> {
+    let grant: emergency::EmergencyGrant =
+        serde_json::from_str(grant_json).map_err(|e| format!("Grant parse error: {}", e))?;
+    serde_json::to_string(&emergency::request_access(&grant, now)).map_err(|e| format!("Request serialize error: {}", e))
+}
+
+impl Engine {
+    /// OWNER: Seals this vault's master key to a trusted contact's public key. Needs
+    /// the master password ('unlock_retyped').
+    pub fn create_emergency_grant(
+        &self,
+        password: &str,
+        contact_public_key: &str,
+        wait_period_secs: u64,
+        now: u64,
+    ) -> Result<String, String> {
+        self.unlock_retyped(password)?;
+        let grant = emergency::wrap_master_key(&self.master_key, contact_public_key, wait_period_secs, now)?;
+        serde_json::to_string(&grant).map_err(|e| format!("Grant serialize error: {}", e))
+    }
+
+    /// CONTACT: Opens a grant after the wait period and returns an engine for the owner's vault.
+    pub fn from_emergency_grant(grant_json: &str, request_json: &str, contact_secret_key: &str, now: u64) -> Result<Engine, String> {
+        let grant: emergency::EmergencyGrant =
+            serde_json::from_str(grant_json).map_err(|e| format!("Grant parse error: {}", e))?;
+        let request: emergency::EmergencyRequest =
+            serde_json::from_str(request_json).map_err(|e| format!("Request parse error: {}", e))?;
+
+        let master_key = emergency::unwrap_master_key(&grant, &request, contact_secret_key, now)?;
+        Ok(Self::from_key(master_key))
+    }
+}
+
+// --- Entry Sharing ---
+
+impl Engine {
+    /// SHARE: Seals one loaded entry to a recipient's public key (the keypair from
+    /// 'generate_emergency_keypair'). The TOTP seed goes along only if 'include_totp'.
+    /// Returns the bundle JSON, safe to send over any channel.
+    pub fn share_entry(&self, entry_id: &str, recipient_public_key: &str, include_totp: bool, now: u64) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let bundle = sharing::share_entry(&entry, recipient_public_key, include_totp, now)?;
+        serde_json::to_string(&bundle).map_err(|e| format!("Share serialize error: {}", e))
+    }
+
+    /// SHARE: Like 'share_entry', but the bundle stops opening at 'expires_at'. The
+    /// expiry is signed with the owner's sharing secret key, and the recipient can
+    /// only view the entry with 'open_shared_entry', not keep a copy.
+    pub fn share_entry_until(
+        &self,
+        entry_id: &str,
+        recipient_public_key: &str,
+        owner_secret_key: &str,
+        include_totp: bool,
+        expires_at: u64,
+        now: u64,
+    ) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let bundle = sharing::share_entry_until(&entry, recipient_public_key, include_totp, owner_secret_key, expires_at, now)?;
+        serde_json::to_string(&bundle).map_err(|e| format!("Share serialize error: {}", e))
+    }
+
+    /// RECEIVE: Opens a shared entry with the recipient's secret key and adds it to
+    /// the loaded vault as a new entry. Returns the new entry's ID. Time-boxed
+    /// shares are refused: a copy in the vault would outlive the expiry.
+    pub fn import_shared_entry(&mut self, bundle_json: &str, secret_key: &str, now: u64) -> Result<String, String> {
+        let bundle: sharing::SharedEntry =
+            serde_json::from_str(bundle_json).map_err(|e| format!("Share parse error: {}", e))?;
+        if bundle.header.expires_at.is_some() {
+            return Err("Share error: a time-boxed share can't be kept; open it with 'open_shared_entry'".to_string());
+        }
+        let mut entry = sharing::open_shared_entry(&bundle, secret_key, now)?;
+        entry.id = vault::new_entry_id();
+        entry.last_modified = now;
+        let id = entry.id.clone();
+        self.vault.entries.push(entry);
+        Ok(id)
+    }
+}
+
+/// RECEIVE: Opens a shared entry for viewing, without adding it to a vault; the
+/// way to read a time-boxed share, which is refused once it has expired. Returns
+/// JSON {entry, expiresAt, ownerVerifyKey}, the last two null for a plain share.
+pub fn open_shared_entry(bundle_json: &str, secret_key: &str, now: u64) -> Result<String, String> {
+    let bundle: sharing::SharedEntry =
+        serde_json::from_str(bundle_json).map_err(|e| format!("Share parse error: {}", e))?;
+    let mut entry = sharing::open_shared_entry(&bundle, secret_key, now)?;
+    let opened = serde_json::json!({
+        "entry": entry,
+        "expiresAt": bundle.header.expires_at,
+        "ownerVerifyKey": bundle.header.owner_verify_key,
+    });
+    entry.wipe();
+    Ok(opened.to_string())
+}
+
+/// ROTATE: Retires a sharing keypair. `received_json` is a JSON array of the bundles
+/// sealed to `old_secret_key` that haven't been imported yet. Returns JSON
+/// {keypair: {public_key, secret_key, verify_key}, received, record, leftBehind}:
+/// keep the keypair and bundles in place of the old ones and publish the record.
+/// 'leftBehind' lists the time-boxed shares, which can't move to the new keypair.
+pub fn rotate_sharing_keypair(old_secret_key: &str, received_json: &str, now: u64) -> Result<String, String> {
+    let received: Vec<sharing::SharedEntry> =
+        serde_json::from_str(received_json).map_err(|e| format!("Share parse error: {}", e))?;
+    let rotated = sharing::rotate_keypair(old_secret_key, &received, now)?;
+    serde_json::to_string(&rotated).map_err(|e| format!("Rotation serialize error: {}", e))
+}
+
+/// The verify key to publish next to a sharing public key, for its secret key.
+pub fn sharing_verify_key(secret_key: &str) -> Result<String, String> {
+    sharing::verify_key(secret_key)
+}
+
+/// CONTACT: Checks a rotation record against the verify key already trusted for
+/// the old keypair. Returns JSON {publicKey, verifyKey}: the keys to use from now on.
+pub fn verify_sharing_key_rotation(record_json: &str, trusted_verify_key: &str) -> Result<String, String> {
+    let record: sharing::RotationRecord =
+        serde_json::from_str(record_json).map_err(|e| format!("Rotation parse error: {}", e))?;
+    sharing::verify_rotation(&record, trusted_verify_key)?;
+    let keys = serde_json::json!({"publicKey": record.rotation.new_public_key, "verifyKey": record.rotation.new_verify_key});
+    Ok(keys.to_string())
+}
+
+// --- PIN Unlock ---
+
+impl Engine {
+    /// PIN: Wraps this vault's master key for PIN unlock on this device, under the
+    /// PIN and the device pepper ('pin::generate_pepper'). Keep the pepper on this device only.
+    /// Needs the master password ('unlock_retyped').
+    pub fn create_pin_lock(&self, password: &str, pin: &str, pepper: &[u8], max_attempts: u32) -> Result<pin::PinLock, String> {
+        self.unlock_retyped(password)?;
+        pin::PinLock::create(&self.master_key, pin, pepper, max_attempts)
+    }
+
+    /// PIN: Opens the vault with a PIN. 'lock' counts the attempt (and is erased by
+    /// the last allowed wrong PIN), so save it again whatever the result.
+    pub fn from_pin(pin: &str, pepper: &[u8], lock: &mut pin::PinLock) -> Result<Engine, String> {
+        lock.unlock(pin, pepper).map(Self::from_key)
+    }
+}
+
+// --- Vault Store, Travel Mode & Autotype ---
+
+impl Engine {
+    /// LOAD (LEGACY): Decrypts a vault ciphertext from 'seal_vault' into the engine,
+    /// replacing whatever was loaded.
+    pub fn load_vault(&mut self, ciphertext: &[u8], iv: &[u8]) -> Result<(), String> {
+        self.load_vault_package(&duress::EncryptedPackage::legacy(iv, ciphertext))
+    }
+
+    /// LOAD: Decrypts a vault package ('seal_vault_package', or a legacy one) into the
+    /// engine, replacing whatever was loaded.
+    pub fn load_vault_package(&mut self, package: &duress::EncryptedPackage) -> Result<(), String> {
+        let mut json = self.decrypt_package(package)?;
+        let parsed = vault::VaultData::from_json(&json);
+        json.zeroize();
+
+        self.vault.wipe();
+        self.idle = None;
+        self.vault = parsed?;
+        Ok(())
+    }
+
+    /// SEAL (LEGACY): Encrypts the loaded vault with a caller's IV so it can be written
+    /// back to storage. Prefer 'seal_vault_package'.
+    pub fn seal_vault(&self, iv: &[u8]) -> Result<Vec<u8>, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let sealed = self.encrypt(&json, iv);
+        json.zeroize();
+        sealed
+    }
+
+    /// SEAL (fresh IV): Same as 'seal_vault', but takes the IV from the engine's nonce
+    /// sequence and returns the {iv, data} package the web app stores.
+    pub fn seal_vault_package(&self) -> Result<duress::EncryptedPackage, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let sealed = self.encrypt_package(&json);
+        json.zeroize();
+        sealed
+    }
+
+    /// The loaded vault (empty until 'load_vault' or 'open_slots').
+    pub fn vault(&self) -> &vault::VaultData {
+        &self.vault
+    }
+
+    /// Mutable access for front ends that edit entries directly; 'seal_vault' persists the changes.
+    /// 'wake' an idle vault first, or edits to secrets are overwritten when it wakes.
+    pub fn vault_mut(&mut self) -> &mut vault::VaultData {
+        &mut self.vault
+    }
+
+    /// How many entries are currently loaded.
+    pub fn entry_count(&self) -> usize {
+        self.vault.entries.len()
+    }
+
+    /// LIST: One page of the loaded vault, sorted by "title", "lastUsed" or "modified".
+    /// Returns JSON {total, offset, entries: [summary]}; summaries carry no credentials.
+    pub fn list_entries_page(&self, offset: usize, limit: usize, sort: &str) -> Result<String, String> {
+        let page = listing::page(&self.vault.entries, offset, limit, listing::EntrySort::parse(sort)?);
+        serde_json::to_string(&page).map_err(|e| format!("Page serialize error: {}", e))
+    }
+
+    /// SEARCH: One page of the entries matching 'query' (every word must appear in
+    /// the title, username, URL or folder), ignoring case, accents and full-width
+    /// forms. Same JSON as 'list_entries_page'; 'total' counts matches.
+    pub fn search_entries(&self, query: &str, offset: usize, limit: usize, sort: &str) -> Result<String, String> {
+        let page = listing::search(&self.vault.entries, query, offset, limit, listing::EntrySort::parse(sort)?);
+        serde_json::to_string(&page).map_err(|e| format!("Page serialize error: {}", e))
+    }
+
+    /// USAGE: Records that an entry was just filled or copied from. Feeds the
+    /// "lastUsed" order and 'frequently_used'.
+    pub fn record_use(&mut self, entry_id: &str, now: u64) -> Result<(), String> {
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        entry.last_used = now;
+        entry.use_count = entry.use_count.saturating_add(1);
+        Ok(())
+    }
+
+    /// USAGE: Up to 'limit' used entries, best first, for the quick-fill dropdown.
+    /// Uses count, but older uses count for less. Returns a JSON array of summaries.
+    pub fn frequently_used(&self, limit: usize, now: u64) -> Result<String, String> {
+        serde_json::to_string(&listing::frequently_used(&self.vault.entries, limit, now))
+            .map_err(|e| format!("Summary serialize error: {}", e))
+    }
+
+    /// FAVORITES: The favorite entries in the user's order, as a JSON array of
+    /// summaries. Favorites not yet placed follow, A to Z.
+    pub fn favorite_entries(&self) -> Result<String, String> {
+        let order = self.settings()?.favorite_order;
+        let favorites: Vec<segments::EntrySummary> =
+            listing::favorites(&self.vault.entries, &order).into_iter().map(segments::EntrySummary::from).collect();
+        serde_json::to_string(&favorites).map_err(|e| format!("Summary serialize error: {}", e))
+    }
+
+    /// FAVORITES: Moves a favorite to 'position' (0 is first) and stores the order in
+    /// the vault settings, so it syncs; 'seal_vault' persists it.
+    pub fn move_favorite(&mut self, entry_id: &str, position: usize) -> Result<(), String> {
+        let mut settings = self.settings()?;
+        settings.favorite_order = listing::move_favorite(&self.vault.entries, &settings.favorite_order, entry_id, position)?;
+        self.store_settings(&settings)
+    }
+
+    /// STATS: Dashboard numbers for the loaded vault. Returns JSON {totalEntries,
+    /// byType, attachmentCount, attachmentBytes, passwordStrength: {weak, fair, good,
+    /// excellent}, logins, loginsWithTotp, totpCoveragePercent}.
+    pub fn vault_stats(&self) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&stats::vault_stats(&self.vault.entries)).map_err(|e| format!("Stats serialize error: {}", e))
+    }
+
+    /// AUDIT: Security findings for the loaded vault: passwords shared between
+    /// sites ("reused") or only within one site ("sameSite"), and weak TOTP setups.
+    /// Returns JSON {reuse: [{kind, sites: [{site, entries: [{id, title}]}]}],
+    /// reusedEntries, totp: [{entry, issue, ...}]}.
+    #[cfg(feature = "audit")]
+    pub fn audit_report(&self) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&self.audit()).map_err(|e| format!("Audit serialize error: {}", e))
+    }
+
+    /// AUDIT: The audit report as CSV (Entry ID, Title, Finding, Detail), one row
+    /// per entry and finding, for tracking fixes in a spreadsheet. No secrets.
+    #[cfg(feature = "audit")]
+    pub fn export_audit_csv(&self) -> Result<String, String> {
+        self.awake()?;
+        Ok(audit::to_csv(&self.audit()))
+    }
+
+    /// AUDIT: The same rows as a JSON array of {entryId, title, finding, detail}.
+    #[cfg(feature = "audit")]
+    pub fn export_audit_json(&self) -> Result<String, String> {
+        self.awake()?;
+        audit::to_json(&self.audit())
+    }
+
+    /// AUDIT: A 0-100 health score for the loaded vault, with its categories
+    /// (strength, reuse, twoFactor, totpSetup) and the trend against
+    /// 'history_json', the JSON array of {at, score} returned by the last call.
+    /// Returns JSON {score, categories: [{category, score, weight, issues, total}],
+    /// trend: {previous, change, direction, change30Days}, history}; store 'history'.
+    #[cfg(feature = "audit")]
+    pub fn vault_health(&self, history_json: &str, now: u64) -> Result<String, String> {
+        self.awake()?;
+        let history: Vec<health::Snapshot> = match history_json.trim() {
+            "" => Vec::new(),
+            json => serde_json::from_str(json).map_err(|e| format!("Health history parse error: {}", e))?,
+        };
+        let report = health::report(&stats::vault_stats(&self.vault.entries), &self.audit(), history, now);
+        serde_json::to_string(&report).map_err(|e| format!("Health serialize error: {}", e))
+    }
+
+    #[cfg(feature = "audit")]
+    fn audit(&self) -> audit::AuditReport {
+        audit::audit(&self.vault.entries, &self.vault.equivalent_domains)
+    }
+
+    /// SETTINGS: The vault's settings, migrated to the current version; defaults if
+    /// it has none yet. Returns JSON {version, autoLockSecs, clipboardClearSecs,
+    /// generator, kdf, presets, dedupAttachments, favoriteOrder}.
+    pub fn vault_settings(&self) -> Result<String, String> {
+        self.settings()?.to_stored().map(|v| v.to_string())
+    }
+
+    /// SETTINGS: Replaces the vault's settings. Takes any version, including the
+    /// old localStorage form, and stores it migrated; 'seal_vault' persists it.
+    pub fn set_vault_settings(&mut self, settings_json: &str) -> Result<(), String> {
+        let raw: serde_json::Value = serde_json::from_str(settings_json).map_err(|e| format!("Settings parse error: {}", e))?;
+        self.store_settings(&settings::VaultSettings::from_stored(&raw)?)
+    }
+
+    /// PRESETS: The saved generator presets, as a JSON array of {name, style, ...options}.
+    pub fn generator_presets(&self) -> Result<String, String> {
+        serde_json::to_string(&self.settings()?.presets).map_err(|e| format!("Settings serialize error: {}", e))
+    }
+
+    /// PRESETS: Saves a preset, replacing the one with the same name (ignoring case).
+    pub fn save_generator_preset(&mut self, preset_json: &str) -> Result<(), String> {
+        let preset = serde_json::from_str(preset_json).map_err(|e| format!("Preset parse error: {}", e))?;
+        let mut settings = self.settings()?;
+        settings.save_preset(preset)?;
+        self.store_settings(&settings)
+    }
+
+    /// PRESETS: Deletes a preset by name; false if there was none.
+    pub fn delete_generator_preset(&mut self, name: &str) -> Result<bool, String> {
+        let mut settings = self.settings()?;
+        let removed = settings.remove_preset(name);
+        if removed {
+            self.store_settings(&settings)?;
+        }
+        Ok(removed)
+    }
+
+    /// PRESETS: A new password from the named preset.
+    pub fn generate_with_preset(&self, name: &str) -> Result<String, String> {
+        let settings = self.settings()?;
+        let preset = settings.preset(name).ok_or_else(|| format!("No generator preset named '{}'", name.trim()))?;
+        generator::generate(&preset.style)
+    }
+
+    fn settings(&self) -> Result<settings::VaultSettings, String> {
+        settings::VaultSettings::from_stored(self.vault.settings.as_ref().unwrap_or(&serde_json::Value::Null))
+    }
+
+    fn store_settings(&mut self, settings: &settings::VaultSettings) -> Result<(), String> {
+        self.vault.settings = Some(settings.to_stored()?);
+        Ok(())
+    }
+
+    /// TRAVEL: Encrypts the vault with every travel-flagged entry and folder removed.
+    /// The output is a normal {iv, data} package, so it unlocks like any other vault.
+    pub fn build_travel_vault_package(&self, vault_json: &str) -> Result<duress::EncryptedPackage, String> {
+        let travel = vault::VaultData::from_json(vault_json)?.travel_view();
+        self.encrypt_package(&travel.to_json()?)
+    }
+
+    /// TRAVEL (LEGACY): 'build_travel_vault_package' with a caller's IV.
+    pub fn build_travel_vault(&self, vault_json: &str, iv: &[u8]) -> Result<Vec<u8>, String> {
+        let travel = vault::VaultData::from_json(vault_json)?.travel_view();
+        self.encrypt(&travel.to_json()?, iv)
+    }
+
+    /// AUTOTYPE: Expands a sequence like "{USERNAME}{TAB}{PASSWORD}{ENTER}" for one entry.
+    /// An empty template falls back to the default sequence. Returns a JSON list of actions.
+    pub fn expand_autotype(&self, template: &str, entry_json: &str) -> Result<String, String> {
+        let entry: vault::VaultEntry = serde_json::from_str(entry_json).map_err(|e| redact::json_error("Entry parse", &e))?;
+        let template = if template.trim().is_empty() { autotype::DEFAULT_SEQUENCE } else { template };
+
+        // Only compute a code when the template actually asks for one.
+        let totp_code = match &entry.totp_secret {
+            Some(secret) if template.to_ascii_uppercase().contains("{TOTP}") => Some(self.get_totp_code(secret)?),
+            _ => None,
+        };
+
+        let actions = autotype::expand(template, &entry, totp_code.as_deref())?;
+        serde_json::to_string(&actions).map_err(|e| format!("Autotype serialize error: {}", e))
+    }
+}
+
+// --- Entry Templates ---
+
+/// TEMPLATES: Every entry template as a JSON array of {id, name, entry_type, fields: [{name, kind, default}]}.
+pub fn list_entry_templates() -> Result<String, String> {
+    serde_json::to_string(&templates::TEMPLATES).map_err(|e| format!("Template serialize error: {}", e))
+}
+
+impl Engine {
+    /// TEMPLATES: Adds a new entry made from a template to the loaded vault. Returns its ID.
+    pub fn add_entry_from_template(&mut self, template_id: &str, title: &str, now: u64) -> Result<String, String> {
+        let entry = templates::find(template_id)?.instantiate(title, now);
+        let id = entry.id.clone();
+        self.vault.entries.push(entry);
+        Ok(id)
+    }
+
+    /// WIFI: 'wifi_qr_payload' for a Wi-Fi entry's network name, password and security.
+    pub fn entry_wifi_qr_payload(&self, entry_id: &str) -> Result<String, String> {
+        wifi::entry_payload(&*self.revealable(entry_id)?)
+    }
+
+    /// CARD: How to show a card entry without its number: JSON {brand, brandName, last4,
+    /// masked, expiry, expiryStatus, display, numberValid}, display being "•••• 4242 (Visa, expires 08/26)".
+    pub fn card_summary(&self, entry_id: &str, now: u64) -> Result<String, String> {
+        self.awake()?;
+        let card = card::summary(self.vault.find_entry(entry_id).ok_or("Entry not found")?, now)?;
+        serde_json::to_string(&card).map_err(|e| format!("Card serialize error: {}", e))
+    }
+
+    /// CARD: A card entry's full number, grouped as printed, for when the user clicks reveal.
+    pub fn reveal_card_number(&self, entry_id: &str) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        card::group_number(card::entry_number(&entry).ok_or("Card error: the entry has no card number")?)
+    }
+
+    /// LICENSE: A license entry's key and purchase metadata as JSON {key, version,
+    /// licensedTo, registeredEmail, purchaseDate, orderNumber, purchasedFrom, price}.
+    pub fn license_details(&self, entry_id: &str) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        serde_json::to_string(&license::LicenseDetails::from_entry(&entry)).map_err(|e| format!("License serialize error: {}", e))
+    }
+
+    /// LICENSE: Checks and stores a license's details (same JSON as 'license_details')
+    /// in the entry, normalizing the key, and makes it a license entry.
+    pub fn set_license_details(&mut self, entry_id: &str, details_json: &str, now: u64) -> Result<(), String> {
+        self.wake()?;
+        let details: license::LicenseDetails =
+            serde_json::from_str(details_json).map_err(|e| redact::json_error("License parse", &e))?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        details.apply_to(entry)?;
+        entry.last_modified = now;
+        Ok(())
+    }
+
+    /// BACKUP CODES: Stores 2FA recovery codes as pasted from the site (one per line,
+    /// or comma-separated, numbered or not) in the entry, replacing any earlier set.
+    /// Returns the status JSON of 'backup_code_status'.
+    pub fn set_backup_codes(&mut self, entry_id: &str, codes: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let codes = backup_codes::from_text(codes)?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        if extra_lock::is_locked(entry) {
+            return Err("Backup codes error: remove the entry's extra lock first".to_string());
+        }
+        backup_codes::set_entry_codes(entry, &codes);
+        entry.last_modified = now;
+        Self::backup_status_json(entry)
+    }
+
+    /// BACKUP CODES: Marks a code used after signing in with it. Returns JSON {entryId,
+    /// title, total, remaining, low}; 'low' means time to generate new codes on the site.
+    pub fn mark_backup_code_used(&mut self, entry_id: &str, code: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        let mut codes = backup_codes::entry_codes(entry).ok_or("Backup codes error: the entry has no backup codes")?;
+        backup_codes::mark_used(&mut codes, code)?;
+        backup_codes::set_entry_codes(entry, &codes);
+        entry.last_modified = now;
+        Self::backup_status_json(entry)
+    }
+
+    /// BACKUP CODES: How many of an entry's codes are left, as in 'mark_backup_code_used'.
+    pub fn backup_code_status(&self, entry_id: &str) -> Result<String, String> {
+        self.awake()?;
+        Self::backup_status_json(self.vault.find_entry(entry_id).ok_or("Entry not found")?)
+    }
+
+    /// BACKUP CODES: Entries with LOW_REMAINING (3) or fewer unused codes, as a JSON
+    /// array of statuses, for a "generate new backup codes" reminder.
+    pub fn low_backup_codes(&self) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&backup_codes::running_low(&self.vault.entries))
+            .map_err(|e| format!("Backup codes serialize error: {}", e))
+    }
+
+    /// BACKUP CODES: An entry's codes as a numbered plain-text sheet for printing, used ones struck.
+    pub fn print_backup_codes(&self, entry_id: &str) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let codes = backup_codes::entry_codes(&entry).ok_or("Backup codes error: the entry has no backup codes")?;
+        Ok(backup_codes::format_for_printing(&entry.title, &codes))
+    }
+
+    /// NOTES: An entry's notes rendered from Markdown to an HTML fragment that is
+    /// safe to insert as is (see 'render_markdown').
+    #[cfg(feature = "markdown")]
+    pub fn render_entry_notes(&self, entry_id: &str) -> Result<String, String> {
+        Ok(markdown::to_html(&self.revealable(entry_id)?.notes))
+    }
+
+    fn backup_status_json(entry: &vault::VaultEntry) -> Result<String, String> {
+        let status = backup_codes::status(entry).ok_or("Backup codes error: the entry has no backup codes")?;
+        serde_json::to_string(&status).map_err(|e| format!("Backup codes serialize error: {}", e))
+    }
+}
+
+/// NOTES: Markdown (CommonMark, tables, strikethrough, task lists) as sanitized HTML:
+/// raw HTML is escaped, links keep only http, https and mailto, images aren't loaded.
+#[cfg(feature = "markdown")]
+pub fn render_markdown(notes: &str) -> String {
+    markdown::to_html(notes)
+}
+
+/// WIFI: The WIFI: text a phone camera reads as "join this network", to show as a QR code.
+/// 'security' is "WPA2", "WPA3", "WEP", "open", ...
+pub fn wifi_qr_payload(ssid: &str, password: &str, security: &str) -> Result<String, String> {
+    wifi::qr_payload(ssid, password, security)
+}
+
+/// CARD: "•••• 4242" for a card number as typed.
+pub fn mask_card_number(number: &str) -> Result<String, String> {
+    card::mask_number(number)
+}
+
+/// CARD: The network of a card number from its first digits: "visa", "mastercard", "amex",
+/// "discover", "diners_club", "jcb", "union_pay", "maestro" or "unknown".
+pub fn detect_card_brand(number: &str) -> Result<String, String> {
+    serde_json::to_value(card::detect_brand(number))
+        .map(|v| v.as_str().unwrap_or_default().to_string())
+        .map_err(|e| format!("Card serialize error: {}", e))
+}
+
+/// CARD: Whether a card expiring 'expiry' ("MM/YY") is "valid", "expiring_soon" (this month
+/// or next) or "expired" at 'now'.
+pub fn card_expiry_status(expiry: &str, now: u64) -> Result<String, String> {
+    serde_json::to_value(card::Expiry::parse(expiry)?.status(now))
+        .map(|v| v.as_str().unwrap_or_default().to_string())
+        .map_err(|e| format!("Card serialize error: {}", e))
+}
+
+/// LICENSE: Normalizes a product key as typed. Returns JSON {key, format, groups, groupLength},
+/// or an error naming a group of the wrong length.
+pub fn normalize_license_key(raw: &str) -> Result<String, String> {
+    serde_json::to_string(&license::normalize_key(raw)?).map_err(|e| format!("License serialize error: {}", e))
+}
+
+/// ENTITLEMENT: Checks a vendor-signed entitlement token against the vendor's
+/// Ed25519 public key (Base64), offline. Returns JSON {version, tier, seats,
+/// issuedAt, expiresAt, subject, active}; 'active' is false once it has expired.
+/// A forged or altered token is an error.
+pub fn verify_entitlement(token: &str, vendor_public_key: &str, now: u64) -> Result<String, String> {
+    let verified = entitlement::verify(token, vendor_public_key, now)?;
+    serde_json::to_string(&verified).map_err(|e| format!("Entitlement serialize error: {}", e))
+}
+
+/// IDENTITY: A postal code in the country's usual spelling ("sw1a2aa" -> "SW1A 2AA").
+/// 'country' is ISO 3166 alpha-2; unknown countries keep the code as typed.
+pub fn format_postal_code(country: &str, raw: &str) -> Result<String, String> {
+    identity::format_postal_code(country, raw)
+}
+
+/// IDENTITY: Splits a phone number into JSON {countryCode, national, e164}. Numbers
+/// without "+" are read as dialled in 'default_country' (ISO 3166 alpha-2).
+pub fn format_phone_number(raw: &str, default_country: &str) -> Result<String, String> {
+    serde_json::to_string(&identity::parse_phone(raw, default_country)?).map_err(|e| format!("Phone serialize error: {}", e))
+}
+
+// --- Clipboard ---
+
+/// CLIPBOARD: A value as plain text for copying, without invisible characters or
+/// trailing whitespace; with 'autotype_safe', escaped for an autotype sequence.
+pub fn clipboard_text(value: &str, autotype_safe: bool) -> String {
+    match autotype_safe {
+        true => clipboard::autotype_literal(value),
+        false => clipboard::plain_text(value),
+    }
+}
+
+/// CLIPBOARD: A TOTP code for copying, digits only ("123 456" to "123456").
+pub fn clipboard_code(code: &str) -> String {
+    clipboard::plain_code(code)
+}
+
+// --- Duplicate Entries ---
+
+impl Engine {
+    /// DEDUPE: Logins that look like the same account (same site and username),
+    /// as JSON [{key, ids, passwordsDiffer}].
+    pub fn find_duplicates(&self) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&dedupe::find_duplicates(&self.vault.entries)).map_err(|e| format!("Dedupe serialize error: {}", e))
+    }
+
+    /// DEDUPE: Merges the entries in 'ids_json' (a JSON array of IDs) into the first
+    /// one, keeping the password 'strategy' picks ("strongest" or "newest"), the
+    /// others in its history, and the union of notes and custom fields. Returns
+    /// JSON {id, removed}; remove those IDs from the search index.
+    pub fn merge_entries(&mut self, ids_json: &str, strategy: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let strategy = dedupe::Strategy::parse(strategy)?;
+        let ids: Vec<String> = serde_json::from_str(ids_json).map_err(|e| redact::json_error("Merge IDs parse", &e))?;
+        dedupe::merge(&mut self.vault.entries, &ids, strategy, now)?;
+        serde_json::to_string(&serde_json::json!({"id": ids[0], "removed": ids[1..]}))
+            .map_err(|e| format!("Dedupe serialize error: {}", e))
+    }
+}
+
+// --- Idle Sealing ---
+
+impl Engine {
+    /// IDLE: Seals every loaded entry's secrets under a session key until 'wake', so
+    /// an unused vault holds no passwords in plain text. Lists, search and page
+    /// matching keep working, and revealing or filling one entry opens only that
+    /// entry. Saving, exporting, syncing, audits and stats need 'wake' first; edits
+    /// to secrets wake the vault themselves.
+    pub fn go_idle(&mut self) -> Result<(), String> {
+        #[cfg(feature = "importers")]
+        self.cancel_import();
+        if self.idle.is_none() {
+            self.idle = Some(idle::IdleStore::seal(&mut self.vault)?);
+        }
+        Ok(())
+    }
+
+    /// IDLE: Puts the sealed secrets back. Does nothing when not idle.
+    pub fn wake(&mut self) -> Result<(), String> {
+        match self.idle.take() {
+            Some(store) => store.restore(&mut self.vault),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.idle.is_some()
+    }
+
+    /// For everything that reads or writes every entry's secrets.
+    fn awake(&self) -> Result<(), String> {
+        if self.idle.is_some() {
+            return Err(idle::IDLE.to_string());
+        }
+        Ok(())
+    }
+}
+
+// --- Master Password Reprompt ---
+
+impl Engine {
+    /// REPROMPT: Checks a retyped master password against the envelope this vault was
+    /// unlocked with ('unlock_retyped'). Reprompt-flagged entries can then be revealed
+    /// until the window set by 'set_reprompt_window' runs out on the engine's clock.
+    /// A wrong password ends any confirmation.
+    pub fn confirm_master(&mut self, password: &str) -> Result<(), String> {
+        let key = match self.unlock_retyped(password) {
+            Ok(key) => key,
+            Err(e) => {
+                self.reprompt.revoke();
+                return Err(e);
+            }
+        };
+        if !self.reprompt.confirm(&key, (self.clock)()) {
+            return Err(kdf::UNLOCK_FAILED.to_string());
+        }
+        Ok(())
+    }
+
+    /// REPROMPT: Ends the current confirmation early.
+    pub fn end_reprompt(&mut self) {
+        self.reprompt.revoke();
+    }
+
+    /// REPROMPT: How long a confirmation lasts, in seconds (default 300, at most
+    /// 'reprompt::MAX_WINDOW_SECS').
+    pub fn set_reprompt_window(&mut self, secs: u64) {
+        self.reprompt.set_window(secs);
+    }
+
+    /// REPROMPT: Flags or unflags an entry as needing the master password to reveal.
+    /// Unflagging needs a confirmation too, or the flag would be one call from useless.
+    pub fn set_entry_reprompt(&mut self, entry_id: &str, reprompt: bool, now: u64) -> Result<(), String> {
+        self.revealable(entry_id)?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        entry.reprompt = reprompt;
+        entry.last_modified = now;
+        Ok(())
+    }
+
+    /// REPROMPT: One loaded entry as JSON, secrets included, for the detail view.
+    /// A reprompt-flagged entry needs a recent 'confirm_master'.
+    pub fn reveal_entry(&self, entry_id: &str) -> Result<String, String> {
+        serde_json::to_string(&*self.revealable(entry_id)?).map_err(|e| format!("Entry serialize error: {}", e))
+    }
+
+    /// The master key a retyped master password unlocks from 'unlock_envelope', if it
+    /// is this vault's (a legacy envelope derives a key from any password). Never from
+    /// an envelope the caller supplies: 'change_master_password' can wrap the key
+    /// under any password at all, so such an envelope proves nothing.
+    fn unlock_retyped(&self, password: &str) -> Result<zeroize::Zeroizing<[u8; 32]>, String> {
+        let envelope = self.unlock_envelope.as_ref().ok_or(NO_MASTER_PASSWORD)?;
+        let key = zeroize::Zeroizing::new(envelope.unlock(password)?);
+        if !self.exports.is_master_key(&key) {
+            return Err(kdf::UNLOCK_FAILED.to_string());
+        }
+        Ok(key)
+    }
+
+    /// The loaded entry, if its secrets may be revealed now. While idle, a copy
+    /// opened from the idle store. Every call that hands out an entry's secrets
+    /// goes through here.
+    fn revealable(&self, entry_id: &str) -> Result<idle::EntryRef<'_>, String> {
+        let entry = self.vault.find_entry(entry_id).ok_or("Entry not found")?;
+        self.reprompt.check(entry, (self.clock)())?;
+        match &self.idle {
+            Some(store) => Ok(idle::EntryRef::Opened(Box::new(store.open_entry(entry)?))),
+            None => Ok(idle::EntryRef::Loaded(entry)),
+        }
+    }
+}
+
+// --- Extra Lock ---
+
+impl Engine {
+    /// EXTRA LOCK: Seals an entry's secrets under a second passphrase as well as the
+    /// master key. The title, URL and folder stay visible.
+    pub fn set_extra_lock(&mut self, entry_id: &str, passphrase: &str, now: u64) -> Result<(), String> {
+        self.wake()?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        extra_lock::lock(&self.master_key, entry, passphrase, kdf::KdfSettings::CURRENT)?;
+        entry.last_modified = now;
+        Ok(())
+    }
+
+    /// EXTRA LOCK: Removes an entry's extra lock, putting its secrets back.
+    pub fn clear_extra_lock(&mut self, entry_id: &str, passphrase: &str, now: u64) -> Result<(), String> {
+        self.wake()?;
+        let entry = self.vault.entries.iter_mut().find(|e| e.id == entry_id).ok_or("Entry not found")?;
+        extra_lock::unlock(&self.master_key, entry, passphrase)?;
+        entry.last_modified = now;
+        Ok(())
+    }
+
+    /// EXTRA LOCK: A locked entry with its secrets, as entry JSON, to show once.
+    /// The entry stays locked in the vault.
+    pub fn open_locked_entry(&self, entry_id: &str, passphrase: &str) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let mut entry = extra_lock::open_entry(&self.master_key, &entry, passphrase)?;
+        let json = serde_json::to_string(&entry).map_err(|e| format!("Entry serialize error: {}", e));
+        entry.wipe();
+        json
+    }
+}
+
+// --- Write-Ahead Journal ---
+
+impl Engine {
+    /// JOURNAL: 'load_vault', then replays the journal records (JSON array) written
+    /// since that save. Returns the journal to append to and what the replay did.
+    pub fn load_vault_journaled(
+        &mut self,
+        ciphertext: &[u8],
+        iv: &[u8],
+        records_json: &str,
+    ) -> Result<(journal::Journal, journal::Replay), String> {
+        let records: Vec<journal::JournalRecord> =
+            serde_json::from_str(records_json).map_err(|e| format!("Journal parse error: {}", e))?;
+        self.load_vault(ciphertext, iv)?;
+        Ok(journal::replay(&self.master_key, iv, &mut self.vault, &records))
+    }
+
+    /// JOURNAL: Seals the edits to the loaded vault since the last record. Returns
+    /// the record JSON to append, or "" when nothing changed.
+    pub fn journal_append(&self, journal: &mut journal::Journal) -> Result<String, String> {
+        self.awake()?;
+        match journal.append(&self.master_key, &self.vault)? {
+            Some(record) => serde_json::to_string(&record).map_err(|e| format!("Journal serialize error: {}", e)),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// JOURNAL: Starts the journal over after 'seal_vault(iv)' was saved.
+    pub fn journal_checkpoint(&self, journal: &mut journal::Journal, iv: &[u8]) {
+        journal.checkpoint(iv, &self.vault);
+    }
+}
+
+// --- Segmented Vault Storage ---
+
+impl Engine {
+    /// SEAL (segmented): The loaded vault with every entry split into a summary and
+    /// a secrets segment. Returns JSON {version, root, entries: [{id, summary, secrets}]}.
+    pub fn seal_vault_segments(&self) -> Result<String, String> {
+        self.awake()?;
+        let sealed = segments::seal_vault(&self.master_key, &self.vault)?;
+        serde_json::to_string(&sealed).map_err(|e| format!("Segments serialize error: {}", e))
+    }
+
+    /// LOAD (segmented): Decrypts every segment into the engine, replacing whatever was loaded.
+    pub fn load_vault_segments(&mut self, sealed_json: &str) -> Result<(), String> {
+        let sealed: segments::SegmentedVault =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        let opened = segments::open_vault(&self.master_key, &sealed)?;
+        self.vault.wipe();
+        self.idle = None;
+        self.vault = opened;
+        Ok(())
+    }
+
+    /// SEAL (one entry): Re-seals a loaded entry after an edit, so only its record
+    /// needs rewriting. Returns JSON {id, summary, secrets}.
+    pub fn seal_entry_segments(&self, entry_id: &str) -> Result<String, String> {
+        self.awake()?;
+        let entry = self.vault.find_entry(entry_id).ok_or("Entry not found")?;
+        let sealed = segments::seal_entry(&self.master_key, entry)?;
+        serde_json::to_string(&sealed).map_err(|e| format!("Segments serialize error: {}", e))
+    }
+
+    /// LIST: Decrypts only the summary of each entry in a segmented vault; no password
+    /// is decrypted. Returns a JSON array of {id, title, url, category, favorite, lastModified, lastUsed, useCount, travelMode}.
+    pub fn list_entry_summaries(&self, sealed_json: &str) -> Result<String, String> {
+        let sealed: segments::SegmentedVault =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        let summaries = segments::summaries(&self.master_key, &sealed)?;
+        serde_json::to_string(&summaries).map_err(|e| format!("Summary serialize error: {}", e))
+    }
+
+    /// OPEN: Decrypts one sealed entry ({id, summary, secrets}) in full, e.g. when the
+    /// user opens it from the list. Returns the entry JSON.
+    pub fn open_entry_segments(&self, sealed_entry_json: &str) -> Result<String, String> {
+        let sealed: segments::SealedEntry =
+            serde_json::from_str(sealed_entry_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        let mut entry = segments::open_entry(&self.master_key, &sealed)?;
+        if let Err(e) = self.reprompt.check(&entry, (self.clock)()) {
+            entry.wipe();
+            return Err(e);
+        }
+        let json = serde_json::to_string(&entry).map_err(|e| format!("Entry serialize error: {}", e));
+        entry.wipe();
+        json
+    }
+}
+
+// --- Search Index ---
+
+impl Engine {
+    /// INDEX: A blind search index of the loaded vault, to store next to the sealed
+    /// entries. Returns JSON {version, keyId, entries: {id: [token]}}; no plain text.
+    pub fn build_search_index(&self) -> Result<String, String> {
+        let index = search_index::Indexer::new(&self.master_key).build(&self.vault.entries);
+        serde_json::to_string(&index).map_err(|e| format!("Index serialize error: {}", e))
+    }
+
+    /// INDEX: The index with a loaded entry added or re-indexed after an edit. The
+    /// change is kept pending until 'compact_search_index', so nothing else is redone.
+    pub fn index_add(&self, index_json: &str, entry_id: &str) -> Result<String, String> {
+        let mut index = parse_search_index(index_json)?;
+        let entry = self.vault.find_entry(entry_id).ok_or("Entry not found")?;
+        search_index::Indexer::new(&self.master_key).add(&mut index, entry)?;
+        serde_json::to_string(&index).map_err(|e| format!("Index serialize error: {}", e))
+    }
+
+    /// INDEX: The index without a deleted entry (also kept pending).
+    pub fn index_remove(&self, index_json: &str, entry_id: &str) -> Result<String, String> {
+        let mut index = parse_search_index(index_json)?;
+        index.remove(entry_id);
+        serde_json::to_string(&index).map_err(|e| format!("Index serialize error: {}", e))
+    }
+
+    /// INDEX: Re-indexes the next `limit` loaded entries after the ID `after` (empty
+    /// to start over). Call again with the returned cursor until it is null. Returns
+    /// JSON {index, next}.
+    pub fn index_rebuild_partial(&self, index_json: &str, after: &str, limit: usize) -> Result<String, String> {
+        let mut index = parse_search_index(index_json)?;
+        let after = Some(after).filter(|a| !a.is_empty());
+        let next = search_index::Indexer::new(&self.master_key).rebuild_partial(&mut index, &self.vault.entries, after, limit)?;
+        serde_json::to_string(&serde_json::json!({"index": index, "next": next}))
+            .map_err(|e| format!("Index serialize error: {}", e))
+    }
+
+    /// INDEX: Folds pending changes into the index. Cheap to call when idle; it does
+    /// nothing useful until the index's pending list grows (see 'needs_compaction').
+    /// Returns JSON {index, compacted}.
+    pub fn compact_search_index(&self, index_json: &str, force: bool) -> Result<String, String> {
+        let mut index = parse_search_index(index_json)?;
+        let compacted = force || index.needs_compaction();
+        if compacted {
+            index.compact();
+        }
+        serde_json::to_string(&serde_json::json!({"index": index, "compacted": compacted}))
+            .map_err(|e| format!("Index serialize error: {}", e))
+    }
+
+    /// INDEX: IDs of the entries that may match `query`, without decrypting any entry.
+    /// Returns a JSON array; confirm the hits on their decrypted summaries.
+    pub fn search_index(&self, index_json: &str, query: &str) -> Result<String, String> {
+        let index = parse_search_index(index_json)?;
+        let hits = search_index::Indexer::new(&self.master_key).search(&index, query)?;
+        serde_json::to_string(&hits).map_err(|e| format!("Index serialize error: {}", e))
+    }
+}
+
+fn parse_search_index(index_json: &str) -> Result<search_index::SearchIndex, String> {
+    serde_json::from_str(index_json).map_err(|e| format!("Index parse error: {}", e))
+}
+
+// --- Re-encryption ---
+
+impl Engine {
+    /// REKEY: Starts moving a segmented vault from 'previous''s key to this engine's
+    /// (e.g. one derived with stronger KDF settings). Persist the returned queue with the vault.
+    pub fn start_reencryption(&self, previous: &Engine, sealed_json: &str) -> Result<reencrypt::Reencryption, String> {
+        let sealed: segments::SegmentedVault =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        reencrypt::Reencryption::start(&previous.master_key, &self.master_key, &sealed)
+    }
+
+    /// REKEY: Re-seals up to 'batch_size' outdated entries under this engine's key and
+    /// returns the updated vault JSON. Save it and 'queue' together; repeat until
+    /// 'queue.is_complete()', yielding to the UI between batches.
+    pub fn reencrypt_batch(
+        &self,
+        previous: &Engine,
+        queue: &mut reencrypt::Reencryption,
+        sealed_json: &str,
+        batch_size: usize,
+    ) -> Result<String, String> {
+        let mut sealed: segments::SegmentedVault =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        queue.step(&previous.master_key, &self.master_key, &mut sealed, batch_size)?;
+        serde_json::to_string(&sealed).map_err(|e| format!("Segments serialize error: {}", e))
+    }
+
+    /// LOAD (mid re-encryption): Like 'load_vault_segments' for a vault that is only
+    /// partly moved; each segment is opened with the key that sealed it.
+    pub fn load_vault_segments_reencrypting(
+        &mut self,
+        previous: &Engine,
+        queue: &reencrypt::Reencryption,
+        sealed_json: &str,
+    ) -> Result<(), String> {
+        let sealed: segments::SegmentedVault =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Segments parse error: {}", e))?;
+        let opened = queue.open_vault(&previous.master_key, &self.master_key, &sealed)?;
+        self.vault.wipe();
+        self.idle = None;
+        self.vault = opened;
+        Ok(())
+    }
+}
+
+// --- Autofill, Phishing, Breaches & Domains ---
+
+impl Engine {
+    /// AUTOFILL: Maps a loaded entry's credentials onto the fields the content script found.
+    pub fn build_fill_payload(
+        &self,
+        entry_id: &str,
+        page_url: &str,
+        form_descriptor_json: &str,
+    ) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let form: autofill::FormDescriptor =
+            serde_json::from_str(form_descriptor_json).map_err(|e| format!("Form parse error: {}", e))?;
+
+        let totp_code = match &entry.totp_secret {
+            Some(secret) => Some(self.get_totp_code(secret)?),
+            None => None,
+        };
+
+        let payload =
+            autofill::build_fill_payload(&entry, page_url, &form, totp_code.as_deref(), &self.vault.equivalent_domains)?;
+        serde_json::to_string(&payload).map_err(|e| format!("Payload serialize error: {}", e))
+    }
+
+    /// IDENTITY: Maps an identity entry's name, phone and address onto a form's fields,
+    /// split and formatted for each. Returns a JSON array of {field_id, part, value}.
+    pub fn build_identity_fill(&self, entry_id: &str, form_descriptor_json: &str) -> Result<String, String> {
+        let entry = self.revealable(entry_id)?;
+        let form: autofill::FormDescriptor =
+            serde_json::from_str(form_descriptor_json).map_err(|e| format!("Form parse error: {}", e))?;
+        let fills = identity::Identity::from_entry(&entry).fills(&form);
+        serde_json::to_string(&fills).map_err(|e| format!("Payload serialize error: {}", e))
+    }
+
+    /// PHISHING: Compares a page against every URL in the loaded vault. Returns a JSON verdict.
+    pub fn check_phishing(&self, page_url: &str) -> Result<String, String> {
+        let saved: Vec<&str> = self.vault.entries.iter().map(|e| e.url.as_str()).collect();
+        let verdict = phishing::check(page_url, &saved, &self.vault.equivalent_domains)?;
+        serde_json::to_string(&verdict).map_err(|e| format!("Verdict serialize error: {}", e))
+    }
+
+    /// BREACH: The loaded entries whose password is probably in an offline breach
+    /// filter. Returns a JSON array of summaries; no password leaves the engine.
+    pub fn breached_entries(&self, filter: &breach::BreachFilter) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&breach::breached_entries(filter, &self.vault.entries))
+            .map_err(|e| format!("Summary serialize error: {}", e))
+    }
+
+    /// BREACH: "Change this password" alerts: logins whose password was set before
+    /// a breach of their site in 'sites'. Returns a JSON array of {entryId, title,
+    /// domain, siteName, breachedAt, passwordSetAt, dataClasses}.
+    pub fn breached_site_alerts(&self, sites: &breached_sites::BreachedSites) -> Result<String, String> {
+        self.awake()?;
+        serde_json::to_string(&sites.alerts(&self.vault.entries)).map_err(|e| format!("Alert serialize error: {}", e))
+    }
+
+    /// The loaded vault's equivalent-domain groups as JSON, e.g. [["amazon.com","amazon.de"]].
+    pub fn equivalent_domains(&self) -> Result<String, String> {
+        serde_json::to_string(&self.vault.equivalent_domains).map_err(|e| format!("Domains serialize error: {}", e))
+    }
+
+    /// Replaces the equivalent-domain groups. Input is normalized and overlapping groups merged.
+    pub fn set_equivalent_domains(&mut self, groups_json: &str) -> Result<(), String> {
+        let raw: Vec<Vec<String>> = serde_json::from_str(groups_json).map_err(|e| format!("Domains parse error: {}", e))?;
+        self.vault.equivalent_domains = domains::EquivalentDomains::from_groups(raw)?;
+        Ok(())
+    }
+
+    /// Adds one group (JSON array of domains), merging it with any group it overlaps.
+    pub fn add_equivalent_domains(&mut self, group_json: &str) -> Result<(), String> {
+        let group: Vec<String> = serde_json::from_str(group_json).map_err(|e| format!("Domains parse error: {}", e))?;
+        self.vault.equivalent_domains.add_group(&group)
+    }
+
+    /// Takes a domain out of its group; groups left with a single domain are dropped.
+    pub fn remove_equivalent_domain(&mut self, domain: &str) {
+        self.vault.equivalent_domains.remove_domain(domain);
+    }
+}
+
+// --- Authenticator Export & Import ---
+
+/// 2FA: Cleans up a pasted TOTP secret (any case, spaces, dashes, padding, or a
+/// whole otpauth://totp/ URI). Returns JSON {secret, issuer, account, algorithm,
+/// digits, period}, with the canonical Base32 secret and the parameters found.
+pub fn normalize_totp_secret(raw: &str) -> Result<String, String> {
+    serde_json::to_string(&otp::TotpConfig::parse(raw)?).map_err(|e| format!("TOTP serialize error: {}", e))
+}
+
+/// 2FA: How far off this device's clock is, from a code known to be good (one the
+/// service just accepted, or the one another device shows) and the device's Unix
+/// time `now`. Returns the skew in seconds for 'get_totp_code_at'; store it per device.
+pub fn estimate_totp_skew(secret: &str, known_good_code: &str, now: u64) -> Result<i64, String> {
+    otp::TotpConfig::parse(secret)?
+        .estimate_skew(known_good_code, now)?
+        .ok_or_else(|| format!("The code doesn't match this secret within {} hours either way", otp::MAX_SKEW_SECS / 3600))
+}
+
+/// MIGRATION: Decodes one otpauth-migration:// QR payload without touching a vault. Returns JSON.
+#[cfg(feature = "importers")]
+pub fn parse_google_migration(uri: &str) -> Result<String, String> {
+    let batch = google_migration::parse_migration_uri(uri)?;
+    serde_json::to_string(&batch).map_err(|e| format!("Batch serialize error: {}", e))
+}
+
+#[cfg(feature = "importers")]
+impl Engine {
+    /// AEGIS: Returns an encrypted Aegis vault (JSON) holding every TOTP seed in the loaded vault.
+    pub fn export_totp_aegis(&self, export_password: &str) -> Result<String, String> {
+        self.awake()?;
+        otp_export::to_aegis(&self.totp_configs()?, export_password)
+    }
+
+    /// ANDOTP: Returns an encrypted andOTP backup (binary, saved as .json.aes).
+    pub fn export_totp_andotp(&self, export_password: &str) -> Result<Vec<u8>, String> {
+        self.awake()?;
+        otp_export::to_andotp(&self.totp_configs()?, export_password)
+    }
+
+    /// Every entry's TOTP config. A broken secret fails the whole export rather than
+    /// silently leaving an account behind.
+    fn totp_configs(&self) -> Result<Vec<otp::TotpConfig>, String> {
+        self.vault
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                otp::TotpConfig::from_entry(entry)
+                    .map(|r| r.map_err(|e| format!("Entry '{}' has an invalid TOTP secret: {}", entry.title, e)))
+            })
+            .collect()
+    }
+
+    /// MIGRATION: Adds every account in the QR payload to the loaded vault as a new entry.
+    /// Returns the number of entries added.
+    pub fn import_google_migration(&mut self, uri: &str, now: u64) -> Result<usize, String> {
+        self.wake()?;
+        let batch = google_migration::parse_migration_uri(uri)?;
+        let added = batch.accounts.len();
+
+        for config in batch.accounts {
+            let title = if config.issuer.is_empty() { config.account.clone() } else { config.issuer.clone() };
+            self.vault.entries.push(vault::VaultEntry {
+                id: vault::new_entry_id(),
+                title,
+                username: config.account.clone(),
+                totp_secret: Some(config.to_uri()),
+                last_modified: now,
+                ..Default::default()
+            });
+        }
+        Ok(added)
+    }
+
+    /// LASTPASS: Adds every login and secure note of a LastPass CSV export to the
+    /// loaded vault. 'folder_map_json' renames folders ({"Work\\Email": "Mail"}, or
+    /// empty); typed notes become entries of the matching template. Returns the
+    /// number of entries added.
+    pub fn import_lastpass_csv(&mut self, csv: &str, folder_map_json: &str, now: u64) -> Result<usize, String> {
+        self.wake()?;
+        let folders = lastpass::FolderMap::from_json(folder_map_json)?;
+        let import = lastpass::LastPass { folders }.import(csv, now)?;
+        Ok(self.add_imported(import).added)
+    }
+
+    /// BROWSER: Adds the logins of a Chrome/Edge or Firefox password export to the
+    /// loaded vault, merging http/https twins. Returns JSON {source, added, duplicates}
+    /// with 'source' "chrome" or "firefox".
+    pub fn import_browser_csv(&mut self, csv: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let import = browser_import::BrowserCsv.import(csv, now)?;
+        let summary = self.add_imported(import);
+        serde_json::to_string(&summary).map_err(|e| format!("Import serialize error: {}", e))
+    }
+
+    /// IMPORT: Adds another manager's export to the loaded vault. 'format' is
+    /// "lastpass", "browser", "apple", "dashlane" or "proton-pass" (see 'import::for_format');
+    /// folders the export used become categories. Returns JSON {source, added, duplicates}.
+    pub fn import_file(&mut self, format: &str, data: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let import = import::for_format(format)?.import(data, now)?;
+        let summary = self.add_imported(import);
+        serde_json::to_string(&summary).map_err(|e| format!("Import serialize error: {}", e))
+    }
+
+    /// IMPORT: Reads an export like 'import_file' but only compares it with the loaded
+    /// vault. Returns JSON {source, items: [{index, title, username, url, status,
+    /// existingId, changed}], merged}, 'status' being "new", "duplicate", "update"
+    /// (newer in the export) or "conflict". The import is held, replacing any earlier
+    /// one, until 'apply_import' or 'cancel_import'; going idle cancels it.
+    pub fn preview_import(&mut self, format: &str, data: &str, now: u64) -> Result<String, String> {
+        self.wake()?;
+        let import = import::for_format(format)?.import(data, now)?;
+        let preview = import::preview(&import, &self.vault.entries);
+        let json = serde_json::to_string(&preview).map_err(|e| format!("Import serialize error: {}", e))?;
+        self.cancel_import();
+        self.pending_import = Some(import::PendingImport { import, preview });
+        Ok(json)
+    }
+
+    /// IMPORT: Carries out the previewed import. 'choices_json' maps item indexes to
+    /// "add", "replace" or "skip" ({"3": "replace"}, or empty); other items get the
+    /// default for their status (add new ones, replace with updates, skip the rest).
+    /// Returns JSON {source, added, replaced, skipped}.
+    pub fn apply_import(&mut self, choices_json: &str) -> Result<String, String> {
+        self.wake()?;
+        let choices: std::collections::BTreeMap<usize, import::Choice> = match choices_json.trim() {
+            "" => Default::default(),
+            json => serde_json::from_str(json).map_err(|e| redact::json_error("Import choices parse", &e))?,
+        };
+        let pending = self.pending_import.take().ok_or("Import error: nothing to apply; preview an import first")?;
+        if let Err(e) = pending.check(&choices) {
+            self.pending_import = Some(pending);
+            return Err(e);
+        }
+
+        let import::PendingImport { import, preview } = pending;
+        let (mut added, mut replaced, mut skipped) = (0, 0, 0);
+        for (item, mut entry) in preview.items.iter().zip(import.entries) {
+            let choice = choices.get(&item.index).copied().unwrap_or_else(|| item.status.default_choice());
+            let existing = item.existing_id.as_deref().and_then(|id| self.vault.entries.iter_mut().find(|e| e.id == id));
+            match (choice, existing) {
+                (import::Choice::Replace, Some(existing)) => {
+                    import::replace_details(existing, entry);
+                    replaced += 1;
+                }
+                (import::Choice::Skip, _) => {
+                    entry.wipe();
+                    skipped += 1;
+                }
+                // Added, or its match was deleted since the preview.
+                _ => {
+                    if !entry.category.is_empty() && !self.vault.categories.contains(&entry.category) {
+                        self.vault.categories.push(entry.category.clone());
+                    }
+                    self.vault.entries.push(entry);
+                    added += 1;
+                }
+            }
+        }
+        let summary = serde_json::json!({"source": import.source, "added": added, "replaced": replaced, "skipped": skipped});
+        serde_json::to_string(&summary).map_err(|e| format!("Import serialize error: {}", e))
+    }
+
+    /// IMPORT: Drops a previewed import, wiping its secrets.
+    pub fn cancel_import(&mut self) {
+        if let Some(pending) = self.pending_import.take() {
+            pending.wipe();
+        }
+    }
+
+    fn add_imported(&mut self, import: import::Imported) -> import::ImportSummary {
+        for folder in import.folders {
+            if !self.vault.categories.contains(&folder) {
+                self.vault.categories.push(folder);
+            }
+        }
+        let added = import.entries.len();
+        self.vault.entries.extend(import.entries);
+        import::ImportSummary { source: import.source, added, duplicates: import.duplicates }
+    }
+}
+
+// --- Plaintext JSON Export ---
+
+impl Engine {
+    /// EXPORT: The loaded vault as unencrypted JSON in the versioned schema of
+    /// 'json_export'. Only for a user who explicitly asked for a plaintext file.
+    pub fn export_json(&self, now: u64) -> Result<String, String> {
+        self.awake()?;
+        let file = json_export::export(&self.vault, now)?;
+        serde_json::to_string_pretty(&file).map_err(|e| format!("Export serialize error: {}", e))
+    }
+
+    /// EXPORT: Adds the items of an export (any schema version, or the vault JSON of
+    /// an age/OpenPGP export) to the loaded vault, with new IDs where they clash.
+    /// Returns the number of entries added.
+    pub fn import_json(&mut self, json: &str, now: u64) -> Result<usize, String> {
+        self.wake()?;
+        let imported = json_export::parse(json)?;
+        let added = imported.entries.len();
+        for folder in imported.categories {
+            if !self.vault.categories.contains(&folder) {
+                self.vault.categories.push(folder);
+            }
+        }
+        for folder in imported.travel_mode_categories {
+            if !self.vault.travel_mode_categories.contains(&folder) {
+                self.vault.travel_mode_categories.push(folder);
+            }
+        }
+        for mut entry in imported.entries {
+            if entry.id.is_empty() || self.vault.find_entry(&entry.id).is_some() {
+                entry.id = vault::new_entry_id();
+            }
+            if entry.last_modified == 0 {
+                entry.last_modified = now;
+            }
+            self.vault.entries.push(entry);
+        }
+        Ok(added)
+    }
+}
+
+// --- age Export ---
+
+/// AGE: A new X25519 identity to export to. Returns JSON {identity, recipient}.
+pub fn generate_age_identity() -> Result<String, String> {
+    serde_json::to_string(&age::generate_identity()?).map_err(|e| format!("Identity serialize error: {}", e))
+}
+
+impl Engine {
+    /// AGE: The loaded vault as JSON in an age file sealed to a passphrase.
+    /// Opens with `age -d` (or `rage -d`) without SecurePass.
+    pub fn export_age_passphrase(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let exported = age::encrypt_with_passphrase(passphrase, json.as_bytes());
+        json.zeroize();
+        exported
+    }
+
+    /// AGE: The loaded vault as JSON in an age file sealed to "age1..." recipients,
+    /// one per line. Opens with `age -d -i <identity file>`.
+    pub fn export_age_recipients(&self, recipients: &str) -> Result<Vec<u8>, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let exported = age::encrypt_to_recipients(recipients, json.as_bytes());
+        json.zeroize();
+        exported
+    }
+}
+
+// --- OpenPGP Export ---
+
+/// OPENPGP: Checks a public key and names the subkey an export would be encrypted to.
+/// Returns JSON {fingerprint, user_ids, encryption_subkey}.
+#[cfg(feature = "openpgp")]
+pub fn describe_openpgp_key(public_key: &[u8], now: u64) -> Result<String, String> {
+    serde_json::to_string(&openpgp::describe_key(public_key, now)?).map_err(|e| format!("Key serialize error: {}", e))
+}
+
+#[cfg(feature = "openpgp")]
+impl Engine {
+    /// OPENPGP: The loaded vault as JSON in an armored message for an OpenPGP public key.
+    /// Opens with `gpg -d` on the machine that holds the secret key.
+    pub fn export_openpgp(&self, public_key: &[u8], now: u64) -> Result<String, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let exported = openpgp::encrypt(public_key, json.as_bytes(), now);
+        json.zeroize();
+        exported
+    }
+}
+
+// --- Export Confirmation ---
+
+impl Engine {
+    /// EXPORT: Starts a two-step export. Returns JSON {token, expiresAt}: pass the
+    /// token to 'export_vault' with the master password before it expires. Times
+    /// come from the engine's clock.
+    pub fn request_export(&mut self) -> Result<String, String> {
+        let ticket = self.exports.request((self.clock)())?;
+        serde_json::to_string(&ticket).map_err(|e| format!("Ticket serialize error: {}", e))
+    }
+
+    /// EXPORT: The loaded vault in the format 'format_json' names (see
+    /// 'export_gate::ExportFormat'); see 'export_confirmed'.
+    pub fn export_vault(&mut self, format_json: &str, token: &str, password: &str) -> Result<Vec<u8>, String> {
+        let format: export_gate::ExportFormat =
+            serde_json::from_str(format_json).map_err(|e| redact::json_error("Export format parse", &e))?;
+        self.export_confirmed(format, token, password)
+    }
+
+    /// EXPORT: The loaded vault in `format`, once 'token' from 'request_export' comes
+    /// back with the master password ('unlock_retyped'). The token is spent by this
+    /// call, whatever the outcome.
+    pub fn export_confirmed(&mut self, format: export_gate::ExportFormat, token: &str, password: &str) -> Result<Vec<u8>, String> {
+        let now = (self.clock)();
+        self.exports.redeem(token, now)?;
+        self.unlock_retyped(password)?;
+
+        match format {
+            export_gate::ExportFormat::Json => self.export_json(now).map(String::into_bytes),
+            export_gate::ExportFormat::AgePassphrase { passphrase } => self.export_age_passphrase(&passphrase),
+            export_gate::ExportFormat::AgeRecipients { recipients } => self.export_age_recipients(&recipients),
+            #[cfg(feature = "openpgp")]
+            export_gate::ExportFormat::Openpgp { public_key } => {
+                let public_key = match public_key.trim_start().starts_with("-----BEGIN") {
+                    true => public_key.into_bytes(),
+                    false => B64.decode(public_key.trim()).map_err(|e| format!("Public key decode error: {}", e))?,
+                };
+                self.export_openpgp(&public_key, now).map(String::into_bytes)
+            }
+            #[cfg(feature = "importers")]
+            export_gate::ExportFormat::Aegis { password } => self.export_totp_aegis(&password).map(String::into_bytes),
+            #[cfg(feature = "importers")]
+            export_gate::ExportFormat::Andotp { password } => self.export_totp_andotp(&password),
+            #[cfg(not(feature = "importers"))]
+            export_gate::ExportFormat::Aegis { .. } | export_gate::ExportFormat::Andotp { .. } => {
+                Err("Export error: this build has no TOTP export".to_string())
+            }
+            #[cfg(not(feature = "openpgp"))]
+            export_gate::ExportFormat::Openpgp { .. } => Err("Export error: this build has no OpenPGP export".to_string()),
+        }
+    }
+}
+
+// --- Offline Sync ---
+
+impl Engine {
+    /// SYNC: Records how the loaded vault differs from the log's replay as new ops.
+    /// Returns them sealed, as a JSON array of {version, id, iv, data}, for the
+    /// front end to store and share. Call this before 'sync_merge', which replaces the vault.
+    pub fn sync_record(&self, log: &mut oplog::OpLog) -> Result<String, String> {
+        self.awake()?;
+        let mut replayed = log.materialize();
+        let changes = oplog::diff(&replayed, &self.vault);
+        replayed.wipe();
+        let sealed = log
+            .record(changes?)
+            .iter()
+            .map(|op| oplog::seal_op(&self.master_key, op))
+            .collect::<Result<Vec<_>, _>>()?;
+        serde_json::to_string(&sealed).map_err(|e| format!("Sync serialize error: {}", e))
+    }
+
+    /// SYNC: Opens sealed ops (a JSON array, from other devices or local storage),
+    /// merges them into the log and replaces the loaded vault with the replay.
+    /// Returns how many ops were new.
+    pub fn sync_merge(&mut self, log: &mut oplog::OpLog, sealed_json: &str) -> Result<usize, String> {
+        self.wake()?;
+        let sealed: Vec<oplog::SealedOp> =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Sync parse error: {}", e))?;
+        let mut ops = sealed.iter().map(|s| oplog::open_op(&self.master_key, s)).collect::<Result<Vec<_>, _>>()?;
+        ops.retain(|op| self.is_listed(&op.id.device));
+        let added = log.merge(ops);
+        self.reload_from(log);
+        Ok(added)
+    }
+
+    /// SYNC: Packs sealed ops from 'sync_record' into this device's next blob for
+    /// remote storage. Merge every pending blob first, so the numbering continues.
+    pub fn sync_pack_ops(&self, log: &mut oplog::OpLog, sealed_json: &str) -> Result<blobs::SyncBlob, String> {
+        let sealed: Vec<oplog::SealedOp> =
+            serde_json::from_str(sealed_json).map_err(|e| format!("Sync parse error: {}", e))?;
+        blobs::pack_ops(&self.master_key, log, &sealed)
+    }
+
+    /// SYNC: Folds the log into a snapshot blob and moves the log onto it. Upload it,
+    /// then delete the names 'blobs::compaction_plan' returns.
+    pub fn sync_pack_snapshot(&self, log: &mut oplog::OpLog) -> Result<blobs::SyncBlob, String> {
+        blobs::pack_snapshot(&self.master_key, log)
+    }
+
+    /// SYNC: Merges one downloaded blob (in 'blobs::pending' order) and replaces the
+    /// loaded vault with the replay, like 'sync_merge'. Returns how many ops were new.
+    /// After 'join_device_list', a blob from a device not in the list is skipped.
+    pub fn sync_merge_blob(&mut self, log: &mut oplog::OpLog, name: &str, data: &[u8]) -> Result<usize, String> {
+        self.wake()?;
+        match blobs::BlobName::parse(name) {
+            Some(blobs::BlobName::Ops { device, number }) if !self.is_listed(&device) => {
+                log.set_last_blob(&device, number.max(log.last_blob(&device)));
+                return Ok(0);
+            }
+            Some(blobs::BlobName::Snapshot { device, .. }) if !self.is_listed(&device) => return Ok(0),
+            _ => {}
+        }
+        let added = blobs::merge_blob(&self.master_key, log, name, data)?;
+        self.reload_from(log);
+        Ok(added)
+    }
+
+    fn is_listed(&self, device: &str) -> bool {
+        self.devices.as_ref().is_none_or(|list| list.contains(device))
+    }
+
+    fn reload_from(&mut self, log: &oplog::OpLog) {
+        let mut previous = std::mem::replace(&mut self.vault, log.materialize());
+        previous.wipe();
+    }
+}
+
+// --- Devices ---
+
+/// DEVICES: A keypair for this device, to enroll with 'enroll_device'. Returns
+/// JSON {public_key, secret_key}; keep the secret on the device.
+pub fn generate_device_keypair() -> Result<String, String> {
+    serde_json::to_string(&devices::generate_keypair()).map_err(|e| format!("Keypair serialize error: {}", e))
+}
+
+impl Engine {
+    /// DEVICES: Adds a device (its sync ID, a name and its public key) to the signed
+    /// device list `list_json` ("" to start one). Returns the new signed list JSON.
+    pub fn enroll_device(&self, list_json: &str, device_id: &str, name: &str, public_key: &str, now: u64) -> Result<String, String> {
+        let mut list = self.verified_device_list(list_json)?;
+        list.enroll(devices::Device {
+            device_id: device_id.to_string(),
+            name: name.trim().to_string(),
+            public_key: public_key.to_string(),
+            enrolled_at: now,
+        })?;
+        self.signed_device_list(list)
+    }
+
+    /// DEVICES: Removes a device from the signed list. Returns the new signed list
+    /// JSON; its ops and snapshots are no longer merged by devices that rejoin it.
+    pub fn revoke_device(&mut self, list_json: &str, device_id: &str) -> Result<String, String> {
+        let mut list = self.verified_device_list(list_json)?;
+        if !list.revoke(device_id) {
+            return Err("Device error: no such device".to_string());
+        }
+        if self.devices.is_some() {
+            self.devices = Some(list.clone());
+        }
+        self.signed_device_list(list)
+    }
+
+    /// DEVICES: Checks the list's signature and returns it as JSON {version, serial,
+    /// devices: [{deviceId, name, publicKey, enrolledAt}]}, for a "my devices" screen.
+    pub fn verify_device_list(&self, list_json: &str) -> Result<String, String> {
+        let list = self.verified_device_list(list_json)?;
+        serde_json::to_string(&list).map_err(|e| format!("Device list serialize error: {}", e))
+    }
+
+    /// DEVICES: Proves this device is in the signed list and, from then on, merges
+    /// only ops and snapshots made by listed devices. `known_serial` is the highest
+    /// serial this device has seen (0 the first time); returns the list's, to store.
+    pub fn join_device_list(&mut self, list_json: &str, device_secret_key: &str, known_serial: u64) -> Result<u64, String> {
+        let signed: devices::SignedDeviceList =
+            serde_json::from_str(list_json).map_err(|e| format!("Device list parse error: {}", e))?;
+        let (list, _) = devices::join(&self.master_key, &signed, device_secret_key, known_serial)?;
+        let serial = list.serial;
+        self.devices = Some(list);
+        Ok(serial)
+    }
+
+    fn verified_device_list(&self, list_json: &str) -> Result<devices::DeviceList, String> {
+        if list_json.trim().is_empty() {
+            return Ok(devices::DeviceList::default());
+        }
+        let signed: devices::SignedDeviceList =
+            serde_json::from_str(list_json).map_err(|e| format!("Device list parse error: {}", e))?;
+        devices::verify(&self.master_key, &signed)
+    }
+
+    fn signed_device_list(&self, list: devices::DeviceList) -> Result<String, String> {
+        let signed = devices::sign(&self.master_key, list)?;
+        serde_json::to_string(&signed).map_err(|e| format!("Device list serialize error: {}", e))
+    }
+}
+
+// --- Attachments ---
+
+impl Engine {
+    /// ATTACH: Encrypts a file under a fresh attachment ID, or, when the vault's
+    /// 'dedupAttachments' setting is on, under an ID derived from the file: the
+    /// same file gets the same ID and content blob again, so store it once.
+    pub fn encrypt_attachment(&self, content: &[u8], filename: &str, mime_type: &str) -> Result<EncryptedAttachment, String> {
+        let meta = attachments::AttachmentMeta {
+            filename: filename.to_string(),
+            mime_type: mime_type.to_string(),
+            size: content.len() as u64,
+        };
+        let (id, content) = if self.settings().is_ok_and(|s| s.dedup_attachments) {
+            attachments::encrypt_content_convergent(&self.master_key, content)?
+        } else {
+            let id = attachments::new_attachment_id();
+            let content = attachments::encrypt_content(&self.master_key, &id, content)?;
+            (id, content)
+        };
+        let sealed_meta = attachments::encrypt_meta(&self.master_key, &id, &meta)?;
+
+        Ok(EncryptedAttachment {
+            meta: serde_json::to_string(&sealed_meta).map_err(|e| format!("Meta serialize error: {}", e))?,
+            id,
+            content,
+        })
+    }
+
+    /// META: Decrypts only the filename, MIME type and size. Returns JSON.
+    pub fn decrypt_attachment_meta(&self, attachment_id: &str, meta_json: &str) -> Result<String, String> {
+        let sealed: attachments::SealedMeta =
+            serde_json::from_str(meta_json).map_err(|e| format!("Meta parse error: {}", e))?;
+        let meta = attachments::decrypt_meta(&self.master_key, attachment_id, &sealed)?;
+        serde_json::to_string(&meta).map_err(|e| format!("Meta serialize error: {}", e))
+    }
+
+    /// CONTENT: Decrypts the file bytes when the user actually opens the attachment.
+    pub fn decrypt_attachment(&self, attachment_id: &str, content: &[u8]) -> Result<Vec<u8>, String> {
+        attachments::decrypt_content(&self.master_key, attachment_id, content)
+    }
+}
+
+// --- Duress Vaults & Key Hierarchy ---
+
+impl Engine {
+    /// CREATE DECOY: Returns a new slot list (JSON) holding the loaded vault and a decoy
+    /// vault that opens with 'decoy_password' over the same 'salt'.
+    pub fn create_decoy_slots(&self, decoy_password: &str, salt: &[u8], decoy_vault_json: &str) -> Result<String, String> {
+        self.awake()?;
+        validate::new_password(decoy_password, "decoy password")?;
+        let decoy = Self::new(decoy_password, salt)?;
+        vault::VaultData::from_json(decoy_vault_json)?;
+
+        let mut json = self.vault.to_json()?;
+        let slots = duress::build_slots(&self.master_key, &json, &decoy.master_key, decoy_vault_json);
+        json.zeroize();
+        serde_json::to_string(&slots?).map_err(|e| format!("Slots serialize error: {}", e))
+    }
+
+    /// Slot list for a user without a decoy: the loaded vault plus random filler,
+    /// so storage looks the same whether or not a decoy exists.
+    pub fn create_vault_slots(&self) -> Result<String, String> {
+        self.awake()?;
+        let mut json = self.vault.to_json()?;
+        let real = self.encrypt_package(&json);
+        let filler = duress::filler_slot(json.len());
+        json.zeroize();
+
+        let mut slots = vec![real?, filler];
+        slots.shuffle(&mut rand::thread_rng());
+        serde_json::to_string(&slots).map_err(|e| format!("Slots serialize error: {}", e))
+    }
+
+    /// OPEN: Derives the key once and loads whichever slot it opens.
+    /// A wrong password and a missing decoy fail with the same error.
+    pub fn open_slots(password: &str, salt: &[u8], slots_json: &str) -> Result<Engine, String> {
+        let slots: Vec<duress::EncryptedPackage> =
+            serde_json::from_str(slots_json).map_err(|e| format!("Slots parse error: {}", e))?;
+        let mut engine = Self::new(password, salt)?;
+
+        let (_, mut json) =
+            duress::open_slots(&engine.master_key, &slots).ok_or("Decryption error: no vault matches this password")?;
+        let parsed = vault::VaultData::from_json(&json);
+        json.zeroize();
+        engine.vault = parsed?;
+        Ok(engine)
+    }
+
+    /// SAVE: Writes the loaded vault back into its own slot and returns the updated list.
+    pub fn seal_into_slots(&self, slots_json: &str) -> Result<String, String> {
+        self.awake()?;
+        let mut slots: Vec<duress::EncryptedPackage> =
+            serde_json::from_str(slots_json).map_err(|e| format!("Slots parse error: {}", e))?;
+        let mut json = self.vault.to_json()?;
+        let resealed = duress::reseal_slot(&self.master_key, &mut slots, &json);
+        json.zeroize();
+        resealed?;
+        serde_json::to_string(&slots).map_err(|e| format!("Slots serialize error: {}", e))
+    }
+
+    /// JSON describing the KDF parameters, derived and wrapped keys, and ciphers in use.
+    /// Contains a key fingerprint but never key material.
+    pub fn describe_key_hierarchy(&self) -> Result<String, String> {
+        serde_json::to_string(&hierarchy::describe(&self.master_key)).map_err(|e| format!("Hierarchy serialize error: {}", e))
+    }
+}
+
+// --- Memory Security (Cleanup) ---
+// When the engine is destroyed, we physically wipe the master key from memory.
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.master_key.zeroize(); // Overwrites the key with zeros in RAM
+        self.vault.wipe(); // ...and every decrypted secret the engine was holding
+        #[cfg(feature = "importers")]
+        self.cancel_import();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use sha1::Digest;
+    use std::cell::Cell;
+
+    thread_local! {
+        static TEST_NOW: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// An engine clock the test sets with 'set_test_clock'; each test runs on its own thread.
+    fn test_clock() -> u64 {
+        TEST_NOW.with(Cell::get)
+    }
+
+    fn set_test_clock(now: u64) {
+        TEST_NOW.with(|n| n.set(now));
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        let password = "master-password";
+        let salt = b"some-salt-123456";
+        let engine = Engine::new(password, salt).unwrap();
+        assert_eq!(engine.master_key.len(), 32);
+
+        let engine2 = Engine::new(password, salt).unwrap();
+        assert_eq!(engine.master_key, engine2.master_key);
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let engine = Engine::new("pwd", b"salt-123456789012").unwrap();
+        let plaintext = "Sensitive data to protect";
+        let iv = [0u8; 12];
+
+        let ciphertext = engine.encrypt(plaintext, &iv).unwrap();
+        assert_ne!(ciphertext, plaintext.as_bytes());
+
+        let decrypted = engine.decrypt(&ciphertext, &iv).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_wrong_length_ivs_are_refused() {
+        let engine = Engine::from_key([0u8; 32]);
+        let sealed = engine.encrypt("secret", &[0u8; 12]).unwrap();
+        for iv in [&[][..], &[0u8; 11], &[0u8; 16]] {
+            let iv_length = |e: String| e.starts_with("Input error [iv_length]");
+            assert!(iv_length(engine.encrypt("secret", iv).unwrap_err()));
+            assert!(iv_length(engine.decrypt(&sealed, iv).unwrap_err()));
+            assert!(iv_length(engine.seal_vault(iv).unwrap_err()));
+            assert!(iv_length(Engine::from_key([0u8; 32]).load_vault(&sealed, iv).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn test_malformed_inputs_are_coded_errors_not_panics() {
+        let engine = Engine::from_key([0u8; 32]);
+        let code = |result: Result<_, String>| validate::error_code(&result.unwrap_err());
+        assert_eq!(code(engine.encrypt("x", &[0u8; 11]).map(drop)), Some(validate::InputCode::IvLength));
+        assert_eq!(code(engine.decrypt(&[0u8; 32], &[]).map(drop)), Some(validate::InputCode::IvLength));
+        assert_eq!(code(Engine::new("pw", b"short").map(drop)), Some(validate::InputCode::SaltLength));
+        assert_eq!(code(crate::biometric::wrap_password("pw", &[0u8; 16], &[0u8; 12]).map(drop)), Some(validate::InputCode::KeyLength));
+
+        let not_text = Aes256Gcm::new_from_slice(&[0u8; 32]).unwrap().encrypt(&[0u8; 12].into(), &[0xffu8][..]).unwrap();
+        assert_eq!(code(engine.decrypt(&not_text, &[0u8; 12]).map(drop)), Some(validate::InputCode::InvalidUtf8));
+    }
+
+    #[test]
+    fn test_totp_generation() {
+        let engine = Engine::from_key([0u8; 32]);
+        let secret = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+        let code = engine.get_totp_code(secret).unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_normalize_totp_secret() {
+        let normalized: serde_json::Value = serde_json::from_str(&normalize_totp_secret("jbsw y3dp ehpk 3pxp").unwrap()).unwrap();
+        assert_eq!((normalized["secret"].as_str(), normalized["digits"].as_u64()), (Some("JBSWY3DPEHPK3PXP"), Some(6)));
+
+        let engine = Engine::from_key([0u8; 32]);
+        let spaced = "jbsw y3dp ehpk 3pxp jbsw y3dp ehpk 3pxp jbsw y3dp ehpk 3pxp";
+        assert_eq!(engine.get_totp_code(spaced).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_search_index_roundtrip() {
+        let mut engine = Engine::from_key([3u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "1", "title": "GitHub"}, {"id": "2", "title": "Bank", "username": "octo"}]}"#,
+        )
+        .unwrap();
+        let index = engine.build_search_index().unwrap();
+        assert_eq!(engine.search_index(&index, "octo").unwrap(), r#"["2"]"#);
+
+        engine.vault.entries[0].title = "GitLab".to_string();
+        let index = engine.index_add(&index, "1").unwrap();
+        assert_eq!(engine.search_index(&index, "lab").unwrap(), r#"["1"]"#);
+        let index = engine.index_remove(&index, "1").unwrap();
+        assert_eq!(engine.search_index(&index, "").unwrap(), r#"["2"]"#);
+
+        let compacted: serde_json::Value = serde_json::from_str(&engine.compact_search_index(&index, true).unwrap()).unwrap();
+        assert!(compacted["index"].get("pending").is_none() && compacted["compacted"] == true);
+        let rebuilt: serde_json::Value = serde_json::from_str(&engine.index_rebuild_partial(&index, "", 1).unwrap()).unwrap();
+        assert_eq!(rebuilt["next"], "1");
+        let rebuilt: serde_json::Value =
+            serde_json::from_str(&engine.index_rebuild_partial(&rebuilt["index"].to_string(), "1", 1).unwrap()).unwrap();
+        assert!(rebuilt["next"].is_null());
+        assert_eq!(engine.search_index(&rebuilt["index"].to_string(), "lab").unwrap(), r#"["1"]"#);
+
+        assert!(Engine::from_key([4u8; 32]).search_index(&index, "bank").is_err());
+        assert!(engine.index_add(&index, "9").is_err());
+    }
+
+    #[test]
+    fn test_yubikey_entries() {
+        let mut engine = Engine::from_key([0u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [
+                {"id": "cr", "title": "KeePassXC", "type": "yubikey",
+                 "yubikey": {"mode": "challengeResponse", "secret": "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b", "slot": 2}},
+                {"id": "otp", "title": "YubiCloud", "type": "yubikey",
+                 "yubikey": {"mode": "otp", "publicId": "vvccccdtfbnc", "privateId": "8792ebfe26cc", "aesKey": "ecde18dbe76fbd0c33330f1c354871db"}},
+                {"id": "mail", "title": "Mail"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let response = engine.yubikey_challenge_response("cr", b"Hi There").unwrap();
+        assert_eq!(response[..4], [0xb6, 0x17, 0x31, 0x86]);
+        assert!(engine.yubikey_challenge_response("otp", b"x").unwrap_err().contains("Yubico OTP"));
+        assert!(engine.verify_yubikey_otp("cr", "x").is_err());
+        assert!(engine.verify_yubikey_otp("mail", "x").unwrap_err().contains("no YubiKey"));
+        assert!(engine.verify_yubikey_otp("otp", "vvccccdtfbnccccccccccccccccccccccccccccccccc").unwrap_err().contains("checksum"));
+
+        // An idle vault answers from the entry's sealed copy.
+        engine.go_idle().unwrap();
+        assert_eq!(engine.yubikey_challenge_response("cr", b"Hi There").unwrap(), response);
+        engine.wake().unwrap();
+
+        engine.vault.entries[0].wipe();
+        assert!(engine.yubikey_challenge_response("cr", b"Hi There").is_err());
+    }
+
+    #[test]
+    fn test_totp_code_honours_parameters() {
+        let engine = Engine::from_key([0u8; 32]);
+        // RFC 6238 SHA256 seed, T = 59.
+        let secret = otp::encode_base32(b"12345678901234567890123456789012");
+        assert_eq!(engine.get_totp_code_with(&secret, "sha-256", 8, 30, 59).unwrap(), "46119246");
+        let uri = format!("otpauth://totp/IdP:me?secret={}&algorithm=SHA256&digits=8", secret);
+        assert_eq!(engine.get_totp_code_at(&uri, 59, 0).unwrap(), "46119246");
+        assert_eq!(engine.get_totp_code(&uri).unwrap().len(), 8);
+        assert!(engine.get_totp_code_with(&secret, "MD5", 6, 30, 59).is_err());
+    }
+
+    #[test]
+    fn test_totp_with_corrected_clock() {
+        let engine = Engine::from_key([0u8; 32]);
+        let secret = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+        let code = engine.get_totp_code_at(secret, 1_700_000_000, 0).unwrap();
+
+        let skew = estimate_totp_skew(secret, &code, 1_700_000_000 - 3600).unwrap();
+        assert_eq!(engine.get_totp_code_at(secret, 1_700_000_000 - 3600, skew).unwrap(), code);
+        assert!(estimate_totp_skew(secret, "abc", 0).is_err());
+    }
+
+    #[test]
+    fn test_history_rotation() {
+        let engine = Engine::from_key([0u8; 32]);
+        let history_json = "[\"old1\", \"old2\"]";
+        let new_history = engine.rotate_history("new_pwd", history_json).unwrap();
+
+        let parsed: Vec<String> = serde_json::from_str(&new_history).unwrap();
+        assert_eq!(parsed[0], "new_pwd");
+        assert_eq!(parsed[1], "old1");
+        assert_eq!(parsed.len(), 3);
+
+        let full_history = "[\"1\", \"2\", \"3\", \"4\", \"5\"]";
+        let rotated = engine.rotate_history("6", full_history).unwrap();
+        let parsed_full: Vec<String> = serde_json::from_str(&rotated).unwrap();
+        assert_eq!(parsed_full.len(), 5);
+        assert_eq!(parsed_full[0], "6");
+        assert_eq!(parsed_full[4], "4");
+    }
+
+    #[test]
+    fn test_emergency_grant_roundtrip() {
+        let owner = Engine::new("owner-pwd", b"salt-123456789012").unwrap();
+        let contact: emergency::EmergencyKeypair = serde_json::from_str(&generate_emergency_keypair().unwrap()).unwrap();
+
+        assert_eq!(owner.create_emergency_grant("wrong", &contact.public_key, 60, 100).unwrap_err(), kdf::UNLOCK_FAILED);
+        let grant = owner.create_emergency_grant("owner-pwd", &contact.public_key, 60, 100).unwrap();
+        let request = request_emergency_access(&grant, 200).unwrap();
+
+        assert!(Engine::from_emergency_grant(&grant, &request, &contact.secret_key, 259).is_err());
+        let recovered = Engine::from_emergency_grant(&grant, &request, &contact.secret_key, 260).unwrap();
+        assert_eq!(recovered.master_key, owner.master_key);
+    }
+
+    #[test]
+    fn test_device_bound_vault_needs_the_device_output() {
+        let salt = b"salt-123456789012";
+        let binding = serde_json::to_string(&device_key::DeviceBinding::generate()).unwrap();
+        let response = [9u8; device_key::RESPONSE_LEN];
+
+        let mut engine = Engine::new("pw", salt).unwrap();
+        engine.bind_to_device(&binding, &response).unwrap();
+        let package = engine.seal_vault_package().unwrap();
+
+        let unbound = Engine::new("pw", salt).unwrap();
+        assert!(unbound.decrypt_package(&package).is_err());
+        let mut bound = Engine::new("pw", salt).unwrap();
+        bound.bind_to_device(&binding, &response).unwrap();
+        assert!(bound.decrypt_package(&package).is_ok());
+        assert!(bound.bind_to_device(&binding, &response[1..]).is_err());
+    }
+
+    #[test]
+    fn test_sharing_key_rotation() {
+        let bob: serde_json::Value = serde_json::from_str(&generate_emergency_keypair().unwrap()).unwrap();
+        let bob_secret = bob["secret_key"].as_str().unwrap();
+        let mut alice = Engine::from_key([1u8; 32]);
+        alice.vault = vault::VaultData::from_json(r#"{"entries": [{"id": "1", "title": "Wi-Fi", "password": "pw"}]}"#).unwrap();
+        let bundle = alice.share_entry("1", bob["public_key"].as_str().unwrap(), false, 5).unwrap();
+
+        let rotated: serde_json::Value =
+            serde_json::from_str(&rotate_sharing_keypair(bob_secret, &format!("[{}]", bundle), 9).unwrap()).unwrap();
+        let keys = verify_sharing_key_rotation(&rotated["record"].to_string(), &sharing_verify_key(bob_secret).unwrap()).unwrap();
+        assert!(keys.contains(rotated["keypair"]["public_key"].as_str().unwrap()));
+
+        let mut bob_vault = Engine::from_key([2u8; 32]);
+        let moved = rotated["received"][0].to_string();
+        assert!(bob_vault.import_shared_entry(&moved, bob_secret, 9).is_err());
+        bob_vault.import_shared_entry(&moved, rotated["keypair"]["secret_key"].as_str().unwrap(), 9).unwrap();
+    }
+
+    #[test]
+    fn test_time_boxed_share_is_viewed_not_kept() {
+        let mut alice = Engine::from_key([1u8; 32]);
+        alice.vault = vault::VaultData::from_json(r#"{"entries": [{"id": "1", "title": "Wi-Fi", "password": "guest-pass"}]}"#).unwrap();
+        let (alice_keys, bob_keys) = (emergency::generate_keypair(), emergency::generate_keypair());
+        let bundle = alice.share_entry_until("1", &bob_keys.public_key, &alice_keys.secret_key, false, 1_000, 100).unwrap();
+
+        let opened: serde_json::Value = serde_json::from_str(&open_shared_entry(&bundle, &bob_keys.secret_key, 500).unwrap()).unwrap();
+        assert_eq!((opened["entry"]["password"].as_str(), opened["expiresAt"].as_u64()), (Some("guest-pass"), Some(1_000)));
+        assert_eq!(opened["ownerVerifyKey"].as_str().unwrap(), sharing_verify_key(&alice_keys.secret_key).unwrap());
+        assert!(open_shared_entry(&bundle, &bob_keys.secret_key, 1_000).unwrap_err().contains("expired"));
+
+        let mut bob = Engine::from_key([2u8; 32]);
+        assert!(bob.import_shared_entry(&bundle, &bob_keys.secret_key, 500).unwrap_err().contains("time-boxed"));
+        assert!(bob.vault().entries.is_empty());
+    }
+
+    #[test]
+    fn test_share_entry_between_vaults() {
+        let mut alice = Engine::from_key([1u8; 32]);
+        alice.vault = vault::VaultData::from_json(r#"{"entries": [{"id": "1", "title": "Wi-Fi", "password": "guest-pass"}]}"#).unwrap();
+        let bob_keys = emergency::generate_keypair();
+        let bundle = alice.share_entry("1", &bob_keys.public_key, false, 100).unwrap();
+        assert!(alice.share_entry("2", &bob_keys.public_key, false, 100).is_err());
+
+        let mut bob = Engine::from_key([2u8; 32]);
+        let id = bob.import_shared_entry(&bundle, &bob_keys.secret_key, 200).unwrap();
+        let received = &bob.vault().entries[0];
+        assert_eq!((received.id.as_str(), received.password.as_str(), received.last_modified), (id.as_str(), "guest-pass", 200));
+        assert_ne!(id, "1");
+    }
+
+    #[test]
+    fn test_pin_unlock_recovers_the_master_key() {
+        let engine = Engine::new("pin-owner", b"salt-123456789012").unwrap();
+        let pepper = pin::generate_pepper();
+        assert!(engine.create_pin_lock("wrong", "1357", &pepper, pin::DEFAULT_MAX_ATTEMPTS).is_err());
+        let mut lock = engine.create_pin_lock("pin-owner", "1357", &pepper, pin::DEFAULT_MAX_ATTEMPTS).unwrap();
+
+        assert!(Engine::from_pin("7531", &pepper, &mut lock).is_err());
+        assert_eq!(Engine::from_pin("1357", &pepper, &mut lock).unwrap().master_key, engine.master_key);
+        assert_eq!(lock.attempts_left(), pin::DEFAULT_MAX_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_travel_vault_contains_only_safe_entries() {
+        let engine = Engine::from_key([1u8; 32]);
+        let vault_json = r#"{"entries": [
+            {"id": "a", "title": "Mail", "password": "x", "category": "personal"},
+            {"id": "b", "title": "Crypto", "password": "y", "category": "personal", "travelMode": true}
+        ]}"#;
+        let iv = [3u8; 12];
+
+        let ciphertext = engine.build_travel_vault(vault_json, &iv).unwrap();
+        let travel = vault::VaultData::from_json(&engine.decrypt(&ciphertext, &iv).unwrap()).unwrap();
+        assert_eq!(travel.entries.len(), 1);
+        assert_eq!(travel.entries[0].id, "a");
+    }
+
+    #[test]
+    fn test_vault_store_and_fill_payload() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let vault_json = r#"{"entries": [
+            {"id": "gh", "title": "GitHub", "username": "octo", "password": "pw", "url": "https://github.com"}
+        ]}"#;
+        let iv = [5u8; 12];
+        let ciphertext = engine.encrypt(vault_json, &iv).unwrap();
+
+        engine.load_vault(&ciphertext, &iv).unwrap();
+        assert_eq!(engine.entry_count(), 1);
+
+        let form = r#"{"fields": [{"field_id": "u", "type": "text", "name": "login"}, {"field_id": "p", "type": "password"}]}"#;
+        let payload = engine.build_fill_payload("gh", "https://github.com/login", form).unwrap();
+        assert!(payload.contains(r#""field_id":"p","kind":"password","value":"pw""#));
+        assert!(engine.build_fill_payload("missing", "https://github.com", form).is_err());
+
+        assert!(engine.seal_vault(&iv).unwrap_err().contains("already used"));
+        let resealed = engine.seal_vault_package().unwrap();
+        let reopened = vault::VaultData::from_json(&engine.decrypt_package(&resealed).unwrap()).unwrap();
+        assert_eq!(reopened.entries[0].id, "gh");
+    }
+
+    #[test]
+    fn test_master_key_nonces_never_repeat() {
+        let engine = Engine::from_key([1u8; 32]);
+        let (first, second) = (engine.encrypt_package("a").unwrap(), engine.encrypt_package("a").unwrap());
+        assert_ne!(first.iv, second.iv);
+        assert_eq!(engine.decrypt_package(&second).unwrap(), "a");
+
+        // Raw IVs: each once, and never one from the engine's own range.
+        engine.encrypt("b", &[9u8; 12]).unwrap();
+        assert!(engine.encrypt("c", &[9u8; 12]).unwrap_err().contains("already used"));
+        let mut ahead = second.iv.clone();
+        ahead[11] += 5;
+        assert!(engine.encrypt("c", &ahead).unwrap_err().contains("already used"));
+        engine.decrypt_package(&first).unwrap();
+        assert!(engine.encrypt("c", &first.iv).is_err());
+
+        // Opening records nothing; sealing with caller IVs is capped.
+        let foreign = Engine::from_key([1u8; 32]).encrypt_package("d").unwrap();
+        engine.decrypt_package(&foreign).unwrap();
+        assert_eq!(engine.sealed_ivs.borrow().len(), 1);
+        for i in 1..MAX_CALLER_IVS as u64 {
+            let mut iv = [0u8; 12];
+            iv[4..].copy_from_slice(&i.to_be_bytes());
+            engine.encrypt("e", &iv).unwrap();
+        }
+        assert!(engine.encrypt("e", &[8u8; 12]).unwrap_err().contains("too many"));
+    }
+
+    #[test]
+    fn test_idle_vault_fills_but_does_not_save() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault.entries.push(vault::VaultEntry {
+            id: "gh".to_string(),
+            password: "pw".to_string(),
+            url: "https://github.com".to_string(),
+            ..Default::default()
+        });
+        engine.go_idle().unwrap();
+        assert!(engine.is_idle() && engine.vault.entries[0].password.is_empty());
+
+        let form = r#"{"fields": [{"field_id": "p", "type": "password"}]}"#;
+        assert!(engine.build_fill_payload("gh", "https://github.com", form).unwrap().contains(r#""value":"pw""#));
+        assert!(engine.reveal_entry("gh").unwrap().contains(r#""password":"pw""#));
+        assert_eq!(engine.seal_vault(&[5u8; 12]).unwrap_err(), idle::IDLE);
+        assert_eq!(engine.card_summary("gh", 0).unwrap_err(), idle::IDLE);
+        // The seeds are sealed away, so an export would come out empty: it fails instead.
+        #[cfg(feature = "importers")]
+        assert_eq!(engine.export_totp_aegis("export pw").unwrap_err(), idle::IDLE);
+        #[cfg(feature = "importers")]
+        assert_eq!(engine.export_totp_andotp("export pw").unwrap_err(), idle::IDLE);
+
+        engine.wake().unwrap();
+        assert!(!engine.is_idle() && engine.vault.entries[0].password == "pw");
+        assert!(engine.seal_vault(&[5u8; 12]).is_ok());
+    }
+
+    #[test]
+    fn test_reprompt_entries_need_a_recent_confirmation() {
+        let key = [1u8; 32];
+        let fast = kdf::KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let envelope = kdf::KeyEnvelope::wrap(&key, "master pw", text::PasswordPolicy::CURRENT, fast).unwrap();
+        let mut engine = Engine::from_envelope("master pw", &envelope).unwrap();
+        engine.clock = test_clock;
+        engine.vault.entries.push(vault::VaultEntry { id: "seed".to_string(), password: "pw".to_string(), ..Default::default() });
+
+        set_test_clock(10);
+        engine.set_entry_reprompt("seed", true, 10).unwrap();
+        assert_eq!(engine.reveal_entry("seed").unwrap_err(), reprompt::REPROMPT_REQUIRED);
+        assert!(engine.share_entry("seed", "", false, 10).unwrap_err().contains("Reprompt"));
+        assert!(engine.yubikey_challenge_response("seed", b"c").unwrap_err().contains("Reprompt"));
+        assert!(engine.build_identity_fill("seed", "{}").unwrap_err().contains("Reprompt"));
+        assert_eq!(engine.confirm_master("wrong").unwrap_err(), kdf::UNLOCK_FAILED);
+
+        set_test_clock(20);
+        engine.confirm_master("master pw").unwrap();
+        set_test_clock(20 + reprompt::DEFAULT_WINDOW_SECS);
+        assert!(engine.reveal_entry("seed").unwrap().contains(r#""password":"pw""#));
+        set_test_clock(21 + reprompt::DEFAULT_WINDOW_SECS);
+        assert!(engine.reveal_entry("seed").is_err());
+
+        // The window can't be stretched past the cap.
+        engine.set_reprompt_window(u64::MAX);
+        engine.confirm_master("master pw").unwrap();
+        set_test_clock(22 + reprompt::DEFAULT_WINDOW_SECS + reprompt::MAX_WINDOW_SECS);
+        assert!(engine.reveal_entry("seed").is_err());
+        engine.end_reprompt();
+        assert!(engine.set_entry_reprompt("seed", false, 20).is_err());
+    }
+
+    #[test]
+    fn test_reprompt_refuses_a_self_minted_envelope() {
+        let key = [1u8; 32];
+        let fast = kdf::KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let envelope = kdf::KeyEnvelope::wrap(&key, "master pw", text::PasswordPolicy::CURRENT, fast).unwrap();
+        let mut engine = Engine::from_envelope("master pw", &envelope).unwrap();
+        engine.vault.entries.push(vault::VaultEntry { id: "seed".to_string(), password: "pw".to_string(), reprompt: true, ..Default::default() });
+
+        // Wraps the right key, but under a password the vault wasn't opened with.
+        let minted = kdf::KeyEnvelope::wrap(&key, "Tangerine-Harbor-42-Lamp", text::PasswordPolicy::CURRENT, fast).unwrap();
+        assert!(minted.unlock("Tangerine-Harbor-42-Lamp").is_ok());
+        assert_eq!(engine.confirm_master("Tangerine-Harbor-42-Lamp").unwrap_err(), kdf::UNLOCK_FAILED);
+        assert_eq!(engine.reveal_entry("seed").unwrap_err(), reprompt::REPROMPT_REQUIRED);
+
+        // Nor can a session opened without the master password confirm one.
+        let mut from_key = Engine::from_key(key);
+        assert_eq!(from_key.confirm_master("master pw").unwrap_err(), NO_MASTER_PASSWORD);
+    }
+
+    #[test]
+    fn test_favorite_order_is_kept_in_settings() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "1", "title": "Bank", "favorite": true}, {"id": "2", "title": "Mail", "favorite": true}]}"#,
+        )
+        .unwrap();
+        engine.move_favorite("2", 0).unwrap();
+        let favorites: serde_json::Value = serde_json::from_str(&engine.favorite_entries().unwrap()).unwrap();
+        assert_eq!((favorites[0]["id"].as_str(), favorites[1]["id"].as_str()), (Some("2"), Some("1")));
+        assert!(engine.vault_settings().unwrap().contains(r#""favoriteOrder":["2","1"]"#));
+        assert!(engine.move_favorite("3", 0).is_err());
+    }
+
+    #[test]
+    fn test_list_entries_page_after_touch() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "1", "title": "Bank", "password": "x"}, {"id": "2", "title": "Mail"}]}"#,
+        )
+        .unwrap();
+        engine.record_use("2", 1_700_000_000).unwrap();
+        assert!(engine.record_use("3", 1).is_err());
+
+        let page: serde_json::Value = serde_json::from_str(&engine.list_entries_page(0, 1, "lastUsed").unwrap()).unwrap();
+        assert_eq!((page["total"].as_u64(), page["entries"][0]["title"].as_str()), (Some(2), Some("Mail")));
+        assert!(!engine.list_entries_page(0, 10, "title").unwrap().contains("password"));
+        assert!(engine.list_entries_page(0, 10, "size").is_err());
+        assert!(engine.search_entries("MAIL", 0, 10, "title").unwrap().contains("\"total\":1"));
+
+        engine.record_use("1", 1_700_000_100).unwrap();
+        engine.record_use("1", 1_700_000_200).unwrap();
+        let ranked: serde_json::Value = serde_json::from_str(&engine.frequently_used(5, 1_700_000_300).unwrap()).unwrap();
+        assert_eq!(ranked[0]["useCount"], 2);
+        assert_eq!(ranked.as_array().map(Vec::len), Some(2));
+
+        let stats: serde_json::Value = serde_json::from_str(&engine.vault_stats().unwrap()).unwrap();
+        assert_eq!((stats["byType"]["login"].as_u64(), stats["logins"].as_u64()), (Some(2), Some(1)));
+        #[cfg(feature = "audit")]
+        {
+            assert_eq!(engine.audit_report().unwrap(), r#"{"reuse":[],"reusedEntries":0,"totp":[]}"#);
+            assert_eq!(engine.export_audit_csv().unwrap(), "Entry ID,Title,Finding,Detail\r\n");
+        }
+    }
+
+    #[test]
+    fn test_vault_settings_travel_with_the_vault() {
+        let mut engine = Engine::new("pw", b"salt-123456789012").unwrap();
+        let defaults: serde_json::Value = serde_json::from_str(&engine.vault_settings().unwrap()).unwrap();
+        assert_eq!(defaults["autoLockSecs"], 300);
+
+        engine.set_vault_settings(r#"{"autolock_duration": "60000"}"#).unwrap();
+        assert!(engine.set_vault_settings(r#"{"version": 1, "clipboardClearSecs": 100000}"#).is_err());
+        let json = engine.vault().to_json().unwrap();
+        assert!(json.contains("\"settings\":{") && json.contains("\"autoLockSecs\":60"));
+
+        *engine.vault_mut() = vault::VaultData::from_json(&json).unwrap();
+        assert!(engine.vault_settings().unwrap().contains("\"autoLockSecs\":60"));
+    }
+
+    #[test]
+    fn test_generate_with_preset() {
+        let mut engine = Engine::new("pw", b"salt-123456789012").unwrap();
+        engine
+            .save_generator_preset(r#"{"name": "PIN-ish", "style": "password", "length": 8, "use_uppercase": false, "use_numbers": true, "use_symbols": false}"#)
+            .unwrap();
+        engine.save_generator_preset(r#"{"name": "Wifi passphrase", "style": "passphrase"}"#).unwrap();
+
+        let pwd = engine.generate_with_preset("pin-ish").unwrap();
+        assert!(pwd.len() == 8 && pwd.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_eq!(engine.generate_with_preset("Wifi passphrase").unwrap().split('-').count(), 4);
+        assert!(engine.generate_with_preset("Banking").is_err());
+
+        assert!(engine.delete_generator_preset("PIN-ISH").unwrap());
+        let presets: serde_json::Value = serde_json::from_str(&engine.generator_presets().unwrap()).unwrap();
+        assert_eq!(presets.as_array().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_password_policy_unifies_encodings() {
+        let salt = b"salt-123456789012";
+        let composed = Engine::new_with_policy("crème brûlée", salt, text::PasswordPolicy::Nfc).unwrap();
+        let decomposed = Engine::new_with_policy("cre\u{300}me bru\u{302}le\u{301}e", salt, text::PasswordPolicy::Nfc).unwrap();
+        assert_eq!(composed.master_key, decomposed.master_key);
+
+        let raw = Engine::new_with_policy("cre\u{300}me", salt, text::PasswordPolicy::Raw).unwrap();
+        assert_eq!(raw.master_key, Engine::new("cre\u{300}me", salt).unwrap().master_key);
+    }
+
+    #[test]
+    fn test_envelope_unlock_upgrades_a_legacy_vault() {
+        let salt = b"securepass-salt!";
+        let mut legacy = Engine::new("hunter2", salt).unwrap();
+        legacy.vault_mut().categories.push("work".to_string());
+        let sealed = legacy.seal_vault_package().unwrap();
+
+        let mut engine = Engine::from_envelope("hunter2", &kdf::KeyEnvelope::legacy(salt, text::PasswordPolicy::Raw)).unwrap();
+        engine.load_vault_package(&sealed).unwrap();
+        let mut log = audit_log::AuditLog::new();
+        let upgraded = engine.commit_kdf_upgrade(&mut log, 1_700_000_000).unwrap();
+        assert!(engine.commit_kdf_upgrade(&mut log, 1_700_000_001).is_none());
+        assert_eq!(log.events.len(), 1);
+        assert!(log.events[0].detail.contains("t=2, p=1, password policy 0, direct -> Argon2id m=65536 KiB, t=3"));
+
+        // The same sealed vault opens through the new envelope, and it needs nothing further.
+        let mut reopened = Engine::from_envelope("hunter2", &upgraded).unwrap();
+        reopened.load_vault_package(&sealed).unwrap();
+        assert_eq!(reopened.vault().categories, ["work"]);
+        assert!(reopened.commit_kdf_upgrade(&mut log, 0).is_none());
+        assert!(Engine::from_envelope("hunter3", &upgraded).is_err());
+    }
+
+    #[test]
+    fn test_segmented_vault_lists_and_loads() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault =
+            vault::VaultData::from_json(r#"{"entries": [{"id": "1", "title": "Mail", "password": "hunter2"}]}"#).unwrap();
+        let sealed = engine.seal_vault_segments().unwrap();
+        assert!(!sealed.contains("Mail"));
+        assert_eq!(
+            engine.list_entry_summaries(&sealed).unwrap(),
+            r#"[{"id":"1","title":"Mail","url":"","category":"","favorite":false,"lastModified":0,"lastUsed":0,"useCount":0,"travelMode":false}]"#
+        );
+
+        let one = engine.seal_entry_segments("1").unwrap();
+        assert!(engine.open_entry_segments(&one).unwrap().contains("hunter2"));
+        assert!(engine.seal_entry_segments("2").is_err());
+
+        let mut reopened = Engine::from_key([1u8; 32]);
+        reopened.load_vault_segments(&sealed).unwrap();
+        assert_eq!(reopened.vault(), engine.vault());
+    }
+
+    #[test]
+    fn test_reencryption_moves_segments_to_the_new_key() {
+        let mut old = Engine::from_key([1u8; 32]);
+        old.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "1", "title": "Mail"}, {"id": "2", "title": "Bank"}, {"id": "3", "title": "Chat"}]}"#,
+        )
+        .unwrap();
+        let mut sealed = old.seal_vault_segments().unwrap();
+
+        let mut new = Engine::from_key([2u8; 32]);
+        let mut queue = new.start_reencryption(&old, &sealed).unwrap();
+        sealed = new.reencrypt_batch(&old, &mut queue, &sealed, 2).unwrap();
+        assert!(!queue.is_complete());
+        new.load_vault_segments_reencrypting(&old, &queue, &sealed).unwrap();
+        assert_eq!(new.vault(), old.vault());
+
+        sealed = new.reencrypt_batch(&old, &mut queue, &sealed, 2).unwrap();
+        assert!(queue.is_complete());
+        let mut reopened = Engine::from_key([2u8; 32]);
+        reopened.load_vault_segments(&sealed).unwrap();
+        assert_eq!(reopened.entry_count(), 3);
+    }
+
+    #[test]
+    fn test_seal_vault_package_loads_back() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault_mut().entries.push(vault::VaultEntry { id: "1".to_string(), ..Default::default() });
+        let package = engine.seal_vault_package().unwrap();
+        assert_eq!(package.iv.len(), 12);
+
+        let mut reopened = Engine::from_key([1u8; 32]);
+        reopened.load_vault_package(&package).unwrap();
+        assert_eq!(reopened.entry_count(), 1);
+    }
+
+    #[test]
+    fn test_find_and_merge_duplicates() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        for (password, modified) in [("old", 1), ("new", 2)] {
+            engine.vault.entries.push(vault::VaultEntry {
+                id: password.to_string(),
+                url: "https://a.example".to_string(),
+                username: "me".to_string(),
+                password: password.to_string(),
+                last_modified: modified,
+                ..Default::default()
+            });
+        }
+        assert_eq!(engine.find_duplicates().unwrap(), r#"[{"key":"a.example me","ids":["old","new"],"passwordsDiffer":true}]"#);
+        assert!(engine.merge_entries(r#"["old", "new"]"#, "longest", 3).is_err());
+        assert_eq!(engine.merge_entries(r#"["old", "new"]"#, "newest", 3).unwrap(), r#"{"id":"old","removed":["new"]}"#);
+        assert_eq!((engine.vault.entries.len(), engine.vault.entries[0].password.as_str()), (1, "new"));
+        assert_eq!(engine.find_duplicates().unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_add_entry_from_template() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let id = engine.add_entry_from_template("passport", "My passport", 5).unwrap();
+        let entry = engine.vault().find_entry(&id).unwrap();
+        assert_eq!((entry.kind(), entry.fields.len()), ("passport", 7));
+        assert!(engine.add_entry_from_template("rocket", "x", 5).is_err());
+        assert!(list_entry_templates().unwrap().contains(r#""id":"wifi_router""#));
+
+        let id = engine.add_entry_from_template("software_license", "Editor", 5).unwrap();
+        engine.set_license_details(&id, r#"{"key": "ab12 cd34 ef56", "orderNumber": "A-1"}"#, 6).unwrap();
+        assert!(engine.license_details(&id).unwrap().contains(r#""key":"AB12-CD34-EF56""#));
+        assert!(engine.set_license_details(&id, r#"{"key": "ab12-cd3"}"#, 6).is_err());
+
+        let id = engine.add_entry_from_template("wifi_router", "Home", 5).unwrap();
+        assert!(engine.entry_wifi_qr_payload(&id).unwrap_err().contains("network name"));
+        let entry = engine.vault_mut().entries.iter_mut().find(|e| e.id == id).unwrap();
+        entry.fields[0].value = "Home".to_string();
+        entry.fields[1].value = "correct horse".to_string();
+        assert_eq!(engine.entry_wifi_qr_payload(&id).unwrap(), "WIFI:T:WPA;S:Home;P:correct horse;;");
+    }
+
+    #[test]
+    fn test_journal_replays_edits_made_after_the_save() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let iv = [2u8; 12];
+        let saved = engine.seal_vault(&iv).unwrap();
+        let (mut journal, _) = engine.load_vault_journaled(&saved, &iv, "[]").unwrap();
+        engine.vault_mut().entries.push(vault::VaultEntry { id: "1".to_string(), ..Default::default() });
+        let record = engine.journal_append(&mut journal).unwrap();
+        assert_eq!(engine.journal_append(&mut journal).unwrap(), "");
+
+        let mut reopened = Engine::from_key([1u8; 32]);
+        let (_, replay) = reopened.load_vault_journaled(&saved, &iv, &format!("[{}]", record)).unwrap();
+        assert_eq!((replay.applied, reopened.entry_count()), (1, 1));
+    }
+
+    #[test]
+    fn test_json_export_imports_into_another_vault() {
+        let mut source = Engine::from_key([1u8; 32]);
+        source.vault.categories.push("work".to_string());
+        source.vault.entries.push(vault::VaultEntry {
+            id: "gh".to_string(),
+            title: "GitHub".to_string(),
+            password: "pw".to_string(),
+            category: "work".to_string(),
+            ..Default::default()
+        });
+        let json = source.export_json(100).unwrap();
+
+        let mut target = Engine::from_key([2u8; 32]);
+        target.vault.entries.push(vault::VaultEntry { id: "gh".to_string(), title: "Other".to_string(), ..Default::default() });
+        assert_eq!(target.import_json(&json, 200).unwrap(), 1);
+        let imported = &target.vault.entries[1];
+        assert_ne!(imported.id, "gh");
+        assert_eq!((imported.title.as_str(), imported.password.as_str(), imported.last_modified), ("GitHub", "pw", 200));
+        assert_eq!(target.vault.categories, vec!["work"]);
+    }
+
+    #[test]
+    fn test_export_age_recipients() {
+        let engine = Engine::from_key([1u8; 32]);
+        let identity: serde_json::Value = serde_json::from_str(&generate_age_identity().unwrap()).unwrap();
+        let exported = engine.export_age_recipients(identity["recipient"].as_str().unwrap()).unwrap();
+        assert!(exported.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+        assert!(engine.export_age_recipients("not-a-key").is_err());
+    }
+
+    #[test]
+    fn test_export_vault_needs_token_and_password() {
+        let key = [1u8; 32];
+        let fast = kdf::KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let envelope = kdf::KeyEnvelope::wrap(&key, "master pw", text::PasswordPolicy::CURRENT, fast).unwrap();
+        let mut engine = Engine::from_envelope("master pw", &envelope).unwrap();
+        engine.clock = test_clock;
+        engine.vault.entries.push(vault::VaultEntry { id: "gh".to_string(), password: "pw".to_string(), ..Default::default() });
+        let json = r#"{"format":"json"}"#;
+        set_test_clock(10);
+        assert!(engine.export_vault(json, "", "master pw").unwrap_err().contains("request an export"));
+
+        let ticket: serde_json::Value = serde_json::from_str(&engine.request_export().unwrap()).unwrap();
+        let token = ticket["token"].as_str().unwrap();
+        assert_eq!(engine.export_vault(json, token, "wrong").unwrap_err(), kdf::UNLOCK_FAILED);
+        assert!(engine.export_vault(json, token, "master pw").is_err());
+
+        // The engine's clock decides when a token has expired.
+        let ticket: serde_json::Value = serde_json::from_str(&engine.request_export().unwrap()).unwrap();
+        set_test_clock(10 + export_gate::TOKEN_LIFETIME_SECS + 1);
+        assert!(engine.export_vault(json, ticket["token"].as_str().unwrap(), "master pw").unwrap_err().contains("expired"));
+
+        let ticket: serde_json::Value = serde_json::from_str(&engine.request_export().unwrap()).unwrap();
+        let exported = engine.export_vault(json, ticket["token"].as_str().unwrap(), "master pw").unwrap();
+        assert!(String::from_utf8(exported).unwrap().contains(r#""password": "pw""#));
+        assert!(engine.request_export().unwrap_err().contains("too many"));
+    }
+
+    #[test]
+    fn test_self_minted_envelope_does_not_pass_for_the_master_password() {
+        let key = [1u8; 32];
+        let fast = kdf::KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+        let envelope = kdf::KeyEnvelope::wrap(&key, "master pw", text::PasswordPolicy::CURRENT, fast).unwrap();
+        let mut engine = Engine::from_envelope("master pw", &envelope).unwrap();
+        let minted = "Tangerine-Harbor-42-Lamp";
+
+        // Minting an envelope under a password of one's own choosing needs the real one.
+        assert_eq!(engine.change_master_password("wrong", minted, &[5u8; 32], "", "[]").unwrap_err(), kdf::UNLOCK_FAILED);
+        let pepper = pin::generate_pepper();
+        assert_eq!(engine.create_pin_lock(minted, "1357", &pepper, 3).unwrap_err(), kdf::UNLOCK_FAILED);
+        let contact: emergency::EmergencyKeypair = serde_json::from_str(&generate_emergency_keypair().unwrap()).unwrap();
+        assert_eq!(engine.create_emergency_grant(minted, &contact.public_key, 60, 100).unwrap_err(), kdf::UNLOCK_FAILED);
+
+        // An envelope wrapping the same key under another password isn't the one the
+        // vault opened with, so its password doesn't export.
+        let ticket: serde_json::Value = serde_json::from_str(&engine.request_export().unwrap()).unwrap();
+        let token = ticket["token"].as_str().unwrap();
+        assert_eq!(engine.export_vault(r#"{"format":"json"}"#, token, minted).unwrap_err(), kdf::UNLOCK_FAILED);
+
+        // A session opened without the master password can't confirm one at all.
+        let mut from_key = Engine::from_key(key);
+        let ticket: serde_json::Value = serde_json::from_str(&from_key.request_export().unwrap()).unwrap();
+        let token = ticket["token"].as_str().unwrap();
+        assert_eq!(from_key.export_vault(r#"{"format":"json"}"#, token, "master pw").unwrap_err(), NO_MASTER_PASSWORD);
+    }
+
+    #[test]
+    fn test_change_master_password_keeps_the_vault_key() {
+        let salt = b"securepass-salt!";
+        let mut engine = Engine::new("old password here", salt).unwrap();
+        let sealed = engine.encrypt("vault", &[1u8; 12]).unwrap();
+        let change = engine.change_master_password("old password here", "Tangerine-Harbor-42-Lamp", &[5u8; 32], "", "[]").unwrap();
+        let change: password_change::PasswordChange = serde_json::from_str(&change).unwrap();
+        // From now on the new password is the one retyped passwords are checked against.
+        let pepper = pin::generate_pepper();
+        assert!(engine.create_pin_lock("old password here", "1357", &pepper, 3).is_err());
+        assert!(engine.create_pin_lock("Tangerine-Harbor-42-Lamp", "1357", &pepper, 3).is_ok());
+
+        let reopened = Engine::from_envelope("Tangerine-Harbor-42-Lamp", &change.envelope).unwrap();
+        assert_eq!(reopened.decrypt(&sealed, &[1u8; 12]).unwrap(), "vault");
+        assert!(change.biometric.is_empty());
+
+        engine.bind_to_device(&serde_json::to_string(&device_key::DeviceBinding::generate()).unwrap(), &[0u8; 48]).unwrap();
+        assert!(engine.change_master_password("Tangerine-Harbor-42-Lamp", "Tangerine-Harbor-42-Lamp", &[5u8; 32], "", "[]").is_err());
+    }
+
+    #[test]
+    fn test_sync_between_two_devices() {
+        let mut phone = Engine::from_key([1u8; 32]);
+        let mut laptop = Engine::from_key([1u8; 32]);
+        let (mut phone_log, mut laptop_log) = (oplog::OpLog::new("phone").unwrap(), oplog::OpLog::new("laptop").unwrap());
+
+        phone.vault_mut().entries.push(vault::VaultEntry { id: "1".to_string(), title: "Mail".to_string(), ..Default::default() });
+        let from_phone = phone.sync_record(&mut phone_log).unwrap();
+        assert_eq!(laptop.sync_merge(&mut laptop_log, &from_phone).unwrap(), 1);
+        assert_eq!(laptop.vault().entries[0].title, "Mail");
+
+        laptop.vault_mut().entries[0].password = "hunter2".to_string();
+        let from_laptop = laptop.sync_record(&mut laptop_log).unwrap();
+        assert_eq!(laptop.sync_record(&mut laptop_log).unwrap(), "[]");
+        phone.sync_merge(&mut phone_log, &from_laptop).unwrap();
+        assert_eq!(phone.vault(), laptop.vault());
+        assert!(Engine::from_key([2u8; 32]).sync_merge(&mut oplog::OpLog::new("x").unwrap(), &from_laptop).is_err());
+    }
+
+    #[test]
+    fn test_revoked_devices_stop_syncing() {
+        let keypair = |json: String| serde_json::from_str::<devices::DeviceKeypair>(&json).unwrap();
+        let (laptop_keys, phone_keys) = (keypair(generate_device_keypair().unwrap()), keypair(generate_device_keypair().unwrap()));
+        let mut laptop = Engine::from_key([1u8; 32]);
+        let list = laptop.enroll_device("", "laptop", "Laptop", &laptop_keys.public_key, 1).unwrap();
+        let list = laptop.enroll_device(&list, "phone", "Phone", &phone_keys.public_key, 2).unwrap();
+        assert!(laptop.verify_device_list(&list).unwrap().contains(r#""deviceId":"phone""#));
+
+        let mut phone = Engine::from_key([1u8; 32]);
+        assert_eq!(phone.join_device_list(&list, &phone_keys.secret_key, 0).unwrap(), 2);
+        let list = laptop.revoke_device(&list, "phone").unwrap();
+        assert!(phone.join_device_list(&list, &phone_keys.secret_key, 2).is_err());
+        assert_eq!(laptop.join_device_list(&list, &laptop_keys.secret_key, 2).unwrap(), 3);
+
+        let mut phone_log = oplog::OpLog::new("phone").unwrap();
+        phone.vault_mut().entries.push(vault::VaultEntry { id: "1".to_string(), ..Default::default() });
+        let from_phone = phone.sync_record(&mut phone_log).unwrap();
+        assert_eq!(laptop.sync_merge(&mut oplog::OpLog::new("laptop").unwrap(), &from_phone).unwrap(), 0);
+        assert!(Engine::from_key([2u8; 32]).verify_device_list(&list).is_err());
+    }
+
+    #[test]
+    fn test_sync_through_blobs() {
+        let mut phone = Engine::from_key([1u8; 32]);
+        let mut phone_log = oplog::OpLog::new("phone").unwrap();
+        phone.vault_mut().entries.push(vault::VaultEntry { id: "1".to_string(), ..Default::default() });
+        let sealed = phone.sync_record(&mut phone_log).unwrap();
+        let ops = phone.sync_pack_ops(&mut phone_log, &sealed).unwrap();
+        let snapshot = phone.sync_pack_snapshot(&mut phone_log).unwrap();
+
+        let mut laptop = Engine::from_key([1u8; 32]);
+        let mut laptop_log = oplog::OpLog::new("laptop").unwrap();
+        let names = vec![ops.name.clone(), snapshot.name.clone()];
+        assert_eq!(blobs::pending(&laptop_log, &names), vec![snapshot.name.clone(), ops.name.clone()]);
+        laptop.sync_merge_blob(&mut laptop_log, &snapshot.name, &snapshot.data).unwrap();
+        assert_eq!(laptop.sync_merge_blob(&mut laptop_log, &ops.name, &ops.data).unwrap(), 0);
+        assert_eq!(laptop.entry_count(), 1);
+        assert_eq!(blobs::compaction_plan(&laptop_log, &names), vec![ops.name]);
+    }
+
+    #[test]
+    fn test_check_phishing_uses_loaded_vault() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault =
+            vault::VaultData::from_json(r#"{"entries": [{"id": "1", "title": "Bank", "url": "https://mybank.com"}]}"#).unwrap();
+
+        let verdict = engine.check_phishing("https://mybank.com.secure-login.io").unwrap();
+        assert!(verdict.contains(r#""level":"suspicious""#));
+        assert!(engine.check_phishing("https://mybank.com/login").unwrap().contains(r#""level":"trusted""#));
+    }
+
+    #[test]
+    fn test_breached_entries_from_filter() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "1", "title": "Forum", "password": "123456"}, {"id": "2", "title": "Bank", "password": "q8#Vd!2mZp"}]}"#,
+        )
+        .unwrap();
+        let mut filter = breach::BreachFilter::with_capacity(10, 0.001).unwrap();
+        filter.insert_sha1(&sha1::Sha1::digest(b"123456").into());
+
+        let found = engine.breached_entries(&filter).unwrap();
+        assert!(found.contains(r#""id":"1""#) && !found.contains(r#""id":"2""#) && !found.contains("123456"));
+    }
+
+    #[test]
+    fn test_equivalent_domains_feed_autofill() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [{"id": "a", "title": "Amazon", "password": "pw", "url": "amazon.com"}]}"#,
+        )
+        .unwrap();
+        let form = r#"{"fields": [{"field_id": "p", "type": "password"}]}"#;
+
+        assert!(engine.build_fill_payload("a", "https://www.amazon.de", form).is_err());
+        engine.set_equivalent_domains(r#"[["amazon.com", "www.amazon.de"]]"#).unwrap();
+        assert!(engine.build_fill_payload("a", "https://www.amazon.de", form).is_ok());
+        assert_eq!(engine.equivalent_domains().unwrap(), r#"[["amazon.com","amazon.de"]]"#);
+
+        engine.add_equivalent_domains(r#"["amazon.co.uk", "amazon.de"]"#).unwrap();
+        engine.remove_equivalent_domain("amazon.de");
+        assert_eq!(engine.equivalent_domains().unwrap(), r#"[["amazon.co.uk","amazon.com"]]"#);
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_totp_configs_from_vault() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        engine.vault = vault::VaultData::from_json(
+            r#"{"entries": [
+            {"id": "1", "title": "GitHub", "username": "octo", "totpSecret": "JBSWY3DPEHPK3PXP"},
+            {"id": "2", "title": "No 2FA"}
+        ]}"#,
+        )
+        .unwrap();
+        let configs = engine.totp_configs().unwrap();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].issuer, "GitHub");
+
+        engine.vault.entries[1].totp_secret = Some("???".to_string());
+        assert!(engine.totp_configs().unwrap_err().contains("No 2FA"));
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_import_lastpass_csv() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let csv = "url,username,password,totp,extra,name,grouping,fav\nhttps://a.example,me,pw,,,A,Work\\Mail,0\n";
+        assert_eq!(engine.import_lastpass_csv(csv, r#"{"Work": "Job"}"#, 5).unwrap(), 1);
+        assert_eq!((engine.vault.entries[0].category.as_str(), engine.vault.categories[0].as_str()), ("Job/Mail", "Job/Mail"));
+        assert!(engine.import_lastpass_csv(csv, "[1]", 5).unwrap_err().contains("folder map"));
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_import_browser_csv() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let csv = "name,url,username,password\n,http://a.example,me,pw\n,https://a.example,me,pw\n";
+        assert_eq!(engine.import_browser_csv(csv, 5).unwrap(), r#"{"source":"chrome","added":1,"duplicates":1}"#);
+        assert_eq!(engine.vault.entries[0].url, "https://a.example");
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_import_file() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let proton = r#"{"encrypted": false, "vaults": {"s": {"name": "Family", "items": [
+            {"state": 1, "data": {"type": "note", "metadata": {"name": "Alarm", "note": "1234"}, "content": {}}}]}}}"#;
+        assert_eq!(engine.import_file("proton-pass", proton, 5).unwrap(), r#"{"source":"proton-pass","added":1,"duplicates":0}"#);
+        assert_eq!((engine.vault.entries[0].category.as_str(), engine.vault.categories.clone()), ("Family", vec!["Family".to_string()]));
+        let dashlane = "title,note\nAlarm,5678\n";
+        assert!(engine.import_file("dashlane", dashlane, 5).unwrap().contains(r#""added":1"#));
+        assert!(engine.import_file("keepass", "", 5).unwrap_err().contains("unknown format"));
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_preview_then_apply_import() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let csv = "name,url,username,password\n,https://a.example,me,old\n";
+        engine.import_browser_csv(csv, 5).unwrap();
+        let existing_id = engine.vault.entries[0].id.clone();
+        assert!(engine.apply_import("").unwrap_err().contains("preview an import first"));
+
+        let csv = "name,url,username,password\n,https://a.example,me,new\n,https://b.example,me,pw\n,https://a.example,me,old\n";
+        let preview: serde_json::Value = serde_json::from_str(&engine.preview_import("browser", csv, 9).unwrap()).unwrap();
+        let statuses: Vec<&str> = preview["items"].as_array().unwrap().iter().map(|i| i["status"].as_str().unwrap()).collect();
+        assert_eq!(statuses, vec!["update", "new", "duplicate"]);
+        assert_eq!(preview["items"][0]["existingId"], existing_id.as_str());
+        assert_eq!(engine.vault.entries.len(), 1);
+
+        // A bad choice keeps the preview so the user can choose again.
+        assert!(engine.apply_import(r#"{"1": "replace"}"#).unwrap_err().contains("matches no entry"));
+        let summary = engine.apply_import(r#"{"2": "add"}"#).unwrap();
+        assert_eq!(summary, r#"{"added":2,"replaced":1,"skipped":0,"source":"chrome"}"#);
+        assert_eq!((engine.vault.entries.len(), engine.vault.entries[0].password.as_str()), (3, "new"));
+        assert_eq!(engine.vault.entries[0].history, vec!["old"]);
+        assert!(engine.apply_import("").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "importers")]
+    fn test_import_google_migration() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        // One TOTP account: secret "Hello!\xde\xad\xbe\xef", name "alice", issuer "Example".
+        let uri = "otpauth-migration://offline?data=CiIKCkhlbGxvId6tvu8SBWFsaWNlGgdFeGFtcGxlIAEoATAC";
+
+        assert_eq!(engine.import_google_migration(uri, 1_700_000_000).unwrap(), 1);
+        let entry = &engine.vault.entries[0];
+        assert_eq!((entry.title.as_str(), entry.username.as_str()), ("Example", "alice"));
+        assert!(entry.totp_secret.as_deref().unwrap().contains("secret=JBSWY3DPEHPK3PXP"));
+    }
+
+    #[test]
+    fn test_attachment_meta_without_content() {
+        let engine = Engine::from_key([1u8; 32]);
+        let attachment = engine.encrypt_attachment(b"%PDF-1.7", "scan.pdf", "application/pdf").unwrap();
+
+        let meta = engine.decrypt_attachment_meta(&attachment.id, &attachment.meta).unwrap();
+        assert_eq!(meta, r#"{"filename":"scan.pdf","mime_type":"application/pdf","size":8}"#);
+        assert_eq!(engine.decrypt_attachment(&attachment.id, &attachment.content).unwrap(), b"%PDF-1.7");
+
+        let other = Engine::from_key([2u8; 32]);
+        assert!(other.decrypt_attachment_meta(&attachment.id, &attachment.meta).is_err());
+    }
+
+    #[test]
+    fn test_dedup_attachments_is_opt_in() {
+        let mut engine = Engine::from_key([1u8; 32]);
+        let twice = |engine: &Engine| {
+            let first = engine.encrypt_attachment(b"%PDF-1.7", "a.pdf", "application/pdf").unwrap();
+            let second = engine.encrypt_attachment(b"%PDF-1.7", "b.pdf", "application/pdf").unwrap();
+            (first, second)
+        };
+        let (first, second) = twice(&engine);
+        assert!(first.id != second.id && first.content != second.content);
+
+        engine.set_vault_settings(r#"{"version": 1, "dedupAttachments": true}"#).unwrap();
+        let (first, second) = twice(&engine);
+        assert_eq!((&first.id, &first.content), (&second.id, &second.content));
+        assert_eq!(engine.decrypt_attachment(&second.id, &second.content).unwrap(), b"%PDF-1.7");
+        assert!(engine.decrypt_attachment_meta(&second.id, &second.meta).unwrap().contains("b.pdf"));
+    }
+
+    #[test]
+    fn test_throttled_unlock_flow() {
+        let mut state = throttle::ThrottleState::default();
+        let salt = b"salt-123456789012";
+        let real = Engine::new("right", salt).unwrap();
+
+        let wrong = Engine::new_throttled("wrong", salt, text::PasswordPolicy::Raw, &mut state).unwrap();
+        assert!(state.pending_attempt);
+        assert_ne!(wrong.master_key, real.master_key);
+        state.record_failure();
+        assert_eq!(state.consecutive_failures, 1);
+
+        let right = Engine::new_throttled("right", salt, text::PasswordPolicy::Raw, &mut state).unwrap();
+        assert_eq!(right.master_key, real.master_key);
+        state.record_success();
+        assert_eq!(state, throttle::ThrottleState::default());
+    }
+
+    #[test]
+    fn test_decoy_slots_open_per_password() {
+        let salt = b"salt-123456789012";
+        let mut real = Engine::new("real-pw", salt).unwrap();
+        real.vault = vault::VaultData::from_json(r#"{"entries":[{"id":"1","title":"Bank"}]}"#).unwrap();
+
+        let slots = real.create_decoy_slots("decoy-pw", salt, r#"{"entries":[]}"#).unwrap();
+        assert!(real.create_decoy_slots("real-pw", salt, "{}").is_err());
+
+        let opened_real = Engine::open_slots("real-pw", salt, &slots).unwrap();
+        let mut opened_decoy = Engine::open_slots("decoy-pw", salt, &slots).unwrap();
+        assert_eq!(opened_real.vault.entries.len(), 1);
+        assert_eq!(opened_decoy.vault.entries.len(), 0);
+        assert!(Engine::open_slots("wrong", salt, &slots).is_err());
+
+        // Saving the decoy must leave the real vault intact.
+        opened_decoy.vault = vault::VaultData::from_json(r#"{"entries":[{"id":"2","title":"Shop"}]}"#).unwrap();
+        let slots = opened_decoy.seal_into_slots(&slots).unwrap();
+        assert_eq!(Engine::open_slots("real-pw", salt, &slots).unwrap().vault.entries[0].title, "Bank");
+        assert_eq!(Engine::open_slots("decoy-pw", salt, &slots).unwrap().vault.entries[0].title, "Shop");
+    }
+
+    #[test]
+    fn test_vault_slots_without_decoy() {
+        let salt = b"salt-123456789012";
+        let engine = Engine::new("pw", salt).unwrap();
+        let slots: Vec<duress::EncryptedPackage> = serde_json::from_str(&engine.create_vault_slots().unwrap()).unwrap();
+        assert_eq!(slots.len(), 2);
+        assert!(Engine::open_slots("pw", salt, &serde_json::to_string(&slots).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_expand_autotype_default() {
+        let engine = Engine::from_key([0u8; 32]);
+        let entry = r#"{"id": "1", "title": "Site", "username": "bob", "password": "pw"}"#;
+        let json = engine.expand_autotype("", entry).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"type":"text","value":"bob"},{"type":"key","key":"Tab"},{"type":"text","value":"pw"},{"type":"key","key":"Enter"}]"#
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_encrypt_decrypt_roundtrip(key in any::<[u8; 32]>(), iv in any::<[u8; 12]>(), plaintext in "\\PC{0,200}") {
+            let engine = Engine::from_key(key);
+            let ciphertext = engine.encrypt(&plaintext, &iv).unwrap();
+            prop_assert_eq!(engine.decrypt(&ciphertext, &iv).unwrap(), plaintext);
+
+            let mut flipped = key;
+            flipped[0] ^= 1;
+            prop_assert!(Engine::from_key(flipped).decrypt(&ciphertext, &iv).is_err());
+        }
+
+        #[test]
+        fn prop_vault_seal_load_roundtrip(titles in proptest::collection::vec("\\PC{0,30}", 0..8), iv in any::<[u8; 12]>()) {
+            let mut engine = Engine::from_key([5u8; 32]);
+            for (i, title) in titles.iter().enumerate() {
+                engine.vault.entries.push(vault::VaultEntry { id: i.to_string(), title: title.clone(), ..Default::default() });
+            }
+            let sealed = engine.seal_vault(&iv).unwrap();
+
+            let mut reopened = Engine::from_key([5u8; 32]);
+            reopened.load_vault(&sealed, &iv).unwrap();
+            prop_assert_eq!(&reopened.vault.entries, &engine.vault.entries);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// --- Entitlements ---
+// What a customer has paid for (a tier, a number of seats, an expiry), signed by
+// the vendor with Ed25519, so a client can unlock premium features without asking
+// a server. The vendor's public key is built into the app; only the vendor's
+// secret key can issue a token.
+//
+// A token is two unpadded Base64url parts joined by '.':
+//   <payload JSON>.<signature>
+// The signature covers a context label followed by the payload bytes exactly as
+// sent, so the JSON never has to be re-serialized to be checked. An expired token
+// still verifies, with 'active' false, so the app can tell "your plan expired"
+// from "this isn't a license". Expiry is judged on the device's clock.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as B64URL, Engine};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::emergency::decode_key;
+use crate::redact;
+
+pub const ENTITLEMENT_VERSION: u8 = 1;
+
+/// Prefixed to the payload before signing, so a signature can't be replayed elsewhere.
+const SIGNING_CONTEXT: &[u8] = b"securepass-entitlement-v1";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Entitlement {
+    pub version: u8,
+    /// The vendor's name for the plan: "premium", "family", "business", ...
+    pub tier: String,
+    pub seats: u32,
+    pub issued_at: u64,
+    /// None for a perpetual license.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Who it was issued to (a customer or order ID), if the vendor says.
+    #[serde(default)]
+    pub subject: String,
+}
+
+/// A verified entitlement and whether it still holds.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedEntitlement {
+    #[serde(flatten)]
+    pub entitlement: Entitlement,
+    pub active: bool,
+}
+
+/// The bytes the vendor signs for a payload.
+pub fn signing_message(payload: &[u8]) -> Vec<u8> {
+    [SIGNING_CONTEXT, payload].concat()
+}
+
+/// Checks `token` against the vendor's Ed25519 public key (Base64) and reads it.
+pub fn verify(token: &str, vendor_public_key: &str, now: u64) -> Result<VerifiedEntitlement, String> {
+    let (payload, signature) = token.trim().split_once('.').ok_or("Entitlement error: not an entitlement token")?;
+    let payload = B64URL.decode(payload).map_err(|e| format!("Entitlement decode error: {}", e))?;
+    let signature = B64URL.decode(signature).map_err(|e| format!("Signature decode error: {}", e))?;
+    let signature = Signature::from_slice(&signature).map_err(|e| format!("Signature error: {}", e))?;
+    let key = decode_key(vendor_public_key, "Vendor public key")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Vendor public key error: {}", e))?;
+    key.verify_strict(&signing_message(&payload), &signature)
+        .map_err(|_| "Entitlement error: the signature does not match".to_string())?;
+
+    let entitlement: Entitlement =
+        serde_json::from_slice(&payload).map_err(|e| redact::json_error("Entitlement parse", &e))?;
+    if entitlement.version != ENTITLEMENT_VERSION {
+        return Err(format!("Unsupported entitlement version: {}", entitlement.version));
+    }
+    if entitlement.seats == 0 {
+        return Err("Entitlement error: the token grants no seats".to_string());
+    }
+    let active = entitlement.expires_at.is_none_or(|expires_at| now < expires_at);
+    Ok(VerifiedEntitlement { entitlement, active })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD as B64;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn issue(vendor: &SigningKey, payload: &str) -> String {
+        let signature = vendor.sign(&signing_message(payload.as_bytes()));
+        format!("{}.{}", B64URL.encode(payload), B64URL.encode(signature.to_bytes()))
+    }
+
+    #[test]
+    fn test_signed_token_verifies_and_expires() {
+        let vendor = SigningKey::from_bytes(&[7u8; 32]);
+        let public = B64.encode(vendor.verifying_key().as_bytes());
+        let token = issue(&vendor, r#"{"version":1,"tier":"family","seats":6,"issuedAt":100,"expiresAt":1000,"subject":"order-42"}"#);
+
+        let verified = verify(&token, &public, 999).unwrap();
+        assert_eq!((verified.entitlement.tier.as_str(), verified.entitlement.seats, verified.active), ("family", 6, true));
+        assert!(!verify(&token, &public, 1000).unwrap().active);
+        let perpetual = issue(&vendor, r#"{"version":1,"tier":"premium","seats":1,"issuedAt":100}"#);
+        assert!(verify(&perpetual, &public, u64::MAX).unwrap().active);
+    }
+
+    #[test]
+    fn test_forged_or_foreign_tokens_are_refused() {
+        let vendor = SigningKey::from_bytes(&[7u8; 32]);
+        let public = B64.encode(vendor.verifying_key().as_bytes());
+        let token = issue(&vendor, r#"{"version":1,"tier":"premium","seats":1,"issuedAt":100,"expiresAt":1000}"#);
+
+        let (_, signature) = token.split_once('.').unwrap();
+        let upgraded = B64URL.encode(r#"{"version":1,"tier":"business","seats":500,"issuedAt":100}"#);
+        assert!(verify(&format!("{}.{}", upgraded, signature), &public, 0).unwrap_err().contains("does not match"));
+
+        let other = B64.encode(SigningKey::from_bytes(&[8u8; 32]).verifying_key().as_bytes());
+        assert!(verify(&token, &other, 0).unwrap_err().contains("does not match"));
+        assert!(verify("premium", &public, 0).is_err());
+        let no_seats = issue(&vendor, r#"{"version":1,"tier":"premium","seats":0,"issuedAt":100}"#);
+        assert!(verify(&no_seats, &public, 0).unwrap_err().contains("no seats"));
+    }
+}
@@ -0,0 +1,168 @@
+// --- Export Confirmation ---
+// An export writes every secret in the vault out of the engine, so a script
+// injected into the page could otherwise call it and post the result away
+// without the user seeing anything. From the bridge an export takes two steps:
+// 'request' hands out a one-time token, and the export only runs when that
+// token comes back with the master password before it expires. The UI asks
+// for the password in between; a script would need the password as well as
+// the bridge. The engine checks it against the envelope the vault was unlocked
+// with, never one the caller passes: a key envelope anyone can mint under a
+// password of their own ('Engine::change_master_password' would wrap the key
+// for them) unlocks the right key too. Token lifetime and throttle run on the
+// engine's clock ('clock'), not on a time the caller passes.
+//
+// This guards the export calls only. The web app still decrypts the vault
+// through the bridge to show it, so a script running in the page can read what
+// the page can; the gate keeps it from producing a ready-made export file.
+//
+// A token works once, whether the password was right or not, and requests are
+// throttled (MAX_REQUESTS per REQUEST_WINDOW_SECS), so the export can't be
+// used to guess the master password either. The CLI, which already asked for
+// the password, calls the export functions directly.
+
+use base64::{
+    engine::general_purpose::{STANDARD as B64, URL_SAFE_NO_PAD as B64URL},
+    Engine,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::keys::derive_subkey;
+
+const PURPOSE: &str = "securepass-export-verifier-v1";
+const TOKEN_LEN: usize = 16;
+/// How long a token can wait for the password.
+pub const TOKEN_LIFETIME_SECS: u64 = 120;
+pub const MAX_REQUESTS: usize = 3;
+pub const REQUEST_WINDOW_SECS: u64 = 600;
+
+/// What 'request' returns.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTicket {
+    pub token: String,
+    pub expires_at: u64,
+}
+
+/// The formats the two-step export writes, as JSON: {"format": "age-passphrase", "passphrase": "..."}.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "format", rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// Unencrypted, versioned JSON ('json_export').
+    Json,
+    AgePassphrase { passphrase: String },
+    /// "age1..." public keys, one per line.
+    AgeRecipients { recipients: String },
+    /// An armored public key, or a binary one in Base64.
+    Openpgp {
+        #[serde(rename = "publicKey")]
+        public_key: String,
+    },
+    /// TOTP seeds only, as an Aegis or andOTP backup.
+    Aegis { password: String },
+    Andotp { password: String },
+}
+
+impl ExportFormat {
+    /// The OpenPGP format for a public key as read from a file: armored, or binary.
+    pub fn openpgp(public_key: &[u8]) -> ExportFormat {
+        let public_key = match std::str::from_utf8(public_key) {
+            Ok(armored) if armored.trim_start().starts_with("-----BEGIN") => armored.to_string(),
+            _ => B64.encode(public_key),
+        };
+        ExportFormat::Openpgp { public_key }
+    }
+}
+
+pub struct ExportGate {
+    verifier: [u8; 32],
+    pending: Option<([u8; TOKEN_LEN], u64)>,
+    /// When recent requests were made, oldest first.
+    requests: Vec<u64>,
+}
+
+impl ExportGate {
+    pub fn new(master_key: &[u8; 32]) -> ExportGate {
+        ExportGate { verifier: derive_subkey(master_key, PURPOSE, b""), pending: None, requests: Vec::new() }
+    }
+
+    /// A new token, replacing any unused one. Refused after MAX_REQUESTS in the window.
+    pub fn request(&mut self, now: u64) -> Result<ExportTicket, String> {
+        self.requests.retain(|&at| at <= now && now - at < REQUEST_WINDOW_SECS);
+        if self.requests.len() >= MAX_REQUESTS {
+            let wait = self.requests[0] + REQUEST_WINDOW_SECS - now;
+            return Err(format!("Export error: too many export requests; try again in {} seconds", wait));
+        }
+        self.requests.push(now);
+        let mut token = [0u8; TOKEN_LEN];
+        OsRng.fill_bytes(&mut token);
+        self.pending = Some((token, now));
+        Ok(ExportTicket { token: B64URL.encode(token), expires_at: now + TOKEN_LIFETIME_SECS })
+    }
+
+    /// Spends the pending token: Ok if `token` is it and hasn't expired at `now`.
+    /// The token is gone afterwards either way.
+    pub fn redeem(&mut self, token: &str, now: u64) -> Result<(), String> {
+        let (expected, issued_at) = self.pending.take().ok_or("Export error: request an export first")?;
+        let given = B64URL.decode(token.trim()).unwrap_or_default();
+        let matches: bool = given.ct_eq(&expected).into();
+        if !matches || now < issued_at || now - issued_at > TOKEN_LIFETIME_SECS {
+            return Err("Export error: the confirmation token is wrong or has expired; request a new one".to_string());
+        }
+        Ok(())
+    }
+
+    /// True if `master_key` (unlocked from the engine's envelope with the password given
+    /// with the token) is this vault's.
+    pub fn is_master_key(&self, master_key: &[u8; 32]) -> bool {
+        let mut candidate = derive_subkey(master_key, PURPOSE, b"");
+        let matches = candidate.ct_eq(&self.verifier).into();
+        candidate.zeroize();
+        matches
+    }
+}
+
+impl Drop for ExportGate {
+    fn drop(&mut self) {
+        self.verifier.zeroize();
+        if let Some((token, _)) = self.pending.as_mut() {
+            token.zeroize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_work_once_and_expire() {
+        let mut gate = ExportGate::new(&[1u8; 32]);
+        assert!(gate.redeem("anything", 0).unwrap_err().contains("request an export first"));
+
+        let ticket = gate.request(100).unwrap();
+        assert_eq!(ticket.expires_at, 100 + TOKEN_LIFETIME_SECS);
+        assert!(gate.redeem(&ticket.token, 100 + TOKEN_LIFETIME_SECS).is_ok());
+        assert!(gate.redeem(&ticket.token, 101).is_err());
+
+        let ticket = gate.request(200).unwrap();
+        assert!(gate.redeem("AAAAAAAAAAAAAAAAAAAAAA", 200).is_err());
+        // A wrong guess spent it.
+        assert!(gate.redeem(&ticket.token, 200).is_err());
+
+        assert!(gate.is_master_key(&[1u8; 32]) && !gate.is_master_key(&[2u8; 32]));
+    }
+
+    #[test]
+    fn test_requests_are_throttled() {
+        let mut gate = ExportGate::new(&[1u8; 32]);
+        let expired = gate.request(0).unwrap();
+        gate.request(10).unwrap();
+        gate.request(20).unwrap();
+        assert_eq!(gate.request(30).unwrap_err(), "Export error: too many export requests; try again in 570 seconds");
+        assert!(gate.redeem(&expired.token, 30).is_err());
+        assert!(gate.request(REQUEST_WINDOW_SECS).is_ok());
+    }
+}
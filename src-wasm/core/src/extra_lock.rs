@@ -0,0 +1,260 @@
+// --- Extra Lock ---
+// A second passphrase on single entries, for items that shouldn't open just
+// because the vault is: a wallet seed, recovery codes. The entry's secrets
+// (username, password, notes, TOTP seed, history and custom fields) are sealed
+// under a random entry key, and that key is wrapped under Argon2id over the
+// extra passphrase. The wrapping key also mixes in an HKDF subkey of the master
+// key, so the lock adds to the vault's protection instead of replacing it: a
+// copy of the entry opens only with both.
+//
+// Title, URL, folder and flags stay readable so the entry still lists, sorts
+// and matches a page. Autofill, audits and sharing see an entry with no
+// secrets until the lock is cleared. Opening the lock to view the entry leaves
+// it locked in the vault.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::kdf::KdfSettings;
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::text::{normalize_password, PasswordPolicy};
+use crate::vault::{CustomField, VaultEntry};
+
+pub(crate) const PURPOSE: &str = "securepass-extra-lock-v1";
+const WRAP_PURPOSE: &str = "securepass-extra-lock-wrap-v1";
+/// Version 2 seals with key commitment; version 1 locks were plain AES-GCM.
+const FORMAT_VERSION: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+const SALT_LEN: usize = 32;
+/// The one error for a wrong passphrase and a damaged lock alike.
+pub const WRONG_PASSPHRASE: &str = "Extra lock error: wrong passphrase";
+
+/// The sealed secrets of a locked entry. Not secret itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraLock {
+    pub version: u8,
+    pub kdf: KdfSettings,
+    pub password_policy: PasswordPolicy,
+    pub salt: String,
+    /// The entry key, sealed under the passphrase and master key (AAD: entry ID).
+    pub key_iv: String,
+    pub wrapped_key: String,
+    /// The locked fields as JSON, sealed under the entry key (AAD: entry ID).
+    pub iv: String,
+    pub data: String,
+}
+
+/// The fields a lock takes out of the entry.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Secrets {
+    username: String,
+    password: String,
+    notes: String,
+    totp_secret: Option<String>,
+    history: Vec<String>,
+    fields: Vec<CustomField>,
+}
+
+impl Secrets {
+    fn take(entry: &mut VaultEntry) -> Secrets {
+        Secrets {
+            username: std::mem::take(&mut entry.username),
+            password: std::mem::take(&mut entry.password),
+            notes: std::mem::take(&mut entry.notes),
+            totp_secret: entry.totp_secret.take(),
+            history: std::mem::take(&mut entry.history),
+            fields: std::mem::take(&mut entry.fields),
+        }
+    }
+
+    fn restore(mut self, entry: &mut VaultEntry) {
+        entry.username = std::mem::take(&mut self.username);
+        entry.password = std::mem::take(&mut self.password);
+        entry.notes = std::mem::take(&mut self.notes);
+        entry.totp_secret = self.totp_secret.take();
+        entry.history = std::mem::take(&mut self.history);
+        entry.fields = std::mem::take(&mut self.fields);
+    }
+}
+
+impl Drop for Secrets {
+    fn drop(&mut self) {
+        self.username.zeroize();
+        self.password.zeroize();
+        self.notes.zeroize();
+        if let Some(secret) = self.totp_secret.as_mut() {
+            secret.zeroize();
+        }
+        self.history.iter_mut().for_each(|old| old.zeroize());
+        self.fields.iter_mut().for_each(|field| field.value.zeroize());
+    }
+}
+
+pub fn is_locked(entry: &VaultEntry) -> bool {
+    entry.extra_lock.is_some()
+}
+
+/// The key that wraps the entry key: Argon2id over the passphrase, then HKDF
+/// bound to the master key and the entry ID.
+fn wrap_key(
+    master_key: &[u8; 32],
+    entry_id: &str,
+    passphrase: &str,
+    kdf: KdfSettings,
+    policy: PasswordPolicy,
+    salt: &[u8],
+) -> Result<[u8; 32], String> {
+    let derived = kdf.derive(normalize_password(passphrase, policy).as_bytes(), salt)?;
+    let mut context = derive_subkey(master_key, PURPOSE, entry_id.as_bytes()).to_vec();
+    context.extend_from_slice(entry_id.as_bytes());
+    let key = derive_subkey(&derived, WRAP_PURPOSE, &context);
+    context.zeroize();
+    Ok(key)
+}
+
+/// Locks `entry` under `passphrase`: its secrets move into an 'ExtraLock'.
+pub fn lock(master_key: &[u8; 32], entry: &mut VaultEntry, passphrase: &str, kdf: KdfSettings) -> Result<(), String> {
+    if is_locked(entry) {
+        return Err("Extra lock error: the entry is already locked".to_string());
+    }
+    if passphrase.is_empty() {
+        return Err("Extra lock error: the passphrase is empty".to_string());
+    }
+    let mut salt = [0u8; SALT_LEN];
+    let mut entry_key = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut entry_key);
+
+    let policy = PasswordPolicy::CURRENT;
+    let secrets = Secrets::take(entry);
+    let sealed = serde_json::to_vec(&secrets).map_err(|e| format!("Extra lock serialize error: {}", e)).and_then(|mut json| {
+        let mut key = wrap_key(master_key, &entry.id, passphrase, kdf, policy, &salt)?;
+        let wrapped = seal_committed(&key, &entry_key, entry.id.as_bytes());
+        let data = seal_committed(&entry_key, &json, entry.id.as_bytes());
+        key.zeroize();
+        json.zeroize();
+        Ok((wrapped?, data?))
+    });
+    entry_key.zeroize();
+    let ((key_iv, wrapped_key), (iv, data)) = match sealed {
+        Ok(sealed) => sealed,
+        Err(e) => {
+            secrets.restore(entry);
+            return Err(e);
+        }
+    };
+
+    entry.extra_lock = Some(ExtraLock {
+        version: FORMAT_VERSION,
+        kdf,
+        password_policy: policy,
+        salt: B64.encode(salt),
+        key_iv: B64.encode(key_iv),
+        wrapped_key: B64.encode(wrapped_key),
+        iv: B64.encode(iv),
+        data: B64.encode(data),
+    });
+    Ok(())
+}
+
+/// A copy of a locked entry with its secrets back and no lock, to show or copy
+/// from. The entry itself stays locked.
+pub fn open_entry(master_key: &[u8; 32], entry: &VaultEntry, passphrase: &str) -> Result<VaultEntry, String> {
+    let lock = entry.extra_lock.as_ref().ok_or("Extra lock error: the entry is not locked")?;
+    if ![LEGACY_VERSION, FORMAT_VERSION].contains(&lock.version) {
+        return Err(format!("Unsupported extra lock version: {}", lock.version));
+    }
+    let open = if lock.version == LEGACY_VERSION { open } else { open_committed };
+    let decode = |field: &str| B64.decode(field).map_err(|e| format!("Extra lock decode error: {}", e));
+    let (salt, key_iv, wrapped_key, iv, data) =
+        (decode(&lock.salt)?, decode(&lock.key_iv)?, decode(&lock.wrapped_key)?, decode(&lock.iv)?, decode(&lock.data)?);
+
+    let mut key = wrap_key(master_key, &entry.id, passphrase, lock.kdf, lock.password_policy, &salt)?;
+    let unwrapped = open(&key, &key_iv, &wrapped_key, entry.id.as_bytes());
+    key.zeroize();
+    let mut plain_key = unwrapped.map_err(|_| WRONG_PASSPHRASE.to_string())?;
+    let entry_key = <[u8; 32]>::try_from(plain_key.as_slice()).map_err(|_| WRONG_PASSPHRASE.to_string());
+    plain_key.zeroize();
+    let mut entry_key = entry_key?;
+    let opened = open(&entry_key, &iv, &data, entry.id.as_bytes());
+    entry_key.zeroize();
+    let mut json = opened.map_err(|_| WRONG_PASSPHRASE.to_string())?;
+    let secrets = serde_json::from_slice::<Secrets>(&json).map_err(|e| crate::redact::json_error("Extra lock parse", &e));
+    json.zeroize();
+
+    let mut copy = entry.clone();
+    copy.extra_lock = None;
+    secrets?.restore(&mut copy);
+    Ok(copy)
+}
+
+/// Removes the lock, putting the secrets back into `entry`.
+pub fn unlock(master_key: &[u8; 32], entry: &mut VaultEntry, passphrase: &str) -> Result<(), String> {
+    *entry = open_entry(master_key, entry, passphrase)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: KdfSettings = KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+    const MASTER: [u8; 32] = [3u8; 32];
+
+    fn seed_entry() -> VaultEntry {
+        VaultEntry {
+            id: "e1".to_string(),
+            title: "Cold wallet".to_string(),
+            url: "https://wallet.example".to_string(),
+            password: "abandon ability able".to_string(),
+            totp_secret: Some("JBSWY3DPEHPK3PXP".to_string()),
+            fields: vec![CustomField { name: "Seed".to_string(), kind: Default::default(), value: "zoo zone".to_string() }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lock_hides_secrets_until_unlocked() {
+        let original = seed_entry();
+        let mut entry = original.clone();
+        lock(&MASTER, &mut entry, "second factor", FAST).unwrap();
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(!json.contains("abandon") && !json.contains("zoo zone") && !json.contains("JBSWY3"));
+        assert_eq!((entry.title.as_str(), entry.password.as_str()), ("Cold wallet", ""));
+        assert!(lock(&MASTER, &mut entry, "again", FAST).unwrap_err().contains("already locked"));
+
+        assert_eq!(open_entry(&MASTER, &entry, "second factor").unwrap(), original);
+        assert!(is_locked(&entry));
+        unlock(&MASTER, &mut entry, "second factor").unwrap();
+        assert_eq!(entry, original);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_or_master_key_fails() {
+        let mut entry = seed_entry();
+        lock(&MASTER, &mut entry, "second factor", FAST).unwrap();
+        assert_eq!(open_entry(&MASTER, &entry, "second").unwrap_err(), WRONG_PASSPHRASE);
+        assert_eq!(open_entry(&[4u8; 32], &entry, "second factor").unwrap_err(), WRONG_PASSPHRASE);
+
+        // A lock copied onto another entry doesn't open there.
+        let mut other = seed_entry();
+        other.id = "e2".to_string();
+        other.extra_lock = entry.extra_lock.clone();
+        assert_eq!(open_entry(&MASTER, &other, "second factor").unwrap_err(), WRONG_PASSPHRASE);
+        assert!(open_entry(&MASTER, &seed_entry(), "x").unwrap_err().contains("not locked"));
+    }
+
+    #[test]
+    fn test_relabelled_lock_is_refused() {
+        let mut entry = seed_entry();
+        lock(&MASTER, &mut entry, "second factor", FAST).unwrap();
+        // Calling a committed lock version 1 doesn't skip the commitment check.
+        entry.extra_lock.as_mut().unwrap().version = LEGACY_VERSION;
+        assert_eq!(open_entry(&MASTER, &entry, "second factor").unwrap_err(), WRONG_PASSPHRASE);
+    }
+}
@@ -0,0 +1,165 @@
+// --- AES-256-GCM-SIV ---
+// Nonce-misuse-resistant AEAD (RFC 8452). With AES-GCM a repeated nonce leaks
+// the XOR of two plaintexts and lets an attacker forge tags; with GCM-SIV it
+// only reveals that the same plaintext was sealed twice under the same nonce.
+// That matters where nonces aren't freshly random: a record sealed again, byte
+// for byte, while a sync replays it, or a nonce derived from the content.
+//
+// The tag is computed first (POLYVAL over AAD and plaintext) and doubles as the
+// CTR starting block, so each key and nonce get their own per-message keys.
+// Built from the AES, POLYVAL and CTR crates aes-gcm already links; the
+// 'aes-gcm-siv' crate does the same but isn't a dependency of this tree.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use aes::cipher::{BlockEncrypt, KeyInit, KeyIvInit, StreamCipher};
+use aes::Aes256;
+use polyval::universal_hash::UniversalHash;
+use polyval::Polyval;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+/// RFC 8452's limit on plaintext and AAD length: 2^36 bytes.
+const MAX_LEN: u64 = 1 << 36;
+
+/// Seals `plaintext` under `key` and `nonce`. Returns ciphertext || tag. The
+/// same inputs always give the same output.
+pub fn encrypt(key: &[u8; 32], nonce: &[u8; NONCE_LEN], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    check_len(plaintext, aad)?;
+    let (mut auth_key, mut enc_key) = derive_keys(key, nonce);
+    let tag = tag(&auth_key, &enc_key, nonce, plaintext, aad);
+
+    let mut sealed = Vec::with_capacity(plaintext.len() + TAG_LEN);
+    sealed.extend_from_slice(plaintext);
+    ctr(&enc_key, &tag, &mut sealed);
+    sealed.extend_from_slice(&tag);
+    auth_key.zeroize();
+    enc_key.zeroize();
+    Ok(sealed)
+}
+
+/// Reverses 'encrypt'. Fails if the key, nonce, ciphertext or AAD don't match.
+pub fn decrypt(key: &[u8; 32], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce: &[u8; NONCE_LEN] = nonce.try_into().map_err(|_| format!("Invalid IV length: {}", nonce.len()))?;
+    if sealed.len() < TAG_LEN {
+        return Err("Decryption error: ciphertext is shorter than its tag".to_string());
+    }
+    let (ciphertext, received) = sealed.split_at(sealed.len() - TAG_LEN);
+    check_len(ciphertext, aad)?;
+    let (mut auth_key, mut enc_key) = derive_keys(key, nonce);
+
+    let mut plain = ciphertext.to_vec();
+    let mut counter = [0u8; TAG_LEN];
+    counter.copy_from_slice(received);
+    ctr(&enc_key, &counter, &mut plain);
+    let expected = tag(&auth_key, &enc_key, nonce, &plain, aad);
+    auth_key.zeroize();
+    enc_key.zeroize();
+
+    if bool::from(expected.ct_eq(received)) {
+        Ok(plain)
+    } else {
+        plain.zeroize();
+        Err("Decryption error: aead::Error".to_string())
+    }
+}
+
+fn check_len(plaintext: &[u8], aad: &[u8]) -> Result<(), String> {
+    if plaintext.len() as u64 > MAX_LEN || aad.len() as u64 > MAX_LEN {
+        return Err("Encryption error: message is too long for AES-GCM-SIV".to_string());
+    }
+    Ok(())
+}
+
+/// The per-nonce POLYVAL key and AES-256 key: the first 8 bytes of AES_K over
+/// LE32(i) || nonce for i = 0..6.
+fn derive_keys(key: &[u8; 32], nonce: &[u8; NONCE_LEN]) -> ([u8; 16], [u8; 32]) {
+    let cipher = Aes256::new(key.into());
+    let mut derived = [0u8; 48];
+    for (i, half) in derived.chunks_exact_mut(8).enumerate() {
+        let mut block = [0u8; 16];
+        block[..4].copy_from_slice(&(i as u32).to_le_bytes());
+        block[4..].copy_from_slice(nonce);
+        cipher.encrypt_block((&mut block).into());
+        half.copy_from_slice(&block[..8]);
+        block.zeroize();
+    }
+    let mut auth_key = [0u8; 16];
+    let mut enc_key = [0u8; 32];
+    auth_key.copy_from_slice(&derived[..16]);
+    enc_key.copy_from_slice(&derived[16..]);
+    derived.zeroize();
+    (auth_key, enc_key)
+}
+
+fn tag(auth_key: &[u8; 16], enc_key: &[u8; 32], nonce: &[u8; NONCE_LEN], plaintext: &[u8], aad: &[u8]) -> [u8; TAG_LEN] {
+    let mut polyval = Polyval::new(auth_key.into());
+    polyval.update_padded(aad);
+    polyval.update_padded(plaintext);
+    let mut lengths = [0u8; 16];
+    lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_le_bytes());
+    lengths[8..].copy_from_slice(&(plaintext.len() as u64 * 8).to_le_bytes());
+    polyval.update_padded(&lengths);
+
+    let mut s: [u8; TAG_LEN] = polyval.finalize().into();
+    for (s, n) in s.iter_mut().zip(nonce) {
+        *s ^= n;
+    }
+    s[15] &= 0x7f;
+    Aes256::new(enc_key.into()).encrypt_block((&mut s).into());
+    s
+}
+
+/// AES-CTR from the tag with its top bit set, a 32-bit little-endian counter in the first four bytes.
+fn ctr(enc_key: &[u8; 32], tag: &[u8; TAG_LEN], data: &mut [u8]) {
+    let mut counter = *tag;
+    counter[15] |= 0x80;
+    ctr::Ctr32LE::<Aes256>::new(enc_key.into(), (&counter).into()).apply_keystream(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    const KEY: [u8; 32] = {
+        let mut key = [0u8; 32];
+        key[0] = 1;
+        key
+    };
+    const NONCE: [u8; 12] = [3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    // RFC 8452 appendix C.2, the first AES-256-GCM-SIV vectors.
+    #[test]
+    fn kat_rfc8452() {
+        for (plaintext, result) in [
+            ("", "07f5f4169bbf55a8400cd47ea6fd400f"),
+            ("0100000000000000", "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28"),
+            ("010000000000000000000000", "9aab2aeb3faa0a34aea8e2b18ca50da9ae6559e48fd10f6e5c9ca17e"),
+            ("01000000000000000000000000000000", "85a01b63025ba19b7fd3ddfc033b3e76c9eac6fa700942702e90862383c6c366"),
+        ] {
+            let sealed = encrypt(&KEY, &NONCE, &unhex(plaintext), b"").unwrap();
+            assert_eq!(sealed, unhex(result), "plaintext {:?}", plaintext);
+            assert_eq!(decrypt(&KEY, &NONCE, &sealed, b"").unwrap(), unhex(plaintext));
+        }
+    }
+
+    #[test]
+    fn test_tampering_and_wrong_aad_fail() {
+        let mut sealed = encrypt(&KEY, &NONCE, b"secret", b"ctx").unwrap();
+        assert!(decrypt(&KEY, &NONCE, &sealed, b"other").is_err());
+        assert!(decrypt(&KEY, &NONCE[..11], &sealed, b"ctx").is_err());
+        assert!(decrypt(&KEY, &NONCE, &sealed[..15], b"ctx").is_err());
+        sealed[0] ^= 1;
+        assert!(decrypt(&KEY, &NONCE, &sealed, b"ctx").is_err());
+    }
+}
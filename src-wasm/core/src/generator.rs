@@ -0,0 +1,281 @@
+// --- Generators ---
+// Random passwords, Mac-style passwords and passphrases. None of these need an
+// unlocked vault, so front ends can offer them before (or without) unlocking.
+//
+// Passphrases can be drawn from German, French or Spanish words as well as the
+// built-in English ones, since words in one's own language are far easier to
+// remember. Each list (data/wordlists/<code>.txt) is behind its own feature,
+// "passphrase-de", "passphrase-fr" and "passphrase-es". The files hold one
+// lowercase ASCII word per line, sorted with no duplicates (a test checks this).
+// Umlauts are spelled out (ae, oe, ue, ss) and words with accents or ñ are left
+// out, so a passphrase types the same on any keyboard.
+
+use std::sync::OnceLock;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::rng;
+
+/// Settings for the random password generator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub use_uppercase: bool,
+    pub use_numbers: bool,
+    pub use_symbols: bool,
+}
+
+/// Longest passphrase accepted, in words.
+pub const MAX_PASSPHRASE_WORDS: usize = 20;
+
+/// The languages passphrase words can come from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [Language::En, Language::De, Language::Fr, Language::Es];
+
+    /// The ISO 639-1 code, as in JSON.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Fr => "fr",
+            Language::Es => "es",
+        }
+    }
+
+    /// The word list, or an error if this build left it out.
+    pub fn words(self) -> Result<&'static [&'static str], String> {
+        static LISTS: [OnceLock<Vec<&'static str>>; 3] = [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+        let (slot, text) = match self {
+            Language::En => return Ok(&ENGLISH),
+            Language::De => (0, GERMAN),
+            Language::Fr => (1, FRENCH),
+            Language::Es => (2, SPANISH),
+        };
+        let words = LISTS[slot].get_or_init(|| text.lines().filter(|line| !line.is_empty()).collect());
+        if words.is_empty() {
+            return Err(format!("Generator error: this build has no '{}' word list", self.code()));
+        }
+        Ok(words)
+    }
+}
+
+const ENGLISH: [&str; 50] = [
+    "azure", "bright", "cloud", "dance", "eagle", "forest", "glory", "honey", "island", "jungle",
+    "knight", "lemon", "mountain", "night", "ocean", "pearl", "quartz", "river", "silver", "tiger",
+    "unique", "valley", "winter", "xenon", "yellow", "zebra", "alpha", "bravo", "cactus", "delta",
+    "echo", "frost", "garden", "harvest", "icon", "jade", "karma", "lunar", "magic", "nebula",
+    "orbit", "plasma", "quest", "rocket", "solar", "terra", "ultra", "vivid", "wave", "yield",
+];
+#[cfg(feature = "passphrase-de")]
+static GERMAN: &str = include_str!("../data/wordlists/de.txt");
+#[cfg(not(feature = "passphrase-de"))]
+static GERMAN: &str = "";
+#[cfg(feature = "passphrase-fr")]
+static FRENCH: &str = include_str!("../data/wordlists/fr.txt");
+#[cfg(not(feature = "passphrase-fr"))]
+static FRENCH: &str = "";
+#[cfg(feature = "passphrase-es")]
+static SPANISH: &str = include_str!("../data/wordlists/es.txt");
+#[cfg(not(feature = "passphrase-es"))]
+static SPANISH: &str = "";
+
+/// Settings for the passphrase generator; every field is optional in JSON.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    /// "en", "de", "fr" or "es".
+    pub language: Language,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions { words: 4, separator: "-".to_string(), language: Language::En }
+    }
+}
+
+/// One of the generators below with its options, as a saved preset stores it:
+/// {"style": "password", "length": 20, ...}, {"style": "mac"} or
+/// {"style": "passphrase", "words": 5, "language": "de", ...}.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "style", rename_all = "camelCase")]
+pub enum GeneratorStyle {
+    Password(PasswordOptions),
+    Mac,
+    Passphrase(PassphraseOptions),
+}
+
+/// Runs the generator `style` names.
+pub fn generate(style: &GeneratorStyle) -> Result<String, String> {
+    match style {
+        GeneratorStyle::Password(options) => Ok(password(options)),
+        GeneratorStyle::Mac => Ok(mac_password()),
+        GeneratorStyle::Passphrase(options) => passphrase_with(options),
+    }
+}
+
+/// GENERATOR: Creates a high-entropy random password with guaranteed diversity.
+pub fn password(options: &PasswordOptions) -> String {
+    let lowercase = b"abcdefghijklmnopqrstuvwxyz";
+    let uppercase = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let numbers = b"0123456789";
+    let symbols = b"!@#$%^&*()_+~`|}{[]:;?><,./-=";
+
+    // Secure random generator (seedable only in 'deterministic-rng' test builds)
+    rng::with(|rng| {
+        let mut charset = lowercase.to_vec();
+        let mut guaranteed_chars = Vec::new();
+
+        // To guarantee diversity, we pick one char from each ENABLED type first
+        guaranteed_chars.push(pick(rng, lowercase));
+
+        if options.use_uppercase {
+            charset.extend_from_slice(uppercase);
+            guaranteed_chars.push(pick(rng, uppercase));
+        }
+        if options.use_numbers {
+            charset.extend_from_slice(numbers);
+            guaranteed_chars.push(pick(rng, numbers));
+        }
+        if options.use_symbols {
+            charset.extend_from_slice(symbols);
+            guaranteed_chars.push(pick(rng, symbols));
+        }
+
+        // Fill the rest of the password length with random chars from the full set
+        if options.length < guaranteed_chars.len() {
+            return guaranteed_chars.into_iter().take(options.length).collect();
+        }
+
+        let mut pwd_chars: Vec<char> = (0..(options.length - guaranteed_chars.len())).map(|_| pick(rng, &charset)).collect();
+
+        // Add our guaranteed chars back in and shuffle them so they aren't always at the end
+        pwd_chars.extend(guaranteed_chars);
+        pwd_chars.shuffle(rng);
+        pwd_chars.into_iter().collect()
+    })
+}
+
+/// MAC-STYLE: Generates passwords like "abc12x-def45y-ghi78z"
+pub fn mac_password() -> String {
+    let charset = b"abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    rng::with(|rng| {
+        let mut generate_block = || (0..6).map(|_| pick(rng, charset)).collect::<String>();
+
+        format!("{}-{}-{}", generate_block(), generate_block(), generate_block())
+    })
+}
+
+/// PASSPHRASE: Generates memorable word-based passwords (four English words).
+pub fn passphrase() -> String {
+    passphrase_with(&PassphraseOptions::default()).unwrap_or_default()
+}
+
+/// PASSPHRASE: Like 'passphrase', with the word count, separator and language chosen.
+pub fn passphrase_with(options: &PassphraseOptions) -> Result<String, String> {
+    check_passphrase(options)?;
+    let words = options.language.words()?;
+    Ok(rng::with(|rng| {
+        (0..options.words)
+            .map(|_| words.choose(rng).copied().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(&options.separator)
+    }))
+}
+
+pub fn check_passphrase(options: &PassphraseOptions) -> Result<(), String> {
+    if options.words == 0 || options.words > MAX_PASSPHRASE_WORDS {
+        return Err(format!("Generator error: passphrases need 1 to {} words", MAX_PASSPHRASE_WORDS));
+    }
+    Ok(())
+}
+
+/// The languages this build has word lists for.
+pub fn passphrase_languages() -> Vec<Language> {
+    Language::ALL.into_iter().filter(|language| language.words().is_ok()).collect()
+}
+
+/// A uniformly random character of `set` (ASCII, never empty).
+fn pick<R: Rng + ?Sized>(rng: &mut R, set: &[u8]) -> char {
+    char::from(set[rng.gen_range(0..set.len())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_generation() {
+        let options = PasswordOptions { length: 16, use_uppercase: true, use_numbers: true, use_symbols: false };
+        let pwd = password(&options);
+        assert_eq!(pwd.len(), 16);
+        assert!(pwd.chars().any(|c| c.is_uppercase()));
+        assert!(pwd.chars().any(|c| c.is_numeric()));
+    }
+
+    #[test]
+    fn test_password_format_mac() {
+        let pwd = mac_password();
+        assert_eq!(pwd.len(), 20);
+        assert_eq!(pwd.chars().filter(|&c| c == '-').count(), 2);
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let phrase = passphrase();
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(words.len(), 4);
+    }
+
+    #[test]
+    fn test_passphrase_options() {
+        let options = PassphraseOptions { words: 6, separator: " ".to_string(), ..Default::default() };
+        assert_eq!(passphrase_with(&options).unwrap().split(' ').count(), 6);
+        let preset: GeneratorStyle = serde_json::from_str(r#"{"style": "passphrase"}"#).unwrap();
+        assert_eq!(preset, GeneratorStyle::Passphrase(PassphraseOptions::default()));
+        assert!(passphrase_with(&PassphraseOptions { words: 0, ..Default::default() }).is_err());
+        assert!(serde_json::from_str::<PassphraseOptions>(r#"{"language": "it"}"#).is_err());
+    }
+
+    #[test]
+    fn test_word_lists() {
+        for language in [Language::De, Language::Fr, Language::Es] {
+            let Ok(words) = language.words() else { continue };
+            assert!(words.len() >= 1024, "{:?} has {} words", language, words.len());
+            assert!(words.windows(2).all(|pair| pair[0] < pair[1]), "{:?} is not sorted and unique", language);
+            assert!(words.iter().all(|w| w.len() >= 3 && w.bytes().all(|b| b.is_ascii_lowercase())));
+
+            let options = PassphraseOptions { language, ..Default::default() };
+            assert!(passphrase_with(&options).unwrap().split('-').all(|w| words.contains(&w)));
+        }
+        assert_eq!(passphrase_languages()[0], Language::En);
+    }
+
+    #[cfg(feature = "deterministic-rng")]
+    #[test]
+    fn test_seeded_generators_replay() {
+        let options = || PasswordOptions { length: 20, use_uppercase: true, use_numbers: true, use_symbols: true };
+        let run = |seed| {
+            rng::seed(seed);
+            (password(&options()), mac_password(), passphrase(), crate::vault::new_entry_id())
+        };
+
+        let first = run(7);
+        assert_eq!(first, run(7));
+        assert_ne!(first, run(8));
+        rng::clear();
+    }
+}
@@ -0,0 +1,284 @@
+// --- Google Authenticator Migration Import ---
+// Google Authenticator's "Transfer accounts" QR codes hold a URI like
+// otpauth-migration://offline?data=<Base64 protobuf>. The protobuf is tiny, so
+// we decode it by hand instead of pulling in a protobuf runtime:
+//
+// message MigrationPayload {
+//   repeated OtpParameters otp_parameters = 1;
+//   int32 version = 2; int32 batch_size = 3; int32 batch_index = 4; int32 batch_id = 5;
+// }
+// message OtpParameters {
+//   bytes secret = 1; string name = 2; string issuer = 3;
+//   Algorithm algorithm = 4;   // 0 unspecified, 1 SHA1, 2 SHA256, 3 SHA512, 4 MD5
+//   DigitCount digits = 5;     // 0 unspecified, 1 six, 2 eight
+//   OtpType type = 6;          // 0 unspecified, 1 HOTP, 2 TOTP
+//   int64 counter = 7;
+// }
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use zeroize::Zeroize;
+
+use crate::otp::{encode_base32, OtpAlgorithm, TotpConfig};
+
+/// Google's exporter pads inconsistently, so accept both forms.
+const LENIENT_B64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The accounts found in one QR code. Large exports span several codes (batches).
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct MigrationBatch {
+    pub batch_index: u32,
+    pub batch_size: u32,
+    pub batch_id: u32,
+    pub accounts: Vec<TotpConfig>,
+    /// Accounts we couldn't import (HOTP counters, MD5), by name, so the UI can say so.
+    pub skipped: Vec<String>,
+}
+
+/// Decodes an otpauth-migration://offline?data=... URI.
+pub fn parse_migration_uri(uri: &str) -> Result<MigrationBatch, String> {
+    let url = Url::parse(uri.trim()).map_err(|e| format!("Migration URI parse error: {}", e))?;
+    if url.scheme() != "otpauth-migration" {
+        return Err("Not an otpauth-migration:// URI".to_string());
+    }
+    let data = url
+        .query_pairs()
+        .find(|(k, _)| k == "data")
+        .map(|(_, v)| v.into_owned())
+        .ok_or("Migration URI has no data parameter")?;
+
+    // Form decoding turns a raw '+' into a space; URL-safe scanners emit '-' and '_'.
+    let data: String = data
+        .chars()
+        .map(|c| match c {
+            ' ' | '-' => '+',
+            '_' => '/',
+            other => other,
+        })
+        .collect();
+    let mut payload = LENIENT_B64
+        .decode(data.as_bytes())
+        .map_err(|e| format!("Migration data decode error: {}", e))?;
+    let batch = parse_payload(&payload);
+    payload.zeroize();
+    batch
+}
+
+fn parse_payload(bytes: &[u8]) -> Result<MigrationBatch, String> {
+    let mut batch = MigrationBatch { batch_size: 1, ..Default::default() };
+    let mut reader = Reader { bytes, pos: 0 };
+
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, Value::Bytes(otp)) => match parse_otp(otp)? {
+                Ok(config) => batch.accounts.push(config),
+                Err(name) => batch.skipped.push(name),
+            },
+            (3, Value::Varint(v)) => batch.batch_size = v as u32,
+            (4, Value::Varint(v)) => batch.batch_index = v as u32,
+            (5, Value::Varint(v)) => batch.batch_id = v as u32,
+            _ => {} // version and unknown fields
+        }
+    }
+    Ok(batch)
+}
+
+/// Ok(Ok(config)) for an importable account, Ok(Err(name)) for one we have to skip.
+fn parse_otp(bytes: &[u8]) -> Result<Result<TotpConfig, String>, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut secret = Vec::new();
+    let (mut name, mut issuer) = (String::new(), String::new());
+    let (mut algorithm, mut digits, mut otp_type) = (0u64, 0u64, 0u64);
+
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, Value::Bytes(b)) => secret = b.to_vec(),
+            (2, Value::Bytes(b)) => name = String::from_utf8_lossy(b).into_owned(),
+            (3, Value::Bytes(b)) => issuer = String::from_utf8_lossy(b).into_owned(),
+            (4, Value::Varint(v)) => algorithm = v,
+            (5, Value::Varint(v)) => digits = v,
+            (6, Value::Varint(v)) => otp_type = v,
+            _ => {}
+        }
+    }
+
+    // Names often repeat the issuer as "Issuer:account".
+    let account = match name.split_once(':') {
+        Some((prefix, rest)) if issuer.is_empty() || prefix.trim() == issuer => {
+            if issuer.is_empty() {
+                issuer = prefix.trim().to_string();
+            }
+            rest.trim().to_string()
+        }
+        _ => name.trim().to_string(),
+    };
+
+    let algorithm = match algorithm {
+        0 | 1 => OtpAlgorithm::SHA1,
+        2 => OtpAlgorithm::SHA256,
+        3 => OtpAlgorithm::SHA512,
+        _ => return Ok(Err(name)), // MD5 has no place in a TOTP generator
+    };
+    if otp_type == 1 || secret.is_empty() {
+        return Ok(Err(name)); // HOTP counters can't be kept in sync, so we don't import them
+    }
+
+    let config = TotpConfig {
+        secret: encode_base32(&secret),
+        issuer,
+        account,
+        algorithm,
+        digits: if digits == 2 { 8 } else { 6 },
+        period: 30,
+    };
+    secret.zeroize();
+    Ok(Ok(config))
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Minimal protobuf wire-format reader: varints and length-delimited fields,
+/// skipping fixed-width ones. Every read is bounds-checked.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn next_field(&mut self) -> Result<Option<(u64, Value<'a>)>, String> {
+        if self.pos >= self.bytes.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let field = key >> 3;
+        match key & 0x7 {
+            0 => Ok(Some((field, Value::Varint(self.varint()?)))),
+            2 => {
+                let len = usize::try_from(self.varint()?).map_err(|_| "Protobuf length overflow")?;
+                let end = self.pos.checked_add(len).filter(|&e| e <= self.bytes.len()).ok_or("Protobuf field runs past the end")?;
+                let slice = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(Some((field, Value::Bytes(slice))))
+            }
+            1 => self.skip(8).map(|_| Some((field, Value::Varint(0)))),
+            5 => self.skip(4).map(|_| Some((field, Value::Varint(0)))),
+            wire => Err(format!("Unsupported protobuf wire type: {}", wire)),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.pos).ok_or("Truncated protobuf varint")?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Protobuf varint too long".to_string())
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), String> {
+        if self.pos + n > self.bytes.len() {
+            return Err("Truncated protobuf field".to_string());
+        }
+        self.pos += n;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use base64::engine::general_purpose::STANDARD as B64;
+
+    fn field_bytes(out: &mut Vec<u8>, field: u8, bytes: &[u8]) {
+        out.push(field << 3 | 2);
+        out.push(bytes.len() as u8);
+        out.extend_from_slice(bytes);
+    }
+
+    fn field_varint(out: &mut Vec<u8>, field: u8, v: u8) {
+        out.push(field << 3);
+        out.push(v);
+    }
+
+    fn otp(secret: &[u8], name: &str, issuer: &str, algorithm: u8, digits: u8, otp_type: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        field_bytes(&mut out, 1, secret);
+        field_bytes(&mut out, 2, name.as_bytes());
+        field_bytes(&mut out, 3, issuer.as_bytes());
+        field_varint(&mut out, 4, algorithm);
+        field_varint(&mut out, 5, digits);
+        field_varint(&mut out, 6, otp_type);
+        out
+    }
+
+    #[test]
+    fn test_parse_migration_batch() {
+        let mut payload = Vec::new();
+        field_bytes(&mut payload, 1, &otp(b"Hello!\xde\xad\xbe\xef", "GitHub:octo", "GitHub", 1, 1, 2));
+        field_bytes(&mut payload, 1, &otp(b"12345678901234567890", "corp@example.com", "Corp", 2, 2, 2));
+        field_bytes(&mut payload, 1, &otp(b"counter-based", "old-hotp", "", 1, 1, 1));
+        field_varint(&mut payload, 2, 1);
+        field_varint(&mut payload, 3, 2);
+        field_varint(&mut payload, 4, 1);
+
+        let encoded = B64.encode(&payload).replace('+', "%2B").replace('/', "%2F").replace('=', "%3D");
+        let batch = parse_migration_uri(&format!("otpauth-migration://offline?data={}", encoded)).unwrap();
+
+        assert_eq!((batch.batch_index, batch.batch_size), (1, 2));
+        assert_eq!(batch.accounts.len(), 2);
+        assert_eq!(batch.accounts[0].secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(batch.accounts[0].account, "octo");
+        assert_eq!(batch.accounts[0].issuer, "GitHub");
+        assert_eq!(batch.accounts[1].algorithm, OtpAlgorithm::SHA256);
+        assert_eq!(batch.accounts[1].digits, 8);
+        assert_eq!(batch.skipped, vec!["old-hotp"]);
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(parse_migration_uri("otpauth://totp/x?secret=AAAA").is_err());
+        assert!(parse_migration_uri("otpauth-migration://offline").is_err());
+        // A length prefix pointing past the end of the buffer.
+        let truncated = B64.encode([0x0a, 0x50, 0x01]);
+        assert!(parse_migration_uri(&format!("otpauth-migration://offline?data={}", truncated)).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_export_import_roundtrip(
+            secret in proptest::collection::vec(any::<u8>(), 1..40),
+            issuer in "[A-Za-z0-9 ]{1,20}",
+            account in "[a-z0-9@.]{1,20}",
+        ) {
+            let mut payload = Vec::new();
+            field_bytes(&mut payload, 1, &otp(&secret, &account, &issuer, 1, 1, 2));
+            let uri = format!("otpauth-migration://offline?data={}", B64.encode(&payload).replace('+', "%2B").replace('/', "%2F").replace('=', "%3D"));
+
+            let batch = parse_migration_uri(&uri).unwrap();
+            prop_assert_eq!(&batch.accounts[0].secret, &encode_base32(&secret));
+            prop_assert_eq!(&batch.accounts[0].issuer, &issuer);
+            prop_assert_eq!(&batch.accounts[0].account, account.trim());
+        }
+
+        #[test]
+        fn prop_arbitrary_payload_never_panics(payload in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let _ = parse_migration_uri(&format!("otpauth-migration://offline?data={}", B64.encode(&payload).replace('+', "%2B").replace('/', "%2F")));
+        }
+    }
+}
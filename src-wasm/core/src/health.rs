@@ -0,0 +1,230 @@
+// --- Vault Health Score ---
+// One 0-100 number for the dashboard, built from the audit report and the vault
+// statistics, with the parts it came from so the user can see what to fix:
+//   strength    35  passwords by 'Strength' label (excellent 1, good 0.85, fair 0.5, weak 0)
+//   reuse       30  passwords not shared with another site
+//   twoFactor   20  logins with a TOTP seed
+//   totpSetup   15  TOTP seeds without a problem (short, MD5, unreadable)
+// A category with nothing to judge (no TOTP seeds, say) is left out and the
+// others' weights scale up, so an empty vault scores 100 rather than 0.
+//
+// The front end keeps the scores of earlier runs (one per day; 'record' replaces
+// a run from the same day) and passes them back, so the dashboard can show the
+// change since the last run and over the last 30 days.
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditReport, TotpIssue};
+use crate::stats::VaultStats;
+
+/// Snapshots kept, oldest dropped first: three months of daily runs.
+pub const MAX_HISTORY: usize = 90;
+const DAY_SECS: u64 = 24 * 60 * 60;
+const MONTH_SECS: u64 = 30 * DAY_SECS;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+    Strength,
+    Reuse,
+    TwoFactor,
+    TotpSetup,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryScore {
+    pub category: Category,
+    /// 0 to 100.
+    pub score: u32,
+    /// Share of the overall score, in percent after leaving out empty categories.
+    pub weight: u32,
+    /// What the category counts ("total") and how many of those fall short.
+    pub issues: usize,
+    pub total: usize,
+}
+
+/// One earlier score, as the front end stores it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub at: u64,
+    pub score: u32,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    Up,
+    Down,
+    Flat,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Trend {
+    /// The last score from before today, if any.
+    pub previous: Option<u32>,
+    /// Score minus 'previous'; 0 without one.
+    pub change: i32,
+    pub direction: Direction,
+    /// Score minus the latest one at least 30 days old.
+    pub change_30_days: Option<i32>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    pub score: u32,
+    pub categories: Vec<CategoryScore>,
+    pub trend: Trend,
+    /// The history with this run recorded: store it for the next one.
+    pub history: Vec<Snapshot>,
+}
+
+/// The overall score and its categories.
+pub fn score(stats: &VaultStats, report: &AuditReport) -> (u32, Vec<CategoryScore>) {
+    let buckets = &stats.password_strength;
+    let strength_points = buckets.excellent as f64 + 0.85 * buckets.good as f64 + 0.5 * buckets.fair as f64;
+    let mut totp_problems: Vec<&str> = report
+        .totp
+        .iter()
+        .filter(|found| !matches!(found.issue, TotpIssue::PasskeyAvailable { .. }))
+        .map(|found| found.entry.id.as_str())
+        .collect();
+    totp_problems.sort_unstable();
+    totp_problems.dedup();
+
+    let parts = [
+        (Category::Strength, 35, stats.logins.saturating_sub(buckets.excellent + buckets.good), stats.logins, strength_points),
+        (Category::Reuse, 30, report.reused_entries, stats.logins, (stats.logins - report.reused_entries.min(stats.logins)) as f64),
+        (Category::TwoFactor, 20, stats.logins.saturating_sub(stats.logins_with_totp), stats.logins, stats.logins_with_totp as f64),
+        (
+            Category::TotpSetup,
+            15,
+            totp_problems.len(),
+            stats.logins_with_totp,
+            stats.logins_with_totp.saturating_sub(totp_problems.len()) as f64,
+        ),
+    ];
+    let weight_sum: u32 = parts.iter().filter(|part| part.3 > 0).map(|part| part.1).sum();
+    let mut overall = 0.0;
+    let mut categories = Vec::new();
+    for (category, weight, issues, total, points) in parts {
+        if total == 0 {
+            continue;
+        }
+        let fraction = (points / total as f64).clamp(0.0, 1.0);
+        let weight = f64::from(weight) / f64::from(weight_sum);
+        overall += fraction * weight;
+        categories.push(CategoryScore {
+            category,
+            score: (fraction * 100.0).round() as u32,
+            weight: (weight * 100.0).round() as u32,
+            issues,
+            total,
+        });
+    }
+    let overall = if categories.is_empty() { 100 } else { (overall * 100.0).round() as u32 };
+    (overall, categories)
+}
+
+/// Adds a run to the history (replacing one from the same day), keeping the last MAX_HISTORY.
+pub fn record(history: &mut Vec<Snapshot>, snapshot: Snapshot) {
+    history.retain(|s| s.at <= snapshot.at);
+    history.sort_by_key(|s| s.at);
+    if history.last().is_some_and(|last| last.at / DAY_SECS == snapshot.at / DAY_SECS) {
+        history.pop();
+    }
+    history.push(snapshot);
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+}
+
+/// How `snapshot` compares with the earlier runs in `history`.
+pub fn trend(history: &[Snapshot], snapshot: Snapshot) -> Trend {
+    let today = snapshot.at / DAY_SECS;
+    let earlier = |s: &&Snapshot| s.at / DAY_SECS < today;
+    let previous = history.iter().filter(earlier).max_by_key(|s| s.at).map(|s| s.score);
+    let change = previous.map_or(0, |previous| snapshot.score as i32 - previous as i32);
+    let month_ago = history.iter().filter(|s| s.at + MONTH_SECS <= snapshot.at).max_by_key(|s| s.at);
+    Trend {
+        previous,
+        change,
+        direction: match change {
+            0 => Direction::Flat,
+            c if c > 0 => Direction::Up,
+            _ => Direction::Down,
+        },
+        change_30_days: month_ago.map(|s| snapshot.score as i32 - s.score as i32),
+    }
+}
+
+/// Scores the vault and records the run in `history`.
+pub fn report(stats: &VaultStats, audit: &AuditReport, mut history: Vec<Snapshot>, now: u64) -> HealthReport {
+    let (score, categories) = score(stats, audit);
+    let snapshot = Snapshot { at: now, score };
+    let trend = trend(&history, snapshot);
+    record(&mut history, snapshot);
+    HealthReport { score, categories, trend, history }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{EntryRef, TotpFinding};
+    use crate::stats::StrengthBuckets;
+
+    #[test]
+    fn test_weighted_score_and_categories() {
+        let empty = score(&VaultStats::default(), &AuditReport::default());
+        assert_eq!(empty, (100, vec![]));
+
+        // Ten logins: 6 excellent, 2 good, 2 weak; 4 reused; 5 with TOTP, 1 of them short.
+        let stats = VaultStats {
+            password_strength: StrengthBuckets { weak: 2, fair: 0, good: 2, excellent: 6 },
+            logins: 10,
+            logins_with_totp: 5,
+            ..Default::default()
+        };
+        let short = TotpFinding {
+            entry: EntryRef { id: "a".to_string(), title: "A".to_string() },
+            issue: TotpIssue::ShortSecret { bits: 80, algorithm: crate::otp::OtpAlgorithm::SHA1 },
+        };
+        let passkey = TotpFinding { entry: short.entry.clone(), issue: TotpIssue::PasskeyAvailable { site: "github.com".to_string() } };
+        let audit = AuditReport { reused_entries: 4, totp: vec![short, passkey], ..Default::default() };
+        let (overall, categories) = score(&stats, &audit);
+        let scores: Vec<(Category, u32, usize)> = categories.iter().map(|c| (c.category, c.score, c.issues)).collect();
+        assert_eq!(
+            scores,
+            [(Category::Strength, 77, 2), (Category::Reuse, 60, 4), (Category::TwoFactor, 50, 5), (Category::TotpSetup, 80, 1)]
+        );
+        // 0.35 * 0.77 + 0.3 * 0.6 + 0.2 * 0.5 + 0.15 * 0.8
+        assert_eq!(overall, 67);
+
+        let no_totp = VaultStats { logins_with_totp: 0, ..stats };
+        let (_, categories) = score(&no_totp, &AuditReport::default());
+        assert_eq!(categories.iter().map(|c| c.weight).collect::<Vec<_>>(), [41, 35, 24]);
+    }
+
+    #[test]
+    fn test_history_and_trend() {
+        let day = |d: u64| d * DAY_SECS + 3600;
+        let mut history = Vec::new();
+        for (d, score) in [(0, 40), (10, 50), (35, 60), (35, 62)] {
+            record(&mut history, Snapshot { at: day(d), score });
+        }
+        assert_eq!(history.iter().map(|s| s.score).collect::<Vec<_>>(), [40, 50, 62]);
+
+        let trend = trend(&history, Snapshot { at: day(42), score: 58 });
+        assert_eq!((trend.previous, trend.change, trend.direction, trend.change_30_days), (Some(62), -4, Direction::Down, Some(8)));
+        let first = super::trend(&[], Snapshot { at: day(1), score: 70 });
+        assert_eq!((first.previous, first.direction, first.change_30_days), (None, Direction::Flat, None));
+
+        for d in 100..300 {
+            record(&mut history, Snapshot { at: day(d), score: 1 });
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].at, day(210));
+    }
+}
@@ -0,0 +1,270 @@
+// --- Key Hierarchy Description ---
+// A non-secret map of how a vault is protected: which KDF turns the password
+// into the master key, which keys are derived or wrapped from it, and which
+// cipher each one feeds. Auditors and the settings screen render this as-is,
+// so it must never contain key material, only parameters and labels.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::Serialize;
+
+use crate::{attachments, blobs, device_key, emergency, extra_lock, hint, kdf::KdfSettings, keys::derive_subkey, oplog, pin, search_index, segments};
+
+/// Bumped whenever a node is added, removed or changes meaning.
+pub const HIERARCHY_VERSION: u8 = 10;
+
+const KEY_ID_PURPOSE: &str = "securepass-key-id-v1";
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub version: u32,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub output_len: usize,
+}
+
+/// One key in the tree. `parent` names the node it is derived from or wrapped by.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct KeyNode {
+    pub id: String,
+    pub name: String,
+    pub parent: Option<String>,
+    pub derivation: String,
+    pub cipher: Option<String>,
+    pub protects: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct KeyHierarchy {
+    pub version: u8,
+    /// Short fingerprint of the master key, so two screens can confirm they describe the same vault.
+    pub master_key_id: String,
+    pub kdf: KdfParams,
+    pub keys: Vec<KeyNode>,
+}
+
+/// First 8 bytes of a dedicated HKDF output, hex encoded. One-way, so it reveals nothing about the key.
+pub fn key_id(master_key: &[u8; 32]) -> String {
+    derive_subkey(master_key, KEY_ID_PURPOSE, b"")[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The Argon2id settings `CryptoBridge::new` actually uses (the crate defaults).
+pub fn kdf_params() -> KdfParams {
+    let argon2 = Argon2::default();
+    let params: &Params = argon2.params();
+    KdfParams {
+        algorithm: format!("{}", Algorithm::default()),
+        version: Version::default() as u32,
+        memory_kib: params.m_cost(),
+        iterations: params.t_cost(),
+        parallelism: params.p_cost(),
+        output_len: 32, // the bridge always derives into a 32-byte buffer
+    }
+}
+
+fn node(id: &str, name: &str, parent: Option<&str>, derivation: String, cipher: Option<&str>, protects: &str) -> KeyNode {
+    KeyNode {
+        id: id.to_string(),
+        name: name.to_string(),
+        parent: parent.map(str::to_string),
+        derivation,
+        cipher: cipher.map(str::to_string),
+        protects: protects.to_string(),
+    }
+}
+
+pub fn describe(master_key: &[u8; 32]) -> KeyHierarchy {
+    let kdf = kdf_params();
+    let argon = format!("{} (m={} KiB, t={}, p={})", kdf.algorithm, kdf.memory_kib, kdf.iterations, kdf.parallelism);
+    let gcm = Some("AES-256-GCM, 96-bit random IV");
+
+    let keys = vec![
+        node(
+            "master",
+            "Master key",
+            None,
+            format!("{} over master password + per-vault salt, or unwrapped from the key envelope", argon),
+            gcm,
+            "Vault JSON",
+        ),
+        node(
+            "envelope",
+            "Key envelope key",
+            None,
+            format!("Argon2id with the envelope's own settings (new envelopes: {}) over master password + envelope salt", KdfSettings::CURRENT.label()),
+            gcm,
+            "The master key, once the vault has a key envelope (AAD: \"securepass-key-envelope-v1\")",
+        ),
+        node(
+            "device-bound",
+            "Browser-bound master key",
+            Some("master"),
+            format!(
+                "HKDF-SHA256, salt = non-extractable WebCrypto AES-GCM output over a stored challenge, info \"{}\" || 0x00 || challenge",
+                device_key::PURPOSE
+            ),
+            gcm,
+            "Vault JSON in place of the master key, when the vault is bound to one browser profile (optional)",
+        ),
+        node(
+            "decoy",
+            "Decoy key",
+            None,
+            format!("{} over decoy password + the same salt", argon),
+            gcm,
+            "Decoy vault slot (optional)",
+        ),
+        node(
+            "entry-summary",
+            "Entry summary key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00 || entry ID", segments::SUMMARY_PURPOSE),
+            gcm,
+            "Title, URL, folder and flags of one entry, segmented format (AAD: entry ID)",
+        ),
+        node(
+            "entry-secrets",
+            "Entry secrets key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00 || entry ID", segments::SECRETS_PURPOSE),
+            gcm,
+            "Username, password, notes, TOTP seed and history of one entry, segmented format (AAD: entry ID)",
+        ),
+        node(
+            "vault-root",
+            "Vault root key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00", segments::ROOT_PURPOSE),
+            gcm,
+            "Folders and vault settings, segmented format",
+        ),
+        node(
+            "extra-lock",
+            "Extra lock key",
+            Some("master"),
+            format!(
+                "Argon2id over the entry's extra passphrase (new locks: {}), then HKDF-SHA256 with an HKDF subkey of the master key (info \"{}\" || 0x00 || entry ID)",
+                KdfSettings::CURRENT.label(),
+                extra_lock::PURPOSE
+            ),
+            gcm,
+            "A random entry key sealing the secrets of one entry with an extra lock (AAD: entry ID)",
+        ),
+        node(
+            "attachment-meta",
+            "Attachment metadata key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00 || attachment ID", attachments::META_PURPOSE),
+            gcm,
+            "Attachment filename, MIME type and size (AAD: attachment ID)",
+        ),
+        node(
+            "attachment-content",
+            "Attachment content key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00 || attachment ID", attachments::CONTENT_PURPOSE),
+            gcm,
+            "Attachment bytes (AAD: attachment ID)",
+        ),
+        node(
+            "sync",
+            "Sync key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00", oplog::PURPOSE),
+            gcm,
+            "Each sync operation (AAD: Lamport clock and device ID)",
+        ),
+        node(
+            "sync-blob",
+            "Sync blob key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00", blobs::PURPOSE),
+            gcm,
+            "Op and snapshot files on remote storage (AAD: file name)",
+        ),
+        node(
+            "search",
+            "Search index key",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\" || 0x00", search_index::PURPOSE),
+            None,
+            "HMAC-SHA256 blind tokens for the n-grams of titles, usernames, URLs and folders",
+        ),
+        node(
+            "emergency-wrap",
+            "Emergency wrapping key",
+            Some("master"),
+            format!(
+                "X25519 (ephemeral, contact) then HKDF-SHA256, info \"{}\"",
+                String::from_utf8_lossy(emergency::HKDF_INFO)
+            ),
+            gcm,
+            "A copy of the master key for one emergency contact (AAD: grant policy)",
+        ),
+        node(
+            "biometric",
+            "Biometric key",
+            None,
+            format!("{} over the WebAuthn credential ID + fixed salt", argon),
+            gcm,
+            "The master password, for biometric unlock",
+        ),
+        node(
+            "pin",
+            "PIN key",
+            None,
+            format!("{} over the PIN + {}-byte device pepper", argon, pin::PEPPER_LEN),
+            gcm,
+            "A copy of the master key for PIN unlock on one device, erased after too many wrong PINs",
+        ),
+        node(
+            "hint",
+            "Password hint key",
+            None,
+            format!("HKDF-SHA256 over a fixed input, salt = per-vault salt, info \"{}\"", hint::PURPOSE),
+            gcm,
+            "The optional master password hint (AAD: salt). Readable by anyone with the salt; not a secret",
+        ),
+        node(
+            "key-id",
+            "Key fingerprint",
+            Some("master"),
+            format!("HKDF-SHA256, info \"{}\", first 8 bytes", KEY_ID_PURPOSE),
+            None,
+            "Nothing; display only",
+        ),
+    ];
+
+    KeyHierarchy { version: HIERARCHY_VERSION, master_key_id: key_id(master_key), kdf, keys }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_has_no_key_material() {
+        let master = [7u8; 32];
+        let hierarchy = describe(&master);
+        let json = serde_json::to_string(&hierarchy).unwrap();
+
+        assert_eq!(hierarchy.master_key_id.len(), 16);
+        assert_eq!(hierarchy.master_key_id, key_id(&master));
+        assert_ne!(hierarchy.master_key_id, key_id(&[8u8; 32]));
+        assert!(!json.contains(&"07".repeat(8)));
+    }
+
+    #[test]
+    fn test_parents_exist_and_kdf_matches_defaults() {
+        let hierarchy = describe(&[1u8; 32]);
+        for key in &hierarchy.keys {
+            if let Some(parent) = &key.parent {
+                assert!(hierarchy.keys.iter().any(|k| &k.id == parent), "dangling parent {}", parent);
+            }
+        }
+        assert_eq!(hierarchy.kdf.algorithm, "argon2id");
+        assert_eq!(hierarchy.kdf.memory_kib, Params::DEFAULT_M_COST);
+        assert_eq!(hierarchy.kdf.output_len, 32);
+    }
+}
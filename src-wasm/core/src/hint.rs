@@ -0,0 +1,103 @@
+// --- Master Password Hint ---
+// An optional reminder the user writes for themselves, shown on the unlock
+// screen. It has to be readable before unlock, so it can't be under the master
+// key. It is sealed under a key derived from the vault's salt alone:
+//
+//   hint key = HKDF-SHA256(salt = vault salt, ikm = "securepass-hint-v1", info = PURPOSE)
+//
+// That is obfuscation, not secrecy: anyone holding the vault's storage holds the
+// salt and can read the hint. What it buys is that the hint never sits in plain
+// text in storage, logs or backups, and is bound to its vault (the salt is also
+// the AAD). Hence the rules below: a hint that gives away the password is refused.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::keys::{open, open_committed, seal_committed};
+use crate::validate;
+
+pub(crate) const PURPOSE: &str = "securepass-hint-key-v1";
+const IKM: &[u8] = b"securepass-hint-v1";
+/// Committed to the hint key, like every new envelope. Version 1 hints predate that.
+const FORMAT_VERSION: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+
+/// In characters; a hint is a nudge, not a note.
+pub const MAX_HINT_LEN: usize = 100;
+
+/// Stored next to the vault's salt. Not secret (see above).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SealedHint {
+    pub version: u8,
+    pub iv: String,
+    pub data: String,
+}
+
+/// Seals `hint` for the vault with this salt. Refuses a hint that is empty, too
+/// long, or contains the master password (ignoring case).
+pub fn seal_hint(salt: &[u8], hint: &str, master_password: &str) -> Result<SealedHint, String> {
+    validate::salt(salt)?;
+    let hint = hint.trim();
+    if hint.is_empty() {
+        return Err("Hint error: the hint is empty".to_string());
+    }
+    if hint.chars().count() > MAX_HINT_LEN {
+        return Err(format!("Hint error: use at most {} characters", MAX_HINT_LEN));
+    }
+    let password = Zeroizing::new(master_password.trim().to_lowercase());
+    if !password.is_empty() && hint.to_lowercase().contains(password.as_str()) {
+        return Err("Hint error: the hint must not contain the master password".to_string());
+    }
+
+    let mut key = hint_key(salt);
+    let sealed = seal_committed(&key, hint.as_bytes(), salt);
+    key.zeroize();
+    let (iv, data) = sealed?;
+    Ok(SealedHint { version: FORMAT_VERSION, iv: B64.encode(iv), data: B64.encode(data) })
+}
+
+/// Reads the hint back, before unlock.
+pub fn open_hint(salt: &[u8], sealed: &SealedHint) -> Result<String, String> {
+    validate::salt(salt)?;
+    if ![LEGACY_VERSION, FORMAT_VERSION].contains(&sealed.version) {
+        return Err(format!("Unsupported hint version: {}", sealed.version));
+    }
+    let iv = B64.decode(&sealed.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&sealed.data).map_err(|e| format!("Hint decode error: {}", e))?;
+
+    let mut key = hint_key(salt);
+    let plain = if sealed.version == LEGACY_VERSION { open(&key, &iv, &data, salt) } else { open_committed(&key, &iv, &data, salt) };
+    key.zeroize();
+    Ok(validate::utf8(plain?, "hint")?)
+}
+
+fn hint_key(salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), IKM)
+        .expand(PURPOSE.as_bytes(), &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_is_bound_to_the_salt() {
+        let sealed = seal_hint(b"salt-one", "  first pet + street  ", "Rover-Elm-Street-1987").unwrap();
+        assert!(!sealed.data.contains("pet"));
+        assert_eq!(open_hint(b"salt-one", &sealed).unwrap(), "first pet + street");
+        assert!(open_hint(b"salt-two", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_refuses_revealing_hints() {
+        assert!(seal_hint(b"saltsalt", "it's ROVER-elm-street-1987", "Rover-Elm-Street-1987").unwrap_err().contains("master password"));
+        assert!(seal_hint(b"saltsalt", "   ", "pw").is_err());
+        assert!(seal_hint(b"saltsalt", &"x".repeat(MAX_HINT_LEN + 1), "pw").is_err());
+    }
+}
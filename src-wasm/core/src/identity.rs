@@ -0,0 +1,432 @@
+// --- Identity Autofill ---
+// The "identity" entry type (the 'identity' template): a name, email, phone
+// number and postal address, filled into checkout and sign-up forms. Forms
+// split these differently by region and by site: one street box or three
+// address lines, one phone box or a country code, area code and local number.
+// This module stores each value once and formats it for whichever field asks.
+//
+// Phone numbers are kept as typed and parsed on use: a leading "+" gives the
+// country, otherwise the identity's country does, and the national trunk
+// prefix ("0" in most of Europe) is dropped to get the E.164 form. Postal codes
+// are normalized to the country's usual spelling ("sw1a1aa" -> "SW1A 1AA").
+// Countries without a known format keep what was typed.
+//
+// Fields are matched by their autocomplete tokens first, then by hints in
+// their name, ID and label, as in 'autofill'.
+
+use serde::{Deserialize, Serialize};
+
+use crate::autofill::{has_token, FormDescriptor, FormField};
+use crate::vault::VaultEntry;
+
+pub const ENTRY_TYPE: &str = "identity";
+
+pub const GIVEN_NAME_FIELD: &str = "First name";
+pub const FAMILY_NAME_FIELD: &str = "Last name";
+pub const EMAIL_FIELD: &str = "Email";
+pub const PHONE_FIELD: &str = "Phone";
+pub const STREET_FIELD: &str = "Street address";
+pub const CITY_FIELD: &str = "City";
+pub const REGION_FIELD: &str = "State or region";
+pub const POSTAL_CODE_FIELD: &str = "Postal code";
+pub const COUNTRY_FIELD: &str = "Country";
+
+/// How a country writes its postal codes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Postal {
+    /// `len` digits, with `sep` inserted after the first `split` (0: none).
+    Digits { len: usize, split: usize, sep: &'static str },
+    /// ZIP or ZIP+4.
+    Us,
+    /// "A1A 1A1".
+    Ca,
+    /// Outward code, space, inward code ("SW1A 1AA").
+    Gb,
+    /// "1234 AB".
+    Nl,
+    /// Eircode, "A65 F4E2".
+    Ie,
+}
+
+struct Country {
+    code: &'static str,
+    calling_code: &'static str,
+    /// Dialled before national numbers inside the country, dropped after "+<code>".
+    trunk_prefix: &'static str,
+    postal: Postal,
+}
+
+const fn digits(len: usize) -> Postal {
+    Postal::Digits { len, split: 0, sep: "" }
+}
+
+const fn country(code: &'static str, calling_code: &'static str, trunk_prefix: &'static str, postal: Postal) -> Country {
+    Country { code, calling_code, trunk_prefix, postal }
+}
+
+const COUNTRIES: [Country; 28] = [
+    country("US", "1", "", Postal::Us),
+    country("CA", "1", "", Postal::Ca),
+    country("GB", "44", "0", Postal::Gb),
+    country("IE", "353", "0", Postal::Ie),
+    country("DE", "49", "0", digits(5)),
+    country("FR", "33", "0", digits(5)),
+    country("IT", "39", "", digits(5)),
+    country("ES", "34", "", digits(5)),
+    country("PT", "351", "", Postal::Digits { len: 7, split: 4, sep: "-" }),
+    country("NL", "31", "0", Postal::Nl),
+    country("BE", "32", "0", digits(4)),
+    country("CH", "41", "0", digits(4)),
+    country("AT", "43", "0", digits(4)),
+    country("DK", "45", "", digits(4)),
+    country("NO", "47", "", digits(4)),
+    country("SE", "46", "0", Postal::Digits { len: 5, split: 3, sep: " " }),
+    country("PL", "48", "", Postal::Digits { len: 5, split: 2, sep: "-" }),
+    country("RU", "7", "8", digits(6)),
+    country("AU", "61", "0", digits(4)),
+    country("NZ", "64", "0", digits(4)),
+    country("JP", "81", "0", Postal::Digits { len: 7, split: 3, sep: "-" }),
+    country("KR", "82", "0", digits(5)),
+    country("CN", "86", "0", digits(6)),
+    country("IN", "91", "0", digits(6)),
+    country("SG", "65", "", digits(6)),
+    country("BR", "55", "0", Postal::Digits { len: 8, split: 5, sep: "-" }),
+    country("MX", "52", "", digits(5)),
+    country("ZA", "27", "0", digits(4)),
+];
+
+fn find_country(code: &str) -> Option<&'static Country> {
+    COUNTRIES.iter().find(|c| c.code.eq_ignore_ascii_case(code.trim()))
+}
+
+/// A phone number split into its parts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Phone {
+    /// Without the "+".
+    pub country_code: String,
+    /// The national significant number: no trunk prefix, digits only.
+    pub national: String,
+    /// "+" country code national.
+    pub e164: String,
+}
+
+/// Parses a typed phone number. `default_country` (ISO 3166 alpha-2) applies when it doesn't start with "+" or "00".
+pub fn parse_phone(raw: &str, default_country: &str) -> Result<Phone, String> {
+    let trimmed = raw.trim();
+    let international = trimmed.starts_with('+') || trimmed.starts_with("00");
+    let digits: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+    if trimmed.chars().any(|c| !(c.is_ascii_digit() || " +-().\u{a0}/".contains(c))) {
+        return Err("Phone error: only digits, spaces, +, -, ., / and parentheses are allowed".to_string());
+    }
+
+    let (country_code, national) = if international {
+        let digits = if trimmed.starts_with("00") { &digits[2..] } else { &digits[..] };
+        // Calling codes are prefix-free, so at most one of these matches.
+        let code = COUNTRIES
+            .iter()
+            .map(|c| c.calling_code)
+            .find(|code| digits.starts_with(code))
+            .ok_or("Phone error: unknown country calling code")?;
+        let mut national = &digits[code.len()..];
+        // "+44 (0)20 ...": the trunk prefix in parentheses, for callers inside the country.
+        if let Some(country) = COUNTRIES.iter().find(|c| c.calling_code == code && !c.trunk_prefix.is_empty()) {
+            if trimmed.contains(&format!("({})", country.trunk_prefix)) {
+                national = national.strip_prefix(country.trunk_prefix).unwrap_or(national);
+            }
+        }
+        (code.to_string(), national.to_string())
+    } else {
+        let country = find_country(default_country).ok_or("Phone error: add a country code (+...)")?;
+        let national = digits.strip_prefix(country.trunk_prefix).unwrap_or(&digits);
+        // NANP numbers are often written with the leading 1.
+        let national = if country.calling_code == "1" && national.len() == 11 { &national[1..] } else { national };
+        (country.calling_code.to_string(), national.to_string())
+    };
+
+    if !(4..=14).contains(&national.len()) {
+        return Err("Phone error: the number is too short or too long".to_string());
+    }
+    if country_code == "1" && national.len() != 10 {
+        return Err("Phone error: North American numbers have 10 digits".to_string());
+    }
+    Ok(Phone { e164: format!("+{}{}", country_code, national), country_code, national })
+}
+
+/// Normalizes a postal code for `country` (ISO 3166 alpha-2), or fails if it can't be one.
+pub fn format_postal_code(country: &str, raw: &str) -> Result<String, String> {
+    let compact: String = raw.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_uppercase();
+    let Some(country) = find_country(country) else {
+        return Ok(raw.split_whitespace().collect::<Vec<_>>().join(" ").to_ascii_uppercase());
+    };
+    let invalid = || Err(format!("Postal code error: not a {} postal code", country.code));
+    let all = |s: &str, f: fn(&u8) -> bool| s.bytes().all(|b| f(&b));
+    let pattern = |s: &str, letters: &[usize]| {
+        s.bytes().enumerate().all(|(i, b)| if letters.contains(&i) { b.is_ascii_alphabetic() } else { b.is_ascii_digit() })
+    };
+
+    match country.postal {
+        Postal::Digits { len, split, sep } if compact.len() == len && all(&compact, u8::is_ascii_digit) => {
+            Ok(if split == 0 { compact } else { format!("{}{}{}", &compact[..split], sep, &compact[split..]) })
+        }
+        Postal::Us if matches!(compact.len(), 5 | 9) && all(&compact, u8::is_ascii_digit) => {
+            Ok(if compact.len() == 5 { compact } else { format!("{}-{}", &compact[..5], &compact[5..]) })
+        }
+        Postal::Ca if compact.len() == 6 && pattern(&compact, &[0, 2, 4]) => Ok(format!("{} {}", &compact[..3], &compact[3..])),
+        Postal::Gb
+            if (5..=7).contains(&compact.len())
+                && compact.as_bytes()[0].is_ascii_alphabetic()
+                && pattern(&compact[compact.len() - 3..], &[1, 2]) =>
+        {
+            let split = compact.len() - 3;
+            Ok(format!("{} {}", &compact[..split], &compact[split..]))
+        }
+        Postal::Nl if compact.len() == 6 && pattern(&compact, &[4, 5]) => Ok(format!("{} {}", &compact[..4], &compact[4..])),
+        Postal::Ie if compact.len() == 7 => Ok(format!("{} {}", &compact[..3], &compact[3..])),
+        _ => invalid(),
+    }
+}
+
+/// Street address lines: one per line of the stored address. Anything past the
+/// third goes on the third, comma-separated.
+pub fn address_lines(street: &str) -> [String; 3] {
+    let lines: Vec<&str> = street.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let line = |i: usize| lines.get(i).map_or(String::new(), |l| l.to_string());
+    [line(0), line(1), if lines.len() > 3 { lines[2..].join(", ") } else { line(2) }]
+}
+
+/// What goes into one identity field, named after its autocomplete token.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdentityPart {
+    Name,
+    GivenName,
+    FamilyName,
+    Email,
+    /// The whole number, E.164.
+    Tel,
+    /// "+" and the calling code.
+    TelCountryCode,
+    /// The number as dialled inside the country, with its trunk prefix.
+    TelNational,
+    TelAreaCode,
+    TelLocal,
+    StreetAddress,
+    AddressLine1,
+    AddressLine2,
+    AddressLine3,
+    /// City.
+    AddressLevel2,
+    /// State, province or region.
+    AddressLevel1,
+    PostalCode,
+    /// ISO 3166 alpha-2.
+    Country,
+}
+
+const PARTS: [(&str, IdentityPart); 17] = [
+    ("name", IdentityPart::Name),
+    ("given-name", IdentityPart::GivenName),
+    ("family-name", IdentityPart::FamilyName),
+    ("email", IdentityPart::Email),
+    ("tel", IdentityPart::Tel),
+    ("tel-country-code", IdentityPart::TelCountryCode),
+    ("tel-national", IdentityPart::TelNational),
+    ("tel-area-code", IdentityPart::TelAreaCode),
+    ("tel-local", IdentityPart::TelLocal),
+    ("street-address", IdentityPart::StreetAddress),
+    ("address-line1", IdentityPart::AddressLine1),
+    ("address-line2", IdentityPart::AddressLine2),
+    ("address-line3", IdentityPart::AddressLine3),
+    ("address-level2", IdentityPart::AddressLevel2),
+    ("address-level1", IdentityPart::AddressLevel1),
+    ("postal-code", IdentityPart::PostalCode),
+    ("country", IdentityPart::Country),
+];
+
+/// Name, ID and label hints for fields without an autocomplete token, most specific first.
+const HINTS: [(&str, IdentityPart); 16] = [
+    ("first", IdentityPart::GivenName),
+    ("given", IdentityPart::GivenName),
+    ("last", IdentityPart::FamilyName),
+    ("surname", IdentityPart::FamilyName),
+    ("email", IdentityPart::Email),
+    ("phone", IdentityPart::Tel),
+    ("mobile", IdentityPart::Tel),
+    ("address2", IdentityPart::AddressLine2),
+    ("address1", IdentityPart::AddressLine1),
+    ("street", IdentityPart::AddressLine1),
+    ("city", IdentityPart::AddressLevel2),
+    ("town", IdentityPart::AddressLevel2),
+    ("state", IdentityPart::AddressLevel1),
+    ("zip", IdentityPart::PostalCode),
+    ("postcode", IdentityPart::PostalCode),
+    ("country", IdentityPart::Country),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Identity {
+    pub given_name: String,
+    pub family_name: String,
+    pub email: String,
+    pub phone: String,
+    /// One address line per line.
+    pub street: String,
+    pub city: String,
+    pub region: String,
+    pub postal_code: String,
+    /// ISO 3166 alpha-2.
+    pub country: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IdentityFill {
+    pub field_id: String,
+    pub part: IdentityPart,
+    pub value: String,
+}
+
+impl Identity {
+    /// Reads an identity entry's fields; missing ones are empty.
+    pub fn from_entry(entry: &VaultEntry) -> Identity {
+        let value = |name: &str| entry.fields.iter().find(|f| f.name == name).map(|f| f.value.clone()).unwrap_or_default();
+        Identity {
+            given_name: value(GIVEN_NAME_FIELD),
+            family_name: value(FAMILY_NAME_FIELD),
+            email: value(EMAIL_FIELD),
+            phone: value(PHONE_FIELD),
+            street: value(STREET_FIELD),
+            city: value(CITY_FIELD),
+            region: value(REGION_FIELD),
+            postal_code: value(POSTAL_CODE_FIELD),
+            country: value(COUNTRY_FIELD).trim().to_ascii_uppercase(),
+        }
+    }
+
+    /// The value for `part`, formatted for the identity's country. Empty when
+    /// there is nothing to fill, or when it can't be split that way.
+    pub fn value(&self, part: IdentityPart) -> String {
+        let phone = || parse_phone(&self.phone, &self.country).ok();
+        let nanp = || phone().filter(|p| p.country_code == "1");
+        let [line1, line2, line3] = address_lines(&self.street);
+        match part {
+            IdentityPart::Name => format!("{} {}", self.given_name.trim(), self.family_name.trim()).trim().to_string(),
+            IdentityPart::GivenName => self.given_name.trim().to_string(),
+            IdentityPart::FamilyName => self.family_name.trim().to_string(),
+            IdentityPart::Email => self.email.trim().to_string(),
+            IdentityPart::Tel => phone().map_or_else(|| self.phone.trim().to_string(), |p| p.e164),
+            IdentityPart::TelCountryCode => phone().map_or(String::new(), |p| format!("+{}", p.country_code)),
+            IdentityPart::TelNational => phone().map_or(String::new(), |p| {
+                let trunk = COUNTRIES.iter().find(|c| c.calling_code == p.country_code).map_or("", |c| c.trunk_prefix);
+                format!("{}{}", trunk, p.national)
+            }),
+            // Only North American numbers have a fixed area code length.
+            IdentityPart::TelAreaCode => nanp().map_or(String::new(), |p| p.national[..3].to_string()),
+            IdentityPart::TelLocal => nanp().map_or(String::new(), |p| p.national[3..].to_string()),
+            IdentityPart::StreetAddress => [line1, line2, line3].iter().filter(|l| !l.is_empty()).cloned().collect::<Vec<_>>().join(", "),
+            IdentityPart::AddressLine1 => line1,
+            IdentityPart::AddressLine2 => line2,
+            IdentityPart::AddressLine3 => line3,
+            IdentityPart::AddressLevel2 => self.city.trim().to_string(),
+            IdentityPart::AddressLevel1 => self.region.trim().to_string(),
+            IdentityPart::PostalCode => {
+                format_postal_code(&self.country, &self.postal_code).unwrap_or_else(|_| self.postal_code.trim().to_string())
+            }
+            IdentityPart::Country => self.country.clone(),
+        }
+    }
+
+    /// What to write into each field of `form` that asks for part of an identity.
+    pub fn fills(&self, form: &FormDescriptor) -> Vec<IdentityFill> {
+        form.fields
+            .iter()
+            .filter_map(|field| {
+                let part = part_of(field)?;
+                let value = self.value(part);
+                (!value.is_empty()).then(|| IdentityFill { field_id: field.field_id.clone(), part, value })
+            })
+            .collect()
+    }
+}
+
+fn part_of(field: &FormField) -> Option<IdentityPart> {
+    if let Some(&(_, part)) = PARTS.iter().find(|(token, _)| has_token(&field.autocomplete, token)) {
+        return Some(part);
+    }
+    if field.field_type.eq_ignore_ascii_case("password") || field.field_type.eq_ignore_ascii_case("hidden") {
+        return None;
+    }
+    let haystack = format!("{} {} {}", field.name, field.html_id, field.label).to_ascii_lowercase();
+    let haystack: String = haystack.chars().filter(|c| !matches!(c, '_' | '-')).collect();
+    HINTS.iter().find(|(hint, _)| haystack.contains(hint)).map(|&(_, part)| part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Identity {
+        Identity {
+            given_name: "Ada".to_string(),
+            family_name: "Lovelace".to_string(),
+            phone: "020 7946 0018".to_string(),
+            street: "Flat 2\n10 Downing Street\n".to_string(),
+            city: "London".to_string(),
+            postal_code: "sw1a2aa".to_string(),
+            country: "GB".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_phone_numbers() {
+        let uk = parse_phone("020 7946 0018", "GB").unwrap();
+        assert_eq!((uk.e164.as_str(), uk.national.as_str()), ("+442079460018", "2079460018"));
+        assert_eq!(parse_phone("+44 (0)20 7946 0018", "US").unwrap(), uk);
+        assert!(parse_phone("+44 20 7946 0018 ext. 2", "GB").unwrap_err().contains("only digits"));
+        assert_eq!(parse_phone("0049 30 1234567", "US").unwrap().e164, "+49301234567");
+        assert_eq!(parse_phone("1 (415) 555-0123", "US").unwrap().national, "4155550123");
+        assert!(parse_phone("555-0123", "US").is_err());
+        assert!(parse_phone("12345678", "").is_err());
+    }
+
+    #[test]
+    fn test_postal_codes() {
+        assert_eq!(format_postal_code("GB", "sw1a2aa").unwrap(), "SW1A 2AA");
+        assert_eq!(format_postal_code("us", "94105 1234").unwrap(), "94105-1234");
+        assert_eq!(format_postal_code("CA", "k1a0b1").unwrap(), "K1A 0B1");
+        assert_eq!(format_postal_code("NL", "1012ab").unwrap(), "1012 AB");
+        assert_eq!(format_postal_code("JP", "1000001").unwrap(), "100-0001");
+        assert_eq!(format_postal_code("XX", " ab  12 ").unwrap(), "AB 12");
+        assert!(format_postal_code("DE", "1234").is_err());
+    }
+
+    #[test]
+    fn test_fills_split_fields_per_form() {
+        let field = |id: &str, name: &str, autocomplete: &str| FormField {
+            field_id: id.to_string(),
+            name: name.to_string(),
+            autocomplete: autocomplete.to_string(),
+            ..Default::default()
+        };
+        let form = FormDescriptor {
+            fields: vec![
+                field("n", "fullname", "name"),
+                field("a1", "addr", "address-line1"),
+                field("a2", "addr2", "address-line2"),
+                field("z", "postcode", ""),
+                field("cc", "", "tel-country-code"),
+                field("t", "", "shipping tel-national"),
+                field("ac", "", "tel-area-code"),
+            ],
+        };
+        let fills = identity().fills(&form);
+        let values: Vec<(&str, &str)> = fills.iter().map(|f| (f.field_id.as_str(), f.value.as_str())).collect();
+        assert_eq!(
+            values,
+            [("n", "Ada Lovelace"), ("a1", "Flat 2"), ("a2", "10 Downing Street"), ("z", "SW1A 2AA"), ("cc", "+44"), ("t", "02079460018")]
+        );
+        assert_eq!(identity().value(IdentityPart::StreetAddress), "Flat 2, 10 Downing Street");
+    }
+}
@@ -0,0 +1,260 @@
+// --- Idle Sealing ---
+// An unlocked vault spends most of its time unused, with every password in
+// plain text in Wasm linear memory, where anything that can read the page's
+// memory (a compromised extension, a memory dump) finds them. When the front
+// end reports the vault idle, the engine seals each entry's secrets under a
+// session key that exists only for this unlock, and keeps the rest in the
+// clear so lists, search and page matching still work. Revealing or filling
+// one entry opens only that entry, into a copy that is wiped after use.
+// Waking puts everything back.
+//
+// Wasm can't lock pages or keep memory out of a dump, so the session key is
+// only obfuscated: stored XOR a random mask, in two separate allocations, and
+// unmasked on the stack for each use. That stops a scan for the key bytes, not
+// a determined attacker with the whole heap; the point is that an idle vault
+// no longer holds its passwords in plain text.
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use zeroize::Zeroize;
+
+use crate::keys::{open, seal};
+use crate::vault::{wipe_value, CustomField, VaultData, VaultEntry};
+use crate::yubikey::YubiKeySecret;
+
+pub const IDLE: &str = "Idle error: wake the vault first";
+
+/// The session key, never stored as itself.
+struct MaskedKey {
+    masked: Box<[u8; 32]>,
+    mask: Box<[u8; 32]>,
+}
+
+impl MaskedKey {
+    fn generate() -> MaskedKey {
+        let mut key = [0u8; 32];
+        let mut mask = Box::new([0u8; 32]);
+        OsRng.fill_bytes(&mut key);
+        OsRng.fill_bytes(mask.as_mut_slice());
+        let mut masked = Box::new([0u8; 32]);
+        for i in 0..32 {
+            masked[i] = key[i] ^ mask[i];
+        }
+        key.zeroize();
+        MaskedKey { masked, mask }
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&[u8; 32]) -> T) -> T {
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = self.masked[i] ^ self.mask[i];
+        }
+        let result = f(&key);
+        key.zeroize();
+        result
+    }
+}
+
+impl Drop for MaskedKey {
+    fn drop(&mut self) {
+        self.masked.zeroize();
+        self.mask.zeroize();
+    }
+}
+
+/// What idling takes out of an entry. The username stays, since lists show and
+/// search it.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Secrets {
+    password: String,
+    notes: String,
+    totp_secret: Option<String>,
+    history: Vec<String>,
+    fields: Vec<CustomField>,
+    yubikey: Option<YubiKeySecret>,
+    extra: Map<String, Value>,
+}
+
+impl Secrets {
+    fn take(entry: &mut VaultEntry) -> Secrets {
+        Secrets {
+            password: std::mem::take(&mut entry.password),
+            notes: std::mem::take(&mut entry.notes),
+            totp_secret: entry.totp_secret.take(),
+            history: std::mem::take(&mut entry.history),
+            fields: std::mem::take(&mut entry.fields),
+            yubikey: entry.yubikey.take(),
+            extra: std::mem::take(&mut entry.extra),
+        }
+    }
+
+    fn restore(mut self, entry: &mut VaultEntry) {
+        entry.password = std::mem::take(&mut self.password);
+        entry.notes = std::mem::take(&mut self.notes);
+        entry.totp_secret = self.totp_secret.take();
+        entry.history = std::mem::take(&mut self.history);
+        entry.fields = std::mem::take(&mut self.fields);
+        entry.yubikey = self.yubikey.take();
+        entry.extra = std::mem::take(&mut self.extra);
+    }
+}
+
+impl Drop for Secrets {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.notes.zeroize();
+        if let Some(secret) = self.totp_secret.as_mut() {
+            secret.zeroize();
+        }
+        self.history.iter_mut().for_each(|old| old.zeroize());
+        self.fields.iter_mut().for_each(|field| field.value.zeroize());
+        if let Some(secret) = self.yubikey.as_mut() {
+            secret.wipe();
+        }
+        self.extra.values_mut().for_each(wipe_value);
+    }
+}
+
+/// The sealed secrets of an idle vault, by entry ID.
+pub struct IdleStore {
+    key: MaskedKey,
+    sealed: BTreeMap<String, ([u8; 12], Vec<u8>)>,
+}
+
+impl IdleStore {
+    /// Takes the secrets out of every entry in `vault` and seals them under a new session key.
+    pub fn seal(vault: &mut VaultData) -> Result<IdleStore, String> {
+        let key = MaskedKey::generate();
+        let mut sealed = BTreeMap::new();
+        for entry in &vault.entries {
+            let mut copy = entry.clone();
+            let secrets = Secrets::take(&mut copy);
+            copy.wipe();
+            let mut json = serde_json::to_vec(&secrets).map_err(|e| format!("Idle serialize error: {}", e))?;
+            let record = key.with(|key| seal(key, &json, entry.id.as_bytes()));
+            json.zeroize();
+            sealed.insert(entry.id.clone(), record?);
+        }
+        // Only strip the entries once all of them are sealed, so a failure leaves the vault as it was.
+        for entry in &mut vault.entries {
+            drop(Secrets::take(entry));
+        }
+        Ok(IdleStore { key, sealed })
+    }
+
+    fn open_secrets(&self, entry_id: &str) -> Result<Option<Secrets>, String> {
+        let Some((iv, data)) = self.sealed.get(entry_id) else { return Ok(None) };
+        let mut json = self.key.with(|key| open(key, iv, data, entry_id.as_bytes()))?;
+        let secrets = serde_json::from_slice(&json).map_err(|e| crate::redact::json_error("Idle parse", &e));
+        json.zeroize();
+        secrets.map(Some)
+    }
+
+    /// A full copy of one entry of the idle vault. Entries added while idle have
+    /// nothing sealed and come back as they are.
+    pub fn open_entry(&self, entry: &VaultEntry) -> Result<VaultEntry, String> {
+        let mut copy = entry.clone();
+        if let Some(secrets) = self.open_secrets(&entry.id)? {
+            secrets.restore(&mut copy);
+        }
+        Ok(copy)
+    }
+
+    /// Puts every entry's secrets back. Entries deleted while idle are skipped.
+    pub fn restore(self, vault: &mut VaultData) -> Result<(), String> {
+        let mut opened = Vec::with_capacity(vault.entries.len());
+        for entry in &vault.entries {
+            opened.push(self.open_secrets(&entry.id)?);
+        }
+        for (entry, secrets) in vault.entries.iter_mut().zip(opened) {
+            if let Some(secrets) = secrets {
+                secrets.restore(entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A loaded entry, or a copy opened from the idle store that is wiped when dropped.
+pub enum EntryRef<'a> {
+    Loaded(&'a VaultEntry),
+    Opened(Box<VaultEntry>),
+}
+
+impl Deref for EntryRef<'_> {
+    type Target = VaultEntry;
+
+    fn deref(&self) -> &VaultEntry {
+        match self {
+            EntryRef::Loaded(entry) => entry,
+            EntryRef::Opened(entry) => entry,
+        }
+    }
+}
+
+impl Drop for EntryRef<'_> {
+    fn drop(&mut self) {
+        if let EntryRef::Opened(entry) = self {
+            entry.wipe();
+            entry.extra.values_mut().for_each(wipe_value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> VaultData {
+        VaultData::from_json(
+            r#"{"entries": [
+                {"id": "1", "title": "Mail", "username": "me", "password": "hunter2", "notes": "pin 1234", "cardNumber": "4242"},
+                {"id": "2", "title": "Bank", "password": "correct horse", "totpSecret": "JBSWY3DPEHPK3PXP"}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_idle_vault_keeps_no_secrets_in_the_clear() {
+        let original = vault();
+        let mut idle = original.clone();
+        let store = IdleStore::seal(&mut idle).unwrap();
+
+        let json = idle.to_json().unwrap();
+        for secret in ["hunter2", "pin 1234", "4242", "correct horse", "JBSWY3"] {
+            assert!(!json.contains(secret), "{}", secret);
+        }
+        assert_eq!((idle.entries[0].title.as_str(), idle.entries[0].username.as_str()), ("Mail", "me"));
+
+        assert_eq!(store.open_entry(&idle.entries[1]).unwrap(), original.entries[1]);
+        store.restore(&mut idle).unwrap();
+        assert_eq!(idle, original);
+    }
+
+    #[test]
+    fn test_entries_added_or_deleted_while_idle() {
+        let mut idle = vault();
+        let store = IdleStore::seal(&mut idle).unwrap();
+        idle.entries.remove(0);
+        idle.entries.push(VaultEntry { id: "3".to_string(), password: "new".to_string(), ..Default::default() });
+
+        assert_eq!(store.open_entry(&idle.entries[1]).unwrap().password, "new");
+        store.restore(&mut idle).unwrap();
+        assert_eq!((idle.entries[0].password.as_str(), idle.entries[1].password.as_str()), ("correct horse", "new"));
+    }
+
+    #[test]
+    fn test_masked_key_is_not_stored_as_itself() {
+        let key = MaskedKey::generate();
+        let plain = key.with(|k| *k);
+        assert_ne!(*key.masked, plain);
+        assert_ne!(*key.mask, plain);
+        assert_eq!(key.with(|k| *k), plain);
+    }
+}
@@ -0,0 +1,291 @@
+// --- Importers ---
+// Every importer reads another manager's export into plain vault entries and
+// the folders they live in; the engine adds them to the loaded vault the same
+// way whichever file they came from. 'for_format' picks one by the name the
+// front end's import screen uses.
+//
+// An import can also be previewed first: 'preview' compares each imported entry
+// with the loaded vault and 'apply' carries out the user's choice for each one.
+// The comparison runs here, over the decrypted entries, so the preview report
+// carries titles, usernames and URLs but never a secret.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::vault::VaultEntry;
+use crate::{apple_keychain, browser_import, dashlane, lastpass, proton_pass};
+
+/// What an importer found.
+#[derive(Debug, Default, PartialEq)]
+pub struct Imported {
+    /// Which export the data turned out to be ("lastpass", "chrome", "firefox", "apple-keychain",
+    /// "dashlane", "proton-pass").
+    pub source: &'static str,
+    pub entries: Vec<VaultEntry>,
+    /// Every folder, including empty ones, in the order first seen.
+    pub folders: Vec<String>,
+    /// Rows merged into another one instead of becoming an entry.
+    pub duplicates: usize,
+}
+
+impl Imported {
+    pub(crate) fn new(source: &'static str) -> Imported {
+        Imported { source, ..Default::default() }
+    }
+
+    /// Records a folder the first time it is seen.
+    pub(crate) fn add_folder(&mut self, folder: &str) {
+        if !folder.is_empty() && !self.folders.iter().any(|f| f == folder) {
+            self.folders.push(folder.to_string());
+        }
+    }
+}
+
+/// What the front end shows after an import.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ImportSummary {
+    pub source: &'static str,
+    pub added: usize,
+    pub duplicates: usize,
+}
+
+pub trait Importer {
+    /// Reads `data` (the export file as text); new entries are stamped `now`
+    /// unless the export says when they last changed.
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String>;
+}
+
+/// The importer for a format name: "lastpass" (CSV), "browser" (Chrome/Edge or
+/// Firefox CSV), "apple" (Safari or Passwords app CSV), "dashlane" (CSV or JSON)
+/// or "proton-pass" (JSON).
+pub fn for_format(format: &str) -> Result<Box<dyn Importer>, String> {
+    match format {
+        "lastpass" => Ok(Box::new(lastpass::LastPass::default())),
+        "browser" => Ok(Box::new(browser_import::BrowserCsv)),
+        "apple" => Ok(Box::new(apple_keychain::AppleKeychain)),
+        "dashlane" => Ok(Box::new(dashlane::Dashlane)),
+        "proton-pass" => Ok(Box::new(proton_pass::ProtonPass)),
+        _ => Err(format!("Import error: unknown format '{}'", format)),
+    }
+}
+
+// --- Preview ---
+
+/// How an imported entry relates to the loaded vault.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// No entry for the same account.
+    New,
+    /// The same account with the same secrets.
+    Duplicate,
+    /// The same account, changed in the export after the vault's copy.
+    Update,
+    /// The same account, changed in the vault since (or the export has no date).
+    Conflict,
+}
+
+/// What to do with one imported entry.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Choice {
+    /// Add it as a new entry, even next to an existing one.
+    Add,
+    /// Overwrite the matching entry's details, keeping its ID and folder.
+    Replace,
+    Skip,
+}
+
+impl Status {
+    /// New entries are added, updates replace, duplicates and conflicts are skipped.
+    pub fn default_choice(self) -> Choice {
+        match self {
+            Status::New => Choice::Add,
+            Status::Update => Choice::Replace,
+            Status::Duplicate | Status::Conflict => Choice::Skip,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewItem {
+    /// Position in the import; the key for this item's choice.
+    pub index: usize,
+    pub title: String,
+    pub username: String,
+    pub url: String,
+    pub status: Status,
+    /// The matching vault entry, unless 'status' is New.
+    pub existing_id: Option<String>,
+    /// Which secrets differ from the matching entry ("password", "totp", "notes",
+    /// "fields"). URLs on the same host don't count.
+    pub changed: Vec<&'static str>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub source: &'static str,
+    pub items: Vec<PreviewItem>,
+    /// Rows the importer merged before the comparison.
+    pub merged: usize,
+}
+
+fn host(url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+/// Two entries are for the same account when they have the same type and
+/// username, and the same host (ignoring "www.") or, without URLs, the same title.
+fn same_account(a: &VaultEntry, b: &VaultEntry) -> bool {
+    let same_place = match (host(&a.url), host(&b.url)) {
+        (Some(x), Some(y)) => x == y,
+        (None, None) => a.title.trim().eq_ignore_ascii_case(b.title.trim()),
+        _ => false,
+    };
+    a.kind() == b.kind() && a.username == b.username && same_place
+}
+
+fn differences(existing: &VaultEntry, imported: &VaultEntry) -> Vec<&'static str> {
+    let values = |entry: &VaultEntry| entry.fields.iter().map(|f| (f.name.clone(), f.value.clone())).collect::<Vec<_>>();
+    let mut changed = Vec::new();
+    if existing.password != imported.password {
+        changed.push("password");
+    }
+    if existing.totp_secret != imported.totp_secret {
+        changed.push("totp");
+    }
+    if existing.notes != imported.notes {
+        changed.push("notes");
+    }
+    if values(existing) != values(imported) {
+        changed.push("fields");
+    }
+    changed
+}
+
+/// Compares each imported entry with the vault's entries.
+pub fn preview(import: &Imported, vault: &[VaultEntry]) -> ImportPreview {
+    let items = import
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let existing = vault.iter().find(|e| same_account(e, entry));
+            let changed = existing.map(|e| differences(e, entry)).unwrap_or_default();
+            let status = match existing {
+                None => Status::New,
+                Some(_) if changed.is_empty() => Status::Duplicate,
+                Some(e) if entry.last_modified > e.last_modified => Status::Update,
+                Some(_) => Status::Conflict,
+            };
+            PreviewItem {
+                index,
+                title: entry.title.clone(),
+                username: entry.username.clone(),
+                url: entry.url.clone(),
+                status,
+                existing_id: existing.map(|e| e.id.clone()),
+                changed,
+            }
+        })
+        .collect();
+    ImportPreview { source: import.source, items, merged: import.duplicates }
+}
+
+/// Choice::Replace: moves the imported details (URL, username, secrets, fields)
+/// into the vault entry, which keeps its ID, title, folder and flags. A replaced
+/// password goes to the front of the history; the old values are wiped.
+pub fn replace_details(existing: &mut VaultEntry, mut imported: VaultEntry) {
+    if imported.password != existing.password && !existing.password.is_empty() {
+        existing.history.insert(0, existing.password.clone());
+        existing.history.truncate(5);
+    }
+    std::mem::swap(&mut existing.url, &mut imported.url);
+    std::mem::swap(&mut existing.username, &mut imported.username);
+    std::mem::swap(&mut existing.password, &mut imported.password);
+    std::mem::swap(&mut existing.totp_secret, &mut imported.totp_secret);
+    std::mem::swap(&mut existing.notes, &mut imported.notes);
+    std::mem::swap(&mut existing.fields, &mut imported.fields);
+    existing.last_modified = imported.last_modified;
+    imported.wipe();
+}
+
+/// An import waiting for the user's choices.
+pub struct PendingImport {
+    pub import: Imported,
+    pub preview: ImportPreview,
+}
+
+impl PendingImport {
+    /// Refuses choices for items the preview doesn't have, and replacing an item
+    /// that matched nothing.
+    pub fn check(&self, choices: &BTreeMap<usize, Choice>) -> Result<(), String> {
+        for (index, choice) in choices {
+            let item = self.preview.items.get(*index).ok_or_else(|| format!("Import error: no item {}", index))?;
+            if *choice == Choice::Replace && item.existing_id.is_none() {
+                return Err(format!("Import error: item {} matches no entry to replace", index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Overwrites the imported secrets before the import is dropped.
+    pub fn wipe(mut self) {
+        self.import.entries.iter_mut().for_each(VaultEntry::wipe);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(title: &str, url: &str, username: &str, password: &str, last_modified: u64) -> VaultEntry {
+        VaultEntry {
+            id: format!("id-{}-{}", title, password),
+            title: title.to_string(),
+            url: url.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            last_modified,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_preview_statuses() {
+        let vault = vec![
+            login("GitHub", "https://github.com", "octo", "pw", 100),
+            login("Bank", "https://bank.example", "me", "old", 100),
+            login("Shop", "https://shop.example", "me", "mine", 300),
+        ];
+        let mut import = Imported::new("chrome");
+        import.entries = vec![
+            login("github.com", "https://www.github.com", "octo", "pw", 200),
+            login("bank.example", "https://bank.example", "me", "new", 200),
+            login("shop.example", "https://shop.example", "me", "theirs", 200),
+            login("mail.example", "https://mail.example", "me", "pw", 200),
+        ];
+        let preview = preview(&import, &vault);
+        let statuses: Vec<Status> = preview.items.iter().map(|item| item.status).collect();
+        assert_eq!(statuses, vec![Status::Duplicate, Status::Update, Status::Conflict, Status::New]);
+        assert_eq!(preview.items[1].changed, vec!["password"]);
+        assert_eq!(preview.items[1].existing_id.as_deref(), Some("id-Bank-old"));
+        assert_eq!(preview.items[3].existing_id, None);
+    }
+
+    #[test]
+    fn test_replace_keeps_identity_and_history() {
+        let mut existing = login("Bank", "https://bank.example", "me", "old", 100);
+        existing.category = "Money".to_string();
+        replace_details(&mut existing, login("bank.example", "https://bank.example/login", "me", "new", 200));
+        assert_eq!((existing.id.as_str(), existing.title.as_str(), existing.category.as_str()), ("id-Bank-old", "Bank", "Money"));
+        assert_eq!((existing.password.as_str(), existing.last_modified), ("new", 200));
+        assert_eq!(existing.url, "https://bank.example/login");
+        assert_eq!(existing.history, vec!["old"]);
+    }
+}
@@ -0,0 +1,219 @@
+// --- Write-Ahead Journal ---
+// Saving re-seals the whole vault, which takes a while for a big vault and, in
+// a browser, can be cut short by a closed tab. So each edit is first appended
+// to a journal as a small sealed record, the changes ('oplog::Change') since
+// the previous record, and the vault itself is saved now and then. After a
+// save ('checkpoint') the journal starts over and the old records are deleted.
+//
+// Each record names the save it follows (by that save's IV) and its place in
+// the journal, both bound into the seal. On load, 'replay' applies the records
+// that follow the loaded save, in order, and stops at the first one missing or
+// unreadable. Records that follow an older save are skipped: a crash after
+// saving but before deleting them leaves them behind, and the save holds them.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::oplog::{self, Change};
+use crate::redact;
+use crate::vault::VaultData;
+
+/// Records are sealed with key commitment since version 2; version 1 was plain AES-GCM.
+pub const JOURNAL_VERSION: u8 = 2;
+const LEGACY_JOURNAL_VERSION: u8 = 1;
+const PURPOSE: &str = "securepass-journal-v1";
+
+/// One journaled edit, as stored: the changes sealed, the position in the clear.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JournalRecord {
+    pub version: u8,
+    /// Base64 IV of the saved vault this record follows.
+    pub base: String,
+    /// 1 for the first record after a save.
+    pub sequence: u64,
+    pub iv: String,
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Replay {
+    /// Records applied to the loaded vault.
+    pub applied: usize,
+    /// Records skipped: for an older save, out of sequence, or unreadable. Delete
+    /// them before appending, since the next record reuses a torn one's sequence.
+    pub skipped: usize,
+}
+
+/// The vault as of the last record, to diff the next edit against.
+pub struct Journal {
+    base: String,
+    sequence: u64,
+    journaled: VaultData,
+}
+
+impl Journal {
+    /// A journal for `vault`, just loaded from or saved as the save sealed under `base_iv`.
+    pub fn new(base_iv: &[u8], vault: &VaultData) -> Journal {
+        Journal { base: B64.encode(base_iv), sequence: 0, journaled: vault.clone() }
+    }
+
+    /// Records appended since the last save.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Seals what changed from the last record to `vault` as the next record.
+    /// Returns None when nothing changed. Write it before reporting the edit saved.
+    pub fn append(&mut self, master_key: &[u8; 32], vault: &VaultData) -> Result<Option<JournalRecord>, String> {
+        let mut changes = oplog::diff(&self.journaled, vault)?;
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let record = seal_record(master_key, &self.base, self.sequence + 1, &changes);
+        changes.iter_mut().for_each(oplog::wipe_change);
+        let record = record?;
+
+        self.sequence += 1;
+        std::mem::replace(&mut self.journaled, vault.clone()).wipe();
+        Ok(Some(record))
+    }
+
+    /// Starts over after `vault` was saved under `base_iv`. Delete the old records
+    /// once the save is written; if writing it fails, save again.
+    pub fn checkpoint(&mut self, base_iv: &[u8], vault: &VaultData) {
+        std::mem::replace(self, Journal::new(base_iv, vault)).journaled.wipe();
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        self.journaled.wipe();
+    }
+}
+
+/// Applies to `vault`, loaded from the save sealed under `base_iv`, the records
+/// that follow it, and returns the journal to continue from.
+pub fn replay(master_key: &[u8; 32], base_iv: &[u8], vault: &mut VaultData, records: &[JournalRecord]) -> (Journal, Replay) {
+    let mut journal = Journal::new(base_iv, vault);
+    let mut following: Vec<&JournalRecord> = records.iter().filter(|r| r.base == journal.base).collect();
+    following.sort_by_key(|r| r.sequence);
+
+    let mut applied = 0;
+    for record in following {
+        if record.sequence != journal.sequence + 1 {
+            break;
+        }
+        let Ok(mut changes) = open_record(master_key, record) else { break };
+        for change in &changes {
+            oplog::apply_change(vault, change);
+        }
+        changes.iter_mut().for_each(oplog::wipe_change);
+        journal.sequence += 1;
+        applied += 1;
+    }
+    std::mem::replace(&mut journal.journaled, vault.clone()).wipe();
+    (journal, Replay { applied, skipped: records.len() - applied })
+}
+
+fn seal_record(master_key: &[u8; 32], base: &str, sequence: u64, changes: &[Change]) -> Result<JournalRecord, String> {
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let mut plain = serde_json::to_vec(changes).map_err(|e| format!("Journal serialize error: {}", e))?;
+    let sealed = seal_committed(&key, &plain, &position(base, sequence));
+    key.zeroize();
+    plain.zeroize();
+
+    let (iv, data) = sealed?;
+    Ok(JournalRecord {
+        version: JOURNAL_VERSION,
+        base: base.to_string(),
+        sequence,
+        iv: B64.encode(iv),
+        data: B64.encode(data),
+    })
+}
+
+fn open_record(master_key: &[u8; 32], record: &JournalRecord) -> Result<Vec<Change>, String> {
+    if ![LEGACY_JOURNAL_VERSION, JOURNAL_VERSION].contains(&record.version) {
+        return Err(format!("Unsupported journal version: {}", record.version));
+    }
+    let iv = B64.decode(&record.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&record.data).map_err(|e| format!("Journal decode error: {}", e))?;
+
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let aad = position(&record.base, record.sequence);
+    let plain = if record.version == LEGACY_JOURNAL_VERSION { open(&key, &iv, &data, &aad) } else { open_committed(&key, &iv, &data, &aad) };
+    key.zeroize();
+
+    let mut plain = plain?;
+    let changes = serde_json::from_slice(&plain).map_err(|e| redact::json_error("Journal parse", &e));
+    plain.zeroize();
+    changes
+}
+
+/// AAD for a record: sequence (u64 BE) || base.
+fn position(base: &str, sequence: u64) -> Vec<u8> {
+    let mut aad = sequence.to_be_bytes().to_vec();
+    aad.extend_from_slice(base.as_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [5u8; 32];
+
+    fn vault_with(titles: &[&str]) -> VaultData {
+        let entries: Vec<String> = titles
+            .iter()
+            .map(|t| format!(r#"{{"id":"{t}","title":"{t}","username":"u","password":"p","url":""}}"#))
+            .collect();
+        VaultData::from_json(&format!(r#"{{"entries":[{}]}}"#, entries.join(","))).unwrap()
+    }
+
+    fn titles(vault: &VaultData) -> Vec<&str> {
+        vault.entries.iter().map(|e| e.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_replay_restores_unsaved_edits() {
+        let saved = vault_with(&["a"]);
+        let mut journal = Journal::new(b"save-1", &saved);
+        let first = journal.append(&KEY, &vault_with(&["a", "b"])).unwrap().unwrap();
+        assert!(journal.append(&KEY, &vault_with(&["a", "b"])).unwrap().is_none());
+        let second = journal.append(&KEY, &vault_with(&["b", "c"])).unwrap().unwrap();
+
+        let mut loaded = saved.clone();
+        let (journal, report) = replay(&KEY, b"save-1", &mut loaded, &[second.clone(), first.clone()]);
+        assert_eq!(titles(&loaded), ["b", "c"]);
+        assert_eq!((report.applied, report.skipped, journal.sequence()), (2, 0, 2));
+
+        // The second record was torn; the first still applies.
+        let mut torn = second;
+        torn.data.truncate(8);
+        let mut loaded = saved.clone();
+        assert_eq!(replay(&KEY, b"save-1", &mut loaded, &[first.clone(), torn]).1.applied, 1);
+        assert_eq!(titles(&loaded), ["a", "b"]);
+
+        // Left over from before the save that now holds them.
+        let mut loaded = vault_with(&["a", "b"]);
+        assert_eq!(replay(&KEY, b"save-2", &mut loaded, &[first]).1, Replay { applied: 0, skipped: 1 });
+    }
+
+    #[test]
+    fn test_records_are_bound_to_their_position() {
+        let mut journal = Journal::new(b"save-1", &vault_with(&[]));
+        let mut record = journal.append(&KEY, &vault_with(&["a"])).unwrap().unwrap();
+        record.sequence = 2;
+        assert!(open_record(&KEY, &record).is_err());
+        record.sequence = 1;
+        assert!(open_record(&[6u8; 32], &record).is_err());
+        assert!(open_record(&KEY, &JournalRecord { version: LEGACY_JOURNAL_VERSION, ..record.clone() }).is_err());
+        assert!(open_record(&KEY, &record).is_ok());
+
+        journal.checkpoint(b"save-2", &vault_with(&["a"]));
+        assert_eq!(journal.append(&KEY, &vault_with(&["a", "b"])).unwrap().unwrap().sequence, 1);
+    }
+}
@@ -0,0 +1,262 @@
+// --- Plaintext JSON Export ---
+// An unencrypted export, for users who explicitly ask for one (to move to
+// another manager, or to keep a copy they can read with any text editor). The
+// types below are the schema: snake_case, every field named for what it is
+// rather than how the web app happens to store it, and a 'schema_version' so a
+// file written today still imports after the vault model moves on.
+//
+// Versions:
+//   0  no 'schema_version': the raw vault JSON inside the age and OpenPGP
+//      exports (see 'age', 'openpgp').
+//   1  'ExportFile' below.
+// The importer reads every version up to 'SCHEMA_VERSION' and refuses newer
+// ones rather than guessing. Changes that add optional fields keep the
+// version; anything that renames, removes or reinterprets a field bumps it and
+// adds a case to 'parse'.
+//
+// Not exported: attachment contents (stored apart from the vault; the export
+// would only carry dangling IDs), usage counters (they describe this device),
+// vault settings, and entries under an extra lock, which only open inside this
+// vault and have to be unlocked first.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::domains::EquivalentDomains;
+use crate::vault::{CustomField, VaultData, VaultEntry};
+use crate::yubikey::YubiKeySecret;
+
+/// The 'format' every export carries, so a stray JSON file isn't taken for one.
+pub const FORMAT: &str = "securepass-export";
+/// The version this build writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A whole export file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExportFile {
+    /// Always 'FORMAT'.
+    pub format: String,
+    pub schema_version: u32,
+    /// Unix seconds.
+    pub exported_at: u64,
+    #[serde(default)]
+    pub folders: Vec<ExportFolder>,
+    #[serde(default)]
+    pub items: Vec<ExportItem>,
+    /// Groups of domains that count as one site for autofill, e.g. ["amazon.com", "amazon.de"].
+    #[serde(default)]
+    pub equivalent_domains: EquivalentDomains,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExportFolder {
+    pub name: String,
+    /// Left out of travel vaults.
+    #[serde(default)]
+    pub travel_mode: bool,
+}
+
+/// One vault item. Only 'id' and 'title' are required; everything else defaults to empty.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExportItem {
+    pub id: String,
+    /// "login", "note", "card", "identity", "wifi", ... (see 'templates').
+    #[serde(rename = "type", default = "login")]
+    pub kind: String,
+    pub title: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub notes: String,
+    /// The folder's name, if the item is in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    /// An "otpauth://" URI or a bare Base32 secret.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Revealing the item should ask for the master password again.
+    #[serde(default)]
+    pub reprompt: bool,
+    /// Left out of travel vaults.
+    #[serde(default)]
+    pub travel_mode: bool,
+    /// Earlier passwords, oldest first.
+    #[serde(default)]
+    pub password_history: Vec<String>,
+    /// Typed fields: {name, kind, value}, kind one of "text", "hidden", "number",
+    /// "date" (YYYY-MM-DD), "url", "email", "phone".
+    #[serde(default)]
+    pub fields: Vec<CustomField>,
+    /// The slot secret of a "yubikey" item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yubikey: Option<YubiKeySecret>,
+    /// Unix seconds; 0 if unknown.
+    #[serde(default)]
+    pub modified_at: u64,
+    /// Item data this schema doesn't name (such as a card number an older web
+    /// app stored as 'cardNumber'), under the key it was stored with.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+fn login() -> String {
+    "login".to_string()
+}
+
+impl ExportItem {
+    fn from_entry(entry: &VaultEntry) -> ExportItem {
+        ExportItem {
+            id: entry.id.clone(),
+            kind: entry.kind().to_string(),
+            title: entry.title.clone(),
+            username: entry.username.clone(),
+            password: entry.password.clone(),
+            url: entry.url.clone(),
+            notes: entry.notes.clone(),
+            folder: (!entry.category.is_empty()).then(|| entry.category.clone()),
+            totp: entry.totp_secret.clone(),
+            favorite: entry.favorite,
+            reprompt: entry.reprompt,
+            travel_mode: entry.travel_mode,
+            password_history: entry.history.clone(),
+            fields: entry.fields.clone(),
+            yubikey: entry.yubikey.clone(),
+            modified_at: entry.last_modified,
+            extra: entry.extra.clone(),
+        }
+    }
+
+    fn into_entry(self) -> VaultEntry {
+        VaultEntry {
+            id: self.id,
+            title: self.title,
+            entry_type: if self.kind == "login" { String::new() } else { self.kind },
+            username: self.username,
+            password: self.password,
+            url: self.url,
+            notes: self.notes,
+            category: self.folder.unwrap_or_default(),
+            totp_secret: self.totp,
+            favorite: self.favorite,
+            history: self.password_history,
+            last_modified: self.modified_at,
+            travel_mode: self.travel_mode,
+            reprompt: self.reprompt,
+            yubikey: self.yubikey,
+            fields: self.fields,
+            extra: self.extra,
+            ..Default::default()
+        }
+    }
+}
+
+/// The export of `vault` at `now`. Fails if any entry is still under an extra lock.
+pub fn export(vault: &VaultData, now: u64) -> Result<ExportFile, String> {
+    if let Some(locked) = vault.entries.iter().find(|entry| entry.extra_lock.is_some()) {
+        return Err(format!("Export error: remove the extra lock from '{}' first", locked.title));
+    }
+    Ok(ExportFile {
+        format: FORMAT.to_string(),
+        schema_version: SCHEMA_VERSION,
+        exported_at: now,
+        folders: vault
+            .categories
+            .iter()
+            .map(|name| ExportFolder { name: name.clone(), travel_mode: vault.travel_mode_categories.contains(name) })
+            .collect(),
+        items: vault.entries.iter().map(ExportItem::from_entry).collect(),
+        equivalent_domains: vault.equivalent_domains.clone(),
+    })
+}
+
+/// Reads an export of any supported version into a vault.
+pub fn parse(json: &str) -> Result<VaultData, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| crate::redact::json_error("Export parse", &e))?;
+    let Some(version) = value.get("schema_version") else {
+        if value.get("entries").is_some_and(Value::is_array) {
+            return VaultData::from_json(json);
+        }
+        return Err("Export error: not a SecurePass export".to_string());
+    };
+    match version.as_u64() {
+        Some(1) => {
+            let file: ExportFile = serde_json::from_value(value).map_err(|e| crate::redact::json_error("Export parse", &e))?;
+            if file.format != FORMAT {
+                return Err("Export error: not a SecurePass export".to_string());
+            }
+            Ok(file.into_vault())
+        }
+        Some(v) if v > SCHEMA_VERSION as u64 => {
+            Err(format!("Export error: schema version {} is newer than this app supports; update SecurePass", v))
+        }
+        _ => Err(format!("Export error: unknown schema version {}", version)),
+    }
+}
+
+impl ExportFile {
+    fn into_vault(self) -> VaultData {
+        let travel_mode_categories = self.folders.iter().filter(|f| f.travel_mode).map(|f| f.name.clone()).collect();
+        VaultData {
+            entries: self.items.into_iter().map(ExportItem::into_entry).collect(),
+            categories: self.folders.into_iter().map(|f| f.name).collect(),
+            travel_mode_categories,
+            equivalent_domains: self.equivalent_domains,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = r#"{
+        "entries": [
+            {"id": "1", "title": "GitHub", "username": "octo", "password": "pw", "url": "https://github.com",
+             "category": "work", "totpSecret": "JBSWY3DPEHPK3PXP", "history": ["old"], "lastModified": 5},
+            {"id": "2", "type": "card", "title": "Visa", "cardNumber": "4242424242424242", "reprompt": true,
+             "fields": [{"name": "PIN", "kind": "hidden", "value": "1234"}]}
+        ],
+        "categories": ["work", "finance"],
+        "travelModeCategories": ["finance"]
+    }"#;
+
+    #[test]
+    fn test_export_roundtrip() {
+        let vault = VaultData::from_json(VAULT).unwrap();
+        let json = serde_json::to_string(&export(&vault, 100).unwrap()).unwrap();
+        assert!(json.starts_with(r#"{"format":"securepass-export","schema_version":1,"exported_at":100,"#));
+        assert!(json.contains(r#""type":"login","title":"GitHub""#) && json.contains(r#""folder":"work""#));
+        assert!(json.contains(r#""extra":{"cardNumber":"4242424242424242"}"#));
+        assert_eq!(parse(&json).unwrap(), vault);
+    }
+
+    #[test]
+    fn test_parse_accepts_every_version() {
+        // Version 0: the vault JSON from an age or OpenPGP export.
+        assert_eq!(parse(VAULT).unwrap(), VaultData::from_json(VAULT).unwrap());
+
+        let minimal = r#"{"format": "securepass-export", "schema_version": 1, "exported_at": 0,
+            "items": [{"id": "a", "title": "Note", "type": "note", "notes": "hi"}]}"#;
+        let vault = parse(minimal).unwrap();
+        assert_eq!((vault.entries[0].kind(), vault.entries[0].notes.as_str()), ("note", "hi"));
+
+        assert!(parse(r#"{"format": "securepass-export", "schema_version": 2}"#).unwrap_err().contains("newer"));
+        assert!(parse(r#"{"format": "other", "schema_version": 1, "exported_at": 0}"#).unwrap_err().contains("not a SecurePass"));
+        assert!(parse(r#"{"items": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_extra_locked_entries_block_the_export() {
+        let mut vault = VaultData::from_json(VAULT).unwrap();
+        let fast = crate::kdf::KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+        crate::extra_lock::lock(&[1u8; 32], &mut vault.entries[0], "second", fast).unwrap();
+        assert!(export(&vault, 0).unwrap_err().contains("'GitHub'"));
+    }
+}
@@ -0,0 +1,340 @@
+// --- KDF Settings & Key Envelope ---
+// Vaults have so far used the master key straight out of Argon2id with the
+// argon2 crate's defaults (19 MiB, 2 passes). Raising those settings directly
+// would change the master key, and with it everything sealed under it. So the
+// master key is wrapped instead: a key envelope holds the KDF settings, a salt,
+// a verifier and the master key sealed under what those settings derive from
+// the password. Stronger settings only ever mean a new envelope.
+//
+// A vault without an envelope is described by a "direct" one: LEGACY settings
+// and nothing wrapped, the derived key is the master key. Unlocking an envelope
+// that needs an upgrade is transitional: the password is hashed under the old
+// settings to recover the master key and under CURRENT to wrap it again, and
+// the replacement envelope is saved in one write once the vault has opened.
+//
+// An upgraded legacy vault keeps its master key: the old Argon2 output over the
+// old salt. Delete that salt when saving the envelope. Without it the old
+// derivation can't be attacked, and the only way to test a guess is through the
+// envelope's CURRENT settings.
+//
+// A wrong password must not be quicker to reject than a right one is to accept,
+// nor say which check caught it: unlocking always derives, compares the verifier
+// and opens the wrapped key, and every failure past the KDF is UNLOCK_FAILED.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::text::{normalize_password, PasswordPolicy};
+
+/// The wrapped key is committed to the wrapping key ('keys::open_committed').
+/// Version 1 envelopes predate that, and are replaced on the next unlock.
+const FORMAT_VERSION: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+const WRAP_PURPOSE: &str = "securepass-envelope-wrap-v1";
+const VERIFIER_PURPOSE: &str = "securepass-envelope-verifier-v1";
+/// AAD for the wrapped master key, so it can't be confused with any other sealed blob.
+const AAD: &[u8] = b"securepass-key-envelope-v1";
+const SALT_LEN: usize = 32;
+pub const MIN_SALT_LEN: usize = 16;
+const VERIFIER_LEN: usize = 16;
+/// The one error for a wrong password, a verifier mismatch and a failed tag alike.
+pub const UNLOCK_FAILED: &str = "Unlock error: wrong password";
+
+/// Envelopes come from storage, so settings that would take minutes (or all the
+/// device's memory) to run are refused rather than attempted.
+const MAX_MEMORY_KIB: u32 = 2 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id cost settings. The output is always 32 bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfSettings {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfSettings {
+    /// The argon2 crate's defaults: what 'Engine::new' and every vault created
+    /// before envelopes use.
+    pub const LEGACY: KdfSettings = KdfSettings {
+        memory_kib: Params::DEFAULT_M_COST,
+        iterations: Params::DEFAULT_T_COST,
+        parallelism: Params::DEFAULT_P_COST,
+    };
+
+    /// What new and upgraded envelopes use: 64 MiB, 3 passes.
+    pub const CURRENT: KdfSettings = KdfSettings { memory_kib: 64 * 1024, iterations: 3, parallelism: 1 };
+
+    /// True when these settings cost less memory or fewer passes than CURRENT.
+    pub fn is_outdated(&self) -> bool {
+        self.memory_kib < Self::CURRENT.memory_kib || self.iterations < Self::CURRENT.iterations
+    }
+
+    /// Refuses settings this client won't run: too costly to be from a real vault.
+    pub fn check_range(&self) -> Result<(), String> {
+        if self.memory_kib > MAX_MEMORY_KIB || self.iterations > MAX_ITERATIONS || self.parallelism > MAX_PARALLELISM {
+            return Err(format!("KDF error: settings out of range ({})", self.label()));
+        }
+        Ok(())
+    }
+
+    /// Argon2id over `password` and `salt` with these settings.
+    pub fn derive(&self, password: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+        self.check_range()?;
+        let params =
+            Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32)).map_err(|e| format!("Argon2 error: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password, salt, key.as_mut_slice())
+            .map_err(|e| format!("Argon2 error: {}", e))?;
+        Ok(key)
+    }
+
+    /// e.g. "Argon2id m=65536 KiB, t=3, p=1", for the audit log and settings screen.
+    pub fn label(&self) -> String {
+        format!("Argon2id m={} KiB, t={}, p={}", self.memory_kib, self.iterations, self.parallelism)
+    }
+}
+
+/// Everything needed to turn the password into the master key, minus the password.
+/// Not secret; store it next to the vault and replace it in a single write.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyEnvelope {
+    pub version: u8,
+    pub kdf: KdfSettings,
+    pub password_policy: PasswordPolicy,
+    pub salt: String,
+    /// Hex HKDF output of the derived key, so a wrong password is told apart from
+    /// a damaged envelope. Empty in a direct envelope.
+    pub verifier: String,
+    pub iv: String,
+    /// The sealed master key. Empty in a direct envelope.
+    pub wrapped_key: String,
+}
+
+impl KeyEnvelope {
+    /// Describes a vault from before envelopes: its salt and password policy, the
+    /// LEGACY settings, and the derived key used as the master key.
+    pub fn legacy(salt: &[u8], policy: PasswordPolicy) -> KeyEnvelope {
+        KeyEnvelope {
+            version: FORMAT_VERSION,
+            kdf: KdfSettings::LEGACY,
+            password_policy: policy,
+            salt: B64.encode(salt),
+            verifier: String::new(),
+            iv: String::new(),
+            wrapped_key: String::new(),
+        }
+    }
+
+    /// Wraps `master_key` under `password` with a fresh salt.
+    pub fn wrap(master_key: &[u8; 32], password: &str, policy: PasswordPolicy, kdf: KdfSettings) -> Result<KeyEnvelope, String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::wrap_with_salt(master_key, password, &salt, policy, kdf)
+    }
+
+    /// 'wrap' with a salt the caller picked, e.g. one the server hands out. At
+    /// least MIN_SALT_LEN bytes.
+    pub fn wrap_with_salt(
+        master_key: &[u8; 32],
+        password: &str,
+        salt: &[u8],
+        policy: PasswordPolicy,
+        kdf: KdfSettings,
+    ) -> Result<KeyEnvelope, String> {
+        if salt.len() < MIN_SALT_LEN {
+            return Err(format!("KDF error: the salt must be at least {} bytes", MIN_SALT_LEN));
+        }
+        let derived = kdf.derive(normalize_password(password, policy).as_bytes(), salt)?;
+
+        let mut wrap_key = derive_subkey(&derived, WRAP_PURPOSE, b"");
+        let sealed = seal_committed(&wrap_key, master_key, AAD);
+        wrap_key.zeroize();
+        let (iv, data) = sealed?;
+
+        Ok(KeyEnvelope {
+            version: FORMAT_VERSION,
+            kdf,
+            password_policy: policy,
+            salt: B64.encode(salt),
+            verifier: verifier(&derived),
+            iv: B64.encode(iv),
+            wrapped_key: B64.encode(data),
+        })
+    }
+
+    /// e.g. "Argon2id m=65536 KiB, t=3, p=1, password policy 1, wrapped".
+    pub fn label(&self) -> String {
+        let form = if self.is_direct() { "direct" } else { "wrapped" };
+        format!("{}, password policy {}, {}", self.kdf.label(), self.password_policy.version(), form)
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.wrapped_key.is_empty()
+    }
+
+    /// True when unlocking should also replace this envelope: it is direct, its
+    /// settings are outdated, the password policy isn't the current one, or it
+    /// predates key commitment.
+    pub fn needs_upgrade(&self) -> bool {
+        self.is_direct()
+            || self.kdf.is_outdated()
+            || self.password_policy != PasswordPolicy::CURRENT
+            || self.version != FORMAT_VERSION
+    }
+
+    /// Recovers the master key. For a direct envelope any password "works": only
+    /// decrypting the vault proves it was right.
+    pub fn unlock(&self, password: &str) -> Result<[u8; 32], String> {
+        if ![LEGACY_VERSION, FORMAT_VERSION].contains(&self.version) {
+            return Err(format!("Unsupported key envelope version: {}", self.version));
+        }
+        let salt = B64.decode(&self.salt).map_err(|e| format!("Salt decode error: {}", e))?;
+        let derived = self.kdf.derive(normalize_password(password, self.password_policy).as_bytes(), &salt)?;
+        if self.is_direct() {
+            return Ok(*derived);
+        }
+
+        // Decode first: these depend only on the stored envelope, not the password.
+        let iv = B64.decode(&self.iv).map_err(|e| format!("IV decode error: {}", e))?;
+        let data = B64.decode(&self.wrapped_key).map_err(|e| format!("Key envelope decode error: {}", e))?;
+
+        let expected = verifier(&derived);
+        let verified: bool = expected.as_bytes().ct_eq(self.verifier.as_bytes()).into();
+
+        // Open the wrapped key even when the verifier already failed, so both paths do the same work.
+        let mut wrap_key = derive_subkey(&derived, WRAP_PURPOSE, b"");
+        let opened = if self.version == LEGACY_VERSION { open(&wrap_key, &iv, &data, AAD) } else { open_committed(&wrap_key, &iv, &data, AAD) };
+        let opened = opened.ok().and_then(|mut plain| {
+            let master_key = <[u8; 32]>::try_from(plain.as_slice()).ok();
+            plain.zeroize();
+            master_key
+        });
+        wrap_key.zeroize();
+
+        match opened {
+            Some(master_key) if verified => Ok(master_key),
+            Some(mut master_key) => {
+                master_key.zeroize();
+                Err(UNLOCK_FAILED.to_string())
+            }
+            None => Err(UNLOCK_FAILED.to_string()),
+        }
+    }
+
+    /// Transitional unlock: the master key, plus a replacement envelope (CURRENT
+    /// settings and policy, fresh salt) when 'needs_upgrade'. Save the replacement
+    /// only once the master key has opened the vault.
+    pub fn unlock_upgrading(&self, password: &str) -> Result<([u8; 32], Option<KeyEnvelope>), String> {
+        let mut master_key = self.unlock(password)?;
+        if !self.needs_upgrade() {
+            return Ok((master_key, None));
+        }
+        match KeyEnvelope::wrap(&master_key, password, PasswordPolicy::CURRENT, KdfSettings::CURRENT) {
+            Ok(upgraded) => Ok((master_key, Some(upgraded))),
+            Err(e) => {
+                master_key.zeroize();
+                Err(e)
+            }
+        }
+    }
+}
+
+fn verifier(derived: &[u8; 32]) -> String {
+    derive_subkey(derived, VERIFIER_PURPOSE, b"")[..VERIFIER_LEN].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Cheap settings, so tests don't pay for CURRENT.
+    const FAST: KdfSettings = KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    #[test]
+    fn test_legacy_settings_match_the_engine() {
+        let salt = b"securepass-salt!";
+        let envelope = KeyEnvelope::legacy(salt, PasswordPolicy::Raw);
+        let engine = crate::Engine::new("correct horse", salt).unwrap();
+        assert_eq!(envelope.unlock("correct horse").unwrap(), engine.master_key);
+        assert!(envelope.needs_upgrade() && KdfSettings::LEGACY.is_outdated() && !KdfSettings::CURRENT.is_outdated());
+    }
+
+    #[test]
+    fn test_wrapped_envelope_roundtrip() {
+        let master = [6u8; 32];
+        let envelope = KeyEnvelope::wrap(&master, "pässword", PasswordPolicy::Nfc, FAST).unwrap();
+        assert_eq!(envelope.unlock("pa\u{308}ssword").unwrap(), master);
+        assert!(envelope.unlock("password").unwrap_err().contains("wrong password"));
+        assert!(envelope.needs_upgrade());
+
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(json.contains(r#""kdf":{"memoryKib":64,"iterations":1,"parallelism":1},"passwordPolicy":1"#));
+        let mut tampered: KeyEnvelope = serde_json::from_str(&json).unwrap();
+        tampered.kdf.memory_kib = u32::MAX;
+        assert!(tampered.unlock("pässword").unwrap_err().contains("out of range"));
+    }
+
+    #[test]
+    fn test_every_unlock_failure_reads_the_same() {
+        let envelope = KeyEnvelope::wrap(&[6u8; 32], "right", PasswordPolicy::Nfc, FAST).unwrap();
+        let mut bad_verifier = envelope.clone();
+        bad_verifier.verifier = "00".repeat(VERIFIER_LEN);
+        let mut bad_tag = envelope.clone();
+        let mut data = B64.decode(&bad_tag.wrapped_key).unwrap();
+        data[0] ^= 1;
+        bad_tag.wrapped_key = B64.encode(data);
+
+        assert_eq!(envelope.unlock("wrong").unwrap_err(), UNLOCK_FAILED);
+        assert_eq!(bad_verifier.unlock("right").unwrap_err(), UNLOCK_FAILED);
+        assert_eq!(bad_tag.unlock("right").unwrap_err(), UNLOCK_FAILED);
+
+        // Relabelled as version 1, the committed key doesn't open as plain AES-GCM.
+        let relabelled = KeyEnvelope { version: LEGACY_VERSION, ..envelope };
+        assert_eq!(relabelled.unlock("right").unwrap_err(), UNLOCK_FAILED);
+    }
+
+    #[test]
+    fn test_version_1_envelopes_open_and_upgrade() {
+        let (master, salt) = ([6u8; 32], [5u8; SALT_LEN]);
+        let derived = FAST.derive(b"right", &salt).unwrap();
+        let (iv, data) = crate::keys::seal(&derive_subkey(&derived, WRAP_PURPOSE, b""), &master, AAD).unwrap();
+        let legacy = KeyEnvelope {
+            version: LEGACY_VERSION,
+            kdf: FAST,
+            password_policy: PasswordPolicy::Raw,
+            salt: B64.encode(salt),
+            verifier: verifier(&derived),
+            iv: B64.encode(iv),
+            wrapped_key: B64.encode(data),
+        };
+        assert_eq!(legacy.unlock("right").unwrap(), master);
+        assert!(legacy.needs_upgrade());
+    }
+
+    #[test]
+    fn test_unlock_upgrading_rewraps_the_same_key() {
+        let envelope = KeyEnvelope::legacy(&[4u8; 16], PasswordPolicy::Raw);
+        let (master, upgraded) = envelope.unlock_upgrading("hunter2").unwrap();
+        let upgraded = upgraded.unwrap();
+
+        assert_eq!((upgraded.kdf, upgraded.password_policy), (KdfSettings::CURRENT, PasswordPolicy::CURRENT));
+        assert!(!upgraded.needs_upgrade());
+        assert_ne!(upgraded.salt, envelope.salt);
+        assert_eq!(upgraded.unlock_upgrading("hunter2").unwrap(), (master, None));
+    }
+}
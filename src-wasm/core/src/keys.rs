@@ -0,0 +1,280 @@
+// --- Key Derivation & Sealing Helpers ---
+// Features that need their own key never use the master key directly. They
+// derive a purpose-bound subkey with HKDF-SHA256, so a bug or leak in one
+// feature can't be turned against another.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::Cell;
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use hkdf::{
+    hmac::{Hmac, Mac},
+    Hkdf,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::gcm_siv;
+
+/// Derives a 32-byte subkey for `purpose`, optionally scoped further by `context`
+/// (e.g. an attachment ID, so every attachment gets a different key).
+pub fn derive_subkey(master_key: &[u8; 32], purpose: &str, context: &[u8]) -> [u8; 32] {
+    let mut info = Vec::with_capacity(purpose.len() + 1 + context.len());
+    info.extend_from_slice(purpose.as_bytes());
+    info.push(0); // separator, so ("ab", "c") and ("a", "bc") can't collide
+    info.extend_from_slice(context);
+
+    let mut key = [0u8; 32];
+    // A 32-byte output is always within HKDF-SHA256's limit, so expand cannot fail.
+    Hkdf::<sha2::Sha256>::new(None, master_key)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// AES-256-GCM with a fresh random nonce. Returns (nonce, ciphertext || tag).
+pub fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<([u8; 12], Vec<u8>), String> {
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let sealed = Aes256Gcm::new(key.into())
+        .encrypt(&Nonce::from(nonce), Payload { msg: plaintext, aad })
+        .map_err(|e| format!("Encryption error: {}", e))?;
+    Ok((nonce, sealed))
+}
+
+/// Reverses `seal`. Fails if the key, nonce, ciphertext or AAD don't match.
+pub fn open(key: &[u8; 32], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = crate::validate::nonce(nonce)?;
+    Aes256Gcm::new(key.into())
+        .decrypt(nonce, Payload { msg: sealed, aad })
+        .map_err(|e| format!("Decryption error: {}", e))
+}
+
+/// AES-256-GCM with a nonce from a 'NonceSequence'. Returns ciphertext || tag.
+pub fn seal_with_nonce(key: &[u8; 32], nonce: &[u8; 12], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    Aes256Gcm::new(key.into())
+        .encrypt(&Nonce::from(*nonce), Payload { msg: plaintext, aad })
+        .map_err(|e| format!("Encryption error: {}", e))
+}
+
+// --- Key Commitment ---
+// AES-GCM doesn't commit to its key: whoever picks the ciphertext can make one
+// that decrypts validly under two different keys. A decoy slot could then open
+// under both the real and the decoy password, or one share bundle show a
+// different entry to two recipients. A committed envelope puts a marker and
+// HMAC-SHA256(key, context || nonce) in front of the ciphertext:
+//   "spc1" | commitment (32 bytes) | ciphertext || tag
+// and 'open_committed' checks the commitment before decrypting, refusing an
+// envelope without one. Formats that were plain AES-GCM before carry a version:
+// only their legacy version is read with 'open', so cutting the commitment off
+// a new envelope doesn't turn it back into an old one.
+
+/// The plaintext prefix of every committed envelope.
+pub const COMMITTED_MARKER: &[u8; 4] = b"spc1";
+const COMMITMENT_CONTEXT: &[u8] = b"securepass-key-commitment-v1";
+/// What a committed envelope adds to plain AES-GCM: the marker and the commitment.
+pub const COMMITMENT_OVERHEAD: usize = COMMITTED_MARKER.len() + 32;
+
+/// Commits to `key` for one nonce.
+pub fn commitment(key: &[u8; 32], nonce: &[u8; 12]) -> [u8; 32] {
+    let mut mac = <Hmac<sha2::Sha256> as Mac>::new_from_slice(key).expect("HMAC takes a key of any length");
+    mac.update(COMMITMENT_CONTEXT);
+    mac.update(nonce);
+    mac.finalize().into_bytes().into()
+}
+
+fn commit(key: &[u8; 32], nonce: &[u8; 12], sealed: Vec<u8>) -> Vec<u8> {
+    [&COMMITTED_MARKER[..], &commitment(key, nonce), &sealed].concat()
+}
+
+/// The ciphertext of a committed envelope, if it is committed to `key`.
+fn committed_ciphertext<'a>(key: &[u8; 32], nonce: &[u8], sealed: &'a [u8]) -> Result<&'a [u8], String> {
+    let nonce: &[u8; 12] = crate::validate::nonce(nonce)?.as_ref();
+    if sealed.len() < COMMITMENT_OVERHEAD || !sealed.starts_with(COMMITTED_MARKER) {
+        return Err("Decryption error: the envelope is not committed to a key".to_string());
+    }
+    let (committed, ciphertext) = sealed[COMMITTED_MARKER.len()..].split_at(32);
+    if !bool::from(committed.ct_eq(&commitment(key, nonce))) {
+        return Err("Decryption error: the envelope is not committed to this key".to_string());
+    }
+    Ok(ciphertext)
+}
+
+/// 'seal_with_nonce', committed to the key. Returns marker || commitment || ciphertext || tag.
+pub fn seal_committed_with_nonce(key: &[u8; 32], nonce: &[u8; 12], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    Ok(commit(key, nonce, seal_with_nonce(key, nonce, plaintext, aad)?))
+}
+
+/// 'seal', committed to the key.
+pub fn seal_committed(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<([u8; 12], Vec<u8>), String> {
+    seal_committed_with(Cipher::Aes256Gcm, key, plaintext, aad)
+}
+
+/// Opens a committed envelope. One without a commitment, or committed to another
+/// key, is refused before any decryption.
+pub fn open_committed(key: &[u8; 32], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    open_committed_with(Cipher::Aes256Gcm, key, nonce, sealed, aad)
+}
+
+// --- Nonce Sequence ---
+
+/// AES-GCM nonces for one key that can't repeat while the sequence lives: a
+/// random 32-bit prefix drawn when it starts, then a 64-bit counter. Two
+/// sequences over the same key (two sessions of one vault) share a prefix with
+/// probability 2^-32, rather than every random nonce ever drawn for the key
+/// counting towards a birthday bound.
+pub struct NonceSequence {
+    prefix: [u8; 4],
+    counter: Cell<u64>,
+}
+
+impl Default for NonceSequence {
+    fn default() -> Self {
+        let mut prefix = [0u8; 4];
+        OsRng.fill_bytes(&mut prefix);
+        NonceSequence { prefix, counter: Cell::new(0) }
+    }
+}
+
+impl NonceSequence {
+    /// The next nonce. Fails once the counter runs out, rather than wrap around.
+    pub fn next(&self) -> Result<[u8; 12], String> {
+        let counter = self.counter.get();
+        self.counter.set(counter.checked_add(1).ok_or("Nonce error: the key's nonces are used up; unlock again")?);
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&self.prefix);
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
+    }
+
+    /// True if `iv` is in this sequence's range (used or not).
+    pub fn covers(&self, iv: &[u8]) -> bool {
+        iv.len() == 12 && iv[..4] == self.prefix
+    }
+}
+
+/// The AEAD a sealed record was written with. Records that don't say are AES-256-GCM.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cipher {
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    /// For records whose nonce can't be trusted to be fresh; see 'gcm_siv'.
+    #[serde(rename = "aes-256-gcm-siv")]
+    Aes256GcmSiv,
+}
+
+impl Cipher {
+    pub fn is_default(&self) -> bool {
+        *self == Cipher::default()
+    }
+}
+
+/// 'seal' with the given cipher.
+pub fn seal_with(cipher: Cipher, key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<([u8; 12], Vec<u8>), String> {
+    match cipher {
+        Cipher::Aes256Gcm => seal(key, plaintext, aad),
+        Cipher::Aes256GcmSiv => {
+            let mut nonce = [0u8; 12];
+            OsRng.fill_bytes(&mut nonce);
+            Ok((nonce, gcm_siv::encrypt(key, &nonce, plaintext, aad)?))
+        }
+    }
+}
+
+/// 'seal_with', committed to the key.
+pub fn seal_committed_with(cipher: Cipher, key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<([u8; 12], Vec<u8>), String> {
+    let (nonce, sealed) = seal_with(cipher, key, plaintext, aad)?;
+    Ok((nonce, commit(key, &nonce, sealed)))
+}
+
+/// 'open_committed' with the given cipher.
+pub fn open_committed_with(cipher: Cipher, key: &[u8; 32], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    open_with(cipher, key, nonce, committed_ciphertext(key, nonce, sealed)?, aad)
+}
+
+/// 'open' with the given cipher.
+pub fn open_with(cipher: Cipher, key: &[u8; 32], nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    match cipher {
+        Cipher::Aes256Gcm => open(key, nonce, sealed, aad),
+        Cipher::Aes256GcmSiv => gcm_siv::decrypt(key, nonce, sealed, aad),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_sequence_never_repeats() {
+        let sequence = NonceSequence::default();
+        let (first, second) = (sequence.next().unwrap(), sequence.next().unwrap());
+        assert_eq!((&first[..4], &first[4..]), (&second[..4], &0u64.to_be_bytes()[..]));
+        assert_eq!(&second[4..], &1u64.to_be_bytes());
+        assert!(sequence.covers(&first) && !sequence.covers(&first[..11]));
+
+        sequence.counter.set(u64::MAX);
+        assert!(sequence.next().is_err());
+        assert!(sequence.next().is_err());
+    }
+
+    #[test]
+    fn test_subkeys_are_separated() {
+        let master = [9u8; 32];
+        let a = derive_subkey(&master, "purpose-a", b"");
+        assert_eq!(a, derive_subkey(&master, "purpose-a", b""));
+        assert_ne!(a, derive_subkey(&master, "purpose-b", b""));
+        assert_ne!(a, derive_subkey(&master, "purpose-a", b"id-1"));
+        assert_ne!(derive_subkey(&master, "ab", b"c"), derive_subkey(&master, "a", b"bc"));
+    }
+
+    #[test]
+    fn test_seal_open_binds_aad() {
+        let key = [1u8; 32];
+        let (nonce, sealed) = seal(&key, b"secret", b"ctx").unwrap();
+        assert_eq!(open(&key, &nonce, &sealed, b"ctx").unwrap(), b"secret");
+        assert!(open(&key, &nonce, &sealed, b"other").is_err());
+        assert!(open(&key, &nonce[..11], &sealed, b"ctx").is_err());
+    }
+
+    #[test]
+    fn test_committed_envelopes_open_under_one_key() {
+        let key = [1u8; 32];
+        let (nonce, sealed) = seal_committed(&key, b"secret", b"ctx").unwrap();
+        assert_eq!(sealed.len(), COMMITMENT_OVERHEAD + 6 + 16);
+        assert_eq!(open_committed(&key, &nonce, &sealed, b"ctx").unwrap(), b"secret");
+        assert!(open_committed(&[2u8; 32], &nonce, &sealed, b"ctx").is_err());
+
+        // The AES-GCM part alone won't do under a key the commitment isn't for.
+        let mut forged = sealed.clone();
+        forged[4..36].copy_from_slice(&commitment(&[2u8; 32], &nonce));
+        assert!(open_committed(&key, &nonce, &forged, b"ctx").is_err());
+
+        // A plain envelope, or a committed one with its commitment cut off, is refused.
+        let (nonce, plain) = seal(&key, b"secret", b"ctx").unwrap();
+        assert!(open_committed(&key, &nonce, &plain, b"ctx").unwrap_err().contains("not committed"));
+        let (nonce, sealed) = seal_committed(&key, b"secret", b"ctx").unwrap();
+        assert!(open_committed(&key, &nonce, &sealed[COMMITMENT_OVERHEAD..], b"ctx").is_err());
+        assert!(open_committed(&key, &nonce[..11], &sealed, b"ctx").is_err());
+
+        let (nonce, sealed) = seal_committed_with(Cipher::Aes256GcmSiv, &key, b"secret", b"ctx").unwrap();
+        assert_eq!(open_committed_with(Cipher::Aes256GcmSiv, &key, &nonce, &sealed, b"ctx").unwrap(), b"secret");
+        assert!(open_committed_with(Cipher::Aes256GcmSiv, &[2u8; 32], &nonce, &sealed, b"ctx").is_err());
+    }
+
+    #[test]
+    fn test_ciphers_do_not_open_each_other() {
+        let key = [1u8; 32];
+        let (nonce, sealed) = seal_with(Cipher::Aes256GcmSiv, &key, b"secret", b"ctx").unwrap();
+        assert_eq!(open_with(Cipher::Aes256GcmSiv, &key, &nonce, &sealed, b"ctx").unwrap(), b"secret");
+        assert!(open_with(Cipher::Aes256Gcm, &key, &nonce, &sealed, b"ctx").is_err());
+    }
+}
@@ -0,0 +1,423 @@
+// --- LastPass CSV Import ---
+// LastPass exports one CSV (url, username, password, totp, extra, name,
+// grouping, fav) for logins and secure notes alike, and a generic CSV mapping
+// loses most of it:
+//   - A secure note has the URL "http://sn" and its text in 'extra'. A typed
+//     note (card, address, Wi-Fi, ...) starts with "NoteType:<type>" followed by
+//     "Key:Value" lines; everything after "Notes:" is the free text.
+//   - An empty folder is a row with the URL "http://group" and no name.
+//   - 'grouping' is the folder path with '\' between levels; shared folders
+//     start with "Shared-". "(none)" means no folder.
+//   - A blank URL is exported as "http://".
+//   - Address notes store phone numbers as JSON ({"num": ..., "cc3l": ...}).
+//   - Dates are "January,15,2020"; card expiry dates are "January,2025".
+// Typed notes become entries of the matching template (see 'templates') with
+// the values in its fields; keys the template has no field for are kept as
+// extra custom fields. Note types without a template become notes with fields.
+//
+// LastPass's old XML export is gone from its current apps; only the CSV is read.
+
+use std::collections::BTreeMap;
+
+use zeroize::Zeroize;
+
+use crate::import::{Imported, Importer};
+use crate::templates;
+use crate::vault::{new_entry_id, CustomField, FieldKind, VaultEntry};
+use crate::{card, identity, license, wifi};
+
+const SECURE_NOTE_URL: &str = "http://sn";
+const FOLDER_URL: &str = "http://group";
+const NO_FOLDER: &str = "(none)";
+/// Lines LastPass adds to every typed note that aren't data.
+const SKIPPED_KEYS: [&str; 1] = ["Language"];
+const REQUIRED_COLUMNS: [&str; 4] = ["url", "username", "password", "name"];
+const MONTHS: [&str; 12] =
+    ["january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december"];
+
+/// Where a value of a typed note goes.
+#[derive(Clone, Copy)]
+enum Target {
+    Username,
+    Password,
+    Url,
+    Field(&'static str),
+}
+
+/// A LastPass note type, the template its entries are made from, and where its keys go.
+struct NoteMapping {
+    note_type: &'static str,
+    template: &'static str,
+    keys: &'static [(&'static str, Target)],
+}
+
+const NOTE_MAPPINGS: [NoteMapping; 7] = [
+    NoteMapping {
+        note_type: "Credit Card",
+        template: "credit_card",
+        keys: &[
+            ("Name on Card", Target::Field(card::CARDHOLDER_FIELD)),
+            ("Number", Target::Field(card::NUMBER_FIELD)),
+            ("Security Code", Target::Field(card::CVV_FIELD)),
+            ("Expiration Date", Target::Field(card::EXPIRY_FIELD)),
+        ],
+    },
+    NoteMapping {
+        note_type: "Bank Account",
+        template: "bank_account",
+        keys: &[
+            ("Bank Name", Target::Field("Bank name")),
+            ("Account Number", Target::Field("Account number")),
+            ("Routing Number", Target::Field("Routing number")),
+            ("IBAN Number", Target::Field("IBAN")),
+            ("SWIFT Code", Target::Field("SWIFT/BIC")),
+            ("Pin", Target::Field("PIN")),
+            ("Branch Phone", Target::Field("Phone")),
+        ],
+    },
+    NoteMapping {
+        note_type: "Address",
+        template: "identity",
+        keys: &[
+            ("First Name", Target::Field(identity::GIVEN_NAME_FIELD)),
+            ("Last Name", Target::Field(identity::FAMILY_NAME_FIELD)),
+            ("Email Address", Target::Field(identity::EMAIL_FIELD)),
+            ("Phone", Target::Field(identity::PHONE_FIELD)),
+            ("Address 1", Target::Field(identity::STREET_FIELD)),
+            ("Address 2", Target::Field(identity::STREET_FIELD)),
+            ("Address 3", Target::Field(identity::STREET_FIELD)),
+            ("City / Town", Target::Field(identity::CITY_FIELD)),
+            ("State", Target::Field(identity::REGION_FIELD)),
+            ("Zip / Postal Code", Target::Field(identity::POSTAL_CODE_FIELD)),
+            ("Country", Target::Field(identity::COUNTRY_FIELD)),
+            ("Username", Target::Username),
+        ],
+    },
+    NoteMapping {
+        note_type: "Wi-Fi Password",
+        template: "wifi_router",
+        keys: &[
+            ("SSID", Target::Field(wifi::SSID_FIELD)),
+            ("Password", Target::Field(wifi::PASSWORD_FIELD)),
+            ("Authentication", Target::Field(wifi::SECURITY_FIELD)),
+        ],
+    },
+    NoteMapping {
+        note_type: "Email Account",
+        template: "email_account",
+        keys: &[
+            ("Username", Target::Username),
+            ("Password", Target::Password),
+            ("Server", Target::Field("IMAP server")),
+            ("Port", Target::Field("IMAP port")),
+            ("SMTP Server", Target::Field("SMTP server")),
+            ("SMTP Port", Target::Field("SMTP port")),
+        ],
+    },
+    NoteMapping {
+        note_type: "Software License",
+        template: "software_license",
+        keys: &[
+            ("License Key", Target::Field(license::KEY_FIELD)),
+            ("Licensee", Target::Field(license::LICENSED_TO_FIELD)),
+            ("Version", Target::Field(license::VERSION_FIELD)),
+            ("Support Email", Target::Field(license::EMAIL_FIELD)),
+            ("Purchase Date", Target::Field(license::PURCHASE_DATE_FIELD)),
+            ("Order Number", Target::Field(license::ORDER_FIELD)),
+            ("Publisher", Target::Field(license::STORE_FIELD)),
+            ("Price", Target::Field(license::PRICE_FIELD)),
+            ("Website", Target::Url),
+        ],
+    },
+    NoteMapping {
+        note_type: "Passport",
+        template: "passport",
+        keys: &[
+            ("Name", Target::Field("Full name")),
+            ("Number", Target::Field("Passport number")),
+            ("Nationality", Target::Field("Nationality")),
+            ("Date of Birth", Target::Field("Date of birth")),
+            ("Issued Date", Target::Field("Issued on")),
+            ("Expiration Date", Target::Field("Expires on")),
+            ("Issuing Authority", Target::Field("Issuing authority")),
+        ],
+    },
+];
+
+/// The LastPass CSV importer, with the folder renames to apply.
+#[derive(Debug, Default)]
+pub struct LastPass {
+    pub folders: FolderMap,
+}
+
+impl Importer for LastPass {
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String> {
+        parse_csv(data, &self.folders, now)
+    }
+}
+
+/// Renames LastPass folders: each key is a LastPass path ("Work\Email" or
+/// "Work/Email"), its value the folder to use instead. A key also renames the
+/// folders below it. Unmapped paths keep their names, with '/' between levels.
+#[derive(Debug, Default)]
+pub struct FolderMap(BTreeMap<String, String>);
+
+impl FolderMap {
+    /// From a JSON object of path to folder; an empty string means no renames.
+    pub fn from_json(json: &str) -> Result<FolderMap, String> {
+        if json.trim().is_empty() {
+            return Ok(FolderMap::default());
+        }
+        let map: BTreeMap<String, String> =
+            serde_json::from_str(json).map_err(|e| format!("LastPass import error: bad folder map: {}", e))?;
+        Ok(FolderMap(map.into_iter().map(|(from, to)| (normalize_path(&from), to)).collect()))
+    }
+
+    /// The folder for a 'grouping' value; None for no folder.
+    pub fn folder(&self, grouping: &str) -> Option<String> {
+        let path = normalize_path(grouping);
+        if path.is_empty() || path == NO_FOLDER {
+            return None;
+        }
+        // The longest mapped prefix wins, so "Work/Email" can go elsewhere than "Work".
+        let mapped = self
+            .0
+            .iter()
+            .filter(|(from, _)| path == **from || path.starts_with(&format!("{}/", from)))
+            .max_by_key(|(from, _)| from.len());
+        Some(match mapped {
+            Some((from, to)) => format!("{}{}", to, &path[from.len()..]),
+            None => path,
+        })
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.split(['\\', '/']).map(str::trim).filter(|part| !part.is_empty()).collect::<Vec<_>>().join("/")
+}
+
+/// Reads a LastPass CSV export.
+pub fn parse_csv(csv: &str, folders: &FolderMap, now: u64) -> Result<Imported, String> {
+    let mut rows = crate::csv::rows(csv)?.into_iter();
+    let header = rows.next().ok_or("LastPass import error: the file is empty")?;
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    for name in REQUIRED_COLUMNS {
+        column(name).ok_or_else(|| format!("LastPass import error: no '{}' column; is this a LastPass export?", name))?;
+    }
+    let columns: Vec<Option<usize>> =
+        ["url", "username", "password", "totp", "extra", "name", "grouping", "fav"].iter().map(|name| column(name)).collect();
+
+    let mut import = Imported::new("lastpass");
+    for mut row in rows {
+        if row.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let mut take = |i: usize| columns[i].and_then(|c| row.get_mut(c)).map(std::mem::take).unwrap_or_default();
+        let (url, username, password, totp, mut extra, name, grouping, fav) =
+            (take(0), take(1), take(2), take(3), take(4), take(5), take(6), take(7));
+        let folder = folders.folder(&grouping);
+        if let Some(folder) = &folder {
+            import.add_folder(folder);
+        }
+        if url == FOLDER_URL && name.is_empty() {
+            continue;
+        }
+
+        let mut entry = if url == SECURE_NOTE_URL {
+            let entry = secure_note(&extra, now);
+            extra.zeroize();
+            entry
+        } else {
+            VaultEntry {
+                id: new_entry_id(),
+                url: if url == "http://" { String::new() } else { url },
+                username,
+                password,
+                notes: extra,
+                totp_secret: (!totp.is_empty()).then_some(totp),
+                last_modified: now,
+                ..Default::default()
+            }
+        };
+        entry.title = name;
+        entry.category = folder.unwrap_or_default();
+        entry.favorite = fav == "1";
+        import.entries.push(entry);
+    }
+    Ok(import)
+}
+
+/// A secure note's 'extra': typed ("NoteType:...") or free text.
+fn secure_note(extra: &str, now: u64) -> VaultEntry {
+    let Some(rest) = extra.strip_prefix("NoteType:") else {
+        return VaultEntry {
+            id: new_entry_id(),
+            entry_type: "note".to_string(),
+            notes: extra.to_string(),
+            last_modified: now,
+            ..Default::default()
+        };
+    };
+    let (note_type, mut body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let note_type = note_type.trim();
+    let mapping = NOTE_MAPPINGS.iter().find(|m| m.note_type.eq_ignore_ascii_case(note_type));
+    let mut entry = match mapping.and_then(|m| templates::find(m.template).ok()) {
+        Some(template) => template.instantiate("", now),
+        None => VaultEntry { id: new_entry_id(), entry_type: "note".to_string(), last_modified: now, ..Default::default() },
+    };
+    // Template fields hold defaults (an IMAP port) until the note sets them; later
+    // values for the same field are appended (the address lines).
+    let mut filled: Vec<&str> = Vec::new();
+    if mapping.is_none() && !note_type.is_empty() {
+        add_field(&mut entry, &mut filled, "Type", FieldKind::Text, note_type.to_string());
+    }
+
+    while !body.is_empty() {
+        // "Notes:" is always last and may span lines.
+        if let Some(notes) = body.strip_prefix("Notes:") {
+            entry.notes = notes.to_string();
+            break;
+        }
+        let (line, rest) = body.split_once('\n').unwrap_or((body, ""));
+        body = rest;
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim(), value.trim_end_matches('\r'));
+        // An unset date is exported as ",".
+        if value.trim_matches([',', ' ']).is_empty() || SKIPPED_KEYS.contains(&key) {
+            continue;
+        }
+        let target = mapping.and_then(|m| m.keys.iter().find(|(k, _)| *k == key)).map(|(_, target)| *target);
+        match target {
+            Some(Target::Username) => entry.username = value.to_string(),
+            Some(Target::Password) => entry.password = value.to_string(),
+            Some(Target::Url) => entry.url = value.to_string(),
+            Some(Target::Field(name)) => {
+                let kind = entry.fields.iter().find(|f| f.name == name).map_or(FieldKind::Text, |f| f.kind);
+                let value = convert(name, kind, value);
+                add_field(&mut entry, &mut filled, name, kind, value);
+            }
+            None if mapping.is_none() && key == "Username" => entry.username = value.to_string(),
+            None if mapping.is_none() && key == "Password" => entry.password = value.to_string(),
+            None => add_field(&mut entry, &mut filled, key, guess_kind(key), value.to_string()),
+        }
+    }
+    entry
+}
+
+/// Sets a field the first time, appends to it after that.
+fn add_field<'a>(entry: &mut VaultEntry, filled: &mut Vec<&'a str>, name: &'a str, kind: FieldKind, value: String) {
+    match entry.fields.iter_mut().find(|f| f.name == name) {
+        Some(field) if filled.contains(&name) => {
+            field.value.push_str(", ");
+            field.value.push_str(&value);
+        }
+        Some(field) => field.value = value,
+        None => entry.fields.push(CustomField { name: name.to_string(), kind, value }),
+    }
+    filled.push(name);
+}
+
+/// LastPass's formats to the ones the templates' fields use.
+fn convert(field: &str, kind: FieldKind, value: &str) -> String {
+    if field == card::EXPIRY_FIELD {
+        if let Some((month, year)) = value.split_once(',').and_then(|(m, y)| Some((month_number(m)?, y.trim()))) {
+            return format!("{:02}/{}", month, &year[year.len().saturating_sub(2)..]);
+        }
+    }
+    if kind == FieldKind::Date {
+        let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+        if let [month, day, year] = parts[..] {
+            if let (Some(month), Ok(day), Ok(year)) = (month_number(month), day.parse::<u32>(), year.parse::<u32>()) {
+                return format!("{:04}-{:02}-{:02}", year, month, day);
+            }
+        }
+    }
+    if kind == FieldKind::Phone && value.starts_with('{') {
+        if let Ok(phone) = serde_json::from_str::<serde_json::Value>(value) {
+            if let Some(number) = phone["num"].as_str() {
+                return number.to_string();
+            }
+        }
+    }
+    value.to_string()
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name.trim())).map(|i| i as u32 + 1)
+}
+
+/// Masks the fields of note types without a template that look secret.
+fn guess_kind(key: &str) -> FieldKind {
+    let key = key.to_ascii_lowercase();
+    if ["password", "pin", "key", "code", "number", "secret"].iter().any(|word| key.contains(word)) {
+        FieldKind::Hidden
+    } else {
+        FieldKind::Text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "\u{feff}url,username,password,totp,extra,name,grouping,fav\r\n\
+        https://github.com,octo,\"p,w\"\"1\",JBSWY3DPEHPK3PXP,\"recovery codes\nline 2\",GitHub,Work\\Dev,1\r\n\
+        http://,,pw,,,No URL,(none),0\r\n\
+        http://sn,,,,just text,Plain note,,0\r\n\
+        http://sn,,,,\"NoteType:Credit Card\nLanguage:en-US\nName on Card:Jo Doe\nType:Visa\nNumber:4242424242424242\nSecurity Code:123\nStart Date:,\nExpiration Date:March,2027\nNotes:first\nsecond\",Visa,Finance,0\r\n\
+        http://sn,,,,\"NoteType:Address\nFirst Name:Jo\nAddress 1:1 Main St\nAddress 2:Apt 2\nPhone:{\"\"num\"\":\"\"5551234\"\",\"\"ext\"\":\"\"\"\",\"\"cc3l\"\":\"\"USA\"\"}\nNotes:\",Home,,0\r\n\
+        http://sn,,,,\"NoteType:Server\nHostname:db1\nUsername:root\nPassword:toor\nNotes:\",DB,,0\r\n\
+        http://group,,,,,,Archive\\Old,0\r\n";
+
+    #[test]
+    fn test_logins_and_folders() {
+        let import = parse_csv(EXPORT, &FolderMap::default(), 9).unwrap();
+        assert_eq!(import.entries.len(), 6);
+        assert_eq!(import.folders, vec!["Work/Dev", "Finance", "Archive/Old"]);
+
+        let github = &import.entries[0];
+        assert_eq!((github.title.as_str(), github.url.as_str(), github.password.as_str()), ("GitHub", "https://github.com", "p,w\"1"));
+        assert_eq!((github.category.as_str(), github.favorite, github.notes.as_str()), ("Work/Dev", true, "recovery codes\nline 2"));
+        assert_eq!(github.totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!((import.entries[1].url.as_str(), import.entries[1].category.as_str()), ("", ""));
+        assert_eq!((import.entries[2].kind(), import.entries[2].notes.as_str()), ("note", "just text"));
+    }
+
+    #[test]
+    fn test_typed_notes_become_template_entries() {
+        let import = parse_csv(EXPORT, &FolderMap::default(), 9).unwrap();
+        let field = |entry: &VaultEntry, name: &str| entry.fields.iter().find(|f| f.name == name).map(|f| f.value.clone());
+
+        let visa = &import.entries[3];
+        assert_eq!((visa.kind(), visa.notes.as_str()), ("card", "first\nsecond"));
+        assert_eq!(field(visa, card::NUMBER_FIELD).as_deref(), Some("4242424242424242"));
+        assert_eq!(field(visa, card::EXPIRY_FIELD).as_deref(), Some("03/27"));
+        assert_eq!(field(visa, "Type").as_deref(), Some("Visa"));
+
+        let home = &import.entries[4];
+        assert_eq!(home.kind(), "identity");
+        assert_eq!(field(home, identity::STREET_FIELD).as_deref(), Some("1 Main St, Apt 2"));
+        assert_eq!(field(home, identity::PHONE_FIELD).as_deref(), Some("5551234"));
+
+        let server = &import.entries[5];
+        assert_eq!((server.kind(), server.username.as_str(), server.password.as_str()), ("note", "root", "toor"));
+        assert_eq!(field(server, "Type").as_deref(), Some("Server"));
+        assert_eq!(field(server, "Hostname").as_deref(), Some("db1"));
+    }
+
+    #[test]
+    fn test_folder_map_renames_paths_and_below() {
+        let map = FolderMap::from_json(r#"{"Work": "Job", "Work\\Dev": "Code", "Shared-Family": "Family"}"#).unwrap();
+        assert_eq!(map.folder("Work\\Dev\\Tools").as_deref(), Some("Code/Tools"));
+        assert_eq!(map.folder("Work\\Mail").as_deref(), Some("Job/Mail"));
+        assert_eq!(map.folder("Workshop").as_deref(), Some("Workshop"));
+        assert_eq!(map.folder("Shared-Family\\Bills").as_deref(), Some("Family/Bills"));
+        assert_eq!(map.folder("(none)"), None);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(parse_csv("title,login\nx,y", &FolderMap::default(), 0).unwrap_err().contains("'url'"));
+        assert!(parse_csv("url,username,password,name\n\"open", &FolderMap::default(), 0).unwrap_err().contains("never closed"));
+    }
+}
@@ -0,0 +1,155 @@
+// --- SecurePass Core ---
+// The vault engine every SecurePass client shares: key derivation, encryption,
+// generators and vault features, in plain Rust with no wasm-bindgen. The Wasm
+// bridge (src-wasm/src/lib.rs) is a thin wrapper around this crate; a CLI,
+// mobile FFI or desktop app links the same code, so they all read and write
+// vaults byte-for-byte the same way.
+//
+// Errors are human-readable Strings ("X error: ..."), which every front end can
+// show or convert without a shared error type. They never quote a secret; see
+// 'redact'.
+//
+// The vault crypto ('keys', 'gcm_siv', 'kdf' and the password half of 'text'),
+// its input checks ('validate') and the scrubbing allocator ('scrub') are no_std + alloc, so they can run in
+// hardware token firmware or a constrained sandbox; everything else needs the
+// "std" feature (on by default). Code in the no_std modules imports String,
+// ToString, Vec and format! from 'alloc'; `npm run check:no-std` (also run in
+// CI) builds them without "std".
+//
+// Web builds that only need the vault can also leave out "importers"
+// (authenticator, LastPass, Dashlane, Proton Pass, Apple and browser import,
+// authenticator export), "wordlists" (the common-password list),
+// "passphrase-languages" (German, French and Spanish passphrase words), "psl"
+// (the public suffix list), "audit", "openpgp" (the OpenPGP export),
+// "qr-transfer" (device transfer by QR code), "pairing" (LAN pairing codes)
+// and "markdown" (Markdown notes); all are on by default.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// Marks each item as needing the "std" feature.
+macro_rules! with_std {
+    ($($item:item)*) => { $(#[cfg(feature = "std")] $item)* };
+}
+
+// The vault crypto, no_std.
+pub mod gcm_siv;
+pub mod kdf;
+pub mod keys;
+pub mod scrub;
+pub mod text;
+pub mod validate;
+
+with_std! {
+// The engine holds the master key and the decrypted vault.
+mod engine;
+pub use engine::{
+    card_expiry_status, clipboard_code, clipboard_text, detect_card_brand, generate_age_identity, estimate_totp_skew,
+    format_phone_number, format_postal_code, generate_emergency_keypair, normalize_totp_secret, generate_device_keypair,
+    list_entry_templates, mask_card_number, normalize_license_key, open_shared_entry, request_emergency_access,
+    rotate_sharing_keypair, sharing_verify_key, verify_entitlement, verify_sharing_key_rotation, wifi_qr_payload,
+};
+#[cfg(feature = "openpgp")]
+pub use engine::describe_openpgp_key;
+#[cfg(feature = "markdown")]
+pub use engine::render_markdown;
+#[cfg(feature = "importers")]
+pub use engine::parse_google_migration;
+pub use engine::{EncryptedAttachment, Engine};
+pub use generator::PasswordOptions;
+
+// Feature modules, each plain Rust logic. Public so front ends can use the types directly.
+pub mod age;
+#[cfg(feature = "importers")]
+pub mod apple_keychain;
+pub mod attachments;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod audit_log;
+pub mod autofill;
+pub mod autotype;
+pub mod backup_codes;
+pub mod backups;
+pub mod biometric;
+pub mod blobs;
+pub mod blocklist;
+pub mod breach;
+pub mod breached_sites;
+#[cfg(feature = "importers")]
+pub mod browser_import;
+pub mod card;
+pub mod clipboard;
+pub mod clock;
+pub mod commit;
+pub mod dedupe;
+#[cfg(feature = "importers")]
+pub mod dashlane;
+#[cfg(feature = "importers")]
+mod csv;
+pub mod device_key;
+pub mod devices;
+pub mod domains;
+pub mod duress;
+pub mod emergency;
+pub mod entitlement;
+pub mod export_gate;
+pub mod extra_lock;
+pub mod generator;
+#[cfg(feature = "audit")]
+pub mod health;
+#[cfg(feature = "importers")]
+pub mod google_migration;
+pub mod hierarchy;
+pub mod hint;
+pub mod identity;
+pub mod idle;
+#[cfg(feature = "importers")]
+pub mod import;
+pub mod journal;
+pub mod json_export;
+#[cfg(feature = "importers")]
+pub mod lastpass;
+pub mod license;
+pub mod listing;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+pub mod master_policy;
+pub mod memorability;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+pub mod oplog;
+pub mod otp;
+#[cfg(feature = "importers")]
+pub mod otp_export;
+#[cfg(feature = "pairing")]
+pub mod pairing;
+pub mod panic_report;
+pub mod paper;
+pub mod password_change;
+pub mod phishing;
+pub mod pin;
+#[cfg(feature = "importers")]
+pub mod proton_pass;
+#[cfg(feature = "qr-transfer")]
+pub mod qr_transfer;
+pub mod redact;
+pub mod reencrypt;
+pub mod reprompt;
+pub mod rng;
+pub mod search_index;
+pub mod segments;
+pub mod sessions;
+pub mod settings;
+pub mod sharing;
+pub mod stats;
+pub mod storage;
+pub mod strength;
+pub mod templates;
+pub mod throttle;
+pub mod vault;
+pub mod wifi;
+pub mod yubikey;
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test_vectors;
@@ -0,0 +1,220 @@
+// --- Software Licenses ---
+// The "license" entry type (the 'software_license' template): a product key
+// plus what was bought, when and where, kept as typed custom fields so the
+// entry reads the same in every client and exports cleanly.
+//
+// Most product keys are groups of letters and digits ("XXXXX-XXXXX-XXXXX"),
+// typed or pasted with stray spaces, lowercase letters or a dash a word
+// processor turned into an en dash. Such keys are normalized to uppercase
+// groups joined by '-', and a group of the wrong length is reported, since
+// it is almost always a typo. Anything else (a signed license blob, a key
+// with symbols) is kept as entered, trimmed.
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::vault::{CustomField, FieldKind, VaultEntry};
+
+pub const ENTRY_TYPE: &str = "license";
+
+pub const KEY_FIELD: &str = "License key";
+pub const VERSION_FIELD: &str = "Version";
+pub const LICENSED_TO_FIELD: &str = "Licensed to";
+pub const EMAIL_FIELD: &str = "Registered email";
+pub const PURCHASE_DATE_FIELD: &str = "Purchase date";
+pub const ORDER_FIELD: &str = "Order number";
+pub const STORE_FIELD: &str = "Purchased from";
+pub const PRICE_FIELD: &str = "Price";
+
+/// Separators accepted between groups: hyphen, space, underscore, en and em dash, minus sign.
+const SEPARATORS: [char; 6] = ['-', ' ', '_', '\u{2013}', '\u{2014}', '\u{2212}'];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyFormat {
+    /// Letters and digits in groups of the same length, joined by '-'.
+    Grouped,
+    /// Letters and digits without separators.
+    Plain,
+    /// Anything else, kept as entered.
+    Opaque,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseKey {
+    pub key: String,
+    pub format: KeyFormat,
+    /// Number of groups and characters per group of a 'Grouped' key; 0 otherwise.
+    pub groups: usize,
+    pub group_length: usize,
+}
+
+/// A license entry's key and purchase metadata, each optional.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LicenseDetails {
+    pub key: String,
+    pub version: String,
+    pub licensed_to: String,
+    pub registered_email: String,
+    /// "YYYY-MM-DD".
+    pub purchase_date: String,
+    pub order_number: String,
+    pub purchased_from: String,
+    pub price: String,
+}
+
+/// Normalizes and checks a product key; see the module comment.
+pub fn normalize_key(raw: &str) -> Result<LicenseKey, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err("License error: the key is empty".to_string());
+    }
+    let groups: Vec<&str> = trimmed.split(|c: char| SEPARATORS.contains(&c)).filter(|g| !g.is_empty()).collect();
+    if !groups.iter().all(|g| g.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return Ok(LicenseKey { key: trimmed.to_string(), format: KeyFormat::Opaque, groups: 0, group_length: 0 });
+    }
+    if groups.len() == 1 {
+        return Ok(LicenseKey { key: groups[0].to_ascii_uppercase(), format: KeyFormat::Plain, groups: 0, group_length: 0 });
+    }
+
+    // The most common length is the intended one; ties go to the first group's.
+    let length = groups
+        .iter()
+        .map(|g| g.len())
+        .max_by_key(|&len| (groups.iter().filter(|g| g.len() == len).count(), len == groups[0].len()))
+        .unwrap_or(0);
+    if let Some(i) = groups.iter().position(|g| g.len() != length) {
+        return Err(format!(
+            "License error: group {} has {} characters, the others {}",
+            i + 1,
+            groups[i].len(),
+            length
+        ));
+    }
+    Ok(LicenseKey {
+        key: groups.join("-").to_ascii_uppercase(),
+        format: KeyFormat::Grouped,
+        groups: groups.len(),
+        group_length: length,
+    })
+}
+
+impl LicenseDetails {
+    fn fields(&self) -> [(&'static str, FieldKind, &String); 8] {
+        [
+            (KEY_FIELD, FieldKind::Hidden, &self.key),
+            (VERSION_FIELD, FieldKind::Text, &self.version),
+            (LICENSED_TO_FIELD, FieldKind::Text, &self.licensed_to),
+            (EMAIL_FIELD, FieldKind::Email, &self.registered_email),
+            (PURCHASE_DATE_FIELD, FieldKind::Date, &self.purchase_date),
+            (ORDER_FIELD, FieldKind::Text, &self.order_number),
+            (STORE_FIELD, FieldKind::Text, &self.purchased_from),
+            (PRICE_FIELD, FieldKind::Text, &self.price),
+        ]
+    }
+
+    /// Reads the license fields of `entry`; missing ones are empty.
+    pub fn from_entry(entry: &VaultEntry) -> LicenseDetails {
+        let value = |name: &str| entry.fields.iter().find(|f| f.name == name).map(|f| f.value.clone()).unwrap_or_default();
+        LicenseDetails {
+            key: value(KEY_FIELD),
+            version: value(VERSION_FIELD),
+            licensed_to: value(LICENSED_TO_FIELD),
+            registered_email: value(EMAIL_FIELD),
+            purchase_date: value(PURCHASE_DATE_FIELD),
+            order_number: value(ORDER_FIELD),
+            purchased_from: value(STORE_FIELD),
+            price: value(PRICE_FIELD),
+        }
+    }
+
+    /// Checks the details, normalizes the key and writes them into `entry`'s fields,
+    /// adding any that are missing, and makes it a license entry. Other fields stay.
+    pub fn apply_to(&self, entry: &mut VaultEntry) -> Result<(), String> {
+        let mut details = self.clone();
+        if !details.key.trim().is_empty() {
+            details.key = normalize_key(&details.key)?.key;
+        }
+        let email = details.registered_email.trim();
+        if !email.is_empty() && !email.split_once('@').is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.')) {
+            return Err("License error: the registered email is not an email address".to_string());
+        }
+        if !details.purchase_date.is_empty() && !is_iso_date(&details.purchase_date) {
+            return Err("License error: the purchase date must be YYYY-MM-DD".to_string());
+        }
+
+        for (name, kind, value) in details.fields() {
+            match entry.fields.iter_mut().find(|f| f.name == name) {
+                Some(field) => {
+                    field.value.zeroize();
+                    field.value = value.trim().to_string();
+                    field.kind = kind;
+                }
+                None => entry.fields.push(CustomField { name: name.to_string(), kind, value: value.trim().to_string() }),
+            }
+        }
+        details.key.zeroize();
+        entry.entry_type = ENTRY_TYPE.to_string();
+        Ok(())
+    }
+}
+
+/// "YYYY-MM-DD" naming a real day.
+fn is_iso_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts[..] else { return false };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_key_formats() {
+        let key = normalize_key(" abcde fghij\u{2013}klmno-PQRST_uvwxy ").unwrap();
+        assert_eq!(key.key, "ABCDE-FGHIJ-KLMNO-PQRST-UVWXY");
+        assert_eq!((key.format, key.groups, key.group_length), (KeyFormat::Grouped, 5, 5));
+
+        assert_eq!(normalize_key("abcd1234").unwrap().format, KeyFormat::Plain);
+        let opaque = normalize_key("  eyJhbGciOi.J9+/=  ").unwrap();
+        assert_eq!((opaque.key.as_str(), opaque.format), ("eyJhbGciOi.J9+/=", KeyFormat::Opaque));
+
+        assert_eq!(normalize_key("ABCDE-FGHI-KLMNO").unwrap_err(), "License error: group 2 has 4 characters, the others 5");
+        assert!(normalize_key("   ").is_err());
+    }
+
+    #[test]
+    fn test_details_roundtrip_through_entry_fields() {
+        let mut entry = crate::templates::find("software_license").unwrap().instantiate("Editor", 1);
+        let details = LicenseDetails {
+            key: "aaaa-bbbb-cccc".to_string(),
+            registered_email: "me@example.com".to_string(),
+            purchase_date: "2024-02-29".to_string(),
+            price: "$49".to_string(),
+            ..Default::default()
+        };
+        details.apply_to(&mut entry).unwrap();
+        assert_eq!(entry.kind(), ENTRY_TYPE);
+        assert_eq!(LicenseDetails::from_entry(&entry), LicenseDetails { key: "AAAA-BBBB-CCCC".to_string(), ..details.clone() });
+
+        assert!(LicenseDetails { purchase_date: "2023-02-29".to_string(), ..details.clone() }.apply_to(&mut entry).is_err());
+        assert!(LicenseDetails { registered_email: "me".to_string(), ..details }.apply_to(&mut entry).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+// --- Entry Listing ---
+// Pages through the loaded vault in a chosen order, so a list view with 10,000+
+// entries asks for the rows it shows instead of every decrypted title at once.
+// Sorting works on borrowed entries and only the requested page is copied out,
+// as summaries without credentials. Also searches, ranks entries by use for quick-fill,
+// and keeps favorites in the order the user dragged them into.
+
+use caseless::Caseless;
+use serde::Serialize;
+
+use crate::segments::EntrySummary;
+use crate::text;
+use crate::vault::VaultEntry;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntrySort {
+    /// A to Z, ignoring case.
+    Title,
+    /// Most recently used first; never-used entries last.
+    LastUsed,
+    /// Most recently modified first.
+    Modified,
+}
+
+impl EntrySort {
+    /// "title", "lastUsed" or "modified".
+    pub fn parse(sort: &str) -> Result<EntrySort, String> {
+        match sort {
+            "title" => Ok(EntrySort::Title),
+            "lastUsed" => Ok(EntrySort::LastUsed),
+            "modified" => Ok(EntrySort::Modified),
+            other => Err(format!("Sort error: unknown order '{}' (use title, lastUsed or modified)", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Page {
+    /// Entries in the whole vault, for the scrollbar.
+    pub total: usize,
+    pub offset: usize,
+    pub entries: Vec<EntrySummary>,
+}
+
+/// Rows `offset..offset + limit` of `entries` in `sort` order. Ties fall back to
+/// title and then ID, so paging is stable across calls.
+pub fn page(entries: &[VaultEntry], offset: usize, limit: usize, sort: EntrySort) -> Page {
+    page_of(entries.iter().collect(), offset, limit, sort)
+}
+
+/// Like 'page', over the entries matching `query` in their title, username, URL
+/// or folder. Matching ignores case, accents and full-width forms (see
+/// 'text::search_key'); `total` counts matches.
+pub fn search(entries: &[VaultEntry], query: &str, offset: usize, limit: usize, sort: EntrySort) -> Page {
+    let found = entries
+        .iter()
+        .filter(|e| text::matches(query, [e.title.as_str(), e.username.as_str(), e.url.as_str(), e.category.as_str()]))
+        .collect();
+    page_of(found, offset, limit, sort)
+}
+
+fn page_of(mut order: Vec<&VaultEntry>, offset: usize, limit: usize, sort: EntrySort) -> Page {
+    let compare = |a: &&VaultEntry, b: &&VaultEntry| {
+        let by_title = || fold_case(&a.title).cmp(fold_case(&b.title)).then_with(|| a.id.cmp(&b.id));
+        match sort {
+            EntrySort::Title => by_title(),
+            EntrySort::LastUsed => b.last_used.cmp(&a.last_used).then_with(by_title),
+            EntrySort::Modified => b.last_modified.cmp(&a.last_modified).then_with(by_title),
+        }
+    };
+
+    let total = order.len();
+    let end = offset.saturating_add(limit).min(total);
+    // Only the first `end` rows need to be in order; partition off the rest.
+    if end > 0 && end < order.len() {
+        order.select_nth_unstable_by(end - 1, compare);
+        order.truncate(end);
+    }
+    order.sort_unstable_by(compare);
+
+    Page {
+        total,
+        offset,
+        entries: order.get(offset..end).unwrap_or_default().iter().map(|e| EntrySummary::from(*e)).collect(),
+    }
+}
+
+/// Each use loses half its weight every this many seconds (30 days).
+const USE_HALF_LIFE_SECS: f64 = 30.0 * 24.0 * 3600.0;
+
+/// Up to `limit` entries that have been used, best first. An entry scores its
+/// use count scaled by how long ago it was last used, so a login used daily last
+/// year sinks below one used weekly now. Ties go to the more recent use.
+pub fn frequently_used(entries: &[VaultEntry], limit: usize, now: u64) -> Vec<EntrySummary> {
+    let score = |e: &VaultEntry| {
+        let age = now.saturating_sub(e.last_used) as f64;
+        f64::from(e.use_count) * (-age / USE_HALF_LIFE_SECS).exp2()
+    };
+    let mut ranked: Vec<(f64, &VaultEntry)> = entries.iter().filter(|e| e.use_count > 0).map(|e| (score(e), e)).collect();
+    ranked.sort_by(|(sa, a), (sb, b)| {
+        sb.total_cmp(sa).then_with(|| b.last_used.cmp(&a.last_used)).then_with(|| a.id.cmp(&b.id))
+    });
+    ranked.into_iter().take(limit).map(|(_, e)| EntrySummary::from(e)).collect()
+}
+
+/// The favorites in the user's order ('VaultSettings::favorite_order'). Favorites
+/// the order doesn't list yet follow it, A to Z; listed IDs that are no longer
+/// favorites are skipped.
+pub fn favorites<'a>(entries: &'a [VaultEntry], order: &[String]) -> Vec<&'a VaultEntry> {
+    let mut listed: Vec<&VaultEntry> = Vec::new();
+    for entry in order.iter().filter_map(|id| entries.iter().find(|e| e.favorite && e.id == *id)) {
+        if !listed.iter().any(|e| e.id == entry.id) {
+            listed.push(entry);
+        }
+    }
+    let mut unlisted: Vec<&VaultEntry> = entries.iter().filter(|e| e.favorite && !order.contains(&e.id)).collect();
+    unlisted.sort_by(|a, b| fold_case(&a.title).cmp(fold_case(&b.title)).then_with(|| a.id.cmp(&b.id)));
+    listed.extend(unlisted);
+    listed
+}
+
+/// The favorite order after moving `id` to `position` (0 is first, past the end is
+/// last). The result lists every favorite, so ones added later go after them
+/// instead of reshuffling the row.
+pub fn move_favorite(entries: &[VaultEntry], order: &[String], id: &str, position: usize) -> Result<Vec<String>, String> {
+    let mut ids: Vec<String> = favorites(entries, order).into_iter().map(|e| e.id.clone()).collect();
+    let from = ids.iter().position(|f| f == id).ok_or("Favorites error: the entry is not a favorite")?;
+    let moved = ids.remove(from);
+    ids.insert(position.min(ids.len()), moved);
+    Ok(ids)
+}
+
+fn fold_case(title: &str) -> impl Iterator<Item = char> + '_ {
+    title.chars().default_case_fold()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn entry(id: usize, title: &str, used: u64, modified: u64) -> VaultEntry {
+        VaultEntry {
+            id: format!("{:05}", id),
+            title: title.to_string(),
+            last_used: used,
+            last_modified: modified,
+            ..Default::default()
+        }
+    }
+
+    fn titles(page: &Page) -> Vec<&str> {
+        page.entries.iter().map(|e| e.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_orders() {
+        let entries = vec![entry(1, "bank", 0, 30), entry(2, "Amazon", 5, 10), entry(3, "Chess", 9, 20)];
+        assert_eq!(titles(&page(&entries, 0, 10, EntrySort::Title)), ["Amazon", "bank", "Chess"]);
+        assert_eq!(titles(&page(&entries, 0, 10, EntrySort::LastUsed)), ["Chess", "Amazon", "bank"]);
+        assert_eq!(titles(&page(&entries, 0, 10, EntrySort::Modified)), ["bank", "Chess", "Amazon"]);
+
+        let second = page(&entries, 1, 1, EntrySort::Title);
+        assert_eq!((second.total, second.offset, titles(&second)), (3, 1, vec!["bank"]));
+        assert!(page(&entries, 5, 10, EntrySort::Title).entries.is_empty());
+        assert!(EntrySort::parse("size").is_err());
+    }
+
+    #[test]
+    fn test_search_folds_accents_and_width() {
+        let mut entries = vec![entry(1, "Café Müller", 0, 0), entry(2, "ＧｉｔＨｕｂ", 0, 0), entry(3, "Bank", 0, 0)];
+        entries[2].username = "cafe-owner".to_string();
+
+        let found = search(&entries, "CAFE", 0, 10, EntrySort::Title);
+        assert_eq!((found.total, titles(&found)), (2, vec!["Bank", "Café Müller"]));
+        assert_eq!(titles(&search(&entries, "github", 0, 10, EntrySort::Title)), ["ＧｉｔＨｕｂ"]);
+        assert_eq!(titles(&search(&entries, "muller cafe", 0, 10, EntrySort::Title)), ["Café Müller"]);
+        assert_eq!(search(&entries, "", 0, 10, EntrySort::Title).total, 3);
+    }
+
+    #[test]
+    fn test_frequently_used_decays_with_age() {
+        const DAY: u64 = 24 * 3600;
+        let now = 400 * DAY;
+        let mut entries =
+            vec![entry(1, "Old habit", now - 365 * DAY, 0), entry(2, "Weekly", now - DAY, 0), entry(3, "Never", 0, 0)];
+        entries[0].use_count = 300;
+        entries[1].use_count = 4;
+
+        let ranked = frequently_used(&entries, 10, now);
+        assert_eq!(ranked.iter().map(|e| e.title.as_str()).collect::<Vec<_>>(), ["Weekly", "Old habit"]);
+        assert_eq!(frequently_used(&entries, 1, now).len(), 1);
+
+        entries[0].last_used = now - 2 * DAY;
+        assert_eq!(frequently_used(&entries, 10, now)[0].title, "Old habit");
+    }
+
+    #[test]
+    fn test_favorites_keep_their_order() {
+        let mut entries = vec![entry(1, "Bank", 0, 0), entry(2, "amazon", 0, 0), entry(3, "Chess", 0, 0), entry(4, "Mail", 0, 0)];
+        for e in &mut entries[..3] {
+            e.favorite = true;
+        }
+        let ids = |list: Vec<&VaultEntry>| list.into_iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(favorites(&entries, &[])), ["00002", "00001", "00003"]);
+
+        let order = move_favorite(&entries, &[], "00003", 0).unwrap();
+        assert_eq!(order, ["00003", "00002", "00001"]);
+        // A new favorite goes last; an unfavorited one drops out.
+        entries[3].favorite = true;
+        entries[1].favorite = false;
+        assert_eq!(ids(favorites(&entries, &order)), ["00003", "00001", "00004"]);
+        assert_eq!(move_favorite(&entries, &order, "00003", 99).unwrap(), ["00001", "00004", "00003"]);
+        assert!(move_favorite(&entries, &order, "00002", 0).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_pages_match_a_full_sort(
+            keys in proptest::collection::vec((0u8..4, 0u64..3, 0u64..3), 0..60),
+            offset in 0usize..70,
+            limit in 0usize..20,
+        ) {
+            let entries: Vec<VaultEntry> = keys
+                .iter()
+                .enumerate()
+                .map(|(i, (t, used, modified))| entry(i, ["a", "B", "c", "b"][*t as usize], *used, *modified))
+                .collect();
+            for sort in [EntrySort::Title, EntrySort::LastUsed, EntrySort::Modified] {
+                let everything = page(&entries, 0, usize::MAX, sort).entries;
+                let expected: Vec<EntrySummary> = everything.into_iter().skip(offset).take(limit).collect();
+                prop_assert_eq!(page(&entries, offset, limit, sort).entries, expected);
+            }
+        }
+    }
+}
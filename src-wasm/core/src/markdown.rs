@@ -0,0 +1,210 @@
+// --- Markdown Notes ---
+// Renders an entry's notes from Markdown to HTML inside the engine, so the
+// decrypted text never passes through a JS Markdown library, and so what the
+// page inserts is safe by construction rather than by a sanitizer run later.
+//
+// pulldown-cmark parses; the HTML is written here from an allowlist of
+// elements, and nothing the note contains becomes markup on its own:
+//   - raw HTML in the note is shown as text, escaped;
+//   - links keep only http, https and mailto targets (anything else, such as
+//     "javascript:" or a relative path, renders as plain text) and carry
+//     rel="noopener noreferrer nofollow";
+//   - images are never loaded, since fetching one would tell its server the
+//     note was opened; they render as a link to the image, or as their alt text;
+//   - the only attributes written are href, title, start, type, checked,
+//     disabled and a code block's "language-..." class.
+// CommonMark plus tables, strikethrough and task lists. A single newline is a
+// line break, as notes are typed in a plain text box.
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+/// Link targets that may be followed from a note.
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+const LINK_REL: &str = "noopener noreferrer nofollow";
+
+/// `notes` as an HTML fragment, safe to assign to innerHTML.
+pub fn to_html(notes: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut writer = Writer::default();
+    for event in Parser::new_ext(notes, options) {
+        writer.event(event);
+    }
+    writer.html
+}
+
+#[derive(Default)]
+struct Writer {
+    html: String,
+    /// The closing markup for each open tag, innermost last ("" for a dropped tag).
+    open: Vec<&'static str>,
+    in_table_head: bool,
+    table_body_open: bool,
+}
+
+impl Writer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => {
+                let close = self.start(tag);
+                self.open.push(close);
+            }
+            Event::End(end) => {
+                match end {
+                    TagEnd::TableHead => self.in_table_head = false,
+                    TagEnd::Table if self.table_body_open => {
+                        self.html.push_str("</tbody>");
+                        self.table_body_open = false;
+                    }
+                    _ => {}
+                }
+                if let Some(close) = self.open.pop() {
+                    self.html.push_str(close);
+                }
+            }
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => escape(&mut self.html, &text),
+            Event::Code(code) => {
+                self.html.push_str("<code>");
+                escape(&mut self.html, &code);
+                self.html.push_str("</code>");
+            }
+            Event::SoftBreak | Event::HardBreak => self.html.push_str("<br>\n"),
+            Event::Rule => self.html.push_str("<hr>\n"),
+            Event::TaskListMarker(checked) => {
+                self.html.push_str(if checked { r#"<input type="checkbox" disabled checked> "# } else { r#"<input type="checkbox" disabled> "# })
+            }
+            // Math and footnotes aren't enabled; keep their text if they ever appear.
+            Event::InlineMath(text) | Event::DisplayMath(text) | Event::FootnoteReference(text) => escape(&mut self.html, &text),
+        }
+    }
+
+    /// Writes the opening markup for `tag` and returns its closing markup.
+    fn start(&mut self, tag: Tag) -> &'static str {
+        let (open, close): (&str, &'static str) = match tag {
+            Tag::Paragraph => ("<p>", "</p>\n"),
+            Tag::Heading { level, .. } => match level {
+                HeadingLevel::H1 => ("<h1>", "</h1>\n"),
+                HeadingLevel::H2 => ("<h2>", "</h2>\n"),
+                HeadingLevel::H3 => ("<h3>", "</h3>\n"),
+                HeadingLevel::H4 => ("<h4>", "</h4>\n"),
+                HeadingLevel::H5 => ("<h5>", "</h5>\n"),
+                HeadingLevel::H6 => ("<h6>", "</h6>\n"),
+            },
+            Tag::BlockQuote(_) => ("<blockquote>\n", "</blockquote>\n"),
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                if !language.is_empty() && language.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+')) {
+                    self.html.push_str(&format!(r#"<pre><code class="language-{}">"#, language));
+                    return "</code></pre>\n";
+                }
+                ("<pre><code>", "</code></pre>\n")
+            }
+            Tag::List(Some(start)) if start != 1 => {
+                self.html.push_str(&format!(r#"<ol start="{}">"#, start));
+                return "</ol>\n";
+            }
+            Tag::List(Some(_)) => ("<ol>\n", "</ol>\n"),
+            Tag::List(None) => ("<ul>\n", "</ul>\n"),
+            Tag::Item => ("<li>", "</li>\n"),
+            Tag::Table(_) => ("<table>", "</table>\n"),
+            Tag::TableHead => {
+                self.in_table_head = true;
+                ("<thead><tr>", "</tr></thead>")
+            }
+            Tag::TableRow => {
+                if !self.table_body_open {
+                    self.html.push_str("<tbody>");
+                    self.table_body_open = true;
+                }
+                ("<tr>", "</tr>")
+            }
+            Tag::TableCell if self.in_table_head => ("<th>", "</th>"),
+            Tag::TableCell => ("<td>", "</td>"),
+            Tag::Emphasis => ("<em>", "</em>"),
+            Tag::Strong => ("<strong>", "</strong>"),
+            Tag::Strikethrough => ("<del>", "</del>"),
+            Tag::Superscript => ("<sup>", "</sup>"),
+            Tag::Subscript => ("<sub>", "</sub>"),
+            Tag::Link { dest_url, title, .. } | Tag::Image { dest_url, title, .. } => {
+                if !is_allowed_link(&dest_url) {
+                    return "";
+                }
+                self.html.push_str("<a href=\"");
+                escape(&mut self.html, dest_url.trim());
+                if !title.is_empty() {
+                    self.html.push_str("\" title=\"");
+                    escape(&mut self.html, &title);
+                }
+                self.html.push_str(&format!("\" rel=\"{}\">", LINK_REL));
+                return "</a>";
+            }
+            // Raw HTML blocks (their content arrives as escaped text), definition
+            // lists, footnotes and metadata: content only.
+            _ => ("", ""),
+        };
+        self.html.push_str(open);
+        close
+    }
+}
+
+/// True for an absolute http, https or mailto URL. Browsers skip tabs and
+/// newlines inside a scheme ("java\tscript:"), so those are dropped first.
+fn is_allowed_link(url: &str) -> bool {
+    let url: String = url.trim().chars().filter(|c| !c.is_ascii_control()).collect();
+    match url.split_once(':') {
+        Some((scheme, _)) => ALLOWED_SCHEMES.iter().any(|allowed| scheme.eq_ignore_ascii_case(allowed)),
+        None => false,
+    }
+}
+
+fn escape(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_renders_to_allowed_elements() {
+        assert_eq!(to_html("# Wi-Fi\n**guest** _pw_ `x<y`"), "<h1>Wi-Fi</h1>\n<p><strong>guest</strong> <em>pw</em> <code>x&lt;y</code></p>\n");
+        assert_eq!(to_html("line one\nline two"), "<p>line one<br>\nline two</p>\n");
+        assert_eq!(
+            to_html("3. c\n4. d\n\n- [x] done"),
+            "<ol start=\"3\"><li>c</li>\n<li>d</li>\n</ol>\n<ul>\n<li><input type=\"checkbox\" disabled checked> done</li>\n</ul>\n"
+        );
+        assert_eq!(
+            to_html("| a | b |\n|---|---|\n| 1 | 2 |"),
+            "<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr></tbody></table>\n"
+        );
+        assert_eq!(to_html("```rust\nfn main() {}\n```"), "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n");
+        assert_eq!(
+            to_html("[site](https://example.com \"t\")"),
+            "<p><a href=\"https://example.com\" title=\"t\" rel=\"noopener noreferrer nofollow\">site</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_hostile_markup_is_inert() {
+        assert_eq!(to_html("<script>alert(1)</script>"), "&lt;script&gt;alert(1)&lt;/script&gt;");
+        assert_eq!(to_html("hi <img src=x onerror=alert(1)>"), "<p>hi &lt;img src=x onerror=alert(1)&gt;</p>\n");
+        assert_eq!(to_html("[x](javascript:alert(1))"), "<p>x</p>\n");
+        assert_eq!(to_html("[x](java\tscript:alert(1))"), "<p>[x](java\tscript:alert(1))</p>\n");
+        assert_eq!(to_html("[x](&#106;avascript:alert(1))"), "<p>x</p>\n");
+        assert_eq!(to_html("[x](/settings)"), "<p>x</p>\n");
+        assert_eq!(to_html("![pixel](https://t.example/p.gif)"), "<p><a href=\"https://t.example/p.gif\" rel=\"noopener noreferrer nofollow\">pixel</a></p>\n");
+        assert_eq!(to_html("```x\" onclick=\"y\nz\n```"), "<pre><code>z\n</code></pre>\n");
+        assert!(!to_html("[a](https://e.com/\"onmouseover=\"x)").contains("\"onmouseover"));
+    }
+}
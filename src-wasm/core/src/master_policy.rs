@@ -0,0 +1,143 @@
+// --- Master Password Policy ---
+// The rules a new master password has to pass, in one place so the web app,
+// the mobile apps and the CLI refuse exactly the same passwords with the same
+// messages. Every broken rule is reported, not just the first, so onboarding
+// can show them all at once. Only applied when a password is chosen: vaults
+// created under older rules keep opening with whatever password they have.
+
+use serde::Serialize;
+
+use crate::blocklist;
+use crate::strength::entropy_bits;
+
+/// Characters, counted as Unicode scalar values.
+pub const MIN_LENGTH: usize = 12;
+/// By 'strength::entropy_bits': twelve lowercase letters alone fall just short.
+pub const MIN_ENTROPY_BITS: f64 = 60.0;
+/// Shorter pieces of personal info ("bob", "nyc") are too likely to appear by chance.
+const MIN_HINT_PART_LEN: usize = 4;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "rule", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    LowEntropy { bits: f64, min_bits: f64 },
+    /// On the common-password blocklist.
+    Common,
+    /// Contains part of one of the hints (e.g. the account email).
+    PersonalInfo { hint: String },
+}
+
+impl PolicyViolation {
+    /// The "rule" tag in the JSON form.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PolicyViolation::TooShort { .. } => "tooShort",
+            PolicyViolation::LowEntropy { .. } => "lowEntropy",
+            PolicyViolation::Common => "common",
+            PolicyViolation::PersonalInfo { .. } => "personalInfo",
+        }
+    }
+
+    /// The text every client shows for this violation.
+    pub fn message(&self) -> String {
+        match self {
+            PolicyViolation::TooShort { min_length } => format!("Use at least {} characters.", min_length),
+            PolicyViolation::LowEntropy { .. } => {
+                "Mix in more kinds of characters or make it longer: this password is easy to guess.".to_string()
+            }
+            PolicyViolation::Common => "This password is too common. Choose one attackers won't try first.".to_string(),
+            PolicyViolation::PersonalInfo { hint } => format!("Don't include personal information such as '{}'.", hint),
+        }
+    }
+}
+
+/// A violation with its message, as the JSON front ends receive.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Report {
+    #[serde(flatten)]
+    pub violation: PolicyViolation,
+    pub message: String,
+}
+
+/// Every rule `password` breaks; empty means it may be used. `hints` are things
+/// the user is known by (email, name, username): a password containing a part of
+/// one, ignoring case, is refused. An email counts by its local part and domain.
+pub fn validate_master_password(password: &str, hints: &[&str]) -> Vec<PolicyViolation> {
+    let mut violations = Vec::new();
+    if password.chars().count() < MIN_LENGTH {
+        violations.push(PolicyViolation::TooShort { min_length: MIN_LENGTH });
+    }
+    let bits = entropy_bits(password);
+    if bits < MIN_ENTROPY_BITS {
+        violations.push(PolicyViolation::LowEntropy { bits: (bits * 10.0).round() / 10.0, min_bits: MIN_ENTROPY_BITS });
+    }
+    if blocklist::is_common_password(password) {
+        violations.push(PolicyViolation::Common);
+    }
+
+    let lower = password.to_lowercase();
+    for hint in hints {
+        if hint_parts(hint).any(|part| lower.contains(&part)) {
+            violations.push(PolicyViolation::PersonalInfo { hint: hint.trim().to_string() });
+        }
+    }
+    violations
+}
+
+/// 'validate_master_password' with each violation's message attached.
+pub fn report(password: &str, hints: &[&str]) -> Vec<Report> {
+    validate_master_password(password, hints)
+        .into_iter()
+        .map(|violation| Report { message: violation.message(), violation })
+        .collect()
+}
+
+/// The lowercase alphanumeric runs of a hint long enough to matter, leaving out
+/// an email's top-level domain: "Jane.Doe@example.com" gives "jane", "example".
+fn hint_parts(hint: &str) -> impl Iterator<Item = String> + '_ {
+    let hint = hint.trim();
+    let hint = match hint.rsplit_once('@') {
+        Some((local, domain)) => &hint[..local.len() + 1 + domain.rfind('.').unwrap_or(domain.len())],
+        None => hint,
+    };
+    hint.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| part.chars().count() >= MIN_HINT_PART_LEN)
+        .map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_every_broken_rule() {
+        let violations = validate_master_password("password", &[]);
+        assert_eq!(violations.len(), 3);
+        assert_eq!(violations[0], PolicyViolation::TooShort { min_length: 12 });
+        assert!(matches!(violations[1], PolicyViolation::LowEntropy { min_bits, .. } if min_bits == 60.0));
+        assert_eq!(violations[2], PolicyViolation::Common);
+
+        assert!(validate_master_password("vexing-Tundra-quill-42", &[]).is_empty());
+        assert!(validate_master_password("abcdefghijkl", &[]).iter().any(|v| matches!(v, PolicyViolation::LowEntropy { .. })));
+    }
+
+    #[test]
+    fn test_personal_info_hints() {
+        let hints = ["Jane.Doe@example.com", "  Springfield "];
+        assert!(validate_master_password("vexing-Tundra-quill-42", &hints).is_empty());
+        assert_eq!(
+            validate_master_password("Jane-family-Tundra-42", &hints),
+            [PolicyViolation::PersonalInfo { hint: "Jane.Doe@example.com".to_string() }]
+        );
+        assert_eq!(validate_master_password("x7!SPRINGFIELD!x7", &hints).len(), 1);
+        // The TLD alone and pieces under four characters don't count.
+        assert!(validate_master_password("vexing-com-Doe-quill-42", &["jd@doe.com"]).is_empty());
+    }
+
+    #[test]
+    fn test_report_json() {
+        let json = serde_json::to_string(&report("short", &[])[0]).unwrap();
+        assert_eq!(json, r#"{"rule":"tooShort","minLength":12,"message":"Use at least 12 characters."}"#);
+    }
+}
@@ -0,0 +1,126 @@
+// --- Passphrase Memorability ---
+// Every passphrase drawn with the same options has the same entropy, but not
+// every one is as easy to remember: "lemon-tiger-river-cloud" sticks, while
+// "quartz-xenon-harvest-unique" takes rehearsal. 'suggest' generates a batch and
+// ranks it by a memorability estimate, so the UI can offer the most memorable
+// of equally strong candidates.
+//
+// The estimate is a heuristic from the words themselves; there is no frequency
+// or imageability data in the build. Short words stand in for frequent ones
+// (the most used words in a language are the shortest), and the word lists are
+// already concrete nouns, which are the most imageable. On top of that it scores
+// how easy a word is to say (syllables, consonant runs) and, for the phrase, how
+// distinct its words are: a repeated word or first letter makes the words
+// easier to mix up.
+
+use serde::Serialize;
+
+use crate::generator::{self, PassphraseOptions};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoredPassphrase {
+    pub passphrase: String,
+    /// log2 of the number of passphrases these options can produce.
+    pub entropy_bits: f64,
+    /// 0 (hard) to 100 (easy).
+    pub memorability: u32,
+}
+
+/// Entropy of a passphrase drawn with these options.
+pub fn entropy_bits(options: &PassphraseOptions) -> Result<f64, String> {
+    let words = options.language.words()?;
+    Ok(options.words as f64 * (words.len() as f64).log2())
+}
+
+/// 0.0 to 1.0 for one word.
+fn word_score(word: &str) -> f64 {
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).map(|c| c.to_ascii_lowercase()).collect();
+    if letters.is_empty() {
+        return 0.0;
+    }
+    let is_vowel = |c: &char| "aeiouy".contains(*c);
+    let syllables = letters.iter().enumerate().filter(|(i, c)| is_vowel(c) && (*i == 0 || !is_vowel(&letters[i - 1]))).count();
+    let mut longest_run = 0;
+    let mut run = 0;
+    for c in &letters {
+        run = if is_vowel(c) { 0 } else { run + 1 };
+        longest_run = longest_run.max(run);
+    }
+
+    let length = letters.len() as f64;
+    let mut score = 1.0;
+    // Four to six letters is the sweet spot; every letter beyond costs.
+    score -= 0.08 * (length - 6.0).max(0.0) + 0.1 * (4.0 - length).max(0.0);
+    score -= 0.15 * (syllables as f64 - 2.0).max(0.0);
+    score -= 0.1 * (longest_run as f64 - 2.0).max(0.0);
+    // Rare letters make a word stand out but are hard to recall in the right spot.
+    score -= 0.05 * letters.iter().filter(|c| "jqxz".contains(**c)).count() as f64;
+    score.clamp(0.0, 1.0)
+}
+
+/// 0 to 100 for a passphrase, its words split on anything but letters.
+pub fn memorability(passphrase: &str) -> u32 {
+    let words: Vec<String> =
+        passphrase.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect();
+    if words.is_empty() {
+        return 0;
+    }
+    let average = words.iter().map(|w| word_score(w)).sum::<f64>() / words.len() as f64;
+    let repeated = |key: &dyn Fn(&String) -> String| {
+        let keys: Vec<String> = words.iter().map(key).collect();
+        keys.iter().enumerate().filter(|(i, k)| keys[..*i].contains(k)).count()
+    };
+    let same_words = repeated(&|w| w.clone());
+    let same_initials = repeated(&|w| w.chars().take(1).collect());
+    let score = average * 0.8_f64.powi(same_words as i32) * 0.95_f64.powi(same_initials as i32);
+    (score * 100.0).round() as u32
+}
+
+/// Scores a passphrase drawn with `options`.
+pub fn score(passphrase: &str, options: &PassphraseOptions) -> Result<ScoredPassphrase, String> {
+    Ok(ScoredPassphrase {
+        passphrase: passphrase.to_string(),
+        entropy_bits: entropy_bits(options)?,
+        memorability: memorability(passphrase),
+    })
+}
+
+/// Generates `count` passphrases (1 to 50) and returns them most memorable first.
+/// All have the same entropy, so the first is the strongest memorable one.
+pub fn suggest(options: &PassphraseOptions, count: usize) -> Result<Vec<ScoredPassphrase>, String> {
+    if count == 0 || count > 50 {
+        return Err("Generator error: suggest 1 to 50 passphrases".to_string());
+    }
+    let mut batch = (0..count)
+        .map(|_| generator::passphrase_with(options).and_then(|phrase| score(&phrase, options)))
+        .collect::<Result<Vec<_>, String>>()?;
+    // Stable, so equal scores keep the order they were drawn in.
+    batch.sort_by_key(|scored| std::cmp::Reverse(scored.memorability));
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easy_words_score_higher() {
+        assert!(memorability("lemon-tiger-river-cloud") > memorability("quartz-xenon-harvest-unique"));
+        assert!(memorability("honey-pearl-solar-dance") > memorability("honey-honey-solar-dance"));
+        assert!(word_score("wave") > word_score("schwertlilie"));
+        assert_eq!(memorability("---"), 0);
+        assert!(memorability("lemon tiger") <= 100);
+    }
+
+    #[test]
+    fn test_suggest_ranks_a_batch() {
+        let options = PassphraseOptions::default();
+        let batch = suggest(&options, 10).unwrap();
+        assert_eq!(batch.len(), 10);
+        assert!(batch.windows(2).all(|pair| pair[0].memorability >= pair[1].memorability));
+        // Four words from the 50-word English list.
+        assert!((batch[0].entropy_bits - 4.0 * 50f64.log2()).abs() < 1e-9);
+        assert!(suggest(&options, 0).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+// --- OpenPGP Export ---
+// Seals an export to a user's existing OpenPGP public key, for people whose
+// recovery plan already revolves around GnuPG, Sequoia or a hardware token.
+// The output is an ASCII-armored SEIPD v1 message (AES-256 with MDC), the form
+// every gpg release decrypts, so `gpg -d vault.json.asc` is all it takes.
+//
+// The key is checked before anything is encrypted: its self-signatures must
+// verify, and the message goes to its newest subkey that is flagged for
+// encryption, unrevoked and unexpired at `now`.
+
+use pgp::composed::{ArmorOptions, Deserializable, MessageBuilder, SignedPublicKey, SignedPublicSubKey};
+use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use pgp::packet::{Signature, SignatureType};
+use pgp::types::KeyDetails;
+use rand::rngs::OsRng;
+use serde::Serialize;
+
+/// Name stored in the literal data packet; `gpg --use-embedded-filename` restores it.
+pub const EXPORT_FILENAME: &str = "securepass-vault.json";
+
+/// What the user should confirm before exporting to a key.
+#[derive(Serialize, Debug)]
+pub struct KeySummary {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    /// Fingerprint of the subkey the export will actually be encrypted to.
+    pub encryption_subkey: String,
+}
+
+/// Checks an armored (or binary) public key and names the subkey an export would use.
+pub fn describe_key(public_key: &[u8], now: u64) -> Result<KeySummary, String> {
+    let key = parse_key(public_key)?;
+    let subkey = encryption_subkey(&key, now)?;
+    Ok(KeySummary {
+        fingerprint: format!("{:X}", key.fingerprint()),
+        user_ids: key.details.users.iter().map(|u| String::from_utf8_lossy(u.id.id()).into_owned()).collect(),
+        encryption_subkey: format!("{:X}", subkey.key.fingerprint()),
+    })
+}
+
+/// Encrypts `plaintext` to the key's encryption subkey. Returns the armored message.
+pub fn encrypt(public_key: &[u8], plaintext: &[u8], now: u64) -> Result<String, String> {
+    let key = parse_key(public_key)?;
+    let subkey = encryption_subkey(&key, now)?;
+
+    let mut builder =
+        MessageBuilder::from_bytes(EXPORT_FILENAME, plaintext.to_vec()).seipd_v1(OsRng, SymmetricKeyAlgorithm::AES256);
+    builder.encrypt_to_key(OsRng, &subkey.key).map_err(|e| format!("OpenPGP encrypt error: {}", e))?;
+    builder
+        .to_armored_string(OsRng, ArmorOptions::default())
+        .map_err(|e| format!("OpenPGP encrypt error: {}", e))
+}
+
+fn parse_key(public_key: &[u8]) -> Result<SignedPublicKey, String> {
+    let (key, _headers) =
+        SignedPublicKey::from_reader_single(public_key).map_err(|e| format!("OpenPGP key error: {}", e))?;
+    key.verify_bindings().map_err(|e| format!("OpenPGP key error: {}", e))?;
+    if !key.details.revocation_signatures.is_empty() {
+        return Err("OpenPGP key error: the key has been revoked".to_string());
+    }
+    Ok(key)
+}
+
+fn encryption_subkey(key: &SignedPublicKey, now: u64) -> Result<&SignedPublicSubKey, String> {
+    key.public_subkeys
+        .iter()
+        .filter(|subkey| can_encrypt_at(subkey, now))
+        .max_by_key(|subkey| subkey.key.created_at().as_secs())
+        .ok_or_else(|| "OpenPGP key error: no valid encryption subkey".to_string())
+}
+
+fn can_encrypt_at(subkey: &SignedPublicSubKey, now: u64) -> bool {
+    if subkey.signatures.iter().any(|s| s.typ() == Some(SignatureType::SubkeyRevocation)) {
+        return false;
+    }
+    // The newest binding signature is the one in force.
+    let Some(binding) = subkey.signatures.iter().max_by_key(|s| signature_time(s)) else {
+        return false;
+    };
+    let flags = binding.key_flags();
+    let expires_at = binding
+        .key_expiration_time()
+        .map(|d| d.as_secs())
+        .filter(|&secs| secs > 0)
+        .map(|secs| u64::from(subkey.key.created_at().as_secs()) + u64::from(secs));
+
+    subkey.key.algorithm().can_encrypt()
+        && (flags.encrypt_comms() || flags.encrypt_storage())
+        && expires_at.is_none_or(|at| now < at)
+}
+
+fn signature_time(signature: &Signature) -> u32 {
+    signature.created().map(|t| t.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pgp::composed::{EncryptionCaps, KeyType, Message, SecretKeyParamsBuilder, SignedSecretKey, SubkeyParamsBuilder};
+    use pgp::crypto::ecc_curve::ECCCurve;
+
+    fn keygen(encrypt: bool) -> SignedSecretKey {
+        let mut subkey = SubkeyParamsBuilder::default();
+        subkey
+            .key_type(KeyType::ECDH(ECCCurve::Curve25519Legacy))
+            .can_encrypt(if encrypt { EncryptionCaps::All } else { EncryptionCaps::None });
+        let mut params = SecretKeyParamsBuilder::default();
+        params
+            .key_type(KeyType::Ed25519Legacy)
+            .can_certify(true)
+            .primary_user_id("Alice <alice@example.com>".into())
+            .subkeys(vec![subkey.build().unwrap()]);
+        params.build().unwrap().generate(OsRng).unwrap()
+    }
+
+    fn armored_public(secret: &SignedSecretKey) -> Vec<u8> {
+        SignedPublicKey::from(secret.clone()).to_armored_string(Default::default()).unwrap().into_bytes()
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let secret = keygen(true);
+        let public = armored_public(&secret);
+
+        let summary = describe_key(&public, 0).unwrap();
+        assert_eq!(summary.user_ids, vec!["Alice <alice@example.com>"]);
+        assert_eq!(summary.encryption_subkey, format!("{:X}", secret.secret_subkeys[0].key.fingerprint()));
+
+        let armored = encrypt(&public, br#"{"entries":[]}"#, 0).unwrap();
+        assert!(armored.starts_with("-----BEGIN PGP MESSAGE-----"));
+
+        let (message, _) = Message::from_armor(armored.as_bytes()).unwrap();
+        let mut decrypted = message.decrypt(&"".into(), &secret).unwrap();
+        assert_eq!(decrypted.as_data_vec().unwrap(), br#"{"entries":[]}"#);
+    }
+
+    #[test]
+    fn test_rejects_unusable_keys() {
+        let sign_only = armored_public(&keygen(false));
+        assert!(encrypt(&sign_only, b"x", 0).unwrap_err().contains("no valid encryption subkey"));
+        assert!(encrypt(b"-----BEGIN PGP PUBLIC KEY BLOCK-----\n\nAAAA\n", b"x", 0).is_err());
+    }
+}
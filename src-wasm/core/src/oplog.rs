@@ -0,0 +1,622 @@
+// --- Sync Op-Log ---
+// Offline-first sync without a server that understands the vault. Every edit
+// becomes an operation (add, update or delete an entry, or set a vault-level
+// field) stamped with a Lamport clock and the ID of the device that made it.
+// Devices swap ops through any dumb store; each op is sealed on its own under a
+// subkey of the master key, so the store only ever sees the stamps.
+//
+// The vault itself is never merged. It is rebuilt by replaying every known op
+// in (lamport, device) order, the same total order on every device, so two
+// devices that hold the same set of ops hold the same vault, whatever order
+// the ops arrived in. Conflicts resolve from that order:
+//   * concurrent edits to different fields of one entry both survive
+//   * concurrent edits to the same field: the op later in the order wins
+//   * a delete is final; later updates to, or re-adds of, that entry are dropped,
+//     including after the delete is folded into a snapshot (see 'Snapshot::deleted')
+//
+// Devices keep their sealed ops (their own and everyone else's) and rebuild the
+// log on startup by merging them back in; the clock resumes from the highest stamp.
+// A snapshot folds a prefix of every device's ops into a base vault, so old ops
+// can be forgotten (see blobs.rs); ops from one device must therefore be merged
+// in the order that device recorded them.
+
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap};
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use zeroize::Zeroize;
+
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::redact;
+use crate::vault::{wipe_value, VaultData, VaultEntry};
+
+pub(crate) const PURPOSE: &str = "securepass-oplog-v1";
+/// Ops are sealed with key commitment since version 2; version 1 ops were plain AES-GCM.
+const OP_VERSION: u8 = 2;
+const LEGACY_OP_VERSION: u8 = 1;
+/// The highest stamp 'merge' accepts. The rest of the range is left for local
+/// edits, so no op from elsewhere can run this device's clock out.
+const MAX_STAMP: u64 = u64::MAX >> 1;
+
+/// Orders ops identically on every device: by clock, then by device ID.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpId {
+    pub lamport: u64,
+    pub device: String,
+}
+
+/// One edit, as recorded by the device that made it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Change {
+    Add { entry: Box<VaultEntry> },
+    /// Only the fields that changed, in the entry's JSON shape; `null` removes a field.
+    Update { entry_id: String, fields: Map<String, Value> },
+    Delete { entry_id: String },
+    /// A top-level vault field other than `entries` (categories, equivalentDomains, ...).
+    SetVault { field: String, value: Value },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Op {
+    pub id: OpId,
+    pub change: Change,
+}
+
+/// An op as it travels and is stored: the stamp in the clear, the change encrypted
+/// and bound to the stamp so it can't be replayed under another one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SealedOp {
+    pub version: u8,
+    pub id: OpId,
+    pub iv: String,
+    pub data: String,
+}
+
+/// The vault after replaying a prefix of every device's ops. Snapshots are
+/// ordered by (number, device); a log only ever moves to a later one.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub number: u64,
+    /// The device that made it; breaks the tie when two pick the same number.
+    pub device: String,
+    pub vault: VaultData,
+    /// Highest Lamport stamp folded in, per device.
+    pub covered: BTreeMap<String, u64>,
+    /// Highest ops blob folded in, per device.
+    pub blobs: BTreeMap<String, u64>,
+    /// Every entry ID deleted so far, so a re-add still in the log stays dropped.
+    #[serde(default)]
+    pub deleted: BTreeSet<String>,
+}
+
+/// Every op this device knows about since its base snapshot, plus its own clock.
+pub struct OpLog {
+    device: String,
+    clock: u64,
+    base: Snapshot,
+    ops: BTreeMap<OpId, Change>,
+    blobs: BTreeMap<String, u64>,
+}
+
+impl OpLog {
+    /// An empty log for `device`, a stable ID unique to this install. It names
+    /// files on remote storage, so only letters, digits, '-' and '_' are allowed.
+    pub fn new(device: &str) -> Result<OpLog, String> {
+        if !is_valid_device(device) {
+            return Err("Sync error: device ID must be 1-64 letters, digits, '-' or '_'".to_string());
+        }
+        Ok(OpLog {
+            device: device.to_string(),
+            clock: 0,
+            base: Snapshot::default(),
+            ops: BTreeMap::new(),
+            blobs: BTreeMap::new(),
+        })
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// The highest stamp seen so far, local or remote.
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// The snapshot the log replays on top of (number 0 until one is adopted).
+    pub fn base(&self) -> &Snapshot {
+        &self.base
+    }
+
+    /// The highest ops blob of `device` merged so far, 0 if none.
+    pub fn last_blob(&self, device: &str) -> u64 {
+        self.blobs.get(device).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn set_last_blob(&mut self, device: &str, number: u64) {
+        self.blobs.insert(device.to_string(), number);
+    }
+
+    /// Stamps local changes with the next clock values. Returns the new ops, to seal and share.
+    pub fn record(&mut self, changes: Vec<Change>) -> Vec<Op> {
+        changes
+            .into_iter()
+            .map(|change| {
+                self.clock = self.clock.saturating_add(1);
+                let id = OpId { lamport: self.clock, device: self.device.clone() };
+                self.ops.insert(id.clone(), change.clone());
+                Op { id, change }
+            })
+            .collect()
+    }
+
+    /// Adds ops from other devices or from local storage. Ops already in the log
+    /// are ignored, so merging is idempotent. An op stamped above MAX_STAMP is
+    /// dropped. Returns how many were new.
+    pub fn merge(&mut self, ops: Vec<Op>) -> usize {
+        let mut added = 0;
+        for op in ops {
+            if op.id.lamport > MAX_STAMP {
+                continue;
+            }
+            self.clock = self.clock.max(op.id.lamport);
+            if op.id.lamport <= self.base.covered.get(&op.id.device).copied().unwrap_or(0) {
+                continue;
+            }
+            if let btree_map::Entry::Vacant(slot) = self.ops.entry(op.id) {
+                slot.insert(op.change);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Replays every op in order into a fresh vault. An op that no longer fits
+    /// the data model is skipped, the same way on every device.
+    pub fn materialize(&self) -> VaultData {
+        let mut vault = self.base.vault.clone();
+        let mut deleted = self.base.deleted.clone();
+        for change in self.ops.values() {
+            match change {
+                Change::Add { entry } if deleted.contains(&entry.id) => continue,
+                Change::Delete { entry_id } => {
+                    deleted.insert(entry_id.clone());
+                }
+                _ => {}
+            }
+            apply_change(&mut vault, change);
+        }
+        vault
+    }
+
+    /// Folds everything the log holds into the next snapshot, made by this device.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut covered = self.base.covered.clone();
+        let mut deleted = self.base.deleted.clone();
+        for (id, change) in &self.ops {
+            let highest = covered.entry(id.device.clone()).or_insert(0);
+            *highest = (*highest).max(id.lamport);
+            if let Change::Delete { entry_id } = change {
+                deleted.insert(entry_id.clone());
+            }
+        }
+        Snapshot {
+            number: self.base.number + 1,
+            device: self.device.clone(),
+            vault: self.materialize(),
+            covered,
+            blobs: self.blobs.clone(),
+            deleted,
+        }
+    }
+
+    /// Moves the log onto `snapshot` if it is later than the current base, dropping
+    /// the ops it covers. Returns false (and changes nothing) for an older one.
+    pub fn rebase(&mut self, snapshot: Snapshot) -> bool {
+        if (snapshot.number, &snapshot.device) <= (self.base.number, &self.base.device) {
+            return false;
+        }
+        self.ops.retain(|id, change| {
+            let keep = id.lamport > snapshot.covered.get(&id.device).copied().unwrap_or(0);
+            if !keep {
+                wipe_change(change);
+            }
+            keep
+        });
+        for (device, &number) in &snapshot.blobs {
+            let last = self.blobs.entry(device.clone()).or_insert(0);
+            *last = (*last).max(number);
+        }
+        self.clock = snapshot.covered.values().fold(self.clock, |clock, &l| clock.max(l));
+        std::mem::replace(&mut self.base, snapshot).vault.wipe();
+        true
+    }
+}
+
+impl Drop for OpLog {
+    fn drop(&mut self) {
+        self.ops.values_mut().for_each(wipe_change);
+        self.base.vault.wipe();
+    }
+}
+
+/// Applies one change to `vault`. An add replaces an entry with the same ID, and
+/// a change that no longer fits the data model does nothing.
+pub(crate) fn apply_change(vault: &mut VaultData, change: &Change) {
+    match change {
+        Change::Add { entry } => match vault.entries.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => {
+                existing.wipe();
+                *existing = VaultEntry::clone(entry);
+            }
+            None => vault.entries.push(VaultEntry::clone(entry)),
+        },
+        Change::Update { entry_id, fields } => {
+            if let Some(existing) = vault.entries.iter_mut().find(|e| &e.id == entry_id) {
+                if let Some(updated) = apply_fields(existing, fields) {
+                    existing.wipe();
+                    *existing = updated;
+                }
+            }
+        }
+        Change::Delete { entry_id } => {
+            vault.entries.iter_mut().filter(|e| &e.id == entry_id).for_each(VaultEntry::wipe);
+            vault.entries.retain(|e| &e.id != entry_id);
+        }
+        Change::SetVault { field, value } => {
+            if field == "entries" {
+                return;
+            }
+            let mut root = root_object(vault);
+            set_field(&mut root, field, value);
+            if let Ok(updated) = serde_json::from_value::<VaultData>(Value::Object(root)) {
+                *vault = VaultData { entries: std::mem::take(&mut vault.entries), ..updated };
+            }
+        }
+    }
+}
+
+/// 1-64 characters from [A-Za-z0-9_-].
+pub(crate) fn is_valid_device(device: &str) -> bool {
+    (1..=64).contains(&device.len()) && device.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// The changes that turn `old` into `new`: adds and field-level updates in `new`'s
+/// entry order, then deletes, then vault-level fields.
+pub fn diff(old: &VaultData, new: &VaultData) -> Result<Vec<Change>, String> {
+    let before: HashMap<&str, &VaultEntry> = old.entries.iter().map(|e| (e.id.as_str(), e)).collect();
+    let after: HashMap<&str, &VaultEntry> = new.entries.iter().map(|e| (e.id.as_str(), e)).collect();
+    let mut changes = Vec::new();
+
+    for entry in &new.entries {
+        match before.get(entry.id.as_str()) {
+            None => changes.push(Change::Add { entry: Box::new(entry.clone()) }),
+            Some(previous) if *previous != entry => {
+                let (mut old_fields, mut new_fields) = (entry_object(previous)?, entry_object(entry)?);
+                let fields = changed_fields(&old_fields, &new_fields);
+                old_fields.values_mut().chain(new_fields.values_mut()).for_each(wipe_value);
+                changes.push(Change::Update { entry_id: entry.id.clone(), fields });
+            }
+            Some(_) => {}
+        }
+    }
+    for entry in &old.entries {
+        if !after.contains_key(entry.id.as_str()) {
+            changes.push(Change::Delete { entry_id: entry.id.clone() });
+        }
+    }
+    for (field, value) in changed_fields(&root_object(old), &root_object(new)) {
+        changes.push(Change::SetVault { field, value });
+    }
+    Ok(changes)
+}
+
+/// Encrypts one op for sharing.
+pub fn seal_op(master_key: &[u8; 32], op: &Op) -> Result<SealedOp, String> {
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let mut plain = serde_json::to_vec(&op.change).map_err(|e| format!("Sync serialize error: {}", e))?;
+    let sealed = seal_committed(&key, &plain, &stamp(&op.id));
+    key.zeroize();
+    plain.zeroize();
+
+    let (iv, data) = sealed?;
+    Ok(SealedOp { version: OP_VERSION, id: op.id.clone(), iv: B64.encode(iv), data: B64.encode(data) })
+}
+
+/// Decrypts one op. Fails if it was sealed under another vault or its stamp was altered.
+pub fn open_op(master_key: &[u8; 32], sealed: &SealedOp) -> Result<Op, String> {
+    if ![LEGACY_OP_VERSION, OP_VERSION].contains(&sealed.version) {
+        return Err(format!("Unsupported sync op version: {}", sealed.version));
+    }
+    let iv = B64.decode(&sealed.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&sealed.data).map_err(|e| format!("Sync decode error: {}", e))?;
+
+    let mut key = derive_subkey(master_key, PURPOSE, b"");
+    let plain = if sealed.version == LEGACY_OP_VERSION {
+        open(&key, &iv, &data, &stamp(&sealed.id))
+    } else {
+        open_committed(&key, &iv, &data, &stamp(&sealed.id))
+    };
+    key.zeroize();
+
+    let mut plain = plain?;
+    let change = serde_json::from_slice(&plain).map_err(|e| redact::json_error("Sync parse", &e));
+    plain.zeroize();
+    Ok(Op { id: sealed.id.clone(), change: change? })
+}
+
+/// AAD for a sealed op: lamport (u64 BE) || device ID.
+fn stamp(id: &OpId) -> Vec<u8> {
+    let mut aad = id.lamport.to_be_bytes().to_vec();
+    aad.extend_from_slice(id.device.as_bytes());
+    aad
+}
+
+fn entry_object(entry: &VaultEntry) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(entry).map_err(|e| format!("Sync serialize error: {}", e))? {
+        Value::Object(map) => Ok(map),
+        _ => Err("Sync serialize error: entry is not an object".to_string()),
+    }
+}
+
+/// The vault's top-level fields, without the entries.
+fn root_object(vault: &VaultData) -> Map<String, Value> {
+    let shell = VaultData {
+        entries: Vec::new(),
+        categories: vault.categories.clone(),
+        travel_mode_categories: vault.travel_mode_categories.clone(),
+        equivalent_domains: vault.equivalent_domains.clone(),
+        settings: vault.settings.clone(),
+        extra: vault.extra.clone(),
+    };
+    match serde_json::to_value(shell) {
+        Ok(Value::Object(mut map)) => {
+            map.remove("entries");
+            map
+        }
+        _ => Map::new(),
+    }
+}
+
+/// Fields whose value differs; a field only `old` has comes out as `null`.
+fn changed_fields(old: &Map<String, Value>, new: &Map<String, Value>) -> Map<String, Value> {
+    let mut changed = Map::new();
+    for (field, value) in new {
+        if old.get(field) != Some(value) {
+            changed.insert(field.clone(), value.clone());
+        }
+    }
+    for field in old.keys().filter(|f| !new.contains_key(*f)) {
+        changed.insert(field.clone(), Value::Null);
+    }
+    changed
+}
+
+fn set_field(object: &mut Map<String, Value>, field: &str, value: &Value) {
+    match value {
+        Value::Null => object.remove(field),
+        value => object.insert(field.to_string(), value.clone()),
+    };
+}
+
+/// The entry with `fields` applied, or None if the result isn't a valid entry.
+/// The ID is never changed by an update.
+fn apply_fields(entry: &VaultEntry, fields: &Map<String, Value>) -> Option<VaultEntry> {
+    let mut object = entry_object(entry).ok()?;
+    for (field, value) in fields.iter().filter(|(f, _)| f.as_str() != "id") {
+        set_field(&mut object, field, value);
+    }
+    let mut value = Value::Object(object);
+    let updated = serde_json::from_value(value.clone()).ok();
+    wipe_value(&mut value);
+    updated
+}
+
+pub(crate) fn wipe_change(change: &mut Change) {
+    match change {
+        Change::Add { entry } => entry.wipe(),
+        Change::Update { fields, .. } => fields.values_mut().for_each(wipe_value),
+        Change::Delete { .. } => {}
+        Change::SetVault { value, .. } => wipe_value(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn entry(id: &str, title: &str, password: &str) -> VaultEntry {
+        VaultEntry { id: id.to_string(), title: title.to_string(), password: password.to_string(), ..Default::default() }
+    }
+
+    fn edit(log: &mut OpLog, change: impl FnOnce(&mut VaultData)) -> Vec<Op> {
+        let before = log.materialize();
+        let mut after = before.clone();
+        change(&mut after);
+        log.record(diff(&before, &after).unwrap())
+    }
+
+    fn all_ops(log: &OpLog) -> Vec<Op> {
+        log.ops.iter().map(|(id, change)| Op { id: id.clone(), change: change.clone() }).collect()
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let mut a = OpLog::new("device-a").unwrap();
+        let shared = edit(&mut a, |v| {
+            v.entries.push(entry("1", "GitHub", "old"));
+            v.entries.push(entry("2", "Bank", "pin"));
+        });
+        let mut b = OpLog::new("device-b").unwrap();
+        b.merge(shared);
+
+        // Offline: A renames and deletes, B changes the password and adds a folder.
+        let from_a = edit(&mut a, |v| {
+            v.entries[0].title = "GitHub (work)".to_string();
+            v.entries.retain(|e| e.id != "2");
+        });
+        let from_b = edit(&mut b, |v| {
+            v.entries[0].password = "new".to_string();
+            v.entries[1].notes = "too late".to_string();
+            v.categories.push("work".to_string());
+        });
+        assert_eq!(a.merge(from_b.clone()), from_b.len());
+        assert_eq!(b.merge(from_a.clone()), from_a.len());
+        assert_eq!(a.merge(from_b), 0);
+
+        let (va, vb) = (a.materialize(), b.materialize());
+        assert_eq!(va, vb);
+        assert_eq!(va.entries.len(), 1);
+        assert_eq!((va.entries[0].title.as_str(), va.entries[0].password.as_str()), ("GitHub (work)", "new"));
+        assert_eq!(va.categories, vec!["work"]);
+        assert!(a.clock() >= 3 && a.clock() == b.clock());
+    }
+
+    #[test]
+    fn test_same_field_conflict_and_removal() {
+        let mut a = OpLog::new("a").unwrap();
+        let mut e = entry("1", "Mail", "x");
+        e.totp_secret = Some("JBSWY3DP".to_string());
+        let shared = edit(&mut a, |v| v.entries.push(e));
+        let mut b = OpLog::new("b").unwrap();
+        b.merge(shared);
+
+        let from_a = edit(&mut a, |v| v.entries[0].password = "from-a".to_string());
+        let from_b = edit(&mut b, |v| {
+            v.entries[0].password = "from-b".to_string();
+            v.entries[0].totp_secret = None;
+        });
+        a.merge(from_b);
+        b.merge(from_a);
+
+        // Same clock, so the device ID breaks the tie: "b" sorts after "a".
+        let vault = a.materialize();
+        assert_eq!(vault, b.materialize());
+        assert_eq!(vault.entries[0].password, "from-b");
+        assert_eq!(vault.entries[0].totp_secret, None);
+    }
+
+    #[test]
+    fn test_sealed_ops_roundtrip_and_bind_stamp() {
+        let master = [5u8; 32];
+        let mut log = OpLog::new("a").unwrap();
+        let ops = edit(&mut log, |v| v.entries.push(entry("1", "Site", "secret")));
+        let sealed = seal_op(&master, &ops[0]).unwrap();
+        assert!(!serde_json::to_string(&sealed).unwrap().contains("secret"));
+        assert_eq!(open_op(&master, &sealed).unwrap(), ops[0]);
+
+        let mut moved = sealed.clone();
+        moved.id.lamport += 1;
+        assert!(open_op(&master, &moved).is_err());
+        assert!(open_op(&[6u8; 32], &sealed).is_err());
+        assert!(open_op(&master, &SealedOp { version: LEGACY_OP_VERSION, ..sealed.clone() }).is_err());
+        assert!(OpLog::new("").is_err());
+        assert!(OpLog::new("../a").is_err());
+    }
+
+    #[test]
+    fn test_rebase_drops_covered_ops() {
+        let mut a = OpLog::new("a").unwrap();
+        let from_a = edit(&mut a, |v| v.entries.push(entry("1", "One", "p")));
+        let snapshot = a.snapshot();
+        assert_eq!((snapshot.number, snapshot.covered.get("a")), (1, Some(&1)));
+
+        let mut b = OpLog::new("b").unwrap();
+        b.merge(from_a.clone());
+        edit(&mut b, |v| v.entries[0].title = "Uno".to_string());
+        let before = b.materialize();
+        assert!(b.rebase(snapshot.clone()));
+        assert_eq!((b.len(), b.materialize()), (1, before));
+        assert_eq!(b.merge(from_a), 0);
+
+        assert!(a.rebase(snapshot.clone()));
+        assert!(a.is_empty() && !a.rebase(snapshot));
+        assert_eq!(a.materialize().entries[0].title, "One");
+    }
+
+    #[test]
+    fn test_rebase_mid_log_converges_with_full_replay() {
+        let mut a = OpLog::new("a").unwrap();
+        let mut ops = edit(&mut a, |v| v.entries.push(entry("1", "One", "p")));
+        let mut b = OpLog::new("b").unwrap();
+        b.merge(ops.clone());
+
+        // A deletes the entry while B, offline, re-adds it under the same stamp;
+        // the delete sorts first, so the re-add is dropped.
+        ops.extend(edit(&mut a, |v| v.entries.clear()));
+        let readd = b.record(vec![Change::Add { entry: Box::new(entry("1", "One again", "q")) }]);
+        ops.extend(readd.clone());
+        ops.extend(edit(&mut b, |v| v.categories.push("misc".to_string())));
+
+        let mut everything = OpLog::new("c").unwrap();
+        everything.merge(ops.clone());
+        assert!(everything.materialize().entries.is_empty());
+
+        // A folds its delete into a snapshot before B's ops arrive.
+        let snapshot = a.snapshot();
+        assert!(snapshot.deleted.contains("1"));
+        assert!(a.rebase(snapshot.clone()));
+        a.merge(ops.clone());
+        assert_eq!(a.materialize(), everything.materialize());
+
+        // A replica that takes the snapshot halfway through B's ops, and B itself.
+        let mut replica = OpLog::new("d").unwrap();
+        replica.merge(ops[..2].to_vec());
+        replica.merge(readd);
+        assert!(replica.rebase(snapshot.clone()));
+        replica.merge(ops.clone());
+        assert!(b.rebase(snapshot));
+        b.merge(ops);
+        assert_eq!(replica.materialize(), everything.materialize());
+        assert_eq!(b.materialize(), everything.materialize());
+        // The next snapshot keeps the tombstone.
+        assert!(replica.snapshot().deleted.contains("1"));
+    }
+
+    #[test]
+    fn test_clock_cannot_be_pushed_to_the_end() {
+        let mut a = OpLog::new("a").unwrap();
+        let stamped = |lamport| Op { id: OpId { lamport, device: "b".to_string() }, change: Change::Delete { entry_id: "1".to_string() } };
+        assert_eq!(a.merge(vec![stamped(u64::MAX), stamped(MAX_STAMP + 1)]), 0);
+        assert_eq!(a.clock(), 0);
+        assert_eq!(a.merge(vec![stamped(MAX_STAMP)]), 1);
+        let ops = edit(&mut a, |v| v.categories.push("misc".to_string()));
+        assert_eq!(ops[0].id.lamport, MAX_STAMP + 1);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_merge_order_does_not_matter(order in Just((0..6usize).collect::<Vec<_>>()).prop_shuffle()) {
+            let mut a = OpLog::new("a").unwrap();
+            let mut ops = edit(&mut a, |v| v.entries.push(entry("1", "One", "p1")));
+            ops.extend(edit(&mut a, |v| v.entries.push(entry("2", "Two", "p2"))));
+            let mut b = OpLog::new("b").unwrap();
+            b.merge(ops.clone());
+            ops.extend(edit(&mut b, |v| v.entries[0].title = "Uno".to_string()));
+            ops.extend(edit(&mut b, |v| v.entries.retain(|e| e.id != "2")));
+            ops.extend(edit(&mut a, |v| v.entries[1].url = "https://two.example".to_string()));
+            ops.extend(edit(&mut a, |v| v.categories.push("misc".to_string())));
+            prop_assert_eq!(ops.len(), 6);
+
+            let mut replica = OpLog::new("c").unwrap();
+            for i in order {
+                replica.merge(vec![ops[i].clone()]);
+            }
+            let mut everything = OpLog::new("d").unwrap();
+            everything.merge(all_ops(&a));
+            everything.merge(all_ops(&b));
+            prop_assert_eq!(replica.materialize(), everything.materialize());
+        }
+    }
+}
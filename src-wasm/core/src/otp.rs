@@ -0,0 +1,434 @@
+// --- TOTP Configuration ---
+// An entry's `totpSecret` is either a bare Base32 secret or a full
+// otpauth://totp/... URI. This module turns both into one typed config
+// (secret + algorithm + digits + period) that exporters and generators share.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, Secret, TOTP};
+use url::Url;
+
+use crate::redact::Redacted;
+use crate::vault::VaultEntry;
+
+/// How far 'estimate_skew' looks either way, in seconds. A clock more than a day
+/// off is better fixed than corrected for.
+pub const MAX_SKEW_SECS: u64 = 24 * 3600;
+
+/// The time to compute codes for: the device's clock `now` corrected by a skew
+/// (seconds to add; positive when the device is behind). Store the skew from
+/// 'estimate_skew', or take trusted time minus device time, e.g. from a server's
+/// Date header.
+pub fn corrected_time(now: u64, skew_secs: i64) -> u64 {
+    now.saturating_add_signed(skew_secs)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OtpAlgorithm {
+    #[default]
+    SHA1,
+    SHA256,
+    SHA512,
+}
+
+impl OtpAlgorithm {
+    pub fn parse(name: &str) -> Result<OtpAlgorithm, String> {
+        match name.to_ascii_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(OtpAlgorithm::SHA1),
+            "SHA256" => Ok(OtpAlgorithm::SHA256),
+            "SHA512" => Ok(OtpAlgorithm::SHA512),
+            other => Err(format!("Unsupported TOTP algorithm: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpAlgorithm::SHA1 => "SHA1",
+            OtpAlgorithm::SHA256 => "SHA256",
+            OtpAlgorithm::SHA512 => "SHA512",
+        }
+    }
+}
+
+/// Everything needed to compute codes for one account. Debug output redacts the secret.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct TotpConfig {
+    /// Canonical Base32: uppercase, no spaces, no padding.
+    pub secret: String,
+    pub issuer: String,
+    pub account: String,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+impl fmt::Debug for TotpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpConfig")
+            .field("secret", &Redacted(&self.secret))
+            .field("issuer", &self.issuer)
+            .field("account", &self.account)
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .finish()
+    }
+}
+
+impl TotpConfig {
+    /// Parses a bare Base32 secret or an otpauth://totp/ URI, as pasted: quotes or
+    /// angle brackets copied along with it are ignored.
+    pub fn parse(raw: &str) -> Result<TotpConfig, String> {
+        let raw = raw.trim().trim_matches(|c| matches!(c, '"' | '\'' | '`' | '<' | '>')).trim();
+        if raw.to_ascii_lowercase().starts_with("otpauth://") {
+            return Self::parse_uri(raw);
+        }
+        Ok(TotpConfig {
+            secret: canonical_base32(raw)?,
+            issuer: String::new(),
+            account: String::new(),
+            algorithm: OtpAlgorithm::SHA1,
+            digits: 6,
+            period: 30,
+        })
+    }
+
+    /// The entry's TOTP config, with issuer/account taken from the entry when the secret lacks them.
+    /// Returns None when the entry has no TOTP secret at all.
+    pub fn from_entry(entry: &VaultEntry) -> Option<Result<TotpConfig, String>> {
+        let raw = entry.totp_secret.as_deref().filter(|s| !s.trim().is_empty())?;
+        Some(Self::parse(raw).map(|mut config| {
+            if config.issuer.is_empty() {
+                config.issuer = entry.title.clone();
+            }
+            if config.account.is_empty() {
+                config.account = entry.username.clone();
+            }
+            config
+        }))
+    }
+
+    fn parse_uri(raw: &str) -> Result<TotpConfig, String> {
+        let url = Url::parse(raw).map_err(|e| format!("otpauth URI parse error: {}", e))?;
+        if !url.host_str().is_some_and(|h| h.eq_ignore_ascii_case("totp")) {
+            return Err("Only otpauth://totp/ URIs are supported".to_string());
+        }
+
+        // The label is "Issuer:account" (percent-encoded); either half may be missing.
+        let label = percent_decode(url.path().trim_start_matches('/'));
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (issuer.trim().to_string(), account.trim().to_string()),
+            None => (String::new(), label.trim().to_string()),
+        };
+
+        let mut config = TotpConfig {
+            secret: String::new(),
+            issuer: label_issuer,
+            account,
+            algorithm: OtpAlgorithm::SHA1,
+            digits: 6,
+            period: 30,
+        };
+
+        for (key, value) in url.query_pairs() {
+            match key.to_ascii_lowercase().as_str() {
+                "secret" => config.secret = canonical_base32(&value)?,
+                // The query parameter wins over the label prefix, per the Key URI spec.
+                "issuer" => config.issuer = value.trim().to_string(),
+                "algorithm" => config.algorithm = OtpAlgorithm::parse(&value)?,
+                "digits" => {
+                    config.digits = value.parse().map_err(|_| format!("Invalid digits: {}", value))?
+                }
+                "period" => {
+                    config.period = value.parse().map_err(|_| format!("Invalid period: {}", value))?
+                }
+                _ => {}
+            }
+        }
+
+        if config.secret.is_empty() {
+            return Err("otpauth URI has no secret".to_string());
+        }
+        config.check_params()?;
+        Ok(config)
+    }
+
+    /// This config with other code parameters, for services whose setup page lists
+    /// them next to a bare secret (e.g. SHA256, 8 digits).
+    pub fn with_params(mut self, algorithm: OtpAlgorithm, digits: u32, period: u64) -> Result<TotpConfig, String> {
+        (self.algorithm, self.digits, self.period) = (algorithm, digits, period);
+        self.check_params()?;
+        Ok(self)
+    }
+
+    fn check_params(&self) -> Result<(), String> {
+        if !(6..=8).contains(&self.digits) {
+            return Err(format!("Unsupported digit count: {}", self.digits));
+        }
+        if self.period == 0 {
+            return Err("Period must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// The otpauth://totp/ URI for this config, which keeps non-default parameters intact.
+    pub fn to_uri(&self) -> String {
+        let label = if self.issuer.is_empty() {
+            self.account.clone()
+        } else {
+            format!("{}:{}", self.issuer, self.account)
+        };
+        let mut url = Url::parse("otpauth://totp/").expect("static URI is valid");
+        url.set_path(&label);
+        url.query_pairs_mut()
+            .append_pair("secret", &self.secret)
+            .append_pair("issuer", &self.issuer)
+            .append_pair("algorithm", self.algorithm.as_str())
+            .append_pair("digits", &self.digits.to_string())
+            .append_pair("period", &self.period.to_string());
+        url.to_string()
+    }
+
+    /// The code for Unix time `now`, with the config's algorithm, digits and period.
+    /// Accepts 80-bit secrets, which are common in the wild.
+    pub fn code_at(&self, now: u64) -> Result<String, String> {
+        let secret = Secret::Encoded(self.secret.clone())
+            .to_bytes()
+            .map_err(|e| format!("TOTP bytes error: {}", e))?;
+        let algorithm = match self.algorithm {
+            OtpAlgorithm::SHA1 => Algorithm::SHA1,
+            OtpAlgorithm::SHA256 => Algorithm::SHA256,
+            OtpAlgorithm::SHA512 => Algorithm::SHA512,
+        };
+        Ok(TOTP::new_unchecked(algorithm, self.digits as usize, 1, self.period, secret).generate(now))
+    }
+
+    /// The skew (a whole number of periods, in seconds) at which `code`, one the
+    /// service just accepted or another device shows, is this config's code,
+    /// searching outward from no skew up to MAX_SKEW_SECS either way. None if no
+    /// step matches. With six digits a wrong code still matches some step about
+    /// once in 170 tries, so only use codes known to be good.
+    pub fn estimate_skew(&self, code: &str, now: u64) -> Result<Option<i64>, String> {
+        let code = code.trim().replace(' ', "");
+        if code.len() != self.digits as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("Expected a {}-digit code", self.digits));
+        }
+        let period = self.period as i64;
+        for step in 0..=(MAX_SKEW_SECS / self.period) as i64 {
+            let skews = [step * period, -step * period];
+            for &skew in skews.iter().take(if step == 0 { 1 } else { 2 }) {
+                if now.checked_add_signed(skew).is_some() && self.code_at(corrected_time(now, skew))? == code {
+                    return Ok(Some(skew));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Uppercases, strips whitespace, dashes and padding, and checks the result decodes.
+/// Secrets are pasted from setup pages in every shape ("jbsw y3dp", "JBSW-Y3DP",
+/// "JBSWY3DP===="); all of those are the same secret.
+pub fn canonical_base32(raw: &str) -> Result<String, String> {
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '=' | '-' | '\u{2010}'..='\u{2015}'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if cleaned.is_empty() {
+        return Err("TOTP secret is empty".to_string());
+    }
+    let mut invalid: Vec<char> = cleaned.chars().filter(|c| !matches!(c, 'A'..='Z' | '2'..='7')).collect();
+    if !invalid.is_empty() {
+        invalid.dedup();
+        let listed: Vec<String> = invalid.iter().take(5).map(|c| format!("'{}'", c)).collect();
+        let mut message = format!("TOTP secret is not Base32: it contains {}", listed.join(", "));
+        if invalid.iter().any(|c| matches!(c, '0' | '1' | '8')) {
+            message.push_str(" (Base32 has no 0, 1 or 8: they are usually O, I and B)");
+        }
+        return Err(message);
+    }
+    Secret::Encoded(cleaned.clone())
+        .to_bytes()
+        .map_err(|e| format!("TOTP bytes error: {}", e))?;
+    Ok(cleaned)
+}
+
+/// Encodes raw bytes as canonical Base32.
+pub fn encode_base32(bytes: &[u8]) -> String {
+    Secret::Raw(bytes.to_vec())
+        .to_encoded()
+        .to_string()
+        .trim_end_matches('=')
+        .to_string()
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() && bytes[i + 1..i + 3].iter().all(u8::is_ascii_hexdigit) {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_bare_secret() {
+        let config = TotpConfig::parse("jbsw y3dp ehpk 3pxp==").unwrap();
+        assert_eq!(config.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!((config.digits, config.period, config.algorithm), (6, 30, OtpAlgorithm::SHA1));
+        assert!(TotpConfig::parse("not base32!").is_err());
+    }
+
+    #[test]
+    fn test_tolerates_real_world_pastes() {
+        for pasted in ["JBSW-Y3DP-EHPK-3PXP", " \"jbswy3dpehpk3pxp\" ", "JBSWY3DP\u{a0}EHPK3PXP====", "<JBSWY3DPEHPK3PXP>"] {
+            assert_eq!(TotpConfig::parse(pasted).unwrap().secret, "JBSWY3DPEHPK3PXP", "{}", pasted);
+        }
+        let uri = "'OTPAUTH://TOTP/GitHub:octo?secret=jbsw%20y3dp%20ehpk%203pxp&digits=8'";
+        assert_eq!(TotpConfig::parse(uri).unwrap().secret, "JBSWY3DPEHPK3PXP");
+
+        let err = canonical_base32("JBSWY3DPEHPK3PX0").unwrap_err();
+        assert!(err.contains("'0'") && err.contains("usually O"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_uri() {
+        let uri = "otpauth://totp/ACME%20Co:john@example.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60";
+        let config = TotpConfig::parse(uri).unwrap();
+        assert_eq!(config.issuer, "ACME Co");
+        assert_eq!(config.account, "john@example.com");
+        assert_eq!(config.algorithm, OtpAlgorithm::SHA256);
+        assert_eq!((config.digits, config.period), (8, 60));
+
+        // An escape at the very end of the label still decodes.
+        let config = TotpConfig::parse("otpauth://totp/ACME:john%40example.co%6D?secret=JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(config.account, "john@example.com");
+        assert_eq!(percent_decode("50%"), "50%");
+        assert_eq!(percent_decode("a%4"), "a%4");
+        assert_eq!(percent_decode("%+1%21"), "%+1!");
+
+        assert!(TotpConfig::parse("otpauth://hotp/x?secret=JBSWY3DPEHPK3PXP").is_err());
+        assert!(TotpConfig::parse("otpauth://totp/x?issuer=y").is_err());
+    }
+
+    #[test]
+    fn test_from_entry_fills_label() {
+        let entry = VaultEntry {
+            title: "GitHub".to_string(),
+            username: "octo".to_string(),
+            totp_secret: Some("JBSWY3DPEHPK3PXP".to_string()),
+            ..Default::default()
+        };
+        let config = TotpConfig::from_entry(&entry).unwrap().unwrap();
+        assert_eq!((config.issuer.as_str(), config.account.as_str()), ("GitHub", "octo"));
+
+        assert!(TotpConfig::from_entry(&VaultEntry::default()).is_none());
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let config = TotpConfig {
+            secret: encode_base32(b"Hello!\xde\xad\xbe\xef"),
+            issuer: "ACME Co".to_string(),
+            account: "john@example.com".to_string(),
+            algorithm: OtpAlgorithm::SHA512,
+            digits: 8,
+            period: 60,
+        };
+        assert_eq!(config.secret, "JBSWY3DPEHPK3PXP");
+        assert_eq!(TotpConfig::parse(&config.to_uri()).unwrap(), config);
+    }
+
+    fn label_part() -> impl Strategy<Value = String> {
+        "([A-Za-z0-9][A-Za-z0-9._@-]{0,15})?"
+    }
+
+    #[test]
+    fn test_code_at_rfc6238() {
+        // RFC 6238 appendix B, SHA1, T = 59.
+        let mut config = TotpConfig::parse(&encode_base32(b"12345678901234567890")).unwrap();
+        assert_eq!(config.code_at(59).unwrap(), "287082");
+        config.digits = 8;
+        assert_eq!(config.code_at(59).unwrap(), "94287082");
+
+        // 80-bit secrets are common in the wild and must still work.
+        assert_eq!(TotpConfig::parse("JBSWY3DPEHPK3PXP").unwrap().code_at(0).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_rfc6238_vectors_for_every_algorithm() {
+        // RFC 6238 appendix B: 8 digits, 30 s, each algorithm with its own seed length.
+        let seed = |len: usize| encode_base32(&b"1234567890".repeat(7)[..len]);
+        let cases = [
+            (OtpAlgorithm::SHA1, seed(20), ["94287082", "07081804", "14050471", "89005924", "69279037", "65353130"]),
+            (OtpAlgorithm::SHA256, seed(32), ["46119246", "68084774", "67062674", "91819424", "90698825", "77737706"]),
+            (OtpAlgorithm::SHA512, seed(64), ["90693936", "25091201", "99943326", "93441116", "38618901", "47863826"]),
+        ];
+        let times = [59, 1_111_111_109, 1_111_111_111, 1_234_567_890, 2_000_000_000, 20_000_000_000];
+        for (algorithm, secret, codes) in cases {
+            let config = TotpConfig::parse(&secret).unwrap().with_params(algorithm, 8, 30).unwrap();
+            for (time, code) in times.iter().zip(codes) {
+                assert_eq!(config.code_at(*time).unwrap(), code, "{:?} at {}", algorithm, time);
+            }
+            let seven = config.clone().with_params(algorithm, 7, 30).unwrap();
+            assert_eq!(seven.code_at(59).unwrap(), codes[0][1..]);
+        }
+        let config = TotpConfig::parse("JBSWY3DPEHPK3PXP").unwrap();
+        assert!(config.clone().with_params(OtpAlgorithm::SHA1, 9, 30).is_err());
+        assert!(config.with_params(OtpAlgorithm::SHA1, 6, 0).is_err());
+    }
+
+    #[test]
+    fn test_estimate_skew() {
+        let config = TotpConfig::parse(&encode_base32(b"12345678901234567890")).unwrap();
+        let server_time = 1_111_111_109;
+        let code = config.code_at(server_time).unwrap();
+
+        // The device is 7 minutes slow; skew is counted in whole periods.
+        let device_time = server_time - 420;
+        let skew = config.estimate_skew(&code, device_time).unwrap().unwrap();
+        assert_eq!(skew, 420);
+        assert_eq!(config.code_at(corrected_time(device_time, skew)).unwrap(), code);
+        assert_eq!(config.estimate_skew(&code, server_time + 60).unwrap(), Some(-60));
+
+        assert!(config.estimate_skew("12345", device_time).is_err());
+        assert_eq!(corrected_time(10, -60), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_uri_roundtrip(
+            secret in proptest::collection::vec(any::<u8>(), 1..64),
+            issuer in label_part(),
+            account in label_part(),
+            algorithm in prop_oneof![Just(OtpAlgorithm::SHA1), Just(OtpAlgorithm::SHA256), Just(OtpAlgorithm::SHA512)],
+            digits in 6u32..=8,
+            period in 1u64..600,
+        ) {
+            let config = TotpConfig { secret: encode_base32(&secret), issuer, account, algorithm, digits, period };
+            prop_assert_eq!(TotpConfig::parse(&config.to_uri()).unwrap(), config);
+        }
+
+        #[test]
+        fn prop_parse_never_panics(raw in "(otpauth://totp/)?\\PC{0,80}") {
+            let _ = TotpConfig::parse(&raw);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// --- Authenticator Exports ---
+// Writes every TOTP seed in the vault to the encrypted backup formats of two
+// popular authenticator apps, so users can move 2FA into a dedicated app:
+//
+// * Aegis (vault version 1, database version 2): scrypt password slot wrapping a
+//   random master key, AES-256-GCM over the JSON database. Tags are stored apart
+//   from the ciphertext, and all binary fields are hex except `db` (Base64).
+// * andOTP (password backup, .json.aes): iterations (u32 BE) || salt (12) ||
+//   nonce (12) || AES-256-GCM(ciphertext + tag), key = PBKDF2-HMAC-SHA1.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, Rng, RngCore};
+use serde_json::json;
+use sha1::Sha1;
+use zeroize::Zeroize;
+
+use crate::keys::seal;
+use crate::otp::TotpConfig;
+use crate::vault::{hex, new_entry_id};
+
+/// Aegis' own default scrypt cost (N = 2^15, r = 8, p = 1).
+pub const AEGIS_SCRYPT_LOG_N: u8 = 15;
+
+/// andOTP picks a random PBKDF2 iteration count in this range.
+const ANDOTP_ITERATIONS: std::ops::Range<u32> = 140_000..160_000;
+
+const GCM_TAG_LEN: usize = 16;
+
+/// Aegis encrypted vault JSON, readable by Aegis' "Import from file".
+pub fn to_aegis(configs: &[TotpConfig], password: &str) -> Result<String, String> {
+    to_aegis_with_cost(configs, password, AEGIS_SCRYPT_LOG_N)
+}
+
+pub(crate) fn to_aegis_with_cost(configs: &[TotpConfig], password: &str, log_n: u8) -> Result<String, String> {
+    let entries: Vec<_> = configs
+        .iter()
+        .map(|c| {
+            json!({
+                "type": "totp",
+                "uuid": new_entry_id(),
+                "name": c.account,
+                "issuer": c.issuer,
+                "note": "",
+                "favorite": false,
+                "icon": null,
+                "info": {
+                    "secret": c.secret,
+                    "algo": c.algorithm.as_str(),
+                    "digits": c.digits,
+                    "period": c.period,
+                },
+            })
+        })
+        .collect();
+    let mut db = serde_json::to_vec(&json!({ "version": 2, "entries": entries }))
+        .map_err(|e| format!("Aegis serialize error: {}", e))?;
+
+    // The database is sealed with a random master key...
+    let mut master_key = [0u8; 32];
+    OsRng.fill_bytes(&mut master_key);
+    let (db_nonce, db_sealed) = seal(&master_key, &db, &[])?;
+    db.zeroize();
+    let (db_ct, db_tag) = db_sealed.split_at(db_sealed.len() - GCM_TAG_LEN);
+
+    // ...and the master key is sealed with scrypt(password) in a password slot.
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let mut slot_key = [0u8; 32];
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|e| format!("scrypt params error: {}", e))?;
+    scrypt::scrypt(password.as_bytes(), &salt, &params, &mut slot_key)
+        .map_err(|e| format!("scrypt error: {}", e))?;
+    let (key_nonce, key_sealed) = seal(&slot_key, &master_key, &[])?;
+    slot_key.zeroize();
+    master_key.zeroize();
+    let (key_ct, key_tag) = key_sealed.split_at(key_sealed.len() - GCM_TAG_LEN);
+
+    let vault = json!({
+        "version": 1,
+        "header": {
+            "slots": [{
+                "type": 1,
+                "uuid": new_entry_id(),
+                "key": hex(key_ct),
+                "key_params": { "nonce": hex(&key_nonce), "tag": hex(key_tag) },
+                "n": 1u32 << log_n,
+                "r": 8,
+                "p": 1,
+                "salt": hex(&salt),
+                "repaired": true,
+            }],
+            "params": { "nonce": hex(&db_nonce), "tag": hex(db_tag) },
+        },
+        "db": B64.encode(db_ct),
+    });
+    serde_json::to_string_pretty(&vault).map_err(|e| format!("Aegis serialize error: {}", e))
+}
+
+/// andOTP encrypted backup bytes (save as "otp_accounts.json.aes").
+pub fn to_andotp(configs: &[TotpConfig], password: &str) -> Result<Vec<u8>, String> {
+    to_andotp_with_cost(configs, password, rand::thread_rng().gen_range(ANDOTP_ITERATIONS))
+}
+
+pub(crate) fn to_andotp_with_cost(configs: &[TotpConfig], password: &str, iterations: u32) -> Result<Vec<u8>, String> {
+    let entries: Vec<_> = configs
+        .iter()
+        .map(|c| {
+            let label = if c.issuer.is_empty() { c.account.clone() } else { format!("{}:{}", c.issuer, c.account) };
+            json!({
+                "secret": c.secret,
+                "issuer": c.issuer,
+                "label": label,
+                "digits": c.digits,
+                "type": "TOTP",
+                "algorithm": c.algorithm.as_str(),
+                "thumbnail": "Default",
+                "last_used": 0,
+                "used_frequency": 0,
+                "period": c.period,
+                "tags": [],
+            })
+        })
+        .collect();
+    let mut plain = serde_json::to_vec(&entries).map_err(|e| format!("andOTP serialize error: {}", e))?;
+
+    let mut salt = [0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), &salt, iterations, &mut key);
+    let (nonce, sealed) = seal(&key, &plain, &[])?;
+    key.zeroize();
+    plain.zeroize();
+
+    let mut out = Vec::with_capacity(4 + 12 + 12 + sealed.len());
+    out.extend_from_slice(&iterations.to_be_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::otp::OtpAlgorithm;
+    use aes_gcm::{
+        aead::{Aead, KeyInit},
+        Aes256Gcm,
+    };
+    use serde_json::Value;
+
+    fn configs() -> Vec<TotpConfig> {
+        vec![TotpConfig {
+            secret: "JBSWY3DPEHPK3PXP".to_string(),
+            issuer: "GitHub".to_string(),
+            account: "octo".to_string(),
+            algorithm: OtpAlgorithm::SHA256,
+            digits: 8,
+            period: 30,
+        }]
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn open(key: &[u8], nonce: &[u8], ct: &[u8], tag: &[u8]) -> Vec<u8> {
+        let mut sealed = ct.to_vec();
+        sealed.extend_from_slice(tag);
+        Aes256Gcm::new_from_slice(key).unwrap().decrypt(crate::validate::nonce(nonce).unwrap(), sealed.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_aegis_roundtrip() {
+        let exported: Value = serde_json::from_str(&to_aegis_with_cost(&configs(), "pw", 4).unwrap()).unwrap();
+        let slot = &exported["header"]["slots"][0];
+        assert_eq!(slot["n"], 16);
+
+        let mut slot_key = [0u8; 32];
+        let params = scrypt::Params::new(4, 8, 1, 32).unwrap();
+        scrypt::scrypt(b"pw", &unhex(slot["salt"].as_str().unwrap()), &params, &mut slot_key).unwrap();
+        let master_key = open(
+            &slot_key,
+            &unhex(slot["key_params"]["nonce"].as_str().unwrap()),
+            &unhex(slot["key"].as_str().unwrap()),
+            &unhex(slot["key_params"]["tag"].as_str().unwrap()),
+        );
+
+        let params = &exported["header"]["params"];
+        let db = open(
+            &master_key,
+            &unhex(params["nonce"].as_str().unwrap()),
+            &B64.decode(exported["db"].as_str().unwrap()).unwrap(),
+            &unhex(params["tag"].as_str().unwrap()),
+        );
+        let db: Value = serde_json::from_slice(&db).unwrap();
+        let info = &db["entries"][0]["info"];
+        assert_eq!(info["secret"], "JBSWY3DPEHPK3PXP");
+        assert_eq!(info["algo"], "SHA256");
+        assert_eq!(info["digits"], 8);
+        assert_eq!(db["entries"][0]["issuer"], "GitHub");
+    }
+
+    #[test]
+    fn test_andotp_roundtrip() {
+        let blob = to_andotp_with_cost(&configs(), "pw", 1_000).unwrap();
+        let iterations = u32::from_be_bytes(blob[0..4].try_into().unwrap());
+        assert_eq!(iterations, 1_000);
+
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha1>(b"pw", &blob[4..16], iterations, &mut key);
+        let body = &blob[28..];
+        let plain = open(&key, &blob[16..28], &body[..body.len() - 16], &body[body.len() - 16..]);
+
+        let entries: Value = serde_json::from_slice(&plain).unwrap();
+        assert_eq!(entries[0]["label"], "GitHub:octo");
+        assert_eq!(entries[0]["type"], "TOTP");
+        assert_eq!(entries[0]["algorithm"], "SHA256");
+    }
+}
@@ -0,0 +1,147 @@
+// --- Device Pairing ---
+// Lets two devices on the same network agree on a shared key from nothing
+// more than a 6-digit code shown on one screen and typed on the other, so LAN
+// sync works without accounts or a server. The transport (a WebRTC data
+// channel, a local socket) is the front end's job; this module is the crypto.
+//
+// 1. One device shows `generate_code()`; both call `Handshake::start(code)`.
+// 2. They swap `message()`s (SPAKE2, symmetric mode over Ed25519), and each
+//    calls `finish(theirs)`, which yields a confirmation tag to send.
+// 3. They swap tags; `confirm(theirs)` returns the 32-byte session key, or an
+//    error if the codes differed or someone in the middle tampered.
+//
+// An attacker on the network gets one guess of the code per handshake (a
+// 1-in-a-million chance), and nothing they observe lets them test guesses
+// offline. A failed handshake should end the attempt and show a new code.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, Rng};
+use sha2::Sha256;
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use zeroize::{Zeroize, Zeroizing};
+
+pub const CODE_DIGITS: usize = 6;
+
+/// Binds the exchange to this protocol; a SPAKE2 message meant for anything else won't finish here.
+const IDENTITY: &[u8] = b"securepass-pairing-v1";
+
+/// A fresh pairing code, e.g. "042917".
+pub fn generate_code() -> String {
+    format!("{:06}", OsRng.gen_range(0..1_000_000u32))
+}
+
+/// One device's side of the exchange, before it has seen the other's message.
+pub struct Handshake {
+    state: Spake2<Ed25519Group>,
+    outbound: Vec<u8>,
+}
+
+/// After both messages: holds the keys until the peer's confirmation tag checks out.
+pub struct PendingKeys {
+    session_key: Zeroizing<[u8; 32]>,
+    confirm_key: Zeroizing<[u8; 32]>,
+    outbound: Vec<u8>,
+    inbound: Vec<u8>,
+}
+
+impl Handshake {
+    /// Starts the exchange with the code as typed; spaces and dashes are ignored.
+    pub fn start(code: &str) -> Result<Handshake, String> {
+        let digits: String = code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        if digits.len() != CODE_DIGITS || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Pairing error: the code must be {} digits", CODE_DIGITS));
+        }
+        let (state, outbound) =
+            Spake2::<Ed25519Group>::start_symmetric(&Password::new(digits.as_bytes()), &Identity::new(IDENTITY));
+        Ok(Handshake { state, outbound })
+    }
+
+    /// The message to send to the other device.
+    pub fn message(&self) -> &[u8] {
+        &self.outbound
+    }
+
+    /// Takes the other device's message and derives the keys.
+    pub fn finish(self, inbound: &[u8]) -> Result<PendingKeys, String> {
+        // A reflected copy of our own message would otherwise "pair" us with ourselves.
+        if inbound == self.outbound.as_slice() {
+            return Err("Pairing error: received our own message back".to_string());
+        }
+        let mut shared = self.state.finish(inbound).map_err(|e| format!("Pairing error: {}", e))?;
+
+        let hkdf = Hkdf::<Sha256>::new(None, &shared);
+        shared.zeroize();
+        let mut session_key = Zeroizing::new([0u8; 32]);
+        let mut confirm_key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(b"securepass-pairing-session", session_key.as_mut_slice())
+            .and_then(|_| hkdf.expand(b"securepass-pairing-confirm", confirm_key.as_mut_slice()))
+            .map_err(|e| format!("HKDF error: {}", e))?;
+
+        Ok(PendingKeys { session_key, confirm_key, outbound: self.outbound, inbound: inbound.to_vec() })
+    }
+}
+
+impl PendingKeys {
+    /// The tag to send to the other device: HMAC over (our message, their message).
+    pub fn confirmation(&self) -> Result<Vec<u8>, String> {
+        Ok(transcript_mac(&self.confirm_key, &self.outbound, &self.inbound)?.finalize().into_bytes().to_vec())
+    }
+
+    /// Checks the other device's tag and releases the session key. A mismatch
+    /// almost always means the two codes were different.
+    pub fn confirm(self, their_confirmation: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+        // Their tag covers the same two messages in their order.
+        transcript_mac(&self.confirm_key, &self.inbound, &self.outbound)?
+            .verify_slice(their_confirmation)
+            .map_err(|_| "Pairing error: the codes did not match; start again with a new code".to_string())?;
+        Ok(self.session_key)
+    }
+}
+
+fn transcript_mac(key: &[u8; 32], first: &[u8], second: &[u8]) -> Result<Hmac<Sha256>, String> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).map_err(|e| format!("HMAC error: {}", e))?;
+    mac.update(first);
+    mac.update(second);
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Outcome = Result<Zeroizing<[u8; 32]>, String>;
+
+    fn pair(code_a: &str, code_b: &str) -> (Outcome, Outcome) {
+        let (a, b) = (Handshake::start(code_a).unwrap(), Handshake::start(code_b).unwrap());
+        let (msg_a, msg_b) = (a.message().to_vec(), b.message().to_vec());
+        let (keys_a, keys_b) = (a.finish(&msg_b).unwrap(), b.finish(&msg_a).unwrap());
+        let (tag_a, tag_b) = (keys_a.confirmation().unwrap(), keys_b.confirmation().unwrap());
+        (keys_a.confirm(&tag_b), keys_b.confirm(&tag_a))
+    }
+
+    #[test]
+    fn test_matching_codes_agree_on_a_key() {
+        let code = generate_code();
+        assert_eq!(code.len(), CODE_DIGITS);
+        let (a, b) = pair(&code, &format!("{}-{}", &code[..3], &code[3..]));
+        assert_eq!(*a.unwrap(), *b.unwrap());
+    }
+
+    #[test]
+    fn test_different_codes_fail_confirmation() {
+        let (a, b) = pair("123456", "123457");
+        assert!(a.unwrap_err().contains("did not match"));
+        assert!(b.is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_codes_and_reflection() {
+        assert!(Handshake::start("12345").is_err());
+        assert!(Handshake::start("12345a").is_err());
+
+        let a = Handshake::start("123456").unwrap();
+        let reflected = a.message().to_vec();
+        assert!(a.finish(&reflected).is_err());
+    }
+}
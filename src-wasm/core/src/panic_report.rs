@@ -0,0 +1,67 @@
+// --- Panic Reports ---
+// What a front end learns when the engine panics. Public APIs return errors
+// rather than panic, so a panic is a bug, and the report is for the bug tracker:
+// a stable code and the source line, never the panic message, because an
+// `expect`, an assert or an index out of range can format a secret into it.
+//
+// The code is a hash of the full source location, so the same bug reported by
+// two users has the same code, and the location is cut down to the file name so
+// no build machine path ends up in a report.
+
+use std::panic::{Location, PanicHookInfo};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The message every report carries in place of the panic's own.
+pub const MESSAGE: &str = "Internal error in the SecurePass engine";
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PanicReport {
+    /// "SP-" and 8 hex digits.
+    pub code: String,
+    pub message: String,
+    /// "generator.rs:52", or "" if the panic had no location.
+    pub location: String,
+}
+
+impl PanicReport {
+    /// The report for a panic hook's `info`; its payload is ignored.
+    pub fn from_hook(info: &PanicHookInfo<'_>) -> PanicReport {
+        info.location().map_or_else(|| PanicReport::with_location(""), PanicReport::at)
+    }
+
+    pub fn at(location: &Location<'_>) -> PanicReport {
+        let file = location.file().rsplit(['/', '\\']).next().unwrap_or_default();
+        let mut report = PanicReport::with_location(&format!("{}:{}", file, location.line()));
+        let digest = Sha256::digest(format!("{}:{}", location.file(), location.line()));
+        report.code = format!("SP-{}", digest[..4].iter().map(|b| format!("{:02X}", b)).collect::<String>());
+        report
+    }
+
+    fn with_location(location: &str) -> PanicReport {
+        PanicReport { code: "SP-00000000".to_string(), message: MESSAGE.to_string(), location: location.to_string() }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!(r#"{{"code":"{}"}}"#, self.code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_names_the_line_not_the_path() {
+        let here = Location::caller();
+        let report = PanicReport::at(here);
+        assert_eq!(report.location, format!("panic_report.rs:{}", here.line()));
+        assert!(report.code.starts_with("SP-") && report.code.len() == 11);
+        assert_eq!(PanicReport::at(here), report);
+        assert_ne!(PanicReport::at(Location::caller()).code, report.code);
+
+        let json = report.to_json();
+        assert!(json.contains(r#""message":"Internal error in the SecurePass engine""#) && !json.contains("src/"), "{}", json);
+    }
+}
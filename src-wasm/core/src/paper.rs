@@ -0,0 +1,251 @@
+// --- Paper Backup ---
+// Renders key material (a master key, an emergency secret key, an age identity)
+// as text meant to be printed, stored in a safe, and typed back in by hand:
+//
+//   SECUREPASS PAPER BACKUP V1
+//   01  7ZQ3 M0KD 9F2A XW4T HV8R 1CNE  5K0G
+//   02  ...
+//   CHECK  A1B2 C3D4
+//
+// Data is Crockford Base32 (no I, L, O or U), 24 characters (15 bytes) per line
+// in groups of four. Each line ends with a CRC-16 of its line number and bytes,
+// so a typo is pinned to one line, and a line typed twice or skipped is caught.
+// CHECK is the start of SHA-256 over everything, the final say on the result.
+//
+// Reading is strict about structure but forgiving about the characters people
+// actually confuse: case is ignored, O reads as 0, and I or L read as 1.
+
+use sha2::{Digest, Sha256};
+
+pub const HEADER: &str = "SECUREPASS PAPER BACKUP V1";
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CHARS_PER_LINE: usize = 24;
+const BYTES_PER_LINE: usize = 15;
+const GROUP: usize = 4;
+const CHECK_BYTES: usize = 5;
+
+/// The printable form of `key_material`, ending in a newline.
+pub fn encode(key_material: &[u8]) -> Result<String, String> {
+    if key_material.is_empty() {
+        return Err("Paper error: nothing to back up".to_string());
+    }
+    let lines = key_material.chunks(BYTES_PER_LINE).count();
+    let width = lines.to_string().len().max(2);
+
+    let mut out = format!("{}\n", HEADER);
+    for (i, chunk) in key_material.chunks(BYTES_PER_LINE).enumerate() {
+        let number = i + 1;
+        out.push_str(&format!(
+            "{:0width$}  {}  {}\n",
+            number,
+            grouped(&base32(chunk)),
+            base32(&line_crc(number, chunk).to_be_bytes()),
+            width = width
+        ));
+    }
+    out.push_str(&format!("CHECK  {}\n", grouped(&base32(&overall_check(key_material)))));
+    Ok(out)
+}
+
+/// Reads a transcribed backup back into the key material. Errors name the line to recheck.
+pub fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let header = lines.next().ok_or("Paper error: backup is empty")?;
+    let fold = |s: &str| {
+        s.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase().replace(['I', 'L'], "1").replace('O', "0")
+    };
+    if fold(header) != fold(HEADER) {
+        return Err(format!("Paper error: expected the header '{}'", HEADER));
+    }
+
+    let mut material = Vec::new();
+    let mut last_line_short = false;
+    let mut number = 0;
+    while let Some(line) = lines.next() {
+        number += 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens[0].eq_ignore_ascii_case("CHECK") {
+            if number == 1 {
+                return Err("Paper error: no data lines before CHECK".to_string());
+            }
+            let expected = overall_check(&material);
+            if unbase32(&tokens[1..].concat(), "the CHECK line")? != expected {
+                return Err("Paper error: the CHECK line does not match; recheck every line".to_string());
+            }
+            return match lines.next() {
+                None => Ok(material),
+                Some(_) => Err("Paper error: unexpected text after the CHECK line".to_string()),
+            };
+        }
+
+        let at = format!("line {}", number);
+        if tokens.len() < 3 {
+            return Err(format!("Paper error: {} is incomplete", at));
+        }
+        let numbered: usize = normalize(tokens[0], &at)?
+            .parse()
+            .map_err(|_| format!("Paper error: {} does not start with a line number", at))?;
+        if numbered != number {
+            return Err(format!("Paper error: expected line {} but found line {}", number, numbered));
+        }
+        if last_line_short {
+            return Err(format!("Paper error: line {} is too short; only the last line may be", number - 1));
+        }
+
+        let data = tokens[1..tokens.len() - 1].concat();
+        if data.len() > CHARS_PER_LINE {
+            return Err(format!("Paper error: {} has {} characters, expected {}", at, data.len(), CHARS_PER_LINE));
+        }
+        let bytes = unbase32(&data, &at)?;
+        let crc = unbase32(tokens[tokens.len() - 1], &at)?;
+        if crc != line_crc(number, &bytes).to_be_bytes() {
+            return Err(format!("Paper error: {} has a typo (checksum mismatch)", at));
+        }
+        last_line_short = data.len() < CHARS_PER_LINE;
+        material.extend_from_slice(&bytes);
+    }
+    Err("Paper error: the CHECK line is missing".to_string())
+}
+
+/// Uppercases and fixes the look-alikes Crockford Base32 leaves out on purpose.
+fn normalize(token: &str, at: &str) -> Result<String, String> {
+    token
+        .chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            'O' => Ok('0'),
+            'I' | 'L' => Ok('1'),
+            c if c.is_ascii() && ALPHABET.contains(&(c as u8)) => Ok(c),
+            c => Err(format!("Paper error: {} has an unexpected character '{}'", at, c)),
+        })
+        .collect()
+}
+
+fn grouped(chars: &str) -> String {
+    chars.as_bytes().chunks(GROUP).map(|g| std::str::from_utf8(g).unwrap_or_default()).collect::<Vec<_>>().join(" ")
+}
+
+/// Crockford Base32 without padding; also the frame encoding of `qr_transfer`.
+pub(crate) fn base32(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[usize::from((buffer >> bits) & 31)] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(ALPHABET[usize::from((buffer << (5 - bits)) & 31)] as char);
+    }
+    out
+}
+
+/// Exact inverse of `base32`: uppercase alphabet only, and leftover bits must be zero.
+pub(crate) fn base32_decode(chars: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(chars.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for c in chars.bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u16;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    (bits < 5 && buffer == 0).then_some(out)
+}
+
+fn unbase32(chars: &str, at: &str) -> Result<Vec<u8>, String> {
+    base32_decode(&normalize(chars, at)?)
+        .ok_or_else(|| format!("Paper error: {} has a missing or extra character", at))
+}
+
+/// CRC over the line number (u16 BE) and the line's bytes.
+fn line_crc(number: usize, bytes: &[u8]) -> u16 {
+    crc16((number as u16).to_be_bytes().iter().chain(bytes))
+}
+
+/// CRC-16/CCITT-FALSE.
+fn crc16<'a>(bytes: impl Iterator<Item = &'a u8>) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in bytes {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn overall_check(material: &[u8]) -> [u8; CHECK_BYTES] {
+    let digest = Sha256::digest(material);
+    let mut check = [0u8; CHECK_BYTES];
+    check.copy_from_slice(&digest[..CHECK_BYTES]);
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn key() -> Vec<u8> {
+        (0u8..32).map(|i| i.wrapping_mul(37).wrapping_add(11)).collect()
+    }
+
+    #[test]
+    fn test_layout_and_roundtrip() {
+        let text = encode(&key()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], HEADER);
+        assert!(lines[1].starts_with("01  ") && lines[1].len() == 2 + 2 + 29 + 2 + 4);
+        assert!(lines[4].starts_with("CHECK  "));
+        assert_eq!(decode(&text).unwrap(), key());
+        assert_eq!(crc16(b"123456789".iter()), 0x29B1);
+        assert!(encode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_corrects_look_alikes() {
+        let text = encode(&key()).unwrap();
+        let sloppy = text.to_lowercase().replace('0', "o").replace('1', "l");
+        assert_eq!(decode(&format!("\n  {}\n\n", sloppy)).unwrap(), key());
+    }
+
+    #[test]
+    fn test_pins_errors_to_a_line() {
+        let text = encode(&key()).unwrap();
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+        let mut typo = lines.clone();
+        let swapped = if typo[2].as_bytes()[4] == b'A' { "B" } else { "A" };
+        typo[2].replace_range(4..5, swapped);
+        assert!(decode(&typo.join("\n")).unwrap_err().contains("line 2 has a typo"));
+
+        let mut bad_char = lines.clone();
+        bad_char[3].replace_range(4..5, "U");
+        assert!(decode(&bad_char.join("\n")).unwrap_err().contains("line 3 has an unexpected character 'U'"));
+
+        let mut skipped = lines.clone();
+        skipped.remove(2);
+        assert!(decode(&skipped.join("\n")).unwrap_err().contains("expected line 2 but found line 3"));
+
+        lines.pop();
+        assert!(decode(&lines.join("\n")).unwrap_err().contains("CHECK line is missing"));
+        assert!(decode("SECUREPASS PAPER BACKUP V2\n").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_roundtrip(material in proptest::collection::vec(any::<u8>(), 1..200)) {
+            prop_assert_eq!(decode(&encode(&material).unwrap()).unwrap(), material);
+        }
+    }
+}
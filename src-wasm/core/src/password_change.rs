@@ -0,0 +1,100 @@
+// --- Master Password Change ---
+// The master key is wrapped (see 'kdf'), so a new password means a new key
+// envelope and nothing else: no entry, segment or attachment is re-encrypted.
+// What does change is everything that holds or checks the password itself: the
+// envelope (salt, KDF settings, verifier and wrapped key) and the biometric
+// wrappings, which seal the password under each passkey (see 'biometric').
+//
+// They are produced together and must be saved together, replacing the old
+// ones in one write. Saving only the envelope leaves Touch ID unlocking with a
+// password that no longer works; saving only the wrappings, the reverse.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::biometric;
+use crate::kdf::{KdfSettings, KeyEnvelope};
+use crate::master_policy;
+use crate::text::PasswordPolicy;
+
+/// The new password sealed for one passkey. Byte arrays, like the arguments of
+/// 'biometric::unwrap_password'.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BiometricWrap {
+    /// Base64 WebAuthn credential ID, as passed in.
+    pub credential_id: String,
+    pub iv: Vec<u8>,
+    pub wrapped: Vec<u8>,
+}
+
+/// Everything to save, in one write, in place of what it replaces.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChange {
+    pub envelope: KeyEnvelope,
+    pub biometric: Vec<BiometricWrap>,
+}
+
+/// Wraps `master_key` under `new_password` (which must pass 'master_policy') with
+/// `salt` and `kdf`, and re-seals the new password for each Base64 credential ID.
+pub fn change_password(
+    master_key: &[u8; 32],
+    new_password: &str,
+    salt: &[u8],
+    kdf: KdfSettings,
+    credential_ids: &[String],
+) -> Result<PasswordChange, String> {
+    let violations = master_policy::validate_master_password(new_password, &[]);
+    if !violations.is_empty() {
+        let messages: Vec<String> = violations.iter().map(|v| v.message()).collect();
+        return Err(format!("Password change error: {}", messages.join("; ")));
+    }
+    if kdf.is_outdated() {
+        return Err(format!("Password change error: {} is weaker than {}", kdf.label(), KdfSettings::CURRENT.label()));
+    }
+
+    let envelope = KeyEnvelope::wrap_with_salt(master_key, new_password, salt, PasswordPolicy::CURRENT, kdf)?;
+    let biometric = credential_ids
+        .iter()
+        .map(|id| {
+            let credential = B64.decode(id).map_err(|e| format!("Credential ID decode error: {}", e))?;
+            let mut bio_key = biometric::derive_bio_key(&credential)?;
+            let mut iv = [0u8; 12];
+            OsRng.fill_bytes(&mut iv);
+            let wrapped = biometric::wrap_password(new_password, &bio_key, &iv);
+            bio_key.zeroize();
+            Ok(BiometricWrap { credential_id: id.clone(), iv: iv.to_vec(), wrapped: wrapped? })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(PasswordChange { envelope, biometric })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW: &str = "Tangerine-Harbor-42-Lamp";
+    const MASTER: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_new_password_opens_the_same_key() {
+        let credential = B64.encode(b"passkey-1");
+        let change = change_password(&MASTER, NEW, &[9u8; 16], KdfSettings::CURRENT, &[credential]).unwrap();
+
+        assert_eq!(change.envelope.unlock(NEW).unwrap(), MASTER);
+        let wrap = &change.biometric[0];
+        let bio_key = biometric::derive_bio_key(b"passkey-1").unwrap();
+        assert_eq!(biometric::unwrap_password(&wrap.wrapped, &bio_key, &wrap.iv).unwrap(), NEW);
+    }
+
+    #[test]
+    fn test_weak_choices_are_refused() {
+        let weak = change_password(&MASTER, "password", &[9u8; 16], KdfSettings::CURRENT, &[]);
+        assert!(weak.unwrap_err().starts_with("Password change error"));
+        assert!(change_password(&MASTER, NEW, &[9u8; 16], KdfSettings::LEGACY, &[]).is_err());
+        assert!(change_password(&MASTER, NEW, &[9u8; 8], KdfSettings::CURRENT, &[]).unwrap_err().contains("salt"));
+    }
+}
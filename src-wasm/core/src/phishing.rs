@@ -0,0 +1,221 @@
+// --- Phishing Detection ---
+// Before autofill, compare the page's host against every host saved in the vault.
+// A page that *almost* matches a saved site is far more dangerous than one that
+// doesn't match at all, so we look for three kinds of near-miss:
+//   1. Homoglyphs: "pаypal.com" with a Cyrillic 'а' (shown as xn--pypal-4ve.com).
+//   2. Typos: "paypa1.com" or "paypall.com" (small edit distance).
+//   3. Embedding: "paypal.com.account-check.io" or "paypal-login.com".
+
+use serde::{Deserialize, Serialize};
+
+use crate::autofill::parse_loose;
+use crate::domains::{public_suffix, registrable_domain_of_host, EquivalentDomains};
+
+/// How worried the extension should be, from "fine" to "block autofill".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningLevel {
+    /// The page doesn't resemble anything in the vault.
+    None,
+    /// The page is a saved site (or a subdomain of one).
+    Trusted,
+    /// Looks a lot like a saved site: typo distance or the saved name embedded in the host.
+    Suspicious,
+    /// Renders the same as a saved site but is a different host. Almost certainly phishing.
+    Danger,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PhishingVerdict {
+    pub level: WarningLevel,
+    /// The saved host the page was compared against when it raised a warning.
+    pub lookalike_of: Option<String>,
+    /// The page host in Unicode, so the UI can show what the user actually sees.
+    pub display_host: String,
+    pub reason: String,
+}
+
+/// Compares `page_url` against every saved URL and returns the most serious finding.
+pub fn check(page_url: &str, saved_urls: &[&str], equivalents: &EquivalentDomains) -> Result<PhishingVerdict, String> {
+    let page = parse_loose(page_url).ok_or("Page URL parse error")?;
+    let page_host = normalize_host(page.host_str().ok_or("Page URL has no host")?);
+    let (display_host, _) = idna::domain_to_unicode(&page_host);
+
+    let mut verdict = PhishingVerdict {
+        level: WarningLevel::None,
+        lookalike_of: None,
+        display_host: display_host.clone(),
+        reason: "No similar saved site".to_string(),
+    };
+
+    for saved in saved_urls.iter().filter_map(|u| parse_loose(u)) {
+        let Some(saved_host) = saved.host_str().map(normalize_host) else {
+            continue;
+        };
+        let (level, reason) = compare(&page_host, &display_host, &saved_host, equivalents);
+
+        // Trusted beats any warning: if the page *is* a saved site, other near-misses don't matter.
+        if level == WarningLevel::Trusted {
+            return Ok(PhishingVerdict {
+                level,
+                lookalike_of: None,
+                display_host,
+                reason,
+            });
+        }
+        if level > verdict.level {
+            verdict.level = level;
+            verdict.lookalike_of = Some(saved_host);
+            verdict.reason = reason;
+        }
+    }
+    Ok(verdict)
+}
+
+fn compare(page_host: &str, page_unicode: &str, saved_host: &str, equivalents: &EquivalentDomains) -> (WarningLevel, String) {
+    if let (Some(page), Some(saved)) = (registrable_domain_of_host(page_host), registrable_domain_of_host(saved_host)) {
+        if equivalents.are_equivalent(&page, &saved) {
+            return (WarningLevel::Trusted, "Matches a saved site".to_string());
+        }
+    }
+
+    let (saved_unicode, _) = idna::domain_to_unicode(saved_host);
+    if skeleton(page_unicode) == skeleton(&saved_unicode) {
+        return (WarningLevel::Danger, format!("Looks identical to {} but is a different site", saved_host));
+    }
+
+    // Compare the part people read: the host minus the public suffix-ish tail.
+    let page_name = site_label(page_host);
+    let saved_name = site_label(saved_host);
+    if saved_name.len() >= 4 {
+        let distance = levenshtein(&skeleton(page_name), &skeleton(saved_name));
+        let allowed = if saved_name.len() >= 8 { 2 } else { 1 };
+        if distance > 0 && distance <= allowed {
+            return (WarningLevel::Suspicious, format!("One or two characters away from {}", saved_host));
+        }
+
+        let labels: Vec<&str> = page_host.split('.').collect();
+        let embeds_label = labels[..labels.len().saturating_sub(2)].contains(&saved_name)
+            || page_name.split('-').any(|part| part == saved_name);
+        if page_name != saved_name && embeds_label {
+            return (WarningLevel::Suspicious, format!("Contains {} but belongs to another domain", saved_name));
+        }
+    }
+
+    (WarningLevel::None, "No similar saved site".to_string())
+}
+
+/// Lowercase, drop the trailing dot and a leading "www.".
+fn normalize_host(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host.strip_prefix("www.").map(str::to_string).unwrap_or(host)
+}
+
+/// The label left of the public suffix: "login.paypal.co.uk" -> "paypal", "paypal.com" -> "paypal".
+fn site_label(host: &str) -> &str {
+    let suffix_len = public_suffix(host).map_or(0, |s| s.len() + 1);
+    let without_suffix = &host[..host.len().saturating_sub(suffix_len)];
+    without_suffix.rsplit('.').next().unwrap_or(host)
+}
+
+/// Maps characters that render alike to one representative, so "pаypal" and "paypal" collide.
+/// Based on the most abused entries of Unicode's confusables.txt plus common ASCII swaps.
+pub fn skeleton(text: &str) -> String {
+    let folded: String = text
+        .chars()
+        .map(|c| match c {
+            'а' | 'α' | 'ɑ' => 'a',
+            'Ь' | 'ь' | 'Ƅ' => 'b',
+            'с' | 'ϲ' | 'ⅽ' => 'c',
+            'ԁ' | 'ⅾ' => 'd',
+            'е' | 'ё' | 'ε' | 'ҽ' => 'e',
+            'ɡ' | 'ց' => 'g',
+            'һ' | 'հ' => 'h',
+            'і' | 'ι' | 'ɩ' | 'ı' | '1' | '!' | '|' | 'ӏ' | 'ⅼ' | 'I' | 'l' => 'l',
+            'ј' | 'ϳ' => 'j',
+            'κ' | 'к' => 'k',
+            'м' => 'm',
+            'п' | 'ո' => 'n',
+            'о' | 'ο' | 'օ' | '0' | 'σ' => 'o',
+            'р' | 'ρ' => 'p',
+            'ԛ' => 'q',
+            'г' => 'r',
+            'ѕ' | '5' | '$' => 's',
+            'т' | 'τ' => 't',
+            'υ' | 'ս' => 'u',
+            'ν' | 'ѵ' => 'v',
+            'ԝ' | 'ѡ' => 'w',
+            'х' | 'χ' => 'x',
+            'у' | 'γ' => 'y',
+            'ᴢ' => 'z',
+            other => other.to_lowercase().next().unwrap_or(other),
+        })
+        .collect();
+    // Multi-character lookalikes.
+    folded.replace("rn", "m").replace("vv", "w").replace("cl", "d")
+}
+
+/// Classic edit distance (insert, delete, substitute), counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVED: [&str; 3] = ["https://www.paypal.com", "github.com", "https://accounts.google.co.uk"];
+
+    #[test]
+    fn test_trusted_and_unrelated() {
+        assert_eq!(check("https://paypal.com/signin", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Trusted);
+        assert_eq!(check("https://gist.github.com", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Trusted);
+        assert_eq!(check("https://example.org", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::None);
+    }
+
+    #[test]
+    fn test_homoglyph_is_danger() {
+        // Cyrillic 'а' in place of the first Latin 'a'; url parsing turns it into punycode.
+        let verdict = check("https://pаypal.com/login", &SAVED, &EquivalentDomains::default()).unwrap();
+        assert_eq!(verdict.level, WarningLevel::Danger);
+        assert_eq!(verdict.lookalike_of.as_deref(), Some("paypal.com"));
+        assert_eq!(verdict.display_host, "pаypal.com");
+
+        assert_eq!(check("https://paypa1.com", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Danger);
+    }
+
+    #[test]
+    fn test_typos_and_embedding_are_suspicious() {
+        assert_eq!(check("https://paypall.com", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Suspicious);
+        assert_eq!(check("https://g1thub.com", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Suspicious);
+        assert_eq!(check("https://paypal-login.com", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Suspicious);
+        assert_eq!(check("https://paypal.com.verify.io", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Suspicious);
+        assert_eq!(check("https://gooogle.co.uk", &SAVED, &EquivalentDomains::default()).unwrap().level, WarningLevel::Suspicious);
+    }
+
+    #[test]
+    fn test_equivalent_domains_are_trusted() {
+        let eq = EquivalentDomains::from_groups(vec![vec!["paypal.com".to_string(), "paypal.de".to_string()]]).unwrap();
+        assert_eq!(check("https://paypal.de", &SAVED, &eq).unwrap().level, WarningLevel::Trusted);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+}
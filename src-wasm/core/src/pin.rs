@@ -0,0 +1,179 @@
+// --- PIN Unlock ---
+// A short PIN unlocks the vault on a device that has already been unlocked
+// with the master password once. The master key is wrapped under a key derived
+// from the PIN and a random 32-byte device pepper:
+//   * the pepper is generated here, kept only on this device (never synced or
+//     exported), and makes the wrapped key useless anywhere else: a 4-digit PIN
+//     can't be brute-forced offline without it.
+//   * the lock counts wrong PINs and erases the wrapped key after `max_attempts`
+//     in a row, so guessing through the app ends in "use your master password".
+// Persist the lock after every unlock call, successful or not. The counter
+// protects the app, not a copied disk: whoever has both the lock and the pepper
+// can guess offline, which is why the pepper belongs in the device's safest store.
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::keys::{open, open_committed, seal_committed};
+
+pub const PEPPER_LEN: usize = 32;
+pub const MIN_PIN_LEN: usize = 4;
+/// Wrong PINs allowed before the wrapped key is erased, unless the app picks another limit.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// The wrapped key is committed to the PIN key ('keys::open_committed'), so no
+/// lock can be built to open under more than one PIN. Version 1 locks predate that.
+const FORMAT_VERSION: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+/// AAD for the wrapped key, so it can't be confused with any other sealed blob.
+const AAD: &[u8] = b"securepass-pin-v1";
+
+/// The persisted PIN state. Not secret on its own: without the device pepper the
+/// wrapped key can't be attacked, even with the PIN space being tiny.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PinLock {
+    pub version: u8,
+    pub iv: String,
+    /// The wrapped master key; empty once the lock has been destroyed.
+    pub data: String,
+    pub failed_attempts: u32,
+    pub max_attempts: u32,
+}
+
+/// A fresh device pepper. Store it on this device only.
+pub fn generate_pepper() -> [u8; PEPPER_LEN] {
+    let mut pepper = [0u8; PEPPER_LEN];
+    OsRng.fill_bytes(&mut pepper);
+    pepper
+}
+
+impl PinLock {
+    /// Wraps `master_key` under the PIN and pepper. `max_attempts` is at least 1.
+    pub fn create(master_key: &[u8; 32], pin: &str, pepper: &[u8], max_attempts: u32) -> Result<PinLock, String> {
+        if pin.chars().count() < MIN_PIN_LEN {
+            return Err(format!("PIN error: the PIN must be at least {} characters", MIN_PIN_LEN));
+        }
+        if max_attempts == 0 {
+            return Err("PIN error: at least one attempt must be allowed".to_string());
+        }
+        let key = pin_key(pin, pepper)?;
+        let (iv, data) = seal_committed(&key, master_key, AAD)?;
+        Ok(PinLock { version: FORMAT_VERSION, iv: B64.encode(iv), data: B64.encode(data), failed_attempts: 0, max_attempts })
+    }
+
+    /// True once too many wrong PINs have erased the wrapped key.
+    pub fn is_destroyed(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn attempts_left(&self) -> u32 {
+        if self.is_destroyed() {
+            return 0;
+        }
+        self.max_attempts.saturating_sub(self.failed_attempts)
+    }
+
+    /// Unwraps the master key. A wrong PIN counts against the limit, and the one
+    /// that reaches it erases the wrapped key for good.
+    pub fn unlock(&mut self, pin: &str, pepper: &[u8]) -> Result<[u8; 32], String> {
+        if ![LEGACY_VERSION, FORMAT_VERSION].contains(&self.version) {
+            return Err(format!("Unsupported PIN lock version: {}", self.version));
+        }
+        if self.attempts_left() == 0 {
+            self.destroy();
+            return Err("PIN error: PIN unlock is disabled after too many wrong PINs; unlock with the master password".to_string());
+        }
+
+        let iv = B64.decode(&self.iv).map_err(|e| format!("IV decode error: {}", e))?;
+        let data = B64.decode(&self.data).map_err(|e| format!("PIN lock decode error: {}", e))?;
+        let key = pin_key(pin, pepper)?;
+        let opened = if self.version == LEGACY_VERSION { open(&key, &iv, &data, AAD) } else { open_committed(&key, &iv, &data, AAD) };
+        let Ok(mut plain) = opened else {
+            self.failed_attempts = self.failed_attempts.saturating_add(1);
+            if self.attempts_left() == 0 {
+                self.destroy();
+                return Err("PIN error: wrong PIN; PIN unlock is now disabled, unlock with the master password".to_string());
+            }
+            return Err(format!("PIN error: wrong PIN; {} more before PIN unlock is disabled", self.attempts_left()));
+        };
+
+        let master_key = <[u8; 32]>::try_from(plain.as_slice()).map_err(|_| "PIN error: wrapped key has the wrong length".to_string());
+        plain.zeroize();
+        self.failed_attempts = 0;
+        master_key
+    }
+
+    /// Erases the wrapped key. The lock stays behind so the app can tell the user why.
+    pub fn destroy(&mut self) {
+        self.iv.zeroize();
+        self.data.zeroize();
+    }
+}
+
+/// Argon2id over the PIN, salted with the pepper. The pepper is the real secret;
+/// Argon2 only makes each guess expensive for someone who has stolen it too.
+fn pin_key(pin: &str, pepper: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+    if pepper.len() != PEPPER_LEN {
+        return Err(format!("PIN error: the device pepper must be {} bytes", PEPPER_LEN));
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(pin.as_bytes(), pepper, key.as_mut_slice())
+        .map_err(|e| format!("Argon2 error: {}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn test_unlock_needs_pin_and_pepper() {
+        let pepper = generate_pepper();
+        let mut lock = PinLock::create(&MASTER, "2468", &pepper, 3).unwrap();
+        assert_eq!(lock.unlock("2468", &pepper).unwrap(), MASTER);
+
+        assert!(lock.unlock("2468", &generate_pepper()).is_err());
+        assert_eq!(lock.attempts_left(), 2);
+        // A success resets the count.
+        assert_eq!(lock.unlock("2468", &pepper).unwrap(), MASTER);
+        assert_eq!(lock.failed_attempts, 0);
+
+        assert!(PinLock::create(&MASTER, "123", &pepper, 3).is_err());
+        assert!(PinLock::create(&MASTER, "1234", &pepper[..16], 3).is_err());
+    }
+
+    #[test]
+    fn test_legacy_locks_open_and_current_ones_stay_committed() {
+        let pepper = generate_pepper();
+        let (iv, data) = crate::keys::seal(&pin_key("2468", &pepper).unwrap(), &MASTER, AAD).unwrap();
+        let mut legacy =
+            PinLock { version: LEGACY_VERSION, iv: B64.encode(iv), data: B64.encode(data), failed_attempts: 0, max_attempts: 3 };
+        assert_eq!(legacy.unlock("2468", &pepper).unwrap(), MASTER);
+
+        let mut relabelled = PinLock { version: LEGACY_VERSION, ..PinLock::create(&MASTER, "2468", &pepper, 3).unwrap() };
+        assert!(relabelled.unlock("2468", &pepper).is_err());
+    }
+
+    #[test]
+    fn test_too_many_wrong_pins_destroy_the_lock() {
+        let pepper = generate_pepper();
+        let mut lock = PinLock::create(&MASTER, "2468", &pepper, 2).unwrap();
+        assert!(lock.unlock("0000", &pepper).unwrap_err().contains("1 more"));
+        assert!(lock.unlock("1111", &pepper).unwrap_err().contains("now disabled"));
+
+        assert!(lock.is_destroyed());
+        assert_eq!(lock.attempts_left(), 0);
+        assert!(lock.unlock("2468", &pepper).is_err());
+
+        // Survives a save and reload.
+        let reloaded: PinLock = serde_json::from_str(&serde_json::to_string(&lock).unwrap()).unwrap();
+        assert!(reloaded.is_destroyed());
+    }
+}
@@ -0,0 +1,184 @@
+// --- Proton Pass Import ---
+// Proton Pass's unencrypted export is one JSON file (data.json in the zip):
+//   {version, encrypted: false, vaults: {<share ID>: {name, items: [item]}}}
+//   item: {state (1 active, 2 trashed), aliasEmail, createTime, modifyTime,
+//          data: {type, metadata: {name, note}, extraFields: [{fieldName,
+//          type: "text" | "hidden" | "totp", data: {content}}], content}}
+// 'content' depends on the type:
+//   login       {itemEmail, itemUsername, password, urls, totpUri}
+//               (before 1.18: {username, password, urls, totpUri})
+//   creditCard  {cardholderName, number, verificationNumber, expirationDate (YYYY-MM), pin}
+//   wifi        {ssid, password, security}
+//   note        {}
+//   alias       {}, with the address in the item's 'aliasEmail'
+// Each vault becomes a folder. An alias becomes a login whose username is the
+// alias address, so it can be searched and filled; trashed items are skipped.
+// An export encrypted with PGP has to be decrypted first.
+
+use serde_json::Value;
+
+use crate::import::{Imported, Importer};
+use crate::vault::{new_entry_id, CustomField, FieldKind, VaultEntry};
+use crate::{card, templates, wifi};
+
+const TRASHED: u64 = 2;
+
+pub struct ProtonPass;
+
+impl Importer for ProtonPass {
+    fn import(&self, data: &str, now: u64) -> Result<Imported, String> {
+        parse_json(data, now)
+    }
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+/// Sets a template field by name.
+fn set_field(entry: &mut VaultEntry, name: &str, value: String) {
+    if let Some(field) = entry.fields.iter_mut().find(|f| f.name == name) {
+        field.value = value;
+    }
+}
+
+fn entry_from_item(item: &Value, now: u64) -> VaultEntry {
+    let data = &item["data"];
+    let content = &data["content"];
+    let title = text(&data["metadata"], "name");
+    let mut entry = match data["type"].as_str().unwrap_or_default() {
+        "creditCard" => {
+            let mut entry = templates::find("credit_card").map(|t| t.instantiate(&title, now)).unwrap_or_default();
+            // "2027-03" to "03/27".
+            let expiry = text(content, "expirationDate");
+            let expiry = match expiry.split_once('-') {
+                Some((year, month)) if year.len() == 4 => format!("{}/{}", month, &year[2..]),
+                _ => expiry,
+            };
+            set_field(&mut entry, card::CARDHOLDER_FIELD, text(content, "cardholderName"));
+            set_field(&mut entry, card::NUMBER_FIELD, text(content, "number"));
+            set_field(&mut entry, card::CVV_FIELD, text(content, "verificationNumber"));
+            set_field(&mut entry, card::EXPIRY_FIELD, expiry);
+            set_field(&mut entry, card::PIN_FIELD, text(content, "pin"));
+            entry
+        }
+        "wifi" => {
+            let mut entry = templates::find("wifi_router").map(|t| t.instantiate(&title, now)).unwrap_or_default();
+            set_field(&mut entry, wifi::SSID_FIELD, text(content, "ssid"));
+            set_field(&mut entry, wifi::PASSWORD_FIELD, text(content, "password"));
+            if let Some(security) = content["security"].as_str().filter(|s| !s.is_empty()) {
+                set_field(&mut entry, wifi::SECURITY_FIELD, security.to_string());
+            }
+            entry
+        }
+        "note" => VaultEntry { entry_type: "note".to_string(), ..Default::default() },
+        "alias" => VaultEntry { username: text(item, "aliasEmail"), ..Default::default() },
+        kind => {
+            let (email, username) = (text(content, "itemEmail"), text(content, "itemUsername"));
+            let username = [username, text(content, "username"), email.clone()].into_iter().find(|u| !u.is_empty());
+            let mut entry = VaultEntry {
+                username: username.unwrap_or_default(),
+                password: text(content, "password"),
+                url: content["urls"].get(0).and_then(Value::as_str).unwrap_or_default().to_string(),
+                totp_secret: content["totpUri"].as_str().filter(|t| !t.is_empty()).map(str::to_string),
+                ..Default::default()
+            };
+            if kind != "login" && !kind.is_empty() {
+                entry.fields.push(CustomField { name: "Type".to_string(), kind: FieldKind::Text, value: kind.to_string() });
+            }
+            if !email.is_empty() && email != entry.username {
+                entry.fields.push(CustomField { name: "Email".to_string(), kind: FieldKind::Email, value: email });
+            }
+            let more_urls = content["urls"].as_array().into_iter().flatten().skip(1).filter_map(Value::as_str);
+            let url_field = |url: &str| CustomField { name: "URL".to_string(), kind: FieldKind::Url, value: url.to_string() };
+            entry.fields.extend(more_urls.map(url_field));
+            entry
+        }
+    };
+    if entry.id.is_empty() {
+        entry.id = new_entry_id();
+    }
+    entry.title = title;
+    entry.notes = text(&data["metadata"], "note");
+    entry.last_modified = item["modifyTime"].as_u64().unwrap_or(now);
+
+    for field in data["extraFields"].as_array().into_iter().flatten() {
+        let value = field["data"]["content"].as_str().unwrap_or_default().to_string();
+        let kind = match field["type"].as_str() {
+            Some("hidden") | Some("totp") => FieldKind::Hidden,
+            _ => FieldKind::Text,
+        };
+        entry.fields.push(CustomField { name: text(field, "fieldName"), kind, value });
+    }
+    entry
+}
+
+/// Reads a Proton Pass JSON export.
+pub fn parse_json(json: &str, now: u64) -> Result<Imported, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| crate::redact::json_error("Proton Pass parse", &e))?;
+    if value["encrypted"].as_bool() == Some(true) {
+        return Err("Proton Pass import error: the export is encrypted; export again without a passphrase".to_string());
+    }
+    let vaults = value["vaults"].as_object().ok_or("Proton Pass import error: not a Proton Pass export")?;
+
+    let mut import = Imported::new("proton-pass");
+    for vault in vaults.values() {
+        let folder = text(vault, "name");
+        import.add_folder(&folder);
+        for item in vault["items"].as_array().into_iter().flatten() {
+            if item["state"].as_u64() == Some(TRASHED) {
+                continue;
+            }
+            let mut entry = entry_from_item(item, now);
+            entry.category = folder.clone();
+            entry.favorite = item["pinned"].as_bool().unwrap_or(false);
+            import.entries.push(entry);
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"{"version": "1.21.0", "encrypted": false, "vaults": {"share1": {"name": "Personal", "items": [
+        {"state": 1, "modifyTime": 1700000000, "pinned": true, "data": {"type": "login",
+            "metadata": {"name": "GitHub", "note": "work account"},
+            "extraFields": [{"fieldName": "Recovery", "type": "hidden", "data": {"content": "abcd-efgh"}}],
+            "content": {"itemEmail": "me@example.com", "itemUsername": "octo", "password": "pw",
+                        "urls": ["https://github.com", "https://gist.github.com"],
+                        "totpUri": "otpauth://totp/GH?secret=JBSWY3DPEHPK3PXP"}}},
+        {"state": 1, "aliasEmail": "shop.x1@passmail.net",
+            "data": {"type": "alias", "metadata": {"name": "Shop alias", "note": ""}, "content": {}}},
+        {"state": 1, "data": {"type": "creditCard", "metadata": {"name": "Visa", "note": ""},
+            "content": {"cardholderName": "Jo", "number": "4242424242424242", "verificationNumber": "123",
+                        "expirationDate": "2027-03"}}},
+        {"state": 2, "data": {"type": "note", "metadata": {"name": "Old", "note": "gone"}, "content": {}}}
+    ]}}}"#;
+
+    #[test]
+    fn test_items_by_type() {
+        let import = ProtonPass.import(EXPORT, 5).unwrap();
+        assert_eq!((import.entries.len(), import.folders.clone()), (3, vec!["Personal".to_string()]));
+
+        let github = &import.entries[0];
+        let login = (github.username.as_str(), github.url.as_str(), github.category.as_str());
+        assert_eq!(login, ("octo", "https://github.com", "Personal"));
+        assert_eq!((github.notes.as_str(), github.last_modified, github.favorite), ("work account", 1_700_000_000, true));
+        assert!(github.totp_secret.as_deref().unwrap().starts_with("otpauth://totp/"));
+        let fields: Vec<(&str, &str)> = github.fields.iter().map(|f| (f.name.as_str(), f.value.as_str())).collect();
+        assert_eq!(fields, vec![("Email", "me@example.com"), ("URL", "https://gist.github.com"), ("Recovery", "abcd-efgh")]);
+
+        let alias = &import.entries[1];
+        assert_eq!((alias.kind(), alias.username.as_str(), alias.last_modified), ("login", "shop.x1@passmail.net", 5));
+        let visa = &import.entries[2];
+        assert_eq!(visa.fields.iter().find(|f| f.name == card::EXPIRY_FIELD).unwrap().value, "03/27");
+    }
+
+    #[test]
+    fn test_encrypted_or_foreign_files_are_refused() {
+        assert!(ProtonPass.import(r#"{"encrypted": true}"#, 0).unwrap_err().contains("encrypted"));
+        assert!(ProtonPass.import(r#"{"entries": []}"#, 0).unwrap_err().contains("not a Proton Pass"));
+    }
+}
@@ -0,0 +1,223 @@
+// --- QR Transfer ---
+// Moves an already-encrypted vault or export between two devices with nothing
+// but a screen and a camera. The sender loops through a sequence of QR frames;
+// the receiver scans whatever it catches, in any order, and is done once it
+// holds slightly more frames than the payload has source symbols.
+//
+// The payload is RaptorQ-encoded (RFC 6330), a fountain code: every frame
+// carries one encoding symbol, source or repair, and any large-enough subset
+// rebuilds the data, so missed or blurred frames never have to come round
+// again. Each frame is Crockford Base32 text (QR alphanumeric mode) of:
+//
+//   version (1) || transfer ID (8) || RaptorQ OTI (12) || sequence (u32 BE) || packet
+//
+// The transfer ID is the start of SHA-256 over the payload. It keeps frames of
+// two transfers from mixing and is checked again on the reassembled bytes.
+// Nothing here encrypts: hand it the output of an encrypting export.
+
+use std::collections::BTreeSet;
+
+use raptorq::{Decoder, Encoder, EncodingPacket, ObjectTransmissionInformation};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::paper::{base32, base32_decode};
+
+pub const FRAME_PREFIX: &str = "SPQ:";
+pub const FRAME_VERSION: u8 = 1;
+
+/// Bytes of payload per frame; a frame is then about 700 alphanumeric characters.
+pub const SYMBOL_SIZE: u16 = 400;
+
+/// Refuses transfers a vault could never need, so a stray frame can't make the receiver allocate gigabytes.
+pub const MAX_TRANSFER_LEN: u64 = 16 * 1024 * 1024;
+
+const HEADER_LEN: usize = 1 + 8 + 12 + 4;
+
+/// What the receiver can show while scanning.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// Distinct frames accepted so far.
+    pub received: usize,
+    /// Frames the payload was cut into; a few more than this are usually enough.
+    pub source_frames: u64,
+    pub complete: bool,
+}
+
+/// Cuts `payload` into frames: every source frame, then `redundancy_percent`
+/// extra repair frames, for the sender to show in a loop.
+pub fn encode_frames(payload: &[u8], redundancy_percent: u32) -> Result<Vec<String>, String> {
+    if payload.is_empty() || payload.len() as u64 > MAX_TRANSFER_LEN {
+        return Err(format!("Transfer error: payload must be 1 to {} bytes", MAX_TRANSFER_LEN));
+    }
+    let encoder = Encoder::with_defaults(payload, SYMBOL_SIZE);
+    let config = encoder.get_config();
+    let per_block = source_frames(&config).div_ceil(u64::from(config.source_blocks()));
+    let repair_per_block = (per_block * u64::from(redundancy_percent)).div_ceil(100) as u32;
+
+    let id = transfer_id(payload);
+    Ok(encoder
+        .get_encoded_packets(repair_per_block)
+        .iter()
+        .enumerate()
+        .map(|(seq, packet)| {
+            let mut frame = Vec::with_capacity(HEADER_LEN + 4 + usize::from(SYMBOL_SIZE));
+            frame.push(FRAME_VERSION);
+            frame.extend_from_slice(&id);
+            frame.extend_from_slice(&config.serialize());
+            frame.extend_from_slice(&(seq as u32).to_be_bytes());
+            frame.extend_from_slice(&packet.serialize());
+            format!("{}{}", FRAME_PREFIX, base32(&frame))
+        })
+        .collect())
+}
+
+/// Collects scanned frames of one transfer until the payload is rebuilt.
+#[derive(Default)]
+pub struct FrameReceiver {
+    transfer: Option<Transfer>,
+    payload: Option<Vec<u8>>,
+}
+
+struct Transfer {
+    id: [u8; 8],
+    config: ObjectTransmissionInformation,
+    decoder: Decoder,
+    seen: BTreeSet<u32>,
+}
+
+impl FrameReceiver {
+    pub fn new() -> FrameReceiver {
+        FrameReceiver::default()
+    }
+
+    /// Feeds one scanned frame. Repeats are harmless; frames of another transfer are an error.
+    pub fn push(&mut self, frame: &str) -> Result<Progress, String> {
+        let bytes = frame
+            .strip_prefix(FRAME_PREFIX)
+            .and_then(base32_decode)
+            .filter(|b| b.len() > HEADER_LEN + 4)
+            .ok_or("Transfer error: not a SecurePass transfer frame")?;
+        if bytes[0] != FRAME_VERSION {
+            return Err(format!("Transfer error: unsupported frame version {}", bytes[0]));
+        }
+        let id: [u8; 8] = bytes[1..9].try_into().map_err(|_| "Transfer error: damaged frame")?;
+        let config_bytes: [u8; 12] = bytes[9..21].try_into().map_err(|_| "Transfer error: damaged frame")?;
+        let seq = u32::from_be_bytes(bytes[21..25].try_into().map_err(|_| "Transfer error: damaged frame")?);
+
+        if self.transfer.is_none() {
+            self.transfer = Some(Transfer::start(id, &config_bytes)?);
+        }
+        let transfer = self.transfer.as_mut().ok_or("Transfer error: no transfer")?;
+        if id != transfer.id || config_bytes != transfer.config.serialize() {
+            return Err("Transfer error: frame belongs to another transfer".to_string());
+        }
+        if self.payload.is_some() || !transfer.seen.insert(seq) {
+            return Ok(self.progress());
+        }
+
+        let packet = EncodingPacket::deserialize(&bytes[HEADER_LEN..]);
+        if usize::from(packet.payload_id().source_block_number()) >= usize::from(transfer.config.source_blocks())
+            || packet.data().len() != usize::from(transfer.config.symbol_size())
+        {
+            transfer.seen.remove(&seq);
+            return Err("Transfer error: damaged frame".to_string());
+        }
+        if let Some(payload) = transfer.decoder.decode(packet) {
+            if transfer_id(&payload) != transfer.id {
+                self.transfer = None;
+                return Err("Transfer error: reassembled data failed its checksum; rescan".to_string());
+            }
+            self.payload = Some(payload);
+        }
+        Ok(self.progress())
+    }
+
+    pub fn progress(&self) -> Progress {
+        match &self.transfer {
+            Some(t) => Progress {
+                received: t.seen.len(),
+                source_frames: source_frames(&t.config),
+                complete: self.payload.is_some(),
+            },
+            None => Progress { received: 0, source_frames: 0, complete: false },
+        }
+    }
+
+    /// The rebuilt payload, once `progress().complete`.
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+}
+
+impl Transfer {
+    /// Only accepts the parameters `encode_frames` itself would choose, which keeps
+    /// malformed headers away from the decoder.
+    fn start(id: [u8; 8], config_bytes: &[u8; 12]) -> Result<Transfer, String> {
+        let config = ObjectTransmissionInformation::deserialize(config_bytes);
+        let length = config.transfer_length();
+        if length == 0
+            || length > MAX_TRANSFER_LEN
+            || config != ObjectTransmissionInformation::with_defaults(length, SYMBOL_SIZE)
+        {
+            return Err("Transfer error: unsupported transfer parameters".to_string());
+        }
+        Ok(Transfer { id, config, decoder: Decoder::new(config), seen: BTreeSet::new() })
+    }
+}
+
+fn source_frames(config: &ObjectTransmissionInformation) -> u64 {
+    config.transfer_length().div_ceil(u64::from(config.symbol_size()))
+}
+
+fn transfer_id(payload: &[u8]) -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&Sha256::digest(payload)[..8]);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_rebuilds_from_any_subset_in_any_order() {
+        let data = payload(10_000);
+        let frames = encode_frames(&data, 50).unwrap();
+        assert_eq!(frames.len(), 25 + 13);
+        assert!(frames.iter().all(|f| f.starts_with(FRAME_PREFIX) && f[4..].chars().all(|c| c.is_ascii_alphanumeric())));
+
+        // Lose every third frame and scan the rest backwards, with a repeat.
+        let mut receiver = FrameReceiver::new();
+        let mut progress = receiver.push(&frames[0]).unwrap();
+        assert_eq!(progress, Progress { received: 1, source_frames: 25, complete: false });
+        assert_eq!(receiver.push(&frames[0]).unwrap().received, 1);
+        for frame in frames.iter().enumerate().rev().filter(|(i, _)| i % 3 != 1).map(|(_, f)| f) {
+            progress = receiver.push(frame).unwrap();
+            if progress.complete {
+                break;
+            }
+        }
+        assert!(progress.complete);
+        assert_eq!(receiver.payload().unwrap(), data.as_slice());
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_damaged_frames() {
+        let frames = encode_frames(&payload(1_000), 0).unwrap();
+        let other = encode_frames(&payload(999), 0).unwrap();
+        let mut receiver = FrameReceiver::new();
+        receiver.push(&frames[0]).unwrap();
+
+        assert!(receiver.push(&other[0]).unwrap_err().contains("another transfer"));
+        assert!(receiver.push("SPQ:not-base32").is_err());
+        assert!(receiver.push(&frames[1][..frames[1].len() - 8]).is_err());
+        assert!(FrameReceiver::new().push(&format!("{}{}", FRAME_PREFIX, base32(&[2u8; 40]))).is_err());
+        assert!(encode_frames(&[], 10).is_err());
+        assert_eq!(receiver.progress().received, 1);
+    }
+}
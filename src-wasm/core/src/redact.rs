@@ -0,0 +1,121 @@
+// --- Redaction ---
+// Keeps secrets out of text that leaves the engine by accident: error messages
+// (which front ends log and show) and `{:?}` output (which ends up in panics,
+// test failures and debug logs).
+//
+// Types holding secrets implement Debug by hand and print 'Redacted' in place of
+// the secret fields, so a debug dump still shows which fields are set. Parsing
+// JSON that holds secrets goes through 'json_error': serde quotes the offending
+// value when a string has the wrong type ("invalid type: string \"hunter2\",
+// expected a sequence"), and a history entry or note can be exactly that.
+
+use std::fmt;
+
+/// What a redacted value prints as.
+pub const REDACTED: &str = "[redacted]";
+
+/// Debug-formats as REDACTED whatever it wraps.
+pub struct Redacted<'a, T: ?Sized>(pub &'a T);
+
+impl<T: ?Sized> fmt::Debug for Redacted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// "<context> error: <serde message>" with every value serde quoted replaced by
+/// REDACTED. Field names and positions are kept; they say what to fix.
+pub fn json_error(context: &str, error: &serde_json::Error) -> String {
+    format!("{} error: {}", context, scrub_values(&error.to_string()))
+}
+
+/// Replaces the values in serde's "string \"...\"", "char '.'" and
+/// "variant `...`" phrases.
+fn scrub_values(message: &str) -> String {
+    const QUOTED: [(&str, char); 3] = [("string \"", '"'), ("char '", '\''), ("variant `", '`')];
+    let mut out = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some((at, prefix, close)) =
+        QUOTED.iter().filter_map(|&(prefix, close)| rest.find(prefix).map(|at| (at, prefix, close))).min_by_key(|&(at, ..)| at)
+    {
+        out.push_str(&rest[..at + prefix.len() - 1]);
+        let value = &rest[at + prefix.len()..];
+        // Serde writes the value with Debug escapes, so a quote inside it is preceded by a backslash.
+        let mut escaped = false;
+        let end = value.char_indices().find(|&(_, c)| {
+            let closes = c == close && !escaped;
+            escaped = c == '\\' && !escaped;
+            closes
+        });
+        out.push_str(REDACTED);
+        rest = end.map_or("", |(i, c)| &value[i + c.len_utf8()..]);
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::otp::TotpConfig;
+    use crate::vault::{VaultData, VaultEntry};
+    use crate::yubikey::{ChallengeResponseSlot, OtpSlot, YubiKeySecret};
+
+    const CANARY: &str = "CANARYSECRETQZ";
+
+    #[test]
+    fn test_debug_output_hides_secrets() {
+        let mut entry = VaultEntry {
+            id: "e1".to_string(),
+            title: "Bank".to_string(),
+            password: CANARY.to_string(),
+            notes: CANARY.to_string(),
+            totp_secret: Some(CANARY.to_string()),
+            history: vec![CANARY.to_string()],
+            yubikey: Some(YubiKeySecret::ChallengeResponse(ChallengeResponseSlot { secret: CANARY.to_string(), slot: 2 })),
+            ..Default::default()
+        };
+        entry.extra.insert("cardNumber".to_string(), CANARY.into());
+        let vault = VaultData { entries: vec![entry], ..Default::default() };
+        let totp = TotpConfig::parse(&format!("otpauth://totp/Bank:me?secret={}", "JBSWY3DPEHPK3PXP")).unwrap();
+        let otp = OtpSlot {
+            public_id: "vvcccc".to_string(),
+            private_id: CANARY.to_string(),
+            aes_key: CANARY.to_string(),
+            use_counter: 1,
+            session_counter: 0,
+        };
+
+        for dump in [format!("{:?}", vault), format!("{:#?}", totp), format!("{:?}", otp)] {
+            assert!(!dump.contains(CANARY) && !dump.contains("JBSWY3DP"), "{}", dump);
+            assert!(dump.contains(REDACTED));
+        }
+        assert!(format!("{:?}", vault).contains("title: \"Bank\""));
+        assert!(format!("{:?}", vault).contains("cardNumber"));
+    }
+
+    #[test]
+    fn test_parse_errors_hide_values() {
+        let attempts = [
+            format!(r#"{{"entries": [{{"id": "1", "title": "x", "history": "{}"}}]}}"#, CANARY),
+            format!(r#"{{"entries": [{{"id": "1", "title": "x", "yubikey": {{"mode": "{}"}}}}]}}"#, CANARY),
+            format!(r#"{{"entries": [{{"id": "1", "title": "x", "favorite": "{}\"{}"}}]}}"#, CANARY, CANARY),
+        ];
+        for json in attempts {
+            let error = VaultData::from_json(&json).unwrap_err();
+            assert!(error.starts_with("Vault parse error: ") && error.contains(REDACTED) && !error.contains(CANARY), "{}", error);
+        }
+        assert!(TotpConfig::parse(&format!("{}!", CANARY)).unwrap_err().find(CANARY).is_none());
+    }
+
+    #[test]
+    fn test_scrub_keeps_the_rest_of_the_message() {
+        assert_eq!(
+            scrub_values(r#"invalid type: string "a\"b", expected a sequence at line 1 column 9"#),
+            "invalid type: string [redacted], expected a sequence at line 1 column 9"
+        );
+        assert_eq!(scrub_values("unknown variant `x`, expected `otp`"), "unknown variant [redacted], expected `otp`");
+        assert_eq!(scrub_values("invalid type: char 'x'"), "invalid type: char [redacted]");
+        assert_eq!(scrub_values("missing field `id`"), "missing field `id`");
+    }
+}
@@ -0,0 +1,169 @@
+// --- Re-encryption Queue ---
+// Moves a segmented vault from one master key to another: after the KDF
+// settings are raised, the password changes or the cipher setup is replaced.
+// Re-sealing thousands of entries in one go would freeze the UI, so it happens
+// in small batches. Every segment records the ID of the key that sealed it,
+// which is how a batch finds what is still outdated; the queue itself only
+// holds the two key IDs and a count, so it can be saved with the vault after
+// each batch and picked up again after a restart. Meanwhile, seal new and
+// edited entries with the new key, and open the half-moved vault with
+// 'Reencryption::open_vault'.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hierarchy::key_id;
+use crate::segments::{self, Segment, SealedEntry, SegmentedVault, ROOT_PURPOSE};
+use crate::vault::VaultData;
+
+const FORMAT_VERSION: u8 = 1;
+
+/// Entries re-sealed per batch unless the app picks another size.
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// Progress of one re-encryption. Not secret: key IDs are one-way fingerprints.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Reencryption {
+    pub version: u8,
+    pub from_key_id: String,
+    pub to_key_id: String,
+    /// Segments moved so far; the vault root counts as one, each entry as one.
+    pub done: usize,
+    /// `done` plus what was still outdated after the last batch.
+    pub total: usize,
+}
+
+impl Reencryption {
+    /// Starts moving `sealed` from `old_key` to `new_key`.
+    pub fn start(old_key: &[u8; 32], new_key: &[u8; 32], sealed: &SegmentedVault) -> Result<Reencryption, String> {
+        segments::check_version(sealed)?;
+        if old_key == new_key {
+            return Err("Re-encryption error: the new key is the old key".to_string());
+        }
+        let to_key_id = key_id(new_key);
+        Ok(Reencryption {
+            version: FORMAT_VERSION,
+            from_key_id: key_id(old_key),
+            total: pending(sealed, &to_key_id),
+            to_key_id,
+            done: 0,
+        })
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done >= self.total
+    }
+
+    /// Re-seals up to `batch_size` outdated segments (the root first, then entries in
+    /// storage order) under `new_key`, each with the cipher it had. Returns how many were moved; 0 means finished.
+    /// On error nothing in the batch should be kept: save `sealed` only on success.
+    pub fn step(
+        &mut self,
+        old_key: &[u8; 32],
+        new_key: &[u8; 32],
+        sealed: &mut SegmentedVault,
+        batch_size: usize,
+    ) -> Result<usize, String> {
+        self.check_keys(old_key, new_key)?;
+        segments::check_version(sealed)?;
+
+        let mut moved = 0;
+        if batch_size > 0 && sealed.root.key_id != self.to_key_id {
+            let mut root: VaultData = segments::open_segment(old_key, ROOT_PURPOSE, b"", &sealed.root)?;
+            let resealed = segments::seal_segment_with(sealed.root.cipher, new_key, ROOT_PURPOSE, b"", &root);
+            root.wipe();
+            sealed.root = resealed?;
+            moved += 1;
+        }
+        let outdated = sealed.entries.iter_mut().filter(|e| is_outdated(e, &self.to_key_id));
+        for entry in outdated.take(batch_size - moved) {
+            let mut opened = segments::open_entry(old_key, entry)?;
+            let resealed = segments::seal_entry_with(entry.summary.cipher, new_key, &opened);
+            opened.wipe();
+            *entry = resealed?;
+            moved += 1;
+        }
+
+        self.done += moved;
+        self.total = self.done + pending(sealed, &self.to_key_id);
+        Ok(moved)
+    }
+
+    /// Opens a vault part-way through, each segment with the key that sealed it.
+    pub fn open_vault(&self, old_key: &[u8; 32], new_key: &[u8; 32], sealed: &SegmentedVault) -> Result<VaultData, String> {
+        self.check_keys(old_key, new_key)?;
+        segments::open_vault_with(sealed, |segment: &Segment| if segment.key_id == self.to_key_id { new_key } else { old_key })
+    }
+
+    fn check_keys(&self, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<(), String> {
+        if self.version != FORMAT_VERSION {
+            return Err(format!("Unsupported re-encryption version: {}", self.version));
+        }
+        if key_id(old_key) != self.from_key_id || key_id(new_key) != self.to_key_id {
+            return Err("Re-encryption error: these keys are not the ones this re-encryption was started with".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Both segments move together, but check both so a damaged record isn't skipped.
+fn is_outdated(entry: &SealedEntry, to_key_id: &str) -> bool {
+    entry.summary.key_id != to_key_id || entry.secrets.key_id != to_key_id
+}
+
+/// Segments in `sealed` not yet under the key `to_key_id`.
+fn pending(sealed: &SegmentedVault, to_key_id: &str) -> usize {
+    let root = usize::from(sealed.root.key_id != to_key_id);
+    root + sealed.entries.iter().filter(|e| is_outdated(e, to_key_id)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::VaultEntry;
+
+    const OLD: [u8; 32] = [1u8; 32];
+    const NEW: [u8; 32] = [2u8; 32];
+
+    fn vault(entries: usize) -> VaultData {
+        VaultData {
+            entries: (0..entries)
+                .map(|i| VaultEntry { id: i.to_string(), title: format!("Site {}", i), password: "pw".to_string(), ..Default::default() })
+                .collect(),
+            categories: vec!["work".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_batches_move_the_vault_and_resume() {
+        let mut sealed = segments::seal_vault(&OLD, &vault(5)).unwrap();
+        let mut queue = Reencryption::start(&OLD, &NEW, &sealed).unwrap();
+        assert_eq!((queue.done, queue.total), (0, 6));
+
+        assert_eq!(queue.step(&OLD, &NEW, &mut sealed, 2).unwrap(), 2);
+        assert_eq!(queue.open_vault(&OLD, &NEW, &sealed).unwrap(), vault(5));
+        assert!(segments::open_vault(&NEW, &sealed).is_err());
+
+        // Saved and reloaded between batches.
+        let mut queue: Reencryption = serde_json::from_str(&serde_json::to_string(&queue).unwrap()).unwrap();
+        assert!(queue.step(&[3u8; 32], &NEW, &mut sealed, 2).is_err());
+        while queue.step(&OLD, &NEW, &mut sealed, 2).unwrap() > 0 {}
+
+        assert!(queue.is_complete());
+        assert_eq!((queue.done, queue.total), (6, 6));
+        assert_eq!(segments::open_vault(&NEW, &sealed).unwrap(), vault(5));
+    }
+
+    #[test]
+    fn test_entries_sealed_with_the_new_key_are_skipped() {
+        let mut sealed = segments::seal_vault(&OLD, &vault(2)).unwrap();
+        let mut queue = Reencryption::start(&OLD, &NEW, &sealed).unwrap();
+        // An entry edited mid-way is written with the new key.
+        sealed.entries[1] = segments::seal_entry(&NEW, &vault(2).entries[1]).unwrap();
+
+        assert_eq!(queue.step(&OLD, &NEW, &mut sealed, 10).unwrap(), 2);
+        assert_eq!((queue.done, queue.total), (2, 2));
+        assert!(Reencryption::start(&OLD, &OLD, &sealed).is_err());
+    }
+}
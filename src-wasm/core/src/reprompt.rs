@@ -0,0 +1,109 @@
+// --- Master Password Reprompt ---
+// Entries flagged 'reprompt' (as in Bitwarden) don't give up their secrets just
+// because the vault is open: revealing, copying, filling or sharing one needs
+// the master password typed again within the last few minutes. The check lives
+// here rather than in the UI, and runs on the engine's clock ('clock') with a
+// window capped at MAX_WINDOW_SECS, so the engine's reveal, copy, fill and share
+// calls can't be talked past it. It doesn't cover a copy of the vault decrypted
+// in full ('Engine::decrypt_package', which the web app uses to load the vault):
+// whoever holds that has every entry already.
+//
+// The gate keeps an HKDF verifier of the master key, taken when the engine is
+// created, and compares the key the retyped password unlocks against it. The
+// engine unlocks the envelope the vault was opened with, never one the caller
+// passes: any envelope wrapping this key would otherwise do, under a password
+// of the caller's own choosing. The verifier is of the unbound key, so
+// confirming works on a device-bound vault too.
+
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::keys::derive_subkey;
+use crate::vault::VaultEntry;
+
+const PURPOSE: &str = "securepass-reprompt-verifier-v1";
+/// How long a confirmation lasts unless the front end sets otherwise.
+pub const DEFAULT_WINDOW_SECS: u64 = 300;
+/// The longest window 'set_window' allows.
+pub const MAX_WINDOW_SECS: u64 = 900;
+pub const REPROMPT_REQUIRED: &str = "Reprompt error: confirm the master password to open this entry";
+
+pub struct RepromptGate {
+    verifier: [u8; 32],
+    confirmed_at: Option<u64>,
+    window_secs: u64,
+}
+
+impl RepromptGate {
+    pub fn new(master_key: &[u8; 32]) -> RepromptGate {
+        RepromptGate { verifier: derive_subkey(master_key, PURPOSE, b""), confirmed_at: None, window_secs: DEFAULT_WINDOW_SECS }
+    }
+
+    /// Records a confirmation at `now` if `master_key` (unlocked from the retyped
+    /// password) is this vault's. A wrong key also ends any earlier confirmation.
+    pub fn confirm(&mut self, master_key: &[u8; 32], now: u64) -> bool {
+        let mut candidate = derive_subkey(master_key, PURPOSE, b"");
+        let matches: bool = candidate.ct_eq(&self.verifier).into();
+        candidate.zeroize();
+        self.confirmed_at = matches.then_some(now);
+        matches
+    }
+
+    /// Ends the current confirmation, e.g. when the window loses focus.
+    pub fn revoke(&mut self) {
+        self.confirmed_at = None;
+    }
+
+    /// 0 means every reprompt-flagged reveal needs its own confirmation in the same
+    /// second. Longer than MAX_WINDOW_SECS is cut to it.
+    pub fn set_window(&mut self, secs: u64) {
+        self.window_secs = secs.min(MAX_WINDOW_SECS);
+    }
+
+    pub fn is_confirmed(&self, now: u64) -> bool {
+        self.confirmed_at.is_some_and(|at| now >= at && now - at <= self.window_secs)
+    }
+
+    /// Ok for an entry without the flag, or within the window of a confirmation.
+    pub fn check(&self, entry: &VaultEntry, now: u64) -> Result<(), String> {
+        if entry.reprompt && !self.is_confirmed(now) {
+            return Err(REPROMPT_REQUIRED.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RepromptGate {
+    fn drop(&mut self) {
+        self.verifier.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_window() {
+        let mut gate = RepromptGate::new(&[1u8; 32]);
+        let flagged = VaultEntry { reprompt: true, ..Default::default() };
+        assert!(gate.check(&VaultEntry::default(), 0).is_ok());
+        assert_eq!(gate.check(&flagged, 100).unwrap_err(), REPROMPT_REQUIRED);
+
+        assert!(!gate.confirm(&[2u8; 32], 100));
+        assert!(gate.confirm(&[1u8; 32], 100));
+        assert!(gate.check(&flagged, 100 + DEFAULT_WINDOW_SECS).is_ok());
+        assert!(gate.check(&flagged, 101 + DEFAULT_WINDOW_SECS).is_err());
+        // A clock that went backwards doesn't count as confirmed.
+        assert!(gate.check(&flagged, 99).is_err());
+
+        gate.set_window(0);
+        assert!(gate.check(&flagged, 100).is_ok() && gate.check(&flagged, 101).is_err());
+        gate.set_window(u64::MAX);
+        assert!(gate.check(&flagged, 100 + MAX_WINDOW_SECS).is_ok() && gate.check(&flagged, 101 + MAX_WINDOW_SECS).is_err());
+        gate.revoke();
+        assert!(gate.check(&flagged, 100).is_err());
+        assert!(gate.confirm(&[1u8; 32], 200) && !gate.confirm(&[3u8; 32], 200));
+        assert!(!gate.is_confirmed(200));
+    }
+}
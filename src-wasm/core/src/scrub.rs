@@ -0,0 +1,131 @@
+// --- Memory Scrubbing ---
+// Zeroizing each secret on Drop misses the copies nobody owns: the buffer a Vec
+// left behind when it grew, a String JS passed in and wasm-bindgen freed, the
+// scratch space of a JSON round trip. In Wasm those stay readable in linear
+// memory until something else happens to reuse them. 'ScrubbingAlloc' wraps an
+// allocator and zeroes every block as it is freed, so dropping the engine on
+// lock leaves no plaintext anywhere on the heap; 'scrub_stack' clears the stack
+// below the caller. The Wasm bridge installs the allocator as its global one.
+//
+// 'Canary' is the test hook that checks it: a known plaintext is put in the
+// vault, the vault is locked, and the scan must not find it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+
+use zeroize::Zeroize;
+
+/// How much stack 'scrub_stack' clears: well past the deepest Argon2 or JSON frame.
+pub const STACK_SCRUB_BYTES: usize = 64 * 1024;
+/// The canary is kept XOR this, so the scan can't find its own copy.
+const CANARY_MASK: u8 = 0xA5;
+
+/// An allocator that zeroes every block before handing it back to `A`.
+/// Reallocation always moves (the default 'realloc'), so the old block goes
+/// through 'dealloc' and is zeroed too.
+pub struct ScrubbingAlloc<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for ScrubbingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        core::slice::from_raw_parts_mut(ptr, layout.size()).zeroize();
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+/// Clears 'STACK_SCRUB_BYTES' of stack below the caller's frame, where the
+/// frames of calls that just returned left their locals.
+#[inline(never)]
+pub fn scrub_stack() {
+    let mut scratch = [0u8; STACK_SCRUB_BYTES];
+    scratch.zeroize();
+    core::hint::black_box(&scratch);
+}
+
+/// A plaintext to look for in memory, held masked.
+pub struct Canary {
+    masked: Vec<u8>,
+}
+
+impl Canary {
+    /// From the canary's bytes as hex, decoded straight into the masked form so
+    /// the plaintext is never in memory here.
+    pub fn from_hex(hex: &str) -> Result<Canary, String> {
+        let nibble = |c: u8| (c as char).to_digit(16).map(|d| d as u8).ok_or("Canary error: not hex");
+        if hex.is_empty() || !hex.len().is_multiple_of(2) {
+            return Err("Canary error: expected an even number of hex digits".into());
+        }
+        let masked = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| Ok((nibble(pair[0])? << 4 | nibble(pair[1])?) ^ CANARY_MASK))
+            .collect::<Result<Vec<u8>, &str>>()?;
+        Ok(Canary { masked })
+    }
+
+    /// Whether the plaintext appears in `len` bytes read through `byte_at`.
+    pub fn found_in(&self, len: usize, byte_at: impl Fn(usize) -> u8) -> bool {
+        let n = self.masked.len();
+        (0..len.saturating_sub(n - 1)).any(|start| (0..n).all(|i| byte_at(start + i) ^ CANARY_MASK == self.masked[i]))
+    }
+
+    /// Scans the whole of Wasm linear memory.
+    #[cfg(target_arch = "wasm32")]
+    pub fn found_in_linear_memory(&self) -> bool {
+        let len = core::arch::wasm32::memory_size(0) * 65536;
+        // Address 0 is never handed out; every other address below 'len' is mapped.
+        self.found_in(len, |addr| if addr == 0 { 0 } else { unsafe { core::ptr::read_volatile(addr as *const u8) } })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::System;
+
+    /// Fails if a block comes back with anything but zeroes in it.
+    struct CheckZeroed;
+
+    unsafe impl GlobalAlloc for CheckZeroed {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            assert!(core::slice::from_raw_parts(ptr, layout.size()).iter().all(|&b| b == 0));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_freed_and_moved_blocks_are_zeroed() {
+        let alloc = ScrubbingAlloc(CheckZeroed);
+        let layout = Layout::from_size_align(16, 8).unwrap();
+        unsafe {
+            let block = alloc.alloc(layout);
+            block.copy_from(b"hunter2 hunter2!".as_ptr(), 16);
+            let grown = alloc.realloc(block, layout, 64);
+            assert_eq!(core::slice::from_raw_parts(grown, 16), b"hunter2 hunter2!");
+            alloc.dealloc(grown, Layout::from_size_align(64, 8).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_canary_scan() {
+        let canary = Canary::from_hex("68756e74657232").unwrap();
+        assert!(!canary.masked.windows(7).any(|w| w == b"hunter2"));
+        let memory = b"...{\"password\":\"hunter2\"}...";
+        assert!(canary.found_in(memory.len(), |i| memory[i]));
+        let memory = b"...{\"password\":\"hunter\"}...";
+        assert!(!canary.found_in(memory.len(), |i| memory[i]));
+        assert!(Canary::from_hex("6g").is_err() && Canary::from_hex("616").is_err() && Canary::from_hex("").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+// --- Blind Search Index ---
+// Lets a large vault be searched without decrypting it. For every entry we keep
+// the set of n-grams (1 to 3 characters) of the words in its title, username,
+// URL and folder, each replaced by HMAC-SHA256 under a search key derived from
+// the master key and cut to 8 bytes. The index is stored next to the sealed
+// entries; a query is turned into tokens the same way and matched against it,
+// so only the matching entries need to be decrypted.
+//
+// Text is folded with 'text::search_key' first, so the index agrees with
+// 'listing::search' on case, accents and full-width forms. A hit is a candidate,
+// not a match: every trigram of a query word being present doesn't mean the
+// word is. Confirm hits on the decrypted summaries.
+//
+// What it gives away to someone holding the index but not the key: how many
+// distinct n-grams each entry has, and which entries share one. Not the text.
+//
+// Edits don't rewrite the index. 'Indexer::add' and 'SearchIndex::remove' append
+// to a short list of pending changes that searches read on top of the indexed
+// entries; 'compact' folds them in when 'needs_compaction' says so, e.g. while
+// the app is idle. 'Indexer::rebuild_partial' re-indexes a batch of entries at a
+// time, for repairing or refreshing the index in the background.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::hierarchy::key_id;
+use crate::keys::derive_subkey;
+use crate::text;
+use crate::vault::VaultEntry;
+
+pub(crate) const PURPOSE: &str = "securepass-search-index-v1";
+pub const FORMAT_VERSION: u8 = 1;
+
+const TOKEN_BYTES: usize = 8;
+/// Longest n-gram indexed. Query words longer than this match on all their trigrams.
+const MAX_GRAM: usize = 3;
+
+/// Pending changes below this many never call for compaction.
+const MIN_COMPACTION: usize = 64;
+
+/// Entry ID -> its sorted tokens (hex), plus changes not yet folded in.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndex {
+    pub version: u8,
+    /// 'hierarchy::key_id' of the master key the tokens were made with.
+    pub key_id: String,
+    pub entries: BTreeMap<String, Vec<String>>,
+    /// Newer than 'entries', at most one per entry ID.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<IndexChange>,
+}
+
+/// An entry indexed anew, or removed when 'tokens' is None.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IndexChange {
+    pub id: String,
+    pub tokens: Option<Vec<String>>,
+}
+
+impl SearchIndex {
+    /// Records that an entry was deleted; false if it wasn't indexed.
+    pub fn remove(&mut self, entry_id: &str) -> bool {
+        let indexed = self.tokens_of(entry_id).is_some();
+        if indexed {
+            self.push(IndexChange { id: entry_id.to_string(), tokens: None });
+        }
+        indexed
+    }
+
+    /// True once the pending changes are many enough to be worth folding in:
+    /// more than 'MIN_COMPACTION' and more than an eighth of the indexed entries.
+    pub fn needs_compaction(&self) -> bool {
+        self.pending.len() > MIN_COMPACTION.max(self.entries.len() / 8)
+    }
+
+    /// Folds the pending changes into the indexed entries.
+    pub fn compact(&mut self) {
+        for change in std::mem::take(&mut self.pending) {
+            match change.tokens {
+                Some(tokens) => self.entries.insert(change.id, tokens),
+                None => self.entries.remove(&change.id),
+            };
+        }
+    }
+
+    /// Number of entries the index currently covers.
+    pub fn len(&self) -> usize {
+        self.ids().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tokens_of(&self, entry_id: &str) -> Option<&Vec<String>> {
+        match self.pending.iter().find(|c| c.id == entry_id) {
+            Some(change) => change.tokens.as_ref(),
+            None => self.entries.get(entry_id),
+        }
+    }
+
+    /// Every indexed ID, sorted, with the pending changes applied.
+    fn ids(&self) -> impl Iterator<Item = &str> {
+        let added = self.pending.iter().filter(|c| c.tokens.is_some() && !self.entries.contains_key(&c.id));
+        let mut ids: Vec<&str> = self.entries.keys().map(String::as_str).chain(added.map(|c| c.id.as_str())).collect();
+        ids.sort_unstable();
+        ids.into_iter().filter(|id| self.tokens_of(id).is_some())
+    }
+
+    fn push(&mut self, change: IndexChange) {
+        self.pending.retain(|c| c.id != change.id);
+        self.pending.push(change);
+    }
+}
+
+/// The search key for one master key. Wiped on drop.
+pub struct Indexer {
+    key: [u8; 32],
+    key_id: String,
+}
+
+impl Drop for Indexer {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl Indexer {
+    pub fn new(master_key: &[u8; 32]) -> Indexer {
+        Indexer { key: derive_subkey(master_key, PURPOSE, b""), key_id: key_id(master_key) }
+    }
+
+    /// A fresh index of `entries`.
+    pub fn build(&self, entries: &[VaultEntry]) -> SearchIndex {
+        let mut index = SearchIndex { version: FORMAT_VERSION, key_id: self.key_id.clone(), ..Default::default() };
+        for entry in entries {
+            index.entries.insert(entry.id.clone(), self.entry_tokens(entry));
+        }
+        index
+    }
+
+    /// Adds `entry` to the index, or replaces its tokens after an edit, as a pending change.
+    pub fn add(&self, index: &mut SearchIndex, entry: &VaultEntry) -> Result<(), String> {
+        self.check(index)?;
+        index.push(IndexChange { id: entry.id.clone(), tokens: Some(self.entry_tokens(entry)) });
+        Ok(())
+    }
+
+    /// Re-indexes up to `limit` of `entries`, in ID order, starting after the ID
+    /// `after` (None to start over), and drops indexed IDs in that range that are
+    /// no longer in `entries`. Returns the ID to pass as `after` next time, or None
+    /// once the end is reached. Writes straight into the indexed entries.
+    pub fn rebuild_partial(
+        &self,
+        index: &mut SearchIndex,
+        entries: &[VaultEntry],
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Option<String>, String> {
+        self.check(index)?;
+        index.compact();
+        let mut batch: Vec<&VaultEntry> = entries.iter().filter(|e| after.is_none_or(|a| e.id.as_str() > a)).collect();
+        batch.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        let done = batch.len() <= limit;
+        batch.truncate(limit.max(1));
+
+        let last = if done { None } else { batch.last().map(|e| e.id.clone()) };
+        let in_range = |id: &str| after.is_none_or(|a| id > a) && last.as_deref().is_none_or(|l| id <= l);
+        index.entries.retain(|id, _| !in_range(id) || batch.iter().any(|e| &e.id == id));
+        for entry in batch {
+            index.entries.insert(entry.id.clone(), self.entry_tokens(entry));
+        }
+        Ok(last)
+    }
+
+    /// IDs of the entries that may match `query` (see the note above), sorted.
+    /// An empty query matches every entry.
+    pub fn search<'a>(&self, index: &'a SearchIndex, query: &str) -> Result<Vec<&'a str>, String> {
+        self.check(index)?;
+        let mut wanted: Vec<String> = Vec::new();
+        for word in text::search_key(query).split_whitespace() {
+            let chars: Vec<char> = word.chars().collect();
+            let n = chars.len().min(MAX_GRAM);
+            wanted.extend(chars.windows(n).map(|gram| self.token(&gram.iter().collect::<String>())));
+        }
+        Ok(index
+            .ids()
+            .filter(|id| index.tokens_of(id).is_some_and(|tokens| wanted.iter().all(|t| tokens.binary_search(t).is_ok())))
+            .collect())
+    }
+
+    /// Refuses an index of another format or made under another master key.
+    pub fn check(&self, index: &SearchIndex) -> Result<(), String> {
+        if index.version != FORMAT_VERSION {
+            return Err(format!("Unsupported search index version: {}", index.version));
+        }
+        if index.key_id != self.key_id {
+            return Err("Search index was built with another key; rebuild it".to_string());
+        }
+        Ok(())
+    }
+
+    fn entry_tokens(&self, entry: &VaultEntry) -> Vec<String> {
+        let mut grams = BTreeSet::new();
+        for field in [&entry.title, &entry.username, &entry.url, &entry.category] {
+            for word in text::search_key(field).split_whitespace() {
+                let chars: Vec<char> = word.chars().collect();
+                for n in 1..=MAX_GRAM.min(chars.len()) {
+                    grams.extend(chars.windows(n).map(|gram| gram.iter().collect::<String>()));
+                }
+            }
+        }
+        let mut tokens: Vec<String> = grams.iter().map(|gram| self.token(gram)).collect();
+        tokens.sort_unstable();
+        tokens.dedup();
+        tokens
+    }
+
+    fn token(&self, gram: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC takes any key length");
+        mac.update(gram.as_bytes());
+        mac.finalize().into_bytes()[..TOKEN_BYTES].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, title: &str, url: &str) -> VaultEntry {
+        VaultEntry { id: id.to_string(), title: title.to_string(), url: url.to_string(), ..Default::default() }
+    }
+
+    fn entries() -> Vec<VaultEntry> {
+        vec![
+            entry("1", "GitHub", "https://github.com"),
+            entry("2", "Café Flore", "https://cafe.example"),
+            entry("3", "Bank", "https://bank.example"),
+        ]
+    }
+
+    #[test]
+    fn test_search_without_plaintext() {
+        let indexer = Indexer::new(&[1u8; 32]);
+        let index = indexer.build(&entries());
+        let json = serde_json::to_string(&index).unwrap();
+        assert!(!json.contains("git") && !json.contains("bank"));
+
+        assert_eq!(indexer.search(&index, "GITHUB").unwrap(), ["1"]);
+        assert_eq!(indexer.search(&index, "ｃａｆé").unwrap(), ["2"]);
+        assert_eq!(indexer.search(&index, "example").unwrap(), ["2", "3"]);
+        assert_eq!(indexer.search(&index, "ba").unwrap(), ["3"]);
+        assert_eq!(indexer.search(&index, "").unwrap().len(), 3);
+        assert!(indexer.search(&index, "gitlab").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_incremental_changes_and_key_check() {
+        let indexer = Indexer::new(&[1u8; 32]);
+        let mut index = indexer.build(&entries());
+        indexer.add(&mut index, &entry("3", "Credit Union", "https://cu.example")).unwrap();
+        indexer.add(&mut index, &entry("4", "Bank of Mars", "")).unwrap();
+        assert_eq!(index.pending.len(), 2);
+        assert_eq!(indexer.search(&index, "bank").unwrap(), ["4"]);
+        assert_eq!(indexer.search(&index, "union").unwrap(), ["3"]);
+        assert!(index.remove("4") && !index.remove("4") && index.remove("1"));
+        assert!(indexer.search(&index, "bank").unwrap().is_empty());
+        assert_eq!(indexer.search(&index, "").unwrap(), ["2", "3"]);
+
+        let before = indexer.search(&index, "").unwrap().len();
+        index.compact();
+        assert!(index.pending.is_empty() && index.len() == before);
+        assert_eq!(indexer.search(&index, "union").unwrap(), ["3"]);
+
+        let other = Indexer::new(&[2u8; 32]);
+        assert!(other.search(&index, "bank").unwrap_err().contains("rebuild"));
+        assert!(other.add(&mut index, &entry("5", "x", "")).is_err());
+    }
+
+    #[test]
+    fn test_compaction_threshold() {
+        let indexer = Indexer::new(&[1u8; 32]);
+        let mut index = indexer.build(&entries());
+        for i in 0..=MIN_COMPACTION {
+            assert!(!index.needs_compaction());
+            indexer.add(&mut index, &entry(&format!("n{}", i), "New", "")).unwrap();
+        }
+        assert!(index.needs_compaction());
+        index.compact();
+        assert_eq!(index.len(), 3 + MIN_COMPACTION + 1);
+    }
+
+    #[test]
+    fn test_rebuild_partial_in_batches() {
+        let indexer = Indexer::new(&[1u8; 32]);
+        let mut index = indexer.build(&entries());
+        index.entries.insert("0-gone".to_string(), Vec::new());
+        let mut vault = entries();
+        vault[0].title = "GitLab".to_string();
+        vault.push(entry("4", "Zoo", ""));
+
+        let mut cursor = None;
+        let mut batches = 0;
+        loop {
+            cursor = indexer.rebuild_partial(&mut index, &vault, cursor.as_deref(), 2).unwrap();
+            batches += 1;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(batches, 2);
+        assert_eq!(index, indexer.build(&vault));
+    }
+}
@@ -0,0 +1,363 @@
+// --- Entry Segments ---
+// A vault storage format that doesn't have to be decrypted all at once. Each
+// entry is stored as two separately encrypted segments:
+//   * summary: what a list row shows (title, URL, folder, flags, timestamps)
+//   * secrets: everything else (username, password, notes, TOTP seed, history
+//     and any field the JS side added)
+// so a list of thousands of entries decrypts summaries only, and a password is
+// decrypted when its entry is opened. Each segment has its own HKDF subkey per
+// entry and is bound to the entry ID, so segments can't be swapped between
+// entries. The vault root (folders, equivalent domains, ...) is one more segment.
+// Every segment names the key that sealed it, so a vault can be moved to a new
+// key a few entries at a time (see 'reencrypt').
+//
+// Segments are sealed with key commitment ('keys::seal_committed_with') since
+// version 2. A segment without a version is from before and opens as plain
+// AES-GCM; a version 2 vault holds no such segments, so one is refused there.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use zeroize::Zeroize;
+
+use crate::hierarchy::key_id;
+use crate::keys::{derive_subkey, open_committed_with, open_with, seal_committed_with, Cipher};
+use crate::redact;
+use crate::vault::{wipe_value, VaultData, VaultEntry};
+
+pub(crate) const SUMMARY_PURPOSE: &str = "securepass-entry-summary-v1";
+pub(crate) const SECRETS_PURPOSE: &str = "securepass-entry-secrets-v1";
+pub(crate) const ROOT_PURPOSE: &str = "securepass-vault-root-v1";
+
+pub const FORMAT_VERSION: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+
+fn legacy_version() -> u8 {
+    LEGACY_VERSION
+}
+
+/// The fields a list row needs. None of them is a credential.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntrySummary {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub last_modified: u64,
+    #[serde(default)]
+    pub last_used: u64,
+    #[serde(default)]
+    pub use_count: u32,
+    #[serde(default)]
+    pub travel_mode: bool,
+    /// Tells the list to ask for the master password before opening the entry.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reprompt: bool,
+}
+
+/// Entry JSON keys that live in the summary segment; every other key is a secret.
+const SUMMARY_FIELDS: [&str; 10] =
+    ["id", "title", "url", "category", "favorite", "lastModified", "lastUsed", "useCount", "travelMode", "reprompt"];
+
+impl From<&VaultEntry> for EntrySummary {
+    fn from(entry: &VaultEntry) -> EntrySummary {
+        EntrySummary {
+            id: entry.id.clone(),
+            title: entry.title.clone(),
+            url: entry.url.clone(),
+            category: entry.category.clone(),
+            favorite: entry.favorite,
+            last_modified: entry.last_modified,
+            last_used: entry.last_used,
+            use_count: entry.use_count,
+            travel_mode: entry.travel_mode,
+            reprompt: entry.reprompt,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// Missing in segments sealed before key commitment.
+    #[serde(default = "legacy_version")]
+    pub version: u8,
+    pub iv: String,
+    pub data: String,
+    /// 'hierarchy::key_id' of the master key it was sealed under; empty in
+    /// segments written before this was recorded.
+    #[serde(default, rename = "keyId")]
+    pub key_id: String,
+    /// Left out for AES-256-GCM, so existing segments read and write unchanged.
+    #[serde(default, skip_serializing_if = "Cipher::is_default")]
+    pub cipher: Cipher,
+}
+
+/// One entry at rest. The ID stays in the clear so an entry can be found, and
+/// rewritten on its own, without decrypting anything.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SealedEntry {
+    pub id: String,
+    pub summary: Segment,
+    pub secrets: Segment,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentedVault {
+    pub version: u8,
+    pub root: Segment,
+    pub entries: Vec<SealedEntry>,
+}
+
+/// Encrypts one entry as a summary and a secrets segment.
+pub fn seal_entry(master_key: &[u8; 32], entry: &VaultEntry) -> Result<SealedEntry, String> {
+    seal_entry_with(Cipher::default(), master_key, entry)
+}
+
+/// 'seal_entry' with the given cipher, e.g. AES-256-GCM-SIV for an entry a sync
+/// may seal again from a replayed nonce. Opening reads the cipher from each segment.
+pub fn seal_entry_with(cipher: Cipher, master_key: &[u8; 32], entry: &VaultEntry) -> Result<SealedEntry, String> {
+    let mut fields = match serde_json::to_value(entry).map_err(|e| format!("Entry serialize error: {}", e))? {
+        Value::Object(map) => map,
+        _ => return Err("Entry serialize error: entry is not an object".to_string()),
+    };
+    fields.retain(|key, _| !SUMMARY_FIELDS.contains(&key.as_str()));
+    let mut secrets = Value::Object(fields);
+
+    let id = entry.id.as_bytes();
+    let summary = seal_segment_with(cipher, master_key, SUMMARY_PURPOSE, id, &EntrySummary::from(entry));
+    let sealed_secrets = seal_segment_with(cipher, master_key, SECRETS_PURPOSE, id, &secrets);
+    wipe_value(&mut secrets);
+    Ok(SealedEntry { id: entry.id.clone(), summary: summary?, secrets: sealed_secrets? })
+}
+
+/// Decrypts only the summary segment.
+pub fn open_summary(master_key: &[u8; 32], sealed: &SealedEntry) -> Result<EntrySummary, String> {
+    let summary: EntrySummary = open_segment(master_key, SUMMARY_PURPOSE, sealed.id.as_bytes(), &sealed.summary)?;
+    if summary.id != sealed.id {
+        return Err(format!("Entry error: segment does not belong to entry {}", sealed.id));
+    }
+    Ok(summary)
+}
+
+/// Decrypts both segments back into the full entry.
+pub fn open_entry(master_key: &[u8; 32], sealed: &SealedEntry) -> Result<VaultEntry, String> {
+    let summary = open_summary(master_key, sealed)?;
+    let mut secrets: Map<String, Value> = open_segment(master_key, SECRETS_PURPOSE, sealed.id.as_bytes(), &sealed.secrets)?;
+    if let Ok(Value::Object(fields)) = serde_json::to_value(&summary) {
+        secrets.extend(fields);
+    }
+    let mut value = Value::Object(secrets);
+    let entry = serde_json::from_value(value.clone()).map_err(|e| redact::json_error("Entry parse", &e));
+    wipe_value(&mut value);
+    entry
+}
+
+/// Encrypts a whole vault in the segmented format.
+pub fn seal_vault(master_key: &[u8; 32], vault: &VaultData) -> Result<SegmentedVault, String> {
+    let root = VaultData {
+        entries: Vec::new(),
+        categories: vault.categories.clone(),
+        travel_mode_categories: vault.travel_mode_categories.clone(),
+        equivalent_domains: vault.equivalent_domains.clone(),
+        settings: vault.settings.clone(),
+        extra: vault.extra.clone(),
+    };
+    Ok(SegmentedVault {
+        version: FORMAT_VERSION,
+        root: seal_segment(master_key, ROOT_PURPOSE, b"", &root)?,
+        entries: vault.entries.iter().map(|e| seal_entry(master_key, e)).collect::<Result<_, _>>()?,
+    })
+}
+
+/// Decrypts every segment of a vault.
+pub fn open_vault(master_key: &[u8; 32], sealed: &SegmentedVault) -> Result<VaultData, String> {
+    open_vault_with(sealed, |_| master_key)
+}
+
+/// Like 'open_vault', with the key for each segment picked by `key_for` (given the
+/// root or an entry's summary segment): a vault part-way through a re-encryption.
+pub(crate) fn open_vault_with<'a>(
+    sealed: &SegmentedVault,
+    key_for: impl Fn(&Segment) -> &'a [u8; 32],
+) -> Result<VaultData, String> {
+    check_version(sealed)?;
+    check_segments(sealed)?;
+    let mut vault: VaultData = open_segment(key_for(&sealed.root), ROOT_PURPOSE, b"", &sealed.root)?;
+    for entry in &sealed.entries {
+        match open_entry(key_for(&entry.summary), entry) {
+            Ok(entry) => vault.entries.push(entry),
+            Err(e) => {
+                vault.wipe();
+                return Err(e);
+            }
+        }
+    }
+    Ok(vault)
+}
+
+/// Every entry's summary, in storage order, without touching a secrets segment.
+pub fn summaries(master_key: &[u8; 32], sealed: &SegmentedVault) -> Result<Vec<EntrySummary>, String> {
+    check_version(sealed)?;
+    check_segments(sealed)?;
+    sealed.entries.iter().map(|e| open_summary(master_key, e)).collect()
+}
+
+pub(crate) fn check_version(sealed: &SegmentedVault) -> Result<(), String> {
+    if ![LEGACY_VERSION, FORMAT_VERSION].contains(&sealed.version) {
+        return Err(format!("Unsupported segmented vault version: {}", sealed.version));
+    }
+    Ok(())
+}
+
+/// A version 2 vault was sealed whole with commitment: an uncommitted segment
+/// in one was put there by someone else.
+fn check_segments(sealed: &SegmentedVault) -> Result<(), String> {
+    let mut segments = sealed.entries.iter().flat_map(|e| [&e.summary, &e.secrets]).chain([&sealed.root]);
+    if sealed.version == FORMAT_VERSION && segments.any(|s| s.version != FORMAT_VERSION) {
+        return Err("Segment error: an uncommitted segment in a committed vault".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) fn seal_segment<T: Serialize>(master_key: &[u8; 32], purpose: &str, id: &[u8], value: &T) -> Result<Segment, String> {
+    seal_segment_with(Cipher::default(), master_key, purpose, id, value)
+}
+
+pub(crate) fn seal_segment_with<T: Serialize>(
+    cipher: Cipher,
+    master_key: &[u8; 32],
+    purpose: &str,
+    id: &[u8],
+    value: &T,
+) -> Result<Segment, String> {
+    let mut key = derive_subkey(master_key, purpose, id);
+    let mut plain = serde_json::to_vec(value).map_err(|e| format!("Segment serialize error: {}", e))?;
+    let sealed = seal_committed_with(cipher, &key, &plain, id);
+    key.zeroize();
+    plain.zeroize();
+
+    let (iv, data) = sealed?;
+    Ok(Segment { version: FORMAT_VERSION, iv: B64.encode(iv), data: B64.encode(data), key_id: key_id(master_key), cipher })
+}
+
+pub(crate) fn open_segment<T: DeserializeOwned>(master_key: &[u8; 32], purpose: &str, id: &[u8], segment: &Segment) -> Result<T, String> {
+    if ![LEGACY_VERSION, FORMAT_VERSION].contains(&segment.version) {
+        return Err(format!("Unsupported segment version: {}", segment.version));
+    }
+    let iv = B64.decode(&segment.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&segment.data).map_err(|e| format!("Segment decode error: {}", e))?;
+
+    let mut key = derive_subkey(master_key, purpose, id);
+    let plain = if segment.version == LEGACY_VERSION {
+        open_with(segment.cipher, &key, &iv, &data, id)
+    } else {
+        open_committed_with(segment.cipher, &key, &iv, &data, id)
+    };
+    key.zeroize();
+
+    let mut plain = plain?;
+    let value = serde_json::from_slice(&plain).map_err(|e| redact::json_error("Segment parse", &e));
+    plain.zeroize();
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [8u8; 32];
+
+    fn vault() -> VaultData {
+        VaultData::from_json(
+            r#"{
+                "entries": [
+                    {"id": "1", "title": "GitHub", "url": "https://github.com", "username": "octo", "password": "hunter2",
+                     "notes": "recovery codes", "totpSecret": "JBSWY3DP", "favorite": true, "lastModified": 7, "lastUsed": 9, "icon": "gh"},
+                    {"id": "2", "title": "Bank", "password": "pin"}
+                ],
+                "categories": ["work"],
+                "version": 2
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_vault_roundtrip() {
+        let sealed = seal_vault(&KEY, &vault()).unwrap();
+        assert_eq!(open_vault(&KEY, &sealed).unwrap(), vault());
+        assert!(open_vault(&[9u8; 32], &sealed).is_err());
+    }
+
+    #[test]
+    fn test_siv_entries_record_their_cipher() {
+        let entry = &vault().entries[0];
+        let sealed = seal_entry_with(Cipher::Aes256GcmSiv, &KEY, entry).unwrap();
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(json.contains(r#""cipher":"aes-256-gcm-siv""#));
+        assert_eq!(&open_entry(&KEY, &serde_json::from_str(&json).unwrap()).unwrap(), entry);
+        assert!(!serde_json::to_string(&seal_entry(&KEY, entry).unwrap()).unwrap().contains("cipher"));
+    }
+
+    #[test]
+    fn test_summaries_leave_secrets_sealed() {
+        let mut sealed = seal_vault(&KEY, &vault()).unwrap();
+        // Corrupt every secrets segment: listing must not need them.
+        sealed.entries.iter_mut().for_each(|e| e.secrets.data = B64.encode(b"garbage-garbage-garbage"));
+
+        let list = summaries(&KEY, &sealed).unwrap();
+        assert_eq!(list[0], EntrySummary {
+            id: "1".to_string(),
+            title: "GitHub".to_string(),
+            url: "https://github.com".to_string(),
+            favorite: true,
+            last_modified: 7,
+            last_used: 9,
+            ..Default::default()
+        });
+        assert_eq!(list[1].title, "Bank");
+        assert!(open_entry(&KEY, &sealed.entries[0]).is_err());
+    }
+
+    #[test]
+    fn test_segments_are_bound_to_their_entry() {
+        let sealed = seal_vault(&KEY, &vault()).unwrap();
+        let mut swapped = sealed.entries[0].clone();
+        swapped.summary = sealed.entries[1].summary.clone();
+        assert!(open_summary(&KEY, &swapped).is_err());
+
+        let mut renamed = sealed.entries[0].clone();
+        renamed.id = "2".to_string();
+        assert!(open_entry(&KEY, &renamed).is_err());
+
+        let mut secrets_only = sealed.entries[0].clone();
+        secrets_only.secrets = sealed.entries[0].summary.clone();
+        assert!(open_entry(&KEY, &secrets_only).is_err());
+    }
+
+    #[test]
+    fn test_legacy_segments_open_only_in_a_legacy_vault() {
+        let root = VaultData { categories: vec!["work".to_string()], ..Default::default() };
+        let mut key = derive_subkey(&KEY, ROOT_PURPOSE, b"");
+        let (iv, data) = crate::keys::seal_with(Cipher::default(), &key, &serde_json::to_vec(&root).unwrap(), b"").unwrap();
+        key.zeroize();
+        let legacy_root = Segment { version: LEGACY_VERSION, iv: B64.encode(iv), data: B64.encode(data), key_id: key_id(&KEY), cipher: Cipher::default() };
+        let mut sealed = SegmentedVault { version: LEGACY_VERSION, root: legacy_root, entries: Vec::new() };
+        assert_eq!(open_vault(&KEY, &sealed).unwrap(), root);
+
+        sealed.version = FORMAT_VERSION;
+        assert!(open_vault(&KEY, &sealed).unwrap_err().contains("uncommitted segment"));
+
+        // A committed segment relabelled as legacy fails to open.
+        let mut relabelled = seal_vault(&KEY, &root).unwrap();
+        relabelled.version = LEGACY_VERSION;
+        relabelled.root.version = LEGACY_VERSION;
+        assert!(open_vault(&KEY, &relabelled).is_err());
+    }
+}
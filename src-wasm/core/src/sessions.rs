@@ -0,0 +1,126 @@
+// --- Vault Sessions ---
+// Several vaults can be open at once (a work vault and a personal one). Sessions
+// owns one engine per vault ID, remembers which one is in front, and locks them
+// one at a time or all together. Locking drops the engine, which wipes its key
+// and decrypted vault, so a front end never has to track stray unlocked engines.
+
+use std::collections::BTreeMap;
+
+use crate::Engine;
+
+#[derive(Default)]
+pub struct Sessions {
+    engines: BTreeMap<String, Engine>,
+    active: Option<String>,
+}
+
+impl Sessions {
+    pub fn new() -> Sessions {
+        Sessions::default()
+    }
+
+    /// Adds an unlocked vault under `vault_id`, locking any session already there.
+    /// The first vault opened becomes the active one.
+    pub fn open(&mut self, vault_id: &str, engine: Engine) -> Result<(), String> {
+        if vault_id.is_empty() {
+            return Err("Session error: the vault ID is empty".to_string());
+        }
+        self.engines.insert(vault_id.to_string(), engine);
+        if self.active.is_none() {
+            self.active = Some(vault_id.to_string());
+        }
+        Ok(())
+    }
+
+    /// Brings an open vault to the front.
+    pub fn switch(&mut self, vault_id: &str) -> Result<(), String> {
+        let id = self.resolve(vault_id)?.to_string();
+        self.get(&id)?;
+        self.active = Some(id);
+        Ok(())
+    }
+
+    pub fn active_id(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// IDs of every open vault, sorted.
+    pub fn ids(&self) -> Vec<&str> {
+        self.engines.keys().map(String::as_str).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+    }
+
+    /// The session for `vault_id`; an empty ID means the active vault.
+    pub fn get(&self, vault_id: &str) -> Result<&Engine, String> {
+        let id = self.resolve(vault_id)?;
+        self.engines.get(id).ok_or_else(|| locked(id))
+    }
+
+    pub fn get_mut(&mut self, vault_id: &str) -> Result<&mut Engine, String> {
+        let id = self.resolve(vault_id)?.to_string();
+        self.engines.get_mut(&id).ok_or_else(|| locked(&id))
+    }
+
+    /// Locks one vault (an empty ID: the active one). Returns false if it wasn't
+    /// open. Locking the active vault leaves none active until the next 'switch'.
+    pub fn lock(&mut self, vault_id: &str) -> bool {
+        let Ok(id) = self.resolve(vault_id).map(str::to_string) else { return false };
+        if self.active.as_deref() == Some(id.as_str()) {
+            self.active = None;
+        }
+        self.engines.remove(&id).is_some()
+    }
+
+    pub fn lock_all(&mut self) {
+        self.engines.clear();
+        self.active = None;
+    }
+
+    fn resolve<'a>(&'a self, vault_id: &'a str) -> Result<&'a str, String> {
+        match vault_id {
+            "" => self.active.as_deref().ok_or_else(|| "Session error: no vault is active".to_string()),
+            id => Ok(id),
+        }
+    }
+}
+
+fn locked(vault_id: &str) -> String {
+    format!("Session error: vault '{}' is locked", vault_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_route_and_lock() {
+        let mut sessions = Sessions::new();
+        sessions.open("work", Engine::from_key([1u8; 32])).unwrap();
+        sessions.open("personal", Engine::from_key([2u8; 32])).unwrap();
+        assert_eq!((sessions.active_id(), sessions.ids()), (Some("work"), vec!["personal", "work"]));
+        assert_eq!(sessions.get("").unwrap().master_key, [1u8; 32]);
+
+        sessions.switch("personal").unwrap();
+        sessions.get_mut("").unwrap().vault_mut().categories.push("home".to_string());
+        assert_eq!(sessions.get("personal").unwrap().vault().categories, ["home"]);
+        assert!(sessions.switch("bank").is_err());
+
+        assert!(sessions.lock("personal"));
+        assert!(!sessions.lock("personal"));
+        assert_eq!(sessions.active_id(), None);
+        assert!(sessions.get("").err().unwrap().contains("no vault is active"));
+        assert!(sessions.get("personal").err().unwrap().contains("locked"));
+        assert!(sessions.get("work").is_ok());
+
+        sessions.lock_all();
+        assert!(sessions.is_empty());
+        assert!(sessions.open("", Engine::from_key([3u8; 32])).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+// --- Vault Settings ---
+// Preferences that belong to the vault rather than to one browser: how soon it
+// locks, how long a copied secret stays on the clipboard, what the generator
+// makes by default and which KDF settings the next key envelope should use.
+// They are stored inside the vault (its "settings" field), so they are
+// encrypted with it and synced with it like any other root field.
+//
+// The stored form is versioned. The vault keeps it as raw JSON, so a vault whose
+// settings come from a newer client still opens; 'VaultSettings::from_stored'
+// runs the migrations in order up to SETTINGS_VERSION. Version 0 is the shape
+// the web app kept in localStorage before this, so it can be passed in as-is
+// the first time a vault without settings is opened. To change the schema, bump
+// SETTINGS_VERSION and append a migration; never edit an existing one. A new
+// field that defaults sensibly when missing (like "presets") needs neither.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::generator::{self, GeneratorStyle, PasswordOptions};
+use crate::kdf::KdfSettings;
+
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Longest auto-lock and clipboard timeouts accepted, in seconds (one day).
+pub const MAX_TIMEOUT_SECS: u32 = 24 * 3600;
+
+/// Saved generator presets per vault, and the longest preset name, in characters.
+pub const MAX_PRESETS: usize = 32;
+pub const MAX_PRESET_NAME_LEN: usize = 64;
+
+/// MIGRATIONS[n] turns version n into version n + 1.
+const MIGRATIONS: [fn(&mut Map<String, Value>); SETTINGS_VERSION as usize] = [from_local_storage];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VaultSettings {
+    pub version: u32,
+    /// Idle time before the vault locks; 0 means never.
+    pub auto_lock_secs: u32,
+    /// How long a copied password or code stays on the clipboard; 0 means it isn't cleared.
+    pub clipboard_clear_secs: u32,
+    pub generator: PasswordOptions,
+    /// What the next key envelope (see 'kdf') is wrapped with.
+    pub kdf: KdfSettings,
+    /// Named generator settings ("Banking 20 chars no symbols"), in the order saved.
+    pub presets: Vec<GeneratorPreset>,
+    /// Encrypt new attachments convergently, so the same file syncs and stores
+    /// once; storage can then tell which attachments are the same file. Off by
+    /// default. See 'attachments'.
+    pub dedup_attachments: bool,
+    /// Favorite entry IDs in the order the user arranged them (see
+    /// 'listing::favorites'), so the quick-access row looks the same on every device.
+    pub favorite_order: Vec<String>,
+    /// Fields from a newer client, kept so writing back doesn't lose them.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        VaultSettings {
+            version: SETTINGS_VERSION,
+            auto_lock_secs: 5 * 60,
+            clipboard_clear_secs: 30,
+            generator: PasswordOptions { length: 20, use_uppercase: true, use_numbers: true, use_symbols: true },
+            kdf: KdfSettings::CURRENT,
+            presets: Vec::new(),
+            dedup_attachments: false,
+            favorite_order: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+}
+
+/// JSON {name, style, ...options}, e.g. {"name": "Wi-Fi", "style": "passphrase"}.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GeneratorPreset {
+    pub name: String,
+    #[serde(flatten)]
+    pub style: GeneratorStyle,
+}
+
+impl VaultSettings {
+    /// Reads stored settings of any version, migrating older ones. Fields the
+    /// stored form doesn't have take their defaults; a missing "version" is 0.
+    pub fn from_stored(stored: &Value) -> Result<VaultSettings, String> {
+        let mut fields = match stored {
+            Value::Object(map) => map.clone(),
+            Value::Null => return Ok(VaultSettings::default()),
+            _ => return Err("Settings error: settings are not an object".to_string()),
+        };
+        let version = fields.get("version").and_then(Value::as_u64).unwrap_or(0);
+        for migrate in MIGRATIONS.iter().skip(version as usize) {
+            migrate(&mut fields);
+        }
+        if version < u64::from(SETTINGS_VERSION) {
+            fields.insert("version".to_string(), SETTINGS_VERSION.into());
+        }
+        let settings: VaultSettings =
+            serde_json::from_value(Value::Object(fields)).map_err(|e| format!("Settings parse error: {}", e))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn to_stored(&self) -> Result<Value, String> {
+        serde_json::to_value(self).map_err(|e| format!("Settings serialize error: {}", e))
+    }
+
+    /// The preset with this name, ignoring case and surrounding spaces.
+    pub fn preset(&self, name: &str) -> Option<&GeneratorPreset> {
+        self.presets.iter().find(|p| same_name(&p.name, name))
+    }
+
+    /// Adds a preset, or replaces the one with the same name in place.
+    pub fn save_preset(&mut self, mut preset: GeneratorPreset) -> Result<(), String> {
+        preset.name = preset.name.trim().to_string();
+        check_preset(&preset)?;
+        match self.presets.iter().position(|p| same_name(&p.name, &preset.name)) {
+            Some(i) => self.presets[i] = preset,
+            None if self.presets.len() >= MAX_PRESETS => {
+                return Err(format!("Settings error: at most {} generator presets", MAX_PRESETS));
+            }
+            None => self.presets.push(preset),
+        }
+        Ok(())
+    }
+
+    /// Removes the preset with this name; false if there was none.
+    pub fn remove_preset(&mut self, name: &str) -> bool {
+        let before = self.presets.len();
+        self.presets.retain(|p| !same_name(&p.name, name));
+        self.presets.len() != before
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.auto_lock_secs > MAX_TIMEOUT_SECS || self.clipboard_clear_secs > MAX_TIMEOUT_SECS {
+            return Err(format!("Settings error: timeouts can be at most {} seconds", MAX_TIMEOUT_SECS));
+        }
+        if self.generator.length == 0 {
+            return Err("Settings error: the generator length must be at least 1".to_string());
+        }
+        if self.presets.len() > MAX_PRESETS {
+            return Err(format!("Settings error: at most {} generator presets", MAX_PRESETS));
+        }
+        for (i, preset) in self.presets.iter().enumerate() {
+            check_preset(preset)?;
+            if self.presets[..i].iter().any(|p| same_name(&p.name, &preset.name)) {
+                return Err(format!("Settings error: two generator presets are named '{}'", preset.name));
+            }
+        }
+        self.kdf.check_range()?;
+        if self.kdf.is_outdated() {
+            return Err(format!("Settings error: KDF settings weaker than {}", KdfSettings::CURRENT.label()));
+        }
+        Ok(())
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+fn check_preset(preset: &GeneratorPreset) -> Result<(), String> {
+    let len = preset.name.trim().chars().count();
+    if len == 0 || len > MAX_PRESET_NAME_LEN {
+        return Err(format!("Settings error: preset names need 1 to {} characters", MAX_PRESET_NAME_LEN));
+    }
+    match &preset.style {
+        GeneratorStyle::Password(options) if options.length == 0 => {
+            return Err("Settings error: the generator length must be at least 1".to_string());
+        }
+        GeneratorStyle::Passphrase(options) => generator::check_passphrase(options)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 0 -> 1: localStorage's "autolock_duration" (milliseconds, stored as a string)
+/// becomes "autoLockSecs".
+fn from_local_storage(fields: &mut Map<String, Value>) {
+    if let Some(ms) = fields.remove("autolock_duration") {
+        let ms = match &ms {
+            Value::String(s) => s.trim().parse::<u64>().ok(),
+            other => other.as_u64(),
+        };
+        if let Some(ms) = ms {
+            fields.insert("autoLockSecs".to_string(), (ms / 1000).min(u64::from(MAX_TIMEOUT_SECS)).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrates_local_storage_settings() {
+        let legacy = serde_json::json!({"autolock_duration": "900000", "theme": "dark"});
+        let settings = VaultSettings::from_stored(&legacy).unwrap();
+        assert_eq!((settings.version, settings.auto_lock_secs, settings.clipboard_clear_secs), (1, 900, 30));
+        assert_eq!(settings.extra["theme"], "dark");
+        assert!(!settings.to_stored().unwrap().as_object().unwrap().contains_key("autolock_duration"));
+        assert_eq!(VaultSettings::from_stored(&Value::Null).unwrap(), VaultSettings::default());
+    }
+
+    #[test]
+    fn test_roundtrip_and_newer_versions() {
+        let mut settings = VaultSettings { auto_lock_secs: 0, ..Default::default() };
+        settings.generator.length = 32;
+        let stored = settings.to_stored().unwrap();
+        assert_eq!(stored["generator"]["use_symbols"], true);
+        assert_eq!(VaultSettings::from_stored(&stored).unwrap(), settings);
+
+        let newer = serde_json::json!({"version": 7, "autoLockSecs": 60, "biometrics": true});
+        let read = VaultSettings::from_stored(&newer).unwrap();
+        assert_eq!((read.version, read.auto_lock_secs), (7, 60));
+        assert_eq!(read.to_stored().unwrap()["biometrics"], true);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_values() {
+        assert!(VaultSettings::from_stored(&serde_json::json!({"version": 1, "autoLockSecs": 90_000})).is_err());
+        let weak = serde_json::json!({"version": 1, "kdf": {"memoryKib": 1024, "iterations": 1, "parallelism": 1}});
+        assert!(VaultSettings::from_stored(&weak).unwrap_err().contains("weaker"));
+        assert!(VaultSettings::from_stored(&serde_json::json!([1])).is_err());
+        let twins = serde_json::json!({"version": 1, "presets": [{"name": "Wi-Fi", "style": "mac"}, {"name": "wi-fi ", "style": "mac"}]});
+        assert!(VaultSettings::from_stored(&twins).unwrap_err().contains("two generator presets"));
+    }
+
+    #[test]
+    fn test_presets_by_name() {
+        let mut settings = VaultSettings::default();
+        let banking: GeneratorPreset = serde_json::from_value(serde_json::json!({
+            "name": " Banking 20 chars no symbols ", "style": "password",
+            "length": 20, "use_uppercase": true, "use_numbers": true, "use_symbols": false
+        }))
+        .unwrap();
+        settings.save_preset(banking).unwrap();
+        settings.save_preset(GeneratorPreset { name: "Wifi passphrase".to_string(), style: GeneratorStyle::Mac }).unwrap();
+        let passphrase = GeneratorStyle::Passphrase(Default::default());
+        settings.save_preset(GeneratorPreset { name: "WIFI PASSPHRASE".to_string(), style: passphrase }).unwrap();
+
+        assert_eq!(settings.presets.len(), 2);
+        assert_eq!(settings.preset("wifi passphrase").unwrap().style, GeneratorStyle::Passphrase(Default::default()));
+        assert_eq!(settings.preset("banking 20 chars no symbols").unwrap().name, "Banking 20 chars no symbols");
+        let stored = settings.to_stored().unwrap();
+        assert_eq!(stored["presets"][0]["style"], "password");
+        assert_eq!(VaultSettings::from_stored(&stored).unwrap(), settings);
+
+        assert!(settings.remove_preset("Wifi Passphrase") && !settings.remove_preset("Wifi Passphrase"));
+        assert!(settings.save_preset(GeneratorPreset { name: "  ".to_string(), style: GeneratorStyle::Mac }).is_err());
+    }
+}
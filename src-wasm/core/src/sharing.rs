@@ -0,0 +1,477 @@
+// --- Entry Sharing ---
+// Sends one entry to another person without sharing a vault with them. The
+// entry is sealed to the recipient's X25519 public key (the same keypair format
+// as an emergency contact's) with a one-time key, so the bundle can travel over
+// any channel: only the recipient's secret key opens it.
+//
+// Only the credential goes in: title, URL, username, password and notes, plus the
+// TOTP seed if the sender chooses. Password history, folder, flags, usage counts
+// and anything the app attached are left behind.
+//
+// A keypair that may be compromised is retired with 'rotate_keypair': received
+// bundles are re-sealed to a new keypair, and a rotation record signed by both
+// the old and the new key tells contacts where to send from now on. The signing
+// key is Ed25519, derived from the X25519 secret, so a keypair still has one
+// secret to store; its public half ('verify_key') is published next to the
+// X25519 public key.
+//
+// A share can also be time-boxed ('share_entry_until'): the header carries an
+// expiry and the owner's verify key, and the owner signs the header together
+// with the one-time key that wraps the entry. The recipient's app refuses the
+// bundle once the expiry has passed, so a share left on storage that can't
+// delete anything (a shared drive, a mail archive) still stops opening. The
+// check runs on the recipient's clock, and a time-boxed share is only ever
+// opened for viewing, never copied into a vault. Apps that predate expiry can't
+// open one at all: their header lacks the new fields, so the AAD doesn't match.
+//
+// Bundles are committed to their one-time key ('keys::open_committed'), so a
+// crafted bundle can't open as one entry for one recipient and another for the next.
+// Bundles from before that (version 1) still open as plain AES-GCM; the version
+// is part of the AAD, so a version 2 bundle can't be relabelled as one.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::emergency::{decode_key, derive_wrapping_key};
+use crate::keys::{derive_subkey, open, open_committed, seal_committed};
+use crate::redact;
+use crate::vault::VaultEntry;
+
+/// Committed to the one-time key. Version 1 bundles predate commitments.
+pub const SHARE_VERSION: u8 = 2;
+const LEGACY_SHARE_VERSION: u8 = 1;
+
+/// Domain separation label for the HKDF step.
+pub(crate) const HKDF_INFO: &[u8] = b"securepass-share-entry-v1";
+const SIGNING_PURPOSE: &str = "securepass-share-signing-v1";
+/// Prefixed to a time-boxed share's header and one-time key before signing.
+const TOKEN_CONTEXT: &[u8] = b"securepass-share-token-v1";
+/// Prefixed to the rotation JSON before signing, so the signature can't be replayed elsewhere.
+const ROTATION_CONTEXT: &[u8] = b"securepass-share-rotation-v1";
+pub const ROTATION_VERSION: u8 = 1;
+
+/// The public part of a bundle. Its JSON is the AES-GCM AAD, so none of it can be changed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareHeader {
+    pub version: u8,
+    pub share_id: String,
+    pub recipient_public_key: String,
+    pub created_at: u64,
+    /// When a time-boxed share stops opening.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// The owner's Ed25519 verify key, on time-boxed shares.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_verify_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedEntry {
+    pub header: ShareHeader,
+    pub ephemeral_public_key: String,
+    pub iv: String,
+    pub data: String,
+    /// The owner's signature over the header and the one-time key, on time-boxed shares.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_signature: Option<String>,
+}
+
+/// The copy of `entry` that gets shared.
+fn shareable(entry: &VaultEntry, include_totp: bool) -> VaultEntry {
+    VaultEntry {
+        id: entry.id.clone(),
+        title: entry.title.clone(),
+        username: entry.username.clone(),
+        password: entry.password.clone(),
+        url: entry.url.clone(),
+        notes: entry.notes.clone(),
+        totp_secret: if include_totp { entry.totp_secret.clone() } else { None },
+        last_modified: entry.last_modified,
+        ..Default::default()
+    }
+}
+
+fn new_header(recipient_public_key: &str, now: u64) -> ShareHeader {
+    let mut id_bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut id_bytes);
+    ShareHeader {
+        version: SHARE_VERSION,
+        share_id: id_bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        recipient_public_key: recipient_public_key.to_string(),
+        created_at: now,
+        expires_at: None,
+        owner_verify_key: None,
+    }
+}
+
+/// Seals `entry` to `recipient_public_key` (Base64 X25519).
+pub fn share_entry(entry: &VaultEntry, recipient_public_key: &str, include_totp: bool, now: u64) -> Result<SharedEntry, String> {
+    let mut copy = shareable(entry, include_totp);
+    let bundle = seal_bundle(new_header(recipient_public_key, now), &copy);
+    copy.wipe();
+    bundle
+}
+
+/// Seals `entry` like 'share_entry', to stop opening at `expires_at`. The expiry is
+/// signed with the owner's sharing secret key (Base64 X25519).
+pub fn share_entry_until(
+    entry: &VaultEntry,
+    recipient_public_key: &str,
+    include_totp: bool,
+    owner_secret_key: &str,
+    expires_at: u64,
+    now: u64,
+) -> Result<SharedEntry, String> {
+    if expires_at <= now {
+        return Err("Share error: the expiry must be in the future".to_string());
+    }
+    let owner = signing_key(owner_secret_key)?;
+    let header = ShareHeader {
+        expires_at: Some(expires_at),
+        owner_verify_key: Some(B64.encode(owner.verifying_key().as_bytes())),
+        ..new_header(recipient_public_key, now)
+    };
+    let mut copy = shareable(entry, include_totp);
+    let bundle = seal_bundle(header, &copy);
+    copy.wipe();
+
+    let mut bundle = bundle?;
+    bundle.owner_signature = Some(B64.encode(owner.sign(&token_message(&bundle)?).to_bytes()));
+    Ok(bundle)
+}
+
+fn token_message(bundle: &SharedEntry) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(&(&bundle.header, &bundle.ephemeral_public_key))
+        .map_err(|e| format!("Share serialize error: {}", e))?;
+    Ok([TOKEN_CONTEXT, &json].concat())
+}
+
+/// Refuses a time-boxed share that isn't signed by the key it names, or that has expired.
+fn check_expiry(bundle: &SharedEntry, now: u64) -> Result<(), String> {
+    let Some(expires_at) = bundle.header.expires_at else {
+        return Ok(());
+    };
+    let (Some(key), Some(signature)) = (&bundle.header.owner_verify_key, &bundle.owner_signature) else {
+        return Err("Share error: the share's expiry is not signed".to_string());
+    };
+    if !signature_matches(key, signature, &token_message(bundle)?)? {
+        return Err("Share error: the owner's signature does not match".to_string());
+    }
+    if now >= expires_at {
+        return Err(format!("Share error: this share expired at {}", expires_at));
+    }
+    Ok(())
+}
+
+fn seal_bundle(header: ShareHeader, copy: &VaultEntry) -> Result<SharedEntry, String> {
+    let recipient = PublicKey::from(decode_key(&header.recipient_public_key, "Recipient public key")?);
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&recipient);
+    let mut key = derive_wrapping_key(shared.as_bytes(), &ephemeral_public, &recipient, HKDF_INFO)?;
+
+    let mut plain = serde_json::to_vec(copy).map_err(|e| format!("Entry serialize error: {}", e))?;
+    let aad = serde_json::to_vec(&header).map_err(|e| format!("Share serialize error: {}", e))?;
+    let sealed = seal_committed(&key, &plain, &aad);
+    key.zeroize();
+    plain.zeroize();
+
+    let (iv, data) = sealed?;
+    Ok(SharedEntry {
+        header,
+        ephemeral_public_key: B64.encode(ephemeral_public.as_bytes()),
+        iv: B64.encode(iv),
+        data: B64.encode(data),
+        owner_signature: None,
+    })
+}
+
+/// Opens a bundle with the recipient's secret key (Base64 X25519). A time-boxed
+/// bundle must be signed by its owner and not expired at `now`.
+pub fn open_shared_entry(bundle: &SharedEntry, recipient_secret_key: &str, now: u64) -> Result<VaultEntry, String> {
+    if ![LEGACY_SHARE_VERSION, SHARE_VERSION].contains(&bundle.header.version) {
+        return Err(format!("Unsupported share version: {}", bundle.header.version));
+    }
+    check_expiry(bundle, now)?;
+    let mut secret_bytes = decode_key(recipient_secret_key, "Recipient secret key")?;
+    let secret = StaticSecret::from(secret_bytes);
+    secret_bytes.zeroize();
+
+    let recipient = PublicKey::from(&secret);
+    if B64.encode(recipient.as_bytes()) != bundle.header.recipient_public_key {
+        return Err("Share error: this entry was shared with someone else".to_string());
+    }
+
+    let ephemeral_public = PublicKey::from(decode_key(&bundle.ephemeral_public_key, "Ephemeral key")?);
+    let shared = secret.diffie_hellman(&ephemeral_public);
+    let mut key = derive_wrapping_key(shared.as_bytes(), &ephemeral_public, &recipient, HKDF_INFO)?;
+
+    let iv = B64.decode(&bundle.iv).map_err(|e| format!("IV decode error: {}", e))?;
+    let data = B64.decode(&bundle.data).map_err(|e| format!("Share decode error: {}", e))?;
+    let aad = serde_json::to_vec(&bundle.header).map_err(|e| format!("Share serialize error: {}", e))?;
+    let plain = if bundle.header.version == LEGACY_SHARE_VERSION {
+        open(&key, &iv, &data, &aad)
+    } else {
+        open_committed(&key, &iv, &data, &aad)
+    };
+    key.zeroize();
+
+    let mut plain = plain?;
+    let entry = serde_json::from_slice(&plain).map_err(|e| redact::json_error("Entry parse", &e));
+    plain.zeroize();
+    entry
+}
+
+// --- Key Rotation ---
+
+/// A sharing keypair: X25519 for sealing, plus the Ed25519 key that checks its
+/// rotation records. Base64, like 'emergency::EmergencyKeypair'.
+#[derive(Serialize, Deserialize)]
+pub struct SharingKeypair {
+    pub public_key: String,
+    pub secret_key: String,
+    pub verify_key: String,
+}
+
+/// "Send to the new key from now on", as signed by both keys.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyRotation {
+    pub version: u8,
+    pub old_public_key: String,
+    pub old_verify_key: String,
+    pub new_public_key: String,
+    pub new_verify_key: String,
+    pub rotated_at: u64,
+}
+
+/// A rotation with the old key's signature (it authorizes the change) and the
+/// new key's (its holder has the secret).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationRecord {
+    pub rotation: KeyRotation,
+    pub old_signature: String,
+    pub new_signature: String,
+}
+
+/// What 'rotate_keypair' hands back: store the keypair and bundles in place of the
+/// old ones, and publish the record.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotatedKeypair {
+    pub keypair: SharingKeypair,
+    pub received: Vec<SharedEntry>,
+    pub record: RotationRecord,
+    /// Share IDs of the time-boxed bundles, which can't be re-sealed (the owner
+    /// signed them for the old key); the owner has to share them again.
+    pub left_behind: Vec<String>,
+}
+
+/// The Ed25519 public key that goes with an X25519 secret key (Base64).
+pub fn verify_key(secret_key: &str) -> Result<String, String> {
+    Ok(B64.encode(signing_key(secret_key)?.verifying_key().as_bytes()))
+}
+
+/// Generates a new keypair, re-seals every bundle in `received` (all must be
+/// addressed to `old_secret_key`) to it, keeping their IDs and dates, and signs
+/// the rotation. Fails without changing anything if a bundle doesn't open.
+/// Time-boxed bundles are left out, listed in 'left_behind'.
+pub fn rotate_keypair(old_secret_key: &str, received: &[SharedEntry], now: u64) -> Result<RotatedKeypair, String> {
+    let old_signing = signing_key(old_secret_key)?;
+    let old_public = PublicKey::from(&StaticSecret::from(decode_key(old_secret_key, "Recipient secret key")?));
+
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let keypair = SharingKeypair {
+        public_key: B64.encode(PublicKey::from(&secret).as_bytes()),
+        secret_key: B64.encode(secret.to_bytes()),
+        verify_key: String::new(),
+    };
+    let new_signing = signing_key(&keypair.secret_key)?;
+    let keypair = SharingKeypair { verify_key: B64.encode(new_signing.verifying_key().as_bytes()), ..keypair };
+
+    let mut rewrapped = Vec::with_capacity(received.len());
+    let mut left_behind = Vec::new();
+    for bundle in received {
+        if bundle.header.expires_at.is_some() {
+            left_behind.push(bundle.header.share_id.clone());
+            continue;
+        }
+        let mut entry = open_shared_entry(bundle, old_secret_key, now)?;
+        let header =
+            ShareHeader { version: SHARE_VERSION, recipient_public_key: keypair.public_key.clone(), ..bundle.header.clone() };
+        let resealed = seal_bundle(header, &entry);
+        entry.wipe();
+        rewrapped.push(resealed?);
+    }
+
+    let rotation = KeyRotation {
+        version: ROTATION_VERSION,
+        old_public_key: B64.encode(old_public.as_bytes()),
+        old_verify_key: B64.encode(old_signing.verifying_key().as_bytes()),
+        new_public_key: keypair.public_key.clone(),
+        new_verify_key: keypair.verify_key.clone(),
+        rotated_at: now,
+    };
+    let message = rotation_message(&rotation)?;
+    let record = RotationRecord {
+        old_signature: B64.encode(old_signing.sign(&message).to_bytes()),
+        new_signature: B64.encode(new_signing.sign(&message).to_bytes()),
+        rotation,
+    };
+    Ok(RotatedKeypair { keypair, received: rewrapped, record, left_behind })
+}
+
+/// Checks a rotation record against the verify key a contact already trusts for
+/// the old keypair. On success the record's new keys replace the old ones.
+pub fn verify_rotation(record: &RotationRecord, trusted_verify_key: &str) -> Result<(), String> {
+    let rotation = &record.rotation;
+    if rotation.version != ROTATION_VERSION {
+        return Err(format!("Unsupported key rotation version: {}", rotation.version));
+    }
+    if rotation.old_verify_key != trusted_verify_key {
+        return Err("Rotation error: the record is for a different key".to_string());
+    }
+    let message = rotation_message(rotation)?;
+    for (key, signature, which) in [
+        (&rotation.old_verify_key, &record.old_signature, "old"),
+        (&rotation.new_verify_key, &record.new_signature, "new"),
+    ] {
+        if !signature_matches(key, signature, &message)? {
+            return Err(format!("Rotation error: the {} key's signature does not match", which));
+        }
+    }
+    Ok(())
+}
+
+/// Checks an Ed25519 signature (both Base64). Errors only if they don't decode.
+fn signature_matches(verify_key: &str, signature: &str, message: &[u8]) -> Result<bool, String> {
+    let key = VerifyingKey::from_bytes(&decode_key(verify_key, "Verify key")?).map_err(|e| format!("Verify key error: {}", e))?;
+    let signature = B64.decode(signature).map_err(|e| format!("Signature decode error: {}", e))?;
+    let signature = Signature::from_slice(&signature).map_err(|e| format!("Signature error: {}", e))?;
+    Ok(key.verify_strict(message, &signature).is_ok())
+}
+
+fn rotation_message(rotation: &KeyRotation) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(rotation).map_err(|e| format!("Rotation serialize error: {}", e))?;
+    Ok([ROTATION_CONTEXT, &json].concat())
+}
+
+fn signing_key(secret_key: &str) -> Result<SigningKey, String> {
+    let mut secret = decode_key(secret_key, "Recipient secret key")?;
+    let mut seed = derive_subkey(&secret, SIGNING_PURPOSE, b"");
+    secret.zeroize();
+    let key = SigningKey::from_bytes(&seed);
+    seed.zeroize();
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emergency::generate_keypair;
+
+    fn entry() -> VaultEntry {
+        VaultEntry {
+            id: "1".to_string(),
+            title: "Router".to_string(),
+            username: "admin".to_string(),
+            password: "hunter2".to_string(),
+            category: "home".to_string(),
+            totp_secret: Some("JBSWY3DP".to_string()),
+            history: vec!["old".to_string()],
+            use_count: 9,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_share_roundtrip_carries_only_the_credential() {
+        let bob = generate_keypair();
+        let bundle = share_entry(&entry(), &bob.public_key, false, 1_700_000_000).unwrap();
+        let received = open_shared_entry(&bundle, &bob.secret_key, 0).unwrap();
+
+        assert_eq!((received.title.as_str(), received.password.as_str()), ("Router", "hunter2"));
+        assert_eq!(received.totp_secret, None);
+        assert!(received.history.is_empty() && received.category.is_empty() && received.use_count == 0);
+
+        let with_totp = share_entry(&entry(), &bob.public_key, true, 0).unwrap();
+        assert_eq!(open_shared_entry(&with_totp, &bob.secret_key, 0).unwrap().totp_secret.as_deref(), Some("JBSWY3DP"));
+    }
+
+    #[test]
+    fn test_only_the_recipient_opens_an_untampered_bundle() {
+        let (bob, eve) = (generate_keypair(), generate_keypair());
+        let bundle = share_entry(&entry(), &bob.public_key, false, 0).unwrap();
+        assert!(open_shared_entry(&bundle, &eve.secret_key, 0).unwrap_err().contains("someone else"));
+
+        let mut backdated = bundle.clone();
+        backdated.header.created_at = 1;
+        assert!(open_shared_entry(&backdated, &bob.secret_key, 0).is_err());
+        assert!(share_entry(&entry(), "not-a-key", false, 0).is_err());
+
+        // Neither relabelling it as a version 1 bundle nor cutting off the commitment opens it.
+        let mut relabelled = bundle.clone();
+        relabelled.header.version = LEGACY_SHARE_VERSION;
+        assert!(open_shared_entry(&relabelled, &bob.secret_key, 0).is_err());
+        let mut stripped = bundle.clone();
+        stripped.data = B64.encode(&B64.decode(&bundle.data).unwrap()[crate::keys::COMMITMENT_OVERHEAD..]);
+        assert!(open_shared_entry(&stripped, &bob.secret_key, 0).is_err());
+    }
+
+    #[test]
+    fn test_rotation_rewraps_received_entries_and_verifies() {
+        let bob = generate_keypair();
+        let bundle = share_entry(&entry(), &bob.public_key, false, 7).unwrap();
+        let rotated = rotate_keypair(&bob.secret_key, std::slice::from_ref(&bundle), 100).unwrap();
+
+        let moved = &rotated.received[0];
+        assert_eq!((&moved.header.share_id, moved.header.created_at), (&bundle.header.share_id, 7));
+        assert_eq!(open_shared_entry(moved, &rotated.keypair.secret_key, 100).unwrap().password, "hunter2");
+        assert!(open_shared_entry(moved, &bob.secret_key, 100).is_err());
+        assert_eq!(rotated.keypair.verify_key, verify_key(&rotated.keypair.secret_key).unwrap());
+
+        let trusted = verify_key(&bob.secret_key).unwrap();
+        verify_rotation(&rotated.record, &trusted).unwrap();
+        assert!(verify_rotation(&rotated.record, &rotated.keypair.verify_key).is_err());
+        let mut redirected = rotated.record.clone();
+        redirected.rotation.new_public_key = generate_keypair().public_key;
+        assert!(verify_rotation(&redirected, &trusted).unwrap_err().contains("signature"));
+
+        let eve = generate_keypair();
+        assert!(rotate_keypair(&eve.secret_key, std::slice::from_ref(&bundle), 100).is_err());
+
+        let alice = generate_keypair();
+        let boxed = share_entry_until(&entry(), &bob.public_key, false, &alice.secret_key, 500, 7).unwrap();
+        let rotated = rotate_keypair(&bob.secret_key, &[bundle, boxed.clone()], 100).unwrap();
+        assert_eq!((rotated.received.len(), rotated.left_behind.clone()), (1, vec![boxed.header.share_id]));
+    }
+
+    #[test]
+    fn test_time_boxed_share_stops_opening() {
+        let (alice, bob) = (generate_keypair(), generate_keypair());
+        let bundle = share_entry_until(&entry(), &bob.public_key, false, &alice.secret_key, 1_000, 100).unwrap();
+        assert_eq!(bundle.header.owner_verify_key, Some(verify_key(&alice.secret_key).unwrap()));
+        assert_eq!(open_shared_entry(&bundle, &bob.secret_key, 999).unwrap().password, "hunter2");
+        assert!(open_shared_entry(&bundle, &bob.secret_key, 1_000).unwrap_err().contains("expired"));
+
+        // Pushing the expiry out breaks the signature (and the AAD); so does dropping it.
+        let mut extended = bundle.clone();
+        extended.header.expires_at = Some(u64::MAX);
+        assert!(open_shared_entry(&extended, &bob.secret_key, 1_500).unwrap_err().contains("signature"));
+        let mut unsigned = bundle.clone();
+        unsigned.owner_signature = None;
+        assert!(open_shared_entry(&unsigned, &bob.secret_key, 500).unwrap_err().contains("not signed"));
+        let mut forever = bundle;
+        forever.header.expires_at = None;
+        assert!(open_shared_entry(&forever, &bob.secret_key, 1_500).is_err());
+
+        assert!(share_entry_until(&entry(), &bob.public_key, false, &alice.secret_key, 100, 100).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+// --- Storage Codec ---
+// How SecurePass lays out its data in IndexedDB. JS only moves opaque
+// key/value pairs between this module and the object store; the binary
+// layout, chunking and schema upgrades all live here.
+//
+// Schema 1 is the localStorage layout the web app has always used (JSON strings
+// under 'encrypted_vault', 'vault_salt', ...). Schema 2 is the IndexedDB layout
+// below. 'migrate_v1_to_v2' converts the first into the second.
+//
+// Every stored value is one chunk of a record:
+//
+//   offset  size  field
+//   0       4     magic "SPDB"
+//   4       1     schema version
+//   5       1     record kind (see RecordKind)
+//   6       1     flags (bit 0: payload is encrypted; bit 1: a vault record's
+//                 package is committed to its key, package version 2)
+//   7       1     password policy of a vault record (text::PasswordPolicy);
+//                 0 in other records and in vaults stored before policies
+//   8       2     chunk index (u16 LE)
+//   10      2     chunk count (u16 LE)
+//   12      4     total payload length (u32 LE)
+//   16      ..    this chunk's slice of the payload
+//
+// Chunk i of record "vault" is stored under the key "vault/i". The manifest
+// (itself a record, under "manifest/0") lists every record and its chunk count.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::duress::{EncryptedPackage, LEGACY_PACKAGE_VERSION, PACKAGE_VERSION};
+use crate::text::PasswordPolicy;
+use crate::validate;
+
+pub const SCHEMA_VERSION: u8 = 2;
+pub const MAGIC: [u8; 4] = *b"SPDB";
+pub const HEADER_LEN: usize = 16;
+/// IndexedDB handles large values, but smaller chunks keep each transaction
+/// and structured-clone copy cheap.
+pub const CHUNK_SIZE: usize = 256 * 1024;
+pub const MANIFEST_KEY: &str = "manifest";
+
+const FLAG_ENCRYPTED: u8 = 1;
+const FLAG_COMMITTED: u8 = 2;
+const IV_LEN: usize = 12;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    /// Salt + encrypted vault package.
+    Vault,
+    /// Encrypted attachment content.
+    Attachment,
+    /// Index of every stored record (plain JSON).
+    Manifest,
+    /// Non-secret app settings (plain JSON object of strings).
+    Settings,
+    /// The last complete write (plain JSON, see 'commit').
+    Commit,
+}
+
+impl RecordKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            RecordKind::Vault => 1,
+            RecordKind::Attachment => 2,
+            RecordKind::Manifest => 3,
+            RecordKind::Settings => 4,
+            RecordKind::Commit => 5,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<RecordKind, String> {
+        match byte {
+            1 => Ok(RecordKind::Vault),
+            2 => Ok(RecordKind::Attachment),
+            3 => Ok(RecordKind::Manifest),
+            4 => Ok(RecordKind::Settings),
+            5 => Ok(RecordKind::Commit),
+            other => Err(format!("Unknown record kind: {}", other)),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<RecordKind, String> {
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| format!("Unknown record kind: {}", name))
+    }
+}
+
+/// One key/value pair for the IndexedDB object store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredRecord {
+    pub key: String,
+    pub value: Vec<u8>,
+}
+
+/// A record after its chunks have been reassembled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedRecord {
+    pub kind: RecordKind,
+    pub encrypted: bool,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    pub key: String,
+    pub kind: RecordKind,
+    pub chunks: u16,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub schema_version: u8,
+    pub updated_at: u64,
+    pub records: Vec<ManifestEntry>,
+}
+
+/// The store key of chunk `index` of record `key`.
+pub fn chunk_key(key: &str, index: u16) -> String {
+    format!("{}/{}", key, index)
+}
+
+// --- Record Encoding ---
+
+/// Splits `payload` into header-prefixed chunks stored under "key/0", "key/1", ...
+/// An empty payload still produces one chunk, so every record has a chunk 0.
+pub fn encode_record(key: &str, kind: RecordKind, encrypted: bool, payload: &[u8]) -> Result<Vec<StoredRecord>, String> {
+    encode_chunks(key, kind, if encrypted { FLAG_ENCRYPTED } else { 0 }, 0, payload)
+}
+
+fn encode_chunks(key: &str, kind: RecordKind, flags: u8, policy: u8, payload: &[u8]) -> Result<Vec<StoredRecord>, String> {
+    let total = u32::try_from(payload.len()).map_err(|_| "Record too large".to_string())?;
+    let count = payload.len().div_ceil(CHUNK_SIZE).max(1);
+    let count = u16::try_from(count).map_err(|_| "Record too large".to_string())?;
+
+    let mut chunks: Vec<&[u8]> = payload.chunks(CHUNK_SIZE).collect();
+    if chunks.is_empty() {
+        chunks.push(&[]);
+    }
+
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, body)| {
+            let mut value = Vec::with_capacity(HEADER_LEN + body.len());
+            value.extend_from_slice(&MAGIC);
+            value.push(SCHEMA_VERSION);
+            value.push(kind.to_byte());
+            value.push(flags);
+            value.push(policy);
+            value.extend_from_slice(&(index as u16).to_le_bytes());
+            value.extend_from_slice(&count.to_le_bytes());
+            value.extend_from_slice(&total.to_le_bytes());
+            value.extend_from_slice(body);
+            StoredRecord { key: chunk_key(key, index as u16), value }
+        })
+        .collect())
+}
+
+struct ChunkHeader {
+    kind: u8,
+    flags: u8,
+    policy: u8,
+    index: u16,
+    count: u16,
+    total: u32,
+}
+
+fn parse_header(chunk: &[u8]) -> Result<ChunkHeader, String> {
+    if chunk.len() < HEADER_LEN || chunk[0..4] != MAGIC {
+        return Err("Storage error: not a SecurePass record".to_string());
+    }
+    if chunk[4] != SCHEMA_VERSION {
+        return Err(format!("Storage error: schema {} needs migration to {}", chunk[4], SCHEMA_VERSION));
+    }
+    Ok(ChunkHeader {
+        kind: chunk[5],
+        flags: chunk[6],
+        policy: chunk[7],
+        index: u16::from_le_bytes([chunk[8], chunk[9]]),
+        count: u16::from_le_bytes([chunk[10], chunk[11]]),
+        total: u32::from_le_bytes([chunk[12], chunk[13], chunk[14], chunk[15]]),
+    })
+}
+
+/// Reassembles a record from its chunks, in key order. Rejects missing,
+/// reordered or mixed-up chunks rather than returning a corrupted payload.
+pub fn decode_record(chunks: &[Vec<u8>]) -> Result<DecodedRecord, String> {
+    let first = parse_header(chunks.first().ok_or("Storage error: record has no chunks")?)?;
+    if usize::from(first.count) != chunks.len() {
+        return Err(format!("Storage error: expected {} chunks, got {}", first.count, chunks.len()));
+    }
+
+    let mut payload = Vec::with_capacity(first.total as usize);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let header = parse_header(chunk)?;
+        if usize::from(header.index) != i
+            || header.count != first.count
+            || header.kind != first.kind
+            || header.flags != first.flags
+            || header.policy != first.policy
+            || header.total != first.total
+        {
+            return Err(format!("Storage error: chunk {} does not belong to this record", i));
+        }
+        payload.extend_from_slice(&chunk[HEADER_LEN..]);
+    }
+    if payload.len() != first.total as usize {
+        return Err("Storage error: record is truncated".to_string());
+    }
+
+    Ok(DecodedRecord {
+        kind: RecordKind::from_byte(first.kind)?,
+        encrypted: first.flags & FLAG_ENCRYPTED != 0,
+        payload,
+    })
+}
+
+// --- Payloads ---
+
+/// Vault payload: salt length (u8) | salt | IV (12 bytes) | ciphertext.
+/// Keeping the salt next to its ciphertext means a vault record is self-contained.
+pub fn encode_vault_payload(salt: &[u8], package: &EncryptedPackage) -> Result<Vec<u8>, String> {
+    validate::salt(salt)?;
+    validate::iv(&package.iv)?;
+    let salt_len = u8::try_from(salt.len()).map_err(|_| "Salt too long".to_string())?;
+    let mut out = Vec::with_capacity(1 + salt.len() + IV_LEN + package.data.len());
+    out.push(salt_len);
+    out.extend_from_slice(salt);
+    out.extend_from_slice(&package.iv);
+    out.extend_from_slice(&package.data);
+    Ok(out)
+}
+
+/// Splits a vault payload back into (salt, package). The payload doesn't say whether
+/// the package is committed; the package comes back as legacy (version 1).
+pub fn decode_vault_payload(payload: &[u8]) -> Result<(Vec<u8>, EncryptedPackage), String> {
+    let salt_len = usize::from(*payload.first().ok_or("Storage error: empty vault payload")?);
+    let iv_start = 1 + salt_len;
+    if payload.len() < iv_start + IV_LEN {
+        return Err("Storage error: vault payload is truncated".to_string());
+    }
+    Ok((
+        payload[1..iv_start].to_vec(),
+        EncryptedPackage::legacy(&payload[iv_start..iv_start + IV_LEN], &payload[iv_start + IV_LEN..]),
+    ))
+}
+
+/// A vault record: the payload above, with the password policy the vault's key
+/// was derived under in the header, so the record holds every KDF input but the password,
+/// and whether the package is committed in its flags.
+pub fn encode_vault_record(
+    key: &str,
+    salt: &[u8],
+    policy: PasswordPolicy,
+    package: &EncryptedPackage,
+) -> Result<Vec<StoredRecord>, String> {
+    let flags = match package.version {
+        LEGACY_PACKAGE_VERSION => FLAG_ENCRYPTED,
+        PACKAGE_VERSION => FLAG_ENCRYPTED | FLAG_COMMITTED,
+        other => return Err(format!("Unsupported package version: {}", other)),
+    };
+    encode_chunks(key, RecordKind::Vault, flags, policy.version(), &encode_vault_payload(salt, package)?)
+}
+
+/// Reads a vault record back into (salt, password policy, package).
+pub fn decode_vault_record(chunks: &[Vec<u8>]) -> Result<(Vec<u8>, PasswordPolicy, EncryptedPackage), String> {
+    let record = decode_record(chunks)?;
+    if record.kind != RecordKind::Vault {
+        return Err("Storage error: not a vault record".to_string());
+    }
+    // decode_record has checked every chunk carries the same policy byte.
+    let policy = PasswordPolicy::from_version(chunks[0][7])?;
+    let (salt, mut package) = decode_vault_payload(&record.payload)?;
+    if chunks[0][6] & FLAG_COMMITTED != 0 {
+        package.version = PACKAGE_VERSION;
+    }
+    Ok((salt, policy, package))
+}
+
+/// The manifest record describing `records` (which should not include the manifest itself).
+pub fn encode_manifest(records: &[StoredRecord], now: u64) -> Result<Vec<StoredRecord>, String> {
+    update_manifest(None, records, now)
+}
+
+/// 'encode_manifest' for a write that replaces only some records: `previous` lists
+/// the rest, and `records` replace or add to its entries.
+pub fn update_manifest(previous: Option<&Manifest>, records: &[StoredRecord], now: u64) -> Result<Vec<StoredRecord>, String> {
+    let mut entries: BTreeMap<String, ManifestEntry> =
+        previous.into_iter().flat_map(|m| m.records.iter()).map(|e| (e.key.clone(), e.clone())).collect();
+    for record in records {
+        let (key, _) = record.key.rsplit_once('/').ok_or("Storage error: chunk key without index")?;
+        let header = parse_header(&record.value)?;
+        entries.insert(
+            key.to_string(),
+            ManifestEntry {
+                key: key.to_string(),
+                kind: RecordKind::from_byte(header.kind)?,
+                chunks: header.count,
+                size: u64::from(header.total),
+            },
+        );
+    }
+    let manifest = Manifest { schema_version: SCHEMA_VERSION, updated_at: now, records: entries.into_values().collect() };
+    let json = serde_json::to_vec(&manifest).map_err(|e| format!("Manifest serialize error: {}", e))?;
+    encode_record(MANIFEST_KEY, RecordKind::Manifest, false, &json)
+}
+
+pub fn decode_manifest(chunks: &[Vec<u8>]) -> Result<Manifest, String> {
+    let record = decode_record(chunks)?;
+    if record.kind != RecordKind::Manifest {
+        return Err("Storage error: not a manifest record".to_string());
+    }
+    serde_json::from_slice(&record.payload).map_err(|e| format!("Manifest parse error: {}", e))
+}
+
+// --- Migrations ---
+
+/// localStorage keys that hold (salt, vault, password policy) in schema 1, and their
+/// schema 2 record keys. A vault without a policy key predates policies: `Raw`.
+const LEGACY_VAULTS: [(&str, &str, &str, &str); 2] = [
+    ("encrypted_vault", "vault_salt", "vault_password_policy", "vault"),
+    ("decoy_vault", "decoy_salt", "decoy_password_policy", "decoy_vault"),
+];
+
+/// Schema 1 -> 2. `items` is every localStorage entry (key -> raw string value).
+/// Returns the full set of records to write, manifest included. Anything that
+/// isn't a vault or salt is carried over unchanged in the settings record, so
+/// nothing the app stored is lost.
+pub fn migrate_v1_to_v2(items: &BTreeMap<String, String>, now: u64) -> Result<Vec<StoredRecord>, String> {
+    let mut records = Vec::new();
+    let mut settings = items.clone();
+
+    for (vault_key, salt_key, policy_key, record_key) in LEGACY_VAULTS {
+        let Some(vault_json) = settings.remove(vault_key) else { continue };
+        let salt_json = settings
+            .remove(salt_key)
+            .ok_or_else(|| format!("Migration error: '{}' has no '{}'", vault_key, salt_key))?;
+
+        let package: EncryptedPackage =
+            serde_json::from_str(&vault_json).map_err(|e| format!("Migration error ({}): {}", vault_key, e))?;
+        let salt: Vec<u8> =
+            serde_json::from_str(&salt_json).map_err(|e| format!("Migration error ({}): {}", salt_key, e))?;
+        let policy = match settings.remove(policy_key) {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Migration error ({}): {}", policy_key, e))?,
+            None => PasswordPolicy::Raw,
+        };
+        records.extend(encode_vault_record(record_key, &salt, policy, &package)?);
+    }
+    // A decoy salt without a decoy vault is leftover state; it has nothing to protect.
+    settings.remove("decoy_salt");
+    settings.remove("decoy_password_policy");
+
+    let settings_json = serde_json::to_vec(&settings).map_err(|e| format!("Settings serialize error: {}", e))?;
+    records.extend(encode_record("settings", RecordKind::Settings, false, &settings_json)?);
+
+    let manifest = encode_manifest(&records, now)?;
+    records.extend(manifest);
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(records: &[StoredRecord], key: &str) -> Vec<Vec<u8>> {
+        records.iter().filter(|r| r.key.rsplit_once('/').unwrap().0 == key).map(|r| r.value.clone()).collect()
+    }
+
+    #[test]
+    fn test_record_roundtrip_and_chunking() {
+        let payload: Vec<u8> = (0..CHUNK_SIZE * 2 + 5).map(|i| i as u8).collect();
+        let records = encode_record("att-1", RecordKind::Attachment, true, &payload).unwrap();
+        let keys: Vec<&str> = records.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, ["att-1/0", "att-1/1", "att-1/2"]);
+        assert_eq!(&records[0].value[0..8], b"SPDB\x02\x02\x01\x00");
+
+        let decoded = decode_record(&values(&records, "att-1")).unwrap();
+        assert_eq!((decoded.kind, decoded.encrypted), (RecordKind::Attachment, true));
+        assert_eq!(decoded.payload, payload);
+
+        let empty = encode_record("settings", RecordKind::Settings, false, b"").unwrap();
+        assert_eq!(empty.len(), 1);
+        assert_eq!(decode_record(&[empty[0].value.clone()]).unwrap().payload, b"");
+    }
+
+    #[test]
+    fn test_decode_rejects_damaged_records() {
+        let payload = vec![9u8; CHUNK_SIZE + 1];
+        let chunks = values(&encode_record("a", RecordKind::Attachment, true, &payload).unwrap(), "a");
+
+        assert!(decode_record(&chunks[..1]).is_err());
+        assert!(decode_record(&[chunks[1].clone(), chunks[0].clone()]).is_err());
+
+        let mut truncated = chunks.clone();
+        truncated[1].pop();
+        assert!(decode_record(&truncated).unwrap_err().contains("truncated"));
+
+        let mut old = chunks.clone();
+        old[0][4] = 1;
+        assert!(decode_record(&old).unwrap_err().contains("needs migration"));
+        assert!(decode_record(&[b"{\"iv\":[]}".to_vec()]).is_err());
+    }
+
+    #[test]
+    fn test_vault_payload_roundtrip() {
+        let package = EncryptedPackage::legacy(&[1; 12], &[2, 3, 4]);
+        let payload = encode_vault_payload(&[7; 32], &package).unwrap();
+        assert_eq!(decode_vault_payload(&payload).unwrap(), (vec![7; 32], package.clone()));
+        assert!(decode_vault_payload(&payload[..20]).is_err());
+
+        // The record's flags keep the package version.
+        for version in [LEGACY_PACKAGE_VERSION, PACKAGE_VERSION] {
+            let package = EncryptedPackage { version, ..package.clone() };
+            let records = encode_vault_record("vault", &[7; 16], PasswordPolicy::Raw, &package).unwrap();
+            assert_eq!(decode_vault_record(&values(&records, "vault")).unwrap().2, package);
+        }
+        let unknown = EncryptedPackage { version: 9, ..package };
+        assert!(encode_vault_record("vault", &[7; 16], PasswordPolicy::Raw, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2() {
+        let mut items = BTreeMap::new();
+        items.insert("encrypted_vault".to_string(), r#"{"iv":[1,1,1,1,1,1,1,1,1,1,1,1],"data":[5,6]}"#.to_string());
+        items.insert("vault_salt".to_string(), "[9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9]".to_string());
+        items.insert("theme".to_string(), "dark".to_string());
+        items.insert("vault_initialized".to_string(), "true".to_string());
+
+        let records = migrate_v1_to_v2(&items, 1_700_000_000).unwrap();
+        let (salt, policy, package) = decode_vault_record(&values(&records, "vault")).unwrap();
+        assert_eq!((salt, policy, package.data), (vec![9; 16], PasswordPolicy::Raw, vec![5, 6]));
+
+        let settings = decode_record(&values(&records, "settings")).unwrap();
+        assert_eq!(settings.payload, br#"{"theme":"dark","vault_initialized":"true"}"#);
+
+        let manifest = decode_manifest(&values(&records, MANIFEST_KEY)).unwrap();
+        let keys: Vec<&str> = manifest.records.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, ["settings", "vault"]);
+        assert_eq!(manifest.records[1].size, 1 + 16 + 12 + 2);
+
+        items.insert("vault_password_policy".to_string(), "1".to_string());
+        let records = migrate_v1_to_v2(&items, 0).unwrap();
+        assert_eq!(decode_vault_record(&values(&records, "vault")).unwrap().1, PasswordPolicy::Nfc);
+        assert!(decode_vault_record(&values(&records, "settings")).is_err());
+
+        items.remove("vault_salt");
+        assert!(migrate_v1_to_v2(&items, 0).unwrap_err().contains("vault_salt"));
+    }
+}
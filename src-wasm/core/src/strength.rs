@@ -0,0 +1,80 @@
+// --- Password Strength ---
+// The same estimate the web app shows under the password field (see
+// src/utils/password.ts): length times log2 of the character pools used, with
+// the same four labels. It is a rough pool-size measure, not a cracking model,
+// but it lets Rust-side reports bucket passwords exactly as the UI labels them.
+// One exception: a password on the common-password blocklist is weak however
+// long it is, since it is among the first guesses any attacker tries.
+
+use serde::Serialize;
+
+use crate::blocklist;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Strength {
+    /// Under 40 bits.
+    Weak,
+    /// 40 to 60 bits.
+    Fair,
+    /// 60 to 80 bits.
+    Good,
+    /// 80 bits or more.
+    Excellent,
+}
+
+impl Strength {
+    pub fn of(password: &str) -> Strength {
+        if blocklist::is_common_password(password) {
+            return Strength::Weak;
+        }
+        let bits = entropy_bits(password);
+        if bits < 40.0 {
+            Strength::Weak
+        } else if bits < 60.0 {
+            Strength::Fair
+        } else if bits < 80.0 {
+            Strength::Good
+        } else {
+            Strength::Excellent
+        }
+    }
+}
+
+/// Length (in characters) times log2 of the pool: 26 for lowercase, 26 for
+/// uppercase, 10 for digits and 33 for anything else, counted once each.
+pub fn entropy_bits(password: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut other) = (false, false, false, false);
+    let mut length = 0usize;
+    for c in password.chars() {
+        length += 1;
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            _ => other = true,
+        }
+    }
+    let pool = 26 * u32::from(lower) + 26 * u32::from(upper) + 10 * u32::from(digit) + 33 * u32::from(other);
+    if pool == 0 {
+        return 0.0;
+    }
+    length as f64 * f64::from(pool).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_the_web_app_labels() {
+        assert_eq!(entropy_bits(""), 0.0);
+        assert!((entropy_bits("abcdefgh") - 8.0 * 26f64.log2()).abs() < 1e-9);
+        assert_eq!(Strength::of("password"), Strength::Weak);
+        assert_eq!(Strength::of("Zebra7q!"), Strength::Fair);
+        assert_eq!(Strength::of("Zebrafish1234"), Strength::Good);
+        assert!(entropy_bits("Password1234") > 60.0);
+        assert_eq!(Strength::of("Password1234"), Strength::Weak);
+        assert_eq!(Strength::of("correct-Horse-battery-staple-9"), Strength::Excellent);
+    }
+}
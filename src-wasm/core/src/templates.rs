@@ -0,0 +1,186 @@
+// --- Entry Templates ---
+// Starting points for items that aren't logins: a bank account, a credit
+// card, a Wi-Fi router, an email account, a software license, a passport, an
+// identity. A template sets the entry type and adds the typed custom fields
+// such an item usually has, empty or with a common default, for the user to
+// fill in.
+//
+// Templates are fixed here rather than stored in the vault, so every client
+// offers the same ones and an entry made from one looks the same everywhere.
+
+use serde::Serialize;
+
+use crate::card;
+use crate::identity;
+use crate::license;
+use crate::vault::{new_entry_id, CustomField, FieldKind, VaultEntry};
+use crate::wifi;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct FieldTemplate {
+    pub name: &'static str,
+    pub kind: FieldKind,
+    /// Pre-filled value; empty for most fields.
+    pub default: &'static str,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Template {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// The entry type ('VaultEntry::entry_type') of entries made from it.
+    pub entry_type: &'static str,
+    pub fields: &'static [FieldTemplate],
+}
+
+const fn field(name: &'static str, kind: FieldKind) -> FieldTemplate {
+    FieldTemplate { name, kind, default: "" }
+}
+
+pub const TEMPLATES: [Template; 7] = [
+    Template {
+        id: "bank_account",
+        name: "Bank account",
+        entry_type: "bank_account",
+        fields: &[
+            field("Bank name", FieldKind::Text),
+            field("Account holder", FieldKind::Text),
+            field("Account number", FieldKind::Hidden),
+            field("Routing number", FieldKind::Text),
+            field("IBAN", FieldKind::Hidden),
+            field("SWIFT/BIC", FieldKind::Text),
+            field("PIN", FieldKind::Hidden),
+            field("Phone", FieldKind::Phone),
+        ],
+    },
+    Template {
+        id: "wifi_router",
+        name: "Wi-Fi router",
+        entry_type: wifi::ENTRY_TYPE,
+        fields: &[
+            field(wifi::SSID_FIELD, FieldKind::Text),
+            field(wifi::PASSWORD_FIELD, FieldKind::Hidden),
+            FieldTemplate { name: wifi::SECURITY_FIELD, kind: FieldKind::Text, default: "WPA2" },
+            field("Admin URL", FieldKind::Url),
+            field("Admin password", FieldKind::Hidden),
+        ],
+    },
+    Template {
+        id: "email_account",
+        name: "Email account",
+        entry_type: "email",
+        fields: &[
+            field("Email address", FieldKind::Email),
+            field("IMAP server", FieldKind::Text),
+            FieldTemplate { name: "IMAP port", kind: FieldKind::Number, default: "993" },
+            field("SMTP server", FieldKind::Text),
+            FieldTemplate { name: "SMTP port", kind: FieldKind::Number, default: "587" },
+        ],
+    },
+    Template {
+        id: "credit_card",
+        name: "Credit card",
+        entry_type: card::ENTRY_TYPE,
+        fields: &[
+            field(card::CARDHOLDER_FIELD, FieldKind::Text),
+            field(card::NUMBER_FIELD, FieldKind::Hidden),
+            field(card::EXPIRY_FIELD, FieldKind::Text),
+            field(card::CVV_FIELD, FieldKind::Hidden),
+            field(card::PIN_FIELD, FieldKind::Hidden),
+        ],
+    },
+    Template {
+        id: "software_license",
+        name: "Software license",
+        entry_type: license::ENTRY_TYPE,
+        fields: &[
+            field(license::KEY_FIELD, FieldKind::Hidden),
+            field(license::VERSION_FIELD, FieldKind::Text),
+            field(license::LICENSED_TO_FIELD, FieldKind::Text),
+            field(license::EMAIL_FIELD, FieldKind::Email),
+            field(license::PURCHASE_DATE_FIELD, FieldKind::Date),
+            field(license::ORDER_FIELD, FieldKind::Text),
+            field(license::STORE_FIELD, FieldKind::Text),
+            field(license::PRICE_FIELD, FieldKind::Text),
+        ],
+    },
+    Template {
+        id: "passport",
+        name: "Passport",
+        entry_type: "passport",
+        fields: &[
+            field("Full name", FieldKind::Text),
+            field("Passport number", FieldKind::Hidden),
+            field("Nationality", FieldKind::Text),
+            field("Date of birth", FieldKind::Date),
+            field("Issued on", FieldKind::Date),
+            field("Expires on", FieldKind::Date),
+            field("Issuing authority", FieldKind::Text),
+        ],
+    },
+    Template {
+        id: "identity",
+        name: "Identity",
+        entry_type: identity::ENTRY_TYPE,
+        fields: &[
+            field(identity::GIVEN_NAME_FIELD, FieldKind::Text),
+            field(identity::FAMILY_NAME_FIELD, FieldKind::Text),
+            field(identity::EMAIL_FIELD, FieldKind::Email),
+            field(identity::PHONE_FIELD, FieldKind::Phone),
+            field(identity::STREET_FIELD, FieldKind::Text),
+            field(identity::CITY_FIELD, FieldKind::Text),
+            field(identity::REGION_FIELD, FieldKind::Text),
+            field(identity::POSTAL_CODE_FIELD, FieldKind::Text),
+            field(identity::COUNTRY_FIELD, FieldKind::Text),
+        ],
+    },
+];
+
+pub fn find(id: &str) -> Result<&'static Template, String> {
+    TEMPLATES.iter().find(|t| t.id == id).ok_or_else(|| format!("Template error: no template '{}'", id))
+}
+
+impl Template {
+    /// A new entry of this template's type with its fields, under a fresh ID.
+    pub fn instantiate(&self, title: &str, now: u64) -> VaultEntry {
+        VaultEntry {
+            id: new_entry_id(),
+            title: title.to_string(),
+            entry_type: self.entry_type.to_string(),
+            last_modified: now,
+            fields: self
+                .fields
+                .iter()
+                .map(|f| CustomField { name: f.name.to_string(), kind: f.kind, value: f.default.to_string() })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instantiate_adds_typed_fields() {
+        let entry = find("email_account").unwrap().instantiate("Work mail", 7);
+        assert_eq!((entry.kind(), entry.title.as_str(), entry.last_modified), ("email", "Work mail", 7));
+        assert_eq!(entry.fields[0].kind, FieldKind::Email);
+        assert_eq!(entry.fields[2].value, "993");
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["fields"][2], serde_json::json!({"name": "IMAP port", "kind": "number", "value": "993"}));
+        assert!(find("spaceship").unwrap_err().contains("spaceship"));
+    }
+
+    #[test]
+    fn test_template_ids_and_field_names_are_unique() {
+        for (i, template) in TEMPLATES.iter().enumerate() {
+            assert!(TEMPLATES[..i].iter().all(|t| t.id != template.id));
+            for (j, field) in template.fields.iter().enumerate() {
+                assert!(template.fields[..j].iter().all(|f| f.name != field.name), "{}", template.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// --- Known-Answer Test Vectors ---
+// Fixed inputs with published outputs for every primitive the vault relies on.
+// Round-trip tests can't catch a dependency upgrade that changes what bytes come
+// out (a new Argon2 default, a TOTP truncation change); these can. Vectors are
+// taken from the cited RFC/NIST documents. The "pinned" ones are this crate's own
+// derivations, frozen so existing vaults keep opening.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, AssociatedData, ParamsBuilder, Version};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::Sha256;
+use totp_rs::TOTP;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::keys::derive_subkey;
+use crate::vault::hex;
+use crate::Engine;
+
+fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+fn key32(s: &str) -> [u8; 32] {
+    unhex(s).try_into().unwrap()
+}
+
+// Argon2id, RFC 9106 section 5.3.
+#[test]
+fn kat_argon2id_rfc9106() {
+    let params = ParamsBuilder::new()
+        .m_cost(32)
+        .t_cost(3)
+        .p_cost(4)
+        .data(AssociatedData::new(&[0x04; 12]).unwrap())
+        .build()
+        .unwrap();
+    let argon2 = Argon2::new_with_secret(&[0x03; 8], Algorithm::Argon2id, Version::V0x13, params).unwrap();
+
+    let mut tag = [0u8; 32];
+    argon2.hash_password_into(&[0x01; 32], &[0x02; 16], &mut tag).unwrap();
+    assert_eq!(hex(&tag), "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659");
+}
+
+// Pinned: the master key Engine::new derives with the crate's default Argon2id parameters.
+#[test]
+fn kat_master_key_pinned() {
+    let engine = Engine::new("correct horse battery staple", b"securepass-salt!").unwrap();
+    assert_eq!(hex(&engine.master_key), "42f3a84f8b860ae89ede314484502bbe674e06438cee0dcf369150a8e804b66f");
+}
+
+// AES-256-GCM, "The Galois/Counter Mode of Operation" (McGrew & Viega), test cases 13-16.
+#[test]
+fn kat_aes256gcm() {
+    let zero = Aes256Gcm::new(&[0u8; 32].into());
+    let nonce = Nonce::from_slice(&[0u8; 12]);
+    assert_eq!(hex(&zero.encrypt(nonce, &[][..]).unwrap()), "530f8afbc74536b9a963b4f1c4cb738b");
+    assert_eq!(
+        hex(&zero.encrypt(nonce, &[0u8; 16][..]).unwrap()),
+        "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919"
+    );
+
+    let cipher = Aes256Gcm::new(&key32("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").into());
+    let iv = unhex("cafebabefacedbaddecaf888");
+    let plaintext = unhex(
+        "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+         1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+    );
+    let ciphertext = "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                      8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad";
+
+    let sealed = cipher.encrypt(Nonce::from_slice(&iv), &plaintext[..]).unwrap();
+    assert_eq!(hex(&sealed), format!("{}b094dac5d93471bdec1a502270e3cc6c", ciphertext));
+
+    let aad = unhex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let sealed = cipher.encrypt(Nonce::from_slice(&iv), Payload { msg: &plaintext[..60], aad: &aad }).unwrap();
+    assert_eq!(hex(&sealed), format!("{}76fc6ece0f4e1768cddf8853bb2d551b", &ciphertext[..120]));
+}
+
+// HKDF-SHA256, RFC 5869 test case 1.
+#[test]
+fn kat_hkdf_sha256_rfc5869() {
+    let hkdf = Hkdf::<Sha256>::new(Some(&unhex("000102030405060708090a0b0c")), &[0x0b; 22]);
+    let mut okm = [0u8; 42];
+    hkdf.expand(&unhex("f0f1f2f3f4f5f6f7f8f9"), &mut okm).unwrap();
+    assert_eq!(
+        hex(&okm),
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+    );
+}
+
+// Pinned: the subkey layout (no salt, info = purpose || 0x00 || context) attachments depend on.
+#[test]
+fn kat_derive_subkey_pinned() {
+    let subkey = derive_subkey(&[0x42; 32], "securepass-attachment-content-v1", b"att-1");
+    assert_eq!(hex(&subkey), "320d2f003ec9bb041482e61df654a381af717160a1192727172639367b08bfb3");
+}
+
+// HMAC-SHA256, RFC 4231 test cases 1 and 2; HMAC-SHA1, RFC 2202 test case 1.
+#[test]
+fn kat_hmac() {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&[0x0b; 20]).unwrap();
+    mac.update(b"Hi There");
+    assert_eq!(hex(&mac.finalize().into_bytes()), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(b"Jefe").unwrap();
+    mac.update(b"what do ya want for nothing?");
+    assert_eq!(hex(&mac.finalize().into_bytes()), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&[0x0b; 20]).unwrap();
+    mac.update(b"Hi There");
+    assert_eq!(hex(&mac.finalize().into_bytes()), "b617318655057264e28bc0b6fb378c8ef146be00");
+}
+
+// TOTP, RFC 6238 appendix B (8 digits, 30 second step).
+#[test]
+fn kat_totp_rfc6238() {
+    let cases: [(u64, &str, &str, &str); 6] = [
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+    let sha1 = TOTP::new(totp_rs::Algorithm::SHA1, 8, 1, 30, b"12345678901234567890".to_vec()).unwrap();
+    let sha256 = TOTP::new(totp_rs::Algorithm::SHA256, 8, 1, 30, b"12345678901234567890123456789012".to_vec()).unwrap();
+    let sha512 = TOTP::new(
+        totp_rs::Algorithm::SHA512,
+        8,
+        1,
+        30,
+        b"1234567890123456789012345678901234567890123456789012345678901234".to_vec(),
+    )
+    .unwrap();
+
+    for (time, want_sha1, want_sha256, want_sha512) in cases {
+        assert_eq!(sha1.generate(time), want_sha1, "SHA1 at {}", time);
+        assert_eq!(sha256.generate(time), want_sha256, "SHA256 at {}", time);
+        assert_eq!(sha512.generate(time), want_sha512, "SHA512 at {}", time);
+    }
+}
+
+// PBKDF2-HMAC-SHA1 (andOTP export), RFC 6070.
+#[test]
+#[cfg(feature = "importers")]
+fn kat_pbkdf2_sha1_rfc6070() {
+    for (iterations, want) in [
+        (1, "0c60c80f961f0e71f3a9b524af6012062fe037a6"),
+        (2, "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957"),
+        (4096, "4b007901b765489abead49d926f721d065a429c1"),
+    ] {
+        let mut out = [0u8; 20];
+        pbkdf2::pbkdf2_hmac::<Sha1>(b"password", b"salt", iterations, &mut out);
+        assert_eq!(hex(&out), want, "c = {}", iterations);
+    }
+}
+
+// scrypt (Aegis export), RFC 7914 section 12, first vector.
+#[test]
+fn kat_scrypt_rfc7914() {
+    let mut out = [0u8; 64];
+    scrypt::scrypt(b"", b"", &scrypt::Params::new(4, 1, 1, 64).unwrap(), &mut out).unwrap();
+    assert_eq!(
+        hex(&out),
+        "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+         fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+    );
+}
+
+// X25519 (emergency access), RFC 7748 section 6.1.
+#[test]
+fn kat_x25519_rfc7748() {
+    let alice = StaticSecret::from(key32("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"));
+    let bob = StaticSecret::from(key32("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"));
+    let alice_public = PublicKey::from(&alice);
+    let bob_public = PublicKey::from(&bob);
+
+    assert_eq!(hex(alice_public.as_bytes()), "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a");
+    assert_eq!(hex(bob_public.as_bytes()), "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+
+    let shared = "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742";
+    assert_eq!(hex(alice.diffie_hellman(&bob_public).as_bytes()), shared);
+    assert_eq!(hex(bob.diffie_hellman(&alice_public).as_bytes()), shared);
+}
@@ -0,0 +1,144 @@
+// --- Text Normalization ---
+// Unicode text that looks the same can be different code points: "é" may be one
+// character or "e" plus a combining accent, "ＧｉｔＨｕｂ" is full-width "GitHub",
+// and "STRASSE" is how "Straße" is written in capitals. Two places care:
+//   * search: titles and queries are compared through `search_key`, so an entry
+//     is found however its title was typed or pasted.
+//   * the master password: the KDF sees bytes, so a password typed on a keyboard
+//     that composes accents and one that doesn't would unlock different vaults.
+//     `normalize_password` maps it to one byte string under a numbered policy;
+//     the policy a vault was created with is the one it must be opened with, so
+//     its number is stored next to the vault's salt. Vaults stored without one
+//     predate policies and open with `Raw`.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+#[cfg(feature = "std")]
+use caseless::Caseless;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+/// How a master password becomes KDF input. The number is stored with a vault;
+/// add a new variant rather than changing what an existing one does. Serialized
+/// as its number.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum PasswordPolicy {
+    /// 0: the UTF-8 bytes as typed. Every vault created before policies existed.
+    #[default]
+    Raw,
+    /// 1: RFC 8265 OpaqueString: non-ASCII spaces become U+0020, then NFC.
+    /// Case and width are kept, so no two passwords that look different merge.
+    Nfc,
+}
+
+impl PasswordPolicy {
+    /// The policy for new vaults.
+    pub const CURRENT: PasswordPolicy = PasswordPolicy::Nfc;
+
+    pub fn version(self) -> u8 {
+        match self {
+            PasswordPolicy::Raw => 0,
+            PasswordPolicy::Nfc => 1,
+        }
+    }
+
+    pub fn from_version(version: u8) -> Result<PasswordPolicy, String> {
+        match version {
+            0 => Ok(PasswordPolicy::Raw),
+            1 => Ok(PasswordPolicy::Nfc),
+            other => Err(format!("Password policy error: unknown version {} (update SecurePass)", other)),
+        }
+    }
+}
+
+impl TryFrom<u8> for PasswordPolicy {
+    type Error = String;
+
+    fn try_from(version: u8) -> Result<PasswordPolicy, String> {
+        PasswordPolicy::from_version(version)
+    }
+}
+
+impl From<PasswordPolicy> for u8 {
+    fn from(policy: PasswordPolicy) -> u8 {
+        policy.version()
+    }
+}
+
+/// The password as the KDF should see it under `policy`.
+pub fn normalize_password(password: &str, policy: PasswordPolicy) -> Zeroizing<String> {
+    match policy {
+        PasswordPolicy::Raw => Zeroizing::new(password.to_string()),
+        PasswordPolicy::Nfc => {
+            let mut spaced = Zeroizing::new(String::with_capacity(password.len()));
+            spaced.extend(password.chars().map(|c| if c != ' ' && is_space_separator(c) { ' ' } else { c }));
+            Zeroizing::new(spaced.nfc().collect())
+        }
+    }
+}
+
+/// Unicode category Zs, the spaces RFC 8265 maps to U+0020.
+fn is_space_separator(c: char) -> bool {
+    matches!(c, ' ' | '\u{A0}' | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}')
+}
+
+/// `text` folded for searching: compatibility forms (full-width, ligatures,
+/// superscripts) unified, case folded the same way in every locale, and accents
+/// dropped, so "cafe", "CAFÉ" and "ｃａｆé" share one key. Needs "std".
+#[cfg(feature = "std")]
+pub fn search_key(text: &str) -> String {
+    text.nfkd().default_case_fold().nfkd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+}
+
+/// True if every whitespace-separated word of `query` appears in one of `fields`.
+/// An empty query matches everything.
+#[cfg(feature = "std")]
+pub fn matches<'a>(query: &str, fields: impl IntoIterator<Item = &'a str>) -> bool {
+    let keys: Vec<String> = fields.into_iter().map(search_key).collect();
+    search_key(query).split_whitespace().all(|word| keys.iter().any(|key| key.contains(word)))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_keys_ignore_form_case_and_accents() {
+        assert_eq!(search_key("ＧｉｔＨｕｂ"), "github");
+        assert_eq!(search_key("Café"), search_key("cafe\u{301}"));
+        assert_eq!(search_key("CAFÉ"), "cafe");
+        assert_eq!(search_key("Straße"), search_key("STRASSE"));
+        assert_eq!(search_key("ﬁle"), "file");
+
+        assert!(matches("cafe paris", ["Café de Flore", "Paris"]));
+        assert!(matches("ｇｉｔ", ["GitHub"]));
+        assert!(!matches("gitlab", ["GitHub"]));
+        assert!(matches("  ", ["anything"]));
+    }
+
+    #[test]
+    fn test_password_policies() {
+        let composed = "pässwörd\u{3000}1";
+        let decomposed = "pa\u{308}sswo\u{308}rd 1";
+        assert_eq!(normalize_password(composed, PasswordPolicy::Nfc), normalize_password(decomposed, PasswordPolicy::Nfc));
+        assert_ne!(normalize_password(composed, PasswordPolicy::Raw), normalize_password(decomposed, PasswordPolicy::Raw));
+
+        // Case and width survive: they are part of the secret.
+        assert_ne!(*normalize_password("Ｐass", PasswordPolicy::Nfc), "Pass");
+        assert_ne!(*normalize_password("Pass", PasswordPolicy::Nfc), "pass");
+
+        for policy in [PasswordPolicy::Raw, PasswordPolicy::Nfc] {
+            assert_eq!(PasswordPolicy::from_version(policy.version()), Ok(policy));
+        }
+        assert!(PasswordPolicy::from_version(9).is_err());
+        assert_eq!(serde_json::to_string(&PasswordPolicy::Nfc).unwrap(), "1");
+        assert!(serde_json::from_str::<PasswordPolicy>("7").is_err());
+    }
+}
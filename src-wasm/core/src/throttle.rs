@@ -0,0 +1,114 @@
+// --- Unlock Throttling ---
+// After a few wrong master passwords in a row, every further attempt has to pay
+// for extra Argon2 runs before the real key derivation happens. The cost doubles
+// with each failure, so guessing through the app on a stolen device gets slow fast.
+//
+// The state is a small JSON blob the app keeps in local storage. An attempt is
+// counted as failed *before* it runs and only cleared on success, so closing the
+// tab mid-attempt can't be used to skip the penalty.
+
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Wrong passwords allowed before any penalty applies (typos happen).
+pub const FREE_ATTEMPTS: u32 = 3;
+
+/// Upper bound on extra Argon2 runs, so a long streak can't lock the user out for good.
+pub const MAX_PENALTY_ROUNDS: u32 = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ThrottleState {
+    pub consecutive_failures: u32,
+    /// True between `begin_attempt` and the matching success/failure report.
+    pub pending_attempt: bool,
+}
+
+impl ThrottleState {
+    /// Extra Argon2 runs the next attempt must pay: 0 for the first few failures, then 1, 2, 4, ...
+    pub fn penalty_rounds(&self) -> u32 {
+        match self.consecutive_failures.checked_sub(FREE_ATTEMPTS) {
+            None => 0,
+            Some(over) => 1u32.checked_shl(over).unwrap_or(u32::MAX).min(MAX_PENALTY_ROUNDS),
+        }
+    }
+
+    /// Starts an attempt and returns its penalty. An attempt that was started but
+    /// never reported (tab closed, crash) is counted as a failure first.
+    pub fn begin_attempt(&mut self) -> u32 {
+        if self.pending_attempt {
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+        self.pending_attempt = true;
+        self.penalty_rounds()
+    }
+
+    pub fn record_success(&mut self) {
+        *self = ThrottleState::default();
+    }
+
+    pub fn record_failure(&mut self) {
+        self.pending_attempt = false;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+    }
+}
+
+/// Burns `rounds` chained Argon2id runs over the password. The output is thrown
+/// away; the point is the time and memory it costs.
+pub fn pay_penalty(password: &str, salt: &[u8], rounds: u32) -> Result<(), String> {
+    let argon2 = Argon2::default();
+    let mut block = [0u8; 32];
+    let mut round_salt = salt.to_vec();
+
+    for round in 0..rounds {
+        // Chain each run into the next so they can't be computed in parallel.
+        round_salt.truncate(salt.len());
+        round_salt.extend_from_slice(b"penalty");
+        round_salt.extend_from_slice(&round.to_le_bytes());
+        round_salt.extend_from_slice(&block);
+
+        argon2
+            .hash_password_into(password.as_bytes(), &round_salt, &mut block)
+            .map_err(|e| format!("Argon2 error: {}", e))?;
+    }
+    block.zeroize();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalty_grows_after_free_attempts() {
+        let mut state = ThrottleState::default();
+        let mut penalties = Vec::new();
+        for _ in 0..8 {
+            penalties.push(state.begin_attempt());
+            state.record_failure();
+        }
+        assert_eq!(penalties, vec![0, 0, 0, 1, 2, 4, 8, 16]);
+
+        state.consecutive_failures = 500;
+        assert_eq!(state.penalty_rounds(), MAX_PENALTY_ROUNDS);
+
+        state.begin_attempt();
+        state.record_success();
+        assert_eq!(state, ThrottleState::default());
+    }
+
+    #[test]
+    fn test_abandoned_attempt_counts_as_failure() {
+        let mut state = ThrottleState { consecutive_failures: 3, pending_attempt: false };
+        assert_eq!(state.begin_attempt(), 1);
+        // The tab was closed before reporting; the next attempt pays for it.
+        assert_eq!(state.begin_attempt(), 2);
+        assert_eq!(state.consecutive_failures, 4);
+    }
+
+    #[test]
+    fn test_pay_penalty_runs() {
+        assert!(pay_penalty("pw", b"salt-123456789012", 0).is_ok());
+        assert!(pay_penalty("pw", b"salt-123456789012", 1).is_ok());
+    }
+}
@@ -0,0 +1,175 @@
+// --- Input Validation ---
+// Checks on the raw bytes and strings callers (mostly JS, through the Wasm
+// bridge) hand the engine, run before any cipher or KDF sees them. Without them
+// an IV of the wrong length panics inside the AEAD crate, a wrong-size key comes
+// back as "Cipher init error: InvalidLength", and a 2 GB string is copied around
+// until the allocator gives up.
+//
+// Each failure carries a stable code in brackets, so a front end can branch on
+// it without matching the wording:
+//   "Input error [iv_length]: the IV must be 12 bytes, got 16"
+// 'error_code' reads it back from an error string.
+
+use aes_gcm::{aead::consts::U12, Nonce};
+use alloc::{format, string::String, vec::Vec};
+
+/// AES-GCM nonce length.
+pub const IV_LEN: usize = 12;
+pub const KEY_LEN: usize = 32;
+/// The shortest salt Argon2 accepts. New envelopes use 'kdf::MIN_SALT_LEN' (16).
+pub const MIN_SALT_LEN: usize = 8;
+/// A vault record stores the salt length in one byte.
+pub const MAX_SALT_LEN: usize = 255;
+/// The largest plaintext the engine encrypts in one piece (a vault's JSON).
+pub const MAX_PLAINTEXT_LEN: usize = 64 * 1024 * 1024;
+/// AES-GCM adds a 16-byte tag, and a committed envelope its commitment.
+pub const MAX_CIPHERTEXT_LEN: usize = MAX_PLAINTEXT_LEN + 16 + crate::keys::COMMITMENT_OVERHEAD;
+/// Master passwords, PINs and export passphrases.
+pub const MAX_PASSWORD_LEN: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputCode {
+    IvLength,
+    SaltLength,
+    KeyLength,
+    TooLarge,
+    InvalidUtf8,
+    InvalidText,
+}
+
+impl InputCode {
+    pub const ALL: [InputCode; 6] = [
+        InputCode::IvLength,
+        InputCode::SaltLength,
+        InputCode::KeyLength,
+        InputCode::TooLarge,
+        InputCode::InvalidUtf8,
+        InputCode::InvalidText,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InputCode::IvLength => "iv_length",
+            InputCode::SaltLength => "salt_length",
+            InputCode::KeyLength => "key_length",
+            InputCode::TooLarge => "too_large",
+            InputCode::InvalidUtf8 => "invalid_utf8",
+            InputCode::InvalidText => "invalid_text",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputError {
+    pub code: InputCode,
+    pub message: String,
+}
+
+impl InputError {
+    fn new(code: InputCode, message: String) -> InputError {
+        InputError { code, message }
+    }
+}
+
+impl From<InputError> for String {
+    fn from(e: InputError) -> String {
+        format!("Input error [{}]: {}", e.code.as_str(), e.message)
+    }
+}
+
+/// The code of an input error string ("iv_length", ...); None for other errors.
+pub fn error_code(error: &str) -> Option<InputCode> {
+    let code = error.strip_prefix("Input error [")?.split_once(']')?.0;
+    InputCode::ALL.into_iter().find(|c| c.as_str() == code)
+}
+
+fn iv_error(len: usize) -> InputError {
+    InputError::new(InputCode::IvLength, format!("the IV must be {} bytes, got {}", IV_LEN, len))
+}
+
+/// An AES-GCM IV: exactly 12 bytes.
+pub fn iv(iv: &[u8]) -> Result<(), InputError> {
+    nonce(iv).map(drop)
+}
+
+/// An IV as the AES-GCM nonce type. Use this instead of 'Nonce::from_slice',
+/// which panics on any length but 12.
+pub fn nonce(iv: &[u8]) -> Result<&Nonce<U12>, InputError> {
+    let iv: &[u8; IV_LEN] = iv.try_into().map_err(|_| iv_error(iv.len()))?;
+    Ok(iv.into())
+}
+
+/// A KDF salt: 8 to 255 bytes.
+pub fn salt(salt: &[u8]) -> Result<(), InputError> {
+    if !(MIN_SALT_LEN..=MAX_SALT_LEN).contains(&salt.len()) {
+        let message = format!("the salt must be {} to {} bytes, got {}", MIN_SALT_LEN, MAX_SALT_LEN, salt.len());
+        return Err(InputError::new(InputCode::SaltLength, message));
+    }
+    Ok(())
+}
+
+/// A 256-bit key.
+pub fn key(key: &[u8], what: &str) -> Result<(), InputError> {
+    if key.len() != KEY_LEN {
+        return Err(InputError::new(InputCode::KeyLength, format!("the {} must be {} bytes, got {}", what, KEY_LEN, key.len())));
+    }
+    Ok(())
+}
+
+/// Anything with a size limit.
+pub fn size(len: usize, max: usize, what: &str) -> Result<(), InputError> {
+    if len > max {
+        return Err(InputError::new(InputCode::TooLarge, format!("the {} is {} bytes, over the limit of {}", what, len, max)));
+    }
+    Ok(())
+}
+
+/// A password (or PIN, or passphrase) being set. JS strings with an unpaired
+/// surrogate reach Rust with U+FFFD in its place, so two different passwords
+/// would derive the same key; such a password, or one with a NUL (which C
+/// front ends cut off), is refused. Existing passwords are only size-checked,
+/// so a vault made before this check still opens.
+pub fn new_password(password: &str, what: &str) -> Result<(), InputError> {
+    size(password.len(), MAX_PASSWORD_LEN, what)?;
+    if password.contains(['\u{FFFD}', '\0']) {
+        let message = format!("the {} contains a character that can't be typed back (U+FFFD or NUL)", what);
+        return Err(InputError::new(InputCode::InvalidText, message));
+    }
+    Ok(())
+}
+
+/// Decrypted or decoded bytes that must be text.
+pub fn utf8(bytes: Vec<u8>, what: &str) -> Result<String, InputError> {
+    String::from_utf8(bytes).map_err(|e| {
+        let message = format!("the {} is not valid UTF-8 (at byte {})", what, e.utf8_error().valid_up_to());
+        // Usually a secret: clear what didn't decode.
+        zeroize::Zeroize::zeroize(&mut e.into_bytes());
+        InputError::new(InputCode::InvalidUtf8, message)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_checks_and_codes() {
+        assert!(iv(&[0; 12]).is_ok());
+        let error: String = iv(&[0; 16]).unwrap_err().into();
+        assert_eq!(error, "Input error [iv_length]: the IV must be 12 bytes, got 16");
+        assert_eq!(error_code(&error), Some(InputCode::IvLength));
+        assert_eq!(error_code("Decryption error: aead::Error"), None);
+
+        assert_eq!(nonce(&[7; 12]).unwrap().as_slice(), &[7; 12]);
+        assert_eq!(nonce(&[7; 13]).unwrap_err().code, InputCode::IvLength);
+
+        assert!(salt(&[0; 8]).is_ok() && salt(&[0; 7]).is_err() && salt(&[0; 256]).is_err());
+        assert_eq!(key(&[0; 31], "key").unwrap_err().code, InputCode::KeyLength);
+        assert_eq!(size(11, 10, "note").unwrap_err().code, InputCode::TooLarge);
+        assert!(new_password("correct horse", "password").is_ok());
+        assert_eq!(new_password("pass\u{FFFD}word", "password").unwrap_err().code, InputCode::InvalidText);
+        assert_eq!(utf8(b"ok".to_vec(), "text").unwrap(), "ok".to_string());
+        assert_eq!(utf8(vec![0x66, 0xff], "text").unwrap_err().code, InputCode::InvalidUtf8);
+    }
+}
@@ -0,0 +1,327 @@
+// --- Vault Data Model ---
+// Mirrors the JSON the TypeScript layer encrypts (see src/types.ts), so Rust can
+// read and rewrite a decrypted vault without losing fields it doesn't know about.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use zeroize::Zeroize;
+
+use crate::attachments::AttachmentRef;
+use crate::domains::EquivalentDomains;
+use crate::extra_lock::ExtraLock;
+use crate::redact::{self, Redacted};
+use crate::yubikey::YubiKeySecret;
+
+/// A single credential, in the same camelCase shape the web app stores.
+/// Debug output redacts the secrets (see 'redact').
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultEntry {
+    pub id: String,
+    pub title: String,
+    /// "login" when empty; otherwise the kind of item ("note", "card", ...) the web app created.
+    #[serde(default, rename = "type", skip_serializing_if = "String::is_empty")]
+    pub entry_type: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub history: Vec<String>,
+    #[serde(default)]
+    pub last_modified: u64,
+    /// When the entry was last filled or copied from; 0 if never.
+    #[serde(default)]
+    pub last_used: u64,
+    /// How many times it has been filled or copied from.
+    #[serde(default)]
+    pub use_count: u32,
+    /// Entries flagged here are left out of travel vaults.
+    #[serde(default)]
+    pub travel_mode: bool,
+    /// Revealing the entry's secrets needs the master password again (see 'reprompt').
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reprompt: bool,
+    /// Files attached to the entry; their contents are stored separately (see 'attachments').
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentRef>,
+    /// The slot secret of a "yubikey" entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yubikey: Option<YubiKeySecret>,
+    /// Typed fields beyond the ones above, such as those a template adds (see 'templates').
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<CustomField>,
+    /// Set when the entry's secrets are sealed under a second passphrase (see 'extra_lock').
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_lock: Option<ExtraLock>,
+    /// Anything the JS side added that Rust doesn't model yet is kept as-is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// What a custom field holds, so the UI can pick an input and mask secrets.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    #[default]
+    Text,
+    /// Masked like a password (a PIN, an account number).
+    Hidden,
+    Number,
+    /// "YYYY-MM-DD".
+    Date,
+    Url,
+    Email,
+    Phone,
+    /// One-time 2FA recovery codes, one per line ('backup_codes').
+    BackupCodes,
+}
+
+/// A named, typed value on an entry. Debug output redacts the value.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CustomField {
+    pub name: String,
+    #[serde(default)]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub value: String,
+}
+
+impl fmt::Debug for CustomField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomField")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("value", &Redacted(&self.value))
+            .finish()
+    }
+}
+
+/// The decrypted vault root.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultData {
+    #[serde(default)]
+    pub entries: Vec<VaultEntry>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Folders whose entries are all left out of travel vaults.
+    #[serde(default)]
+    pub travel_mode_categories: Vec<String>,
+    /// Domains the user says belong to the same site (amazon.com ≈ amazon.de).
+    #[serde(default)]
+    pub equivalent_domains: EquivalentDomains,
+    /// 'settings::VaultSettings' as stored. Kept raw so settings written by a newer
+    /// client, or a broken settings blob, never stop the vault from loading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A fresh RFC 4122 version 4 UUID, the ID format the web app uses for entries.
+pub fn new_entry_id() -> String {
+    let mut b = [0u8; 16];
+    crate::rng::with(|rng| rng.fill_bytes(&mut b));
+    b[6] = (b[6] & 0x0f) | 0x40;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex(&b);
+    format!("{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+}
+
+/// Lowercase hex.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Overwrites every string inside a JSON value, for copies of entries taken apart as JSON.
+pub(crate) fn wipe_value(value: &mut Value) {
+    match value {
+        Value::String(s) => s.zeroize(),
+        Value::Array(items) => items.iter_mut().for_each(wipe_value),
+        Value::Object(map) => map.values_mut().for_each(wipe_value),
+        _ => {}
+    }
+}
+
+impl VaultEntry {
+    /// The entry's type, with untyped entries counted as "login".
+    pub fn kind(&self) -> &str {
+        if self.entry_type.is_empty() {
+            "login"
+        } else {
+            &self.entry_type
+        }
+    }
+
+    /// Overwrites the secret fields in place before the entry is dropped.
+    pub fn wipe(&mut self) {
+        self.username.zeroize();
+        self.password.zeroize();
+        self.notes.zeroize();
+        if let Some(secret) = self.totp_secret.as_mut() {
+            secret.zeroize();
+        }
+        self.history.iter_mut().for_each(|old| old.zeroize());
+        if let Some(secret) = self.yubikey.as_mut() {
+            secret.wipe();
+        }
+        self.fields.iter_mut().for_each(|field| field.value.zeroize());
+    }
+}
+
+impl fmt::Debug for VaultEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 'extra' can hold anything the web app stores (card numbers, PINs): only its keys are shown.
+        let extra: Vec<&String> = self.extra.keys().collect();
+        f.debug_struct("VaultEntry")
+            .field("id", &self.id)
+            .field("title", &self.title)
+            .field("entry_type", &self.entry_type)
+            .field("username", &self.username)
+            .field("password", &Redacted(&self.password))
+            .field("url", &self.url)
+            .field("notes", &Redacted(&self.notes))
+            .field("category", &self.category)
+            .field("totp_secret", &self.totp_secret.as_ref().map(Redacted))
+            .field("favorite", &self.favorite)
+            .field("history", &self.history.iter().map(Redacted).collect::<Vec<_>>())
+            .field("last_modified", &self.last_modified)
+            .field("last_used", &self.last_used)
+            .field("use_count", &self.use_count)
+            .field("travel_mode", &self.travel_mode)
+            .field("reprompt", &self.reprompt)
+            .field("attachments", &self.attachments)
+            .field("yubikey", &self.yubikey)
+            .field("fields", &self.fields)
+            .field("extra_lock", &self.extra_lock.is_some())
+            .field("extra", &extra)
+            .finish()
+    }
+}
+
+impl VaultData {
+    pub fn from_json(json: &str) -> Result<VaultData, String> {
+        serde_json::from_str(json).map_err(|e| redact::json_error("Vault parse", &e))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Vault serialize error: {}", e))
+    }
+
+    pub fn find_entry(&self, id: &str) -> Option<&VaultEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Wipes every entry and empties the vault (used on lock and on drop).
+    pub fn wipe(&mut self) {
+        self.entries.iter_mut().for_each(VaultEntry::wipe);
+        *self = VaultData::default();
+    }
+
+    /// True when the entry, or the folder it lives in, is flagged for travel mode.
+    pub fn is_travel_flagged(&self, entry: &VaultEntry) -> bool {
+        entry.travel_mode || self.travel_mode_categories.contains(&entry.category)
+    }
+
+    /// TRAVEL MODE: A copy of the vault with every flagged entry and folder removed.
+    /// The folder flags themselves are dropped too, and the favorite order keeps only
+    /// the entries that remain, so the copy doesn't hint at what's missing.
+    pub fn travel_view(&self) -> VaultData {
+        let entries: Vec<VaultEntry> = self
+            .entries
+            .iter()
+            .filter(|e| !self.is_travel_flagged(e))
+            .cloned()
+            .collect();
+
+        let categories = self
+            .categories
+            .iter()
+            .filter(|c| !self.travel_mode_categories.contains(c))
+            .cloned()
+            .collect();
+
+        let mut settings = self.settings.clone();
+        if let Some(Value::Array(order)) = settings.as_mut().and_then(|s| s.get_mut("favoriteOrder")) {
+            order.retain(|id| entries.iter().any(|e| id.as_str() == Some(e.id.as_str())));
+        }
+
+        VaultData {
+            entries,
+            categories,
+            travel_mode_categories: Vec::new(),
+            equivalent_domains: self.equivalent_domains.clone(),
+            settings,
+            extra: self.extra.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = r#"{
+        "entries": [
+            {"id": "1", "title": "GitHub", "password": "a", "category": "work"},
+            {"id": "2", "title": "Bank", "password": "b", "category": "finance"},
+            {"id": "3", "title": "Seed", "password": "c", "category": "work", "travelMode": true, "customFlag": 1}
+        ],
+        "categories": ["work", "finance"],
+        "travelModeCategories": ["finance"],
+        "settings": {"favoriteOrder": ["3", "1", "2"]},
+        "version": 2
+    }"#;
+
+    #[test]
+    fn test_roundtrip_keeps_unknown_fields() {
+        let vault = VaultData::from_json(VAULT).unwrap();
+        assert_eq!(vault.entries[2].extra["customFlag"], 1);
+        assert_eq!(vault.extra["version"], 2);
+
+        let again = VaultData::from_json(&vault.to_json().unwrap()).unwrap();
+        assert_eq!(again, vault);
+    }
+
+    #[test]
+    fn test_new_entry_id_shape() {
+        let id = new_entry_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(id, new_entry_id());
+    }
+
+    #[test]
+    fn test_wipe_clears_secrets() {
+        let mut vault = VaultData::from_json(VAULT).unwrap();
+        assert!(vault.find_entry("2").is_some());
+        vault.wipe();
+        assert!(vault.entries.is_empty());
+        assert!(vault.find_entry("2").is_none());
+    }
+
+    #[test]
+    fn test_travel_view_excludes_flagged() {
+        let vault = VaultData::from_json(VAULT).unwrap();
+        let travel = vault.travel_view();
+
+        let ids: Vec<&str> = travel.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["1"]);
+        assert_eq!(travel.categories, vec!["work"]);
+        assert!(travel.travel_mode_categories.is_empty());
+        assert_eq!(travel.settings.unwrap()["favoriteOrder"], serde_json::json!(["1"]));
+    }
+}
@@ -0,0 +1,120 @@
+// --- Wi-Fi Networks ---
+// The "wifi" entry type (the 'wifi_router' template) and the text a phone
+// camera reads as "join this network": WIFI:T:WPA;S:<ssid>;P:<password>;;,
+// the format ZXing introduced and Android and iOS both understand. Shown as a
+// QR code, it lets a guest join without the password being read aloud.
+//
+// This module only builds the text; the UI renders it as a QR code the same
+// way it renders transfer frames ('qr_transfer').
+
+use crate::vault::VaultEntry;
+
+pub const ENTRY_TYPE: &str = "wifi";
+
+pub const SSID_FIELD: &str = "Network name";
+pub const PASSWORD_FIELD: &str = "Wi-Fi password";
+pub const SECURITY_FIELD: &str = "Security";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Security {
+    /// WPA, WPA2 and WPA3 personal all join as "WPA".
+    Wpa,
+    Wep,
+    Open,
+}
+
+impl Security {
+    /// Accepts what people write in the Security field: "WPA2", "wpa2/wpa3", "WEP", "open", "none", "".
+    pub fn parse(name: &str) -> Result<Security, String> {
+        let upper = name.trim().to_ascii_uppercase();
+        match upper.as_str() {
+            "" | "OPEN" | "NONE" | "NOPASS" => Ok(Security::Open),
+            "WEP" => Ok(Security::Wep),
+            _ if upper.starts_with("WPA") || upper == "SAE" => Ok(Security::Wpa),
+            _ => Err(format!("Wi-Fi error: unknown security '{}'", name.trim())),
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Security::Wpa => "WPA",
+            Security::Wep => "WEP",
+            Security::Open => "nopass",
+        }
+    }
+}
+
+/// The WIFI: text for a network. Checks the lengths each security type allows.
+pub fn qr_payload(ssid: &str, password: &str, security: &str) -> Result<String, String> {
+    let security = Security::parse(security)?;
+    if ssid.is_empty() || ssid.len() > 32 {
+        return Err("Wi-Fi error: the network name must be 1-32 bytes".to_string());
+    }
+    let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+    let (valid, error) = match security {
+        Security::Open => (password.is_empty(), "an open network has no password"),
+        Security::Wpa => (
+            (8..=63).contains(&password.len()) || (password.len() == 64 && is_hex(password)),
+            "a WPA password must be 8-63 characters",
+        ),
+        Security::Wep => (
+            matches!(password.len(), 5 | 13) || (matches!(password.len(), 10 | 26) && is_hex(password)),
+            "a WEP key must be 5 or 13 characters, or 10 or 26 hex digits",
+        ),
+    };
+    if !valid {
+        return Err(format!("Wi-Fi error: {}", error));
+    }
+
+    let mut payload = format!("WIFI:T:{};S:{};", security.code(), escape(ssid));
+    if security != Security::Open {
+        payload.push_str(&format!("P:{};", escape(password)));
+    }
+    payload.push(';');
+    Ok(payload)
+}
+
+/// 'qr_payload' for a Wi-Fi entry's fields.
+pub fn entry_payload(entry: &VaultEntry) -> Result<String, String> {
+    let value = |name: &str| entry.fields.iter().find(|f| f.name == name).map_or("", |f| f.value.as_str());
+    qr_payload(value(SSID_FIELD), value(PASSWORD_FIELD), value(SECURITY_FIELD))
+}
+
+/// Backslash before \ ; , : and ". A value of only hex digits is also quoted, or
+/// scanners would read it as hex-encoded bytes.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        out = format!("\"{}\"", out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads() {
+        assert_eq!(qr_payload("Home", "correct horse", "WPA2").unwrap(), "WIFI:T:WPA;S:Home;P:correct horse;;");
+        assert_eq!(qr_payload("Café;Guest", "a:b\\c,d\"e", "wpa3").unwrap(), r#"WIFI:T:WPA;S:Café\;Guest;P:a\:b\\c\,d\"e;;"#);
+        assert_eq!(qr_payload("Lobby", "", "").unwrap(), "WIFI:T:nopass;S:Lobby;;");
+        assert_eq!(qr_payload("CAFE", "12345678", "WPA").unwrap(), r#"WIFI:T:WPA;S:"CAFE";P:"12345678";;"#);
+    }
+
+    #[test]
+    fn test_invalid_networks_are_refused() {
+        assert!(qr_payload("Home", "short", "WPA2").unwrap_err().contains("8-63"));
+        assert!(qr_payload("Home", "12345", "WEP").is_ok());
+        assert!(qr_payload("Home", "123456", "WEP").is_err());
+        assert!(qr_payload("Lobby", "secret", "open").is_err());
+        assert!(qr_payload(&"x".repeat(33), "correct horse", "WPA2").is_err());
+        assert!(qr_payload("Home", "correct horse", "Enterprise").unwrap_err().contains("'Enterprise'"));
+    }
+}
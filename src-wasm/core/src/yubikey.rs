@@ -0,0 +1,263 @@
+// --- YubiKey Slots ---
+// The two YubiKey slot modes people keep secrets for, stored on a "yubikey"
+// entry so a vault can hold what a KeePass database relied on:
+//
+//   Yubico OTP          AES-128 key + private ID; the key types a 44-character
+//                       modhex OTP that we decrypt and check (CRC, ID, counters).
+//   Challenge-response  20-byte HMAC-SHA1 secret; the response to a challenge is
+//                       HMAC-SHA1(secret, challenge), as the key computes it in
+//                       variable-length mode (KeePassXC's setting).
+//
+// Counters only move forward: a verified OTP records its counters on the slot,
+// and an OTP at or below them is a replay.
+
+use std::fmt;
+
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt, KeyInit};
+use aes::Aes128;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::redact::Redacted;
+
+/// The 'VaultEntry::entry_type' of entries holding a 'YubiKeySecret'.
+pub const ENTRY_TYPE: &str = "yubikey";
+
+/// Modhex digits for nibbles 0-f: letters that sit in the same place on most keyboard layouts.
+const MODHEX: &[u8; 16] = b"cbdefghijklnrtuv";
+/// The encrypted token, as modhex characters.
+const TOKEN_LEN: usize = 32;
+/// What CRC-16 over a whole valid token (CRC included) comes to.
+const CRC_RESIDUE: u16 = 0xf0b8;
+/// Longest challenge a YubiKey takes.
+pub const MAX_CHALLENGE_LEN: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum YubiKeySecret {
+    Otp(OtpSlot),
+    ChallengeResponse(ChallengeResponseSlot),
+}
+
+/// A slot programmed for Yubico OTP. Debug output redacts the private ID and key.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpSlot {
+    /// Modhex, the OTP's unencrypted prefix (usually 12 characters).
+    pub public_id: String,
+    /// Hex, 6 bytes: the ID inside every token.
+    pub private_id: String,
+    /// Hex, 16 bytes.
+    pub aes_key: String,
+    /// Counters of the last accepted OTP.
+    #[serde(default)]
+    pub use_counter: u16,
+    #[serde(default)]
+    pub session_counter: u8,
+}
+
+/// A slot programmed for HMAC-SHA1 challenge-response. Debug output redacts the secret.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeResponseSlot {
+    /// Hex, 20 bytes.
+    pub secret: String,
+    /// 1 or 2, the slot it is programmed in; for display.
+    #[serde(default)]
+    pub slot: u8,
+}
+
+/// What a verified OTP carried.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OtpToken {
+    /// Bumped each time the key is plugged in.
+    pub use_counter: u16,
+    /// Bumped with each OTP within one insertion.
+    pub session_counter: u8,
+    /// The key's 8 Hz timer since insertion, 24 bits.
+    pub timestamp: u32,
+}
+
+impl fmt::Debug for OtpSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtpSlot")
+            .field("public_id", &self.public_id)
+            .field("private_id", &Redacted(&self.private_id))
+            .field("aes_key", &Redacted(&self.aes_key))
+            .field("use_counter", &self.use_counter)
+            .field("session_counter", &self.session_counter)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ChallengeResponseSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChallengeResponseSlot").field("secret", &Redacted(&self.secret)).field("slot", &self.slot).finish()
+    }
+}
+
+impl YubiKeySecret {
+    /// Overwrites the key material in place.
+    pub fn wipe(&mut self) {
+        match self {
+            YubiKeySecret::Otp(slot) => {
+                slot.private_id.zeroize();
+                slot.aes_key.zeroize();
+            }
+            YubiKeySecret::ChallengeResponse(slot) => slot.secret.zeroize(),
+        }
+    }
+}
+
+impl OtpSlot {
+    /// Checks `otp` as typed by this slot's key and, if it is valid and newer than
+    /// the last one accepted, records its counters. A replayed OTP is refused.
+    pub fn verify(&mut self, otp: &str) -> Result<OtpToken, String> {
+        let otp = otp.trim().to_ascii_lowercase();
+        let public_id = self.public_id.trim().to_ascii_lowercase();
+        let token = otp
+            .strip_prefix(public_id.as_str())
+            .filter(|token| token.len() == TOKEN_LEN)
+            .ok_or("YubiKey error: this OTP is not from this key")?;
+
+        let mut block = Zeroizing::new(modhex_decode(token)?);
+        let key = Zeroizing::new(decode_hex(&self.aes_key, 16, "AES key")?);
+        Aes128::new(GenericArray::from_slice(&key)).decrypt_block(GenericArray::from_mut_slice(block.as_mut_slice()));
+
+        if crc16(&block[..]) != CRC_RESIDUE {
+            return Err("YubiKey error: OTP failed its checksum (wrong key or mistyped)".to_string());
+        }
+        if block[..6] != decode_hex(&self.private_id, 6, "Private ID")?[..] {
+            return Err("YubiKey error: OTP has the wrong private ID".to_string());
+        }
+        let token = OtpToken {
+            use_counter: u16::from_le_bytes([block[6], block[7]]),
+            session_counter: block[11],
+            timestamp: u32::from_le_bytes([block[8], block[9], block[10], 0]),
+        };
+        if (token.use_counter, token.session_counter) <= (self.use_counter, self.session_counter) {
+            return Err("YubiKey error: OTP was already used".to_string());
+        }
+        (self.use_counter, self.session_counter) = (token.use_counter, token.session_counter);
+        Ok(token)
+    }
+}
+
+impl ChallengeResponseSlot {
+    /// The 20-byte response the key gives for `challenge`.
+    pub fn respond(&self, challenge: &[u8]) -> Result<[u8; 20], String> {
+        if challenge.len() > MAX_CHALLENGE_LEN {
+            return Err(format!("YubiKey error: challenges are at most {} bytes", MAX_CHALLENGE_LEN));
+        }
+        let secret = Zeroizing::new(decode_hex(&self.secret, 20, "HMAC secret")?);
+        let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(&secret).map_err(|e| format!("HMAC error: {}", e))?;
+        mac.update(challenge);
+        Ok(mac.finalize().into_bytes().into())
+    }
+}
+
+fn modhex_decode(text: &str) -> Result<Vec<u8>, String> {
+    let nibble = |c: u8| MODHEX.iter().position(|&m| m == c).ok_or_else(|| format!("YubiKey error: '{}' is not modhex", c as char));
+    text.as_bytes().chunks(2).map(|pair| Ok((nibble(pair[0])? << 4 | nibble(pair[1])?) as u8)).collect()
+}
+
+fn decode_hex(text: &str, len: usize, what: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    if text.len() != len * 2 || !text.is_ascii() {
+        return Err(format!("YubiKey error: {} must be {} hex characters", what, len * 2));
+    }
+    (0..len)
+        .map(|i| u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).map_err(|_| format!("YubiKey error: {} is not hex", what)))
+        .collect()
+}
+
+/// CRC-16/ISO-HDLC without the final inversion, as Yubico OTP uses it.
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |mut crc, &byte| {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0x8408 } else { crc >> 1 };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncrypt;
+
+    const KEY: &str = "ecde18dbe76fbd0c33330f1c354871db";
+    const PRIVATE_ID: &str = "8792ebfe26cc";
+
+    fn slot() -> OtpSlot {
+        OtpSlot {
+            public_id: "vvccccdtfbnc".to_string(),
+            private_id: PRIVATE_ID.to_string(),
+            aes_key: KEY.to_string(),
+            use_counter: 0,
+            session_counter: 0,
+        }
+    }
+
+    /// What the key types: the public ID, then the token encrypted and in modhex.
+    fn type_otp(use_counter: u16, session_counter: u8) -> String {
+        let mut block = [0u8; 16];
+        block[..6].copy_from_slice(&decode_hex(PRIVATE_ID, 6, "").unwrap());
+        block[6..8].copy_from_slice(&use_counter.to_le_bytes());
+        block[8..11].copy_from_slice(&[0x12, 0x34, 0x56]);
+        block[11] = session_counter;
+        block[12..14].copy_from_slice(&[0xab, 0xcd]);
+        let crc = !crc16(&block[..14]);
+        block[14..].copy_from_slice(&crc.to_le_bytes());
+
+        let key = decode_hex(KEY, 16, "").unwrap();
+        Aes128::new(GenericArray::from_slice(&key)).encrypt_block(GenericArray::from_mut_slice(&mut block));
+        let token: String = block.iter().flat_map(|b| [MODHEX[(b >> 4) as usize], MODHEX[(b & 15) as usize]]).map(char::from).collect();
+        format!("vvccccdtfbnc{}", token)
+    }
+
+    #[test]
+    fn test_otp_verifies_once_and_counters_advance() {
+        let mut slot = slot();
+        let otp = type_otp(3, 1);
+        assert_eq!(otp.len(), 44);
+        assert_eq!(slot.verify(&otp.to_uppercase()).unwrap(), OtpToken { use_counter: 3, session_counter: 1, timestamp: 0x563412 });
+        assert!(slot.verify(&otp).unwrap_err().contains("already used"));
+        assert!(slot.verify(&type_otp(2, 9)).is_err());
+        assert!(slot.verify(&type_otp(3, 2)).is_ok() && slot.verify(&type_otp(4, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_otp_rejects_tampering_and_other_keys() {
+        let mut slot = slot();
+        let mut otp = type_otp(1, 0).into_bytes();
+        otp[20] = if otp[20] == b'c' { b'b' } else { b'c' };
+        assert!(slot.verify(std::str::from_utf8(&otp).unwrap()).unwrap_err().contains("checksum"));
+        assert!(slot.verify(&type_otp(1, 0).replacen("vvcc", "cccc", 1)).unwrap_err().contains("not from this key"));
+
+        slot.private_id = "000000000000".to_string();
+        assert!(slot.verify(&type_otp(1, 0)).unwrap_err().contains("private ID"));
+    }
+
+    #[test]
+    fn test_challenge_response_rfc2202() {
+        // RFC 2202 test case 1.
+        let slot = ChallengeResponseSlot { secret: "0b".repeat(20), slot: 2 };
+        let response: String = slot.respond(b"Hi There").unwrap().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(response, "b617318655057264e28bc0b6fb378c8ef146be00");
+        assert!(slot.respond(&[0u8; 65]).is_err());
+        assert!(ChallengeResponseSlot { secret: "0b".to_string(), slot: 1 }.respond(b"x").is_err());
+    }
+
+    #[test]
+    fn test_secret_json() {
+        let secret: YubiKeySecret = serde_json::from_str(r#"{"mode": "challengeResponse", "secret": "00", "slot": 2}"#).unwrap();
+        assert!(matches!(secret, YubiKeySecret::ChallengeResponse(ChallengeResponseSlot { slot: 2, .. })));
+        let json = serde_json::to_string(&YubiKeySecret::Otp(slot())).unwrap();
+        assert!(json.starts_with(r#"{"mode":"otp","publicId":"vvccccdtfbnc""#), "{}", json);
+    }
+}
@@ -0,0 +1,385 @@
+// --- SecurePass FFI ---
+// UniFFI bindings to securepass-core for the native iOS and Android apps.
+// Swift and Kotlin get the same key derivation, vault envelope and generators
+// the web app runs, instead of reimplementing them. Like the Wasm bridge, this
+// is a thin layer: every body is a call into the core crate.
+//
+// Structured vault data crosses as the same camelCase JSON the web app stores;
+// binary data (salts, IVs, ciphertext) as byte arrays.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use securepass_core::duress::EncryptedPackage;
+use securepass_core::{audit_log, biometric, domains, generator, hint, kdf, master_policy, text, vault, Engine};
+
+uniffi::setup_scaffolding!();
+
+/// Every core failure, carrying the same human-readable message the web app shows.
+#[derive(Debug, uniffi::Error)]
+pub enum SecurePassError {
+    Failed { message: String },
+}
+
+impl fmt::Display for SecurePassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecurePassError::Failed { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for SecurePassError {}
+
+impl From<String> for SecurePassError {
+    fn from(message: String) -> Self {
+        SecurePassError::Failed { message }
+    }
+}
+
+type Result<T> = std::result::Result<T, SecurePassError>;
+
+/// An encrypted vault as stored by every client: {version, iv, data}. Version 1
+/// is a legacy, uncommitted envelope.
+#[derive(uniffi::Record)]
+pub struct Envelope {
+    #[uniffi(default = 1)]
+    pub version: u8,
+    pub iv: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl From<EncryptedPackage> for Envelope {
+    fn from(package: EncryptedPackage) -> Envelope {
+        Envelope { version: package.version, iv: package.iv, data: package.data }
+    }
+}
+
+impl From<Envelope> for EncryptedPackage {
+    fn from(envelope: Envelope) -> EncryptedPackage {
+        EncryptedPackage { version: envelope.version, iv: envelope.iv, data: envelope.data }
+    }
+}
+
+/// What 'Vault::commit_kdf_upgrade' returns: both JSON, to store in one write.
+#[derive(uniffi::Record)]
+pub struct KdfUpgrade {
+    pub envelope_json: String,
+    pub audit_log_json: String,
+}
+
+/// Settings for 'generate_password'.
+#[derive(uniffi::Record)]
+pub struct PasswordOptions {
+    pub length: u32,
+    pub use_uppercase: bool,
+    pub use_numbers: bool,
+    pub use_symbols: bool,
+}
+
+// --- Vault Session ---
+
+/// An unlocked vault. Holds the master key and decrypted entries until the
+/// native side drops it, then wipes both (see Engine's Drop).
+#[derive(uniffi::Object)]
+pub struct Vault {
+    engine: Mutex<Engine>,
+}
+
+impl Vault {
+    /// A poisoned lock only means another call panicked; the engine itself is still consistent.
+    fn engine(&self) -> MutexGuard<'_, Engine> {
+        self.engine.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[uniffi::export]
+impl Vault {
+    /// Derives the master key from the password and the vault's salt with Argon2id,
+    /// after normalizing the password under the policy stored with the vault
+    /// (0 = as typed, 1 = NFC). Vaults saved without a policy use 0; new vaults
+    /// use 'current_password_policy()'.
+    #[uniffi::constructor(default(password_policy = 0))]
+    pub fn new(password: String, salt: Vec<u8>, password_policy: u8) -> Result<Arc<Self>> {
+        let policy = text::PasswordPolicy::from_version(password_policy)?;
+        let engine = Engine::new_with_policy(&password, &salt, policy)?;
+        Ok(Arc::new(Vault { engine: Mutex::new(engine) }))
+    }
+
+    /// Derives the master key through the vault's key envelope (JSON, as the web app
+    /// stores it; 'legacy_key_envelope' describes a vault saved without one). If the
+    /// envelope uses outdated settings its replacement is prepared too, which costs a
+    /// second Argon2 run; fetch it with 'commit_kdf_upgrade' once the vault loads.
+    #[uniffi::constructor]
+    pub fn from_envelope(password: String, envelope_json: String) -> Result<Arc<Self>> {
+        let envelope: kdf::KeyEnvelope =
+            serde_json::from_str(&envelope_json).map_err(|e| format!("Key envelope parse error: {}", e))?;
+        let engine = Engine::from_envelope(&password, &envelope)?;
+        Ok(Arc::new(Vault { engine: Mutex::new(engine) }))
+    }
+
+    /// Call after 'load' succeeded on a session from 'from_envelope'. Returns the
+    /// replacement envelope and the audit log ('audit_log_json', "" for a new one)
+    /// with the upgrade recorded, to save together in one write; None if the
+    /// envelope was current. The vault itself stays as it is.
+    pub fn commit_kdf_upgrade(&self, audit_log_json: String, now: u64) -> Result<Option<KdfUpgrade>> {
+        let mut log: audit_log::AuditLog = if audit_log_json.is_empty() {
+            audit_log::AuditLog::new()
+        } else {
+            serde_json::from_str(&audit_log_json).map_err(|e| format!("Audit log parse error: {}", e))?
+        };
+        let Some(envelope) = self.engine().commit_kdf_upgrade(&mut log, now) else {
+            return Ok(None);
+        };
+        Ok(Some(KdfUpgrade {
+            envelope_json: serde_json::to_string(&envelope).map_err(|e| format!("Key envelope serialize error: {}", e))?,
+            audit_log_json: serde_json::to_string(&log).map_err(|e| format!("Audit log serialize error: {}", e))?,
+        }))
+    }
+
+    /// Decrypts a stored envelope into this session, replacing whatever was loaded.
+    /// Fails with a decryption error when the password was wrong.
+    pub fn load(&self, envelope: Envelope) -> Result<()> {
+        Ok(self.engine().load_vault_package(&envelope.into())?)
+    }
+
+    /// Encrypts the loaded vault under a fresh IV, ready to be stored.
+    pub fn seal(&self) -> Result<Envelope> {
+        Ok(self.engine().seal_vault_package()?.into())
+    }
+
+    /// The decrypted vault as JSON, for the native UI to render.
+    pub fn vault_json(&self) -> Result<String> {
+        Ok(self.engine().vault().to_json()?)
+    }
+
+    /// Replaces the loaded vault with edited JSON. Call 'seal' to persist it.
+    pub fn set_vault_json(&self, json: String) -> Result<()> {
+        let parsed = vault::VaultData::from_json(&json)?;
+        let mut engine = self.engine();
+        engine.vault_mut().wipe();
+        *engine.vault_mut() = parsed;
+        Ok(())
+    }
+
+    pub fn entry_count(&self) -> u32 {
+        self.engine().entry_count() as u32
+    }
+
+    /// Encrypts arbitrary text with the master key under a nonce the engine picks.
+    pub fn encrypt_package(&self, plaintext: String) -> Result<Envelope> {
+        Ok(self.engine().encrypt_package(&plaintext)?.into())
+    }
+
+    /// Encrypts arbitrary text with the master key (same format as the web app's 'encrypt').
+    /// Legacy: the IV must never have been used with this vault; prefer 'encrypt_package'.
+    pub fn encrypt(&self, plaintext: String, iv: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(self.engine().encrypt(&plaintext, &iv)?)
+    }
+
+    /// Decrypts what 'encrypt_package' returned, or a legacy version 1 envelope.
+    pub fn decrypt_package(&self, envelope: Envelope) -> Result<String> {
+        Ok(self.engine().decrypt_package(&envelope.into())?)
+    }
+
+    /// Decrypts what the legacy 'encrypt' returned.
+    pub fn decrypt(&self, ciphertext: Vec<u8>, iv: Vec<u8>) -> Result<String> {
+        Ok(self.engine().decrypt(&ciphertext, &iv)?)
+    }
+
+    /// The TOTP code of a loaded entry at Unix time 'now'.
+    pub fn totp_code(&self, entry_id: String, now: u64) -> Result<String> {
+        let engine = self.engine();
+        let entry = engine.vault().find_entry(&entry_id).ok_or("Entry not found".to_string())?;
+        let config = securepass_core::otp::TotpConfig::from_entry(entry)
+            .ok_or_else(|| format!("Entry '{}' has no TOTP secret", entry.title))??;
+        Ok(config.code_at(now)?)
+    }
+
+    /// Phishing verdict for a URL against the loaded vault. Returns JSON.
+    pub fn check_phishing(&self, page_url: String) -> Result<String> {
+        Ok(self.engine().check_phishing(&page_url)?)
+    }
+
+    /// Non-secret description of the key hierarchy. Returns JSON.
+    pub fn describe_key_hierarchy(&self) -> Result<String> {
+        Ok(self.engine().describe_key_hierarchy()?)
+    }
+}
+
+// --- Generators ---
+
+#[uniffi::export]
+pub fn generate_password(options: PasswordOptions) -> String {
+    generator::password(&generator::PasswordOptions {
+        length: options.length as usize,
+        use_uppercase: options.use_uppercase,
+        use_numbers: options.use_numbers,
+        use_symbols: options.use_symbols,
+    })
+}
+
+#[uniffi::export]
+pub fn generate_mac_password() -> String {
+    generator::mac_password()
+}
+
+#[uniffi::export]
+pub fn generate_passphrase() -> String {
+    generator::passphrase()
+}
+
+/// The key envelope (JSON) of a vault saved before envelopes, from its salt and
+/// password policy. Pass it to 'Vault::from_envelope' when no envelope is stored.
+#[uniffi::export]
+pub fn legacy_key_envelope(salt: Vec<u8>, password_policy: u8) -> Result<String> {
+    let policy = text::PasswordPolicy::from_version(password_policy)?;
+    Ok(serde_json::to_string(&kdf::KeyEnvelope::legacy(&salt, policy)).map_err(|e| format!("Key envelope serialize error: {}", e))?)
+}
+
+// --- Master Password Policy ---
+
+/// The password policy to create new vaults with. Store it next to the vault's salt.
+#[uniffi::export]
+pub fn current_password_policy() -> u8 {
+    text::PasswordPolicy::CURRENT.version()
+}
+
+/// Why a new master password was refused, with the message every client shows.
+#[derive(uniffi::Record)]
+pub struct PolicyViolation {
+    /// "tooShort", "lowEntropy", "common" or "personalInfo".
+    pub rule: String,
+    pub message: String,
+}
+
+/// Every rule a new master password breaks; empty means it may be used.
+/// 'hints' are things the user is known by (email, name).
+#[uniffi::export]
+pub fn validate_master_password(password: String, hints: Vec<String>) -> Vec<PolicyViolation> {
+    let hints: Vec<&str> = hints.iter().map(String::as_str).collect();
+    master_policy::validate_master_password(&password, &hints)
+        .into_iter()
+        .map(|violation| PolicyViolation { rule: violation.rule().to_string(), message: violation.message() })
+        .collect()
+}
+
+// --- Password Hint ---
+
+/// A sealed master password hint, stored next to the salt. Readable by anyone
+/// with the salt; a reminder, not a secret.
+#[derive(uniffi::Record)]
+pub struct SealedHint {
+    pub version: u8,
+    pub iv: String,
+    pub data: String,
+}
+
+#[uniffi::export]
+pub fn seal_password_hint(salt: Vec<u8>, hint: String, master_password: String) -> Result<SealedHint> {
+    let sealed = hint::seal_hint(&salt, &hint, &master_password)?;
+    Ok(SealedHint { version: sealed.version, iv: sealed.iv, data: sealed.data })
+}
+
+#[uniffi::export]
+pub fn open_password_hint(salt: Vec<u8>, sealed: SealedHint) -> Result<String> {
+    let sealed = hint::SealedHint { version: sealed.version, iv: sealed.iv, data: sealed.data };
+    Ok(hint::open_hint(&salt, &sealed)?)
+}
+
+// --- Biometric Unlock & Domains ---
+
+/// The wrapping key for a platform credential (Secure Enclave / Keystore key ID).
+#[uniffi::export]
+pub fn derive_bio_key(credential_id: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(biometric::derive_bio_key(&credential_id)?)
+}
+
+#[uniffi::export]
+pub fn wrap_password(password: String, bio_key: Vec<u8>, iv: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(biometric::wrap_password(&password, &bio_key, &iv)?)
+}
+
+#[uniffi::export]
+pub fn unwrap_password(wrapped: Vec<u8>, bio_key: Vec<u8>, iv: Vec<u8>) -> Result<String> {
+    Ok(biometric::unwrap_password(&wrapped, &bio_key, &iv)?)
+}
+
+/// The registrable domain of a URL ("https://mail.google.co.uk" -> "google.co.uk").
+#[uniffi::export]
+pub fn registrable_domain(url: String) -> Option<String> {
+    domains::registrable_domain(&url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_session_roundtrip() {
+        let salt = b"salt-123456789012".to_vec();
+        let vault = Vault::new("pw".to_string(), salt.clone(), 0).unwrap();
+        vault
+            .set_vault_json(r#"{"entries": [{"id": "1", "title": "GitHub", "totpSecret": "JBSWY3DPEHPK3PXP"}]}"#.to_string())
+            .unwrap();
+        let envelope = vault.seal().unwrap();
+
+        let reopened = Vault::new("pw".to_string(), salt.clone(), 0).unwrap();
+        reopened.load(Envelope { version: envelope.version, iv: envelope.iv.clone(), data: envelope.data.clone() }).unwrap();
+        // A committed envelope relabelled as legacy doesn't open.
+        assert!(reopened.load(Envelope { version: 1, iv: envelope.iv.clone(), data: envelope.data.clone() }).is_err());
+        assert_eq!(reopened.entry_count(), 1);
+        assert_eq!(reopened.totp_code("1".to_string(), 0).unwrap().len(), 6);
+
+        let wrong = Vault::new("wrong".to_string(), salt, 0).unwrap();
+        assert!(matches!(wrong.load(envelope), Err(SecurePassError::Failed { .. })));
+    }
+
+    #[test]
+    fn test_password_policy_is_applied() {
+        let salt = b"salt-123456789012".to_vec();
+        let composed = Vault::new("cr\u{e8}me".to_string(), salt.clone(), current_password_policy()).unwrap();
+        let envelope = composed.seal().unwrap();
+
+        let decomposed = Vault::new("cre\u{300}me".to_string(), salt.clone(), current_password_policy()).unwrap();
+        assert!(decomposed.load(Envelope { version: envelope.version, iv: envelope.iv.clone(), data: envelope.data.clone() }).is_ok());
+        let raw = Vault::new("cre\u{300}me".to_string(), salt.clone(), 0).unwrap();
+        assert!(raw.load(envelope).is_err());
+        assert!(Vault::new("pw".to_string(), salt, 9).is_err());
+    }
+
+    #[test]
+    fn test_legacy_envelope_unlocks_and_upgrades() {
+        let salt = b"salt-123456789012".to_vec();
+        let direct = Vault::new("pw".to_string(), salt.clone(), 0).unwrap();
+        let envelope = direct.seal().unwrap();
+
+        let legacy = legacy_key_envelope(salt.clone(), 0).unwrap();
+        let vault = Vault::from_envelope("pw".to_string(), legacy.clone()).unwrap();
+        vault.load(envelope).unwrap();
+        let upgrade = vault.commit_kdf_upgrade(String::new(), 42).unwrap().unwrap();
+        assert!(upgrade.audit_log_json.contains("42"));
+        assert!(vault.commit_kdf_upgrade(upgrade.audit_log_json.clone(), 43).unwrap().is_none());
+
+        // The upgraded envelope wraps the same master key.
+        let reopened = Vault::from_envelope("pw".to_string(), upgrade.envelope_json.clone()).unwrap();
+        assert!(reopened.load(direct.seal().unwrap()).is_ok());
+        assert!(reopened.commit_kdf_upgrade(String::new(), 44).unwrap().is_none());
+        assert!(Vault::from_envelope("wrong".to_string(), upgrade.envelope_json).is_err());
+        assert!(Vault::from_envelope("pw".to_string(), "{}".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_generators() {
+        let options = PasswordOptions { length: 24, use_uppercase: true, use_numbers: true, use_symbols: false };
+        assert_eq!(generate_password(options).len(), 24);
+        assert_eq!(generate_passphrase().split('-').count(), 4);
+    }
+
+    #[test]
+    fn test_master_password_policy() {
+        let violations = validate_master_password("qwerty".to_string(), vec![]);
+        assert_eq!(violations.iter().map(|v| v.rule.as_str()).collect::<Vec<_>>(), ["tooShort", "lowEntropy", "common"]);
+        assert!(validate_master_password("vexing-Tundra-quill-42".to_string(), vec!["a@b.c".to_string()]).is_empty());
+    }
+}
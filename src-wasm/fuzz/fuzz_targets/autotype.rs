@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use securepass_core::{autotype, vault};
+
+fuzz_target!(|input: &str| {
+    let _ = autotype::expand(input, &vault::VaultEntry::default(), Some("123456"));
+});
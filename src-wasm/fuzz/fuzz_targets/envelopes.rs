@@ -0,0 +1,24 @@
+#![no_main]
+
+// Every encrypted envelope format we read back from storage or another device.
+
+use libfuzzer_sys::fuzz_target;
+use securepass_core::{attachments, duress, emergency};
+
+fuzz_target!(|input: &[u8]| {
+    let key = [7u8; 32];
+    let _ = attachments::decrypt_content(&key, "fuzz", input);
+
+    let Ok(text) = std::str::from_utf8(input) else { return };
+    if let Ok(meta) = serde_json::from_str::<attachments::SealedMeta>(text) {
+        let _ = attachments::decrypt_meta(&key, "fuzz", &meta);
+    }
+    if let Ok(slots) = serde_json::from_str::<Vec<duress::EncryptedPackage>>(text) {
+        let _ = duress::open_slots(&key, &slots);
+    }
+    if let Ok(grant) = serde_json::from_str::<emergency::EmergencyGrant>(text) {
+        let request = emergency::request_access(&grant, 0);
+        let secret = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+        let _ = emergency::unwrap_master_key(&grant, &request, secret, u64::MAX);
+    }
+});
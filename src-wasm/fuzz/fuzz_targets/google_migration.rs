@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use securepass_core::google_migration;
+
+fuzz_target!(|input: &str| {
+    let _ = google_migration::parse_migration_uri(input);
+});
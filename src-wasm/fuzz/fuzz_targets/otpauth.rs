@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use securepass_core::otp;
+
+fuzz_target!(|input: &str| {
+    let _ = otp::TotpConfig::parse(input);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use securepass_core::vault;
+
+fuzz_target!(|input: &str| {
+    if let Ok(data) = vault::VaultData::from_json(input) {
+        let _ = data.to_json();
+        let _ = data.travel_view();
+    }
+});
@@ -1,18 +1,50 @@
 // --- 1. Imports ---
 // wasm_bindgen is the bridge between JavaScript and Rust.
+// Everything else lives in securepass-core (src-wasm/core); this file only
+// converts between JS values and the engine's plain Rust API.
 use wasm_bindgen::prelude::*;
 
-// Specialized cryptographic libraries (Crates)
-use argon2::Argon2; // Memory-hard key derivation
-use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce,
-}; // Authenticated encryption (Modern standard)
-use zeroize::Zeroize; // Security: physically wipes sensitive data from RAM
-use rand::{Rng, seq::SliceRandom}; // Secure randomness from the OS/Hardware
-use totp_rs::{Algorithm, TOTP, Secret}; // 2FA/TOTP logic
+use securepass_core::{
+    audit_log, backups, biometric, blobs, blocklist, breach, breached_sites, commit, device_key, domains, export_gate::ExportFormat, generator, hint, journal, kdf, master_policy, memorability, oplog, panic_report, paper, pin, reencrypt, scrub, sessions, storage,
+    text, throttle, validate, Engine,
+};
+#[cfg(feature = "pairing")]
+use securepass_core::pairing;
+#[cfg(feature = "qr-transfer")]
+use securepass_core::qr_transfer;
 use serde::{Deserialize, Serialize}; // Translates between JSON and Rust Data Types
 
+// Every block is zeroed as it is freed, so secrets dropped by the engine (and
+// strings JS passed in) don't linger in linear memory; see 'scrub'. The `talc`
+// feature swaps the allocator underneath on wasm32.
+#[cfg(not(all(feature = "talc", target_arch = "wasm32")))]
+#[global_allocator]
+static ALLOCATOR: scrub::ScrubbingAlloc<std::alloc::System> = scrub::ScrubbingAlloc(std::alloc::System);
+
+// SAFETY: a Wasm module built from this crate runs single-threaded, which is
+// all TalckWasm needs.
+#[cfg(all(feature = "talc", target_arch = "wasm32"))]
+#[global_allocator]
+static ALLOCATOR: scrub::ScrubbingAlloc<talc::TalckWasm> = scrub::ScrubbingAlloc(unsafe { talc::TalckWasm::new_global() });
+
+/// Turns a core error string into the exception JS sees.
+fn js_err(e: String) -> JsValue {
+    JsValue::from_str(&e)
+}
+
+/// A stored {version, iv, data} package; without a version it is a legacy one.
+fn parse_package(package_json: &str) -> Result<securepass_core::duress::EncryptedPackage, JsValue> {
+    serde_json::from_str(package_json).map_err(|e| js_err(format!("Package parse error: {}", e)))
+}
+
+/// The code of an input error thrown by any call ("iv_length", "salt_length",
+/// "key_length", "too_large", "invalid_utf8" or "invalid_text"), or undefined if
+/// the error isn't about malformed input. See 'validate'.
+#[wasm_bindgen]
+pub fn error_code(message: &str) -> Option<String> {
+    validate::error_code(message).map(|code| code.as_str().to_string())
+}
+
 /// --- 2. Data Structures ---
 /// This struct defines the settings for our password generator.
 /// #[wasm_bindgen] tells Rust to prepare this for use in JavaScript.
@@ -25,11 +57,30 @@ pub struct PasswordOptions {
     pub use_symbols: bool,
 }
 
+impl From<PasswordOptions> for securepass_core::PasswordOptions {
+    fn from(o: PasswordOptions) -> Self {
+        securepass_core::PasswordOptions {
+            length: o.length,
+            use_uppercase: o.use_uppercase,
+            use_numbers: o.use_numbers,
+            use_symbols: o.use_symbols,
+        }
+    }
+}
+
 /// The main "Bridge" that stays alive in the browser's memory.
-/// It holds the 'master_key' which is derived from your master password.
+/// It owns the engine, which holds the 'master_key' derived from your master
+/// password plus the decrypted vault once 'load_vault' has been called.
+/// The engine wipes both when the bridge is freed.
 #[wasm_bindgen]
 pub struct CryptoBridge {
-    master_key: [u8; 32],
+    engine: Engine,
+}
+
+impl From<Engine> for CryptoBridge {
+    fn from(engine: Engine) -> Self {
+        CryptoBridge { engine }
+    }
 }
 
 #[wasm_bindgen]
@@ -38,60 +89,50 @@ impl CryptoBridge {
     /// It takes your password and a unique "salt", then runs Argon2id.
     #[wasm_bindgen(constructor)]
     pub fn new(password: &str, salt: &[u8]) -> Result<CryptoBridge, JsValue> {
-        // We use an _internal version so we can test it without Wasm
-        Self::new_internal(password, salt).map_err(|e| JsValue::from_str(&e))
+        Engine::new(password, salt).map(Self::from).map_err(js_err)
     }
 
-    /// The actual logic for deriving the vault's master key.
-    fn new_internal(password: &str, salt: &[u8]) -> Result<CryptoBridge, String> {
-        let mut master_key = [0u8; 32];
-        let argon2 = Argon2::default(); // Uses Argon2id (the modern industry standard)
-        
-        // This line does the heavy lifting: turning a readable password into raw binary bytes.
-        argon2.hash_password_into(password.as_bytes(), salt, &mut master_key)
-            .map_err(|e| format!("Argon2 error: {}", e))?;
+    /// Like the constructor, but normalizes the password first under the policy
+    /// stored with the vault (0 = as typed, 1 = NFC). Vaults saved without a policy use 0;
+    /// new vaults use 'current_password_policy()'.
+    pub fn new_with_policy(password: &str, salt: &[u8], password_policy: u8) -> Result<CryptoBridge, JsValue> {
+        text::PasswordPolicy::from_version(password_policy)
+            .and_then(|policy| Engine::new_with_policy(password, salt, policy))
+            .map(Self::from)
+            .map_err(js_err)
+    }
 
-        Ok(CryptoBridge { master_key })
+    /// DEVICE KEY: Binds the key to this browser profile. 'response' is
+    /// crypto.subtle.encrypt({name: "AES-GCM", iv}, deviceKey, challenge) with the
+    /// profile's non-extractable key and the binding's iv and challenge. Call before
+    /// decrypting or encrypting the vault.
+    pub fn bind_to_device(&mut self, binding_json: &str, response: &[u8]) -> Result<(), JsValue> {
+        self.engine.bind_to_device(binding_json, response).map_err(js_err)
     }
 
-    /// ENCRYPT: Seals a piece of text using the master key.
-    /// 'iv' is a unique random number that makes the result different every time.
-    pub fn encrypt(&self, plaintext: &str, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
-        self.encrypt_internal(plaintext, iv).map_err(|e| JsValue::from_str(&e))
+    /// ENCRYPT: Seals a piece of text using the master key, with a nonce the engine
+    /// picks (it never repeats one). Returns JSON {iv, data}, byte arrays.
+    pub fn encrypt_package(&self, plaintext: &str) -> Result<String, JsValue> {
+        let package = self.engine.encrypt_package(plaintext).map_err(js_err)?;
+        serde_json::to_string(&package).map_err(|e| JsValue::from_str(&format!("Package serialize error: {}", e)))
     }
 
-    fn encrypt_internal(&self, plaintext: &str, iv: &[u8]) -> Result<Vec<u8>, String> {
-        // Initialize the AES-256-GCM cipher using our master key
-        let cipher = Aes256Gcm::new_from_slice(&self.master_key)
-            .map_err(|e| format!("Cipher init error: {}", e))?;
-        
-        let nonce = Nonce::from_slice(iv); // Nonce is just another word for IV
-        
-        // Perform the encryption
-        let ciphertext = cipher.encrypt(nonce, plaintext.as_bytes())
-            .map_err(|e| format!("Encryption error: {}", e))?;
-            
-        Ok(ciphertext)
+    /// ENCRYPT (LEGACY): Seals a piece of text with a caller's IV. An IV this bridge
+    /// has already sealed with is refused; prefer 'encrypt_package'.
+    pub fn encrypt(&self, plaintext: &str, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.engine.encrypt(plaintext, iv).map_err(js_err)
     }
 
-    /// DECRYPT: Unseals encrypted data.
-    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<String, JsValue> {
-        self.decrypt_internal(ciphertext, iv).map_err(|e| JsValue::from_str(&e))
+    /// DECRYPT: Unseals a JSON {version, iv, data} package from 'encrypt_package'. A
+    /// package without a version is a legacy one from 'encrypt'.
+    pub fn decrypt_package(&self, package_json: &str) -> Result<String, JsValue> {
+        let package = parse_package(package_json)?;
+        self.engine.decrypt_package(&package).map_err(js_err)
     }
 
-    fn decrypt_internal(&self, ciphertext: &[u8], iv: &[u8]) -> Result<String, String> {
-        let cipher = Aes256Gcm::new_from_slice(&self.master_key)
-            .map_err(|e| format!("Cipher init error: {}", e))?;
-            
-        let nonce = Nonce::from_slice(iv);
-        
-        // Decrypt the binary data back into a vector of bytes
-        let plaintext_vec = cipher.decrypt(nonce, ciphertext)
-            .map_err(|e| format!("Decryption error: {}", e))?;
-            
-        // Convert the bytes back into a readable UTF-8 string
-        String::from_utf8(plaintext_vec)
-            .map_err(|e| format!("UTF-8 error: {}", e))
+    /// DECRYPT (LEGACY): Unseals what 'encrypt' sealed.
+    pub fn decrypt(&self, ciphertext: &[u8], iv: &[u8]) -> Result<String, JsValue> {
+        self.engine.decrypt(ciphertext, iv).map_err(js_err)
     }
 
     /// GENERATOR: Creates a high-entropy random password.
@@ -100,292 +141,2117 @@ impl CryptoBridge {
         // Convert the JavaScript "Options" object into our Rust struct
         let options: PasswordOptions = serde_wasm_bindgen::from_value(options_val)
             .map_err(|e| JsValue::from_str(&format!("Options parse error: {}", e)))?;
-            
-        Ok(self.generate_password_core(options))
-    }
 
-    /// The core logic for generating passwords with guaranteed diversity.
-    fn generate_password_core(&self, options: PasswordOptions) -> String {
-        let lowercase = "abcdefghijklmnopqrstuvwxyz";
-        let uppercase = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-        let numbers = "0123456789";
-        let symbols = "!@#$%^&*()_+~`|}{[]:;?><,./-=";
+        Ok(generator::password(&options.into()))
+    }
 
-        let mut charset = lowercase.to_string();
-        let mut guaranteed_chars = Vec::new();
-        let mut rng = rand::thread_rng(); // Secure random generator
+    /// MAC-STYLE: Generates passwords like "abc12x-def45y-ghi78z"
+    pub fn generate_mac_password(&self) -> String {
+        generator::mac_password()
+    }
 
-        // To guarantee diversity, we pick one char from each ENABLED type first
-        guaranteed_chars.push(lowercase.chars().nth(rng.gen_range(0..lowercase.len())).unwrap());
+    /// PASSPHRASE: Generates memorable word-based passwords.
+    pub fn generate_passphrase(&self) -> String {
+        generator::passphrase()
+    }
 
-        if options.use_uppercase { 
-            charset.push_str(uppercase);
-            guaranteed_chars.push(uppercase.chars().nth(rng.gen_range(0..uppercase.len())).unwrap());
-        }
-        if options.use_numbers { 
-            charset.push_str(numbers);
-            guaranteed_chars.push(numbers.chars().nth(rng.gen_range(0..numbers.len())).unwrap());
-        }
-        if options.use_symbols { 
-            charset.push_str(symbols);
-            guaranteed_chars.push(symbols.chars().nth(rng.gen_range(0..symbols.len())).unwrap());
-        }
+    /// PASSPHRASE: A passphrase from JSON {words, separator, language}, each optional
+    /// (4, "-", "en"); 'language' is one of 'passphrase_languages'.
+    pub fn generate_passphrase_with(&self, options_json: &str) -> Result<String, JsValue> {
+        let options: generator::PassphraseOptions =
+            serde_json::from_str(options_json).map_err(|e| JsValue::from_str(&format!("Options parse error: {}", e)))?;
+        generator::passphrase_with(&options).map_err(js_err)
+    }
 
-        // Fill the rest of the password length with random chars from the full set
-        if options.length < guaranteed_chars.len() {
-            return guaranteed_chars.into_iter().take(options.length).collect();
-        }
+    /// PASSPHRASE: 'count' passphrases for the options (as 'generate_passphrase_with'),
+    /// most memorable first: JSON [{passphrase, entropyBits, memorability}], with
+    /// memorability from 0 to 100. Offer the first as the strongest memorable one.
+    pub fn suggest_passphrases(&self, options_json: &str, count: usize) -> Result<String, JsValue> {
+        let options: generator::PassphraseOptions =
+            serde_json::from_str(options_json).map_err(|e| JsValue::from_str(&format!("Options parse error: {}", e)))?;
+        let batch = memorability::suggest(&options, count).map_err(js_err)?;
+        serde_json::to_string(&batch).map_err(|e| JsValue::from_str(&format!("Passphrase serialize error: {}", e)))
+    }
 
-        let mut pwd_chars: Vec<char> = (0..(options.length - guaranteed_chars.len()))
-            .map(|_| {
-                let idx = rng.gen_range(0..charset.len());
-                charset.chars().nth(idx).unwrap()
-            })
-            .collect();
-        
-        // Add our guaranteed chars back in and shuffle them so they aren't always at the end
-        pwd_chars.extend(guaranteed_chars);
-        pwd_chars.shuffle(&mut rng);
-        pwd_chars.into_iter().collect()
+    /// PASSPHRASE: The languages this build has passphrase words for, as a JSON array
+    /// of codes (["en", "de", "fr", "es"]).
+    pub fn passphrase_languages(&self) -> String {
+        let codes: Vec<&str> = generator::passphrase_languages().into_iter().map(generator::Language::code).collect();
+        serde_json::to_string(&codes).unwrap_or_default()
     }
 
-    /// MAC-STYLE: Generates passwords like "abc12x-def45y-ghi78z"
-    pub fn generate_mac_password(&self) -> String {
-        let charset = "abcdefghijkmnopqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
-        let mut rng = rand::thread_rng();
-        
-        let mut generate_block = || {
-            (0..6)
-                .map(|_| {
-                    let idx = rng.gen_range(0..charset.len());
-                    charset.chars().nth(idx).unwrap()
-                })
-                .collect::<String>()
-        };
+    /// 2FA: Calculates the current TOTP code. 'secret' is a Base32 secret (SHA1,
+    /// 6 digits, 30 s) or an otpauth://totp/ URI, whose parameters are honoured.
+    pub fn get_totp_code(&self, secret: &str) -> Result<String, JsValue> {
+        self.engine.get_totp_code(secret).map_err(js_err)
+    }
 
-        format!("{}-{}-{}", generate_block(), generate_block(), generate_block())
+    /// 2FA: The TOTP code for Unix time 'now' (seconds) plus 'skew_secs'. Pass a
+    /// trusted time (e.g. a server's Date header) as 'now', or the device's time
+    /// and the skew 'estimate_totp_skew' found.
+    pub fn get_totp_code_at(&self, secret: &str, now: f64, skew_secs: f64) -> Result<String, JsValue> {
+        self.engine.get_totp_code_at(secret, now as u64, skew_secs as i64).map_err(js_err)
     }
 
-    /// PASSPHRASE: Generates memorable word-based passwords.
-    pub fn generate_passphrase(&self) -> String {
-        let words = vec![
-            "azure", "bright", "cloud", "dance", "eagle", "forest", "glory", "honey", "island", "jungle",
-            "knight", "lemon", "mountain", "night", "ocean", "pearl", "quartz", "river", "silver", "tiger",
-            "unique", "valley", "winter", "xenon", "yellow", "zebra", "alpha", "bravo", "cactus", "delta",
-            "echo", "frost", "garden", "harvest", "icon", "jade", "karma", "lunar", "magic", "nebula",
-            "orbit", "plasma", "quest", "rocket", "solar", "terra", "ultra", "vivid", "wave", "yield"
-        ];
-
-        let mut rng = rand::thread_rng();
-        (0..4)
-            .map(|_| *words.choose(&mut rng).unwrap())
-            .collect::<Vec<_>>()
-            .join("-")
-    }
-
-    /// 2FA: Calculates the current 6-digit TOTP code.
-    pub fn get_totp_code(&self, secret: &str) -> Result<String, JsValue> {
-        self.get_totp_code_internal(secret).map_err(|e| JsValue::from_str(&e))
+    /// 2FA: The code at Unix time 'now' with explicit parameters, for services that
+    /// list them next to a bare secret: algorithm "SHA1" | "SHA256" | "SHA512",
+    /// 6-8 digits, period in seconds.
+    pub fn get_totp_code_with(&self, secret: &str, algorithm: &str, digits: u32, period: f64, now: f64) -> Result<String, JsValue> {
+        self.engine.get_totp_code_with(secret, algorithm, digits, period as u64, now as u64).map_err(js_err)
     }
 
-    fn get_totp_code_internal(&self, secret: &str) -> Result<String, String> {
-        // Parse the secret (usually a Base32 string)
-        let secret_bytes = Secret::Encoded(secret.to_string())
-            .to_bytes()
-            .map_err(|e| format!("TOTP bytes error: {}", e))?;
+    /// YUBIKEY: Checks an OTP against the Yubico OTP key saved in a "yubikey" entry
+    /// and records its counters (seal the vault afterwards). Returns JSON
+    /// {useCounter, sessionCounter, timestamp}; throws on a replayed or foreign OTP.
+    pub fn verify_yubikey_otp(&mut self, entry_id: &str, otp: &str) -> Result<String, JsValue> {
+        self.engine.verify_yubikey_otp(entry_id, otp).map_err(js_err)
+    }
 
-        // Initialize the TOTP object with standard settings (SHA1, 6 digits, 30s)
-        let totp = TOTP::new(
-            Algorithm::SHA1,
-            6,
-            1,
-            30,
-            secret_bytes,
-        ).map_err(|e| format!("TOTP init error: {}", e))?;
-        
-        Ok(totp.generate_current().map_err(|e| format!("TOTP generation error: {}", e))?)
+    /// YUBIKEY: The HMAC-SHA1 challenge-response a "yubikey" entry's slot would give.
+    pub fn yubikey_challenge_response(&self, entry_id: &str, challenge: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.engine.yubikey_challenge_response(entry_id, challenge).map_err(js_err)
     }
 
     /// HISTORY: Manages the "Sliding Window" of previous passwords.
     pub fn rotate_history(&self, current_password: &str, history_json: &str) -> Result<String, JsValue> {
-        self.rotate_history_internal(current_password, history_json).map_err(|e| JsValue::from_str(&e))
-    }
-
-    fn rotate_history_internal(&self, current_password: &str, history_json: &str) -> Result<String, String> {
-        // Deserialize the JSON string back into a Rust Vector (List)
-        let mut history: Vec<String> = serde_json::from_str(history_json)
-            .map_err(|e| format!("History parse error: {}", e))?;
-            
-        // Insert at the front (index 0)
-        history.insert(0, current_password.to_string());
-        
-        // Keep only the most recent 5
-        if history.len() > 5 {
-            history.truncate(5);
-        }
-        
-    serde_json::to_string(&history)
-            .map_err(|e| format!("History serialize error: {}", e))
+        self.engine.rotate_history(current_password, history_json).map_err(js_err)
     }
 }
 
+/// A fresh browser binding as JSON {version, iv, challenge}. Store it next to the
+/// vault's salt when binding the vault to this profile's WebCrypto key.
+#[wasm_bindgen]
+pub fn new_device_binding() -> Result<String, JsValue> {
+    serde_json::to_string(&device_key::DeviceBinding::generate())
+        .map_err(|e| JsValue::from_str(&format!("Device binding serialize error: {}", e)))
+}
+
+/// The password policy to create new vaults with. Store it next to the vault's salt.
+#[wasm_bindgen]
+pub fn current_password_policy() -> u8 {
+    text::PasswordPolicy::CURRENT.version()
+}
+
+/// HINT: Seals an optional master password hint for the vault with this salt.
+/// Returns JSON {version, iv, data} to store next to the salt. Refuses a hint
+/// that contains the password. Anyone with the salt can read it: it's a reminder, not a secret.
+#[wasm_bindgen]
+pub fn seal_password_hint(salt: &[u8], hint: &str, master_password: &str) -> Result<String, JsValue> {
+    let sealed = hint::seal_hint(salt, hint, master_password).map_err(js_err)?;
+    serde_json::to_string(&sealed).map_err(|e| JsValue::from_str(&format!("Hint serialize error: {}", e)))
+}
+
+/// HINT: Reads the stored hint back, for the unlock screen.
+#[wasm_bindgen]
+pub fn open_password_hint(salt: &[u8], sealed_json: &str) -> Result<String, JsValue> {
+    serde_json::from_str(sealed_json)
+        .map_err(|e| format!("Hint parse error: {}", e))
+        .and_then(|sealed| hint::open_hint(salt, &sealed))
+        .map_err(js_err)
+}
+
+/// True if the password is on the embedded common-password blocklist (ignoring
+/// case and a trailing run of digits or symbols). Refuse such a master password.
+#[wasm_bindgen]
+pub fn is_common_password(password: &str) -> bool {
+    blocklist::is_common_password(password)
+}
+
+/// Checks a new master password against the shared policy. 'hints_json' is a JSON
+/// array of things the user is known by (email, name). Returns a JSON array of
+/// {rule, ..., message}; empty means the password may be used.
+#[wasm_bindgen]
+pub fn validate_master_password(password: &str, hints_json: &str) -> Result<String, JsValue> {
+    let hints: Vec<String> =
+        serde_json::from_str(hints_json).map_err(|e| JsValue::from_str(&format!("Hints parse error: {}", e)))?;
+    let hints: Vec<&str> = hints.iter().map(String::as_str).collect();
+    serde_json::to_string(&master_policy::report(password, &hints))
+        .map_err(|e| JsValue::from_str(&format!("Policy serialize error: {}", e)))
+}
+
 /// --- 3. Standalone Biometric Logic ---
 /// These don't require an active bridge because they deal with derivation.
 
 #[wasm_bindgen]
 pub fn derive_bio_key(credential_id: &[u8]) -> Result<Vec<u8>, JsValue> {
-    let mut key = [0u8; 32];
-    let argon2 = Argon2::default();
-    
-    // We use a fixed salt for biometric key derivation so it's consistent across sessions.
-    let salt = b"WebVault_BioSalt"; 
-    
-    argon2.hash_password_into(credential_id, salt, &mut key)
-        .map_err(|e| JsValue::from_str(&format!("Argon2 error: {}", e)))?;
-        
-    Ok(key.to_vec())
+    biometric::derive_bio_key(credential_id).map_err(js_err)
 }
 
 /// WRAP: Encrypts the master password so it can be stored in browser storage safely.
 #[wasm_bindgen]
 pub fn wrap_password(password: &str, bio_key: &[u8], iv: &[u8]) -> Result<Vec<u8>, JsValue> {
-    let cipher = Aes256Gcm::new_from_slice(bio_key)
-        .map_err(|e| JsValue::from_str(&format!("Cipher init error: {}", e)))?;
-        
-    let nonce = Nonce::from_slice(iv);
-    
-    let ciphertext = cipher.encrypt(nonce, password.as_bytes())
-        .map_err(|e| JsValue::from_str(&format!("Wrapping error: {}", e)))?;
-        
-    Ok(ciphertext)
+    biometric::wrap_password(password, bio_key, iv).map_err(js_err)
 }
 
 /// UNWRAP: Decrypts the master password when you use TouchID/FaceID.
 #[wasm_bindgen]
 pub fn unwrap_password(wrapped_data: &[u8], bio_key: &[u8], iv: &[u8]) -> Result<String, JsValue> {
-    let cipher = Aes256Gcm::new_from_slice(bio_key)
-        .map_err(|e| JsValue::from_str(&format!("Cipher init error: {}", e)))?;
-        
-    let nonce = Nonce::from_slice(iv);
-    
-    let plaintext_vec = cipher.decrypt(nonce, wrapped_data)
-        .map_err(|e| JsValue::from_str(&format!("Unwrapping error: {}", e)))?;
-        
-    String::from_utf8(plaintext_vec)
-        .map_err(|e| JsValue::from_str(&format!("UTF-8 error: {}", e)))
+    biometric::unwrap_password(wrapped_data, bio_key, iv).map_err(js_err)
+}
+
+// --- 4. Emergency Access ---
+// A trusted contact can recover the vault after a waiting period the owner chooses.
+// The same keypair also receives single entries shared by other users.
+
+/// Creates the keypair a trusted contact shares with vault owners. Returns JSON.
+#[wasm_bindgen]
+pub fn generate_emergency_keypair() -> Result<String, JsValue> {
+    securepass_core::generate_emergency_keypair().map_err(js_err)
+}
+
+/// CONTACT: Records the moment the contact asks for access (the server starts the clock).
+#[wasm_bindgen]
+pub fn request_emergency_access(grant_json: &str, now: f64) -> Result<String, JsValue> {
+    securepass_core::request_emergency_access(grant_json, now as u64).map_err(js_err)
 }
 
-/// --- 4. Memory Security (Cleanup) ---
-/// This is a CRITICAL security feature. 
-/// When the 'CryptoBridge' object is destroyed, we physically wipe the master key from memory.
-impl Drop for CryptoBridge {
-    fn drop(&mut self) {
-        self.master_key.zeroize(); // Overwrites the key with zeros in RAM
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// OWNER: Seals this vault's master key to a trusted contact's public key, once
+    /// 'password' checks out as the master password. 'wait_period_secs' is how long
+    /// the contact must wait after requesting access.
+    pub fn create_emergency_grant(&self, password: &str, contact_public_key: &str, wait_period_secs: f64, now: f64) -> Result<String, JsValue> {
+        self.engine
+            .create_emergency_grant(password, contact_public_key, wait_period_secs as u64, now as u64)
+            .map_err(js_err)
+    }
+
+    /// CONTACT: Opens a grant after the wait period and returns a bridge for the owner's vault.
+    pub fn from_emergency_grant(grant_json: &str, request_json: &str, contact_secret_key: &str, now: f64) -> Result<CryptoBridge, JsValue> {
+        Engine::from_emergency_grant(grant_json, request_json, contact_secret_key, now as u64)
+            .map(Self::from)
+            .map_err(js_err)
+    }
+
+    /// SHARE: Seals one entry to another user's public key (same keypair format as
+    /// an emergency contact's). Returns the bundle JSON.
+    pub fn share_entry(&self, entry_id: &str, recipient_public_key: &str, include_totp: bool, now: f64) -> Result<String, JsValue> {
+        self.engine.share_entry(entry_id, recipient_public_key, include_totp, now as u64).map_err(js_err)
+    }
+
+    /// SHARE: Like 'share_entry', but the bundle stops opening at 'expires_at' (Unix
+    /// seconds). The expiry is signed with the owner's sharing secret key.
+    pub fn share_entry_until(
+        &self,
+        entry_id: &str,
+        recipient_public_key: &str,
+        owner_secret_key: &str,
+        include_totp: bool,
+        expires_at: f64,
+        now: f64,
+    ) -> Result<String, JsValue> {
+        self.engine
+            .share_entry_until(entry_id, recipient_public_key, owner_secret_key, include_totp, expires_at as u64, now as u64)
+            .map_err(js_err)
+    }
+
+    /// RECEIVE: Opens a shared entry and adds it to this vault. Returns the new entry's ID.
+    /// Time-boxed shares can't be added; view them with 'open_shared_entry'.
+    pub fn import_shared_entry(&mut self, bundle_json: &str, secret_key: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.import_shared_entry(bundle_json, secret_key, now as u64).map_err(js_err)
     }
 }
 
-// --- 5. Unit Tests ---
-// These ensure that the "Engine" is working perfectly before we even connect it to the web.
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// RECEIVE: Opens a shared entry for viewing only (refused once a time-boxed share
+/// has expired). Returns JSON {entry, expiresAt, ownerVerifyKey}.
+#[wasm_bindgen]
+pub fn open_shared_entry(bundle_json: &str, secret_key: &str, now: f64) -> Result<String, JsValue> {
+    securepass_core::open_shared_entry(bundle_json, secret_key, now as u64).map_err(js_err)
+}
 
-    #[test]
-    fn test_key_derivation() {
-        let password = "master-password";
-        let salt = b"some-salt-123456";
-        let bridge = CryptoBridge::new_internal(password, salt).unwrap();
-        assert_eq!(bridge.master_key.len(), 32);
-        
-        let bridge2 = CryptoBridge::new_internal(password, salt).unwrap();
-        assert_eq!(bridge.master_key, bridge2.master_key);
+/// ROTATE: Retires a sharing keypair and re-seals the bundles in 'received_json'
+/// (a JSON array) to a new one. Returns JSON {keypair, received, record, leftBehind}.
+#[wasm_bindgen]
+pub fn rotate_sharing_keypair(old_secret_key: &str, received_json: &str, now: f64) -> Result<String, JsValue> {
+    securepass_core::rotate_sharing_keypair(old_secret_key, received_json, now as u64).map_err(js_err)
+}
+
+/// The verify key to publish next to a sharing public key, for its secret key.
+#[wasm_bindgen]
+pub fn sharing_verify_key(secret_key: &str) -> Result<String, JsValue> {
+    securepass_core::sharing_verify_key(secret_key).map_err(js_err)
+}
+
+/// CONTACT: Checks a rotation record against the verify key trusted for the old
+/// keypair. Returns JSON {publicKey, verifyKey}: the keys to use from now on.
+#[wasm_bindgen]
+pub fn verify_sharing_key_rotation(record_json: &str, trusted_verify_key: &str) -> Result<String, JsValue> {
+    securepass_core::verify_sharing_key_rotation(record_json, trusted_verify_key).map_err(js_err)
+}
+
+// --- 5. Travel Mode & Autotype ---
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// TRAVEL: Encrypts the vault with every travel-flagged entry and folder removed.
+    /// Returns JSON {iv, data}, a normal vault package that unlocks like any other.
+    pub fn build_travel_vault_package(&self, vault_json: &str) -> Result<String, JsValue> {
+        let package = self.engine.build_travel_vault_package(vault_json).map_err(js_err)?;
+        serde_json::to_string(&package).map_err(|e| JsValue::from_str(&format!("Package serialize error: {}", e)))
     }
 
-    #[test]
-    fn test_encrypt_decrypt() {
-        let bridge = CryptoBridge::new_internal("pwd", b"salt-123456789012").unwrap();
-        let plaintext = "Sensitive data to protect";
-        let iv = [0u8; 12];
-        
-        let ciphertext = bridge.encrypt_internal(plaintext, &iv).unwrap();
-        assert_ne!(ciphertext, plaintext.as_bytes());
-        
-        let decrypted = bridge.decrypt_internal(&ciphertext, &iv).unwrap();
-        assert_eq!(decrypted, plaintext);
+    /// TRAVEL (LEGACY): 'build_travel_vault_package' with a caller's IV.
+    pub fn build_travel_vault(&self, vault_json: &str, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.engine.build_travel_vault(vault_json, iv).map_err(js_err)
     }
 
-    #[test]
-    fn test_password_generation() {
-        let bridge = CryptoBridge::new_internal("p", b"salt-123456789012").unwrap();
-        let options = PasswordOptions {
-            length: 16,
-            use_uppercase: true,
-            use_numbers: true,
-            use_symbols: false,
-        };
-        let pwd = bridge.generate_password_core(options);
-        assert_eq!(pwd.len(), 16);
-        assert!(pwd.chars().any(|c| c.is_uppercase()));
-        assert!(pwd.chars().any(|c| c.is_numeric()));
+    /// AUTOTYPE: Expands a sequence like "{USERNAME}{TAB}{PASSWORD}{ENTER}" for one entry.
+    /// An empty template falls back to the default sequence. Returns a JSON list of actions.
+    pub fn expand_autotype(&self, template: &str, entry_json: &str) -> Result<String, JsValue> {
+        self.engine.expand_autotype(template, entry_json).map_err(js_err)
     }
+}
 
-    #[test]
-    fn test_password_format_mac() {
-        let bridge = CryptoBridge::new_internal("p", b"salt-123456789012").unwrap();
-        let pwd = bridge.generate_mac_password();
-        assert_eq!(pwd.len(), 20); 
-        assert_eq!(pwd.chars().filter(|&c| c == '-').count(), 2);
+// --- 6. Vault Store ---
+// Keeps the decrypted vault inside Wasm memory so features can work by entry ID.
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// LOAD: Decrypts a vault package (JSON, from 'seal_vault_package') into the
+    /// bridge, replacing whatever was loaded.
+    pub fn load_vault_package(&mut self, package_json: &str) -> Result<(), JsValue> {
+        let package = parse_package(package_json)?;
+        self.engine.load_vault_package(&package).map_err(js_err)
     }
 
-    #[test]
-    fn test_passphrase() {
-        let bridge = CryptoBridge::new_internal("p", b"salt-123456789012").unwrap();
-        let phrase = bridge.generate_passphrase();
-        let words: Vec<&str> = phrase.split('-').collect();
-        assert_eq!(words.len(), 4);
+    /// LOAD (LEGACY): Decrypts what 'seal_vault' sealed.
+    pub fn load_vault(&mut self, ciphertext: &[u8], iv: &[u8]) -> Result<(), JsValue> {
+        self.engine.load_vault(ciphertext, iv).map_err(js_err)
     }
 
-    #[test]
-    fn test_totp_generation() {
-        let bridge = CryptoBridge::new_internal("p", b"salt-123456789012").unwrap();
-        let secret = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP"; 
-        let code = bridge.get_totp_code_internal(secret).unwrap();
-        assert_eq!(code.len(), 6);
-        assert!(code.chars().all(|c| c.is_digit(10)));
+    /// SEAL: Encrypts the loaded vault so it can be written back to storage, with a
+    /// nonce the engine picks. Returns JSON {iv, data}, byte arrays.
+    pub fn seal_vault_package(&self) -> Result<String, JsValue> {
+        let package = self.engine.seal_vault_package().map_err(js_err)?;
+        serde_json::to_string(&package).map_err(|e| JsValue::from_str(&format!("Package serialize error: {}", e)))
     }
 
-    #[test]
-    fn test_history_rotation() {
-        let bridge = CryptoBridge::new_internal("p", b"salt-123456789012").unwrap();
-        let history_json = "[\"old1\", \"old2\"]";
-        let new_history = bridge.rotate_history_internal("new_pwd", history_json).unwrap();
-        
-        let parsed: Vec<String> = serde_json::from_str(&new_history).unwrap();
-        assert_eq!(parsed[0], "new_pwd");
-        assert_eq!(parsed[1], "old1");
-        assert_eq!(parsed.len(), 3);
-        
-        let full_history = "[\"1\", \"2\", \"3\", \"4\", \"5\"]";
-        let rotated = bridge.rotate_history_internal("6", full_history).unwrap();
-        let parsed_full: Vec<String> = serde_json::from_str(&rotated).unwrap();
-        assert_eq!(parsed_full.len(), 5);
-        assert_eq!(parsed_full[0], "6");
-        assert_eq!(parsed_full[4], "4");
+    /// SEAL (LEGACY): 'seal_vault_package' with a caller's IV, which must be fresh.
+    pub fn seal_vault(&self, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.engine.seal_vault(iv).map_err(js_err)
     }
 
-    #[test]
-    fn test_biometric_wrapping() {
-        let credential_id = b"test-credential-id";
-        let password = "super-secret-master-password";
-        let iv = [1u8; 12];
-        
-        let bio_key = derive_bio_key(credential_id).unwrap();
-        assert_eq!(bio_key.len(), 32);
-        
-        let wrapped = wrap_password(password, &bio_key, &iv).unwrap();
-        assert_ne!(wrapped, password.as_bytes());
-        
-        let unwrapped = unwrap_password(&wrapped, &bio_key, &iv).unwrap();
-        assert_eq!(unwrapped, password);
+    /// How many entries are currently loaded.
+    pub fn entry_count(&self) -> usize {
+        self.engine.entry_count()
+    }
+
+    /// IDLE: Seals the loaded entries' secrets in memory while the vault is unused.
+    /// Lists, search and single-entry reveal and fill keep working; saving, exports
+    /// and sync fail with an "Idle error" until 'wake'.
+    pub fn go_idle(&mut self) -> Result<(), JsValue> {
+        self.engine.go_idle().map_err(js_err)
+    }
+
+    /// IDLE: Puts the sealed secrets back, e.g. when the popup opens.
+    pub fn wake(&mut self) -> Result<(), JsValue> {
+        self.engine.wake().map_err(js_err)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.engine.is_idle()
+    }
+
+    /// LOCK: Wipes the key and vault and frees the bridge (the JS object is
+    /// consumed). The scrubbing allocator zeroes everything the engine freed;
+    /// this also clears the stack the last calls used.
+    pub fn lock(self) {
+        drop(self.engine);
+        scrub::scrub_stack();
+    }
+
+    /// DEDUPE: Groups of logins for the same site and username, as JSON
+    /// [{key, ids, passwordsDiffer}], for a "clean up duplicates" screen.
+    pub fn find_duplicates(&self) -> Result<String, JsValue> {
+        self.engine.find_duplicates().map_err(js_err)
+    }
+
+    /// DEDUPE: Merges the entries (JSON array of IDs) into the first, keeping the
+    /// "strongest" or "newest" password and the rest in its history. Returns JSON
+    /// {id, removed}.
+    pub fn merge_entries(&mut self, ids_json: &str, strategy: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.merge_entries(ids_json, strategy, now as u64).map_err(js_err)
+    }
+
+    /// TEMPLATES: Adds an entry made from a template ('list_entry_templates') with its
+    /// typed fields. Returns the new entry's ID.
+    pub fn add_entry_from_template(&mut self, template_id: &str, title: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.add_entry_from_template(template_id, title, now as u64).map_err(js_err)
+    }
+
+    /// WIFI: The "join this network" QR text for a Wi-Fi entry.
+    pub fn entry_wifi_qr_payload(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.entry_wifi_qr_payload(entry_id).map_err(js_err)
+    }
+
+    /// CARD: A card entry as "•••• 4242 (Visa, expires 08/26)" plus its parts, as JSON.
+    /// The full number stays in the engine.
+    pub fn card_summary(&self, entry_id: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.card_summary(entry_id, now as u64).map_err(js_err)
+    }
+
+    /// CARD: The full card number, grouped as printed. Call only when the user clicks reveal.
+    pub fn reveal_card_number(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.reveal_card_number(entry_id).map_err(js_err)
+    }
+
+    /// LICENSE: A license entry's key and purchase metadata as JSON.
+    pub fn license_details(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.license_details(entry_id).map_err(js_err)
+    }
+
+    /// LICENSE: Checks and stores a license's details (JSON {key, version, licensedTo,
+    /// registeredEmail, purchaseDate, orderNumber, purchasedFrom, price}), normalizing the key.
+    pub fn set_license_details(&mut self, entry_id: &str, details_json: &str, now: f64) -> Result<(), JsValue> {
+        self.engine.set_license_details(entry_id, details_json, now as u64).map_err(js_err)
+    }
+
+    /// NOTES: An entry's notes as sanitized HTML, rendered from Markdown in Rust. Safe for innerHTML.
+    #[cfg(feature = "markdown")]
+    pub fn render_entry_notes(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.render_entry_notes(entry_id).map_err(js_err)
+    }
+
+    /// BACKUP CODES: Stores 2FA recovery codes as pasted from the site, replacing any
+    /// earlier set. Returns JSON {entryId, title, total, remaining, low}.
+    pub fn set_backup_codes(&mut self, entry_id: &str, codes: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.set_backup_codes(entry_id, codes, now as u64).map_err(js_err)
+    }
+
+    /// BACKUP CODES: Marks a code used; returns the status JSON. Warn the user when 'low'.
+    pub fn mark_backup_code_used(&mut self, entry_id: &str, code: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.mark_backup_code_used(entry_id, code, now as u64).map_err(js_err)
+    }
+
+    /// BACKUP CODES: How many of an entry's codes are left, as status JSON.
+    pub fn backup_code_status(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.backup_code_status(entry_id).map_err(js_err)
+    }
+
+    /// BACKUP CODES: Entries running low on codes, as a JSON array of statuses.
+    pub fn low_backup_codes(&self) -> Result<String, JsValue> {
+        self.engine.low_backup_codes().map_err(js_err)
+    }
+
+    /// BACKUP CODES: An entry's codes as plain text for printing.
+    pub fn print_backup_codes(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.print_backup_codes(entry_id).map_err(js_err)
+    }
+
+    /// EXTRA LOCK: Seals an entry's secrets under a second passphrase, for items like
+    /// wallet seeds. The entry still lists; its secrets need the passphrase to show.
+    pub fn set_extra_lock(&mut self, entry_id: &str, passphrase: &str, now: f64) -> Result<(), JsValue> {
+        self.engine.set_extra_lock(entry_id, passphrase, now as u64).map_err(js_err)
+    }
+
+    /// EXTRA LOCK: Removes an entry's extra lock.
+    pub fn clear_extra_lock(&mut self, entry_id: &str, passphrase: &str, now: f64) -> Result<(), JsValue> {
+        self.engine.clear_extra_lock(entry_id, passphrase, now as u64).map_err(js_err)
+    }
+
+    /// EXTRA LOCK: The full entry JSON of a locked entry, which stays locked.
+    pub fn open_locked_entry(&self, entry_id: &str, passphrase: &str) -> Result<String, JsValue> {
+        self.engine.open_locked_entry(entry_id, passphrase).map_err(js_err)
+    }
+
+    /// REPROMPT: Confirms the master password again, against the envelope the vault
+    /// was unlocked with, so reprompt-flagged entries can be revealed for the next
+    /// 5 minutes, timed by the engine. Reveal, fill, copy and share calls on such
+    /// entries fail with a "Reprompt error" until then.
+    pub fn confirm_master(&mut self, password: &str) -> Result<(), JsValue> {
+        self.engine.confirm_master(password).map_err(js_err)
+    }
+
+    /// REPROMPT: Ends the current confirmation, e.g. when the popup closes.
+    pub fn end_reprompt(&mut self) {
+        self.engine.end_reprompt();
+    }
+
+    /// REPROMPT: Flags or unflags an entry; unflagging needs a confirmation first.
+    pub fn set_entry_reprompt(&mut self, entry_id: &str, reprompt: bool, now: f64) -> Result<(), JsValue> {
+        self.engine.set_entry_reprompt(entry_id, reprompt, now as u64).map_err(js_err)
+    }
+
+    /// REPROMPT: One loaded entry as JSON with its secrets, for the detail view.
+    pub fn reveal_entry(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.reveal_entry(entry_id).map_err(js_err)
+    }
+
+    /// LIST: One page of the loaded vault for a virtualized list. 'sort' is "title",
+    /// "lastUsed" or "modified". Returns JSON {total, offset, entries}.
+    pub fn list_entries_page(&self, offset: usize, limit: usize, sort: &str) -> Result<String, JsValue> {
+        self.engine.list_entries_page(offset, limit, sort).map_err(js_err)
+    }
+
+    /// SEARCH: Like 'list_entries_page', over entries matching 'query'. Case, accents
+    /// and full-width characters are ignored, so "cafe" finds "Café".
+    pub fn search_entries(&self, query: &str, offset: usize, limit: usize, sort: &str) -> Result<String, JsValue> {
+        self.engine.search_entries(query, offset, limit, sort).map_err(js_err)
+    }
+
+    /// USAGE: Records a fill or copy (unix seconds): bumps the entry's use count and last-used time.
+    pub fn record_use(&mut self, entry_id: &str, now: f64) -> Result<(), JsValue> {
+        self.engine.record_use(entry_id, now as u64).map_err(js_err)
+    }
+
+    /// USAGE: The entries to offer first in the quick-fill dropdown. Returns a JSON array of summaries.
+    pub fn frequently_used(&self, limit: usize, now: f64) -> Result<String, JsValue> {
+        self.engine.frequently_used(limit, now as u64).map_err(js_err)
+    }
+
+    /// FAVORITES: The favorites in the order the user arranged them, as a JSON array
+    /// of summaries.
+    pub fn favorite_entries(&self) -> Result<String, JsValue> {
+        self.engine.favorite_entries().map_err(js_err)
+    }
+
+    /// FAVORITES: Moves a favorite to 'position' (0 is first) after a drag and drop.
+    /// The order is kept in the vault settings and syncs with them.
+    pub fn move_favorite(&mut self, entry_id: &str, position: usize) -> Result<(), JsValue> {
+        self.engine.move_favorite(entry_id, position).map_err(js_err)
+    }
+
+    /// STATS: Dashboard numbers for the loaded vault: counts by type, attachment
+    /// total, password strength buckets and 2FA coverage. Returns JSON.
+    pub fn vault_stats(&self) -> Result<String, JsValue> {
+        self.engine.vault_stats().map_err(js_err)
+    }
+
+    /// AUDIT: Password reuse across sites, with entries of one site (subdomains,
+    /// equivalent domains) told apart from real reuse. Returns JSON; no secrets.
+    #[cfg(feature = "audit")]
+    pub fn audit_report(&self) -> Result<String, JsValue> {
+        self.engine.audit_report().map_err(js_err)
+    }
+
+    /// AUDIT: The audit findings as CSV, one row per entry and finding.
+    #[cfg(feature = "audit")]
+    pub fn export_audit_csv(&self) -> Result<String, JsValue> {
+        self.engine.export_audit_csv().map_err(js_err)
+    }
+
+    /// AUDIT: The audit findings as a JSON array of rows.
+    #[cfg(feature = "audit")]
+    pub fn export_audit_json(&self) -> Result<String, JsValue> {
+        self.engine.export_audit_json().map_err(js_err)
+    }
+
+    /// AUDIT: The 0-100 vault health score, its categories and the trend against
+    /// the history the last call returned ("" the first time). Returns JSON
+    /// {score, categories, trend, history}; store 'history' for the next call.
+    #[cfg(feature = "audit")]
+    pub fn vault_health(&self, history_json: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.vault_health(history_json, now as u64).map_err(js_err)
+    }
+
+    /// SETTINGS: The loaded vault's settings (auto-lock, clipboard clearing,
+    /// generator defaults, KDF settings), migrated and defaulted. Returns JSON.
+    pub fn vault_settings(&self) -> Result<String, JsValue> {
+        self.engine.vault_settings().map_err(js_err)
+    }
+
+    /// SETTINGS: Replaces the vault's settings. Accepts the old localStorage values
+    /// too ({"autolock_duration": "300000"}), so they can move into the vault once.
+    pub fn set_vault_settings(&mut self, settings_json: &str) -> Result<(), JsValue> {
+        self.engine.set_vault_settings(settings_json).map_err(js_err)
+    }
+
+    /// PRESETS: The vault's saved generator presets as a JSON array of {name, style, ...options}.
+    pub fn generator_presets(&self) -> Result<String, JsValue> {
+        self.engine.generator_presets().map_err(js_err)
+    }
+
+    /// PRESETS: Saves {name, style: "password" | "mac" | "passphrase", ...options},
+    /// replacing a preset with the same name.
+    pub fn save_generator_preset(&mut self, preset_json: &str) -> Result<(), JsValue> {
+        self.engine.save_generator_preset(preset_json).map_err(js_err)
+    }
+
+    /// PRESETS: Deletes a preset by name; false if there was none.
+    pub fn delete_generator_preset(&mut self, name: &str) -> Result<bool, JsValue> {
+        self.engine.delete_generator_preset(name).map_err(js_err)
+    }
+
+    /// PRESETS: Generates with the named preset's generator and options.
+    pub fn generate_with_preset(&self, name: &str) -> Result<String, JsValue> {
+        self.engine.generate_with_preset(name).map_err(js_err)
+    }
+}
+
+/// Segmented storage: each entry's title/URL summary and its secrets are sealed
+/// separately, so a list view never decrypts a password.
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// SEAL (segmented): The loaded vault as JSON {version, root, entries}.
+    pub fn seal_vault_segments(&self) -> Result<String, JsValue> {
+        self.engine.seal_vault_segments().map_err(js_err)
+    }
+
+    /// LOAD (segmented): Decrypts a segmented vault into the bridge.
+    pub fn load_vault_segments(&mut self, sealed_json: &str) -> Result<(), JsValue> {
+        self.engine.load_vault_segments(sealed_json).map_err(js_err)
+    }
+
+    /// SEAL (one entry): Re-seals one loaded entry after an edit. Returns JSON {id, summary, secrets}.
+    pub fn seal_entry_segments(&self, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.seal_entry_segments(entry_id).map_err(js_err)
+    }
+
+    /// LIST: Summaries of every entry in a segmented vault, decrypting no secrets.
+    pub fn list_entry_summaries(&self, sealed_json: &str) -> Result<String, JsValue> {
+        self.engine.list_entry_summaries(sealed_json).map_err(js_err)
+    }
+
+    /// OPEN: Decrypts one sealed entry in full. Returns the entry JSON.
+    pub fn open_entry_segments(&self, sealed_entry_json: &str) -> Result<String, JsValue> {
+        self.engine.open_entry_segments(sealed_entry_json).map_err(js_err)
+    }
+
+    /// INDEX: A blind search index of the loaded vault (HMAC'd n-grams of titles,
+    /// usernames, URLs and folders), to store next to the sealed entries. Returns JSON.
+    pub fn build_search_index(&self) -> Result<String, JsValue> {
+        self.engine.build_search_index().map_err(js_err)
+    }
+
+    /// INDEX: The index with one loaded entry added or re-indexed, as a pending
+    /// change; nothing else is redone. Returns JSON.
+    pub fn index_add(&self, index_json: &str, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.index_add(index_json, entry_id).map_err(js_err)
+    }
+
+    /// INDEX: The index without a deleted entry. Returns JSON.
+    pub fn index_remove(&self, index_json: &str, entry_id: &str) -> Result<String, JsValue> {
+        self.engine.index_remove(index_json, entry_id).map_err(js_err)
+    }
+
+    /// INDEX: Re-indexes the next 'limit' entries after the ID 'after' ("" to start).
+    /// Returns JSON {index, next}; repeat with 'next' until it is null.
+    pub fn index_rebuild_partial(&self, index_json: &str, after: &str, limit: usize) -> Result<String, JsValue> {
+        self.engine.index_rebuild_partial(index_json, after, limit).map_err(js_err)
+    }
+
+    /// INDEX: Folds pending changes in when there are enough of them (always if
+    /// 'force'). Call when idle. Returns JSON {index, compacted}.
+    pub fn compact_search_index(&self, index_json: &str, force: bool) -> Result<String, JsValue> {
+        self.engine.compact_search_index(index_json, force).map_err(js_err)
+    }
+
+    /// INDEX: IDs of entries that may match 'query', found without decrypting any.
+    /// Returns a JSON array; open their summaries to confirm.
+    pub fn search_index(&self, index_json: &str, query: &str) -> Result<String, JsValue> {
+        self.engine.search_index(index_json, query).map_err(js_err)
+    }
+}
+
+/// Progress of moving a segmented vault to a new key (after a KDF upgrade or a
+/// password change), a batch at a time. Persist 'to_json()' with the vault after
+/// every batch and restore it with 'Reencryption.from_json' to resume.
+#[wasm_bindgen]
+pub struct Reencryption {
+    inner: reencrypt::Reencryption,
+}
+
+#[wasm_bindgen]
+impl Reencryption {
+    pub fn from_json(json: &str) -> Result<Reencryption, JsValue> {
+        serde_json::from_str(json)
+            .map(|inner| Reencryption { inner })
+            .map_err(|e| JsValue::from_str(&format!("Re-encryption parse error: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.inner).map_err(|e| JsValue::from_str(&format!("Re-encryption serialize error: {}", e)))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.inner.is_complete()
+    }
+
+    /// Segments moved so far and the total, for a progress bar.
+    pub fn done(&self) -> usize {
+        self.inner.done
+    }
+
+    pub fn total(&self) -> usize {
+        self.inner.total
+    }
+}
+
+/// Called on the bridge holding the NEW key; 'previous' holds the key the vault is sealed under.
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// REKEY: Starts moving a segmented vault from 'previous''s key to this bridge's.
+    pub fn start_reencryption(&self, previous: &CryptoBridge, sealed_json: &str) -> Result<Reencryption, JsValue> {
+        self.engine.start_reencryption(&previous.engine, sealed_json).map(|inner| Reencryption { inner }).map_err(js_err)
     }
+
+    /// REKEY: Re-seals up to 'batch_size' outdated entries and returns the updated vault
+    /// JSON. Call it from an idle callback until 'queue.is_complete()'.
+    pub fn reencrypt_batch(
+        &self,
+        previous: &CryptoBridge,
+        queue: &mut Reencryption,
+        sealed_json: &str,
+        batch_size: usize,
+    ) -> Result<String, JsValue> {
+        self.engine
+            .reencrypt_batch(&previous.engine, &mut queue.inner, sealed_json, batch_size)
+            .map_err(js_err)
+    }
+
+    /// LOAD: Opens a vault that is only partly moved to the new key.
+    pub fn load_vault_segments_reencrypting(
+        &mut self,
+        previous: &CryptoBridge,
+        queue: &Reencryption,
+        sealed_json: &str,
+    ) -> Result<(), JsValue> {
+        self.engine
+            .load_vault_segments_reencrypting(&previous.engine, &queue.inner, sealed_json)
+            .map_err(js_err)
+    }
+}
+
+/// Entries a re-encryption batch handles unless told otherwise.
+#[wasm_bindgen]
+pub fn reencryption_batch_size() -> usize {
+    reencrypt::DEFAULT_BATCH_SIZE
+}
+
+/// TEMPLATES: The entry templates (bank account, Wi-Fi router, ...) as a JSON array of
+/// {id, name, entry_type, fields: [{name, kind, default}]}.
+#[wasm_bindgen]
+pub fn list_entry_templates() -> Result<String, JsValue> {
+    securepass_core::list_entry_templates().map_err(js_err)
+}
+
+/// WIFI: The WIFI:T:..;S:..;P:..;; text guests scan to join a network; render it as a QR
+/// code. 'security' is "WPA2", "WPA3", "WEP", "open", ...
+#[wasm_bindgen]
+pub fn wifi_qr_payload(ssid: &str, password: &str, security: &str) -> Result<String, JsValue> {
+    securepass_core::wifi_qr_payload(ssid, password, security).map_err(js_err)
+}
+
+/// CLIPBOARD: What to put on the clipboard for a password, username or note: plain
+/// text without invisible characters or trailing whitespace. With 'autotype_safe',
+/// escaped to be typed verbatim inside an autotype sequence.
+#[wasm_bindgen]
+pub fn clipboard_text(value: &str, autotype_safe: bool) -> String {
+    securepass_core::clipboard_text(value, autotype_safe)
+}
+
+/// CLIPBOARD: What to put on the clipboard for a TOTP code: digits only.
+#[wasm_bindgen]
+pub fn clipboard_code(code: &str) -> String {
+    securepass_core::clipboard_code(code)
+}
+
+/// CARD: "•••• 4242" for a card number as it is typed.
+#[wasm_bindgen]
+pub fn mask_card_number(number: &str) -> Result<String, JsValue> {
+    securepass_core::mask_card_number(number).map_err(js_err)
+}
+
+/// CARD: "visa", "mastercard", "amex", ... or "unknown", from the number's first digits.
+#[wasm_bindgen]
+pub fn detect_card_brand(number: &str) -> Result<String, JsValue> {
+    securepass_core::detect_card_brand(number).map_err(js_err)
+}
+
+/// CARD: "valid", "expiring_soon" or "expired" for an "MM/YY" expiry date at 'now'.
+#[wasm_bindgen]
+pub fn card_expiry_status(expiry: &str, now: f64) -> Result<String, JsValue> {
+    securepass_core::card_expiry_status(expiry, now as u64).map_err(js_err)
+}
+
+/// LICENSE: Normalizes a product key as it is typed. Returns JSON {key, format, groups,
+/// groupLength}; fails naming a group of the wrong length.
+#[wasm_bindgen]
+pub fn normalize_license_key(raw: &str) -> Result<String, JsValue> {
+    securepass_core::normalize_license_key(raw).map_err(js_err)
+}
+
+/// ENTITLEMENT: Checks a vendor-signed entitlement token ("<payload>.<signature>",
+/// Base64url) against the vendor's Ed25519 public key (Base64), with no server.
+/// Returns JSON {version, tier, seats, issuedAt, expiresAt, subject, active};
+/// 'active' is false once it has expired. A forged or altered token is an error.
+#[wasm_bindgen]
+pub fn verify_entitlement(token: &str, vendor_public_key: &str, now: f64) -> Result<String, JsValue> {
+    securepass_core::verify_entitlement(token, vendor_public_key, now as u64).map_err(js_err)
+}
+
+/// IDENTITY: A postal code in the country's usual spelling; 'country' is ISO 3166 alpha-2.
+#[wasm_bindgen]
+pub fn format_postal_code(country: &str, raw: &str) -> Result<String, JsValue> {
+    securepass_core::format_postal_code(country, raw).map_err(js_err)
+}
+
+/// IDENTITY: A phone number as JSON {countryCode, national, e164}. Numbers without "+"
+/// are read as dialled in 'default_country'.
+#[wasm_bindgen]
+pub fn format_phone_number(raw: &str, default_country: &str) -> Result<String, JsValue> {
+    securepass_core::format_phone_number(raw, default_country).map_err(js_err)
+}
+
+// --- 7. Autofill, Phishing & Breach Detection ---
+// Breach checks run against a downloaded Bloom filter, so no password hash
+// (not even a prefix) leaves the device, and against a signed list of breached
+// sites, so no URL does either.
+
+/// An offline filter of breached password hashes, loaded from its gzip file.
+#[wasm_bindgen]
+pub struct BreachFilter {
+    inner: breach::BreachFilter,
+}
+
+#[wasm_bindgen]
+impl BreachFilter {
+    pub fn from_compressed(data: &[u8]) -> Result<BreachFilter, JsValue> {
+        breach::BreachFilter::from_compressed(data).map(|inner| BreachFilter { inner }).map_err(js_err)
+    }
+
+    /// True if the password (e.g. a freshly generated one) is probably breached.
+    pub fn contains_password(&self, password: &str) -> bool {
+        self.inner.contains_password(password)
+    }
+
+    /// JSON {bitCount, hashCount, fillRatio, falsePositiveRate}.
+    pub fn info(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.inner.info()).map_err(|e| JsValue::from_str(&format!("Filter serialize error: {}", e)))
+    }
+}
+
+/// The vendor's signed list of breached sites and their breach dates.
+#[wasm_bindgen]
+pub struct BreachedSites {
+    inner: breached_sites::BreachedSites,
+}
+
+#[wasm_bindgen]
+impl BreachedSites {
+    /// Reads a downloaded bundle, checking its signature against the vendor's
+    /// Ed25519 public key (Base64) built into the app.
+    pub fn from_signed(bundle: &[u8], vendor_public_key: &str) -> Result<BreachedSites, JsValue> {
+        breached_sites::BreachedSites::from_signed(bundle, vendor_public_key).map(|inner| BreachedSites { inner }).map_err(js_err)
+    }
+
+    /// JSON {publishedAt, siteCount}.
+    pub fn info(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.inner.info()).map_err(|e| JsValue::from_str(&format!("List serialize error: {}", e)))
+    }
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// AUTOFILL: Maps a loaded entry's credentials onto the fields the content script found.
+    /// 'form_descriptor_json' is {"fields": [{"field_id", "name", "type", "autocomplete", ...}]}.
+    pub fn build_fill_payload(&self, entry_id: &str, page_url: &str, form_descriptor_json: &str) -> Result<String, JsValue> {
+        self.engine.build_fill_payload(entry_id, page_url, form_descriptor_json).map_err(js_err)
+    }
+
+    /// IDENTITY: Fills a form's name, phone and address fields from an identity entry,
+    /// split per field. Returns a JSON array of {field_id, part, value}.
+    pub fn build_identity_fill(&self, entry_id: &str, form_descriptor_json: &str) -> Result<String, JsValue> {
+        self.engine.build_identity_fill(entry_id, form_descriptor_json).map_err(js_err)
+    }
+
+    /// PHISHING: Compares a page against every URL in the loaded vault.
+    /// Returns JSON {level, lookalike_of, display_host, reason}; level is
+    /// "none", "trusted", "suspicious" or "danger".
+    pub fn check_phishing(&self, page_url: &str) -> Result<String, JsValue> {
+        self.engine.check_phishing(page_url).map_err(js_err)
+    }
+
+    /// BREACH: The loaded entries whose password is probably in 'filter'.
+    /// Returns a JSON array of summaries.
+    pub fn breached_entries(&self, filter: &BreachFilter) -> Result<String, JsValue> {
+        self.engine.breached_entries(&filter.inner).map_err(js_err)
+    }
+
+    /// BREACH: Logins whose password predates a breach of their site. Returns a JSON
+    /// array of {entryId, title, domain, siteName, breachedAt, passwordSetAt, dataClasses}.
+    pub fn breached_site_alerts(&self, sites: &BreachedSites) -> Result<String, JsValue> {
+        self.engine.breached_site_alerts(&sites.inner).map_err(js_err)
+    }
+}
+
+// --- 8. Domains ---
+// Public-suffix-aware domain helpers and the user's equivalent-domain groups.
+
+/// DOMAIN: The registrable domain of a URL ("https://mail.google.co.uk" -> "google.co.uk").
+/// Returns undefined when the input has no usable host.
+#[wasm_bindgen]
+pub fn registrable_domain(url: &str) -> Option<String> {
+    domains::registrable_domain(url)
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// Returns the loaded vault's equivalent-domain groups as JSON, e.g. [["amazon.com","amazon.de"]].
+    pub fn equivalent_domains(&self) -> Result<String, JsValue> {
+        self.engine.equivalent_domains().map_err(js_err)
+    }
+
+    /// Replaces the equivalent-domain groups. Input is normalized and overlapping groups merged.
+    /// Call 'seal_vault' afterwards to persist the change.
+    pub fn set_equivalent_domains(&mut self, groups_json: &str) -> Result<(), JsValue> {
+        self.engine.set_equivalent_domains(groups_json).map_err(js_err)
+    }
+
+    /// Adds one group (JSON array of domains), merging it with any group it overlaps.
+    pub fn add_equivalent_domains(&mut self, group_json: &str) -> Result<(), JsValue> {
+        self.engine.add_equivalent_domains(group_json).map_err(js_err)
+    }
+
+    /// Takes a domain out of its group; groups left with a single domain are dropped.
+    pub fn remove_equivalent_domain(&mut self, domain: &str) {
+        self.engine.remove_equivalent_domain(domain);
+    }
+}
+
+// --- 9. Authenticator Export & Import ---
+// Moves TOTP seeds into Aegis or andOTP, and in from Google Authenticator's
+// "Transfer accounts" QR codes. The exports are confirmed like the backup
+// exports below: a token from 'request_export' and the master password.
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// AEGIS: Returns an encrypted Aegis vault (JSON) holding every TOTP seed in the loaded vault.
+    #[cfg(feature = "importers")]
+    pub fn export_totp_aegis(&mut self, export_password: &str, token: &str, password: &str) -> Result<String, JsValue> {
+        let format = ExportFormat::Aegis { password: export_password.to_string() };
+        self.export_text(format, token, password)
+    }
+
+    /// ANDOTP: Returns an encrypted andOTP backup (binary, saved as .json.aes).
+    #[cfg(feature = "importers")]
+    pub fn export_totp_andotp(&mut self, export_password: &str, token: &str, password: &str) -> Result<Vec<u8>, JsValue> {
+        let format = ExportFormat::Andotp { password: export_password.to_string() };
+        self.engine.export_confirmed(format, token, password).map_err(js_err)
+    }
+
+    /// MIGRATION: Adds every account in the QR payload to the loaded vault as a new entry.
+    /// Returns the number of entries added; call 'seal_vault' to persist them.
+    #[cfg(feature = "importers")]
+    pub fn import_google_migration(&mut self, uri: &str, now: f64) -> Result<usize, JsValue> {
+        self.engine.import_google_migration(uri, now as u64).map_err(js_err)
+    }
+
+    /// LASTPASS: Adds the logins and secure notes of a LastPass CSV export to the
+    /// loaded vault. 'folder_map_json' is a JSON object renaming LastPass folder
+    /// paths (and the folders below them), or "". Returns the number added.
+    #[cfg(feature = "importers")]
+    pub fn import_lastpass_csv(&mut self, csv: &str, folder_map_json: &str, now: f64) -> Result<usize, JsValue> {
+        self.engine.import_lastpass_csv(csv, folder_map_json, now as u64).map_err(js_err)
+    }
+
+    /// BROWSER: Adds the logins of a Chrome/Edge or Firefox password CSV to the loaded
+    /// vault, merging the http and https copies of a login. Returns JSON
+    /// {source: "chrome" | "firefox", added, duplicates}.
+    #[cfg(feature = "importers")]
+    pub fn import_browser_csv(&mut self, csv: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.import_browser_csv(csv, now as u64).map_err(js_err)
+    }
+
+    /// IMPORT: Adds another manager's export to the loaded vault. 'format' is
+    /// "lastpass", "browser", "apple" (Safari or Passwords app CSV), "dashlane" (CSV
+    /// or JSON) or "proton-pass" (unencrypted JSON). Returns JSON {source, added,
+    /// duplicates}.
+    #[cfg(feature = "importers")]
+    pub fn import_file(&mut self, format: &str, data: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.import_file(format, data, now as u64).map_err(js_err)
+    }
+
+    /// IMPORT: Compares an export with the loaded vault without changing it. Returns
+    /// JSON {source, items: [{index, title, username, url, status, existingId, changed}],
+    /// merged}; 'status' is "new", "duplicate", "update" or "conflict". The import is
+    /// held until 'apply_import' or 'cancel_import'.
+    #[cfg(feature = "importers")]
+    pub fn preview_import(&mut self, format: &str, data: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.preview_import(format, data, now as u64).map_err(js_err)
+    }
+
+    /// IMPORT: Carries out the previewed import with the user's choices, JSON
+    /// {"<index>": "add" | "replace" | "skip"}; unlisted items get their status's
+    /// default. Returns JSON {source, added, replaced, skipped}.
+    #[cfg(feature = "importers")]
+    pub fn apply_import(&mut self, choices_json: &str) -> Result<String, JsValue> {
+        self.engine.apply_import(choices_json).map_err(js_err)
+    }
+
+    /// IMPORT: Drops a previewed import.
+    #[cfg(feature = "importers")]
+    pub fn cancel_import(&mut self) {
+        self.engine.cancel_import();
+    }
+}
+
+/// 2FA: Cleans up a pasted TOTP secret or otpauth://totp/ URI before it is saved.
+/// Returns JSON {secret, issuer, account, algorithm, digits, period}; throws with
+/// a readable reason (e.g. which characters aren't Base32) when it can't be used.
+#[wasm_bindgen]
+pub fn normalize_totp_secret(raw: &str) -> Result<String, JsValue> {
+    securepass_core::normalize_totp_secret(raw).map_err(js_err)
+}
+
+/// 2FA: The clock skew, in seconds, at which 'known_good_code' is the secret's code
+/// given the device's Unix time 'now'. Store it on this device for 'get_totp_code_at'.
+#[wasm_bindgen]
+pub fn estimate_totp_skew(secret: &str, known_good_code: &str, now: f64) -> Result<f64, JsValue> {
+    securepass_core::estimate_totp_skew(secret, known_good_code, now as u64).map(|skew| skew as f64).map_err(js_err)
+}
+
+/// MIGRATION: Decodes one otpauth-migration:// QR payload without touching a vault.
+/// Returns JSON {batch_index, batch_size, batch_id, accounts: [...], skipped: [...]}.
+#[cfg(feature = "importers")]
+#[wasm_bindgen]
+pub fn parse_google_migration(uri: &str) -> Result<String, JsValue> {
+    securepass_core::parse_google_migration(uri).map_err(js_err)
+}
+
+// --- 10. Attachments ---
+// File metadata and file content are encrypted separately, so lists never pull file bytes.
+
+/// The two encrypted parts of a newly added attachment. Store them separately:
+/// 'meta' is small JSON for lists, 'content' is the (large) encrypted file.
+#[wasm_bindgen]
+pub struct EncryptedAttachment {
+    inner: securepass_core::EncryptedAttachment,
+}
+
+#[wasm_bindgen]
+impl EncryptedAttachment {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.inner.id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn meta(&self) -> String {
+        self.inner.meta.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn content(&self) -> Vec<u8> {
+        self.inner.content.clone()
+    }
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// ATTACH: Encrypts a file under a fresh attachment ID, or, when the vault's
+    /// 'dedupAttachments' setting is on, under an ID derived from the file: the
+    /// same file gets the same ID and content blob again, so store it once.
+    pub fn encrypt_attachment(&self, content: &[u8], filename: &str, mime_type: &str) -> Result<EncryptedAttachment, JsValue> {
+        self.engine
+            .encrypt_attachment(content, filename, mime_type)
+            .map(|inner| EncryptedAttachment { inner })
+            .map_err(js_err)
+    }
+
+    /// META: Decrypts only the filename, MIME type and size. Returns JSON.
+    pub fn decrypt_attachment_meta(&self, attachment_id: &str, meta_json: &str) -> Result<String, JsValue> {
+        self.engine.decrypt_attachment_meta(attachment_id, meta_json).map_err(js_err)
+    }
+
+    /// CONTENT: Decrypts the file bytes when the user actually opens the attachment.
+    pub fn decrypt_attachment(&self, attachment_id: &str, content: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.engine.decrypt_attachment(attachment_id, content).map_err(js_err)
+    }
+}
+
+// --- 11. Unlock Throttling, PIN Unlock & KDF Upgrades ---
+// Each run of wrong passwords makes the next unlock attempt cost more Argon2 work.
+// A device can also be unlocked with a short PIN, for a limited number of tries.
+// Vaults unlock through a key envelope, which is upgraded to stronger Argon2
+// settings on the first successful unlock after they change.
+
+/// Consecutive-failure counter for unlock attempts. Persist 'to_json()' after every
+/// call that changes it, and restore it with 'UnlockThrottle.from_json' on startup.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct UnlockThrottle {
+    state: throttle::ThrottleState,
+}
+
+#[wasm_bindgen]
+impl UnlockThrottle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> UnlockThrottle {
+        UnlockThrottle::default()
+    }
+
+    pub fn from_json(json: &str) -> Result<UnlockThrottle, JsValue> {
+        serde_json::from_str(json)
+            .map(|state| UnlockThrottle { state })
+            .map_err(|e| JsValue::from_str(&format!("Throttle parse error: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.state)
+            .map_err(|e| JsValue::from_str(&format!("Throttle serialize error: {}", e)))
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.state.consecutive_failures
+    }
+
+    /// Extra Argon2 runs the next attempt will cost (useful for a "please wait" hint).
+    pub fn penalty_rounds(&self) -> u32 {
+        self.state.penalty_rounds()
+    }
+
+    /// Call once the derived key has been proven correct (e.g. the vault decrypted).
+    pub fn record_success(&mut self) {
+        self.state.record_success();
+    }
+
+    /// Call when the derived key turned out to be wrong.
+    pub fn record_failure(&mut self) {
+        self.state.record_failure();
+    }
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// THROTTLED UNLOCK: Like the constructor, but pays the current penalty first.
+    /// The attempt is marked pending inside 'throttle'; report the outcome with
+    /// 'record_success' / 'record_failure' once you know whether the key was right.
+    pub fn new_throttled(
+        password: &str,
+        salt: &[u8],
+        password_policy: u8,
+        throttle: &mut UnlockThrottle,
+    ) -> Result<CryptoBridge, JsValue> {
+        text::PasswordPolicy::from_version(password_policy)
+            .and_then(|policy| Engine::new_throttled(password, salt, policy, &mut throttle.state))
+            .map(Self::from)
+            .map_err(js_err)
+    }
+}
+
+/// A PIN-wrapped copy of the master key for quick unlock on this device. Persist
+/// 'to_json()' after every unlock attempt; once too many wrong PINs have erased it,
+/// 'is_destroyed()' is true and the user must unlock with the master password.
+#[wasm_bindgen]
+pub struct PinLock {
+    inner: pin::PinLock,
+}
+
+#[wasm_bindgen]
+impl PinLock {
+    pub fn from_json(json: &str) -> Result<PinLock, JsValue> {
+        serde_json::from_str(json)
+            .map(|inner| PinLock { inner })
+            .map_err(|e| JsValue::from_str(&format!("PIN lock parse error: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.inner).map_err(|e| JsValue::from_str(&format!("PIN lock serialize error: {}", e)))
+    }
+
+    pub fn attempts_left(&self) -> u32 {
+        self.inner.attempts_left()
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.inner.is_destroyed()
+    }
+}
+
+/// A fresh 32-byte device pepper for PIN unlock. Keep it on this device only,
+/// apart from anything that is synced or exported.
+#[wasm_bindgen]
+pub fn generate_pin_pepper() -> Vec<u8> {
+    pin::generate_pepper().to_vec()
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// PIN SETUP: Wraps the master key under 'pin' and the device pepper, once 'password'
+    /// checks out as the master password. 'max_attempts' wrong PINs in a row erase the lock.
+    pub fn create_pin_lock(&self, password: &str, pin: &str, pepper: &[u8], max_attempts: u32) -> Result<PinLock, JsValue> {
+        self.engine.create_pin_lock(password, pin, pepper, max_attempts).map(|inner| PinLock { inner }).map_err(js_err)
+    }
+
+    /// PIN UNLOCK: Opens the vault with a PIN. 'lock' counts the attempt, so save it whatever the result.
+    pub fn from_pin(pin: &str, pepper: &[u8], lock: &mut PinLock) -> Result<CryptoBridge, JsValue> {
+        Engine::from_pin(pin, pepper, &mut lock.inner).map(Self::from).map_err(js_err)
+    }
+}
+
+/// Security events (KDF upgrades) for the settings screen. Holds no secrets;
+/// persist 'to_json()' after every call that takes it.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct AuditLog {
+    inner: audit_log::AuditLog,
+}
+
+#[wasm_bindgen]
+impl AuditLog {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> AuditLog {
+        AuditLog::default()
+    }
+
+    pub fn from_json(json: &str) -> Result<AuditLog, JsValue> {
+        serde_json::from_str(json)
+            .map(|inner| AuditLog { inner })
+            .map_err(|e| JsValue::from_str(&format!("Audit log parse error: {}", e)))
+    }
+
+    /// JSON {events: [{at, kind, detail}]}, oldest first.
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.inner).map_err(|e| JsValue::from_str(&format!("Audit log serialize error: {}", e)))
+    }
+}
+
+/// The key envelope of a vault saved before envelopes, from its salt and password
+/// policy. Pass it to 'CryptoBridge.from_envelope' when no envelope is stored.
+#[wasm_bindgen]
+pub fn legacy_key_envelope(salt: &[u8], password_policy: u8) -> Result<String, JsValue> {
+    let policy = text::PasswordPolicy::from_version(password_policy).map_err(js_err)?;
+    serde_json::to_string(&kdf::KeyEnvelope::legacy(salt, policy))
+        .map_err(|e| JsValue::from_str(&format!("Key envelope serialize error: {}", e)))
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// ENVELOPE UNLOCK: Derives the key through the vault's key envelope (JSON). If the
+    /// envelope uses outdated settings, its replacement is prepared too, which costs
+    /// a second Argon2 run; fetch it with 'commit_kdf_upgrade' once the vault opens.
+    pub fn from_envelope(password: &str, envelope_json: &str) -> Result<CryptoBridge, JsValue> {
+        serde_json::from_str(envelope_json)
+            .map_err(|e| format!("Key envelope parse error: {}", e))
+            .and_then(|envelope| Engine::from_envelope(password, &envelope))
+            .map(Self::from)
+            .map_err(js_err)
+    }
+
+    /// KDF UPGRADE: Call after the vault has decrypted. Returns the replacement envelope
+    /// JSON to store in place of the old one ("" if none) and records it in 'log'.
+    /// The vault itself stays as it is.
+    pub fn commit_kdf_upgrade(&mut self, log: &mut AuditLog, now: f64) -> Result<String, JsValue> {
+        match self.engine.commit_kdf_upgrade(&mut log.inner, now as u64) {
+            Some(envelope) => serde_json::to_string(&envelope)
+                .map_err(|e| JsValue::from_str(&format!("Key envelope serialize error: {}", e))),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// PASSWORD: Changes the master password without re-encrypting the vault. Needs
+    /// the current password. 'kdf_json' may be "" for the vault's settings; 'credential_ids_json' is a JSON
+    /// array of Base64 passkey IDs with biometric unlock. Returns JSON {envelope,
+    /// biometric}, to save in one write. On a device-bound vault, call it before 'bind_to_device'.
+    pub fn change_master_password(
+        &mut self,
+        current_password: &str,
+        new_password: &str,
+        new_salt: &[u8],
+        kdf_json: &str,
+        credential_ids_json: &str,
+    ) -> Result<String, JsValue> {
+        self.engine
+            .change_master_password(current_password, new_password, new_salt, kdf_json, credential_ids_json)
+            .map_err(js_err)
+    }
+}
+
+// --- 12. Duress Vaults & Key Hierarchy ---
+// The real and a decoy vault are stored as an unlabeled, shuffled list of slots
+// (JSON array of {iv, data}) under the same salt. Whichever password is typed,
+// the app runs the same code: derive, 'open_slots', later 'seal_into_slots'.
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// CREATE DECOY: Returns a new slot list holding the loaded vault and a decoy
+    /// vault that opens with 'decoy_password' over the same 'salt'.
+    pub fn create_decoy_slots(&self, decoy_password: &str, salt: &[u8], decoy_vault_json: &str) -> Result<String, JsValue> {
+        self.engine.create_decoy_slots(decoy_password, salt, decoy_vault_json).map_err(js_err)
+    }
+
+    /// Slot list for a user without a decoy: the loaded vault plus random filler,
+    /// so storage looks the same whether or not a decoy exists.
+    pub fn create_vault_slots(&self) -> Result<String, JsValue> {
+        self.engine.create_vault_slots().map_err(js_err)
+    }
+
+    /// OPEN: Derives the key once and loads whichever slot it opens.
+    /// A wrong password and a missing decoy fail with the same error.
+    pub fn open_slots(password: &str, salt: &[u8], slots_json: &str) -> Result<CryptoBridge, JsValue> {
+        Engine::open_slots(password, salt, slots_json).map(Self::from).map_err(js_err)
+    }
+
+    /// SAVE: Writes the loaded vault back into its own slot and returns the updated list.
+    pub fn seal_into_slots(&self, slots_json: &str) -> Result<String, JsValue> {
+        self.engine.seal_into_slots(slots_json).map_err(js_err)
+    }
+
+    /// Returns JSON describing the KDF parameters, derived and wrapped keys,
+    /// and ciphers in use. Contains a key fingerprint but never key material.
+    pub fn describe_key_hierarchy(&self) -> Result<String, JsValue> {
+        self.engine.describe_key_hierarchy().map_err(js_err)
+    }
+}
+
+// --- 13. Storage Codec ---
+// The IndexedDB layout is defined in securepass-core (storage.rs). JS writes each
+// StorageRecord's 'value' under its 'key' and reads chunks back in key order.
+
+/// One key/value pair for the IndexedDB object store.
+#[wasm_bindgen]
+pub struct StorageRecord {
+    inner: storage::StoredRecord,
+}
+
+#[wasm_bindgen]
+impl StorageRecord {
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> String {
+        self.inner.key.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Vec<u8> {
+        self.inner.value.clone()
+    }
+}
+
+fn to_js_records(records: Vec<storage::StoredRecord>) -> Vec<StorageRecord> {
+    records.into_iter().map(|inner| StorageRecord { inner }).collect()
+}
+
+/// Encodes a payload as the chunks of record 'key'. 'kind' is "vault", "attachment",
+/// "manifest" or "settings".
+#[wasm_bindgen]
+pub fn encode_storage_record(key: &str, kind: &str, encrypted: bool, payload: &[u8]) -> Result<Vec<StorageRecord>, JsValue> {
+    storage::RecordKind::parse(kind)
+        .and_then(|kind| storage::encode_record(key, kind, encrypted, payload))
+        .map(to_js_records)
+        .map_err(js_err)
+}
+
+/// Encodes a salt, the vault's password policy and an encrypted vault (JSON
+/// {version, iv, data}, as stored) as one self-contained vault record.
+#[wasm_bindgen]
+pub fn encode_vault_record(key: &str, salt: &[u8], password_policy: u8, package_json: &str) -> Result<Vec<StorageRecord>, JsValue> {
+    let package = parse_package(package_json)?;
+    text::PasswordPolicy::from_version(password_policy)
+        .and_then(|policy| storage::encode_vault_record(key, salt, policy, &package))
+        .map(to_js_records)
+        .map_err(js_err)
+}
+
+/// MIGRATION: Converts the schema 1 localStorage contents (JSON object of every
+/// key -> string value) into the schema 2 records, manifest included.
+#[wasm_bindgen]
+pub fn migrate_local_storage(items_json: &str, now: f64) -> Result<Vec<StorageRecord>, JsValue> {
+    serde_json::from_str(items_json)
+        .map_err(|e| format!("Migration parse error: {}", e))
+        .and_then(|items| storage::migrate_v1_to_v2(&items, now as u64))
+        .map(to_js_records)
+        .map_err(js_err)
+}
+
+/// Collects a record's chunks (push them in key order), then decodes them.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct StorageReader {
+    chunks: Vec<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl StorageReader {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> StorageReader {
+        StorageReader::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.chunks.push(chunk.to_vec());
+    }
+
+    /// The reassembled payload. Fails if the record is damaged or of another kind.
+    pub fn read_payload(&self, expected_kind: &str) -> Result<Vec<u8>, JsValue> {
+        let expected = storage::RecordKind::parse(expected_kind).map_err(js_err)?;
+        let record = storage::decode_record(&self.chunks).map_err(js_err)?;
+        if record.kind != expected {
+            return Err(JsValue::from_str(&format!("Storage error: record is not a {}", expected_kind)));
+        }
+        Ok(record.payload)
+    }
+
+    /// A vault record as JSON {salt, passwordPolicy, version, iv, data} (byte arrays, like the stored package).
+    pub fn read_vault(&self) -> Result<String, JsValue> {
+        let (salt, policy, package) = storage::decode_vault_record(&self.chunks).map_err(js_err)?;
+        Ok(serde_json::json!({"salt": salt, "passwordPolicy": policy, "version": package.version, "iv": package.iv, "data": package.data})
+            .to_string())
+    }
+
+    /// The manifest record as JSON {schema_version, updated_at, records: [{key, kind, chunks, size}]}.
+    pub fn read_manifest(&self) -> Result<String, JsValue> {
+        storage::decode_manifest(&self.chunks)
+            .and_then(|m| serde_json::to_string(&m).map_err(|e| format!("Manifest serialize error: {}", e)))
+            .map_err(js_err)
+    }
+}
+
+/// Gathers every record one operation writes (a password change, a save), then
+/// returns them with the updated manifest and a commit record, to write in one
+/// IndexedDB transaction. See securepass-core commit.rs.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CommitBuilder {
+    records: Vec<storage::StoredRecord>,
+}
+
+#[wasm_bindgen]
+impl CommitBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CommitBuilder {
+        CommitBuilder::default()
+    }
+
+    /// Adds a record, as 'encode_storage_record' would encode it.
+    pub fn add_record(&mut self, key: &str, kind: &str, encrypted: bool, payload: &[u8]) -> Result<(), JsValue> {
+        let kind = storage::RecordKind::parse(kind).map_err(js_err)?;
+        self.records.extend(storage::encode_record(key, kind, encrypted, payload).map_err(js_err)?);
+        Ok(())
+    }
+
+    /// Adds a vault record, as 'encode_vault_record' would encode it.
+    pub fn add_vault_record(&mut self, key: &str, salt: &[u8], password_policy: u8, package_json: &str) -> Result<(), JsValue> {
+        let package = parse_package(package_json)?;
+        let policy = text::PasswordPolicy::from_version(password_policy).map_err(js_err)?;
+        self.records.extend(storage::encode_vault_record(key, salt, policy, &package).map_err(js_err)?);
+        Ok(())
+    }
+
+    /// Everything to write, commit record last. 'serial' is one more than the stored
+    /// commit's; 'manifest_json' is the stored manifest ('read_manifest') or "" for none.
+    pub fn finish(&self, serial: f64, manifest_json: &str, now: f64) -> Result<Vec<StorageRecord>, JsValue> {
+        let manifest: Option<storage::Manifest> = if manifest_json.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(manifest_json).map_err(|e| js_err(format!("Manifest parse error: {}", e)))?)
+        };
+        commit::CommitBundle::new(serial as u64, self.records.clone(), manifest.as_ref(), now as u64)
+            .map(|bundle| to_js_records(bundle.records))
+            .map_err(js_err)
+    }
+}
+
+/// Collects stored chunks (any order) on load, then checks them against the commit record.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CommitVerifier {
+    stored: std::collections::BTreeMap<String, Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl CommitVerifier {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CommitVerifier {
+        CommitVerifier::default()
+    }
+
+    pub fn put(&mut self, key: &str, value: &[u8]) {
+        self.stored.insert(key.to_string(), value.to_vec());
+    }
+
+    /// The commit as JSON {serial, created_at, keys, checksum}. Fails, naming the
+    /// records involved, if a write was interrupted.
+    pub fn verify(&self) -> Result<String, JsValue> {
+        commit::verify(&self.stored)
+            .and_then(|c| serde_json::to_string(&c).map_err(|e| format!("Commit serialize error: {}", e)))
+            .map_err(js_err)
+    }
+}
+
+// The write-ahead journal (securepass-core journal.rs): after each edit, append
+// 'journal_append''s record; now and then save the vault, call 'journal_checkpoint'
+// and delete the records. On load, pass every record to 'load_vault_journaled'.
+
+/// JOURNAL: Where the journal stands since the last save.
+#[wasm_bindgen]
+pub struct VaultJournal {
+    inner: journal::Journal,
+    replay: journal::Replay,
+}
+
+#[wasm_bindgen]
+impl VaultJournal {
+    /// Records appended since the last save.
+    pub fn sequence(&self) -> f64 {
+        self.inner.sequence() as f64
+    }
+
+    /// Records the load replayed into the vault.
+    #[wasm_bindgen(getter)]
+    pub fn applied(&self) -> usize {
+        self.replay.applied
+    }
+
+    /// Records the load skipped (older saves, or torn by a crash). Delete them before appending.
+    #[wasm_bindgen(getter)]
+    pub fn skipped(&self) -> usize {
+        self.replay.skipped
+    }
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// JOURNAL: 'load_vault', then replays the journal records (JSON array) written since that save.
+    pub fn load_vault_journaled(&mut self, ciphertext: &[u8], iv: &[u8], records_json: &str) -> Result<VaultJournal, JsValue> {
+        self.engine
+            .load_vault_journaled(ciphertext, iv, records_json)
+            .map(|(inner, replay)| VaultJournal { inner, replay })
+            .map_err(js_err)
+    }
+
+    /// JOURNAL: The edits since the last record, sealed as JSON to append ("" if none).
+    pub fn journal_append(&self, journal: &mut VaultJournal) -> Result<String, JsValue> {
+        self.engine.journal_append(&mut journal.inner).map_err(js_err)
+    }
+
+    /// JOURNAL: Starts the journal over once 'seal_vault(iv)' has been written.
+    pub fn journal_checkpoint(&self, journal: &mut VaultJournal, iv: &[u8]) {
+        self.engine.journal_checkpoint(&mut journal.inner, iv);
+    }
+}
+
+// --- 14. Backup Exports ---
+// Full vault exports in the age v1 and OpenPGP formats, so a backup stays
+// readable with standard tools (age, gpg) even without SecurePass, an
+// unencrypted JSON export on explicit request, and paper backups of key material.
+// Every export takes two steps: 'request_export', then the export call with the
+// token, the master password and the key envelope to check it against (see
+// 'export_gate'). A token lives for two minutes on the engine's clock.
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// EXPORT: Starts an export. Returns JSON {token, expiresAt}; show the password
+    /// dialog, then pass the token to the export call. At most 3 requests per 10 minutes.
+    pub fn request_export(&mut self) -> Result<String, JsValue> {
+        self.engine.request_export().map_err(js_err)
+    }
+
+    /// AGE: The loaded vault as JSON in an age file (binary, saved as .age) sealed to a passphrase.
+    pub fn export_age_passphrase(&mut self, passphrase: &str, token: &str, password: &str) -> Result<Vec<u8>, JsValue> {
+        let format = ExportFormat::AgePassphrase { passphrase: passphrase.to_string() };
+        self.engine.export_confirmed(format, token, password).map_err(js_err)
+    }
+
+    /// AGE: Same, sealed to "age1..." public keys given one per line ('#' comments allowed).
+    pub fn export_age_recipients(&mut self, recipients: &str, token: &str, password: &str) -> Result<Vec<u8>, JsValue> {
+        let format = ExportFormat::AgeRecipients { recipients: recipients.to_string() };
+        self.engine.export_confirmed(format, token, password).map_err(js_err)
+    }
+
+    /// EXPORT: The loaded vault as unencrypted, versioned JSON. Only call this after
+    /// the user has confirmed they want a plaintext file.
+    pub fn export_json(&mut self, token: &str, password: &str) -> Result<String, JsValue> {
+        self.export_text(ExportFormat::Json, token, password)
+    }
+
+    /// OPENPGP: The loaded vault as JSON in an armored PGP message (save as .asc)
+    /// for an armored or binary public key.
+    #[cfg(feature = "openpgp")]
+    pub fn export_openpgp(&mut self, public_key: &[u8], token: &str, password: &str) -> Result<String, JsValue> {
+        self.export_text(ExportFormat::openpgp(public_key), token, password)
+    }
+
+    /// EXPORT: The file for 'format_json', once the token and the master password
+    /// (checked against the envelope the vault was unlocked with) match. Formats:
+    ///   {"format": "json"}                               unencrypted, versioned JSON
+    ///   {"format": "age-passphrase", "passphrase": ...}   age file (.age)
+    ///   {"format": "age-recipients", "recipients": ...}   "age1..." keys, one per line
+    ///   {"format": "openpgp", "publicKey": ...}           armored message (.asc); armored or Base64 key
+    ///   {"format": "aegis", "password": ...}              TOTP seeds as an Aegis vault
+    ///   {"format": "andotp", "password": ...}             TOTP seeds as an andOTP backup (.json.aes)
+    /// The token is spent whatever the outcome.
+    pub fn export_vault(&mut self, format_json: &str, token: &str, password: &str) -> Result<Vec<u8>, JsValue> {
+        self.engine.export_vault(format_json, token, password).map_err(js_err)
+    }
+
+    /// EXPORT: Adds the items of a JSON export (any schema version) to the loaded vault.
+    /// Returns how many were added.
+    pub fn import_json(&mut self, json: &str, now: f64) -> Result<usize, JsValue> {
+        self.engine.import_json(json, now as u64).map_err(js_err)
+    }
+}
+
+impl CryptoBridge {
+    /// A confirmed export whose file is text.
+    fn export_text(&mut self, format: ExportFormat, token: &str, password: &str) -> Result<String, JsValue> {
+        let bytes = self.engine.export_confirmed(format, token, password).map_err(js_err)?;
+        String::from_utf8(bytes).map_err(|e| js_err(format!("Export error: {}", e)))
+    }
+}
+
+/// AGE: A new X25519 identity. Returns JSON {identity: "AGE-SECRET-KEY-1...", recipient: "age1..."}.
+#[wasm_bindgen]
+pub fn generate_age_identity() -> Result<String, JsValue> {
+    securepass_core::generate_age_identity().map_err(js_err)
+}
+
+/// OPENPGP: Checks a public key before exporting to it.
+/// Returns JSON {fingerprint, user_ids, encryption_subkey} for the user to confirm.
+#[cfg(feature = "openpgp")]
+#[wasm_bindgen]
+pub fn describe_openpgp_key(public_key: &[u8], now: f64) -> Result<String, JsValue> {
+    securepass_core::describe_openpgp_key(public_key, now as u64).map_err(js_err)
+}
+
+/// PAPER: Printable, hand-typeable text for key material (grouped Base32, a CRC per line
+/// and an overall CHECK line).
+#[wasm_bindgen]
+pub fn export_paper(key_material: &[u8]) -> Result<String, JsValue> {
+    paper::encode(key_material).map_err(js_err)
+}
+
+/// PAPER: The key material back from a transcribed backup. Case, O/0 and I/L/1 mix-ups are
+/// corrected; anything else fails with the line to recheck.
+#[wasm_bindgen]
+pub fn parse_paper(text: &str) -> Result<Vec<u8>, JsValue> {
+    paper::decode(text).map_err(js_err)
+}
+
+fn backup_list(backups_json: &str) -> Result<Vec<backups::BackupMeta>, JsValue> {
+    serde_json::from_str(backups_json).map_err(|e| js_err(format!("Backup list parse error: {}", e)))
+}
+
+/// BACKUPS: Metadata for a local backup made now, given the existing ones (JSON array
+/// of {id, created_at, generation}). Returns JSON; store the backup under its 'id'.
+#[wasm_bindgen]
+pub fn next_backup(backups_json: &str, now: f64) -> Result<String, JsValue> {
+    let meta = backups::next_backup(&backup_list(backups_json)?, now as u64);
+    serde_json::to_string(&meta).map_err(|e| js_err(format!("Backup serialize error: {}", e)))
+}
+
+/// BACKUPS: Whether the newest backup is at least 'interval_secs' old (or there is none).
+#[wasm_bindgen]
+pub fn backup_due(backups_json: &str, now: f64, interval_secs: f64) -> Result<bool, JsValue> {
+    Ok(backups::is_due(&backup_list(backups_json)?, now as u64, interval_secs as u64))
+}
+
+/// BACKUPS: Which backups to keep and delete. 'policy_json' is {daily, weekly, monthly},
+/// or "" for 7/4/12. Returns JSON {keep, delete}, newest first.
+#[wasm_bindgen]
+pub fn plan_backup_pruning(backups_json: &str, policy_json: &str) -> Result<String, JsValue> {
+    let policy = if policy_json.is_empty() {
+        backups::RetentionPolicy::default()
+    } else {
+        serde_json::from_str(policy_json).map_err(|e| js_err(format!("Retention policy parse error: {}", e)))?
+    };
+    let plan = backups::prune(&backup_list(backups_json)?, policy);
+    serde_json::to_string(&plan).map_err(|e| js_err(format!("Backup plan serialize error: {}", e)))
+}
+
+// --- 15. Device Transfer & Pairing ---
+// Offline device-to-device migration: the sender animates the frames as QR
+// codes, the receiver feeds whatever it scans into a QrReceiver. Pass bytes
+// that are already encrypted (a backup file, an age or OpenPGP export).
+// PairingSession turns a 6-digit code into a shared key for LAN sync.
+
+/// QR: Frames for `payload`, source frames first, plus `redundancy_percent` repair frames.
+/// Returns a JSON array of strings; show them in a loop.
+#[cfg(feature = "qr-transfer")]
+#[wasm_bindgen]
+pub fn qr_transfer_frames(payload: &[u8], redundancy_percent: u32) -> Result<String, JsValue> {
+    qr_transfer::encode_frames(payload, redundancy_percent)
+        .and_then(|frames| serde_json::to_string(&frames).map_err(|e| format!("Frames serialize error: {}", e)))
+        .map_err(js_err)
+}
+
+#[cfg(feature = "qr-transfer")]
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct QrReceiver {
+    inner: qr_transfer::FrameReceiver,
+}
+
+#[cfg(feature = "qr-transfer")]
+#[wasm_bindgen]
+impl QrReceiver {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> QrReceiver {
+        QrReceiver::default()
+    }
+
+    /// Feeds one scanned frame, in any order. Returns JSON {received, source_frames, complete}.
+    pub fn push(&mut self, frame: &str) -> Result<String, JsValue> {
+        let progress = self.inner.push(frame).map_err(js_err)?;
+        serde_json::to_string(&progress).map_err(|e| js_err(format!("Progress serialize error: {}", e)))
+    }
+
+    /// The transferred bytes. Fails until 'push' has reported complete.
+    pub fn payload(&self) -> Result<Vec<u8>, JsValue> {
+        self.inner.payload().map(<[u8]>::to_vec).ok_or_else(|| JsValue::from_str("Transfer error: not complete yet"))
+    }
+}
+
+/// PAIRING: A fresh 6-digit code for this device to show.
+#[cfg(feature = "pairing")]
+#[wasm_bindgen]
+pub fn generate_pairing_code() -> String {
+    pairing::generate_code()
+}
+
+/// PAIRING: One side of a handshake. Both devices create one with the same code,
+/// swap 'message()', swap the tags 'finish' returns, and 'confirm' yields the key.
+#[cfg(feature = "pairing")]
+#[wasm_bindgen]
+pub struct PairingSession {
+    handshake: Option<pairing::Handshake>,
+    keys: Option<pairing::PendingKeys>,
+}
+
+#[cfg(feature = "pairing")]
+#[wasm_bindgen]
+impl PairingSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new(code: &str) -> Result<PairingSession, JsValue> {
+        let handshake = pairing::Handshake::start(code).map_err(js_err)?;
+        Ok(PairingSession { handshake: Some(handshake), keys: None })
+    }
+
+    /// The message to send to the other device.
+    pub fn message(&self) -> Result<Vec<u8>, JsValue> {
+        self.handshake
+            .as_ref()
+            .map(|h| h.message().to_vec())
+            .ok_or_else(|| JsValue::from_str("Pairing error: handshake already finished"))
+    }
+
+    /// Takes the other device's message. Returns the confirmation tag to send back.
+    pub fn finish(&mut self, inbound: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let handshake = self.handshake.take().ok_or_else(|| JsValue::from_str("Pairing error: handshake already finished"))?;
+        let keys = handshake.finish(inbound).map_err(js_err)?;
+        let tag = keys.confirmation().map_err(js_err)?;
+        self.keys = Some(keys);
+        Ok(tag)
+    }
+
+    /// Checks the other device's tag. Returns the 32-byte session key.
+    pub fn confirm(&mut self, their_confirmation: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let keys = self.keys.take().ok_or_else(|| JsValue::from_str("Pairing error: call finish first"))?;
+        keys.confirm(their_confirmation).map(|key| key.to_vec()).map_err(js_err)
+    }
+}
+
+// --- 16. Offline Sync ---
+// Every edit becomes a sealed op with a Lamport stamp; devices swap ops through
+// any store and replay them into the same vault. Keep every sealed op you see
+// (yours and other devices'), and on startup merge them all into a fresh SyncLog.
+// On put/get/list/delete storage (WebDAV, S3), ops travel as numbered SyncBlobs:
+// list, merge 'pending' blobs in order, record and pack, and now and then
+// snapshot and delete the 'compaction_plan'.
+
+/// SYNC: This device's view of the op-log. 'device_id' must be stable and unique per install.
+#[wasm_bindgen]
+pub struct SyncLog {
+    inner: oplog::OpLog,
+}
+
+#[wasm_bindgen]
+impl SyncLog {
+    #[wasm_bindgen(constructor)]
+    pub fn new(device_id: &str) -> Result<SyncLog, JsValue> {
+        oplog::OpLog::new(device_id).map(|inner| SyncLog { inner }).map_err(js_err)
+    }
+
+    /// Number of ops known, local and remote.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// The highest Lamport stamp seen so far.
+    pub fn clock(&self) -> f64 {
+        self.inner.clock() as f64
+    }
+
+    /// Of the blob names listed on storage (JSON array), the ones to download and
+    /// merge, in order. Returns a JSON array.
+    pub fn pending(&self, names_json: &str) -> Result<String, JsValue> {
+        blob_names(names_json, |names| blobs::pending(&self.inner, names))
+    }
+
+    /// Of the blob names listed on storage (JSON array), the ones the latest
+    /// snapshot made redundant. Returns a JSON array of names to delete.
+    pub fn compaction_plan(&self, names_json: &str) -> Result<String, JsValue> {
+        blob_names(names_json, |names| blobs::compaction_plan(&self.inner, names))
+    }
+}
+
+fn blob_names(names_json: &str, select: impl FnOnce(&[String]) -> Vec<String>) -> Result<String, JsValue> {
+    let names: Vec<String> = serde_json::from_str(names_json).map_err(|e| js_err(format!("Names parse error: {}", e)))?;
+    serde_json::to_string(&select(&names)).map_err(|e| js_err(format!("Names serialize error: {}", e)))
+}
+
+/// A file for remote storage: write 'data' under 'name', never overwriting.
+#[wasm_bindgen]
+pub struct SyncBlob {
+    inner: blobs::SyncBlob,
+}
+
+#[wasm_bindgen]
+impl SyncBlob {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.inner.data.clone()
+    }
+}
+
+#[wasm_bindgen]
+impl CryptoBridge {
+    /// SYNC: Turns edits to the loaded vault since the last sync into sealed ops.
+    /// Returns a JSON array to store and upload ('[]' when nothing changed).
+    pub fn sync_record(&self, log: &mut SyncLog) -> Result<String, JsValue> {
+        self.engine.sync_record(&mut log.inner).map_err(js_err)
+    }
+
+    /// SYNC: Merges a JSON array of sealed ops and reloads the vault from the log.
+    /// Call 'sync_record' first so local edits are not lost. Returns how many ops were new.
+    pub fn sync_merge(&mut self, log: &mut SyncLog, sealed_json: &str) -> Result<usize, JsValue> {
+        self.engine.sync_merge(&mut log.inner, sealed_json).map_err(js_err)
+    }
+
+    /// SYNC: Packs the JSON from 'sync_record' into this device's next ops blob.
+    pub fn sync_pack_ops(&self, log: &mut SyncLog, sealed_json: &str) -> Result<SyncBlob, JsValue> {
+        self.engine.sync_pack_ops(&mut log.inner, sealed_json).map(|inner| SyncBlob { inner }).map_err(js_err)
+    }
+
+    /// SYNC: A snapshot of everything the log holds, for compaction.
+    pub fn sync_pack_snapshot(&self, log: &mut SyncLog) -> Result<SyncBlob, JsValue> {
+        self.engine.sync_pack_snapshot(&mut log.inner).map(|inner| SyncBlob { inner }).map_err(js_err)
+    }
+
+    /// SYNC: Merges one downloaded blob and reloads the vault. Returns how many ops were new.
+    pub fn sync_merge_blob(&mut self, log: &mut SyncLog, name: &str, data: &[u8]) -> Result<usize, JsValue> {
+        self.engine.sync_merge_blob(&mut log.inner, name, data).map_err(js_err)
+    }
+
+    /// DEVICES: Adds a device to the signed device list ("" to start one). Returns the new list JSON.
+    pub fn enroll_device(&self, list_json: &str, device_id: &str, name: &str, public_key: &str, now: f64) -> Result<String, JsValue> {
+        self.engine.enroll_device(list_json, device_id, name, public_key, now as u64).map_err(js_err)
+    }
+
+    /// DEVICES: Removes a device from the signed device list. Returns the new list JSON.
+    pub fn revoke_device(&mut self, list_json: &str, device_id: &str) -> Result<String, JsValue> {
+        self.engine.revoke_device(list_json, device_id).map_err(js_err)
+    }
+
+    /// DEVICES: Checks the list's signature and returns its devices as JSON.
+    pub fn verify_device_list(&self, list_json: &str) -> Result<String, JsValue> {
+        self.engine.verify_device_list(list_json).map_err(js_err)
+    }
+
+    /// DEVICES: Proves this device is listed; sync then merges only listed devices'
+    /// blobs. Returns the list's serial, to pass as 'known_serial' next time.
+    pub fn join_device_list(&mut self, list_json: &str, device_secret_key: &str, known_serial: f64) -> Result<f64, JsValue> {
+        self.engine
+            .join_device_list(list_json, device_secret_key, known_serial as u64)
+            .map(|serial| serial as f64)
+            .map_err(js_err)
+    }
+}
+
+/// DEVICES: A keypair for this device. Returns JSON {public_key, secret_key}.
+#[wasm_bindgen]
+pub fn generate_device_keypair() -> Result<String, JsValue> {
+    securepass_core::generate_device_keypair().map_err(js_err)
+}
+
+// --- 17. Vault Sessions ---
+// One manager holds every unlocked vault (work, personal, ...) by vault ID.
+// Operations name the vault they are for; an empty ID means the active vault.
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct BridgeManager {
+    sessions: sessions::Sessions,
+}
+
+#[wasm_bindgen]
+impl BridgeManager {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BridgeManager {
+        BridgeManager::default()
+    }
+
+    /// Takes over an unlocked bridge (the JS object is consumed) under 'vault_id',
+    /// locking whatever was open under that ID. The first vault added becomes active.
+    pub fn add(&mut self, vault_id: &str, bridge: CryptoBridge) -> Result<(), JsValue> {
+        self.sessions.open(vault_id, bridge.engine).map_err(js_err)
+    }
+
+    /// Makes an open vault the active one.
+    pub fn switch_to(&mut self, vault_id: &str) -> Result<(), JsValue> {
+        self.sessions.switch(vault_id).map_err(js_err)
+    }
+
+    pub fn active_id(&self) -> Option<String> {
+        self.sessions.active_id().map(str::to_string)
+    }
+
+    /// JSON array of the open vault IDs.
+    pub fn vault_ids(&self) -> String {
+        serde_json::to_string(&self.sessions.ids()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Locks one vault and wipes its key. Returns false if it wasn't open.
+    pub fn lock(&mut self, vault_id: &str) -> bool {
+        let locked = self.sessions.lock(vault_id);
+        scrub::scrub_stack();
+        locked
+    }
+
+    /// Locks every vault (auto-lock, logout).
+    pub fn lock_all(&mut self) {
+        self.sessions.lock_all();
+        scrub::scrub_stack();
+    }
+
+    pub fn load_vault(&mut self, vault_id: &str, ciphertext: &[u8], iv: &[u8]) -> Result<(), JsValue> {
+        self.sessions.get_mut(vault_id).and_then(|engine| engine.load_vault(ciphertext, iv)).map_err(js_err)
+    }
+
+    /// Returns JSON {iv, data}, like 'CryptoBridge::seal_vault_package'.
+    pub fn seal_vault_package(&self, vault_id: &str) -> Result<String, JsValue> {
+        let package = self.sessions.get(vault_id).and_then(|engine| engine.seal_vault_package()).map_err(js_err)?;
+        serde_json::to_string(&package).map_err(|e| JsValue::from_str(&format!("Package serialize error: {}", e)))
+    }
+
+    pub fn seal_vault(&self, vault_id: &str, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.sessions.get(vault_id).and_then(|engine| engine.seal_vault(iv)).map_err(js_err)
+    }
+
+    pub fn encrypt(&self, vault_id: &str, plaintext: &str, iv: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.sessions.get(vault_id).and_then(|engine| engine.encrypt(plaintext, iv)).map_err(js_err)
+    }
+
+    pub fn decrypt(&self, vault_id: &str, ciphertext: &[u8], iv: &[u8]) -> Result<String, JsValue> {
+        self.sessions.get(vault_id).and_then(|engine| engine.decrypt(ciphertext, iv)).map_err(js_err)
+    }
+
+    pub fn list_entries_page(&self, vault_id: &str, offset: usize, limit: usize, sort: &str) -> Result<String, JsValue> {
+        self.sessions.get(vault_id).and_then(|engine| engine.list_entries_page(offset, limit, sort)).map_err(js_err)
+    }
+
+    pub fn search_entries(&self, vault_id: &str, query: &str, offset: usize, limit: usize, sort: &str) -> Result<String, JsValue> {
+        self.sessions
+            .get(vault_id)
+            .and_then(|engine| engine.search_entries(query, offset, limit, sort))
+            .map_err(js_err)
+    }
+
+    pub fn record_use(&mut self, vault_id: &str, entry_id: &str, now: f64) -> Result<(), JsValue> {
+        self.sessions.get_mut(vault_id).and_then(|engine| engine.record_use(entry_id, now as u64)).map_err(js_err)
+    }
+}
+
+// --- 18. Deterministic Test Mode ---
+// Only compiled with the 'deterministic-rng' and 'memory-scan' features; release
+// builds never contain them.
+
+/// Seeds the password, passphrase and entry-ID generators on this thread so
+/// their output can be replayed. Keys, nonces and salts stay on the OS CSPRNG.
+#[cfg(feature = "deterministic-rng")]
+#[wasm_bindgen]
+pub fn with_rng(seed: u64) {
+    securepass_core::rng::seed(seed);
+}
+
+/// Switches the generators back to the OS CSPRNG.
+#[cfg(feature = "deterministic-rng")]
+#[wasm_bindgen]
+pub fn clear_rng() {
+    securepass_core::rng::clear();
+}
+
+/// Whether the bytes 'canary_hex' encodes appear anywhere in linear memory, for
+/// tests that put a known secret in the vault, lock it and expect false. The hex
+/// string is freed (and scrubbed) before the scan, so only real copies count.
+#[cfg(all(feature = "memory-scan", target_arch = "wasm32"))]
+#[wasm_bindgen]
+pub fn memory_contains(canary_hex: String) -> Result<bool, JsValue> {
+    let canary = scrub::Canary::from_hex(&canary_hex).map_err(js_err)?;
+    drop(canary_hex);
+    Ok(canary.found_in_linear_memory())
+}
+
+// --- 19. Panic Reports ---
+// Release builds abort on panic, so JS sees only a RuntimeError ("unreachable").
+// The hook below runs first: it logs a sanitized report (code and source line,
+// never the panic message; see 'panic_report') and keeps it for 'last_panic',
+// which JS can call after catching the RuntimeError to show a stable error code.
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = error)]
+    fn console_error(message: &str);
+}
+
+thread_local! {
+    static LAST_PANIC: std::cell::RefCell<Option<panic_report::PanicReport>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Installs the panic hook; runs by itself when the module is instantiated.
+#[wasm_bindgen(start)]
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let report = panic_report::PanicReport::from_hook(info);
+        console_error(&report.to_json());
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some(report));
+    }));
+}
+
+/// The report of the last panic as JSON {code, message, location}, if there was one.
+#[wasm_bindgen]
+pub fn last_panic() -> Option<String> {
+    LAST_PANIC.with(|last| last.borrow().as_ref().map(panic_report::PanicReport::to_json))
+}
+
+// --- 20. Unit Tests ---
+// The engine is tested in securepass-core; these only check the JS-facing glue.
+// (Error paths build a JsValue, which panics outside a Wasm runtime.)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_roundtrip() {
+        let bridge = CryptoBridge::new("pwd", b"salt-123456789012").unwrap();
+        let iv = [0u8; 12];
+        let ciphertext = bridge.encrypt("Sensitive data to protect", &iv).unwrap();
+        assert_eq!(bridge.decrypt(&ciphertext, &iv).unwrap(), "Sensitive data to protect");
+        assert_eq!(bridge.entry_count(), 0);
+    }
+
+    #[test]
+    fn test_attachment_getters() {
+        let bridge = CryptoBridge::new("pwd", b"salt-123456789012").unwrap();
+        let attachment = bridge.encrypt_attachment(b"%PDF-1.7", "scan.pdf", "application/pdf").unwrap();
+        assert_eq!(bridge.decrypt_attachment(&attachment.id(), &attachment.content()).unwrap(), b"%PDF-1.7");
+        assert!(bridge.decrypt_attachment_meta(&attachment.id(), &attachment.meta()).unwrap().contains("scan.pdf"));
+    }
+
+    #[test]
+    fn test_storage_reader_roundtrip() {
+        let package = r#"{"version":2,"iv":[1,1,1,1,1,1,1,1,1,1,1,1],"data":[5,6]}"#;
+        let records = encode_vault_record("vault", &[9; 8], 1, package).unwrap();
+        let mut reader = StorageReader::new();
+        for record in &records {
+            reader.push(&record.value());
+        }
+        assert_eq!(records[0].key(), "vault/0");
+        assert_eq!(reader.read_vault().unwrap(), r#"{"data":[5,6],"iv":[1,1,1,1,1,1,1,1,1,1,1,1],"passwordPolicy":1,"salt":[9,9,9,9,9,9,9,9],"version":2}"#);
+    }
+
+    #[test]
+    fn test_paper_roundtrip() {
+        let text = export_paper(&[7u8; 32]).unwrap();
+        assert_eq!(parse_paper(&text.to_lowercase()).unwrap(), vec![7u8; 32]);
+    }
+
+    #[cfg(feature = "qr-transfer")]
+    #[test]
+    fn test_qr_receiver_roundtrip() {
+        let frames: Vec<String> = serde_json::from_str(&qr_transfer_frames(&[3u8; 1_000], 25).unwrap()).unwrap();
+        let mut receiver = QrReceiver::new();
+        let progress: Vec<String> = frames.iter().map(|f| receiver.push(f).unwrap()).collect();
+        assert!(progress.last().unwrap().contains(r#""complete":true"#));
+        assert_eq!(receiver.payload().unwrap(), vec![3u8; 1_000]);
+    }
+
+    #[cfg(feature = "pairing")]
+    #[test]
+    fn test_pairing_sessions_agree() {
+        let code = generate_pairing_code();
+        let (mut a, mut b) = (PairingSession::new(&code).unwrap(), PairingSession::new(&code).unwrap());
+        let (msg_a, msg_b) = (a.message().unwrap(), b.message().unwrap());
+        let (tag_a, tag_b) = (a.finish(&msg_b).unwrap(), b.finish(&msg_a).unwrap());
+        assert_eq!(a.confirm(&tag_b).unwrap(), b.confirm(&tag_a).unwrap());
+    }
+
+    #[test]
+    fn test_sync_log_merges_sealed_ops() {
+        let mut source = Engine::from_key([4u8; 32]);
+        source.vault_mut().entries.push(securepass_core::vault::VaultEntry { id: "1".to_string(), ..Default::default() });
+        let mut browser = CryptoBridge::from(Engine::from_key([4u8; 32]));
+        browser.load_vault(&source.seal_vault(&[0u8; 12]).unwrap(), &[0u8; 12]).unwrap();
+
+        let mut log = SyncLog::new("browser").unwrap();
+        let sealed = browser.sync_record(&mut log).unwrap();
+        assert_eq!(browser.sync_record(&mut log).unwrap(), "[]");
+
+        let mut phone = CryptoBridge::from(Engine::from_key([4u8; 32]));
+        let mut phone_log = SyncLog::new("phone").unwrap();
+        assert_eq!(phone.sync_merge(&mut phone_log, &sealed).unwrap(), 1);
+        assert_eq!((phone.entry_count(), phone_log.len(), phone_log.clock()), (1, 1, 1.0));
+
+        let blob = browser.sync_pack_ops(&mut log, &sealed).unwrap();
+        let names = serde_json::to_string(&[blob.name()]).unwrap();
+        assert_eq!(SyncLog::new("tablet").unwrap().pending(&names).unwrap(), names);
+        assert_eq!(phone.sync_merge_blob(&mut phone_log, &blob.name(), &blob.data()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pin_lock_roundtrip() {
+        let bridge = CryptoBridge::new("pw", b"salt-123456789012").unwrap();
+        let pepper = generate_pin_pepper();
+        let lock = bridge.create_pin_lock("pw", "9753", &pepper, 3).unwrap();
+
+        let mut restored = PinLock::from_json(&lock.to_json().unwrap()).unwrap();
+        let reopened = CryptoBridge::from_pin("9753", &pepper, &mut restored).unwrap();
+        let sealed = bridge.encrypt("vault", &[1u8; 12]).unwrap();
+        assert_eq!(reopened.decrypt(&sealed, &[1u8; 12]).unwrap(), "vault");
+        assert_eq!((restored.attempts_left(), restored.is_destroyed()), (3, false));
+    }
+
+    #[test]
+    fn test_bridge_manager_routes_by_vault() {
+        let mut manager = BridgeManager::new();
+        let work = CryptoBridge::from(Engine::from_key([1u8; 32]));
+        let sealed = work.encrypt("work vault", &[2u8; 12]).unwrap();
+        manager.add("work", work).unwrap();
+        manager.add("personal", CryptoBridge::from(Engine::from_key([3u8; 32]))).unwrap();
+
+        assert_eq!(manager.vault_ids(), r#"["personal","work"]"#);
+        assert_eq!(manager.decrypt("", &sealed, &[2u8; 12]).unwrap(), "work vault");
+        manager.switch_to("personal").unwrap();
+        assert_eq!(manager.active_id().as_deref(), Some("personal"));
+
+        assert!(manager.lock("work"));
+        manager.lock_all();
+        assert_eq!(manager.vault_ids(), "[]");
+    }
+
+    #[test]
+    fn test_throttle_state_roundtrip() {
+        let mut throttle = UnlockThrottle::new();
+        CryptoBridge::new_throttled("pw", b"salt-123456789012", 1, &mut throttle).unwrap();
+        throttle.record_failure();
+
+        let restored = UnlockThrottle::from_json(&throttle.to_json().unwrap()).unwrap();
+        assert_eq!(restored.consecutive_failures(), 1);
+    }
+}
+
+/// NOTES: Markdown as sanitized HTML, for a note being edited before it is saved.
+#[cfg(feature = "markdown")]
+#[wasm_bindgen]
+pub fn render_markdown(notes: &str) -> String {
+    securepass_core::render_markdown(notes)
 }
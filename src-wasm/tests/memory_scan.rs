@@ -0,0 +1,34 @@
+// --- Memory Scan Tests ---
+// Puts a secret made at runtime into an unlocked vault, locks it, and scans all
+// of linear memory for the secret with 'memory_contains'. Only builds with the
+// 'memory-scan' feature: run `npm run test:wasm:memory`.
+#![cfg(all(feature = "memory-scan", target_arch = "wasm32", target_os = "unknown"))]
+
+use securepass_wasm::{memory_contains, CryptoBridge};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const SALT: &[u8] = b"salt-123456789012";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[wasm_bindgen_test]
+fn locked_vault_leaves_no_secret_in_memory() {
+    let sealer = CryptoBridge::new("pw", SALT).unwrap();
+    // Random, so the secret isn't a constant in the module's data section; only
+    // its hex goes into 'memory_contains'.
+    let (canary_hex, package) = {
+        let canary = sealer.generate_passphrase();
+        let vault_json = format!(r#"{{"entries": [{{"id": "1", "title": "Bank", "password": "{}"}}]}}"#, canary);
+        (hex(canary.as_bytes()), sealer.encrypt_package(&vault_json).unwrap())
+    };
+    sealer.lock();
+
+    let mut bridge = CryptoBridge::new("pw", SALT).unwrap();
+    bridge.load_vault_package(&package).unwrap();
+    assert!(memory_contains(canary_hex.clone()).unwrap());
+
+    bridge.lock();
+    assert!(!memory_contains(canary_hex).unwrap());
+}
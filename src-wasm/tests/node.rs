@@ -0,0 +1,41 @@
+// --- Node.js Tests ---
+// Runs the compiled Wasm module under Node (wasm-bindgen-test's default runtime)
+// to check it needs nothing browser-only: randomness comes from Node's crypto,
+// and errors reach JS as exceptions. Run with `wasm-pack test --node src-wasm`.
+#![cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+
+use securepass_wasm::{generate_emergency_keypair, registrable_domain, CryptoBridge, PasswordOptions};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const SALT: &[u8] = b"salt-123456789012";
+
+#[wasm_bindgen_test]
+fn randomness_comes_from_node_crypto() {
+    let bridge = CryptoBridge::new("pw", SALT).unwrap();
+    assert_ne!(bridge.generate_passphrase(), bridge.generate_passphrase());
+
+    let options = PasswordOptions { length: 24, use_uppercase: true, use_numbers: true, use_symbols: true };
+    let password = bridge.generate_password(serde_wasm_bindgen::to_value(&options).unwrap()).unwrap();
+    assert_eq!(password.chars().count(), 24);
+
+    // X25519 keypairs and slot filler draw from OsRng.
+    assert_ne!(generate_emergency_keypair().unwrap(), generate_emergency_keypair().unwrap());
+    assert!(bridge.create_vault_slots().is_ok());
+}
+
+#[wasm_bindgen_test]
+fn errors_surface_as_js_values() {
+    let bridge = CryptoBridge::new("pw", SALT).unwrap();
+    let iv = [0u8; 12];
+    let ciphertext = bridge.encrypt("secret", &iv).unwrap();
+    assert_eq!(bridge.decrypt(&ciphertext, &iv).unwrap(), "secret");
+
+    let wrong = CryptoBridge::new("wrong", SALT).unwrap();
+    let err = wrong.decrypt(&ciphertext, &iv).unwrap_err();
+    assert!(err.as_string().unwrap().starts_with("Decryption error"));
+}
+
+#[wasm_bindgen_test]
+fn domains_work_without_a_window() {
+    assert_eq!(registrable_domain("https://mail.google.co.uk").as_deref(), Some("google.co.uk"));
+}
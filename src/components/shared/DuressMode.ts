@@ -37,17 +37,15 @@ export class DuressMode extends BaseComponent {
         try {
             // Generate unique salt for duress vault
             const salt = generateSalt();
-            const bridge = await WasmCryptoService.createBridge(dPwd, salt);
+            const passwordPolicy = await WasmCryptoService.currentPasswordPolicy();
+            const bridge = await WasmCryptoService.createBridge(dPwd, salt, passwordPolicy);
 
-            const iv = crypto.getRandomValues(new Uint8Array(12));
-            const ciphertext = WasmCryptoService.encrypt(bridge, JSON.stringify(decoy), iv);
+            const sealed = WasmCryptoService.encryptPackage(bridge, JSON.stringify(decoy));
 
             // Store decoy vault and its salt
-            localStorage.setItem('decoy_vault', JSON.stringify({
-                iv: Array.from(iv),
-                data: Array.from(ciphertext)
-            }));
+            localStorage.setItem('decoy_vault', JSON.stringify(sealed));
             localStorage.setItem('decoy_salt', JSON.stringify(Array.from(salt)));
+            localStorage.setItem('decoy_password_policy', JSON.stringify(passwordPolicy));
 
 
             showToast("Duress Vault Ready. Log in with this password to show fake data.", 'success', 5000);
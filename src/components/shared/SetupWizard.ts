@@ -100,33 +100,34 @@ export class SetupWizard extends BaseComponent {
         const p1 = (this.querySelector('#setup-pwd') as HTMLInputElement).value;
         const p2 = (this.querySelector('#setup-pwd-conf') as HTMLInputElement).value;
 
-        if (p1.length < 12) {
-            showToast("Security Requirement: Master Password must be at least 12 characters.", 'error');
-            return;
-        }
         if (p1 !== p2) {
             showToast("Passwords do not match.", 'error');
             return;
         }
+        // Same rules and messages as the mobile apps and CLI
+        const violations = await WasmCryptoService.validateMasterPassword(p1);
+        if (violations.length > 0) {
+            showToast(`Security Requirement: ${violations.map(v => v.message).join(' ')}`, 'error');
+            return;
+        }
 
         try {
             const initialVault = { entries: [] };
 
             // Generate unique salt for this vault
             const salt = this.generateSalt();
-            const bridge = await WasmCryptoService.createBridge(p1, salt);
+            const passwordPolicy = await WasmCryptoService.currentPasswordPolicy();
+            const bridge = await WasmCryptoService.createBridge(p1, salt, passwordPolicy);
 
-            const iv = crypto.getRandomValues(new Uint8Array(12));
-            const ciphertext = WasmCryptoService.encrypt(bridge, JSON.stringify(initialVault), iv);
+            const sealed = WasmCryptoService.encryptPackage(bridge, JSON.stringify(initialVault));
 
             // Store encrypted vault
-            localStorage.setItem('encrypted_vault', JSON.stringify({
-                iv: Array.from(iv),
-                data: Array.from(ciphertext)
-            }));
+            localStorage.setItem('encrypted_vault', JSON.stringify(sealed));
 
             // Store salt separately (unencrypted - salt is not secret)
             localStorage.setItem('vault_salt', JSON.stringify(Array.from(salt)));
+            // How the password was normalized before Argon2id; unlock must use the same
+            localStorage.setItem('vault_password_policy', JSON.stringify(passwordPolicy));
             localStorage.setItem('vault_initialized', 'true');
 
             showToast("Vault created successfully! Please log in with your new password.", 'success');
@@ -136,14 +136,10 @@ document.addEventListener('save-vault', (async () => {
 
     if (!bridge) return;
 
-    const iv = crypto.getRandomValues(new Uint8Array(12));
-    const ciphertext = WasmCryptoService.encrypt(bridge, JSON.stringify(vault), iv);
+    const sealed = WasmCryptoService.encryptPackage(bridge, JSON.stringify(vault));
 
     const storageKey = isDecoy ? 'decoy_vault' : 'encrypted_vault';
-    localStorage.setItem(storageKey, JSON.stringify({
-        iv: Array.from(iv),
-        data: Array.from(ciphertext)
-    }));
+    localStorage.setItem(storageKey, JSON.stringify(sealed));
     showToast("Vault Encrypted & Saved Successfully.", 'success');
 }) as EventListener);
 
@@ -0,0 +1,85 @@
+/**
+ * Device Key Service
+ *
+ * Keeps a non-extractable AES-GCM key in IndexedDB and uses it to bind the vault
+ * to this browser profile. Script can use the key but never read it, so a copy of
+ * localStorage (or a backup file) of a bound vault can't be decrypted elsewhere.
+ * The Rust side mixes the key's output into the master key (CryptoBridge.bind_to_device).
+ */
+
+import { CryptoBridge } from '../pkg/securepass_wasm.js';
+
+export interface DeviceBinding {
+    version: number;
+    iv: number[];
+    challenge: number[];
+}
+
+export class DeviceKeyService {
+    private static readonly DB_NAME = 'securepass-device';
+    private static readonly STORE = 'keys';
+    private static readonly KEY_ID = 'vault-binding';
+
+    private static openDb(): Promise<IDBDatabase> {
+        return new Promise((resolve, reject) => {
+            const request = indexedDB.open(this.DB_NAME, 1);
+            request.onupgradeneeded = () => request.result.createObjectStore(this.STORE);
+            request.onsuccess = () => resolve(request.result);
+            request.onerror = () => reject(request.error);
+        });
+    }
+
+    private static async loadKey(): Promise<CryptoKey | undefined> {
+        const db = await this.openDb();
+        return new Promise((resolve, reject) => {
+            const request = db.transaction(this.STORE).objectStore(this.STORE).get(this.KEY_ID);
+            request.onsuccess = () => resolve(request.result as CryptoKey | undefined);
+            request.onerror = () => reject(request.error);
+        });
+    }
+
+    /**
+     * Returns this profile's device key, creating it on first use.
+     * IndexedDB stores the CryptoKey object itself; its bytes stay inside the browser.
+     */
+    static async getOrCreateKey(): Promise<CryptoKey> {
+        const existing = await this.loadKey();
+        if (existing) return existing;
+
+        const key = await crypto.subtle.generateKey({ name: 'AES-GCM', length: 256 }, false, ['encrypt']);
+        const db = await this.openDb();
+        await new Promise<void>((resolve, reject) => {
+            const tx = db.transaction(this.STORE, 'readwrite');
+            tx.objectStore(this.STORE).put(key, this.KEY_ID);
+            tx.oncomplete = () => resolve();
+            tx.onerror = () => reject(tx.error);
+        });
+        return key;
+    }
+
+    /**
+     * The device key's output for a binding: what CryptoBridge.bind_to_device expects.
+     * @throws Error if this profile has no device key (e.g. site data was cleared)
+     */
+    static async respond(binding: DeviceBinding): Promise<Uint8Array> {
+        const key = await this.loadKey();
+        if (!key) {
+            throw new Error("This vault is bound to a browser profile whose device key is missing.");
+        }
+        const output = await crypto.subtle.encrypt(
+            { name: 'AES-GCM', iv: new Uint8Array(binding.iv) },
+            key,
+            new Uint8Array(binding.challenge)
+        );
+        return new Uint8Array(output);
+    }
+
+    /**
+     * Binds a freshly derived bridge to this profile, if the vault has a binding.
+     * @param bindingJson - The binding stored next to the vault's salt, or null for an unbound vault
+     */
+    static async bind(bridge: CryptoBridge, bindingJson: string | null): Promise<void> {
+        if (!bindingJson) return;
+        bridge.bind_to_device(bindingJson, await this.respond(JSON.parse(bindingJson)));
+    }
+}
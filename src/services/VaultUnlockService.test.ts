@@ -12,8 +12,8 @@ vi.mock('../pkg/securepass_wasm.js', () => ({
 vi.mock('./WasmCryptoService.js', () => ({
     WasmCryptoService: {
         createBridge: vi.fn(),
-        encrypt: vi.fn(),
-        decrypt: vi.fn()
+        encryptPackage: vi.fn(),
+        decryptPackage: vi.fn()
     }
 }));
 
@@ -40,14 +40,27 @@ describe('VaultUnlockService', () => {
 
         const mockBridge = {} as any;
         (WasmCryptoService.createBridge as any).mockResolvedValue(mockBridge);
-        (WasmCryptoService.decrypt as any).mockReturnValue(JSON.stringify(vault));
+        (WasmCryptoService.decryptPackage as any).mockReturnValue(JSON.stringify(vault));
 
         const result = await VaultUnlockService.unlock(password);
 
         expect(result.success).toBe(true);
         expect(result.vault).toEqual(vault);
         expect(result.isDecoyMode).toBe(false);
-        expect(WasmCryptoService.createBridge).toHaveBeenCalledWith(password, salt);
+        expect(WasmCryptoService.createBridge).toHaveBeenCalledWith(password, salt, 0);
+    });
+
+    it('should derive with the password policy stored next to the salt', async () => {
+        const salt = new Uint8Array(32);
+        localStorage.setItem('vault_salt', JSON.stringify(Array.from(salt)));
+        localStorage.setItem('vault_password_policy', '1');
+        localStorage.setItem('encrypted_vault', JSON.stringify({ iv: Array.from(new Uint8Array(12)), data: [1] }));
+
+        (WasmCryptoService.createBridge as any).mockResolvedValue({});
+        (WasmCryptoService.decryptPackage as any).mockReturnValue(JSON.stringify({ entries: [] }));
+
+        await VaultUnlockService.unlock('pässword');
+        expect(WasmCryptoService.createBridge).toHaveBeenCalledWith('pässword', salt, 1);
     });
 
     it('should unlock decoy vault correctly', async () => {
@@ -63,7 +76,7 @@ describe('VaultUnlockService', () => {
 
         const mockBridge = {} as any;
         (WasmCryptoService.createBridge as any).mockResolvedValue(mockBridge);
-        (WasmCryptoService.decrypt as any).mockReturnValue(JSON.stringify(vault));
+        (WasmCryptoService.decryptPackage as any).mockReturnValue(JSON.stringify(vault));
 
         const result = await VaultUnlockService.unlock(password);
 
@@ -80,7 +93,7 @@ describe('VaultUnlockService', () => {
         localStorage.setItem('encrypted_vault', JSON.stringify({ iv: [], data: [] }));
 
         (WasmCryptoService.createBridge as any).mockResolvedValue({});
-        (WasmCryptoService.decrypt as any).mockImplementation(() => { throw new Error('Decryption failed'); });
+        (WasmCryptoService.decryptPackage as any).mockImplementation(() => { throw new Error('Decryption failed'); });
 
         const result = await VaultUnlockService.unlock('wrong-password');
         expect(result.success).toBe(false);
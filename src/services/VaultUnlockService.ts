@@ -6,7 +6,8 @@
 
 import { WasmCryptoService } from './WasmCryptoService.js';
 import { CryptoBridge } from '../pkg/securepass_wasm.js';
-import { getSalt } from '../utils/crypto-utils.js';
+import { DeviceKeyService } from './DeviceKeyService.js';
+import { deviceBindingKey, getPasswordPolicy, getSalt } from '../utils/crypto-utils.js';
 
 export interface UnlockResult {
     success: boolean;
@@ -87,14 +88,9 @@ export class VaultUnlockService {
     ): Promise<UnlockResult> {
         try {
             const salt = getSalt(saltKey);
-            const bridge = await WasmCryptoService.createBridge(password, salt);
-            const { iv, data } = JSON.parse(encryptedVault);
-
-            const decrypted = WasmCryptoService.decrypt(
-                bridge,
-                new Uint8Array(data),
-                new Uint8Array(iv)
-            );
+            const bridge = await WasmCryptoService.createBridge(password, salt, getPasswordPolicy(saltKey));
+            await DeviceKeyService.bind(bridge, localStorage.getItem(deviceBindingKey(saltKey)));
+            const decrypted = WasmCryptoService.decryptPackage(bridge, JSON.parse(encryptedVault));
             const vault = JSON.parse(decrypted);
 
             return {
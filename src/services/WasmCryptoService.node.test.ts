@@ -0,0 +1,52 @@
+// @vitest-environment node
+import { describe, it, expect, beforeAll } from 'vitest';
+import { existsSync, readFileSync } from 'node:fs';
+import { fileURLToPath } from 'node:url';
+import { WasmCryptoService } from './WasmCryptoService.js';
+
+// Runs the real module from `npm run build:wasm` under plain Node: no window,
+// no fetch of the .wasm URL, randomness from Node's crypto.
+const wasmPath = fileURLToPath(new URL('../pkg/securepass_wasm_bg.wasm', import.meta.url));
+
+describe.skipIf(!existsSync(wasmPath))('WasmCryptoService under Node', () => {
+    beforeAll(async () => {
+        await WasmCryptoService.ensureInitialized(readFileSync(wasmPath));
+    });
+
+    it('should encrypt and decrypt with the shipped artifact', async () => {
+        const salt = new Uint8Array(16).fill(7);
+        const bridge = await WasmCryptoService.createBridge('password', salt);
+        const iv = crypto.getRandomValues(new Uint8Array(12));
+
+        const ciphertext = WasmCryptoService.encrypt(bridge, 'secret', iv);
+        expect(WasmCryptoService.decrypt(bridge, ciphertext, iv)).toBe('secret');
+
+        const other = await WasmCryptoService.createBridge('other', salt);
+        expect(() => WasmCryptoService.decrypt(other, ciphertext, iv)).toThrow(/Decryption error/);
+    });
+
+    it('should seal committed packages and still open legacy ones', async () => {
+        const salt = new Uint8Array(16).fill(7);
+        const bridge = await WasmCryptoService.createBridge('password', salt);
+        const sealed = WasmCryptoService.encryptPackage(bridge, 'secret');
+        expect(sealed.version).toBe(2);
+        expect(WasmCryptoService.decryptPackage(bridge, sealed)).toBe('secret');
+        expect(() => WasmCryptoService.decryptPackage(bridge, { ...sealed, version: 1 })).toThrow(/Decryption error/);
+
+        const iv = crypto.getRandomValues(new Uint8Array(12));
+        const legacy = { iv: Array.from(iv), data: Array.from(WasmCryptoService.encrypt(bridge, 'old', iv)) };
+        expect(WasmCryptoService.decryptPackage(bridge, legacy)).toBe('old');
+    });
+
+    it('should generate passwords from Node randomness', async () => {
+        const bridge = await WasmCryptoService.createBridge('password', new Uint8Array(16));
+        expect(bridge.generate_passphrase()).not.toBe(bridge.generate_passphrase());
+    });
+
+    it('should wrap and unwrap the biometric password', async () => {
+        const key = await WasmCryptoService.deriveBioKey(new TextEncoder().encode('credential'));
+        const iv = new Uint8Array(12);
+        const wrapped = await WasmCryptoService.wrapPassword('master', key, iv);
+        expect(await WasmCryptoService.unwrapPassword(wrapped, key, iv)).toBe('master');
+    });
+});
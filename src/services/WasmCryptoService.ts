@@ -1,27 +1,95 @@
-import init, { CryptoBridge, derive_bio_key, wrap_password, unwrap_password } from '../pkg/securepass_wasm.js';
+import init, { CryptoBridge, current_password_policy, derive_bio_key, is_common_password, validate_master_password, wrap_password, unwrap_password } from '../pkg/securepass_wasm.js';
+import { WASM_INTEGRITY } from '../pkg/securepass_wasm_integrity.js';
+import type { EncryptedPackage } from '../types.js';
 
 export class WasmCryptoService {
     private static initialized = false;
 
     /**
      * Initialize the Wasm module.
-     * Must be called once before any other operations.
+     * Must be called once before any other operations. The module's bytes are
+     * checked against WASM_INTEGRITY, the SRI hash recorded when it was built,
+     * before it is instantiated; any other module is refused.
+     * @param wasm Raw module bytes, for runtimes that can't fetch the .wasm by URL (e.g. Node)
      */
-    static async ensureInitialized(): Promise<void> {
+    static async ensureInitialized(wasm?: BufferSource): Promise<void> {
         if (!this.initialized) {
-            await init();
+            const bytes = wasm ?? await this.fetchModule();
+            if (await this.integrityOf(bytes) !== WASM_INTEGRITY) {
+                throw new Error('The crypto module does not match the pinned build');
+            }
+            await init({ module_or_path: bytes });
             this.initialized = true;
         }
     }
 
+    /** The shipped .wasm, fetched with its SRI hash so the browser checks it too. */
+    private static async fetchModule(): Promise<ArrayBuffer> {
+        const response = await fetch(new URL('../pkg/securepass_wasm_bg.wasm', import.meta.url), { integrity: WASM_INTEGRITY });
+        if (!response.ok) {
+            throw new Error(`Could not load the crypto module (${response.status})`);
+        }
+        return response.arrayBuffer();
+    }
+
+    /** The SRI form ("sha384-" + Base64) of the SHA-384 of 'bytes'. */
+    private static async integrityOf(bytes: BufferSource): Promise<string> {
+        const digest = new Uint8Array(await crypto.subtle.digest('SHA-384', bytes));
+        return `sha384-${btoa(String.fromCharCode(...digest))}`;
+    }
+
     /**
      * Create a new CryptoBridge instance with Argon2id key derivation.
      * @param password Master password
      * @param salt Vault salt
+     * @param passwordPolicy How the password is normalized before Argon2id: the
+     *        vault's stored policy, 0 for vaults created before policies existed
+     */
+    static async createBridge(password: string, salt: Uint8Array, passwordPolicy: number = 0): Promise<CryptoBridge> {
+        await this.ensureInitialized();
+        return CryptoBridge.new_with_policy(password, salt, passwordPolicy);
+    }
+
+    /**
+     * The password policy to create new vaults with. Store it next to the salt.
+     */
+    static async currentPasswordPolicy(): Promise<number> {
+        await this.ensureInitialized();
+        return current_password_policy();
+    }
+
+    /**
+     * True if the password is on the embedded common-password blocklist.
+     * Vault creation refuses such a master password.
      */
-    static async createBridge(password: string, salt: Uint8Array): Promise<CryptoBridge> {
+    static async isCommonPassword(password: string): Promise<boolean> {
         await this.ensureInitialized();
-        return new CryptoBridge(password, salt);
+        return is_common_password(password);
+    }
+
+    /**
+     * Every master-password rule the password breaks, with the message to show.
+     * Empty means it may be used.
+     * @param hints Things the user is known by (email, name)
+     */
+    static async validateMasterPassword(password: string, hints: string[] = []): Promise<{ rule: string; message: string }[]> {
+        await this.ensureInitialized();
+        return JSON.parse(validate_master_password(password, JSON.stringify(hints)));
+    }
+
+    /**
+     * Encrypt text under a nonce the engine picks, as the {version, iv, data}
+     * package the app stores.
+     */
+    static encryptPackage(bridge: CryptoBridge, plaintext: string): EncryptedPackage {
+        return JSON.parse(bridge.encrypt_package(plaintext));
+    }
+
+    /**
+     * Decrypt a stored package. One without a version predates key commitment.
+     */
+    static decryptPackage(bridge: CryptoBridge, pkg: EncryptedPackage): string {
+        return bridge.decrypt_package(JSON.stringify(pkg));
     }
 
     /**
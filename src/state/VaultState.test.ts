@@ -2,6 +2,7 @@ import { describe, it, expect, beforeEach, vi } from 'vitest';
 import { VaultState } from './VaultState.js';
 
 // Mock Wasm module
+vi.mock('../pkg/securepass_wasm_integrity.js', () => ({ WASM_INTEGRITY: '' }));
 vi.mock('../pkg/securepass_wasm.js', () => ({
     CryptoBridge: class {
         constructor() { }
@@ -122,6 +123,14 @@ describe('VaultState', () => {
             expect(state.getFilteredEntries()).toHaveLength(1);
             expect(state.getFilteredEntries()[0].title).toBe('Gmail');
         });
+
+        it('should ignore accents and full-width characters in search', () => {
+            state.addEntry({ id: '4', title: 'Café Müller', password: 'p4', category: 'personal' });
+            state.setSearchQuery('ＭＵＬＬＥＲ');
+            expect(state.getFilteredEntries().map(e => e.title)).toEqual(['Café Müller']);
+            state.setSearchQuery('cafe');
+            expect(state.getFilteredEntries()).toHaveLength(1);
+        });
     });
 
     describe('Favorites', () => {
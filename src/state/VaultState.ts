@@ -82,8 +82,8 @@ export class VaultState {
 
             // Search filter
             if (this.searchQuery) {
-                const searchableText = `${entry.title} ${entry.username || ''}`.toLowerCase();
-                if (!searchableText.includes(this.searchQuery.toLowerCase())) return false;
+                const searchableText = searchKey(`${entry.title} ${entry.username || ''}`);
+                if (!searchableText.includes(searchKey(this.searchQuery))) return false;
             }
 
             return true;
@@ -216,5 +216,11 @@ export class VaultState {
     }
 }
 
+// Folds text the way the core's text::search_key does: full-width and other
+// compatibility forms unified, accents dropped, case ignored ("ＣＡＦÉ" -> "cafe").
+function searchKey(text: string): string {
+    return text.normalize('NFKD').replace(/\p{M}/gu, '').toLowerCase().normalize('NFC');
+}
+
 // Export singleton instance
 export const vaultState = VaultState.getInstance();
@@ -4,12 +4,52 @@
 export interface VaultEntry {
   id: string;             // Unique identifier (UUID)
   title: string;          // Name of the service (e.g., GitHub)
+  type?: string;          // "login" when absent; "note", "card", ...
   username: string;       // Login identity
   password: string;       // The sensitive secret
   url: string;            // Login page link
   notes: string;          // Additional encrypted details
   category: string;       // Folder/Tag for organization
   lastModified: number;   // Unix timestamp for sync conflict resolution
+  lastUsed?: number;      // Unix timestamp of the last fill or copy
+  useCount?: number;      // Fills and copies so far, for quick-fill ranking
+  travelMode?: boolean;   // Excluded from travel vaults when true
+  reprompt?: boolean;     // Master password needed again to reveal secrets
+  attachments?: AttachmentRef[]; // Files attached to this entry
+  fields?: CustomField[];  // Typed extra fields (templates add these)
+  extraLock?: ExtraLock;   // Secrets sealed under a second passphrase
+}
+
+/**
+ * An entry's secrets sealed under an extra passphrase (see extra_lock.rs).
+ * While set, username, password, notes, totpSecret, history and fields are empty.
+ */
+export interface ExtraLock {
+  version: number;
+  kdf: { memoryKib: number; iterations: number; parallelism: number };
+  passwordPolicy: number;
+  salt: string;           // Base64
+  keyIv: string;          // Base64; the wrapped entry key
+  wrappedKey: string;
+  iv: string;             // Base64; the sealed secrets
+  data: string;
+}
+
+/**
+ * A named, typed value beyond the built-in entry fields.
+ */
+export interface CustomField {
+  name: string;           // Label shown in the UI (e.g., "Account number")
+  kind: 'text' | 'hidden' | 'number' | 'date' | 'url' | 'email' | 'phone';
+  value: string;          // Dates are "YYYY-MM-DD"
+}
+
+/**
+ * An entry's reference to an attachment stored outside the vault blob.
+ */
+export interface AttachmentRef {
+  id: string;             // Attachment ID the meta and content are bound to
+  size: number;           // File size in bytes, for totals without decrypting meta
 }
 
 /**
@@ -21,12 +61,14 @@ export interface VaultSchema {
   lastSync: number;       // Last time the vault was saved
   entries: VaultEntry[];  // Array of all stored credentials
   categories: string[];   // User-defined organizational categories
+  travelModeCategories?: string[]; // Folders excluded from travel vaults
 }
 
 /**
  * Structure used for storing the encrypted vault in LocalStorage or a Database.
  */
 export interface EncryptedPackage {
+  version?: number;       // 2: key-committed; missing or 1: legacy plain AES-GCM
   iv: number[];           // Initialization Vector (stored as array for JSON)
   data: number[];         // Ciphertext (stored as array for JSON)
 }
\ No newline at end of file
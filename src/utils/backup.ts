@@ -12,6 +12,7 @@ export interface BackupData {
         data: number[];
     };
     salt: number[];
+    passwordPolicy?: number; // Absent for vaults created before password policies (0)
 }
 
 export class BackupService {
@@ -28,6 +29,7 @@ export class BackupService {
 
         const vaultData = JSON.parse(encryptedVault);
         const salt = JSON.parse(saltStr);
+        const policyStr = localStorage.getItem('vault_password_policy');
 
         const backup: BackupData = {
             version: 1,
@@ -35,6 +37,9 @@ export class BackupService {
             vault: vaultData,
             salt: salt
         };
+        if (policyStr) {
+            backup.passwordPolicy = JSON.parse(policyStr);
+        }
 
         const blob = new Blob([JSON.stringify(backup, null, 2)], { type: 'application/json' });
         const url = URL.createObjectURL(blob);
@@ -72,6 +77,9 @@ export class BackupService {
 
                     localStorage.setItem('encrypted_vault', JSON.stringify(backup.vault));
                     localStorage.setItem('vault_salt', JSON.stringify(backup.salt));
+                    if (backup.passwordPolicy !== undefined) {
+                        localStorage.setItem('vault_password_policy', JSON.stringify(backup.passwordPolicy));
+                    }
                     localStorage.setItem('vault_initialized', 'true');
 
                     resolve();
@@ -23,3 +23,29 @@ export function getSalt(storageKey: string): Uint8Array {
     }
     return generateSalt();
 }
+
+/**
+ * The localStorage key holding the password policy of the vault whose salt is
+ * under `saltKey` ('vault_salt' -> 'vault_password_policy')
+ */
+export function passwordPolicyKey(saltKey: string): string {
+    return saltKey.replace(/_salt$/, '_password_policy');
+}
+
+/**
+ * Password policy the vault's key was derived under.
+ * Vaults created before policies existed have none stored and use 0 (password as typed).
+ * @param saltKey - The localStorage key of the vault's salt
+ */
+export function getPasswordPolicy(saltKey: string): number {
+    const stored = localStorage.getItem(passwordPolicyKey(saltKey));
+    return stored ? Number(JSON.parse(stored)) : 0;
+}
+
+/**
+ * The localStorage key holding the browser binding (see DeviceKeyService) of the
+ * vault whose salt is under `saltKey` ('vault_salt' -> 'vault_device_binding')
+ */
+export function deviceBindingKey(saltKey: string): string {
+    return saltKey.replace(/_salt$/, '_device_binding');
+}